@@ -0,0 +1,32 @@
+//! List the VMs on a node agent
+//!
+//! ```text
+//! cargo run -p api-client --example list_vms -- http://localhost:6001 [api-key]
+//! ```
+
+use api_client::{Auth, Client, ListOptions};
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut args = std::env::args().skip(1);
+    let base_url = args
+        .next()
+        .unwrap_or_else(|| "http://localhost:6001".to_string());
+    let mut client = Client::new(base_url);
+    if let Some(key) = args.next() {
+        client = client.with_auth(Auth::api_key(key));
+    }
+
+    let vms = client.list_vms(&ListOptions::new().sort("name")).await?;
+    println!("{} VM(s)", vms.total);
+    for vm in vms.items {
+        let memory = vm.memory_mb.map(|mb| format!("{} MB", mb));
+        println!(
+            "{:<24} {:<10} {}",
+            vm.name,
+            vm.state,
+            memory.unwrap_or_default()
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,231 @@
+//! Cluster routes (`/api/v1/cluster`)
+//!
+//! `cluster` selects the cluster to connect to; `None` is the local cluster.
+
+use http::Method;
+
+use api::dto::{
+    ClusterNameQuery, CsvDto, CsvPathQuery, GroupDto, JobDto, MaintenanceModeRequest, NodeDto,
+    ResourceDto,
+};
+
+use crate::{segment, Client, ListOptions, Page, Result};
+
+fn cluster_name(cluster: Option<&str>) -> ClusterNameQuery {
+    ClusterNameQuery {
+        name: cluster.map(str::to_string),
+    }
+}
+
+impl Client {
+    // -------------------------------------------------------------------------
+    // Cluster Info
+    // -------------------------------------------------------------------------
+
+    /// `GET /api/v1/cluster`; returns the cluster name
+    pub async fn cluster_info(&self, cluster: Option<&str>) -> Result<String> {
+        self.call(Method::GET, "/api/v1/cluster".to_string())
+            .query(&cluster_name(cluster))
+            .send()
+            .await
+    }
+
+    /// `GET /api/v1/cluster/connect/{name}`
+    pub async fn connect_cluster(&self, name: &str) -> Result<String> {
+        let path = format!("/api/v1/cluster/connect/{}", segment(name));
+        self.call(Method::GET, path).send().await
+    }
+
+    // -------------------------------------------------------------------------
+    // Nodes
+    // -------------------------------------------------------------------------
+
+    /// `GET /api/v1/cluster/nodes`
+    pub async fn list_nodes(
+        &self,
+        cluster: Option<&str>,
+        options: &ListOptions,
+    ) -> Result<Page<NodeDto>> {
+        self.call(Method::GET, "/api/v1/cluster/nodes".to_string())
+            .query(&cluster_name(cluster))
+            .list(options)
+            .page()
+            .await
+    }
+
+    /// `GET /api/v1/cluster/nodes/{name}`
+    pub async fn get_node(&self, name: &str, cluster: Option<&str>) -> Result<NodeDto> {
+        let path = format!("/api/v1/cluster/nodes/{}", segment(name));
+        self.call(Method::GET, path)
+            .query(&cluster_name(cluster))
+            .send()
+            .await
+    }
+
+    /// `POST /api/v1/cluster/nodes/{name}/pause`
+    pub async fn pause_node(&self, name: &str, cluster: Option<&str>) -> Result<String> {
+        let path = format!("/api/v1/cluster/nodes/{}/pause", segment(name));
+        self.call(Method::POST, path)
+            .query(&cluster_name(cluster))
+            .send()
+            .await
+    }
+
+    /// `POST /api/v1/cluster/nodes/{name}/resume`
+    pub async fn resume_node(&self, name: &str, cluster: Option<&str>) -> Result<String> {
+        let path = format!("/api/v1/cluster/nodes/{}/resume", segment(name));
+        self.call(Method::POST, path)
+            .query(&cluster_name(cluster))
+            .send()
+            .await
+    }
+
+    // -------------------------------------------------------------------------
+    // Groups
+    // -------------------------------------------------------------------------
+
+    /// `GET /api/v1/cluster/groups`
+    pub async fn list_groups(
+        &self,
+        cluster: Option<&str>,
+        options: &ListOptions,
+    ) -> Result<Page<GroupDto>> {
+        self.call(Method::GET, "/api/v1/cluster/groups".to_string())
+            .query(&cluster_name(cluster))
+            .list(options)
+            .page()
+            .await
+    }
+
+    /// `GET /api/v1/cluster/groups/{name}`
+    pub async fn get_group(&self, name: &str, cluster: Option<&str>) -> Result<GroupDto> {
+        let path = format!("/api/v1/cluster/groups/{}", segment(name));
+        self.call(Method::GET, path)
+            .query(&cluster_name(cluster))
+            .send()
+            .await
+    }
+
+    /// `POST /api/v1/cluster/groups/{name}/online`
+    pub async fn group_online(&self, name: &str, cluster: Option<&str>) -> Result<String> {
+        let path = format!("/api/v1/cluster/groups/{}/online", segment(name));
+        self.call(Method::POST, path)
+            .query(&cluster_name(cluster))
+            .send()
+            .await
+    }
+
+    /// `POST /api/v1/cluster/groups/{name}/offline`
+    pub async fn group_offline(&self, name: &str, cluster: Option<&str>) -> Result<String> {
+        let path = format!("/api/v1/cluster/groups/{}/offline", segment(name));
+        self.call(Method::POST, path)
+            .query(&cluster_name(cluster))
+            .send()
+            .await
+    }
+
+    /// `POST /api/v1/cluster/groups/{name}/move/{target_node}`; returns the move job
+    pub async fn move_group(
+        &self,
+        name: &str,
+        target_node: &str,
+        cluster: Option<&str>,
+    ) -> Result<JobDto> {
+        let path = format!(
+            "/api/v1/cluster/groups/{}/move/{}",
+            segment(name),
+            segment(target_node)
+        );
+        self.call(Method::POST, path)
+            .query(&cluster_name(cluster))
+            .send()
+            .await
+    }
+
+    // -------------------------------------------------------------------------
+    // Resources
+    // -------------------------------------------------------------------------
+
+    /// `GET /api/v1/cluster/resources`
+    pub async fn list_resources(
+        &self,
+        cluster: Option<&str>,
+        options: &ListOptions,
+    ) -> Result<Page<ResourceDto>> {
+        self.call(Method::GET, "/api/v1/cluster/resources".to_string())
+            .query(&cluster_name(cluster))
+            .list(options)
+            .page()
+            .await
+    }
+
+    /// `GET /api/v1/cluster/resources/{name}`
+    pub async fn get_resource(&self, name: &str, cluster: Option<&str>) -> Result<ResourceDto> {
+        let path = format!("/api/v1/cluster/resources/{}", segment(name));
+        self.call(Method::GET, path)
+            .query(&cluster_name(cluster))
+            .send()
+            .await
+    }
+
+    /// `POST /api/v1/cluster/resources/{name}/online`
+    pub async fn resource_online(&self, name: &str, cluster: Option<&str>) -> Result<String> {
+        let path = format!("/api/v1/cluster/resources/{}/online", segment(name));
+        self.call(Method::POST, path)
+            .query(&cluster_name(cluster))
+            .send()
+            .await
+    }
+
+    /// `POST /api/v1/cluster/resources/{name}/offline`
+    pub async fn resource_offline(&self, name: &str, cluster: Option<&str>) -> Result<String> {
+        let path = format!("/api/v1/cluster/resources/{}/offline", segment(name));
+        self.call(Method::POST, path)
+            .query(&cluster_name(cluster))
+            .send()
+            .await
+    }
+
+    // -------------------------------------------------------------------------
+    // CSV
+    // -------------------------------------------------------------------------
+
+    /// `GET /api/v1/cluster/csv`
+    pub async fn list_csvs(
+        &self,
+        cluster: Option<&str>,
+        options: &ListOptions,
+    ) -> Result<Page<CsvDto>> {
+        self.call(Method::GET, "/api/v1/cluster/csv".to_string())
+            .query(&cluster_name(cluster))
+            .list(options)
+            .page()
+            .await
+    }
+
+    /// `GET /api/v1/cluster/csv/check-path`; whether `path` is on a CSV
+    pub async fn csv_check_path(&self, path: &str) -> Result<bool> {
+        let query = CsvPathQuery {
+            path: path.to_string(),
+        };
+        self.call(Method::GET, "/api/v1/cluster/csv/check-path".to_string())
+            .query(&query)
+            .send()
+            .await
+    }
+
+    /// `POST /api/v1/cluster/csv/{name}/maintenance`
+    pub async fn set_csv_maintenance(
+        &self,
+        name: &str,
+        request: &MaintenanceModeRequest,
+        cluster: Option<&str>,
+    ) -> Result<String> {
+        let path = format!("/api/v1/cluster/csv/{}/maintenance", segment(name));
+        self.call(Method::POST, path)
+            .query(&cluster_name(cluster))
+            .json(request)
+            .send()
+            .await
+    }
+}
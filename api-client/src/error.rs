@@ -0,0 +1,83 @@
+//! Error types
+
+use std::time::Duration;
+
+use api::{ApiError, ErrorCode};
+use http::StatusCode;
+
+/// Failure of a client call
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// The API answered with an error; carries its status, stable code,
+    /// message and whether the server considers it retryable
+    #[error("{0}")]
+    Api(ApiError),
+
+    /// The call, including any retries, did not finish within its timeout
+    #[error("request timed out after {0:?}")]
+    Timeout(Duration),
+
+    /// The request could not be sent or the response could not be read
+    #[error("transport error: {0}")]
+    Transport(String),
+
+    /// The response body is not what the route returns
+    #[error("invalid response: {0}")]
+    Decode(String),
+
+    /// The request could not be built, e.g. a credential that is not a valid
+    /// header value
+    #[error("invalid request: {0}")]
+    Request(String),
+}
+
+impl Error {
+    /// Whether sending the same request again may succeed
+    ///
+    /// API errors follow the server's `retryable` flag; transport failures
+    /// are always worth retrying.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Error::Api(err) => err.retryable,
+            Error::Transport(_) => true,
+            Error::Timeout(_) | Error::Decode(_) | Error::Request(_) => false,
+        }
+    }
+
+    /// Stable error code of an API error
+    pub fn code(&self) -> Option<ErrorCode> {
+        match self {
+            Error::Api(err) => Some(err.code),
+            _ => None,
+        }
+    }
+
+    /// HTTP status of an API error
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            Error::Api(err) => Some(err.status),
+            _ => None,
+        }
+    }
+}
+
+impl From<ApiError> for Error {
+    fn from(err: ApiError) -> Self {
+        Error::Api(err)
+    }
+}
+
+/// Message of `err` followed by its sources
+pub(crate) fn describe(err: &dyn std::error::Error) -> String {
+    let mut message = err.to_string();
+    let mut source = err.source();
+    while let Some(err) = source {
+        message.push_str(": ");
+        message.push_str(&err.to_string());
+        source = err.source();
+    }
+    message
+}
+
+/// Result type for client calls
+pub type Result<T> = std::result::Result<T, Error>;
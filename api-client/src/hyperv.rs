@@ -0,0 +1,686 @@
+//! Hyper-V routes (`/api/v1/hyperv`)
+
+use http::Method;
+
+use api::dto::{
+    AddGpuRequest, AddVmNetworkAdapterRequest, AssignableDeviceDto, AttachDiskRequest,
+    BatchVmRequest, BootOrderRequest, ConfigureGpuRequest, ConnectAdapterRequest,
+    CreateSnapshotRequest, CreateSwitchRequest, CreateVhdRequest, CreateVhdxFromIsoRequest,
+    CreateVmRequest, DdaSupportDto, DeleteSnapshotQuery, DetachDiskRequest, DeviceLocationRequest,
+    DevicePathRequest, DiffVhdRequest, DiskDto, ExportSnapshotRequest, ExportVmRequest,
+    GpuAdapterDto, GpuDto, HostCapabilitiesDto, HostInfoDto, InitVhdRequest, IsoPathQuery, JobDto,
+    MountIsoRequest, NetworkAdapterDto, RenameSnapshotRequest, ResizeVhdRequest,
+    SetVmProcessorRequest, SetVmSecurityRequest, SnapshotDto, SnapshotTreeDto, SwitchDto,
+    UpdateVmNetworkAdapterRequest, UpdateVmRequest, VhdDto, VhdPathRequest, VmDto,
+    VmNetworkAdapterDto, VmProcessorDto, VmSecurityDto, WindowsEditionDto,
+};
+
+use crate::{segment, Client, ListOptions, Page, Result};
+
+impl Client {
+    // -------------------------------------------------------------------------
+    // Host Info
+    // -------------------------------------------------------------------------
+
+    /// `GET /api/v1/hyperv/host`
+    pub async fn host_info(&self) -> Result<HostInfoDto> {
+        self.call(Method::GET, "/api/v1/hyperv/host".to_string())
+            .send()
+            .await
+    }
+
+    /// `GET /api/v1/hyperv/host/capabilities`
+    pub async fn host_capabilities(&self) -> Result<HostCapabilitiesDto> {
+        self.call(Method::GET, "/api/v1/hyperv/host/capabilities".to_string())
+            .send()
+            .await
+    }
+
+    /// `GET /api/v1/hyperv/adapters`
+    pub async fn list_network_adapters(
+        &self,
+        options: &ListOptions,
+    ) -> Result<Page<NetworkAdapterDto>> {
+        self.call(Method::GET, "/api/v1/hyperv/adapters".to_string())
+            .list(options)
+            .page()
+            .await
+    }
+
+    // -------------------------------------------------------------------------
+    // VMs
+    // -------------------------------------------------------------------------
+
+    /// `GET /api/v1/hyperv/vms`
+    pub async fn list_vms(&self, options: &ListOptions) -> Result<Page<VmDto>> {
+        self.call(Method::GET, "/api/v1/hyperv/vms".to_string())
+            .list(options)
+            .page()
+            .await
+    }
+
+    /// `POST /api/v1/hyperv/vms`
+    pub async fn create_vm(&self, request: &CreateVmRequest) -> Result<VmDto> {
+        self.call(Method::POST, "/api/v1/hyperv/vms".to_string())
+            .json(request)
+            .send()
+            .await
+    }
+
+    /// `POST /api/v1/hyperv/vms:batch`; returns the batch job
+    pub async fn batch_vms(&self, request: &BatchVmRequest) -> Result<JobDto> {
+        self.call(Method::POST, "/api/v1/hyperv/vms:batch".to_string())
+            .json(request)
+            .send()
+            .await
+    }
+
+    /// `GET /api/v1/hyperv/vms/{name}`
+    pub async fn get_vm(&self, name: &str) -> Result<VmDto> {
+        let path = format!("/api/v1/hyperv/vms/{}", segment(name));
+        self.call(Method::GET, path).send().await
+    }
+
+    /// `PATCH /api/v1/hyperv/vms/{name}`
+    pub async fn update_vm(&self, name: &str, request: &UpdateVmRequest) -> Result<VmDto> {
+        let path = format!("/api/v1/hyperv/vms/{}", segment(name));
+        self.call(Method::PATCH, path).json(request).send().await
+    }
+
+    /// `DELETE /api/v1/hyperv/vms/{name}`
+    pub async fn delete_vm(&self, name: &str) -> Result<String> {
+        let path = format!("/api/v1/hyperv/vms/{}", segment(name));
+        self.call(Method::DELETE, path).send().await
+    }
+
+    /// `POST /api/v1/hyperv/vms/{name}/start`
+    pub async fn start_vm(&self, name: &str) -> Result<String> {
+        let path = format!("/api/v1/hyperv/vms/{}/start", segment(name));
+        self.call(Method::POST, path).send().await
+    }
+
+    /// `POST /api/v1/hyperv/vms/{name}/stop`
+    pub async fn stop_vm(&self, name: &str) -> Result<String> {
+        let path = format!("/api/v1/hyperv/vms/{}/stop", segment(name));
+        self.call(Method::POST, path).send().await
+    }
+
+    /// `POST /api/v1/hyperv/vms/{name}/force-stop`
+    pub async fn force_stop_vm(&self, name: &str) -> Result<String> {
+        let path = format!("/api/v1/hyperv/vms/{}/force-stop", segment(name));
+        self.call(Method::POST, path).send().await
+    }
+
+    /// `POST /api/v1/hyperv/vms/{name}/pause`
+    pub async fn pause_vm(&self, name: &str) -> Result<String> {
+        let path = format!("/api/v1/hyperv/vms/{}/pause", segment(name));
+        self.call(Method::POST, path).send().await
+    }
+
+    /// `POST /api/v1/hyperv/vms/{name}/resume`
+    pub async fn resume_vm(&self, name: &str) -> Result<String> {
+        let path = format!("/api/v1/hyperv/vms/{}/resume", segment(name));
+        self.call(Method::POST, path).send().await
+    }
+
+    /// `POST /api/v1/hyperv/vms/{name}/save`
+    pub async fn save_vm(&self, name: &str) -> Result<String> {
+        let path = format!("/api/v1/hyperv/vms/{}/save", segment(name));
+        self.call(Method::POST, path).send().await
+    }
+
+    /// `POST /api/v1/hyperv/vms/{name}/reset`
+    pub async fn reset_vm(&self, name: &str) -> Result<String> {
+        let path = format!("/api/v1/hyperv/vms/{}/reset", segment(name));
+        self.call(Method::POST, path).send().await
+    }
+
+    /// `POST /api/v1/hyperv/vms/{name}/export`; returns the export job
+    pub async fn export_vm(&self, name: &str, request: &ExportVmRequest) -> Result<JobDto> {
+        let path = format!("/api/v1/hyperv/vms/{}/export", segment(name));
+        self.call(Method::POST, path).json(request).send().await
+    }
+
+    /// `POST /api/v1/hyperv/vms/{name}/upgrade-version`
+    pub async fn upgrade_vm_version(&self, name: &str) -> Result<VmDto> {
+        let path = format!("/api/v1/hyperv/vms/{}/upgrade-version", segment(name));
+        self.call(Method::POST, path).send().await
+    }
+
+    // -------------------------------------------------------------------------
+    // VM Processor and Security
+    // -------------------------------------------------------------------------
+
+    /// `GET /api/v1/hyperv/vms/{name}/processor`
+    pub async fn vm_processor(&self, name: &str) -> Result<VmProcessorDto> {
+        let path = format!("/api/v1/hyperv/vms/{}/processor", segment(name));
+        self.call(Method::GET, path).send().await
+    }
+
+    /// `PUT /api/v1/hyperv/vms/{name}/processor`
+    pub async fn set_vm_processor(
+        &self,
+        name: &str,
+        request: &SetVmProcessorRequest,
+    ) -> Result<VmProcessorDto> {
+        let path = format!("/api/v1/hyperv/vms/{}/processor", segment(name));
+        self.call(Method::PUT, path).json(request).send().await
+    }
+
+    /// `GET /api/v1/hyperv/vms/{name}/security`
+    pub async fn vm_security(&self, name: &str) -> Result<VmSecurityDto> {
+        let path = format!("/api/v1/hyperv/vms/{}/security", segment(name));
+        self.call(Method::GET, path).send().await
+    }
+
+    /// `PUT /api/v1/hyperv/vms/{name}/security`
+    pub async fn set_vm_security(
+        &self,
+        name: &str,
+        request: &SetVmSecurityRequest,
+    ) -> Result<VmSecurityDto> {
+        let path = format!("/api/v1/hyperv/vms/{}/security", segment(name));
+        self.call(Method::PUT, path).json(request).send().await
+    }
+
+    // -------------------------------------------------------------------------
+    // VM Disks
+    // -------------------------------------------------------------------------
+
+    /// `GET /api/v1/hyperv/vms/{name}/disks`
+    pub async fn list_vm_disks(&self, name: &str, options: &ListOptions) -> Result<Page<DiskDto>> {
+        let path = format!("/api/v1/hyperv/vms/{}/disks", segment(name));
+        self.call(Method::GET, path).list(options).page().await
+    }
+
+    /// `POST /api/v1/hyperv/vms/{name}/disks/attach`
+    pub async fn attach_disk(&self, name: &str, request: &AttachDiskRequest) -> Result<String> {
+        let path = format!("/api/v1/hyperv/vms/{}/disks/attach", segment(name));
+        self.call(Method::POST, path).json(request).send().await
+    }
+
+    /// `POST /api/v1/hyperv/vms/{name}/disks/detach`
+    pub async fn detach_disk(&self, name: &str, request: &DetachDiskRequest) -> Result<String> {
+        let path = format!("/api/v1/hyperv/vms/{}/disks/detach", segment(name));
+        self.call(Method::POST, path).json(request).send().await
+    }
+
+    // -------------------------------------------------------------------------
+    // VM DVD/ISO
+    // -------------------------------------------------------------------------
+
+    /// `GET /api/v1/hyperv/vms/{name}/dvd`
+    pub async fn list_vm_dvd_drives(
+        &self,
+        name: &str,
+        options: &ListOptions,
+    ) -> Result<Page<DiskDto>> {
+        let path = format!("/api/v1/hyperv/vms/{}/dvd", segment(name));
+        self.call(Method::GET, path).list(options).page().await
+    }
+
+    /// `POST /api/v1/hyperv/vms/{name}/dvd/mount`
+    pub async fn mount_iso(&self, name: &str, request: &MountIsoRequest) -> Result<String> {
+        let path = format!("/api/v1/hyperv/vms/{}/dvd/mount", segment(name));
+        self.call(Method::POST, path).json(request).send().await
+    }
+
+    /// `POST /api/v1/hyperv/vms/{name}/dvd/eject`
+    pub async fn eject_iso(&self, name: &str) -> Result<String> {
+        let path = format!("/api/v1/hyperv/vms/{}/dvd/eject", segment(name));
+        self.call(Method::POST, path).send().await
+    }
+
+    /// `POST /api/v1/hyperv/vms/{name}/boot-order`
+    pub async fn set_boot_order(&self, name: &str, request: &BootOrderRequest) -> Result<String> {
+        let path = format!("/api/v1/hyperv/vms/{}/boot-order", segment(name));
+        self.call(Method::POST, path).json(request).send().await
+    }
+
+    // -------------------------------------------------------------------------
+    // VM Network Adapters
+    // -------------------------------------------------------------------------
+
+    /// `GET /api/v1/hyperv/vms/{name}/adapters`
+    pub async fn list_vm_network_adapters(
+        &self,
+        name: &str,
+        options: &ListOptions,
+    ) -> Result<Page<VmNetworkAdapterDto>> {
+        let path = format!("/api/v1/hyperv/vms/{}/adapters", segment(name));
+        self.call(Method::GET, path).list(options).page().await
+    }
+
+    /// `POST /api/v1/hyperv/vms/{name}/adapters`
+    pub async fn add_vm_network_adapter(
+        &self,
+        name: &str,
+        request: &AddVmNetworkAdapterRequest,
+    ) -> Result<VmNetworkAdapterDto> {
+        let path = format!("/api/v1/hyperv/vms/{}/adapters", segment(name));
+        self.call(Method::POST, path).json(request).send().await
+    }
+
+    /// `PATCH /api/v1/hyperv/vms/{name}/adapters/{adapter}`
+    pub async fn update_vm_network_adapter(
+        &self,
+        name: &str,
+        adapter: &str,
+        request: &UpdateVmNetworkAdapterRequest,
+    ) -> Result<VmNetworkAdapterDto> {
+        let path = format!(
+            "/api/v1/hyperv/vms/{}/adapters/{}",
+            segment(name),
+            segment(adapter)
+        );
+        self.call(Method::PATCH, path).json(request).send().await
+    }
+
+    /// `DELETE /api/v1/hyperv/vms/{name}/adapters/{adapter}`
+    pub async fn remove_vm_network_adapter(&self, name: &str, adapter: &str) -> Result<String> {
+        let path = format!(
+            "/api/v1/hyperv/vms/{}/adapters/{}",
+            segment(name),
+            segment(adapter)
+        );
+        self.call(Method::DELETE, path).send().await
+    }
+
+    /// `POST /api/v1/hyperv/vms/{name}/adapters/{adapter}/connect`
+    pub async fn connect_vm_network_adapter(
+        &self,
+        name: &str,
+        adapter: &str,
+        request: &ConnectAdapterRequest,
+    ) -> Result<VmNetworkAdapterDto> {
+        let path = format!(
+            "/api/v1/hyperv/vms/{}/adapters/{}/connect",
+            segment(name),
+            segment(adapter)
+        );
+        self.call(Method::POST, path).json(request).send().await
+    }
+
+    /// `POST /api/v1/hyperv/vms/{name}/adapters/{adapter}/disconnect`
+    pub async fn disconnect_vm_network_adapter(
+        &self,
+        name: &str,
+        adapter: &str,
+    ) -> Result<VmNetworkAdapterDto> {
+        let path = format!(
+            "/api/v1/hyperv/vms/{}/adapters/{}/disconnect",
+            segment(name),
+            segment(adapter)
+        );
+        self.call(Method::POST, path).send().await
+    }
+
+    // -------------------------------------------------------------------------
+    // VM Snapshots
+    // -------------------------------------------------------------------------
+
+    /// `GET /api/v1/hyperv/vms/{name}/snapshots`
+    pub async fn list_snapshots(
+        &self,
+        name: &str,
+        options: &ListOptions,
+    ) -> Result<Page<SnapshotDto>> {
+        let path = format!("/api/v1/hyperv/vms/{}/snapshots", segment(name));
+        self.call(Method::GET, path).list(options).page().await
+    }
+
+    /// `POST /api/v1/hyperv/vms/{name}/snapshots`
+    pub async fn create_snapshot(
+        &self,
+        name: &str,
+        request: &CreateSnapshotRequest,
+    ) -> Result<SnapshotDto> {
+        let path = format!("/api/v1/hyperv/vms/{}/snapshots", segment(name));
+        self.call(Method::POST, path).json(request).send().await
+    }
+
+    /// `GET /api/v1/hyperv/vms/{name}/snapshots/{snapshot}`
+    pub async fn get_snapshot(&self, name: &str, snapshot: &str) -> Result<SnapshotDto> {
+        let path = format!(
+            "/api/v1/hyperv/vms/{}/snapshots/{}",
+            segment(name),
+            segment(snapshot)
+        );
+        self.call(Method::GET, path).send().await
+    }
+
+    /// `POST /api/v1/hyperv/vms/{name}/snapshots/{snapshot}/apply`; returns the apply job
+    pub async fn apply_snapshot(&self, name: &str, snapshot: &str) -> Result<JobDto> {
+        let path = format!(
+            "/api/v1/hyperv/vms/{}/snapshots/{}/apply",
+            segment(name),
+            segment(snapshot)
+        );
+        self.call(Method::POST, path).send().await
+    }
+
+    /// `DELETE /api/v1/hyperv/vms/{name}/snapshots/{snapshot}/delete`
+    pub async fn delete_snapshot(&self, name: &str, snapshot: &str) -> Result<String> {
+        let path = format!(
+            "/api/v1/hyperv/vms/{}/snapshots/{}/delete",
+            segment(name),
+            segment(snapshot)
+        );
+        self.call(Method::DELETE, path).send().await
+    }
+
+    /// `DELETE /api/v1/hyperv/vms/{name}/snapshots/{snapshot}/delete?subtree=true`
+    pub async fn delete_snapshot_subtree(&self, name: &str, snapshot: &str) -> Result<String> {
+        let path = format!(
+            "/api/v1/hyperv/vms/{}/snapshots/{}/delete",
+            segment(name),
+            segment(snapshot)
+        );
+        let query = DeleteSnapshotQuery {
+            subtree: Some(true),
+        };
+        self.call(Method::DELETE, path).query(&query).send().await
+    }
+
+    /// `GET /api/v1/hyperv/vms/{name}/snapshots/tree`
+    pub async fn snapshot_tree(&self, name: &str) -> Result<SnapshotTreeDto> {
+        let path = format!("/api/v1/hyperv/vms/{}/snapshots/tree", segment(name));
+        self.call(Method::GET, path).send().await
+    }
+
+    /// `POST /api/v1/hyperv/vms/{name}/snapshots/{snapshot}/rename`
+    pub async fn rename_snapshot(
+        &self,
+        name: &str,
+        snapshot: &str,
+        request: &RenameSnapshotRequest,
+    ) -> Result<SnapshotDto> {
+        let path = format!(
+            "/api/v1/hyperv/vms/{}/snapshots/{}/rename",
+            segment(name),
+            segment(snapshot)
+        );
+        self.call(Method::POST, path).json(request).send().await
+    }
+
+    /// `POST /api/v1/hyperv/vms/{name}/snapshots/{snapshot}/export`; returns the export job
+    pub async fn export_snapshot(
+        &self,
+        name: &str,
+        snapshot: &str,
+        request: &ExportSnapshotRequest,
+    ) -> Result<JobDto> {
+        let path = format!(
+            "/api/v1/hyperv/vms/{}/snapshots/{}/export",
+            segment(name),
+            segment(snapshot)
+        );
+        self.call(Method::POST, path).json(request).send().await
+    }
+
+    // -------------------------------------------------------------------------
+    // VM GPU
+    // -------------------------------------------------------------------------
+
+    /// `GET /api/v1/hyperv/vms/{name}/gpu`
+    pub async fn list_vm_gpu_adapters(
+        &self,
+        name: &str,
+        options: &ListOptions,
+    ) -> Result<Page<GpuAdapterDto>> {
+        let path = format!("/api/v1/hyperv/vms/{}/gpu", segment(name));
+        self.call(Method::GET, path).list(options).page().await
+    }
+
+    /// `POST /api/v1/hyperv/vms/{name}/gpu/add`
+    pub async fn add_gpu(&self, name: &str, request: &AddGpuRequest) -> Result<String> {
+        let path = format!("/api/v1/hyperv/vms/{}/gpu/add", segment(name));
+        self.call(Method::POST, path).json(request).send().await
+    }
+
+    /// `POST /api/v1/hyperv/vms/{name}/gpu/remove`
+    pub async fn remove_gpu(&self, name: &str) -> Result<String> {
+        let path = format!("/api/v1/hyperv/vms/{}/gpu/remove", segment(name));
+        self.call(Method::POST, path).send().await
+    }
+
+    /// `POST /api/v1/hyperv/vms/{name}/gpu/configure`
+    pub async fn configure_gpu(&self, name: &str, request: &ConfigureGpuRequest) -> Result<String> {
+        let path = format!("/api/v1/hyperv/vms/{}/gpu/configure", segment(name));
+        self.call(Method::POST, path).json(request).send().await
+    }
+
+    // -------------------------------------------------------------------------
+    // VM DDA
+    // -------------------------------------------------------------------------
+
+    /// `GET /api/v1/hyperv/vms/{name}/dda`
+    pub async fn list_vm_dda_devices(
+        &self,
+        name: &str,
+        options: &ListOptions,
+    ) -> Result<Page<AssignableDeviceDto>> {
+        let path = format!("/api/v1/hyperv/vms/{}/dda", segment(name));
+        self.call(Method::GET, path).list(options).page().await
+    }
+
+    /// `POST /api/v1/hyperv/vms/{name}/dda/assign`
+    pub async fn assign_device(
+        &self,
+        name: &str,
+        request: &DeviceLocationRequest,
+    ) -> Result<String> {
+        let path = format!("/api/v1/hyperv/vms/{}/dda/assign", segment(name));
+        self.call(Method::POST, path).json(request).send().await
+    }
+
+    /// `POST /api/v1/hyperv/vms/{name}/dda/remove`
+    pub async fn remove_device(
+        &self,
+        name: &str,
+        request: &DeviceLocationRequest,
+    ) -> Result<String> {
+        let path = format!("/api/v1/hyperv/vms/{}/dda/remove", segment(name));
+        self.call(Method::POST, path).json(request).send().await
+    }
+
+    // -------------------------------------------------------------------------
+    // Switches
+    // -------------------------------------------------------------------------
+
+    /// `GET /api/v1/hyperv/switches`
+    pub async fn list_switches(&self, options: &ListOptions) -> Result<Page<SwitchDto>> {
+        self.call(Method::GET, "/api/v1/hyperv/switches".to_string())
+            .list(options)
+            .page()
+            .await
+    }
+
+    /// `POST /api/v1/hyperv/switches`
+    pub async fn create_switch(&self, request: &CreateSwitchRequest) -> Result<SwitchDto> {
+        self.call(Method::POST, "/api/v1/hyperv/switches".to_string())
+            .json(request)
+            .send()
+            .await
+    }
+
+    /// `GET /api/v1/hyperv/switches/{name}`
+    pub async fn get_switch(&self, name: &str) -> Result<SwitchDto> {
+        let path = format!("/api/v1/hyperv/switches/{}", segment(name));
+        self.call(Method::GET, path).send().await
+    }
+
+    /// `DELETE /api/v1/hyperv/switches/{name}`
+    pub async fn delete_switch(&self, name: &str) -> Result<String> {
+        let path = format!("/api/v1/hyperv/switches/{}", segment(name));
+        self.call(Method::DELETE, path).send().await
+    }
+
+    // -------------------------------------------------------------------------
+    // VHDs
+    // -------------------------------------------------------------------------
+
+    /// `POST /api/v1/hyperv/vhds`; returns the create job
+    pub async fn create_vhd(&self, request: &CreateVhdRequest) -> Result<JobDto> {
+        self.call(Method::POST, "/api/v1/hyperv/vhds".to_string())
+            .json(request)
+            .send()
+            .await
+    }
+
+    /// `GET /api/v1/hyperv/vhds/info`
+    pub async fn get_vhd_info(&self, path: &str) -> Result<VhdDto> {
+        let query = VhdPathRequest {
+            path: path.to_string(),
+        };
+        self.call(Method::GET, "/api/v1/hyperv/vhds/info".to_string())
+            .query(&query)
+            .send()
+            .await
+    }
+
+    /// `POST /api/v1/hyperv/vhds/resize`
+    pub async fn resize_vhd(&self, request: &ResizeVhdRequest) -> Result<String> {
+        self.call(Method::POST, "/api/v1/hyperv/vhds/resize".to_string())
+            .json(request)
+            .send()
+            .await
+    }
+
+    /// `POST /api/v1/hyperv/vhds/compact`; returns the compact job
+    pub async fn compact_vhd(&self, request: &VhdPathRequest) -> Result<JobDto> {
+        self.call(Method::POST, "/api/v1/hyperv/vhds/compact".to_string())
+            .json(request)
+            .send()
+            .await
+    }
+
+    /// `POST /api/v1/hyperv/vhds/mount`
+    pub async fn mount_vhd(&self, request: &VhdPathRequest) -> Result<String> {
+        self.call(Method::POST, "/api/v1/hyperv/vhds/mount".to_string())
+            .json(request)
+            .send()
+            .await
+    }
+
+    /// `POST /api/v1/hyperv/vhds/dismount`
+    pub async fn dismount_vhd(&self, request: &VhdPathRequest) -> Result<String> {
+        self.call(Method::POST, "/api/v1/hyperv/vhds/dismount".to_string())
+            .json(request)
+            .send()
+            .await
+    }
+
+    /// `POST /api/v1/hyperv/vhds/differencing`
+    pub async fn create_differencing_vhd(&self, request: &DiffVhdRequest) -> Result<VhdDto> {
+        self.call(Method::POST, "/api/v1/hyperv/vhds/differencing".to_string())
+            .json(request)
+            .send()
+            .await
+    }
+
+    /// `POST /api/v1/hyperv/vhds/initialize`; returns the disk's volume path
+    pub async fn initialize_vhd(&self, request: &InitVhdRequest) -> Result<String> {
+        self.call(Method::POST, "/api/v1/hyperv/vhds/initialize".to_string())
+            .json(request)
+            .send()
+            .await
+    }
+
+    // -------------------------------------------------------------------------
+    // Windows Image
+    // -------------------------------------------------------------------------
+
+    /// `GET /api/v1/hyperv/iso/editions`
+    pub async fn list_iso_editions(
+        &self,
+        path: &str,
+        options: &ListOptions,
+    ) -> Result<Page<WindowsEditionDto>> {
+        let query = IsoPathQuery {
+            path: path.to_string(),
+        };
+        self.call(Method::GET, "/api/v1/hyperv/iso/editions".to_string())
+            .query(&query)
+            .list(options)
+            .page()
+            .await
+    }
+
+    /// `POST /api/v1/hyperv/iso/create-vhdx`; returns the create job
+    pub async fn create_vhdx_from_iso(&self, request: &CreateVhdxFromIsoRequest) -> Result<JobDto> {
+        self.call(Method::POST, "/api/v1/hyperv/iso/create-vhdx".to_string())
+            .json(request)
+            .send()
+            .await
+    }
+
+    // -------------------------------------------------------------------------
+    // GPUs
+    // -------------------------------------------------------------------------
+
+    /// `GET /api/v1/hyperv/gpus`
+    pub async fn list_gpus(&self, options: &ListOptions) -> Result<Page<GpuDto>> {
+        self.call(Method::GET, "/api/v1/hyperv/gpus".to_string())
+            .list(options)
+            .page()
+            .await
+    }
+
+    /// `GET /api/v1/hyperv/gpus/partitionable`
+    pub async fn list_partitionable_gpus(&self, options: &ListOptions) -> Result<Page<GpuDto>> {
+        self.call(Method::GET, "/api/v1/hyperv/gpus/partitionable".to_string())
+            .list(options)
+            .page()
+            .await
+    }
+
+    // -------------------------------------------------------------------------
+    // DDA
+    // -------------------------------------------------------------------------
+
+    /// `GET /api/v1/hyperv/dda/support`
+    pub async fn dda_support(&self) -> Result<DdaSupportDto> {
+        self.call(Method::GET, "/api/v1/hyperv/dda/support".to_string())
+            .send()
+            .await
+    }
+
+    /// `GET /api/v1/hyperv/dda/devices`
+    pub async fn list_dda_devices(
+        &self,
+        options: &ListOptions,
+    ) -> Result<Page<AssignableDeviceDto>> {
+        self.call(Method::GET, "/api/v1/hyperv/dda/devices".to_string())
+            .list(options)
+            .page()
+            .await
+    }
+
+    /// `GET /api/v1/hyperv/dda/device-path`; returns the device's location path
+    pub async fn device_path(&self, instance_id: &str) -> Result<String> {
+        let query = DevicePathRequest {
+            instance_id: instance_id.to_string(),
+        };
+        self.call(Method::GET, "/api/v1/hyperv/dda/device-path".to_string())
+            .query(&query)
+            .send()
+            .await
+    }
+
+    /// `POST /api/v1/hyperv/dda/dismount`
+    pub async fn dismount_device(&self, request: &DeviceLocationRequest) -> Result<String> {
+        self.call(Method::POST, "/api/v1/hyperv/dda/dismount".to_string())
+            .json(request)
+            .send()
+            .await
+    }
+
+    /// `POST /api/v1/hyperv/dda/mount`
+    pub async fn mount_device(&self, request: &DeviceLocationRequest) -> Result<String> {
+        self.call(Method::POST, "/api/v1/hyperv/dda/mount".to_string())
+            .json(request)
+            .send()
+            .await
+    }
+}
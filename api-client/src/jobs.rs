@@ -0,0 +1,65 @@
+//! Job and audit routes (`/api/v1/jobs`, `/api/v1/audit`)
+
+use std::time::Duration;
+
+use http::Method;
+
+use api::dto::{AuditQuery, AuditRecordDto, JobDto, JobWaitQuery};
+
+use crate::{Client, ListOptions, Page, Result};
+
+/// Time allowed on top of a wait's `timeout_secs` for the response to arrive
+const WAIT_MARGIN: Duration = Duration::from_secs(10);
+
+impl Client {
+    // -------------------------------------------------------------------------
+    // Jobs
+    // -------------------------------------------------------------------------
+
+    /// `GET /api/v1/jobs`
+    pub async fn list_jobs(&self, options: &ListOptions) -> Result<Page<JobDto>> {
+        self.call(Method::GET, "/api/v1/jobs".to_string())
+            .list(options)
+            .page()
+            .await
+    }
+
+    /// `GET /api/v1/jobs/{id}`
+    pub async fn get_job(&self, id: u64) -> Result<JobDto> {
+        self.call(Method::GET, format!("/api/v1/jobs/{}", id))
+            .send()
+            .await
+    }
+
+    /// `POST /api/v1/jobs/{id}/cancel`
+    pub async fn cancel_job(&self, id: u64) -> Result<JobDto> {
+        self.call(Method::POST, format!("/api/v1/jobs/{}/cancel", id))
+            .send()
+            .await
+    }
+
+    /// `GET /api/v1/jobs/{id}/wait`; returns the job once finished, or as it
+    /// is after `timeout_secs` (server default 30)
+    ///
+    /// The call's timeout is raised to cover the wait when it is shorter.
+    pub async fn wait_job(&self, id: u64, timeout_secs: Option<u64>) -> Result<JobDto> {
+        let wait = Duration::from_secs(timeout_secs.unwrap_or(30));
+        self.call(Method::GET, format!("/api/v1/jobs/{}/wait", id))
+            .query(&JobWaitQuery { timeout_secs })
+            .min_timeout(wait + WAIT_MARGIN)
+            .send()
+            .await
+    }
+
+    // -------------------------------------------------------------------------
+    // Audit
+    // -------------------------------------------------------------------------
+
+    /// `GET /api/v1/audit`
+    pub async fn query_audit(&self, query: &AuditQuery) -> Result<Vec<AuditRecordDto>> {
+        self.call(Method::GET, "/api/v1/audit".to_string())
+            .query(query)
+            .send()
+            .await
+    }
+}
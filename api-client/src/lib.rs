@@ -0,0 +1,623 @@
+//! Async client for the node agent REST API
+//!
+//! [`Client`] has one method per `/api/v1` route, taking and returning the
+//! request and response types from [`api::dto`]. An error response comes back
+//! as [`Error::Api`] holding the same [`ApiError`] the server produced, so
+//! callers match on its stable [`ErrorCode`] rather than on messages.
+//!
+//! ```no_run
+//! # async fn example() -> api_client::Result<()> {
+//! use std::time::Duration;
+//! use api_client::{Auth, Client, ListOptions};
+//!
+//! let client = Client::new("https://node1:6001").with_auth(Auth::api_key("secret"));
+//! let running = client
+//!     .list_vms(&ListOptions::new().filter("state", "Running"))
+//!     .await?;
+//! for vm in running.items {
+//!     client
+//!         .with_timeout(Duration::from_secs(120))
+//!         .stop_vm(&vm.name)
+//!         .await?;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Every call has a timeout covering all of its attempts ([`DEFAULT_TIMEOUT`]
+//! unless changed) and is retried with exponential backoff when the failure
+//! is retryable: the request could not be sent, or the server marked the
+//! error `retryable` (`429 rate_limited`, `503 too_many_operations`, ...).
+//! A `Retry-After` header takes precedence over the backoff. POST requests
+//! carry a generated `Idempotency-Key` that is reused across attempts, so a
+//! retried create or action is applied once.
+//!
+//! [`Client::with_timeout`], [`Client::with_retry`] and [`Client::with_auth`]
+//! return a modified copy, which makes them usable per call as well as when
+//! building the client.
+//!
+//! The event streams (`/api/v1/events` and `/api/v1/events/ws`) are not
+//! wrapped; use an SSE or WebSocket client for those.
+
+pub mod error;
+pub mod transport;
+
+mod cluster;
+mod hyperv;
+mod jobs;
+mod webhooks;
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use bytes::Bytes;
+use http::header::{ACCEPT, AUTHORIZATION, CONTENT_TYPE, RETRY_AFTER};
+use http::{HeaderName, HeaderValue, Method, Request, Response};
+use percent_encoding::{utf8_percent_encode, AsciiSet, PercentEncode, NON_ALPHANUMERIC};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tokio::time::Instant;
+
+use api::auth::API_KEY_HEADER;
+use api::error::PROBLEM_JSON;
+use api::idempotency::IDEMPOTENCY_KEY;
+use api::{ApiResponse, ProblemDetails};
+
+pub use api::dto;
+pub use api::{ApiError, ErrorCode, FieldError, FieldErrorCode};
+pub use error::{Error, Result};
+pub use transport::{HttpTransport, ServiceTransport, Transport};
+
+/// Timeout of a call, including retries, unless changed with
+/// [`Client::with_timeout`]
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Error responses are requested as problem details, which carry `retryable`
+const ACCEPT_TYPES: &str = "application/problem+json, application/json";
+
+/// Characters left as-is in a path segment
+const SEGMENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+// =============================================================================
+// Client Settings
+// =============================================================================
+
+/// Credentials sent with every request
+#[derive(Clone, PartialEq, Eq)]
+pub enum Auth {
+    /// `X-API-Key: <key>`
+    ApiKey(String),
+    /// `Authorization: Bearer <token>`
+    Bearer(String),
+}
+
+impl Auth {
+    pub fn api_key(key: impl Into<String>) -> Self {
+        Auth::ApiKey(key.into())
+    }
+
+    pub fn bearer(token: impl Into<String>) -> Self {
+        Auth::Bearer(token.into())
+    }
+
+    fn header(&self) -> Result<(HeaderName, HeaderValue)> {
+        let (name, value) = match self {
+            Auth::ApiKey(key) => (HeaderName::from_static(API_KEY_HEADER), key.clone()),
+            Auth::Bearer(token) => (AUTHORIZATION, format!("Bearer {}", token)),
+        };
+        let mut value = HeaderValue::from_str(&value)
+            .map_err(|_| Error::Request("credential is not a valid header value".to_string()))?;
+        value.set_sensitive(true);
+        Ok((name, value))
+    }
+}
+
+/// Keeps credentials out of logs
+impl fmt::Debug for Auth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Auth::ApiKey(_) => f.write_str("ApiKey(..)"),
+            Auth::Bearer(_) => f.write_str("Bearer(..)"),
+        }
+    }
+}
+
+/// When and how often retryable failures are retried
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Attempts in total, including the first; 1 disables retries
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled for each one after it
+    pub initial_backoff: Duration,
+    /// Longest delay between attempts
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(200),
+            max_backoff: Duration::from_secs(5),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Send every request once
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    /// Delay after failed attempt number `attempt` (1-based)
+    fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 1u32 << attempt.saturating_sub(1).min(16);
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+// =============================================================================
+// Lists
+// =============================================================================
+
+/// Filters, order and page of a list call
+///
+/// Filters take the same `field=value` globs as the query string; see the
+/// API documentation on list queries. `fields` projection is not offered
+/// because items are returned as typed DTOs.
+#[derive(Debug, Clone, Default)]
+pub struct ListOptions {
+    pub filters: BTreeMap<String, String>,
+    /// Comma-separated sort keys, each `field` or `field:desc`
+    pub sort: Option<String>,
+    pub limit: Option<usize>,
+    /// `next_cursor` of the previous page
+    pub cursor: Option<String>,
+}
+
+impl ListOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn filter(mut self, field: impl Into<String>, value: impl Into<String>) -> Self {
+        self.filters.insert(field.into(), value.into());
+        self
+    }
+
+    pub fn sort(mut self, sort: impl Into<String>) -> Self {
+        self.sort = Some(sort.into());
+        self
+    }
+
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn cursor(mut self, cursor: impl Into<String>) -> Self {
+        self.cursor = Some(cursor.into());
+        self
+    }
+
+    fn pairs(&self) -> Vec<(String, String)> {
+        let mut pairs: Vec<(String, String)> = self
+            .filters
+            .iter()
+            .map(|(field, value)| (field.clone(), value.clone()))
+            .collect();
+        pairs.extend(self.sort.clone().map(|sort| ("sort".to_string(), sort)));
+        pairs.extend(
+            self.limit
+                .map(|limit| ("limit".to_string(), limit.to_string())),
+        );
+        pairs.extend(
+            self.cursor
+                .clone()
+                .map(|cursor| ("cursor".to_string(), cursor)),
+        );
+        pairs
+    }
+}
+
+/// One page of a list
+#[derive(Debug, Clone)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// Items matching the filters, across all pages
+    pub total: usize,
+    /// Pass to [`ListOptions::cursor`] for the next page; `None` on the last
+    pub next_cursor: Option<String>,
+    /// Nodes whose items are missing, when the server is an aggregator
+    pub node_errors: Vec<dto::NodeErrorDto>,
+}
+
+// =============================================================================
+// Client
+// =============================================================================
+
+/// Node agent API client
+///
+/// Cheap to clone; clones share the transport.
+#[derive(Clone)]
+pub struct Client {
+    base_url: String,
+    transport: Arc<dyn Transport>,
+    auth: Option<Auth>,
+    timeout: Duration,
+    retry: RetryPolicy,
+}
+
+impl fmt::Debug for Client {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Client")
+            .field("base_url", &self.base_url)
+            .field("auth", &self.auth)
+            .field("timeout", &self.timeout)
+            .field("retry", &self.retry)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Client {
+    /// Client for the agent at `base_url`, e.g. `https://node1:6001`
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self::with_transport(base_url, HttpTransport::default())
+    }
+
+    /// Client sending requests for `base_url` through `transport`
+    pub fn with_transport(
+        base_url: impl Into<String>,
+        transport: impl Transport + 'static,
+    ) -> Self {
+        Self {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            transport: Arc::new(transport),
+            auth: None,
+            timeout: DEFAULT_TIMEOUT,
+            retry: RetryPolicy::default(),
+        }
+    }
+
+    /// Client calling `service` in process, e.g. the router from
+    /// `api::create_router`
+    pub fn from_service<S>(service: S) -> Self
+    where
+        ServiceTransport<S>: Transport + 'static,
+    {
+        Self::with_transport("", ServiceTransport::new(service))
+    }
+
+    pub fn with_auth(&self, auth: Auth) -> Self {
+        Self {
+            auth: Some(auth),
+            ..self.clone()
+        }
+    }
+
+    pub fn with_timeout(&self, timeout: Duration) -> Self {
+        Self {
+            timeout,
+            ..self.clone()
+        }
+    }
+
+    pub fn with_retry(&self, retry: RetryPolicy) -> Self {
+        Self {
+            retry,
+            ..self.clone()
+        }
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    fn call(&self, method: Method, path: String) -> Call<'_> {
+        Call {
+            client: self,
+            method,
+            path,
+            query: Vec::new(),
+            body: None,
+            timeout: self.timeout,
+            error: None,
+        }
+    }
+
+    /// Send a request, retrying retryable failures until the deadline
+    async fn execute(
+        &self,
+        method: Method,
+        path_and_query: &str,
+        body: Option<Bytes>,
+        timeout: Duration,
+    ) -> Result<Response<Bytes>> {
+        let deadline = Instant::now() + timeout;
+        let idempotency_key =
+            (method == Method::POST && self.retry.max_attempts > 1).then(idempotency_key);
+        let mut attempt = 1;
+        loop {
+            let request = self.request(
+                &method,
+                path_and_query,
+                body.clone(),
+                idempotency_key.as_deref(),
+            )?;
+            let sent = tokio::time::timeout_at(deadline, self.transport.send(request))
+                .await
+                .map_err(|_| Error::Timeout(timeout))?;
+            let (err, retry_after) = match sent {
+                Ok(response) if is_success(&response) => return Ok(response),
+                Ok(response) => (error_response(&response), retry_after(&response)),
+                Err(err) => (err, None),
+            };
+
+            if attempt >= self.retry.max_attempts || !err.is_retryable() {
+                return Err(err);
+            }
+            let delay = retry_after.unwrap_or_else(|| self.retry.backoff(attempt));
+            if Instant::now() + delay >= deadline {
+                return Err(err);
+            }
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    fn request(
+        &self,
+        method: &Method,
+        path_and_query: &str,
+        body: Option<Bytes>,
+        idempotency_key: Option<&str>,
+    ) -> Result<Request<Bytes>> {
+        let mut request = Request::builder()
+            .method(method.clone())
+            .uri(format!("{}{}", self.base_url, path_and_query))
+            .header(ACCEPT, ACCEPT_TYPES);
+        if body.is_some() {
+            request = request.header(CONTENT_TYPE, "application/json");
+        }
+        if let Some(auth) = &self.auth {
+            let (name, value) = auth.header()?;
+            request = request.header(name, value);
+        }
+        if let Some(key) = idempotency_key {
+            request = request.header(IDEMPOTENCY_KEY, key);
+        }
+        request
+            .body(body.unwrap_or_default())
+            .map_err(|e| Error::Request(error::describe(&e)))
+    }
+
+    // -------------------------------------------------------------------------
+    // Root
+    // -------------------------------------------------------------------------
+
+    /// `GET /health`
+    pub async fn health(&self) -> Result<String> {
+        self.call(Method::GET, "/health".to_string()).send().await
+    }
+
+    /// `GET /api/v1/openapi.json`
+    pub async fn openapi(&self) -> Result<serde_json::Value> {
+        let response = self
+            .call(Method::GET, "/api/v1/openapi.json".to_string())
+            .response()
+            .await?;
+        decode(response.body())
+    }
+}
+
+/// One request being put together by a route method
+struct Call<'a> {
+    client: &'a Client,
+    method: Method,
+    /// Percent-encoded path
+    path: String,
+    /// Encoded query strings, joined with `&`
+    query: Vec<String>,
+    body: Option<Bytes>,
+    timeout: Duration,
+    /// First failure while building; returned by `send`
+    error: Option<Error>,
+}
+
+impl Call<'_> {
+    /// Add the fields of `query` to the query string
+    fn query(mut self, query: &impl Serialize) -> Self {
+        match serde_urlencoded::to_string(query) {
+            Ok(query) if query.is_empty() => {}
+            Ok(query) => self.query.push(query),
+            Err(e) => self.fail(Error::Request(error::describe(&e))),
+        }
+        self
+    }
+
+    fn list(self, options: &ListOptions) -> Self {
+        self.query(&options.pairs())
+    }
+
+    fn json(mut self, body: &impl Serialize) -> Self {
+        match serde_json::to_vec(body) {
+            Ok(body) => self.body = Some(body.into()),
+            Err(e) => self.fail(Error::Request(error::describe(&e))),
+        }
+        self
+    }
+
+    /// Allow at least `timeout`, for routes that wait on the server
+    fn min_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = self.timeout.max(timeout);
+        self
+    }
+
+    fn fail(&mut self, err: Error) {
+        self.error.get_or_insert(err);
+    }
+
+    async fn response(self) -> Result<Response<Bytes>> {
+        if let Some(err) = self.error {
+            return Err(err);
+        }
+        let path_and_query = match self.query.is_empty() {
+            true => self.path,
+            false => format!("{}?{}", self.path, self.query.join("&")),
+        };
+        self.client
+            .execute(self.method, &path_and_query, self.body, self.timeout)
+            .await
+    }
+
+    /// Send and return the envelope's `data`
+    async fn send<T: DeserializeOwned>(self) -> Result<T> {
+        let response = self.response().await?;
+        let envelope: ApiResponse<T> = decode(response.body())?;
+        envelope
+            .data
+            .ok_or_else(|| Error::Decode("response has no data".to_string()))
+    }
+
+    /// Send to a list route
+    async fn page<T: DeserializeOwned>(self) -> Result<Page<T>> {
+        let response = self.response().await?;
+        let envelope: ApiResponse<Vec<T>> = decode(response.body())?;
+        let items = envelope.data.unwrap_or_default();
+        Ok(Page {
+            total: envelope.total.unwrap_or(items.len()),
+            next_cursor: envelope.next_cursor,
+            node_errors: envelope.node_errors,
+            items,
+        })
+    }
+}
+
+// =============================================================================
+// Helpers
+// =============================================================================
+
+/// Percent-encode one path segment
+fn segment(value: &str) -> PercentEncode<'_> {
+    utf8_percent_encode(value, SEGMENT)
+}
+
+fn decode<T: DeserializeOwned>(body: &[u8]) -> Result<T> {
+    serde_json::from_slice(body).map_err(|e| Error::Decode(e.to_string()))
+}
+
+fn is_success(response: &Response<Bytes>) -> bool {
+    let status = response.status();
+    !status.is_client_error() && !status.is_server_error()
+}
+
+/// The [`ApiError`] of an error response
+///
+/// Problem details are preferred since only they carry `retryable`; an
+/// envelope or a plain-text body (extractor rejections) falls back to the
+/// status's generic code and retryability.
+fn error_response(response: &Response<Bytes>) -> Error {
+    let status = response.status();
+    let body = response.body();
+    let is_problem = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with(PROBLEM_JSON));
+    if is_problem {
+        if let Ok(problem) = serde_json::from_slice::<ProblemDetails>(body) {
+            let mut err = ApiError::new(status, problem.code, problem.detail)
+                .with_retryable(problem.retryable);
+            err.errors = problem.errors;
+            err.node_errors = problem.node_errors;
+            return err.into();
+        }
+    }
+
+    let text = String::from_utf8_lossy(body).trim().to_string();
+    let mut err = ApiError::from_status(status, text);
+    if let Ok(envelope) = serde_json::from_slice::<ApiResponse<serde_json::Value>>(body) {
+        if let Some(message) = envelope.error {
+            err.message = message;
+        }
+        if let Some(code) = envelope.code {
+            err.code = code;
+        }
+        err.errors = envelope.errors;
+        err.node_errors = envelope.node_errors;
+    }
+    if err.message.is_empty() {
+        err.message = status.canonical_reason().unwrap_or("Error").to_string();
+    }
+    err.into()
+}
+
+/// Delay requested by a `Retry-After: <seconds>` header
+fn retry_after(response: &Response<Bytes>) -> Option<Duration> {
+    let seconds = response.headers().get(RETRY_AFTER)?.to_str().ok()?;
+    seconds.trim().parse().ok().map(Duration::from_secs)
+}
+
+/// Unique key for one logical POST, shared by its attempts
+fn idempotency_key() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    format!(
+        "api-client-{}-{:x}-{}",
+        std::process::id(),
+        nanos,
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+// =============================================================================
+// Tests
+// =============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_doubles_up_to_max() {
+        let retry = RetryPolicy::default();
+        assert_eq!(retry.backoff(1), Duration::from_millis(200));
+        assert_eq!(retry.backoff(2), Duration::from_millis(400));
+        assert_eq!(retry.backoff(3), Duration::from_millis(800));
+        assert_eq!(retry.backoff(10), Duration::from_secs(5));
+        assert_eq!(retry.backoff(100), Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_segments_are_encoded() {
+        assert_eq!(segment("web-01").to_string(), "web-01");
+        assert_eq!(segment("before patch/1").to_string(), "before%20patch%2F1");
+    }
+
+    #[test]
+    fn test_plain_text_errors_use_status_code() {
+        let response = Response::builder()
+            .status(422)
+            .body(Bytes::from("missing field `name`"))
+            .unwrap();
+        let err = error_response(&response);
+        assert_eq!(err.code(), Some(ErrorCode::ValidationFailed));
+        assert!(err.to_string().contains("missing field `name`"));
+        assert!(!err.is_retryable());
+    }
+}
@@ -0,0 +1,112 @@
+//! How requests reach the API
+//!
+//! [`HttpTransport`] talks HTTP or HTTPS to a running node agent.
+//! [`ServiceTransport`] calls any `tower::Service` in process, such as the
+//! router from `api::create_router`, which is how the tests run the client
+//! without opening a socket.
+
+use std::future::Future;
+use std::pin::Pin;
+
+use bytes::Bytes;
+use http::{Request, Response};
+use http_body_util::{BodyExt, Full};
+use tower::{Service, ServiceExt};
+
+use crate::error::{describe, Error, Result};
+
+/// Boxed future returned by [`Transport::send`]
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Sends one request and reads the whole response
+///
+/// The request URI is absolute for [`HttpTransport`] and path-only for
+/// [`ServiceTransport`]; see [`Client`](crate::Client) for how it is built.
+pub trait Transport: Send + Sync {
+    fn send(&self, request: Request<Bytes>) -> BoxFuture<'_, Result<Response<Bytes>>>;
+}
+
+/// HTTP or HTTPS through a `reqwest` client
+///
+/// Build the `reqwest::Client` yourself to add CA certificates or a client
+/// certificate for mutual TLS.
+#[derive(Debug, Clone, Default)]
+pub struct HttpTransport {
+    client: reqwest::Client,
+}
+
+impl HttpTransport {
+    pub fn new(client: reqwest::Client) -> Self {
+        Self { client }
+    }
+}
+
+impl Transport for HttpTransport {
+    fn send(&self, request: Request<Bytes>) -> BoxFuture<'_, Result<Response<Bytes>>> {
+        Box::pin(async move {
+            let request = reqwest::Request::try_from(request.map(reqwest::Body::from))
+                .map_err(|e| Error::Request(describe(&e)))?;
+            let response = self
+                .client
+                .execute(request)
+                .await
+                .map_err(|e| Error::Transport(describe(&e)))?;
+
+            let mut builder = Response::builder().status(response.status());
+            if let Some(headers) = builder.headers_mut() {
+                headers.extend(
+                    response
+                        .headers()
+                        .iter()
+                        .map(|(name, value)| (name.clone(), value.clone())),
+                );
+            }
+            let body = response
+                .bytes()
+                .await
+                .map_err(|e| Error::Transport(describe(&e)))?;
+            builder.body(body).map_err(|e| Error::Decode(describe(&e)))
+        })
+    }
+}
+
+/// An in-process `tower::Service`, called with `ServiceExt::oneshot`
+#[derive(Debug, Clone)]
+pub struct ServiceTransport<S> {
+    service: S,
+}
+
+impl<S> ServiceTransport<S> {
+    pub fn new(service: S) -> Self {
+        Self { service }
+    }
+}
+
+impl<S, R> Transport for ServiceTransport<S>
+where
+    S: Service<Request<Full<Bytes>>, Response = Response<R>> + Clone + Send + Sync + 'static,
+    S::Error: Into<BoxError>,
+    S::Future: Send,
+    R: http_body::Body + Send,
+    R::Data: Send,
+    R::Error: Into<BoxError>,
+{
+    fn send(&self, request: Request<Bytes>) -> BoxFuture<'_, Result<Response<Bytes>>> {
+        let service = self.service.clone();
+        Box::pin(async move {
+            let response = service
+                .oneshot(request.map(Full::new))
+                .await
+                .map_err(|e| Error::Transport(describe(&*e.into())))?;
+            let (parts, body) = response.into_parts();
+            let body = body
+                .collect()
+                .await
+                .map_err(|e| Error::Transport(describe(&*e.into())))?
+                .to_bytes();
+            Ok(Response::from_parts(parts, body))
+        })
+    }
+}
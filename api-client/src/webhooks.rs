@@ -0,0 +1,64 @@
+//! Webhook routes (`/api/v1/webhooks`)
+
+use http::Method;
+
+use api::dto::{CreateWebhookRequest, WebhookDeliveryDto, WebhookDeliveryQuery, WebhookDto};
+
+use crate::{segment, Client, Result};
+
+impl Client {
+    /// `GET /api/v1/webhooks`
+    pub async fn list_webhooks(&self) -> Result<Vec<WebhookDto>> {
+        self.call(Method::GET, "/api/v1/webhooks".to_string())
+            .send()
+            .await
+    }
+
+    /// `POST /api/v1/webhooks`
+    pub async fn create_webhook(&self, request: &CreateWebhookRequest) -> Result<WebhookDto> {
+        self.call(Method::POST, "/api/v1/webhooks".to_string())
+            .json(request)
+            .send()
+            .await
+    }
+
+    /// `GET /api/v1/webhooks/{name}`
+    pub async fn get_webhook(&self, name: &str) -> Result<WebhookDto> {
+        let path = format!("/api/v1/webhooks/{}", segment(name));
+        self.call(Method::GET, path).send().await
+    }
+
+    /// `DELETE /api/v1/webhooks/{name}`
+    pub async fn delete_webhook(&self, name: &str) -> Result<String> {
+        let path = format!("/api/v1/webhooks/{}", segment(name));
+        self.call(Method::DELETE, path).send().await
+    }
+
+    /// `GET /api/v1/webhooks/deliveries`
+    pub async fn webhook_deliveries(
+        &self,
+        query: &WebhookDeliveryQuery,
+    ) -> Result<Vec<WebhookDeliveryDto>> {
+        self.call(Method::GET, "/api/v1/webhooks/deliveries".to_string())
+            .query(query)
+            .send()
+            .await
+    }
+
+    /// `GET /api/v1/webhooks/dead-letters`
+    pub async fn webhook_dead_letters(
+        &self,
+        query: &WebhookDeliveryQuery,
+    ) -> Result<Vec<WebhookDeliveryDto>> {
+        self.call(Method::GET, "/api/v1/webhooks/dead-letters".to_string())
+            .query(query)
+            .send()
+            .await
+    }
+
+    /// `POST /api/v1/webhooks/dead-letters/{id}/retry`
+    pub async fn retry_webhook_delivery(&self, id: u64) -> Result<WebhookDeliveryDto> {
+        let path = format!("/api/v1/webhooks/dead-letters/{}/retry", id);
+        self.call(Method::POST, path).send().await
+    }
+}
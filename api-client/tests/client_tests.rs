@@ -0,0 +1,305 @@
+//! Integration tests running the client against the in-process router
+
+use std::convert::Infallible;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::{
+    body::Body,
+    http::{header, Request, StatusCode},
+    response::{IntoResponse, Response},
+    Router,
+};
+use bytes::Bytes;
+use http_body_util::Full;
+use tower::ServiceExt;
+
+use api::config::{ApiKeyConfig, AuthConfig, BackendKind};
+use api::dto::{CreateSnapshotRequest, CreateVmRequest, ExportVmRequest};
+use api::error::PROBLEM_JSON;
+use api::{create_router, AppState, Config, Role};
+use api_client::{
+    ApiError, Auth, Client, Error, ErrorCode, FieldErrorCode, ListOptions, RetryPolicy,
+};
+
+fn create_app(config: Config) -> Router {
+    create_router(Arc::new(AppState::from_config(&config).unwrap()))
+}
+
+fn fake_config() -> Config {
+    let mut config = Config::default();
+    config.backend.kind = BackendKind::Fake;
+    config
+}
+
+fn create_vm_request(name: &str, memory_mb: u64) -> CreateVmRequest {
+    serde_json::from_value(serde_json::json!({
+        "name": name,
+        "memory_mb": memory_mb,
+        "vhd_path": format!(r"C:\VMs\{}.vhdx", name),
+        "vhd_size_bytes": 10737418240u64
+    }))
+    .unwrap()
+}
+
+/// Router behind a service that fails the first `failures` requests with
+/// `error` and records each request's `Idempotency-Key`
+fn flaky(
+    app: Router,
+    failures: usize,
+    error: ApiError,
+) -> (Client, Arc<Mutex<Vec<Option<String>>>>) {
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let service = tower::service_fn({
+        let seen = seen.clone();
+        move |request: Request<Full<Bytes>>| {
+            let app = app.clone();
+            let seen = seen.clone();
+            let error = error.clone();
+            async move {
+                let attempt = {
+                    let mut seen = seen.lock().unwrap();
+                    let key = request.headers().get("idempotency-key");
+                    seen.push(key.map(|key| key.to_str().unwrap().to_string()));
+                    seen.len()
+                };
+                if attempt <= failures {
+                    let problem = serde_json::to_string(&error.problem(None)).unwrap();
+                    let response = (
+                        error.status,
+                        [(header::CONTENT_TYPE, PROBLEM_JSON)],
+                        problem,
+                    );
+                    return Ok::<Response, Infallible>(response.into_response());
+                }
+                app.oneshot(request).await
+            }
+        }
+    });
+    (Client::from_service(service), seen)
+}
+
+#[tokio::test]
+async fn test_routes_round_trip_dto_types() {
+    let client = Client::from_service(create_app(fake_config()));
+    assert_eq!(client.health().await.unwrap(), "ok");
+
+    for (name, memory_mb) in [("web-01", 2048), ("web-02", 4096), ("db-01", 8192)] {
+        let vm = client
+            .create_vm(&create_vm_request(name, memory_mb))
+            .await
+            .unwrap();
+        assert_eq!(vm.name, name);
+    }
+    client.start_vm("web-02").await.unwrap();
+    assert_eq!(client.get_vm("web-02").await.unwrap().state, "Running");
+
+    // Filters, sort and cursor paging
+    let options = ListOptions::new()
+        .filter("name", "web-*")
+        .sort("memory_mb:desc")
+        .limit(1);
+    let first = client.list_vms(&options).await.unwrap();
+    assert_eq!(first.total, 2);
+    assert_eq!(first.items[0].name, "web-02");
+    let cursor = first.next_cursor.unwrap();
+    let second = client.list_vms(&options.cursor(cursor)).await.unwrap();
+    assert_eq!(second.items[0].name, "web-01");
+    assert!(second.next_cursor.is_none());
+
+    // Path segments are percent-encoded
+    let snapshot = client
+        .create_snapshot(
+            "web-01",
+            &CreateSnapshotRequest {
+                name: "before patch".to_string(),
+                snapshot_type: None,
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(snapshot.name, "before patch");
+    let snapshot = client.get_snapshot("web-01", "before patch").await.unwrap();
+    assert_eq!(snapshot.vm_name, "web-01");
+
+    // Accepted routes return the job, which can be waited on
+    let job = client
+        .export_vm(
+            "db-01",
+            &ExportVmRequest {
+                path: r"D:\Exports".to_string(),
+            },
+        )
+        .await
+        .unwrap();
+    let job = client.wait_job(job.id, Some(5)).await.unwrap();
+    assert_eq!(job.state, "Completed");
+    assert_eq!(
+        client.list_jobs(&ListOptions::new()).await.unwrap().total,
+        1
+    );
+
+    assert_eq!(client.cluster_info(None).await.unwrap(), "FAKE-CLUSTER");
+    let openapi = client.openapi().await.unwrap();
+    assert!(openapi["paths"]["/api/v1/hyperv/vms"].is_object());
+}
+
+#[tokio::test]
+async fn test_error_responses_are_typed() {
+    let client = Client::from_service(create_app(fake_config()));
+
+    let err = client.get_vm("missing").await.unwrap_err();
+    match &err {
+        Error::Api(err) => {
+            assert_eq!(err.status, StatusCode::NOT_FOUND);
+            assert_eq!(err.code, ErrorCode::VmNotFound);
+            assert!(err.message.contains("missing"), "{}", err.message);
+            assert!(!err.retryable);
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+    assert!(!err.is_retryable());
+
+    let err = client
+        .list_vms(&ListOptions::new().filter("colour", "blue"))
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Some(ErrorCode::BadRequest));
+
+    // Validation failures list the offending fields
+    match client.create_vm(&create_vm_request("web-01", 8)).await {
+        Err(Error::Api(err)) => {
+            assert_eq!(err.code, ErrorCode::ValidationFailed);
+            assert_eq!(err.errors.len(), 1);
+            assert_eq!(err.errors[0].field, "memory_mb");
+            assert_eq!(err.errors[0].code, FieldErrorCode::OutOfRange);
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+
+    client
+        .create_vm(&create_vm_request("app", 1024))
+        .await
+        .unwrap();
+    let err = client.resume_vm("app").await.unwrap_err();
+    assert_eq!(err.status(), Some(StatusCode::CONFLICT));
+}
+
+#[tokio::test]
+async fn test_auth_headers() {
+    let mut config = fake_config();
+    config.auth = AuthConfig {
+        enabled: true,
+        api_keys: vec![ApiKeyConfig {
+            name: "reader".to_string(),
+            key: "reader-key".to_string(),
+            role: Role::Reader,
+        }],
+        ..AuthConfig::default()
+    };
+    let app = create_app(config);
+
+    let anonymous = Client::from_service(app.clone());
+    let err = anonymous.list_vms(&ListOptions::new()).await.unwrap_err();
+    assert_eq!(err.code(), Some(ErrorCode::Unauthorized));
+
+    let reader = anonymous.with_auth(Auth::api_key("reader-key"));
+    assert_eq!(reader.list_vms(&ListOptions::new()).await.unwrap().total, 0);
+    let err = reader.start_vm("web-01").await.unwrap_err();
+    assert_eq!(err.code(), Some(ErrorCode::Forbidden));
+
+    let bearer = Client::from_service(app).with_auth(Auth::bearer("token"));
+    match bearer.list_vms(&ListOptions::new()).await.unwrap_err() {
+        Error::Api(err) => assert_eq!(err.message, "Bearer tokens are not accepted"),
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_retries_retryable_errors_with_one_idempotency_key() {
+    let busy = ApiError::new(
+        StatusCode::SERVICE_UNAVAILABLE,
+        ErrorCode::TooManyOperations,
+        "Too many operations in progress",
+    )
+    .with_retryable(true);
+    let retry = RetryPolicy {
+        max_attempts: 3,
+        initial_backoff: Duration::from_millis(10),
+        max_backoff: Duration::from_millis(50),
+    };
+
+    let (client, seen) = flaky(create_app(fake_config()), 2, busy.clone());
+    let vm = client
+        .with_retry(retry)
+        .create_vm(&create_vm_request("web-01", 1024))
+        .await
+        .unwrap();
+    assert_eq!(vm.name, "web-01");
+    let keys = seen.lock().unwrap().clone();
+    assert_eq!(keys.len(), 3);
+    assert!(keys[0].is_some());
+    assert!(keys.iter().all(|key| *key == keys[0]));
+
+    // Attempts run out
+    let (client, seen) = flaky(create_app(fake_config()), 5, busy.clone());
+    let err = client.with_retry(retry).host_info().await.unwrap_err();
+    assert_eq!(err.code(), Some(ErrorCode::TooManyOperations));
+    assert!(err.is_retryable());
+    assert_eq!(seen.lock().unwrap().len(), 3);
+
+    // Disabled retries and non-retryable errors are sent once
+    let (client, seen) = flaky(create_app(fake_config()), 5, busy.clone());
+    client
+        .with_retry(RetryPolicy::none())
+        .host_info()
+        .await
+        .unwrap_err();
+    assert_eq!(*seen.lock().unwrap(), [None]);
+
+    let (client, seen) = flaky(create_app(fake_config()), 0, busy);
+    let err = client
+        .with_retry(retry)
+        .get_vm("missing")
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Some(ErrorCode::VmNotFound));
+    assert_eq!(seen.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_per_call_timeouts() {
+    let slow = tower::service_fn(|_: Request<Full<Bytes>>| async {
+        tokio::time::sleep(Duration::from_secs(5)).await;
+        Ok::<_, Infallible>(Response::new(Body::empty()))
+    });
+    let client = Client::from_service(slow);
+    let started = Instant::now();
+    let err = client
+        .with_timeout(Duration::from_millis(100))
+        .host_info()
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::Timeout(_)), "{:?}", err);
+    assert!(started.elapsed() < Duration::from_secs(2));
+
+    // Waiting on a job extends a shorter timeout to cover the wait
+    let mut config = fake_config();
+    config.backend.operation_delay_ms = 500;
+    let client = Client::from_service(create_app(config)).with_timeout(Duration::from_millis(200));
+    client
+        .create_vm(&create_vm_request("web-01", 1024))
+        .await
+        .unwrap();
+    let job = client
+        .export_vm(
+            "web-01",
+            &ExportVmRequest {
+                path: r"D:\Exports".to_string(),
+            },
+        )
+        .await
+        .unwrap();
+    let job = client.wait_job(job.id, Some(5)).await.unwrap();
+    assert_eq!(job.state, "Completed");
+}
@@ -0,0 +1,851 @@
+//! Aggregator mode: one API in front of every node agent of a cluster
+//!
+//! With `[aggregator] enabled`, `/api/v1/hyperv` and `/api/v1/jobs` are
+//! answered by the peer node agents instead of the local backend:
+//!
+//! - `GET` requests under `/hyperv` and `GET /jobs` go to every node in
+//!   parallel. List items are merged and tagged with the `node` they came
+//!   from, then sorted, projected and paged here, so `sort`, `fields`,
+//!   `limit` and `cursor` work across the cluster. Other reads return one
+//!   tagged entry per node that has the object.
+//! - Requests under `/hyperv/vms/{name}` go to the node that owns the VM. The
+//!   owner is found by asking every node and remembered until the VM is no
+//!   longer there.
+//! - Any other request names its node with `node=NAME`; the same parameter
+//!   narrows a fan-out to a comma-separated list of nodes.
+//!
+//! A node that fails or misses `timeout_ms` does not fail a fan-out: it is
+//! listed in the envelope's `node_errors` next to the data of the others.
+//! Only when no node answers is the request a `502`.
+//!
+//! Peers come from `peers` and, with `discover`, from the cluster's node
+//! list. Requests to peers carry [`FORWARDED_HEADER`] so a peer that is itself
+//! an aggregator answers from its own backend, and authenticate with
+//! `api_key` or else the caller's own credentials. Roles are checked here
+//! before anything is forwarded.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use axum::{
+    body::{to_bytes, Bytes},
+    extract::{FromRequestParts, MatchedPath, RawPathParams, Request, State},
+    http::{header, request::Parts, HeaderMap, HeaderName, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde_json::Value;
+
+use crate::auth::{authorize, Principal, Role, API_KEY_HEADER};
+use crate::config::{AggregatorConfig, ConfigError};
+use crate::dto::NodeErrorDto;
+use crate::error::{ApiError, ErrorCode};
+use crate::idempotency::IDEMPOTENCY_KEY;
+use crate::listing::ListQuery;
+use crate::openapi::API_PREFIX;
+use crate::response::{api_error, ApiResponse};
+use crate::{routes, AppState, SharedState};
+
+/// Marks a request sent by an aggregator; it is answered locally
+pub const FORWARDED_HEADER: &str = "x-nodeagent-forwarded";
+
+/// Query parameter naming the node, or nodes, a request is for
+pub const NODE_PARAM: &str = "node";
+
+/// Largest request body forwarded to a peer
+const BODY_LIMIT: usize = 2 * 1024 * 1024;
+
+/// Routes answered by the peers
+const AGGREGATED: [&str; 2] = ["/api/v1/hyperv", "/api/v1/jobs"];
+
+/// Routes owned by the node hosting the VM
+const VM_ROUTE: &str = "/api/v1/hyperv/vms/{name}";
+
+/// Listing parameters applied to the merged list rather than by each node
+const LISTING_PARAMS: [&str; 4] = ["sort", "fields", "limit", "cursor"];
+
+/// When discovery ran, the peers it found and the nodes it skipped as down
+type Discovered = (Instant, Vec<Peer>, Vec<NodeErrorDto>);
+
+/// One node agent behind the aggregator
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Peer {
+    pub name: String,
+    /// Base URL, e.g. `https://node1:6001`
+    pub url: String,
+}
+
+/// A peer's answer
+struct Reply {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+impl Reply {
+    fn json(&self) -> Option<Value> {
+        serde_json::from_slice(&self.body).ok()
+    }
+
+    /// The envelope's `error`, or a problem document's `detail`
+    fn message(&self) -> String {
+        self.json()
+            .and_then(|body| {
+                body.get("error")
+                    .or_else(|| body.get("detail"))
+                    .and_then(Value::as_str)
+                    .map(str::to_string)
+            })
+            .unwrap_or_else(|| format!("HTTP {}", self.status))
+    }
+
+    fn code(&self) -> Option<ErrorCode> {
+        serde_json::from_value(self.json()?.get("code")?.clone()).ok()
+    }
+
+    fn node_error(&self, node: &str) -> NodeErrorDto {
+        NodeErrorDto {
+            node: node.to_string(),
+            status: Some(self.status.as_u16()),
+            error: self.message(),
+        }
+    }
+
+    fn into_response(self) -> Response {
+        let mut response = (self.status, self.body).into_response();
+        for (name, value) in &self.headers {
+            response.headers_mut().insert(name, value.clone());
+        }
+        response
+    }
+}
+
+/// A request as it is sent to peers
+#[derive(Clone)]
+struct Call {
+    method: Method,
+    path: String,
+    /// Query parameters other than `node`
+    query: Vec<(String, String)>,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+/// Fans requests out to the node agents of a cluster
+pub struct Aggregator {
+    enabled: bool,
+    peers: Vec<Peer>,
+    discover: bool,
+    node_url: String,
+    discovery_interval: Duration,
+    api_key: Option<HeaderValue>,
+    timeout: Duration,
+    forward_timeout: Duration,
+    client: reqwest::Client,
+    /// Minimum role per method and route template
+    roles: HashMap<(Method, String), Role>,
+    /// Last discovery
+    discovered: Mutex<Option<Discovered>>,
+    /// Node owning each VM, keyed by lowercased VM name
+    owners: Mutex<HashMap<String, String>>,
+}
+
+impl Default for Aggregator {
+    fn default() -> Self {
+        Self::new(&AggregatorConfig::default()).expect("default aggregator settings are valid")
+    }
+}
+
+impl Aggregator {
+    pub fn new(config: &AggregatorConfig) -> Result<Self, ConfigError> {
+        let mut client = reqwest::Client::builder();
+        if let Some(path) = &config.ca_path {
+            let certificate = std::fs::read(path)
+                .map_err(|e| e.to_string())
+                .and_then(|pem| reqwest::Certificate::from_pem(&pem).map_err(|e| e.to_string()))
+                .map_err(|e| {
+                    ConfigError::Invalid(format!("cannot read peer CA '{}': {}", path, e))
+                })?;
+            client = client.add_root_certificate(certificate);
+        }
+        let client = client
+            .build()
+            .map_err(|e| ConfigError::Invalid(format!("cannot create peer client: {}", e)))?;
+        let api_key = config
+            .api_key
+            .as_deref()
+            .map(HeaderValue::from_str)
+            .transpose()
+            .map_err(|_| ConfigError::Invalid("aggregator.api_key: not a header value".into()))?;
+        let roles = match config.enabled {
+            true => routes::api_routes()
+                .policies()
+                .iter()
+                .map(|policy| {
+                    let path = format!("{}{}", API_PREFIX, policy.path);
+                    ((policy.method.clone(), path), policy.role)
+                })
+                .collect(),
+            false => HashMap::new(),
+        };
+        Ok(Self {
+            enabled: config.enabled,
+            peers: config
+                .peers
+                .iter()
+                .map(|peer| Peer {
+                    name: peer.name.clone(),
+                    url: peer.url.trim_end_matches('/').to_string(),
+                })
+                .collect(),
+            discover: config.discover,
+            node_url: config.node_url.trim_end_matches('/').to_string(),
+            discovery_interval: Duration::from_secs(config.discovery_interval_secs),
+            api_key,
+            timeout: Duration::from_millis(config.timeout_ms),
+            forward_timeout: Duration::from_millis(config.forward_timeout_ms),
+            client,
+            roles,
+            discovered: Mutex::new(None),
+            owners: Mutex::new(HashMap::new()),
+        })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Configured and discovered peers, and discovered nodes that are down
+    ///
+    /// A failed discovery falls back to the last discovered list.
+    pub async fn peers(&self, state: &AppState) -> (Vec<Peer>, Vec<NodeErrorDto>) {
+        let mut peers = self.peers.clone();
+        if !self.discover {
+            return (peers, Vec::new());
+        }
+        let cached = self.discovered.lock().unwrap().clone();
+        let (discovered, down) = match cached {
+            Some((at, discovered, down)) if at.elapsed() < self.discovery_interval => {
+                (discovered, down)
+            }
+            cached => {
+                let cluster = state.cluster.clone();
+                let nodes = tokio::task::spawn_blocking(move || cluster.list_nodes(None))
+                    .await
+                    .map_err(|e| e.to_string())
+                    .and_then(|nodes| nodes.map_err(|e| e.to_string()));
+                match nodes {
+                    Ok(nodes) => {
+                        let (up, down): (Vec<_>, Vec<_>) = nodes
+                            .into_iter()
+                            .partition(|node| matches!(node.state.as_str(), "Up" | "Paused"));
+                        let discovered: Vec<Peer> = up
+                            .into_iter()
+                            .map(|node| Peer {
+                                url: self.node_url.replace("{node}", &node.name),
+                                name: node.name,
+                            })
+                            .collect();
+                        let down: Vec<NodeErrorDto> = down
+                            .into_iter()
+                            .map(|node| NodeErrorDto {
+                                error: format!("Node is {}", node.state),
+                                node: node.name,
+                                status: None,
+                            })
+                            .collect();
+                        *self.discovered.lock().unwrap() =
+                            Some((Instant::now(), discovered.clone(), down.clone()));
+                        (discovered, down)
+                    }
+                    Err(error) => {
+                        tracing::warn!("Cannot discover cluster nodes: {}", error);
+                        cached
+                            .map(|(_, discovered, down)| (discovered, down))
+                            .unwrap_or_default()
+                    }
+                }
+            }
+        };
+        for peer in discovered {
+            if !peers
+                .iter()
+                .any(|p| p.name.eq_ignore_ascii_case(&peer.name))
+            {
+                peers.push(peer);
+            }
+        }
+        let down = down
+            .into_iter()
+            .filter(|node| {
+                !peers
+                    .iter()
+                    .any(|p| p.name.eq_ignore_ascii_case(&node.node))
+            })
+            .collect();
+        (peers, down)
+    }
+
+    /// Send `call` to one peer
+    async fn send(
+        &self,
+        peer: &Peer,
+        call: &Call,
+        timeout: Duration,
+    ) -> Result<Reply, NodeErrorDto> {
+        let node_error = |error: String| NodeErrorDto {
+            node: peer.name.clone(),
+            status: None,
+            error,
+        };
+        let response = self
+            .client
+            .request(call.method.clone(), format!("{}{}", peer.url, call.path))
+            .query(&call.query)
+            .headers(call.headers.clone())
+            .body(call.body.clone())
+            .timeout(timeout)
+            .send()
+            .await
+            .map_err(|e| match e.is_timeout() {
+                true => node_error(format!("Timed out after {} ms", timeout.as_millis())),
+                false => node_error(format!("Request failed: {}", e)),
+            })?;
+        let status = response.status();
+        let mut headers = HeaderMap::new();
+        for name in [header::CONTENT_TYPE, header::LOCATION, header::RETRY_AFTER] {
+            if let Some(value) = response.headers().get(&name) {
+                headers.insert(name, value.clone());
+            }
+        }
+        let body = response
+            .bytes()
+            .await
+            .map_err(|e| node_error(format!("Reading the response failed: {}", e)))?;
+        Ok(Reply {
+            status,
+            headers,
+            body,
+        })
+    }
+
+    /// Send a read to one peer, following `next_cursor` until the last page
+    async fn read_all(&self, peer: &Peer, call: &Call) -> Result<Reply, NodeErrorDto> {
+        let mut reply = self.send(peer, call, self.timeout).await?;
+        let envelope = reply.json().filter(|_| reply.status.is_success());
+        let Some(mut envelope) = envelope.filter(|body| body["next_cursor"].is_string()) else {
+            return Ok(reply);
+        };
+        while let Some(cursor) = envelope["next_cursor"].as_str() {
+            let mut page = call.clone();
+            page.query.push(("cursor".to_string(), cursor.to_string()));
+            let next = self.send(peer, &page, self.timeout).await?;
+            if !next.status.is_success() {
+                return Err(next.node_error(&peer.name));
+            }
+            let mut next = next.json().unwrap_or_default();
+            if let (Some(items), Some(more)) =
+                (envelope["data"].as_array_mut(), next["data"].as_array_mut())
+            {
+                items.append(more);
+            }
+            envelope["next_cursor"] = next["next_cursor"].take();
+        }
+        reply.body = Bytes::from(envelope.to_string());
+        Ok(reply)
+    }
+
+    /// Send `call` to every peer in parallel, in peer order
+    async fn fan_out(
+        state: &SharedState,
+        peers: &[Peer],
+        call: Call,
+    ) -> Vec<(String, Result<Reply, NodeErrorDto>)> {
+        let call = std::sync::Arc::new(call);
+        let tasks: Vec<_> = peers
+            .iter()
+            .map(|peer| {
+                let (state, peer, call) = (state.clone(), peer.clone(), call.clone());
+                tokio::spawn(async move {
+                    let reply = state.aggregator.read_all(&peer, &call).await;
+                    (peer.name, reply)
+                })
+            })
+            .collect();
+        let mut replies = Vec::with_capacity(tasks.len());
+        for (peer, task) in peers.iter().zip(tasks) {
+            replies.push(task.await.unwrap_or_else(|e| {
+                let error = NodeErrorDto {
+                    node: peer.name.clone(),
+                    status: None,
+                    error: e.to_string(),
+                };
+                (peer.name.clone(), Err(error))
+            }));
+        }
+        replies
+    }
+
+    /// Find the node that owns `vm`, asking every peer unless it is known
+    async fn owner(
+        state: &SharedState,
+        peers: &[Peer],
+        vm: &str,
+        call: &Call,
+    ) -> Result<(Peer, bool), ApiError> {
+        let aggregator = &state.aggregator;
+        let known = aggregator
+            .owners
+            .lock()
+            .unwrap()
+            .get(&vm.to_lowercase())
+            .cloned();
+        if let Some(peer) = known.and_then(|name| peers.iter().find(|p| p.name == name)) {
+            return Ok((peer.clone(), true));
+        }
+
+        let lookup = Call {
+            method: Method::GET,
+            path: format!("{}/hyperv/vms/{}", API_PREFIX, segment(vm)),
+            query: Vec::new(),
+            headers: call.headers.clone(),
+            body: Bytes::new(),
+        };
+        let mut owners = Vec::new();
+        let mut node_errors = Vec::new();
+        for (node, reply) in Self::fan_out(state, peers, lookup).await {
+            match reply {
+                Ok(reply) if reply.status.is_success() => owners.push(node),
+                Ok(reply) if reply.status == StatusCode::NOT_FOUND => {}
+                Ok(reply) => node_errors.push(reply.node_error(&node)),
+                Err(error) => node_errors.push(error),
+            }
+        }
+        match owners.as_slice() {
+            [owner] => {
+                aggregator
+                    .owners
+                    .lock()
+                    .unwrap()
+                    .insert(vm.to_lowercase(), owner.clone());
+                let peer = peers.iter().find(|p| &p.name == owner).cloned();
+                Ok((peer.expect("owner is a peer"), false))
+            }
+            [] if node_errors.is_empty() => Err(ApiError::new(
+                StatusCode::NOT_FOUND,
+                ErrorCode::VmNotFound,
+                format!("VM '{}' not found on any node", vm),
+            )),
+            [] => {
+                let mut err = ApiError::from_status(
+                    StatusCode::BAD_GATEWAY,
+                    format!(
+                        "VM '{}' not found on the nodes that answered; {} did not",
+                        vm,
+                        node_errors.len()
+                    ),
+                );
+                err.node_errors = node_errors;
+                Err(err)
+            }
+            owners => Err(ApiError::new(
+                StatusCode::CONFLICT,
+                ErrorCode::Conflict,
+                format!(
+                    "VM '{}' exists on nodes {}; pass {}=NAME to choose one",
+                    vm,
+                    owners.join(", "),
+                    NODE_PARAM
+                ),
+            )),
+        }
+    }
+
+    fn forget_owner(&self, vm: &str) {
+        self.owners.lock().unwrap().remove(&vm.to_lowercase());
+    }
+
+    fn remember_owners(&self, items: &[Value]) {
+        let mut owners = self.owners.lock().unwrap();
+        for item in items {
+            if let (Some(vm), Some(node)) = (item["name"].as_str(), item["node"].as_str()) {
+                owners.insert(vm.to_lowercase(), node.to_string());
+            }
+        }
+    }
+}
+
+// =============================================================================
+// Middleware
+// =============================================================================
+
+/// Answer `/api/v1/hyperv` and `/api/v1/jobs` from the peers in aggregator mode
+pub async fn route(State(state): State<SharedState>, request: Request, next: Next) -> Response {
+    let aggregator = &state.aggregator;
+    if !aggregator.enabled || request.headers().contains_key(FORWARDED_HEADER) {
+        return next.run(request).await;
+    }
+    let Some(template) = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().trim_end_matches('/').to_string())
+    else {
+        return next.run(request).await;
+    };
+    let aggregated = AGGREGATED.iter().any(|prefix| {
+        template
+            .strip_prefix(prefix)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+    });
+    let role = aggregator
+        .roles
+        .get(&(request.method().clone(), template.clone()))
+        .copied();
+    let Some(role) = role.filter(|_| aggregated) else {
+        return next.run(request).await;
+    };
+    if let Err(response) = authorize(request.extensions().get::<Principal>(), role) {
+        return response;
+    }
+
+    let (mut parts, body) = request.into_parts();
+    let body = match to_bytes(body, BODY_LIMIT).await {
+        Ok(body) => body,
+        Err(_) => {
+            return api_error(StatusCode::PAYLOAD_TOO_LARGE, "Request body is too large")
+                .into_response()
+        }
+    };
+    match dispatch(&state, &template, &mut parts, body).await {
+        Ok(response) => response,
+        Err(err) => err.into_response(),
+    }
+}
+
+async fn dispatch(
+    state: &SharedState,
+    template: &str,
+    parts: &mut Parts,
+    body: Bytes,
+) -> Result<Response, ApiError> {
+    let aggregator = &state.aggregator;
+    let pairs: Vec<(String, String)> = parts
+        .uri
+        .query()
+        .map(|query| {
+            url_pairs(query)
+                .into_iter()
+                .filter(|(key, _)| key != NODE_PARAM)
+                .collect()
+        })
+        .unwrap_or_default();
+    let selected = url_pairs(parts.uri.query().unwrap_or_default())
+        .into_iter()
+        .find(|(key, _)| key == NODE_PARAM)
+        .map(|(_, nodes)| nodes);
+
+    let (peers, down) = aggregator.peers(state).await;
+    let (peers, mut node_errors) = match &selected {
+        Some(nodes) => (select(&peers, &down, nodes)?, Vec::new()),
+        None => (peers, down),
+    };
+
+    let mut headers = HeaderMap::new();
+    headers.insert(FORWARDED_HEADER, HeaderValue::from_static("1"));
+    match &aggregator.api_key {
+        Some(key) => {
+            headers.insert(API_KEY_HEADER, key.clone());
+        }
+        None => copy(
+            &parts.headers,
+            &mut headers,
+            &[API_KEY_HEADER, "authorization"],
+        ),
+    }
+    copy(
+        &parts.headers,
+        &mut headers,
+        &["content-type", IDEMPOTENCY_KEY],
+    );
+    let mut call = Call {
+        method: parts.method.clone(),
+        path: parts.uri.path().to_string(),
+        query: pairs,
+        headers,
+        body,
+    };
+
+    if template == VM_ROUTE || template.starts_with(&format!("{}/", VM_ROUTE)) {
+        let params = RawPathParams::from_request_parts(parts, &()).await.ok();
+        let vm = params
+            .iter()
+            .flat_map(|params| params.iter())
+            .find(|(key, _)| *key == "name")
+            .map(|(_, value)| value.to_string())
+            .unwrap_or_default();
+        copy(&parts.headers, &mut call.headers, &["accept"]);
+        if selected.is_some() {
+            let peer = only(&peers)?;
+            return forward(state, peer, &call).await;
+        }
+        let (peer, cached) = Aggregator::owner(state, &peers, &vm, &call).await?;
+        let reply = aggregator
+            .send(&peer, &call, aggregator.forward_timeout)
+            .await
+            .map_err(no_answer)?;
+        if reply.status == StatusCode::NOT_FOUND && reply.code() == Some(ErrorCode::VmNotFound) {
+            aggregator.forget_owner(&vm);
+            if cached {
+                let (peer, _) = Aggregator::owner(state, &peers, &vm, &call).await?;
+                return forward(state, &peer, &call).await;
+            }
+        }
+        return Ok(tagged(reply, &peer.name));
+    }
+
+    let fans_out = parts.method == Method::GET
+        && (template.starts_with("/api/v1/hyperv/") || template == "/api/v1/jobs");
+    if !fans_out {
+        copy(&parts.headers, &mut call.headers, &["accept"]);
+        let peer = match (selected.is_some(), peers.as_slice()) {
+            (false, [peer]) => peer,
+            (false, _) => {
+                return Err(api_error(
+                    StatusCode::BAD_REQUEST,
+                    &format!(
+                        "Pass {}=NAME: this request is not for one VM, so the aggregator \
+                         cannot tell which node it is for",
+                        NODE_PARAM
+                    ),
+                ))
+            }
+            (true, _) => only(&peers)?,
+        };
+        return forward(state, peer, &call).await;
+    }
+
+    let list = ListQuery::from_request_parts(parts, &())
+        .await?
+        .without(NODE_PARAM);
+    call.query
+        .retain(|(key, _)| !LISTING_PARAMS.contains(&key.as_str()));
+    call.headers
+        .insert(header::ACCEPT, HeaderValue::from_static("application/json"));
+
+    let mut items = Vec::new();
+    let (mut is_list, mut is_paged) = (false, false);
+    let mut refused = None;
+    let mut unreachable_nodes = 0;
+    for (node, reply) in Aggregator::fan_out(state, &peers, call).await {
+        let reply = match reply {
+            Ok(reply) => reply,
+            Err(error) => {
+                unreachable_nodes += 1;
+                node_errors.push(error);
+                continue;
+            }
+        };
+        if reply.status == StatusCode::NOT_FOUND {
+            refused.get_or_insert(reply);
+            continue;
+        }
+        let envelope = reply.json().filter(|_| reply.status.is_success());
+        let Some(mut envelope) = envelope else {
+            node_errors.push(reply.node_error(&node));
+            if reply.status.is_client_error() {
+                refused = Some(reply);
+            }
+            continue;
+        };
+        is_paged |= envelope.get("total").is_some();
+        match envelope["data"].take() {
+            Value::Array(data) => {
+                is_list = true;
+                items.extend(data.into_iter().map(|item| tag(item, &node)));
+            }
+            data => items.push(tag(data, &node)),
+        }
+    }
+
+    if items.is_empty() && !is_list {
+        return match refused {
+            Some(reply) if unreachable_nodes == 0 => Ok(reply.into_response()),
+            _ if node_errors.is_empty() => {
+                Err(api_error(StatusCode::BAD_GATEWAY, "No nodes are available"))
+            }
+            _ => {
+                let mut err = ApiError::from_status(
+                    StatusCode::BAD_GATEWAY,
+                    format!("No node answered: {} failed", node_errors.len()),
+                );
+                err.node_errors = node_errors;
+                Err(err)
+            }
+        };
+    }
+    if template == "/api/v1/hyperv/vms" {
+        aggregator.remember_owners(&items);
+    }
+    let mut envelope = ApiResponse::success(items);
+    if is_paged {
+        let page = list.merge(envelope.data.take().unwrap_or_default())?;
+        envelope.data = Some(page.items);
+        envelope.total = Some(page.total);
+        envelope.next_cursor = page.next_cursor;
+    }
+    envelope.node_errors = node_errors;
+    Ok(Json(envelope).into_response())
+}
+
+/// Send `call` to `peer` and tag the answer with its name
+async fn forward(state: &SharedState, peer: &Peer, call: &Call) -> Result<Response, ApiError> {
+    let reply = state
+        .aggregator
+        .send(peer, call, state.aggregator.forward_timeout)
+        .await
+        .map_err(no_answer)?;
+    Ok(tagged(reply, &peer.name))
+}
+
+/// `502`, or `504` on a timeout, for a peer that did not answer
+fn no_answer(error: NodeErrorDto) -> ApiError {
+    let status = match error.error.starts_with("Timed out") {
+        true => StatusCode::GATEWAY_TIMEOUT,
+        false => StatusCode::BAD_GATEWAY,
+    };
+    let mut err = ApiError::from_status(
+        status,
+        format!("Node '{}' did not answer: {}", error.node, error.error),
+    );
+    err.node_errors = vec![error];
+    err
+}
+
+/// A peer's answer with `node` added to its data, or unchanged if it has none
+fn tagged(reply: Reply, node: &str) -> Response {
+    let envelope = reply.json().filter(|_| reply.status.is_success());
+    let Some(mut envelope) = envelope.filter(|body| body.get("data").is_some()) else {
+        return reply.into_response();
+    };
+    envelope["data"] = match envelope["data"].take() {
+        Value::Array(items) => Value::Array(items.into_iter().map(|i| tag(i, node)).collect()),
+        data => tag(data, node),
+    };
+    let mut response = (reply.status, Json(envelope)).into_response();
+    for (name, value) in &reply.headers {
+        if name != header::CONTENT_TYPE {
+            response.headers_mut().insert(name, value.clone());
+        }
+    }
+    response
+}
+
+fn tag(mut item: Value, node: &str) -> Value {
+    if let Some(object) = item.as_object_mut() {
+        object.insert(NODE_PARAM.to_string(), Value::String(node.to_string()));
+    }
+    item
+}
+
+/// Peers named by a comma-separated `node` parameter
+fn select(peers: &[Peer], down: &[NodeErrorDto], nodes: &str) -> Result<Vec<Peer>, ApiError> {
+    nodes
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| {
+            if let Some(peer) = peers.iter().find(|p| p.name.eq_ignore_ascii_case(name)) {
+                return Ok(peer.clone());
+            }
+            match down
+                .iter()
+                .find(|node| node.node.eq_ignore_ascii_case(name))
+            {
+                Some(node) => Err(no_answer(node.clone())),
+                None => Err(ApiError::new(
+                    StatusCode::NOT_FOUND,
+                    ErrorCode::NodeNotFound,
+                    format!("Node '{}' is not a peer of this aggregator", name),
+                )),
+            }
+        })
+        .collect()
+}
+
+fn only(peers: &[Peer]) -> Result<&Peer, ApiError> {
+    match peers {
+        [peer] => Ok(peer),
+        _ => Err(api_error(
+            StatusCode::BAD_REQUEST,
+            &format!("{} must name exactly one node for this request", NODE_PARAM),
+        )),
+    }
+}
+
+fn copy(from: &HeaderMap, to: &mut HeaderMap, names: &[&str]) {
+    for name in names {
+        if let Some(value) = from.get(*name) {
+            to.insert(
+                HeaderName::from_bytes(name.as_bytes()).unwrap(),
+                value.clone(),
+            );
+        }
+    }
+}
+
+fn url_pairs(query: &str) -> Vec<(String, String)> {
+    reqwest::Url::parse(&format!("http://aggregator/?{}", query))
+        .map(|url| url.query_pairs().into_owned().collect())
+        .unwrap_or_default()
+}
+
+/// Percent-encode a VM name for use as one path segment
+fn segment(name: &str) -> String {
+    let mut url = reqwest::Url::parse("http://aggregator/").expect("static URL parses");
+    url.path_segments_mut()
+        .expect("http URLs have a path")
+        .push(name);
+    url.path()[1..].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select_and_segment() {
+        let peers = vec![
+            Peer {
+                name: "NODE1".into(),
+                url: "https://node1:6001".into(),
+            },
+            Peer {
+                name: "NODE2".into(),
+                url: "https://node2:6001".into(),
+            },
+        ];
+        let down = [NodeErrorDto {
+            node: "NODE3".into(),
+            status: None,
+            error: "Node is Down".into(),
+        }];
+        let selected = select(&peers, &down, "node2, NODE1").unwrap();
+        assert_eq!(selected, [peers[1].clone(), peers[0].clone()]);
+        let err = select(&peers, &down, "node3").unwrap_err();
+        assert_eq!(err.status, StatusCode::BAD_GATEWAY);
+        assert_eq!(err.node_errors, down);
+        let err = select(&peers, &down, "node4").unwrap_err();
+        assert_eq!(err.code, ErrorCode::NodeNotFound);
+
+        assert_eq!(segment("web 01/a"), "web%2001%2Fa");
+        assert_eq!(
+            url_pairs("node=NODE1&state=Running%2COff"),
+            [
+                ("node".to_string(), "NODE1".to_string()),
+                ("state".to_string(), "Running,Off".to_string())
+            ]
+        );
+    }
+}
@@ -0,0 +1,562 @@
+//! Audit log of mutating API calls
+//!
+//! The [`record`] middleware writes one [`AuditRecordDto`] per POST, PUT,
+//! PATCH or DELETE request, after the handler has run, with the caller from
+//! [`Principal`], the route template and path parameters, the JSON body with
+//! secret fields redacted, the response status and the duration.
+//!
+//! Records go to the sink chosen by `[audit] sink`. The `file` sink appends
+//! JSON lines and rotates the file by size and age, renaming it to
+//! `<stem>.<UTC timestamp>.<ext>` and deleting the oldest rotated files
+//! beyond `max_files`. `GET /api/v1/audit` reads the file and memory sinks.
+
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Instant;
+
+use axum::{
+    body::{to_bytes, Body},
+    extract::{ConnectInfo, FromRequestParts, MatchedPath, RawPathParams, Request, State},
+    http::{Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use serde_json::Value;
+
+use crate::auth::Principal;
+use crate::config::{AuditConfig, AuditSink, ConfigError};
+use crate::dto::{AuditQuery, AuditRecordDto};
+use crate::response::api_error;
+use crate::tls::ConnectionInfo;
+use crate::SharedState;
+
+/// Largest request body buffered for the audit record (axum's default limit)
+const BODY_LIMIT: usize = 2 * 1024 * 1024;
+
+const DEFAULT_QUERY_LIMIT: usize = 100;
+const MAX_QUERY_LIMIT: usize = 1000;
+
+/// Errors returned by [`AuditLog::query`]
+#[derive(Debug, thiserror::Error)]
+pub enum AuditError {
+    #[error("Audit records are not queryable with the '{0}' sink")]
+    NotQueryable(&'static str),
+
+    #[error("Invalid '{0}' time: {1}")]
+    InvalidTime(&'static str, String),
+
+    #[error("Failed to read audit log: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+// =============================================================================
+// Log
+// =============================================================================
+
+enum Sink {
+    Disabled,
+    Stdout,
+    Memory {
+        records: Mutex<VecDeque<AuditRecordDto>>,
+        limit: usize,
+    },
+    File(Mutex<RotatingFile>),
+}
+
+/// Destination for audit records
+pub struct AuditLog {
+    sink: Sink,
+    redact_fields: Vec<String>,
+}
+
+impl Default for AuditLog {
+    fn default() -> Self {
+        Self::new(&AuditConfig::default()).expect("default audit configuration is valid")
+    }
+}
+
+impl AuditLog {
+    /// Open the configured sink; the `file` sink creates missing directories
+    pub fn new(config: &AuditConfig) -> Result<Self, ConfigError> {
+        let sink = match (config.enabled, config.sink) {
+            (false, _) => Sink::Disabled,
+            (true, AuditSink::Stdout) => Sink::Stdout,
+            (true, AuditSink::Memory) => Sink::Memory {
+                records: Mutex::new(VecDeque::new()),
+                limit: config.memory_limit,
+            },
+            (true, AuditSink::File) => {
+                Sink::File(Mutex::new(RotatingFile::open(config).map_err(|e| {
+                    ConfigError::Invalid(format!("cannot open audit log '{}': {}", config.path, e))
+                })?))
+            }
+        };
+        Ok(Self {
+            sink,
+            redact_fields: config
+                .redact_fields
+                .iter()
+                .map(|f| f.to_ascii_lowercase())
+                .collect(),
+        })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !matches!(self.sink, Sink::Disabled)
+    }
+
+    /// Whether records survive a restart
+    pub fn is_durable(&self) -> bool {
+        matches!(self.sink, Sink::File(_) | Sink::Stdout)
+    }
+
+    /// Append a record; failures are logged and never fail the request
+    pub fn write(&self, record: AuditRecordDto) {
+        match &self.sink {
+            Sink::Disabled => {}
+            Sink::Stdout => {
+                let line = serde_json::to_string(&record).expect("audit record serializes");
+                let mut stdout = std::io::stdout().lock();
+                let _ = writeln!(stdout, "{}", line);
+            }
+            Sink::Memory { records, limit } => {
+                let mut records = records.lock().unwrap();
+                records.push_back(record);
+                while records.len() > *limit {
+                    records.pop_front();
+                }
+            }
+            Sink::File(file) => {
+                let line = serde_json::to_string(&record).expect("audit record serializes");
+                if let Err(e) = file.lock().unwrap().append(&line) {
+                    tracing::error!("Failed to write audit record: {}", e);
+                }
+            }
+        }
+    }
+
+    /// Flush buffered records to the sink
+    pub fn flush(&self) {
+        if let Sink::File(file) = &self.sink {
+            if let Err(e) = file.lock().unwrap().file.sync_data() {
+                tracing::error!("Failed to flush audit log: {}", e);
+            }
+        }
+    }
+
+    /// Matching records, newest first
+    pub fn query(&self, query: &AuditQuery) -> Result<Vec<AuditRecordDto>, AuditError> {
+        let since = parse_time("since", query.since.as_deref())?;
+        let until = parse_time("until", query.until.as_deref())?;
+        let limit = query
+            .limit
+            .unwrap_or(DEFAULT_QUERY_LIMIT)
+            .min(MAX_QUERY_LIMIT);
+
+        let matches = |record: &AuditRecordDto| {
+            let time = DateTime::parse_from_rfc3339(&record.timestamp)
+                .map(|t| t.with_timezone(&Utc))
+                .ok();
+            since.is_none_or(|since| time.is_some_and(|t| t >= since))
+                && until.is_none_or(|until| time.is_some_and(|t| t < until))
+                && query
+                    .vm
+                    .as_ref()
+                    .is_none_or(|vm| record.vm_name.as_ref() == Some(vm))
+                && query
+                    .caller
+                    .as_ref()
+                    .is_none_or(|caller| record.caller.as_ref() == Some(caller))
+        };
+
+        let records: Vec<AuditRecordDto> = match &self.sink {
+            Sink::Disabled => Vec::new(),
+            Sink::Stdout => return Err(AuditError::NotQueryable("stdout")),
+            Sink::Memory { records, .. } => records
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|r| matches(r))
+                .cloned()
+                .collect(),
+            Sink::File(file) => file.lock().unwrap().read(&matches)?,
+        };
+        Ok(records.into_iter().rev().take(limit).collect())
+    }
+
+    /// Replace the values of secret-looking fields, recursively
+    pub fn sanitize(&self, value: Value) -> Value {
+        match value {
+            Value::Object(map) => Value::Object(
+                map.into_iter()
+                    .map(|(key, value)| {
+                        let lower = key.to_ascii_lowercase();
+                        if self
+                            .redact_fields
+                            .iter()
+                            .any(|f| lower.contains(f.as_str()))
+                        {
+                            (key, Value::String("***".to_string()))
+                        } else {
+                            (key, self.sanitize(value))
+                        }
+                    })
+                    .collect(),
+            ),
+            Value::Array(items) => {
+                Value::Array(items.into_iter().map(|v| self.sanitize(v)).collect())
+            }
+            other => other,
+        }
+    }
+}
+
+fn parse_time(
+    name: &'static str,
+    value: Option<&str>,
+) -> Result<Option<DateTime<Utc>>, AuditError> {
+    value
+        .map(|v| {
+            DateTime::parse_from_rfc3339(v)
+                .map(|t| t.with_timezone(&Utc))
+                .map_err(|e| AuditError::InvalidTime(name, e.to_string()))
+        })
+        .transpose()
+}
+
+fn timestamp(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+// =============================================================================
+// Rotating File
+// =============================================================================
+
+struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    opened_at: DateTime<Utc>,
+    max_size: u64,
+    max_age: Duration,
+    max_files: usize,
+}
+
+impl RotatingFile {
+    fn open(config: &AuditConfig) -> std::io::Result<Self> {
+        let path = PathBuf::from(&config.path);
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let (file, size, opened_at) = Self::open_file(&path)?;
+        Ok(Self {
+            path,
+            file,
+            size,
+            opened_at,
+            max_size: config.max_size_bytes,
+            max_age: Duration::hours(config.max_age_hours.try_into().unwrap_or(i64::MAX)),
+            max_files: config.max_files,
+        })
+    }
+
+    /// Open for append; an existing file keeps its original creation time
+    fn open_file(path: &Path) -> std::io::Result<(File, u64, DateTime<Utc>)> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let metadata = file.metadata()?;
+        let opened_at = if metadata.len() > 0 {
+            metadata
+                .created()
+                .or_else(|_| metadata.modified())
+                .map(DateTime::<Utc>::from)
+                .unwrap_or_else(|_| Utc::now())
+        } else {
+            Utc::now()
+        };
+        Ok((file, metadata.len(), opened_at))
+    }
+
+    fn append(&mut self, line: &str) -> std::io::Result<()> {
+        let len = line.len() as u64 + 1;
+        let now = Utc::now();
+        if self.size > 0
+            && (self.size + len > self.max_size || now - self.opened_at >= self.max_age)
+        {
+            self.rotate(now)?;
+        }
+        self.file.write_all(format!("{}\n", line).as_bytes())?;
+        self.size += len;
+        Ok(())
+    }
+
+    fn rotate(&mut self, now: DateTime<Utc>) -> std::io::Result<()> {
+        let rotated = self.rotated_name(&now.format("%Y%m%dT%H%M%S%3fZ").to_string());
+        fs::rename(&self.path, &rotated)?;
+        let (file, size, opened_at) = Self::open_file(&self.path)?;
+        self.file = file;
+        self.size = size;
+        self.opened_at = opened_at;
+
+        let rotated = self.rotated_files()?;
+        for old in rotated
+            .iter()
+            .take(rotated.len().saturating_sub(self.max_files))
+        {
+            if let Err(e) = fs::remove_file(old) {
+                tracing::warn!("Failed to delete rotated audit log {:?}: {}", old, e);
+            }
+        }
+        Ok(())
+    }
+
+    fn rotated_name(&self, stamp: &str) -> PathBuf {
+        let stem = self.path.file_stem().unwrap_or_default().to_string_lossy();
+        let name = match self.path.extension() {
+            Some(ext) => format!("{}.{}.{}", stem, stamp, ext.to_string_lossy()),
+            None => format!("{}.{}", stem, stamp),
+        };
+        self.path.with_file_name(name)
+    }
+
+    /// Rotated files, oldest first
+    fn rotated_files(&self) -> std::io::Result<Vec<PathBuf>> {
+        let template = self.rotated_name("*").to_string_lossy().into_owned();
+        let (prefix, suffix) = template.split_once('*').unwrap_or((&template, ""));
+        let dir = match self.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            Some(dir) => dir.to_path_buf(),
+            None => PathBuf::from("."),
+        };
+        let mut files: Vec<PathBuf> = fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| {
+                let path = path.to_string_lossy();
+                path.len() > prefix.len() + suffix.len()
+                    && path.starts_with(prefix)
+                    && path.ends_with(suffix)
+            })
+            .collect();
+        files.sort();
+        Ok(files)
+    }
+
+    /// Matching records from the rotated files and the current file, oldest first
+    fn read(
+        &self,
+        matches: &dyn Fn(&AuditRecordDto) -> bool,
+    ) -> std::io::Result<Vec<AuditRecordDto>> {
+        let mut records = Vec::new();
+        for path in self
+            .rotated_files()?
+            .into_iter()
+            .chain(std::iter::once(self.path.clone()))
+        {
+            let file = match File::open(&path) {
+                Ok(file) => file,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e),
+            };
+            for line in BufReader::new(file).lines() {
+                if let Ok(record) = serde_json::from_str::<AuditRecordDto>(&line?) {
+                    if matches(&record) {
+                        records.push(record);
+                    }
+                }
+            }
+        }
+        Ok(records)
+    }
+}
+
+// =============================================================================
+// Middleware
+// =============================================================================
+
+fn is_mutating(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::POST | Method::PUT | Method::PATCH | Method::DELETE
+    )
+}
+
+/// Record mutating requests once the handler has answered
+pub async fn record(State(state): State<SharedState>, request: Request, next: Next) -> Response {
+    if !state.audit.is_enabled() || !is_mutating(request.method()) {
+        return next.run(request).await;
+    }
+    let start = Instant::now();
+    let (mut parts, body) = request.into_parts();
+    let body = match to_bytes(body, BODY_LIMIT).await {
+        Ok(body) => body,
+        Err(_) => {
+            return api_error(StatusCode::PAYLOAD_TOO_LARGE, "Request body is too large")
+                .into_response()
+        }
+    };
+
+    let route = parts
+        .extensions
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+    let path_params: std::collections::BTreeMap<String, String> =
+        RawPathParams::from_request_parts(&mut parts, &())
+            .await
+            .map(|params| {
+                params
+                    .iter()
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+                    .collect()
+            })
+            .unwrap_or_default();
+    let request_body = serde_json::from_slice::<Value>(&body)
+        .ok()
+        .map(|value| state.audit.sanitize(value));
+    let vm_name = if route.starts_with("/api/v1/hyperv/vms/{name}") {
+        path_params.get("name").cloned()
+    } else if route == "/api/v1/hyperv/vms" {
+        request_body
+            .as_ref()
+            .and_then(|b| b["name"].as_str())
+            .map(str::to_string)
+    } else {
+        None
+    };
+    let principal = parts.extensions.get::<Principal>().cloned();
+    let client_addr = parts
+        .extensions
+        .get::<ConnectInfo<ConnectionInfo>>()
+        .map(|info| info.0.remote_addr.to_string());
+    let method = parts.method.to_string();
+    let path = parts.uri.path().to_string();
+    let timestamp = timestamp(Utc::now());
+
+    let response = next.run(Request::from_parts(parts, Body::from(body))).await;
+
+    state.audit.write(AuditRecordDto {
+        timestamp,
+        caller: principal.as_ref().map(|p| p.name.clone()),
+        role: principal.as_ref().map(|p| p.role.to_string()),
+        auth_method: principal.as_ref().map(|p| p.method.as_str().to_string()),
+        client_addr,
+        method,
+        route,
+        path,
+        path_params,
+        vm_name,
+        request_body,
+        status: response.status().as_u16(),
+        duration_ms: start.elapsed().as_millis() as u64,
+    });
+    response
+}
+
+// =============================================================================
+// Tests
+// =============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(vm: &str, caller: &str) -> AuditRecordDto {
+        AuditRecordDto {
+            timestamp: timestamp(Utc::now()),
+            caller: Some(caller.to_string()),
+            role: Some("operator".to_string()),
+            auth_method: Some("api_key".to_string()),
+            client_addr: None,
+            method: "POST".to_string(),
+            route: "/api/v1/hyperv/vms/{name}/stop".to_string(),
+            path: format!("/api/v1/hyperv/vms/{}/stop", vm),
+            path_params: [("name".to_string(), vm.to_string())].into(),
+            vm_name: Some(vm.to_string()),
+            request_body: None,
+            status: 200,
+            duration_ms: 1,
+        }
+    }
+
+    #[test]
+    fn test_sanitize() {
+        let log = AuditLog::default();
+        let body = serde_json::json!({
+            "name": "web01",
+            "admin_password": "hunter2",
+            "nested": [{ "ApiKey": "abc", "size": 10 }]
+        });
+        let sanitized = log.sanitize(body);
+        assert_eq!(sanitized["name"], "web01");
+        assert_eq!(sanitized["admin_password"], "***");
+        assert_eq!(sanitized["nested"][0]["ApiKey"], "***");
+        assert_eq!(sanitized["nested"][0]["size"], 10);
+    }
+
+    #[test]
+    fn test_memory_query() {
+        let log = AuditLog::default();
+        log.write(record("web01", "alice"));
+        log.write(record("web02", "bob"));
+        log.write(record("web01", "bob"));
+
+        let query = |vm: Option<&str>, caller: Option<&str>| AuditQuery {
+            vm: vm.map(str::to_string),
+            caller: caller.map(str::to_string),
+            ..AuditQuery::default()
+        };
+        assert_eq!(log.query(&query(Some("web01"), None)).unwrap().len(), 2);
+        let bob_web01 = log.query(&query(Some("web01"), Some("bob"))).unwrap();
+        assert_eq!(bob_web01.len(), 1);
+        assert_eq!(
+            log.query(&query(None, None)).unwrap()[0].caller.as_deref(),
+            Some("bob")
+        );
+
+        let future = AuditQuery {
+            since: Some("2999-01-01T00:00:00Z".to_string()),
+            ..AuditQuery::default()
+        };
+        assert!(log.query(&future).unwrap().is_empty());
+        let invalid = AuditQuery {
+            until: Some("yesterday".to_string()),
+            ..AuditQuery::default()
+        };
+        assert!(log.query(&invalid).is_err());
+    }
+
+    #[test]
+    fn test_file_rotation() {
+        let dir = std::env::temp_dir().join(format!("api-audit-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let config = AuditConfig {
+            sink: AuditSink::File,
+            path: dir.join("audit.jsonl").display().to_string(),
+            max_size_bytes: 600,
+            max_files: 2,
+            ..AuditConfig::default()
+        };
+        let log = AuditLog::new(&config).unwrap();
+        for i in 0..8 {
+            log.write(record(&format!("vm{}", i), "alice"));
+            // Rotated names have millisecond resolution
+            std::thread::sleep(std::time::Duration::from_millis(2));
+        }
+
+        let files: Vec<_> = fs::read_dir(&dir).unwrap().collect();
+        assert_eq!(files.len(), 3, "current file plus two rotated files");
+
+        let records = log.query(&AuditQuery::default()).unwrap();
+        assert!(records.len() < 8, "oldest rotated files are deleted");
+        assert_eq!(records[0].vm_name.as_deref(), Some("vm7"));
+        let reopened = AuditLog::new(&config).unwrap();
+        assert_eq!(
+            reopened.query(&AuditQuery::default()).unwrap().len(),
+            records.len()
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
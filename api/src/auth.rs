@@ -0,0 +1,670 @@
+//! Authentication and role-based authorization
+//!
+//! Requests authenticate with either a static API key (`X-API-Key` header)
+//! or a JWT bearer token (`Authorization: Bearer <token>`) signed with
+//! HS256 or RS256, or with an mTLS client certificate whose subject is
+//! mapped to a role. Every credential resolves to a [`Principal`] whose
+//! [`Role`] is checked against the minimum role of the matched route.
+
+use std::fmt;
+
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::config::{AuthConfig, ConfigError, JwtAlgorithm, JwtConfig};
+use crate::response::api_error;
+use crate::tls::{ClientCertificate, ConnectionInfo};
+use crate::SharedState;
+
+/// Header carrying a static API key
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Access level granted to a caller, ordered from least to most privileged
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// Read-only access to inventory and job status
+    Reader,
+    /// Day-to-day operations: power state, snapshots, disks, group moves
+    Operator,
+    /// Destructive and host-level changes: deletes, switches, GPU/DDA, node and CSV maintenance
+    Admin,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Reader => "reader",
+            Role::Operator => "operator",
+            Role::Admin => "admin",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "reader" => Some(Role::Reader),
+            "operator" => Some(Role::Operator),
+            "admin" => Some(Role::Admin),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// How a principal was authenticated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthMethod {
+    ApiKey,
+    Jwt,
+    ClientCertificate,
+    /// Authentication is disabled
+    Anonymous,
+}
+
+impl AuthMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuthMethod::ApiKey => "api_key",
+            AuthMethod::Jwt => "jwt",
+            AuthMethod::ClientCertificate => "client_certificate",
+            AuthMethod::Anonymous => "anonymous",
+        }
+    }
+}
+
+/// Authenticated caller, available to handlers as `Extension<Principal>`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Principal {
+    /// API key name, JWT `sub` claim or certificate subject
+    pub name: String,
+    pub role: Role,
+    pub method: AuthMethod,
+}
+
+impl Principal {
+    fn anonymous() -> Self {
+        Self {
+            name: "anonymous".to_string(),
+            role: Role::Admin,
+            method: AuthMethod::Anonymous,
+        }
+    }
+}
+
+/// Authentication failures
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuthError {
+    /// The `X-API-Key` header does not match any configured key
+    InvalidApiKey,
+    /// The bearer token could not be verified
+    InvalidToken(String),
+    /// A bearer token was presented but JWT authentication is not configured
+    JwtNotConfigured,
+    /// The `Authorization` header is not a bearer token
+    UnsupportedScheme,
+}
+
+impl fmt::Display for AuthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuthError::InvalidApiKey => write!(f, "Invalid API key"),
+            AuthError::InvalidToken(e) => write!(f, "Invalid bearer token: {}", e),
+            AuthError::JwtNotConfigured => write!(f, "Bearer tokens are not accepted"),
+            AuthError::UnsupportedScheme => {
+                write!(f, "Unsupported authorization scheme, expected Bearer")
+            }
+        }
+    }
+}
+
+impl std::error::Error for AuthError {}
+
+struct ApiKey {
+    name: String,
+    key: Vec<u8>,
+    role: Role,
+}
+
+struct JwtVerifier {
+    key: DecodingKey,
+    validation: Validation,
+    role_claim: String,
+}
+
+/// Verifies credentials against the `[auth]` configuration
+pub struct Authenticator {
+    enabled: bool,
+    api_keys: Vec<ApiKey>,
+    jwt: Option<JwtVerifier>,
+    client_certs: Vec<(String, Role)>,
+}
+
+impl Authenticator {
+    /// Authenticator that treats every caller as an anonymous admin
+    pub fn disabled() -> Self {
+        Self {
+            enabled: false,
+            api_keys: Vec::new(),
+            jwt: None,
+            client_certs: Vec::new(),
+        }
+    }
+
+    /// Build an authenticator, loading the RS256 public key if configured
+    pub fn from_config(config: &AuthConfig) -> Result<Self, ConfigError> {
+        if !config.enabled {
+            return Ok(Self::disabled());
+        }
+        if config.api_keys.is_empty() && config.jwt.is_none() && config.client_certs.is_empty() {
+            return Err(ConfigError::Invalid(
+                "auth is enabled but no api_keys, jwt or client_certs are configured".to_string(),
+            ));
+        }
+
+        let mut api_keys = Vec::with_capacity(config.api_keys.len());
+        for entry in &config.api_keys {
+            if entry.key.is_empty() {
+                return Err(ConfigError::Invalid(format!(
+                    "auth.api_keys '{}' has an empty key",
+                    entry.name
+                )));
+            }
+            api_keys.push(ApiKey {
+                name: entry.name.clone(),
+                key: entry.key.as_bytes().to_vec(),
+                role: entry.role,
+            });
+        }
+
+        let jwt = config.jwt.as_ref().map(JwtVerifier::new).transpose()?;
+
+        let client_certs = config
+            .client_certs
+            .iter()
+            .map(|entry| (normalize_subject(&entry.subject), entry.role))
+            .collect();
+
+        Ok(Self {
+            enabled: true,
+            api_keys,
+            jwt,
+            client_certs,
+        })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Resolve the caller from request headers and the TLS client certificate
+    ///
+    /// Headers take precedence over the certificate. Returns `Ok(None)` when
+    /// no usable credentials were presented.
+    pub fn authenticate(
+        &self,
+        headers: &HeaderMap,
+        client_cert: Option<&ClientCertificate>,
+    ) -> Result<Option<Principal>, AuthError> {
+        if !self.enabled {
+            return Ok(Some(Principal::anonymous()));
+        }
+
+        if let Some(value) = headers.get(API_KEY_HEADER) {
+            return self
+                .verify_api_key(value.as_bytes())
+                .map(Some)
+                .ok_or(AuthError::InvalidApiKey);
+        }
+
+        if let Some(value) = headers.get(header::AUTHORIZATION) {
+            let value = value.to_str().map_err(|_| AuthError::UnsupportedScheme)?;
+            let token = value
+                .strip_prefix("Bearer ")
+                .or_else(|| value.strip_prefix("bearer "))
+                .ok_or(AuthError::UnsupportedScheme)?;
+            let jwt = self.jwt.as_ref().ok_or(AuthError::JwtNotConfigured)?;
+            return jwt.verify(token.trim()).map(Some);
+        }
+
+        Ok(client_cert.and_then(|cert| self.verify_client_cert(cert)))
+    }
+
+    fn verify_client_cert(&self, cert: &ClientCertificate) -> Option<Principal> {
+        let subject = normalize_subject(&cert.subject);
+        self.client_certs
+            .iter()
+            .find(|(allowed, _)| *allowed == subject)
+            .map(|(_, role)| Principal {
+                name: cert.subject.clone(),
+                role: *role,
+                method: AuthMethod::ClientCertificate,
+            })
+    }
+
+    fn verify_api_key(&self, presented: &[u8]) -> Option<Principal> {
+        // Check every key so timing does not reveal which one matched
+        let mut matched = None;
+        for key in &self.api_keys {
+            if constant_time_eq(&key.key, presented) && matched.is_none() {
+                matched = Some(Principal {
+                    name: key.name.clone(),
+                    role: key.role,
+                    method: AuthMethod::ApiKey,
+                });
+            }
+        }
+        matched
+    }
+
+    fn challenge(&self) -> Option<HeaderValue> {
+        if !self.enabled {
+            return None;
+        }
+        let value = if self.jwt.is_some() {
+            "Bearer realm=\"nodeagent\""
+        } else {
+            "ApiKey realm=\"nodeagent\""
+        };
+        Some(HeaderValue::from_static(value))
+    }
+}
+
+impl JwtVerifier {
+    fn new(config: &JwtConfig) -> Result<Self, ConfigError> {
+        let (algorithm, key) = match config.algorithm {
+            JwtAlgorithm::HS256 => {
+                let secret = config.secret.as_deref().unwrap_or_default();
+                if secret.is_empty() {
+                    return Err(ConfigError::Invalid(
+                        "auth.jwt.secret is required for HS256".to_string(),
+                    ));
+                }
+                (
+                    Algorithm::HS256,
+                    DecodingKey::from_secret(secret.as_bytes()),
+                )
+            }
+            JwtAlgorithm::RS256 => {
+                let path = config.public_key_path.as_deref().ok_or_else(|| {
+                    ConfigError::Invalid("auth.jwt.public_key_path is required for RS256".into())
+                })?;
+                let pem = std::fs::read(path)
+                    .map_err(|e| ConfigError::ReadError(path.to_string(), e.to_string()))?;
+                let key = DecodingKey::from_rsa_pem(&pem)
+                    .map_err(|e| ConfigError::ParseError(path.to_string(), e.to_string()))?;
+                (Algorithm::RS256, key)
+            }
+        };
+
+        let mut validation = Validation::new(algorithm);
+        validation.leeway = config.leeway_secs;
+        if let Some(issuer) = &config.issuer {
+            validation.set_issuer(&[issuer]);
+        }
+        match &config.audience {
+            Some(audience) => validation.set_audience(&[audience]),
+            None => validation.validate_aud = false,
+        }
+
+        Ok(Self {
+            key,
+            validation,
+            role_claim: config.role_claim.clone(),
+        })
+    }
+
+    fn verify(&self, token: &str) -> Result<Principal, AuthError> {
+        let data = jsonwebtoken::decode::<serde_json::Map<String, Value>>(
+            token,
+            &self.key,
+            &self.validation,
+        )
+        .map_err(|e| AuthError::InvalidToken(e.to_string()))?;
+        let claims = data.claims;
+
+        let name = claims
+            .get("sub")
+            .and_then(Value::as_str)
+            .ok_or_else(|| AuthError::InvalidToken("missing 'sub' claim".to_string()))?
+            .to_string();
+
+        // The role claim may be a single role or a list; the highest one wins
+        let role = match claims.get(&self.role_claim) {
+            Some(Value::String(role)) => Role::parse(role),
+            Some(Value::Array(roles)) => roles
+                .iter()
+                .filter_map(Value::as_str)
+                .filter_map(Role::parse)
+                .max(),
+            _ => None,
+        }
+        .ok_or_else(|| {
+            AuthError::InvalidToken(format!("missing or unknown '{}' claim", self.role_claim))
+        })?;
+
+        Ok(Principal {
+            name,
+            role,
+            method: AuthMethod::Jwt,
+        })
+    }
+}
+
+/// Compare distinguished names regardless of spacing around separators
+fn normalize_subject(subject: &str) -> String {
+    subject
+        .split(',')
+        .map(str::trim)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn unauthorized(auth: &Authenticator, message: &str) -> Response {
+    let mut response = api_error(StatusCode::UNAUTHORIZED, message).into_response();
+    if let Some(challenge) = auth.challenge() {
+        response
+            .headers_mut()
+            .insert(header::WWW_AUTHENTICATE, challenge);
+    }
+    response
+}
+
+// =============================================================================
+// Middleware
+// =============================================================================
+
+/// Resolve the caller and store the [`Principal`] in request extensions
+///
+/// The verified TLS client certificate, if any, is stored as a
+/// [`ClientCertificate`] extension as well. Requests without credentials
+/// continue unauthenticated so public routes stay reachable; protected
+/// routes reject them in [`require_role`].
+pub async fn authenticate(
+    State(state): State<SharedState>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let client_cert = request
+        .extensions()
+        .get::<ConnectInfo<ConnectionInfo>>()
+        .and_then(|info| info.0.client_certificate.clone());
+
+    match state
+        .auth
+        .authenticate(request.headers(), client_cert.as_ref())
+    {
+        Ok(Some(principal)) => {
+            request.extensions_mut().insert(principal);
+        }
+        Ok(None) => {}
+        Err(e) => {
+            tracing::warn!("Rejected credentials for {}: {}", request.uri().path(), e);
+            return unauthorized(&state.auth, &e.to_string());
+        }
+    }
+    if let Some(cert) = client_cert {
+        request.extensions_mut().insert(cert);
+    }
+    next.run(request).await
+}
+
+/// Reject callers whose role is below the route's minimum role
+pub async fn require_role(State(required): State<Role>, request: Request, next: Next) -> Response {
+    match authorize(request.extensions().get::<Principal>(), required) {
+        Ok(()) => next.run(request).await,
+        Err(response) => response,
+    }
+}
+
+/// The `401` or `403` response for a caller below `required`, if it is
+pub fn authorize(principal: Option<&Principal>, required: Role) -> Result<(), Response> {
+    match principal {
+        None => {
+            let mut response =
+                api_error(StatusCode::UNAUTHORIZED, "Authentication required").into_response();
+            response.headers_mut().insert(
+                header::WWW_AUTHENTICATE,
+                HeaderValue::from_static("Bearer realm=\"nodeagent\""),
+            );
+            Err(response)
+        }
+        Some(principal) if principal.role < required => Err(api_error(
+            StatusCode::FORBIDDEN,
+            &format!(
+                "Role '{}' is required, '{}' has role '{}'",
+                required, principal.name, principal.role
+            ),
+        )
+        .into_response()),
+        Some(_) => Ok(()),
+    }
+}
+
+// =============================================================================
+// Tests
+// =============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ApiKeyConfig, ClientCertConfig};
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use serde_json::json;
+
+    const SECRET: &str = "test-secret";
+
+    fn config() -> AuthConfig {
+        AuthConfig {
+            enabled: true,
+            api_keys: vec![ApiKeyConfig {
+                name: "monitoring".to_string(),
+                key: "reader-key".to_string(),
+                role: Role::Reader,
+            }],
+            jwt: Some(JwtConfig {
+                algorithm: JwtAlgorithm::HS256,
+                secret: Some(SECRET.to_string()),
+                issuer: Some("https://issuer.example".to_string()),
+                ..JwtConfig::default()
+            }),
+            client_certs: vec![ClientCertConfig {
+                subject: "CN=ops-automation, O=Contoso".to_string(),
+                role: Role::Operator,
+            }],
+        }
+    }
+
+    fn token(claims: Value) -> String {
+        encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret(SECRET.as_bytes()),
+        )
+        .unwrap()
+    }
+
+    fn headers(name: header::HeaderName, value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(name, value.parse().unwrap());
+        headers
+    }
+
+    fn exp() -> i64 {
+        chrono::Utc::now().timestamp() + 300
+    }
+
+    #[test]
+    fn test_role_ordering() {
+        assert!(Role::Reader < Role::Operator);
+        assert!(Role::Operator < Role::Admin);
+    }
+
+    #[test]
+    fn test_disabled_is_anonymous_admin() {
+        let auth = Authenticator::disabled();
+        let principal = auth.authenticate(&HeaderMap::new(), None).unwrap().unwrap();
+        assert_eq!(principal.role, Role::Admin);
+        assert_eq!(principal.method, AuthMethod::Anonymous);
+    }
+
+    #[test]
+    fn test_api_key() {
+        let auth = Authenticator::from_config(&config()).unwrap();
+        let principal = auth
+            .authenticate(
+                &headers(
+                    header::HeaderName::from_static(API_KEY_HEADER),
+                    "reader-key",
+                ),
+                None,
+            )
+            .unwrap()
+            .unwrap();
+        assert_eq!(principal.name, "monitoring");
+        assert_eq!(principal.role, Role::Reader);
+
+        let err = auth
+            .authenticate(
+                &headers(header::HeaderName::from_static(API_KEY_HEADER), "wrong"),
+                None,
+            )
+            .unwrap_err();
+        assert_eq!(err, AuthError::InvalidApiKey);
+
+        assert!(auth
+            .authenticate(&HeaderMap::new(), None)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_jwt_role_claim() {
+        let auth = Authenticator::from_config(&config()).unwrap();
+        let bearer = format!(
+            "Bearer {}",
+            token(json!({
+                "sub": "alice",
+                "role": ["reader", "operator"],
+                "iss": "https://issuer.example",
+                "exp": exp(),
+            }))
+        );
+        let principal = auth
+            .authenticate(&headers(header::AUTHORIZATION, &bearer), None)
+            .unwrap()
+            .unwrap();
+        assert_eq!(principal.name, "alice");
+        assert_eq!(principal.role, Role::Operator);
+        assert_eq!(principal.method, AuthMethod::Jwt);
+    }
+
+    #[test]
+    fn test_jwt_rejects_wrong_issuer_and_expired() {
+        let auth = Authenticator::from_config(&config()).unwrap();
+
+        let bearer = format!(
+            "Bearer {}",
+            token(json!({ "sub": "bob", "role": "admin", "iss": "other", "exp": exp() }))
+        );
+        assert!(matches!(
+            auth.authenticate(&headers(header::AUTHORIZATION, &bearer), None),
+            Err(AuthError::InvalidToken(_))
+        ));
+
+        let bearer = format!(
+            "Bearer {}",
+            token(json!({
+                "sub": "bob",
+                "role": "admin",
+                "iss": "https://issuer.example",
+                "exp": chrono::Utc::now().timestamp() - 3600,
+            }))
+        );
+        assert!(matches!(
+            auth.authenticate(&headers(header::AUTHORIZATION, &bearer), None),
+            Err(AuthError::InvalidToken(_))
+        ));
+
+        let err = auth
+            .authenticate(&headers(header::AUTHORIZATION, "Basic Zm9vOmJhcg=="), None)
+            .unwrap_err();
+        assert_eq!(err, AuthError::UnsupportedScheme);
+    }
+
+    #[test]
+    fn test_client_certificate_subject() {
+        let auth = Authenticator::from_config(&config()).unwrap();
+        let cert = ClientCertificate {
+            subject: "CN=ops-automation,O=Contoso".to_string(),
+            issuer: "CN=Test CA".to_string(),
+            serial: "01".to_string(),
+        };
+        let principal = auth
+            .authenticate(&HeaderMap::new(), Some(&cert))
+            .unwrap()
+            .unwrap();
+        assert_eq!(principal.role, Role::Operator);
+        assert_eq!(principal.method, AuthMethod::ClientCertificate);
+
+        // An API key overrides the certificate
+        let principal = auth
+            .authenticate(
+                &headers(
+                    header::HeaderName::from_static(API_KEY_HEADER),
+                    "reader-key",
+                ),
+                Some(&cert),
+            )
+            .unwrap()
+            .unwrap();
+        assert_eq!(principal.role, Role::Reader);
+
+        let unknown = ClientCertificate {
+            subject: "CN=someone-else".to_string(),
+            ..cert
+        };
+        assert!(auth
+            .authenticate(&HeaderMap::new(), Some(&unknown))
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        let mut config = config();
+        config.api_keys.clear();
+        config.jwt = None;
+        config.client_certs.clear();
+        assert!(Authenticator::from_config(&config).is_err());
+
+        let mut config = self::config();
+        config.jwt = Some(JwtConfig {
+            algorithm: JwtAlgorithm::RS256,
+            public_key_path: Some("does-not-exist.pem".to_string()),
+            ..JwtConfig::default()
+        });
+        assert!(matches!(
+            Authenticator::from_config(&config),
+            Err(ConfigError::ReadError(_, _))
+        ));
+    }
+}
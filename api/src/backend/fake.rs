@@ -0,0 +1,2064 @@
+//! Stateful in-memory backends for development and tests
+//!
+//! [`FakeHyperV`] simulates a single Hyper-V host with VMs, switches, VHDs,
+//! snapshots, GPUs and DDA devices. [`FakeCluster`] simulates a two-node
+//! failover cluster with groups, resources and CSVs. State transitions and
+//! failures follow Hyper-V and cluster semantics closely enough to drive the
+//! REST surface end to end, and errors use the same `hv`/`clus` variants the
+//! native backends produce.
+
+use std::collections::BTreeMap;
+use std::sync::{Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use clus::ClusError;
+use hv::HvError;
+use windows_hyperv::{
+    AutomaticStartAction, AutomaticStopAction, CheckpointType, Generation, HostCapabilities,
+    NetworkAdapterSettings, ProcessorSettings, SecuritySettings, VmSettings, VmVersionInfo,
+};
+
+use super::{
+    processor_dto, security_dto, BackendResult, ClusterBackend, HypervBackend, ProgressFn,
+    CAPABILITY_PROPERTIES,
+};
+use crate::dto::*;
+use crate::validation::{
+    checkpoint_type, port_mirroring, processor_settings, security_settings, start_action,
+    stop_action,
+};
+
+// =============================================================================
+// Hyper-V
+// =============================================================================
+
+/// VM power states, named after `hv::VmState`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FakeVmState {
+    Off,
+    Running,
+    Paused,
+    Saved,
+}
+
+#[derive(Debug, Clone)]
+struct FakeVm {
+    id: String,
+    name: String,
+    state: FakeVmState,
+    generation: u32,
+    cpu_count: u32,
+    memory_mb: u64,
+    dynamic_memory: bool,
+    dynamic_memory_min_mb: u64,
+    dynamic_memory_max_mb: u64,
+    memory_buffer_percentage: u32,
+    automatic_start_action: AutomaticStartAction,
+    automatic_start_delay_secs: u32,
+    automatic_stop_action: AutomaticStopAction,
+    checkpoint_type: CheckpointType,
+    notes: String,
+    version: String,
+    /// Processor settings apart from the count, which is `cpu_count`
+    processor: ProcessorSettings,
+    security: SecuritySettings,
+    adapters: Vec<VmNetworkAdapterDto>,
+    disks: Vec<DiskDto>,
+    dvd_drives: Vec<DiskDto>,
+    boot_order: Vec<String>,
+    snapshots: Vec<SnapshotDto>,
+    /// Id of the checkpoint "Now" was taken from
+    current_snapshot: Option<String>,
+    gpu_adapters: Vec<GpuAdapterDto>,
+    started_at: Option<Instant>,
+}
+
+impl FakeVm {
+    fn to_dto(&self) -> VmDto {
+        VmDto {
+            id: self.id.clone(),
+            name: self.name.clone(),
+            state: format!("{:?}", self.state),
+            cpu_count: Some(self.cpu_count),
+            memory_mb: Some(self.memory_mb),
+            uptime_seconds: self.started_at.map(|t| t.elapsed().as_secs()),
+            generation: Some(self.generation),
+            dynamic_memory: Some(self.dynamic_memory),
+            dynamic_memory_min_mb: Some(self.dynamic_memory_min_mb),
+            dynamic_memory_max_mb: Some(self.dynamic_memory_max_mb),
+            memory_buffer_percentage: Some(self.memory_buffer_percentage),
+            automatic_start_action: Some(format!("{:?}", self.automatic_start_action)),
+            automatic_start_delay_secs: Some(self.automatic_start_delay_secs),
+            automatic_stop_action: Some(format!("{:?}", self.automatic_stop_action)),
+            checkpoint_type: Some(format!("{:?}", self.checkpoint_type)),
+            notes: Some(self.notes.clone()),
+            version: Some(self.version.clone()),
+        }
+    }
+
+    /// Check the VM's settings with `windows_hyperv`'s rules
+    fn validate_settings(&self) -> BackendResult<()> {
+        let generation = match self.generation {
+            1 => Generation::Gen1,
+            _ => Generation::Gen2,
+        };
+        VmSettings::builder()
+            .name(&self.name)
+            .generation(generation)
+            .memory_mb(self.memory_mb)
+            .processor_count(self.cpu_count)
+            .dynamic_memory(self.dynamic_memory)
+            .dynamic_memory_min_mb(self.dynamic_memory_min_mb)
+            .dynamic_memory_max_mb(self.dynamic_memory_max_mb)
+            .memory_buffer_percentage(self.memory_buffer_percentage)
+            .build()?;
+        Ok(())
+    }
+
+    fn processor(&self) -> ProcessorSettings {
+        ProcessorSettings {
+            count: self.cpu_count,
+            ..self.processor.clone()
+        }
+    }
+
+    fn controller_type(&self) -> &'static str {
+        if self.generation == 1 {
+            "IDE"
+        } else {
+            "SCSI"
+        }
+    }
+
+    fn require_state(&self, allowed: &[FakeVmState], action: &str) -> BackendResult<()> {
+        if allowed.contains(&self.state) {
+            Ok(())
+        } else {
+            Err(HvError::InvalidState(format!(
+                "cannot {} VM '{}' while it is {:?}",
+                action, self.name, self.state
+            ))
+            .into())
+        }
+    }
+
+    fn require_off(&self, action: &str) -> BackendResult<()> {
+        self.require_state(&[FakeVmState::Off], action)
+    }
+
+    /// Gen1 VMs only take adapter changes while off
+    fn require_hot_plug(&self, action: &str) -> BackendResult<()> {
+        match self.generation {
+            1 => self.require_off(action),
+            _ => Ok(()),
+        }
+    }
+
+    /// Find an adapter by id, or by name when that is unambiguous
+    fn adapter_index(&self, adapter: &str) -> BackendResult<usize> {
+        if let Some(index) = self.adapters.iter().position(|a| a.id == adapter) {
+            return Ok(index);
+        }
+        let named: Vec<usize> = (0..self.adapters.len())
+            .filter(|i| self.adapters[*i].name.eq_ignore_ascii_case(adapter))
+            .collect();
+        match named[..] {
+            [index] => Ok(index),
+            [] => Err(windows_hyperv::Error::NetworkAdapterNotFound {
+                vm_name: self.name.clone(),
+                adapter_id: adapter.to_string(),
+            }
+            .into()),
+            _ => Err(HvError::InvalidParameter(format!(
+                "VM '{}' has {} network adapters named '{}'; use the adapter id",
+                self.name,
+                named.len(),
+                adapter
+            ))
+            .into()),
+        }
+    }
+
+    /// Find a checkpoint by id, or by name when that is unambiguous
+    fn snapshot_index(&self, snapshot: &str) -> BackendResult<usize> {
+        if let Some(index) = self.snapshots.iter().position(|s| s.id == snapshot) {
+            return Ok(index);
+        }
+        let named: Vec<usize> = (0..self.snapshots.len())
+            .filter(|i| self.snapshots[*i].name == snapshot)
+            .collect();
+        match named[..] {
+            [index] => Ok(index),
+            [] => Err(HvError::SnapshotNotFound(snapshot.to_string()).into()),
+            _ => Err(HvError::InvalidParameter(format!(
+                "VM '{}' has {} checkpoints named '{}'; use the checkpoint id",
+                self.name,
+                named.len(),
+                snapshot
+            ))
+            .into()),
+        }
+    }
+
+    fn set_state(&mut self, state: FakeVmState) {
+        match state {
+            FakeVmState::Running => {
+                if self.state != FakeVmState::Paused {
+                    self.started_at = Some(Instant::now());
+                }
+            }
+            FakeVmState::Off | FakeVmState::Saved => self.started_at = None,
+            FakeVmState::Paused => {}
+        }
+        self.state = state;
+    }
+}
+
+#[derive(Debug, Clone)]
+struct FakeVhd {
+    info: VhdDto,
+    mounted: bool,
+    drive_letter: Option<String>,
+}
+
+#[derive(Debug)]
+struct FakeHost {
+    host: HostInfoDto,
+    adapters: Vec<NetworkAdapterDto>,
+    vms: BTreeMap<String, FakeVm>,
+    switches: BTreeMap<String, SwitchDto>,
+    vhds: BTreeMap<String, FakeVhd>,
+    gpus: Vec<GpuDto>,
+    devices: Vec<AssignableDeviceDto>,
+    next_id: u64,
+}
+
+impl FakeHost {
+    fn next_id(&mut self) -> String {
+        self.next_id += 1;
+        format!("00000000-0000-4000-8000-{:012x}", self.next_id)
+    }
+
+    fn vm(&self, name: &str) -> BackendResult<&FakeVm> {
+        self.vms
+            .get(name)
+            .ok_or_else(|| HvError::VmNotFound(name.to_string()).into())
+    }
+
+    fn vm_mut(&mut self, name: &str) -> BackendResult<&mut FakeVm> {
+        self.vms
+            .get_mut(name)
+            .ok_or_else(|| HvError::VmNotFound(name.to_string()).into())
+    }
+
+    /// An adapter with Hyper-V's defaults and a dynamic MAC from the
+    /// Microsoft range when `mac_address` is not given
+    fn new_adapter(
+        &mut self,
+        name: Option<String>,
+        switch_name: Option<String>,
+        mac_address: Option<String>,
+    ) -> VmNetworkAdapterDto {
+        let id = self.next_id();
+        let dynamic_mac = mac_address.is_none();
+        let mac_address = mac_address.unwrap_or_else(|| format!("00155D{:06X}", self.next_id));
+        VmNetworkAdapterDto {
+            id,
+            name: name.unwrap_or_else(|| "Network Adapter".to_string()),
+            switch_name,
+            mac_address: Some(mac_address),
+            dynamic_mac,
+            vlan_id: None,
+            mac_spoofing: Some(false),
+            dhcp_guard: Some(false),
+            router_guard: Some(false),
+            port_mirroring: Some("None".to_string()),
+            bandwidth: None,
+        }
+    }
+
+    fn require_switch(&self, switch_name: Option<&str>) -> BackendResult<()> {
+        match switch_name {
+            Some(switch) if !self.switches.contains_key(switch) => {
+                Err(HvError::SwitchNotFound(switch.to_string()).into())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn vhd(&self, path: &str) -> BackendResult<&FakeVhd> {
+        self.vhds
+            .get(&vhd_key(path))
+            .ok_or_else(|| HvError::VhdNotFound(path.to_string()).into())
+    }
+
+    fn vhd_mut(&mut self, path: &str) -> BackendResult<&mut FakeVhd> {
+        self.vhds
+            .get_mut(&vhd_key(path))
+            .ok_or_else(|| HvError::VhdNotFound(path.to_string()).into())
+    }
+
+    fn device_index(&self, location_path: &str) -> BackendResult<usize> {
+        self.devices
+            .iter()
+            .position(|d| d.location_path.eq_ignore_ascii_case(location_path))
+            .ok_or_else(|| {
+                HvError::InvalidParameter(format!("no device at location '{}'", location_path))
+                    .into()
+            })
+    }
+
+    fn insert_vhd(
+        &mut self,
+        path: &str,
+        vhd_type: &str,
+        max_size_bytes: u64,
+        parent_path: Option<String>,
+    ) -> BackendResult<VhdDto> {
+        if path.trim().is_empty() {
+            return Err(HvError::InvalidParameter("VHD path is required".to_string()).into());
+        }
+        if self.vhds.contains_key(&vhd_key(path)) {
+            return Err(HvError::InvalidParameter(format!("VHD '{}' already exists", path)).into());
+        }
+        if max_size_bytes == 0 {
+            return Err(HvError::InvalidParameter(
+                "VHD size must be greater than zero".to_string(),
+            )
+            .into());
+        }
+        let file_size_bytes = match vhd_type {
+            "Fixed" => max_size_bytes,
+            _ => EMPTY_DYNAMIC_VHD_BYTES,
+        };
+        let info = VhdDto {
+            path: path.to_string(),
+            format: vhd_format(path).to_string(),
+            vhd_type: vhd_type.to_string(),
+            max_size_bytes,
+            file_size_bytes,
+            parent_path,
+            is_attached: false,
+        };
+        self.vhds.insert(
+            vhd_key(path),
+            FakeVhd {
+                info: info.clone(),
+                mounted: false,
+                drive_letter: None,
+            },
+        );
+        Ok(info)
+    }
+
+    /// Marks a VHD as attached or detached to keep `is_attached` consistent
+    fn set_vhd_attached(&mut self, path: &str, attached: bool) {
+        if let Some(vhd) = self.vhds.get_mut(&vhd_key(path)) {
+            vhd.info.is_attached = attached || vhd.mounted;
+        }
+    }
+
+    fn vhd_in_use(&self, path: &str) -> Option<&str> {
+        self.vms
+            .values()
+            .find(|vm| {
+                vm.disks.iter().any(|d| {
+                    d.path
+                        .as_deref()
+                        .is_some_and(|p| vhd_key(p) == vhd_key(path))
+                })
+            })
+            .map(|vm| vm.name.as_str())
+    }
+}
+
+/// Report progress in steps while sleeping for `delay` in total
+fn simulate_progress(delay: Duration, progress: ProgressFn<'_>, status: &str) {
+    const STEPS: u32 = 4;
+    for step in 0..STEPS {
+        progress(step * 100 / STEPS, status);
+        if !delay.is_zero() {
+            thread::sleep(delay / STEPS);
+        }
+    }
+}
+
+/// Size reported for a freshly created dynamic or differencing VHD
+const EMPTY_DYNAMIC_VHD_BYTES: u64 = 4 * 1024 * 1024;
+
+fn vhd_key(path: &str) -> String {
+    path.to_lowercase()
+}
+
+fn vhd_format(path: &str) -> &'static str {
+    if path.to_lowercase().ends_with(".vhd") {
+        "Vhd"
+    } else {
+        "Vhdx"
+    }
+}
+
+/// Synthetic network adapters Hyper-V allows per VM
+const MAX_NETWORK_ADAPTERS: usize = 8;
+
+/// VM configuration versions the fake host runs, oldest first
+const VM_VERSIONS: [&str; 4] = ["8.0", "9.0", "9.1", DEFAULT_VM_VERSION];
+
+/// Configuration version new VMs get
+const DEFAULT_VM_VERSION: &str = "10.0";
+
+/// Apply the given adapter settings, checked with `windows_hyperv`'s rules
+fn apply_adapter_settings(
+    mut adapter: VmNetworkAdapterDto,
+    req: &UpdateVmNetworkAdapterRequest,
+) -> BackendResult<VmNetworkAdapterDto> {
+    if let Some(mac) = &req.mac_address {
+        adapter.mac_address = Some(mac.clone());
+        adapter.dynamic_mac = false;
+    }
+    if let Some(dynamic) = req.dynamic_mac {
+        adapter.dynamic_mac = dynamic;
+    }
+    if let Some(vlan) = req.vlan_id {
+        adapter.vlan_id = (vlan != 0).then_some(vlan);
+    }
+    if let Some(enabled) = req.mac_spoofing {
+        adapter.mac_spoofing = Some(enabled);
+    }
+    if let Some(enabled) = req.dhcp_guard {
+        adapter.dhcp_guard = Some(enabled);
+    }
+    if let Some(enabled) = req.router_guard {
+        adapter.router_guard = Some(enabled);
+    }
+    if let Some(mode) = &req.port_mirroring {
+        let mode = port_mirroring(mode).ok_or_else(|| {
+            HvError::InvalidParameter(format!("invalid port mirroring '{}'", mode))
+        })?;
+        adapter.port_mirroring = Some(format!("{:?}", mode));
+    }
+    if let Some(bandwidth) = &req.bandwidth {
+        adapter.bandwidth = Some(bandwidth.clone()).filter(|b| *b != BandwidthDto::default());
+    }
+
+    let settings = NetworkAdapterSettings {
+        name: Some(adapter.name.clone()),
+        switch_name: adapter.switch_name.clone(),
+        mac_address: adapter.mac_address.clone().filter(|_| !adapter.dynamic_mac),
+        dynamic_mac: adapter.dynamic_mac,
+        vlan_id: adapter.vlan_id,
+        mac_spoofing: adapter.mac_spoofing.unwrap_or(false),
+        dhcp_guard: adapter.dhcp_guard.unwrap_or(false),
+        router_guard: adapter.router_guard.unwrap_or(false),
+        ..NetworkAdapterSettings::default()
+    };
+    settings.validate()?;
+    Ok(adapter)
+}
+
+fn now_rfc3339() -> String {
+    chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+}
+
+/// In-memory Hyper-V host
+#[derive(Debug)]
+pub struct FakeHyperV {
+    state: Mutex<FakeHost>,
+    operation_delay: Duration,
+}
+
+impl Default for FakeHyperV {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FakeHyperV {
+    /// Create a host with no VMs, one physical adapter, one partitionable GPU
+    /// and two DDA-capable PCI devices
+    pub fn new() -> Self {
+        let host = FakeHost {
+            host: HostInfoDto {
+                computer_name: "FAKE-HV01".to_string(),
+                logical_processor_count: 16,
+                memory_capacity_bytes: 64 * 1024 * 1024 * 1024,
+                vm_path: r"C:\ProgramData\Microsoft\Windows\Hyper-V".to_string(),
+                vhd_path: r"C:\ProgramData\Microsoft\Windows\Virtual Hard Disks".to_string(),
+            },
+            adapters: vec![NetworkAdapterDto {
+                name: "Ethernet".to_string(),
+                description: "Fake Ethernet Adapter".to_string(),
+                mac_address: "00-15-5D-00-00-01".to_string(),
+                link_speed: "10 Gbps".to_string(),
+            }],
+            vms: BTreeMap::new(),
+            switches: BTreeMap::new(),
+            vhds: BTreeMap::new(),
+            gpus: vec![GpuDto {
+                device_instance_id: r"PCI\VEN_10DE&DEV_2236\FAKE0001".to_string(),
+                name: "Fake GPU A10".to_string(),
+                description: "Fake partitionable GPU".to_string(),
+                manufacturer: "NVIDIA".to_string(),
+                supports_partitioning: true,
+            }],
+            devices: vec![
+                AssignableDeviceDto {
+                    instance_id: r"PCI\VEN_10DE&DEV_2236\FAKE0001".to_string(),
+                    name: "Fake GPU A10".to_string(),
+                    location_path: "PCIROOT(0)#PCI(0300)#PCI(0000)".to_string(),
+                    is_assigned: false,
+                    assigned_vm: None,
+                    is_dismounted: false,
+                    status: "OK".to_string(),
+                },
+                AssignableDeviceDto {
+                    instance_id: r"PCI\VEN_144D&DEV_A808\FAKE0002".to_string(),
+                    name: "Fake NVMe Controller".to_string(),
+                    location_path: "PCIROOT(0)#PCI(0100)#PCI(0000)".to_string(),
+                    is_assigned: false,
+                    assigned_vm: None,
+                    is_dismounted: false,
+                    status: "OK".to_string(),
+                },
+            ],
+            next_id: 0,
+        };
+        Self {
+            state: Mutex::new(host),
+            operation_delay: Duration::ZERO,
+        }
+    }
+
+    /// Make long-running operations (export, VHD creation and compaction,
+    /// image creation, snapshot apply) take `delay`, reporting progress
+    /// along the way
+    pub fn with_operation_delay(mut self, delay: Duration) -> Self {
+        self.operation_delay = delay;
+        self
+    }
+
+    fn host(&self) -> MutexGuard<'_, FakeHost> {
+        self.state.lock().expect("fake Hyper-V state poisoned")
+    }
+
+    fn transition(
+        &self,
+        name: &str,
+        allowed: &[FakeVmState],
+        action: &str,
+        to: FakeVmState,
+    ) -> BackendResult<()> {
+        let mut host = self.host();
+        let vm = host.vm_mut(name)?;
+        vm.require_state(allowed, action)?;
+        vm.set_state(to);
+        Ok(())
+    }
+}
+
+impl HypervBackend for FakeHyperV {
+    fn host_info(&self) -> BackendResult<HostInfoDto> {
+        Ok(self.host().host.clone())
+    }
+
+    fn host_capabilities(&self) -> BackendResult<HostCapabilitiesDto> {
+        let limits = HostCapabilities::default();
+        Ok(HostCapabilitiesDto {
+            supported_vm_versions: VM_VERSIONS.iter().map(|v| v.to_string()).collect(),
+            default_vm_version: Some(DEFAULT_VM_VERSION.to_string()),
+            nested_virtualization_supported: true,
+            dda_supported: true,
+            gpu_p_supported: self.host().gpus.iter().any(|g| g.supports_partitioning),
+            tpm_supported: true,
+            live_migration_supported: true,
+            max_processors_per_vm: limits.max_processors_per_vm,
+            max_memory_per_vm_mb: limits.max_memory_per_vm_mb,
+            properties: CAPABILITY_PROPERTIES
+                .iter()
+                .map(|(class_name, property)| PropertySupportDto {
+                    class_name: class_name.to_string(),
+                    property: property.to_string(),
+                    support: "Supported".to_string(),
+                    reason: None,
+                })
+                .collect(),
+        })
+    }
+
+    fn list_network_adapters(&self) -> BackendResult<Vec<NetworkAdapterDto>> {
+        Ok(self.host().adapters.clone())
+    }
+
+    fn list_vms(&self) -> BackendResult<Vec<VmDto>> {
+        Ok(self.host().vms.values().map(FakeVm::to_dto).collect())
+    }
+
+    fn get_vm(&self, name: &str) -> BackendResult<VmDto> {
+        Ok(self.host().vm(name)?.to_dto())
+    }
+
+    fn create_vm(&self, req: &CreateVmRequest) -> BackendResult<VmDto> {
+        let mut host = self.host();
+        if req.name.trim().is_empty() {
+            return Err(HvError::InvalidParameter("VM name is required".to_string()).into());
+        }
+        if host.vms.contains_key(&req.name) {
+            return Err(
+                HvError::InvalidParameter(format!("VM '{}' already exists", req.name)).into(),
+            );
+        }
+        if req.memory_mb == 0 {
+            return Err(HvError::InvalidParameter(
+                "memory_mb must be greater than zero".to_string(),
+            )
+            .into());
+        }
+        let generation = req.generation.unwrap_or(2);
+        if generation != 1 && generation != 2 {
+            return Err(HvError::InvalidParameter(format!(
+                "invalid generation {}, expected 1 or 2",
+                generation
+            ))
+            .into());
+        }
+        if let Some(switch) = &req.switch_name {
+            if !host.switches.contains_key(switch) {
+                return Err(HvError::SwitchNotFound(switch.clone()).into());
+            }
+        }
+        let version = req.version.as_deref().unwrap_or(DEFAULT_VM_VERSION);
+        if !VM_VERSIONS.contains(&version) {
+            return Err(HvError::InvalidParameter(format!(
+                "configuration version {} is not supported by this host",
+                version
+            ))
+            .into());
+        }
+
+        host.insert_vhd(&req.vhd_path, "Dynamic", req.vhd_size_bytes, None)?;
+        host.set_vhd_attached(&req.vhd_path, true);
+
+        let id = host.next_id();
+        let adapter = host.new_adapter(None, req.switch_name.clone(), None);
+        let controller_type = if generation == 1 { "IDE" } else { "SCSI" };
+        let vm = FakeVm {
+            id,
+            name: req.name.clone(),
+            state: FakeVmState::Off,
+            generation,
+            cpu_count: req.cpu_count.unwrap_or(2),
+            memory_mb: req.memory_mb,
+            dynamic_memory: false,
+            dynamic_memory_min_mb: 512,
+            dynamic_memory_max_mb: 1_048_576,
+            memory_buffer_percentage: 20,
+            automatic_start_action: AutomaticStartAction::default(),
+            automatic_start_delay_secs: 0,
+            automatic_stop_action: AutomaticStopAction::default(),
+            checkpoint_type: CheckpointType::default(),
+            notes: String::new(),
+            version: version.to_string(),
+            processor: ProcessorSettings::default(),
+            security: match generation {
+                1 => SecuritySettings::none(),
+                _ => SecuritySettings::windows_gen2(),
+            },
+            adapters: vec![adapter],
+            disks: vec![DiskDto {
+                controller_type: controller_type.to_string(),
+                controller_number: 0,
+                controller_location: 0,
+                path: Some(req.vhd_path.clone()),
+            }],
+            dvd_drives: vec![DiskDto {
+                controller_type: controller_type.to_string(),
+                controller_number: if generation == 1 { 1 } else { 0 },
+                controller_location: if generation == 1 { 0 } else { 1 },
+                path: None,
+            }],
+            boot_order: Vec::new(),
+            snapshots: Vec::new(),
+            current_snapshot: None,
+            gpu_adapters: Vec::new(),
+            started_at: None,
+        };
+        let dto = vm.to_dto();
+        host.vms.insert(req.name.clone(), vm);
+        Ok(dto)
+    }
+
+    fn update_vm(&self, name: &str, req: &UpdateVmRequest) -> BackendResult<VmDto> {
+        let mut host = self.host();
+        let vm = host.vm_mut(name)?;
+        let invalid = |field: &str, value: &str| -> BackendResult<VmDto> {
+            Err(HvError::InvalidParameter(format!("invalid {} '{}'", field, value)).into())
+        };
+
+        // Changes are made on a copy so a refused request leaves the VM as is
+        let mut next = vm.clone();
+        if let Some(count) = req.cpu_count.filter(|c| *c != vm.cpu_count) {
+            vm.require_off("change the processor count of")?;
+            next.cpu_count = count;
+        }
+        if let Some(enabled) = req.dynamic_memory.filter(|e| *e != vm.dynamic_memory) {
+            vm.require_off(match enabled {
+                true => "enable dynamic memory on",
+                false => "disable dynamic memory on",
+            })?;
+            next.dynamic_memory = enabled;
+        }
+        if let Some(mb) = req.memory_mb.filter(|mb| *mb != vm.memory_mb) {
+            // Static memory of a Gen2 VM can be resized while it runs
+            if vm.generation == 1 || next.dynamic_memory {
+                vm.require_off("change the startup memory of")?;
+            }
+            next.memory_mb = mb;
+        }
+        if let Some(mb) = req.dynamic_memory_min_mb {
+            if mb > vm.dynamic_memory_min_mb {
+                vm.require_off("raise the minimum memory of")?;
+            }
+            next.dynamic_memory_min_mb = mb;
+        }
+        if let Some(mb) = req.dynamic_memory_max_mb {
+            if mb < vm.dynamic_memory_max_mb {
+                vm.require_off("lower the maximum memory of")?;
+            }
+            next.dynamic_memory_max_mb = mb;
+        }
+        if let Some(percent) = req.memory_buffer_percentage {
+            next.memory_buffer_percentage = percent;
+        }
+        if let Some(action) = &req.automatic_start_action {
+            match start_action(action) {
+                Some(action) => next.automatic_start_action = action,
+                None => return invalid("automatic start action", action),
+            }
+        }
+        if let Some(secs) = req.automatic_start_delay_secs {
+            next.automatic_start_delay_secs = secs;
+        }
+        if let Some(action) = &req.automatic_stop_action {
+            match stop_action(action) {
+                Some(action) => next.automatic_stop_action = action,
+                None => return invalid("automatic stop action", action),
+            }
+        }
+        if let Some(value) = &req.checkpoint_type {
+            match checkpoint_type(value) {
+                Some(value) => next.checkpoint_type = value,
+                None => return invalid("checkpoint type", value),
+            }
+        }
+        if let Some(notes) = &req.notes {
+            next.notes = notes.clone();
+        }
+        next.validate_settings()?;
+
+        *vm = next;
+        Ok(vm.to_dto())
+    }
+
+    fn delete_vm(&self, name: &str) -> BackendResult<()> {
+        let mut host = self.host();
+        let vm = host.vm(name)?;
+        vm.require_state(&[FakeVmState::Off, FakeVmState::Saved], "delete")?;
+        let disks: Vec<String> = vm.disks.iter().filter_map(|d| d.path.clone()).collect();
+        host.vms.remove(name);
+        for path in disks {
+            host.set_vhd_attached(&path, false);
+        }
+        for device in host.devices.iter_mut() {
+            if device.assigned_vm.as_deref() == Some(name) {
+                device.is_assigned = false;
+                device.assigned_vm = None;
+            }
+        }
+        Ok(())
+    }
+
+    fn start_vm(&self, name: &str) -> BackendResult<()> {
+        self.transition(
+            name,
+            &[FakeVmState::Off, FakeVmState::Saved],
+            "start",
+            FakeVmState::Running,
+        )
+    }
+
+    fn stop_vm(&self, name: &str) -> BackendResult<()> {
+        self.transition(
+            name,
+            &[FakeVmState::Running, FakeVmState::Paused],
+            "stop",
+            FakeVmState::Off,
+        )
+    }
+
+    fn force_stop_vm(&self, name: &str) -> BackendResult<()> {
+        self.transition(
+            name,
+            &[
+                FakeVmState::Running,
+                FakeVmState::Paused,
+                FakeVmState::Saved,
+            ],
+            "force stop",
+            FakeVmState::Off,
+        )
+    }
+
+    fn pause_vm(&self, name: &str) -> BackendResult<()> {
+        self.transition(name, &[FakeVmState::Running], "pause", FakeVmState::Paused)
+    }
+
+    fn resume_vm(&self, name: &str) -> BackendResult<()> {
+        self.transition(name, &[FakeVmState::Paused], "resume", FakeVmState::Running)
+    }
+
+    fn save_vm(&self, name: &str) -> BackendResult<()> {
+        self.transition(
+            name,
+            &[FakeVmState::Running, FakeVmState::Paused],
+            "save",
+            FakeVmState::Saved,
+        )
+    }
+
+    fn reset_vm(&self, name: &str) -> BackendResult<()> {
+        let mut host = self.host();
+        let vm = host.vm_mut(name)?;
+        vm.require_state(&[FakeVmState::Running], "reset")?;
+        vm.started_at = Some(Instant::now());
+        Ok(())
+    }
+
+    fn export_vm(&self, name: &str, path: &str, progress: ProgressFn<'_>) -> BackendResult<()> {
+        self.host().vm(name)?;
+        if path.trim().is_empty() {
+            return Err(HvError::InvalidParameter("export path is required".to_string()).into());
+        }
+        simulate_progress(self.operation_delay, progress, "Exporting VM");
+        self.host().vm(name)?;
+        progress(100, "Export completed");
+        Ok(())
+    }
+
+    fn upgrade_vm_version(&self, name: &str) -> BackendResult<VmDto> {
+        let mut host = self.host();
+        let vm = host.vm_mut(name)?;
+        vm.require_off("upgrade the configuration version of")?;
+        let capabilities = HostCapabilities {
+            default_vm_version: VmVersionInfo::parse(DEFAULT_VM_VERSION),
+            ..HostCapabilities::default()
+        };
+        let Some(recommended) = capabilities.recommended_vm_version() else {
+            return Ok(vm.to_dto());
+        };
+        if VmVersionInfo::parse(&vm.version).is_some_and(|v| v.is_compatible_with(recommended)) {
+            return Err(HvError::InvalidState(format!(
+                "VM '{}' is already at configuration version {}",
+                name, vm.version
+            ))
+            .into());
+        }
+        vm.version = recommended.to_string();
+        Ok(vm.to_dto())
+    }
+
+    fn vm_processor(&self, name: &str) -> BackendResult<VmProcessorDto> {
+        Ok(processor_dto(&self.host().vm(name)?.processor()))
+    }
+
+    fn set_vm_processor(
+        &self,
+        name: &str,
+        req: &SetVmProcessorRequest,
+    ) -> BackendResult<VmProcessorDto> {
+        let settings = processor_settings(req)?;
+        let mut host = self.host();
+        let vm = host.vm_mut(name)?;
+        let current = vm.processor();
+
+        // Limit, reservation, weight and CPU group apply to a running VM;
+        // the processors the guest sees do not
+        let offline = [
+            (
+                "change the processor count of",
+                settings.count != current.count,
+            ),
+            (
+                "change the threads per core of",
+                settings.hw_threads_per_core != current.hw_threads_per_core,
+            ),
+            (
+                "change nested virtualization on",
+                settings.expose_virtualization_extensions
+                    != current.expose_virtualization_extensions,
+            ),
+            (
+                "change the NUMA topology of",
+                settings.numa_topology != current.numa_topology,
+            ),
+        ];
+        if let Some((action, _)) = offline.iter().find(|(_, changed)| *changed) {
+            vm.require_off(action)?;
+        }
+        vm.cpu_count = settings.count;
+        vm.processor = settings;
+        Ok(processor_dto(&vm.processor()))
+    }
+
+    fn vm_security(&self, name: &str) -> BackendResult<VmSecurityDto> {
+        Ok(security_dto(&self.host().vm(name)?.security))
+    }
+
+    fn set_vm_security(
+        &self,
+        name: &str,
+        req: &SetVmSecurityRequest,
+    ) -> BackendResult<VmSecurityDto> {
+        let settings = security_settings(req)?;
+        let mut host = self.host();
+        let vm = host.vm_mut(name)?;
+        if settings.requires_gen2() && vm.generation == 1 {
+            return Err(HvError::InvalidParameter(format!(
+                "Secure Boot and vTPM need a generation 2 VM, '{}' is generation 1",
+                name
+            ))
+            .into());
+        }
+        if settings != vm.security {
+            vm.require_off("change the security settings of")?;
+        }
+        vm.security = settings;
+        Ok(security_dto(&vm.security))
+    }
+
+    fn vm_disks(&self, name: &str) -> BackendResult<Vec<DiskDto>> {
+        Ok(self.host().vm(name)?.disks.clone())
+    }
+
+    fn attach_disk(&self, name: &str, vhd_path: &str) -> BackendResult<()> {
+        let mut host = self.host();
+        host.vm(name)?;
+        host.vhd(vhd_path)?;
+        if let Some(owner) = host.vhd_in_use(vhd_path) {
+            return Err(HvError::InvalidState(format!(
+                "VHD '{}' is already attached to VM '{}'",
+                vhd_path, owner
+            ))
+            .into());
+        }
+        let vm = host.vm_mut(name)?;
+        let controller_type = vm.controller_type().to_string();
+        let occupied: Vec<u32> = vm
+            .disks
+            .iter()
+            .chain(vm.dvd_drives.iter())
+            .filter(|d| d.controller_number == 0)
+            .map(|d| d.controller_location)
+            .collect();
+        let location = (0..).find(|l| !occupied.contains(l)).unwrap_or_default();
+        vm.disks.push(DiskDto {
+            controller_type,
+            controller_number: 0,
+            controller_location: location,
+            path: Some(vhd_path.to_string()),
+        });
+        host.set_vhd_attached(vhd_path, true);
+        Ok(())
+    }
+
+    fn detach_disk(
+        &self,
+        name: &str,
+        controller_number: u32,
+        controller_location: u32,
+    ) -> BackendResult<()> {
+        let mut host = self.host();
+        let vm = host.vm_mut(name)?;
+        let index = vm
+            .disks
+            .iter()
+            .position(|d| {
+                d.controller_number == controller_number
+                    && d.controller_location == controller_location
+            })
+            .ok_or_else(|| {
+                HvError::InvalidParameter(format!(
+                    "no disk at controller {} location {}",
+                    controller_number, controller_location
+                ))
+            })?;
+        let disk = vm.disks.remove(index);
+        if let Some(path) = disk.path {
+            host.set_vhd_attached(&path, false);
+        }
+        Ok(())
+    }
+
+    fn vm_dvd_drives(&self, name: &str) -> BackendResult<Vec<DiskDto>> {
+        Ok(self.host().vm(name)?.dvd_drives.clone())
+    }
+
+    fn mount_iso(&self, name: &str, iso_path: &str) -> BackendResult<()> {
+        if !iso_path.to_lowercase().ends_with(".iso") {
+            return Err(
+                HvError::InvalidParameter(format!("'{}' is not an ISO file", iso_path)).into(),
+            );
+        }
+        let mut host = self.host();
+        let vm = host.vm_mut(name)?;
+        let drive = vm
+            .dvd_drives
+            .first_mut()
+            .ok_or_else(|| HvError::OperationFailed(format!("VM '{}' has no DVD drive", name)))?;
+        drive.path = Some(iso_path.to_string());
+        Ok(())
+    }
+
+    fn eject_iso(&self, name: &str) -> BackendResult<()> {
+        let mut host = self.host();
+        let vm = host.vm_mut(name)?;
+        for drive in vm.dvd_drives.iter_mut() {
+            drive.path = None;
+        }
+        Ok(())
+    }
+
+    fn set_boot_order(&self, name: &str, devices: &[String]) -> BackendResult<()> {
+        let mut host = self.host();
+        let vm = host.vm_mut(name)?;
+        if devices.is_empty() {
+            return Err(HvError::InvalidParameter(
+                "boot order must list at least one device".to_string(),
+            )
+            .into());
+        }
+        vm.boot_order = devices.to_vec();
+        Ok(())
+    }
+
+    fn vm_network_adapters(&self, name: &str) -> BackendResult<Vec<VmNetworkAdapterDto>> {
+        Ok(self.host().vm(name)?.adapters.clone())
+    }
+
+    fn add_network_adapter(
+        &self,
+        name: &str,
+        req: &AddVmNetworkAdapterRequest,
+    ) -> BackendResult<VmNetworkAdapterDto> {
+        let mut host = self.host();
+        let vm = host.vm(name)?;
+        vm.require_hot_plug("add a network adapter to")?;
+        if vm.adapters.len() >= MAX_NETWORK_ADAPTERS {
+            return Err(HvError::InvalidParameter(format!(
+                "VM '{}' already has {} network adapters",
+                name, MAX_NETWORK_ADAPTERS
+            ))
+            .into());
+        }
+        host.require_switch(req.switch_name.as_deref())?;
+
+        let mac_address = match req.dynamic_mac {
+            Some(true) => None,
+            _ => req.mac_address.clone(),
+        };
+        let adapter = host.new_adapter(req.name.clone(), req.switch_name.clone(), mac_address);
+        let adapter = apply_adapter_settings(
+            adapter,
+            &UpdateVmNetworkAdapterRequest {
+                mac_address: None,
+                dynamic_mac: None,
+                vlan_id: req.vlan_id,
+                mac_spoofing: req.mac_spoofing,
+                dhcp_guard: req.dhcp_guard,
+                router_guard: req.router_guard,
+                port_mirroring: req.port_mirroring.clone(),
+                bandwidth: req.bandwidth.clone(),
+            },
+        )?;
+        host.vm_mut(name)?.adapters.push(adapter.clone());
+        Ok(adapter)
+    }
+
+    fn update_network_adapter(
+        &self,
+        name: &str,
+        adapter: &str,
+        req: &UpdateVmNetworkAdapterRequest,
+    ) -> BackendResult<VmNetworkAdapterDto> {
+        let mut host = self.host();
+        let vm = host.vm_mut(name)?;
+        let index = vm.adapter_index(adapter)?;
+        let current = &vm.adapters[index];
+        let mac_changes = req.dynamic_mac.is_some_and(|d| d != current.dynamic_mac)
+            || req
+                .mac_address
+                .as_ref()
+                .is_some_and(|mac| Some(mac) != current.mac_address.as_ref());
+        if mac_changes {
+            vm.require_off("change the MAC address of")?;
+        }
+        let updated = apply_adapter_settings(current.clone(), req)?;
+        vm.adapters[index] = updated.clone();
+        Ok(updated)
+    }
+
+    fn connect_network_adapter(
+        &self,
+        name: &str,
+        adapter: &str,
+        switch_name: Option<&str>,
+    ) -> BackendResult<VmNetworkAdapterDto> {
+        let mut host = self.host();
+        host.require_switch(switch_name)?;
+        let vm = host.vm_mut(name)?;
+        let index = vm.adapter_index(adapter)?;
+        vm.adapters[index].switch_name = switch_name.map(str::to_string);
+        Ok(vm.adapters[index].clone())
+    }
+
+    fn remove_network_adapter(&self, name: &str, adapter: &str) -> BackendResult<()> {
+        let mut host = self.host();
+        let vm = host.vm_mut(name)?;
+        let index = vm.adapter_index(adapter)?;
+        vm.require_hot_plug("remove a network adapter from")?;
+        vm.adapters.remove(index);
+        Ok(())
+    }
+
+    fn list_snapshots(&self, vm_name: &str) -> BackendResult<Vec<SnapshotDto>> {
+        Ok(self.host().vm(vm_name)?.snapshots.clone())
+    }
+
+    fn get_snapshot(&self, vm_name: &str, snapshot: &str) -> BackendResult<SnapshotDto> {
+        let host = self.host();
+        let vm = host.vm(vm_name)?;
+        Ok(vm.snapshots[vm.snapshot_index(snapshot)?].clone())
+    }
+
+    fn create_snapshot(
+        &self,
+        vm_name: &str,
+        req: &CreateSnapshotRequest,
+    ) -> BackendResult<SnapshotDto> {
+        let mut host = self.host();
+        host.vm(vm_name)?;
+        if req.name.trim().is_empty() {
+            return Err(HvError::InvalidParameter("snapshot name is required".to_string()).into());
+        }
+        let id = host.next_id();
+        let vm = host.vm_mut(vm_name)?;
+        // Hyper-V allows several checkpoints with the same name
+        let parent = vm
+            .current_snapshot
+            .as_ref()
+            .and_then(|current| vm.snapshots.iter().find(|s| &s.id == current));
+        let snapshot = SnapshotDto {
+            name: req.name.clone(),
+            id,
+            vm_name: vm_name.to_string(),
+            creation_time: Some(now_rfc3339()),
+            parent_name: parent.map(|p| p.name.clone()),
+            parent_id: parent.map(|p| p.id.clone()),
+        };
+        vm.current_snapshot = Some(snapshot.id.clone());
+        vm.snapshots.push(snapshot.clone());
+        Ok(snapshot)
+    }
+
+    fn apply_snapshot(
+        &self,
+        vm_name: &str,
+        snapshot: &str,
+        progress: ProgressFn<'_>,
+    ) -> BackendResult<()> {
+        let id = self.get_snapshot(vm_name, snapshot)?.id;
+        simulate_progress(self.operation_delay, progress, "Applying snapshot");
+        let mut host = self.host();
+        let vm = host.vm_mut(vm_name)?;
+        if !vm.snapshots.iter().any(|s| s.id == id) {
+            return Err(HvError::SnapshotNotFound(snapshot.to_string()).into());
+        }
+        vm.current_snapshot = Some(id);
+        vm.set_state(FakeVmState::Off);
+        progress(100, "Snapshot applied");
+        Ok(())
+    }
+
+    fn delete_snapshot(&self, vm_name: &str, snapshot: &str) -> BackendResult<()> {
+        let mut host = self.host();
+        let vm = host.vm_mut(vm_name)?;
+        let index = vm.snapshot_index(snapshot)?;
+        let removed = vm.snapshots.remove(index);
+        // Children merge into the deleted checkpoint's parent
+        for child in vm.snapshots.iter_mut() {
+            if child.parent_id.as_ref() == Some(&removed.id) {
+                child.parent_name = removed.parent_name.clone();
+                child.parent_id = removed.parent_id.clone();
+            }
+        }
+        if vm.current_snapshot.as_ref() == Some(&removed.id) {
+            vm.current_snapshot = removed.parent_id;
+        }
+        Ok(())
+    }
+
+    fn delete_snapshot_subtree(&self, vm_name: &str, snapshot: &str) -> BackendResult<()> {
+        let mut host = self.host();
+        let vm = host.vm_mut(vm_name)?;
+        let root = vm.snapshots[vm.snapshot_index(snapshot)?].clone();
+        let mut doomed = vec![root.id];
+        let mut next = 0;
+        while next < doomed.len() {
+            let parent = doomed[next].clone();
+            doomed.extend(
+                vm.snapshots
+                    .iter()
+                    .filter(|s| s.parent_id.as_ref() == Some(&parent))
+                    .filter(|s| !doomed.contains(&s.id))
+                    .map(|s| s.id.clone())
+                    .collect::<Vec<_>>(),
+            );
+            next += 1;
+        }
+        vm.snapshots.retain(|s| !doomed.contains(&s.id));
+        if let Some(current) = &vm.current_snapshot {
+            if doomed.contains(current) {
+                vm.current_snapshot = root.parent_id;
+            }
+        }
+        Ok(())
+    }
+
+    fn rename_snapshot(
+        &self,
+        vm_name: &str,
+        snapshot: &str,
+        new_name: &str,
+    ) -> BackendResult<SnapshotDto> {
+        let mut host = self.host();
+        let vm = host.vm_mut(vm_name)?;
+        if new_name.trim().is_empty() {
+            return Err(HvError::InvalidParameter("snapshot name is required".to_string()).into());
+        }
+        let index = vm.snapshot_index(snapshot)?;
+        let id = vm.snapshots[index].id.clone();
+        vm.snapshots[index].name = new_name.to_string();
+        for s in vm.snapshots.iter_mut() {
+            if s.parent_id.as_ref() == Some(&id) {
+                s.parent_name = Some(new_name.to_string());
+            }
+        }
+        Ok(vm.snapshots[index].clone())
+    }
+
+    fn export_snapshot(
+        &self,
+        vm_name: &str,
+        snapshot: &str,
+        path: &str,
+        progress: ProgressFn<'_>,
+    ) -> BackendResult<()> {
+        let id = self.get_snapshot(vm_name, snapshot)?.id;
+        if path.trim().is_empty() {
+            return Err(HvError::InvalidParameter("export path is required".to_string()).into());
+        }
+        simulate_progress(self.operation_delay, progress, "Exporting snapshot");
+        self.get_snapshot(vm_name, &id)?;
+        progress(100, "Export completed");
+        Ok(())
+    }
+
+    fn current_snapshot_id(&self, vm_name: &str) -> BackendResult<Option<String>> {
+        Ok(self.host().vm(vm_name)?.current_snapshot.clone())
+    }
+
+    fn list_switches(&self) -> BackendResult<Vec<SwitchDto>> {
+        Ok(self.host().switches.values().cloned().collect())
+    }
+
+    fn get_switch(&self, name: &str) -> BackendResult<SwitchDto> {
+        self.host()
+            .switches
+            .get(name)
+            .cloned()
+            .ok_or_else(|| HvError::SwitchNotFound(name.to_string()).into())
+    }
+
+    fn create_switch(&self, req: &CreateSwitchRequest) -> BackendResult<SwitchDto> {
+        let mut host = self.host();
+        if req.name.trim().is_empty() {
+            return Err(HvError::InvalidParameter("switch name is required".to_string()).into());
+        }
+        if host.switches.contains_key(&req.name) {
+            return Err(
+                HvError::InvalidParameter(format!("switch '{}' already exists", req.name)).into(),
+            );
+        }
+        let switch_type = match req.switch_type.to_lowercase().as_str() {
+            "external" => {
+                let adapter = req.network_adapter.as_deref().ok_or_else(|| {
+                    HvError::InvalidParameter(
+                        "network_adapter required for external switch".to_string(),
+                    )
+                })?;
+                if !host.adapters.iter().any(|a| a.name == adapter) {
+                    return Err(HvError::InvalidParameter(format!(
+                        "network adapter '{}' not found",
+                        adapter
+                    ))
+                    .into());
+                }
+                "External"
+            }
+            "internal" => "Internal",
+            "private" => "Private",
+            _ => return Err(HvError::InvalidParameter("Invalid switch_type".to_string()).into()),
+        };
+        let dto = SwitchDto {
+            name: req.name.clone(),
+            id: host.next_id(),
+            switch_type: switch_type.to_string(),
+        };
+        host.switches.insert(req.name.clone(), dto.clone());
+        Ok(dto)
+    }
+
+    fn delete_switch(&self, name: &str) -> BackendResult<()> {
+        let mut host = self.host();
+        if host.switches.remove(name).is_none() {
+            return Err(HvError::SwitchNotFound(name.to_string()).into());
+        }
+        // Hyper-V disconnects adapters from a removed switch
+        for adapter in host.vms.values_mut().flat_map(|vm| vm.adapters.iter_mut()) {
+            if adapter.switch_name.as_deref() == Some(name) {
+                adapter.switch_name = None;
+            }
+        }
+        Ok(())
+    }
+
+    fn get_vhd(&self, path: &str) -> BackendResult<VhdDto> {
+        let host = self.host();
+        let vhd = host.vhd(path)?;
+        Ok(vhd.info.clone())
+    }
+
+    fn create_vhd(
+        &self,
+        req: &CreateVhdRequest,
+        progress: ProgressFn<'_>,
+    ) -> BackendResult<VhdDto> {
+        let vhd_type = match req.vhd_type.as_deref() {
+            Some("Fixed") => "Fixed",
+            Some("Differencing") => {
+                return Err(HvError::InvalidParameter(
+                    "use /vhds/differencing to create a differencing VHD".to_string(),
+                )
+                .into())
+            }
+            _ => "Dynamic",
+        };
+        if self.host().vhds.contains_key(&vhd_key(&req.path)) {
+            return Err(
+                HvError::InvalidParameter(format!("VHD '{}' already exists", req.path)).into(),
+            );
+        }
+        simulate_progress(self.operation_delay, progress, "Creating VHD");
+        let vhd = self
+            .host()
+            .insert_vhd(&req.path, vhd_type, req.size_bytes, None)?;
+        progress(100, "VHD created");
+        Ok(vhd)
+    }
+
+    fn resize_vhd(&self, path: &str, size_bytes: u64) -> BackendResult<()> {
+        let mut host = self.host();
+        let vhd = host.vhd_mut(path)?;
+        if size_bytes < vhd.info.max_size_bytes && vhd.info.is_attached {
+            return Err(HvError::InvalidState(format!(
+                "cannot shrink VHD '{}' while it is attached",
+                path
+            ))
+            .into());
+        }
+        if size_bytes == 0 {
+            return Err(HvError::InvalidParameter(
+                "VHD size must be greater than zero".to_string(),
+            )
+            .into());
+        }
+        vhd.info.max_size_bytes = size_bytes;
+        if vhd.info.vhd_type == "Fixed" {
+            vhd.info.file_size_bytes = size_bytes;
+        }
+        Ok(())
+    }
+
+    fn compact_vhd(&self, path: &str, progress: ProgressFn<'_>) -> BackendResult<()> {
+        self.host().vhd(path)?;
+        simulate_progress(self.operation_delay, progress, "Compacting VHD");
+        let mut host = self.host();
+        let vhd = host.vhd_mut(path)?;
+        if vhd.info.vhd_type == "Fixed" {
+            return Err(HvError::InvalidParameter(format!(
+                "VHD '{}' is fixed size and cannot be compacted",
+                path
+            ))
+            .into());
+        }
+        if vhd.info.is_attached {
+            return Err(HvError::InvalidState(format!(
+                "VHD '{}' must be detached before compacting",
+                path
+            ))
+            .into());
+        }
+        vhd.info.file_size_bytes = vhd.info.file_size_bytes.min(EMPTY_DYNAMIC_VHD_BYTES);
+        progress(100, "VHD compacted");
+        Ok(())
+    }
+
+    fn mount_vhd(&self, path: &str) -> BackendResult<()> {
+        let mut host = self.host();
+        let vhd = host.vhd_mut(path)?;
+        if vhd.mounted {
+            return Err(HvError::InvalidState(format!("VHD '{}' is already mounted", path)).into());
+        }
+        vhd.mounted = true;
+        vhd.info.is_attached = true;
+        Ok(())
+    }
+
+    fn dismount_vhd(&self, path: &str) -> BackendResult<()> {
+        let mut host = self.host();
+        let in_use = host.vhd_in_use(path).is_some();
+        let vhd = host.vhd_mut(path)?;
+        if !vhd.mounted {
+            return Err(HvError::InvalidState(format!("VHD '{}' is not mounted", path)).into());
+        }
+        vhd.mounted = false;
+        vhd.drive_letter = None;
+        vhd.info.is_attached = in_use;
+        Ok(())
+    }
+
+    fn create_differencing_vhd(&self, path: &str, parent_path: &str) -> BackendResult<VhdDto> {
+        let mut host = self.host();
+        let parent_size = host.vhd(parent_path)?.info.max_size_bytes;
+        host.insert_vhd(
+            path,
+            "Differencing",
+            parent_size,
+            Some(parent_path.to_string()),
+        )
+    }
+
+    fn initialize_vhd(&self, req: &InitVhdRequest) -> BackendResult<String> {
+        let mut host = self.host();
+        let used: Vec<String> = host
+            .vhds
+            .values()
+            .filter_map(|v| v.drive_letter.clone())
+            .collect();
+        let vhd = host.vhd_mut(&req.path)?;
+        if vhd.drive_letter.is_some() {
+            return Err(HvError::InvalidState(format!(
+                "VHD '{}' is already initialized",
+                req.path
+            ))
+            .into());
+        }
+        let letter = ('F'..='Z')
+            .map(|c| format!("{}:", c))
+            .find(|l| !used.contains(l))
+            .ok_or_else(|| HvError::OperationFailed("no free drive letters".to_string()))?;
+        vhd.mounted = true;
+        vhd.info.is_attached = true;
+        vhd.drive_letter = Some(letter.clone());
+        Ok(letter)
+    }
+
+    fn windows_editions(&self, iso_path: &str) -> BackendResult<Vec<WindowsEditionDto>> {
+        if !iso_path.to_lowercase().ends_with(".iso") {
+            return Err(
+                HvError::InvalidParameter(format!("'{}' is not an ISO file", iso_path)).into(),
+            );
+        }
+        Ok(fake_editions())
+    }
+
+    fn create_vhdx_from_iso(
+        &self,
+        req: &CreateVhdxFromIsoRequest,
+        progress: ProgressFn<'_>,
+    ) -> BackendResult<()> {
+        let editions = self.windows_editions(&req.iso_path)?;
+        if !editions.iter().any(|e| e.index == req.edition_index) {
+            return Err(HvError::InvalidParameter(format!(
+                "edition index {} not found in '{}'",
+                req.edition_index, req.iso_path
+            ))
+            .into());
+        }
+        simulate_progress(self.operation_delay, progress, "Applying Windows image");
+        self.host().insert_vhd(
+            &req.vhdx_path,
+            "Dynamic",
+            req.size_gb * 1024 * 1024 * 1024,
+            None,
+        )?;
+        progress(100, "VHDX created");
+        Ok(())
+    }
+
+    fn list_gpus(&self) -> BackendResult<Vec<GpuDto>> {
+        Ok(self.host().gpus.clone())
+    }
+
+    fn list_partitionable_gpus(&self) -> BackendResult<Vec<GpuDto>> {
+        Ok(self
+            .host()
+            .gpus
+            .iter()
+            .filter(|g| g.supports_partitioning)
+            .cloned()
+            .collect())
+    }
+
+    fn vm_gpu_adapters(&self, name: &str) -> BackendResult<Vec<GpuAdapterDto>> {
+        Ok(self.host().vm(name)?.gpu_adapters.clone())
+    }
+
+    fn add_gpu(&self, name: &str, instance_path: Option<&str>) -> BackendResult<()> {
+        let mut host = self.host();
+        let instance_path = match instance_path {
+            Some(path) => {
+                if !host.gpus.iter().any(|g| g.device_instance_id == path) {
+                    return Err(
+                        HvError::InvalidParameter(format!("GPU '{}' not found", path)).into(),
+                    );
+                }
+                Some(path.to_string())
+            }
+            None => None,
+        };
+        let vm = host.vm_mut(name)?;
+        vm.require_off("add a GPU partition to")?;
+        vm.gpu_adapters.push(GpuAdapterDto {
+            vm_name: name.to_string(),
+            instance_path,
+            min_partition_vram: 80_000_000,
+            max_partition_vram: 1_000_000_000,
+            optimal_partition_vram: 1_000_000_000,
+        });
+        Ok(())
+    }
+
+    fn remove_gpu(&self, name: &str) -> BackendResult<()> {
+        let mut host = self.host();
+        let vm = host.vm_mut(name)?;
+        vm.require_off("remove a GPU partition from")?;
+        vm.gpu_adapters.clear();
+        Ok(())
+    }
+
+    fn configure_gpu(&self, name: &str, low_mmio_gb: u32, high_mmio_gb: u32) -> BackendResult<()> {
+        let mut host = self.host();
+        let vm = host.vm_mut(name)?;
+        vm.require_off("configure GPU MMIO for")?;
+        if low_mmio_gb == 0 || high_mmio_gb == 0 {
+            return Err(HvError::InvalidParameter(
+                "MMIO space must be greater than zero".to_string(),
+            )
+            .into());
+        }
+        Ok(())
+    }
+
+    fn dda_support(&self) -> BackendResult<DdaSupportDto> {
+        Ok(DdaSupportDto {
+            is_supported: true,
+            is_server: true,
+            has_iommu: true,
+            cmdlet_available: true,
+            reason: None,
+        })
+    }
+
+    fn assignable_devices(&self) -> BackendResult<Vec<AssignableDeviceDto>> {
+        Ok(self.host().devices.clone())
+    }
+
+    fn device_location_path(&self, instance_id: &str) -> BackendResult<String> {
+        self.host()
+            .devices
+            .iter()
+            .find(|d| d.instance_id.eq_ignore_ascii_case(instance_id))
+            .map(|d| d.location_path.clone())
+            .ok_or_else(|| {
+                HvError::InvalidParameter(format!("device '{}' not found", instance_id)).into()
+            })
+    }
+
+    fn dismount_device(&self, location_path: &str) -> BackendResult<()> {
+        let mut host = self.host();
+        let index = host.device_index(location_path)?;
+        let device = &mut host.devices[index];
+        if device.is_dismounted {
+            return Err(HvError::InvalidState(format!(
+                "device at '{}' is already dismounted",
+                location_path
+            ))
+            .into());
+        }
+        device.is_dismounted = true;
+        device.status = "Dismounted".to_string();
+        Ok(())
+    }
+
+    fn mount_device(&self, location_path: &str) -> BackendResult<()> {
+        let mut host = self.host();
+        let index = host.device_index(location_path)?;
+        let device = &mut host.devices[index];
+        if device.is_assigned {
+            return Err(HvError::InvalidState(format!(
+                "device at '{}' is assigned to VM '{}'",
+                location_path,
+                device.assigned_vm.clone().unwrap_or_default()
+            ))
+            .into());
+        }
+        if !device.is_dismounted {
+            return Err(HvError::InvalidState(format!(
+                "device at '{}' is already mounted to the host",
+                location_path
+            ))
+            .into());
+        }
+        device.is_dismounted = false;
+        device.status = "OK".to_string();
+        Ok(())
+    }
+
+    fn vm_assigned_devices(&self, name: &str) -> BackendResult<Vec<AssignableDeviceDto>> {
+        let host = self.host();
+        host.vm(name)?;
+        Ok(host
+            .devices
+            .iter()
+            .filter(|d| d.assigned_vm.as_deref() == Some(name))
+            .cloned()
+            .collect())
+    }
+
+    fn assign_device(&self, name: &str, location_path: &str) -> BackendResult<()> {
+        let mut host = self.host();
+        host.vm(name)?.require_off("assign a device to")?;
+        let index = host.device_index(location_path)?;
+        let device = &mut host.devices[index];
+        if !device.is_dismounted {
+            return Err(HvError::InvalidState(format!(
+                "device at '{}' must be dismounted from the host first",
+                location_path
+            ))
+            .into());
+        }
+        if device.is_assigned {
+            return Err(HvError::InvalidState(format!(
+                "device at '{}' is already assigned",
+                location_path
+            ))
+            .into());
+        }
+        device.is_assigned = true;
+        device.assigned_vm = Some(name.to_string());
+        Ok(())
+    }
+
+    fn remove_device(&self, name: &str, location_path: &str) -> BackendResult<()> {
+        let mut host = self.host();
+        host.vm(name)?.require_off("remove a device from")?;
+        let index = host.device_index(location_path)?;
+        let device = &mut host.devices[index];
+        if device.assigned_vm.as_deref() != Some(name) {
+            return Err(HvError::InvalidState(format!(
+                "device at '{}' is not assigned to VM '{}'",
+                location_path, name
+            ))
+            .into());
+        }
+        device.is_assigned = false;
+        device.assigned_vm = None;
+        Ok(())
+    }
+}
+
+fn fake_editions() -> Vec<WindowsEditionDto> {
+    vec![
+        WindowsEditionDto {
+            index: 1,
+            name: "Windows Server 2022 Standard".to_string(),
+            description: "Windows Server 2022 Standard (Server Core)".to_string(),
+            size_bytes: 9_000_000_000,
+        },
+        WindowsEditionDto {
+            index: 2,
+            name: "Windows Server 2022 Datacenter".to_string(),
+            description: "Windows Server 2022 Datacenter (Desktop Experience)".to_string(),
+            size_bytes: 14_000_000_000,
+        },
+    ]
+}
+
+// =============================================================================
+// Failover Cluster
+// =============================================================================
+
+/// Node states, named after `clus::NodeState`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FakeNodeState {
+    Up,
+    Down,
+    Paused,
+}
+
+/// Group and resource states, named after `clus::GroupState`/`ResourceState`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FakeOnlineState {
+    Online,
+    Offline,
+}
+
+#[derive(Debug, Clone)]
+struct FakeResource {
+    name: String,
+    group: String,
+    state: FakeOnlineState,
+    is_csv: bool,
+    maintenance: bool,
+}
+
+#[derive(Debug, Clone)]
+struct FakeGroup {
+    name: String,
+    state: FakeOnlineState,
+    owner_node: String,
+}
+
+#[derive(Debug)]
+struct FakeClusterState {
+    name: String,
+    nodes: BTreeMap<String, FakeNodeState>,
+    groups: BTreeMap<String, FakeGroup>,
+    resources: BTreeMap<String, FakeResource>,
+}
+
+impl FakeClusterState {
+    fn check_cluster(&self, cluster: Option<&str>) -> BackendResult<()> {
+        match cluster {
+            Some(name) if !name.eq_ignore_ascii_case(&self.name) => {
+                Err(ClusError::OpenClusterFailed(name.to_string()).into())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn node(&self, name: &str) -> BackendResult<FakeNodeState> {
+        self.nodes
+            .get(name)
+            .copied()
+            .ok_or_else(|| ClusError::OpenNodeFailed(name.to_string()).into())
+    }
+
+    fn group_mut(&mut self, name: &str) -> BackendResult<&mut FakeGroup> {
+        self.groups
+            .get_mut(name)
+            .ok_or_else(|| ClusError::OpenGroupFailed(name.to_string()).into())
+    }
+
+    fn resource(&self, name: &str) -> BackendResult<&FakeResource> {
+        self.resources
+            .get(name)
+            .ok_or_else(|| ClusError::OpenResourceFailed(name.to_string()).into())
+    }
+
+    fn resource_mut(&mut self, name: &str) -> BackendResult<&mut FakeResource> {
+        self.resources
+            .get_mut(name)
+            .ok_or_else(|| ClusError::OpenResourceFailed(name.to_string()).into())
+    }
+
+    fn group_dto(&self, group: &FakeGroup) -> GroupDto {
+        GroupDto {
+            name: group.name.clone(),
+            state: format!("{:?}", group.state),
+            owner_node: Some(group.owner_node.clone()),
+        }
+    }
+
+    fn resource_dto(&self, resource: &FakeResource) -> ResourceDto {
+        ResourceDto {
+            name: resource.name.clone(),
+            state: format!("{:?}", resource.state),
+            owner_node: self
+                .groups
+                .get(&resource.group)
+                .map(|g| g.owner_node.clone()),
+        }
+    }
+}
+
+/// In-memory failover cluster
+#[derive(Debug)]
+pub struct FakeCluster {
+    state: Mutex<FakeClusterState>,
+}
+
+impl Default for FakeCluster {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FakeCluster {
+    /// Create a two-node cluster with the core groups and two CSV disks
+    pub fn new() -> Self {
+        let nodes = ["NODE1", "NODE2"]
+            .into_iter()
+            .map(|n| (n.to_string(), FakeNodeState::Up))
+            .collect();
+        let groups = [("Cluster Group", "NODE1"), ("Available Storage", "NODE1")]
+            .into_iter()
+            .map(|(name, owner)| {
+                (
+                    name.to_string(),
+                    FakeGroup {
+                        name: name.to_string(),
+                        state: FakeOnlineState::Online,
+                        owner_node: owner.to_string(),
+                    },
+                )
+            })
+            .collect();
+        let resources = [
+            ("Cluster Name", "Cluster Group", false),
+            ("Cluster IP Address", "Cluster Group", false),
+            ("Cluster Disk 1", "Available Storage", true),
+            ("Cluster Disk 2", "Available Storage", true),
+        ]
+        .into_iter()
+        .map(|(name, group, is_csv)| {
+            (
+                name.to_string(),
+                FakeResource {
+                    name: name.to_string(),
+                    group: group.to_string(),
+                    state: FakeOnlineState::Online,
+                    is_csv,
+                    maintenance: false,
+                },
+            )
+        })
+        .collect();
+        Self {
+            state: Mutex::new(FakeClusterState {
+                name: "FAKE-CLUSTER".to_string(),
+                nodes,
+                groups,
+                resources,
+            }),
+        }
+    }
+
+    fn cluster(&self, cluster: Option<&str>) -> BackendResult<MutexGuard<'_, FakeClusterState>> {
+        let state = self.state.lock().expect("fake cluster state poisoned");
+        state.check_cluster(cluster)?;
+        Ok(state)
+    }
+
+    fn set_group_state(
+        &self,
+        cluster: Option<&str>,
+        name: &str,
+        to: FakeOnlineState,
+    ) -> BackendResult<()> {
+        let mut state = self.cluster(cluster)?;
+        state.group_mut(name)?.state = to;
+        for resource in state.resources.values_mut() {
+            if resource.group == name {
+                resource.state = to;
+            }
+        }
+        Ok(())
+    }
+
+    fn set_resource_state(
+        &self,
+        cluster: Option<&str>,
+        name: &str,
+        to: FakeOnlineState,
+    ) -> BackendResult<()> {
+        let mut state = self.cluster(cluster)?;
+        state.resource_mut(name)?.state = to;
+        Ok(())
+    }
+}
+
+impl ClusterBackend for FakeCluster {
+    fn cluster_name(&self, cluster: Option<&str>) -> BackendResult<String> {
+        Ok(self.cluster(cluster)?.name.clone())
+    }
+
+    fn list_nodes(&self, cluster: Option<&str>) -> BackendResult<Vec<NodeDto>> {
+        Ok(self
+            .cluster(cluster)?
+            .nodes
+            .iter()
+            .map(|(name, state)| NodeDto {
+                name: name.clone(),
+                state: format!("{:?}", state),
+            })
+            .collect())
+    }
+
+    fn get_node(&self, cluster: Option<&str>, name: &str) -> BackendResult<NodeDto> {
+        let state = self.cluster(cluster)?.node(name)?;
+        Ok(NodeDto {
+            name: name.to_string(),
+            state: format!("{:?}", state),
+        })
+    }
+
+    fn pause_node(&self, cluster: Option<&str>, name: &str) -> BackendResult<()> {
+        let mut state = self.cluster(cluster)?;
+        if state.node(name)? == FakeNodeState::Down {
+            return Err(ClusError::OperationFailed(format!("node '{}' is down", name)).into());
+        }
+        state.nodes.insert(name.to_string(), FakeNodeState::Paused);
+        Ok(())
+    }
+
+    fn resume_node(&self, cluster: Option<&str>, name: &str) -> BackendResult<()> {
+        let mut state = self.cluster(cluster)?;
+        if state.node(name)? != FakeNodeState::Paused {
+            return Err(
+                ClusError::OperationFailed(format!("node '{}' is not paused", name)).into(),
+            );
+        }
+        state.nodes.insert(name.to_string(), FakeNodeState::Up);
+        Ok(())
+    }
+
+    fn list_groups(&self, cluster: Option<&str>) -> BackendResult<Vec<GroupDto>> {
+        let state = self.cluster(cluster)?;
+        Ok(state.groups.values().map(|g| state.group_dto(g)).collect())
+    }
+
+    fn get_group(&self, cluster: Option<&str>, name: &str) -> BackendResult<GroupDto> {
+        let state = self.cluster(cluster)?;
+        let group = state
+            .groups
+            .get(name)
+            .ok_or_else(|| ClusError::OpenGroupFailed(name.to_string()))?;
+        Ok(state.group_dto(group))
+    }
+
+    fn group_online(&self, cluster: Option<&str>, name: &str) -> BackendResult<()> {
+        self.set_group_state(cluster, name, FakeOnlineState::Online)
+    }
+
+    fn group_offline(&self, cluster: Option<&str>, name: &str) -> BackendResult<()> {
+        self.set_group_state(cluster, name, FakeOnlineState::Offline)
+    }
+
+    fn move_group(
+        &self,
+        cluster: Option<&str>,
+        name: &str,
+        target_node: &str,
+        progress: ProgressFn<'_>,
+    ) -> BackendResult<()> {
+        let mut state = self.cluster(cluster)?;
+        progress(0, "Moving group");
+        let node_state = state.node(target_node)?;
+        if node_state != FakeNodeState::Up {
+            return Err(ClusError::OperationFailed(format!(
+                "node '{}' is {:?} and cannot own groups",
+                target_node, node_state
+            ))
+            .into());
+        }
+        state.group_mut(name)?.owner_node = target_node.to_string();
+        progress(100, "Group moved");
+        Ok(())
+    }
+
+    fn list_resources(&self, cluster: Option<&str>) -> BackendResult<Vec<ResourceDto>> {
+        let state = self.cluster(cluster)?;
+        Ok(state
+            .resources
+            .values()
+            .map(|r| state.resource_dto(r))
+            .collect())
+    }
+
+    fn get_resource(&self, cluster: Option<&str>, name: &str) -> BackendResult<ResourceDto> {
+        let state = self.cluster(cluster)?;
+        let resource = state.resource(name)?;
+        Ok(state.resource_dto(resource))
+    }
+
+    fn resource_online(&self, cluster: Option<&str>, name: &str) -> BackendResult<()> {
+        self.set_resource_state(cluster, name, FakeOnlineState::Online)
+    }
+
+    fn resource_offline(&self, cluster: Option<&str>, name: &str) -> BackendResult<()> {
+        self.set_resource_state(cluster, name, FakeOnlineState::Offline)
+    }
+
+    fn list_csvs(&self, cluster: Option<&str>) -> BackendResult<Vec<CsvDto>> {
+        let state = self.cluster(cluster)?;
+        Ok(state
+            .resources
+            .values()
+            .filter(|r| r.is_csv)
+            .map(|r| {
+                let dto = state.resource_dto(r);
+                CsvDto {
+                    name: dto.name,
+                    state: dto.state,
+                    owner_node: dto.owner_node,
+                    is_csv: true,
+                }
+            })
+            .collect())
+    }
+
+    fn is_path_on_csv(&self, path: &str) -> BackendResult<bool> {
+        Ok(path.to_lowercase().starts_with(r"c:\clusterstorage\"))
+    }
+
+    fn set_csv_maintenance(
+        &self,
+        cluster: Option<&str>,
+        name: &str,
+        enable: bool,
+    ) -> BackendResult<()> {
+        let mut state = self.cluster(cluster)?;
+        let resource = state.resource_mut(name)?;
+        if !resource.is_csv {
+            return Err(ClusError::OperationFailed(format!(
+                "resource '{}' is not a cluster shared volume",
+                name
+            ))
+            .into());
+        }
+        resource.maintenance = enable;
+        Ok(())
+    }
+}
@@ -0,0 +1,150 @@
+//! Backend decorators that record call metrics
+//!
+//! [`InstrumentedHyperV`] and [`InstrumentedCluster`] wrap any backend and
+//! report the duration and outcome of every call to [`Metrics`], labelled
+//! with the backend (`hyperv` or `cluster`) and the trait method name.
+
+use std::sync::Arc;
+use std::time::Instant;
+
+use super::{BackendResult, ClusterBackend, HypervBackend, ProgressFn};
+use crate::dto::*;
+use crate::metrics::Metrics;
+
+/// Forward each listed method to `self.inner`, timing the call
+macro_rules! instrument {
+    ($backend:literal; $(fn $name:ident(&self $(, $arg:ident: $ty:ty)*) -> $ret:ty;)*) => {
+        $(
+            fn $name(&self $(, $arg: $ty)*) -> BackendResult<$ret> {
+                let start = Instant::now();
+                let result = self.inner.$name($($arg),*);
+                self.metrics
+                    .observe_backend($backend, stringify!($name), start.elapsed(), result.is_ok());
+                result
+            }
+        )*
+    };
+}
+
+/// [`HypervBackend`] that records metrics for an inner backend
+pub struct InstrumentedHyperV {
+    inner: Arc<dyn HypervBackend>,
+    metrics: Arc<Metrics>,
+}
+
+impl InstrumentedHyperV {
+    pub fn new(inner: Arc<dyn HypervBackend>, metrics: Arc<Metrics>) -> Self {
+        Self { inner, metrics }
+    }
+}
+
+/// [`ClusterBackend`] that records metrics for an inner backend
+pub struct InstrumentedCluster {
+    inner: Arc<dyn ClusterBackend>,
+    metrics: Arc<Metrics>,
+}
+
+impl InstrumentedCluster {
+    pub fn new(inner: Arc<dyn ClusterBackend>, metrics: Arc<Metrics>) -> Self {
+        Self { inner, metrics }
+    }
+}
+
+impl HypervBackend for InstrumentedHyperV {
+    instrument! {
+        "hyperv";
+        fn host_info(&self) -> HostInfoDto;
+        fn host_capabilities(&self) -> HostCapabilitiesDto;
+        fn list_network_adapters(&self) -> Vec<NetworkAdapterDto>;
+        fn list_vms(&self) -> Vec<VmDto>;
+        fn get_vm(&self, name: &str) -> VmDto;
+        fn create_vm(&self, req: &CreateVmRequest) -> VmDto;
+        fn update_vm(&self, name: &str, req: &UpdateVmRequest) -> VmDto;
+        fn delete_vm(&self, name: &str) -> ();
+        fn start_vm(&self, name: &str) -> ();
+        fn stop_vm(&self, name: &str) -> ();
+        fn force_stop_vm(&self, name: &str) -> ();
+        fn pause_vm(&self, name: &str) -> ();
+        fn resume_vm(&self, name: &str) -> ();
+        fn save_vm(&self, name: &str) -> ();
+        fn reset_vm(&self, name: &str) -> ();
+        fn export_vm(&self, name: &str, path: &str, progress: ProgressFn<'_>) -> ();
+        fn upgrade_vm_version(&self, name: &str) -> VmDto;
+        fn vm_processor(&self, name: &str) -> VmProcessorDto;
+        fn set_vm_processor(&self, name: &str, req: &SetVmProcessorRequest) -> VmProcessorDto;
+        fn vm_security(&self, name: &str) -> VmSecurityDto;
+        fn set_vm_security(&self, name: &str, req: &SetVmSecurityRequest) -> VmSecurityDto;
+        fn vm_disks(&self, name: &str) -> Vec<DiskDto>;
+        fn attach_disk(&self, name: &str, vhd_path: &str) -> ();
+        fn detach_disk(&self, name: &str, controller_number: u32, controller_location: u32) -> ();
+        fn vm_dvd_drives(&self, name: &str) -> Vec<DiskDto>;
+        fn mount_iso(&self, name: &str, iso_path: &str) -> ();
+        fn eject_iso(&self, name: &str) -> ();
+        fn set_boot_order(&self, name: &str, devices: &[String]) -> ();
+        fn vm_network_adapters(&self, name: &str) -> Vec<VmNetworkAdapterDto>;
+        fn add_network_adapter(&self, name: &str, req: &AddVmNetworkAdapterRequest) -> VmNetworkAdapterDto;
+        fn update_network_adapter(&self, name: &str, adapter: &str, req: &UpdateVmNetworkAdapterRequest) -> VmNetworkAdapterDto;
+        fn connect_network_adapter(&self, name: &str, adapter: &str, switch_name: Option<&str>) -> VmNetworkAdapterDto;
+        fn remove_network_adapter(&self, name: &str, adapter: &str) -> ();
+        fn list_snapshots(&self, vm_name: &str) -> Vec<SnapshotDto>;
+        fn get_snapshot(&self, vm_name: &str, snapshot: &str) -> SnapshotDto;
+        fn create_snapshot(&self, vm_name: &str, req: &CreateSnapshotRequest) -> SnapshotDto;
+        fn apply_snapshot(&self, vm_name: &str, snapshot: &str, progress: ProgressFn<'_>) -> ();
+        fn delete_snapshot(&self, vm_name: &str, snapshot: &str) -> ();
+        fn delete_snapshot_subtree(&self, vm_name: &str, snapshot: &str) -> ();
+        fn rename_snapshot(&self, vm_name: &str, snapshot: &str, new_name: &str) -> SnapshotDto;
+        fn export_snapshot(&self, vm_name: &str, snapshot: &str, path: &str, progress: ProgressFn<'_>) -> ();
+        fn current_snapshot_id(&self, vm_name: &str) -> Option<String>;
+        fn list_switches(&self) -> Vec<SwitchDto>;
+        fn get_switch(&self, name: &str) -> SwitchDto;
+        fn create_switch(&self, req: &CreateSwitchRequest) -> SwitchDto;
+        fn delete_switch(&self, name: &str) -> ();
+        fn get_vhd(&self, path: &str) -> VhdDto;
+        fn create_vhd(&self, req: &CreateVhdRequest, progress: ProgressFn<'_>) -> VhdDto;
+        fn resize_vhd(&self, path: &str, size_bytes: u64) -> ();
+        fn compact_vhd(&self, path: &str, progress: ProgressFn<'_>) -> ();
+        fn mount_vhd(&self, path: &str) -> ();
+        fn dismount_vhd(&self, path: &str) -> ();
+        fn create_differencing_vhd(&self, path: &str, parent_path: &str) -> VhdDto;
+        fn initialize_vhd(&self, req: &InitVhdRequest) -> String;
+        fn windows_editions(&self, iso_path: &str) -> Vec<WindowsEditionDto>;
+        fn create_vhdx_from_iso(&self, req: &CreateVhdxFromIsoRequest, progress: ProgressFn<'_>) -> ();
+        fn list_gpus(&self) -> Vec<GpuDto>;
+        fn list_partitionable_gpus(&self) -> Vec<GpuDto>;
+        fn vm_gpu_adapters(&self, name: &str) -> Vec<GpuAdapterDto>;
+        fn add_gpu(&self, name: &str, instance_path: Option<&str>) -> ();
+        fn remove_gpu(&self, name: &str) -> ();
+        fn configure_gpu(&self, name: &str, low_mmio_gb: u32, high_mmio_gb: u32) -> ();
+        fn dda_support(&self) -> DdaSupportDto;
+        fn assignable_devices(&self) -> Vec<AssignableDeviceDto>;
+        fn device_location_path(&self, instance_id: &str) -> String;
+        fn dismount_device(&self, location_path: &str) -> ();
+        fn mount_device(&self, location_path: &str) -> ();
+        fn vm_assigned_devices(&self, name: &str) -> Vec<AssignableDeviceDto>;
+        fn assign_device(&self, name: &str, location_path: &str) -> ();
+        fn remove_device(&self, name: &str, location_path: &str) -> ();
+    }
+}
+
+impl ClusterBackend for InstrumentedCluster {
+    instrument! {
+        "cluster";
+        fn cluster_name(&self, cluster: Option<&str>) -> String;
+        fn list_nodes(&self, cluster: Option<&str>) -> Vec<NodeDto>;
+        fn get_node(&self, cluster: Option<&str>, name: &str) -> NodeDto;
+        fn pause_node(&self, cluster: Option<&str>, name: &str) -> ();
+        fn resume_node(&self, cluster: Option<&str>, name: &str) -> ();
+        fn list_groups(&self, cluster: Option<&str>) -> Vec<GroupDto>;
+        fn get_group(&self, cluster: Option<&str>, name: &str) -> GroupDto;
+        fn group_online(&self, cluster: Option<&str>, name: &str) -> ();
+        fn group_offline(&self, cluster: Option<&str>, name: &str) -> ();
+        fn move_group(&self, cluster: Option<&str>, name: &str, target_node: &str, progress: ProgressFn<'_>) -> ();
+        fn list_resources(&self, cluster: Option<&str>) -> Vec<ResourceDto>;
+        fn get_resource(&self, cluster: Option<&str>, name: &str) -> ResourceDto;
+        fn resource_online(&self, cluster: Option<&str>, name: &str) -> ();
+        fn resource_offline(&self, cluster: Option<&str>, name: &str) -> ();
+        fn list_csvs(&self, cluster: Option<&str>) -> Vec<CsvDto>;
+        fn is_path_on_csv(&self, path: &str) -> bool;
+        fn set_csv_maintenance(&self, cluster: Option<&str>, name: &str, enable: bool) -> ();
+    }
+}
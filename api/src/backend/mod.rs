@@ -0,0 +1,353 @@
+//! Pluggable backends for the Hyper-V and cluster endpoints
+//!
+//! Handlers never talk to `hv` or `clus` directly. They go through the
+//! [`HypervBackend`] and [`ClusterBackend`] trait objects held in
+//! [`AppState`](crate::AppState), which lets the same router run against:
+//! - `native`: `hv::HyperV` and `clus::Cluster` (Windows only)
+//! - `fake`: a stateful in-memory simulation, usable on any platform
+//!
+//! On non-Windows platforms the `native` backend resolves to
+//! [`UnsupportedBackend`], which answers every call with `501 Not Implemented`.
+
+pub mod fake;
+pub mod instrumented;
+#[cfg(windows)]
+pub mod native;
+pub mod unsupported;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use thiserror::Error;
+use windows_hyperv::{ProcessorSettings, SecuritySettings};
+
+use crate::config::{BackendConfig, BackendKind};
+use crate::dto::*;
+
+pub use fake::{FakeCluster, FakeHyperV};
+pub use instrumented::{InstrumentedCluster, InstrumentedHyperV};
+pub use unsupported::UnsupportedBackend;
+
+// =============================================================================
+// Errors
+// =============================================================================
+
+/// Errors returned by backend implementations
+#[derive(Error, Debug)]
+pub enum BackendError {
+    #[error(transparent)]
+    HyperV(#[from] hv::HvError),
+
+    #[error(transparent)]
+    Cluster(#[from] clus::ClusError),
+
+    /// Boxed: `windows_hyperv::Error` is several times larger than the others
+    #[error(transparent)]
+    WindowsHyperV(Box<windows_hyperv::Error>),
+
+    #[error("{0}")]
+    NotSupported(String),
+}
+
+impl From<windows_hyperv::Error> for BackendError {
+    fn from(err: windows_hyperv::Error) -> Self {
+        BackendError::WindowsHyperV(Box::new(err))
+    }
+}
+
+pub type BackendResult<T> = Result<T, BackendError>;
+
+/// Settings properties `host_capabilities` reports support for, by WMI class
+pub const CAPABILITY_PROPERTIES: [(&str, &str); 13] = [
+    ("Msvm_ProcessorSettingData", "HwThreadsPerCore"),
+    (
+        "Msvm_ProcessorSettingData",
+        "ExposeVirtualizationExtensions",
+    ),
+    ("Msvm_ProcessorSettingData", "EnableHostResourceProtection"),
+    ("Msvm_ProcessorSettingData", "CpuGroupId"),
+    ("Msvm_ProcessorSettingData", "L3CacheWays"),
+    ("Msvm_MemorySettingData", "DynamicMemoryEnabled"),
+    ("Msvm_MemorySettingData", "HugePagesEnabled"),
+    ("Msvm_MemorySettingData", "SgxEnabled"),
+    ("Msvm_VirtualSystemSettingData", "SecureBootEnabled"),
+    ("Msvm_VirtualSystemSettingData", "TurnOffOnGuestRestart"),
+    ("Msvm_SecuritySettingData", "TpmEnabled"),
+    (
+        "Msvm_SecuritySettingData",
+        "EncryptStateAndVmMigrationTraffic",
+    ),
+    ("Msvm_SecuritySettingData", "ShieldingRequested"),
+];
+
+/// Processor settings as the API reports them
+pub fn processor_dto(settings: &ProcessorSettings) -> VmProcessorDto {
+    VmProcessorDto {
+        count: settings.count,
+        limit_percent: settings.limit.as_percent(),
+        reservation_percent: settings.reservation.as_percent(),
+        weight: settings.weight.value(),
+        hw_threads_per_core: settings.hw_threads_per_core.map(|t| t.value()),
+        expose_virtualization_extensions: settings.expose_virtualization_extensions,
+        cpu_group_id: settings.cpu_group_id.clone(),
+        numa_nodes: settings
+            .numa_topology
+            .iter()
+            .flat_map(|topology| &topology.nodes)
+            .map(|node| NumaNodeDto {
+                id: node.id,
+                processor_count: node.processor_count,
+                memory_mb: node.memory_mb,
+            })
+            .collect(),
+    }
+}
+
+/// Security settings as the API reports them, enums by variant name
+pub fn security_dto(settings: &SecuritySettings) -> VmSecurityDto {
+    VmSecurityDto {
+        secure_boot_enabled: settings.secure_boot_enabled,
+        secure_boot_template: settings.secure_boot_template.map(|t| format!("{:?}", t)),
+        tpm_enabled: settings.tpm_enabled,
+        guest_isolation_type: format!("{:?}", settings.guest_isolation_type),
+        encrypt_state_and_migration: settings.encrypt_state_and_migration,
+        shielding_requested: settings.shielding_requested,
+        key_protector: format!("{:?}", settings.key_protector_type),
+    }
+}
+
+/// Progress callback for long-running operations
+///
+/// Mirrors `windows_hyperv::JobProgress`: percent complete (0-100) and a
+/// short status description. Backends without native progress reporting
+/// call it once when starting and once when finished.
+pub type ProgressFn<'a> = &'a dyn Fn(u32, &str);
+
+// =============================================================================
+// Traits
+// =============================================================================
+
+/// Hyper-V host operations used by the `/api/v1/hyperv` handlers
+pub trait HypervBackend: Send + Sync {
+    // Host
+    fn host_info(&self) -> BackendResult<HostInfoDto>;
+    fn host_capabilities(&self) -> BackendResult<HostCapabilitiesDto>;
+    fn list_network_adapters(&self) -> BackendResult<Vec<NetworkAdapterDto>>;
+
+    // VMs
+    fn list_vms(&self) -> BackendResult<Vec<VmDto>>;
+    fn get_vm(&self, name: &str) -> BackendResult<VmDto>;
+    fn create_vm(&self, req: &CreateVmRequest) -> BackendResult<VmDto>;
+    fn update_vm(&self, name: &str, req: &UpdateVmRequest) -> BackendResult<VmDto>;
+    fn delete_vm(&self, name: &str) -> BackendResult<()>;
+    fn start_vm(&self, name: &str) -> BackendResult<()>;
+    fn stop_vm(&self, name: &str) -> BackendResult<()>;
+    fn force_stop_vm(&self, name: &str) -> BackendResult<()>;
+    fn pause_vm(&self, name: &str) -> BackendResult<()>;
+    fn resume_vm(&self, name: &str) -> BackendResult<()>;
+    fn save_vm(&self, name: &str) -> BackendResult<()>;
+    fn reset_vm(&self, name: &str) -> BackendResult<()>;
+    fn export_vm(&self, name: &str, path: &str, progress: ProgressFn<'_>) -> BackendResult<()>;
+    /// Raise the VM's configuration version to the host's default
+    fn upgrade_vm_version(&self, name: &str) -> BackendResult<VmDto>;
+
+    // VM processor and security settings
+    fn vm_processor(&self, name: &str) -> BackendResult<VmProcessorDto>;
+    fn set_vm_processor(
+        &self,
+        name: &str,
+        req: &SetVmProcessorRequest,
+    ) -> BackendResult<VmProcessorDto>;
+    fn vm_security(&self, name: &str) -> BackendResult<VmSecurityDto>;
+    fn set_vm_security(
+        &self,
+        name: &str,
+        req: &SetVmSecurityRequest,
+    ) -> BackendResult<VmSecurityDto>;
+
+    // VM disks and DVD drives
+    fn vm_disks(&self, name: &str) -> BackendResult<Vec<DiskDto>>;
+    fn attach_disk(&self, name: &str, vhd_path: &str) -> BackendResult<()>;
+    fn detach_disk(
+        &self,
+        name: &str,
+        controller_number: u32,
+        controller_location: u32,
+    ) -> BackendResult<()>;
+    fn vm_dvd_drives(&self, name: &str) -> BackendResult<Vec<DiskDto>>;
+    fn mount_iso(&self, name: &str, iso_path: &str) -> BackendResult<()>;
+    fn eject_iso(&self, name: &str) -> BackendResult<()>;
+    fn set_boot_order(&self, name: &str, devices: &[String]) -> BackendResult<()>;
+
+    // VM network adapters, addressed by id or name
+    fn vm_network_adapters(&self, name: &str) -> BackendResult<Vec<VmNetworkAdapterDto>>;
+    fn add_network_adapter(
+        &self,
+        name: &str,
+        req: &AddVmNetworkAdapterRequest,
+    ) -> BackendResult<VmNetworkAdapterDto>;
+    fn update_network_adapter(
+        &self,
+        name: &str,
+        adapter: &str,
+        req: &UpdateVmNetworkAdapterRequest,
+    ) -> BackendResult<VmNetworkAdapterDto>;
+    /// Connect to `switch_name`, or disconnect when it is `None`
+    fn connect_network_adapter(
+        &self,
+        name: &str,
+        adapter: &str,
+        switch_name: Option<&str>,
+    ) -> BackendResult<VmNetworkAdapterDto>;
+    fn remove_network_adapter(&self, name: &str, adapter: &str) -> BackendResult<()>;
+
+    // Snapshots
+    fn list_snapshots(&self, vm_name: &str) -> BackendResult<Vec<SnapshotDto>>;
+    fn get_snapshot(&self, vm_name: &str, snapshot: &str) -> BackendResult<SnapshotDto>;
+    fn create_snapshot(
+        &self,
+        vm_name: &str,
+        req: &CreateSnapshotRequest,
+    ) -> BackendResult<SnapshotDto>;
+    fn apply_snapshot(
+        &self,
+        vm_name: &str,
+        snapshot: &str,
+        progress: ProgressFn<'_>,
+    ) -> BackendResult<()>;
+    fn delete_snapshot(&self, vm_name: &str, snapshot: &str) -> BackendResult<()>;
+    /// Delete a checkpoint together with all of its descendants
+    fn delete_snapshot_subtree(&self, vm_name: &str, snapshot: &str) -> BackendResult<()>;
+    fn rename_snapshot(
+        &self,
+        vm_name: &str,
+        snapshot: &str,
+        new_name: &str,
+    ) -> BackendResult<SnapshotDto>;
+    fn export_snapshot(
+        &self,
+        vm_name: &str,
+        snapshot: &str,
+        path: &str,
+        progress: ProgressFn<'_>,
+    ) -> BackendResult<()>;
+    /// Id of the checkpoint the VM's running state was taken from, if any
+    fn current_snapshot_id(&self, vm_name: &str) -> BackendResult<Option<String>>;
+
+    // Switches
+    fn list_switches(&self) -> BackendResult<Vec<SwitchDto>>;
+    fn get_switch(&self, name: &str) -> BackendResult<SwitchDto>;
+    fn create_switch(&self, req: &CreateSwitchRequest) -> BackendResult<SwitchDto>;
+    fn delete_switch(&self, name: &str) -> BackendResult<()>;
+
+    // VHDs
+    fn get_vhd(&self, path: &str) -> BackendResult<VhdDto>;
+    fn create_vhd(&self, req: &CreateVhdRequest, progress: ProgressFn<'_>)
+        -> BackendResult<VhdDto>;
+    fn resize_vhd(&self, path: &str, size_bytes: u64) -> BackendResult<()>;
+    fn compact_vhd(&self, path: &str, progress: ProgressFn<'_>) -> BackendResult<()>;
+    fn mount_vhd(&self, path: &str) -> BackendResult<()>;
+    fn dismount_vhd(&self, path: &str) -> BackendResult<()>;
+    fn create_differencing_vhd(&self, path: &str, parent_path: &str) -> BackendResult<VhdDto>;
+    fn initialize_vhd(&self, req: &InitVhdRequest) -> BackendResult<String>;
+
+    // Windows images
+    fn windows_editions(&self, iso_path: &str) -> BackendResult<Vec<WindowsEditionDto>>;
+    fn create_vhdx_from_iso(
+        &self,
+        req: &CreateVhdxFromIsoRequest,
+        progress: ProgressFn<'_>,
+    ) -> BackendResult<()>;
+
+    // GPUs
+    fn list_gpus(&self) -> BackendResult<Vec<GpuDto>>;
+    fn list_partitionable_gpus(&self) -> BackendResult<Vec<GpuDto>>;
+    fn vm_gpu_adapters(&self, name: &str) -> BackendResult<Vec<GpuAdapterDto>>;
+    fn add_gpu(&self, name: &str, instance_path: Option<&str>) -> BackendResult<()>;
+    fn remove_gpu(&self, name: &str) -> BackendResult<()>;
+    fn configure_gpu(&self, name: &str, low_mmio_gb: u32, high_mmio_gb: u32) -> BackendResult<()>;
+
+    // DDA
+    fn dda_support(&self) -> BackendResult<DdaSupportDto>;
+    fn assignable_devices(&self) -> BackendResult<Vec<AssignableDeviceDto>>;
+    fn device_location_path(&self, instance_id: &str) -> BackendResult<String>;
+    fn dismount_device(&self, location_path: &str) -> BackendResult<()>;
+    fn mount_device(&self, location_path: &str) -> BackendResult<()>;
+    fn vm_assigned_devices(&self, name: &str) -> BackendResult<Vec<AssignableDeviceDto>>;
+    fn assign_device(&self, name: &str, location_path: &str) -> BackendResult<()>;
+    fn remove_device(&self, name: &str, location_path: &str) -> BackendResult<()>;
+}
+
+/// Failover cluster operations used by the `/api/v1/cluster` handlers
+///
+/// Every method takes the optional cluster name from [`ClusterNameQuery`];
+/// `None` targets the local cluster.
+pub trait ClusterBackend: Send + Sync {
+    fn cluster_name(&self, cluster: Option<&str>) -> BackendResult<String>;
+
+    // Nodes
+    fn list_nodes(&self, cluster: Option<&str>) -> BackendResult<Vec<NodeDto>>;
+    fn get_node(&self, cluster: Option<&str>, name: &str) -> BackendResult<NodeDto>;
+    fn pause_node(&self, cluster: Option<&str>, name: &str) -> BackendResult<()>;
+    fn resume_node(&self, cluster: Option<&str>, name: &str) -> BackendResult<()>;
+
+    // Groups
+    fn list_groups(&self, cluster: Option<&str>) -> BackendResult<Vec<GroupDto>>;
+    fn get_group(&self, cluster: Option<&str>, name: &str) -> BackendResult<GroupDto>;
+    fn group_online(&self, cluster: Option<&str>, name: &str) -> BackendResult<()>;
+    fn group_offline(&self, cluster: Option<&str>, name: &str) -> BackendResult<()>;
+    fn move_group(
+        &self,
+        cluster: Option<&str>,
+        name: &str,
+        target_node: &str,
+        progress: ProgressFn<'_>,
+    ) -> BackendResult<()>;
+
+    // Resources
+    fn list_resources(&self, cluster: Option<&str>) -> BackendResult<Vec<ResourceDto>>;
+    fn get_resource(&self, cluster: Option<&str>, name: &str) -> BackendResult<ResourceDto>;
+    fn resource_online(&self, cluster: Option<&str>, name: &str) -> BackendResult<()>;
+    fn resource_offline(&self, cluster: Option<&str>, name: &str) -> BackendResult<()>;
+
+    // CSV
+    fn list_csvs(&self, cluster: Option<&str>) -> BackendResult<Vec<CsvDto>>;
+    fn is_path_on_csv(&self, path: &str) -> BackendResult<bool>;
+    fn set_csv_maintenance(
+        &self,
+        cluster: Option<&str>,
+        name: &str,
+        enable: bool,
+    ) -> BackendResult<()>;
+}
+
+// =============================================================================
+// Selection
+// =============================================================================
+
+/// Build the Hyper-V and cluster backends selected by the configuration
+pub fn from_config(config: &BackendConfig) -> (Arc<dyn HypervBackend>, Arc<dyn ClusterBackend>) {
+    match config.kind {
+        BackendKind::Fake => {
+            let delay = Duration::from_millis(config.operation_delay_ms);
+            (
+                Arc::new(FakeHyperV::new().with_operation_delay(delay)),
+                Arc::new(FakeCluster::new()),
+            )
+        }
+        BackendKind::Native => native_backends(),
+    }
+}
+
+#[cfg(windows)]
+fn native_backends() -> (Arc<dyn HypervBackend>, Arc<dyn ClusterBackend>) {
+    (
+        Arc::new(native::NativeHyperV),
+        Arc::new(native::NativeCluster),
+    )
+}
+
+#[cfg(not(windows))]
+fn native_backends() -> (Arc<dyn HypervBackend>, Arc<dyn ClusterBackend>) {
+    (Arc::new(UnsupportedBackend), Arc::new(UnsupportedBackend))
+}
@@ -0,0 +1,1280 @@
+//! Native backends backed by `hv::HyperV` and `clus::Cluster`
+//!
+//! Each call opens a fresh connection, mirroring how the handlers used the
+//! bindings before the backend traits existed.
+
+use clus::{Cluster, Csv, GroupState, ResourceState};
+use hv::{
+    HvError, HyperV, HyperVWmi, SnapshotType, StartAction, StopAction, SwitchType, VhdType,
+    VmConfigUpdate, VmGeneration,
+};
+use windows_hyperv::{
+    AutomaticStartAction, AutomaticStopAction, BandwidthSettings, CheckpointType, Generation,
+    HostCapabilities, KeyProtectorType, NetworkAdapterSettings, ProcessorSettings, PropertySupport,
+    PropertyValidator, SecuritySettings, VmSettings, VmVersionInfo,
+};
+
+use super::{
+    processor_dto, security_dto, BackendError, BackendResult, ClusterBackend, HypervBackend,
+    ProgressFn, CAPABILITY_PROPERTIES,
+};
+use crate::dto::*;
+use crate::validation::{
+    checkpoint_type, port_mirroring, processor_settings, security_settings, start_action,
+    stop_action,
+};
+
+// =============================================================================
+// Hyper-V
+// =============================================================================
+
+/// Hyper-V backend using the `hv` crate
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NativeHyperV;
+
+fn vm_dto(vm: &mut hv::Vm) -> VmDto {
+    let config = vm.config().ok();
+    VmDto {
+        id: vm.id().to_string(),
+        name: vm.name().to_string(),
+        state: vm.state().map(|s| format!("{:?}", s)).unwrap_or_default(),
+        cpu_count: vm.cpu_count().ok(),
+        memory_mb: vm.memory_mb().ok(),
+        uptime_seconds: None,
+        generation: vm.generation().map(|g| match g {
+            VmGeneration::Gen1 => 1,
+            VmGeneration::Gen2 => 2,
+        }),
+        dynamic_memory: config.as_ref().map(|c| c.dynamic_memory),
+        dynamic_memory_min_mb: config.as_ref().map(|c| c.dynamic_memory_min_mb),
+        dynamic_memory_max_mb: config.as_ref().map(|c| c.dynamic_memory_max_mb),
+        memory_buffer_percentage: config.as_ref().map(|c| c.memory_buffer_percentage),
+        automatic_start_action: config
+            .as_ref()
+            .map(|c| format!("{:?}", c.automatic_start_action)),
+        automatic_start_delay_secs: config.as_ref().map(|c| c.automatic_start_delay_secs),
+        automatic_stop_action: config
+            .as_ref()
+            .map(|c| format!("{:?}", c.automatic_stop_action)),
+        checkpoint_type: config.as_ref().map(|c| format!("{:?}", c.checkpoint_type)),
+        notes: config.map(|c| c.notes),
+        version: vm.version().ok().flatten(),
+    }
+}
+
+/// The request's settings other than startup memory and processor count,
+/// with the action names parsed as validation accepted them
+fn vm_config_update(req: &UpdateVmRequest) -> BackendResult<VmConfigUpdate> {
+    let invalid = |field: &str, value: &str| {
+        HvError::InvalidParameter(format!("invalid {} '{}'", field, value))
+    };
+    let automatic_start_action = match req.automatic_start_action.as_deref() {
+        None => None,
+        Some(value) => Some(match start_action(value) {
+            Some(AutomaticStartAction::Nothing) => StartAction::Nothing,
+            Some(AutomaticStartAction::StartIfRunning) => StartAction::StartIfRunning,
+            Some(AutomaticStartAction::AlwaysStart) => StartAction::AlwaysStart,
+            None => return Err(invalid("automatic start action", value).into()),
+        }),
+    };
+    let automatic_stop_action = match req.automatic_stop_action.as_deref() {
+        None => None,
+        Some(value) => Some(match stop_action(value) {
+            Some(AutomaticStopAction::TurnOff) => StopAction::TurnOff,
+            Some(AutomaticStopAction::Save) => StopAction::Save,
+            Some(AutomaticStopAction::Shutdown) => StopAction::Shutdown,
+            None => return Err(invalid("automatic stop action", value).into()),
+        }),
+    };
+    let checkpoint_type = match req.checkpoint_type.as_deref() {
+        None => None,
+        Some(value) => Some(match checkpoint_type(value) {
+            Some(CheckpointType::Disabled) => hv::CheckpointType::Disabled,
+            Some(CheckpointType::Production) => hv::CheckpointType::Production,
+            Some(CheckpointType::ProductionOnly) => hv::CheckpointType::ProductionOnly,
+            Some(CheckpointType::Standard) => hv::CheckpointType::Standard,
+            None => return Err(invalid("checkpoint type", value).into()),
+        }),
+    };
+    Ok(VmConfigUpdate {
+        dynamic_memory: req.dynamic_memory,
+        dynamic_memory_min_mb: req.dynamic_memory_min_mb,
+        dynamic_memory_max_mb: req.dynamic_memory_max_mb,
+        memory_buffer_percentage: req.memory_buffer_percentage,
+        automatic_start_action,
+        automatic_start_delay_secs: req.automatic_start_delay_secs,
+        automatic_stop_action,
+        checkpoint_type,
+        notes: req.notes.clone(),
+    })
+}
+
+/// The host's default VM configuration version, from `Get-VMHostSupportedVersion`
+fn default_vm_version(hyperv: &HyperV) -> BackendResult<Option<VmVersionInfo>> {
+    Ok(hyperv
+        .supported_vm_versions()?
+        .into_iter()
+        .find(|v| v.is_default)
+        .and_then(|v| VmVersionInfo::parse(&v.version)))
+}
+
+fn property_support_dto(
+    class_name: &str,
+    property: &str,
+    support: PropertySupport,
+) -> PropertySupportDto {
+    let (support, reason) = match support {
+        PropertySupport::Supported => ("Supported", None),
+        PropertySupport::NotSupported(reason) => ("NotSupported", Some(reason)),
+        PropertySupport::Unknown => ("Unknown", None),
+    };
+    PropertySupportDto {
+        class_name: class_name.to_string(),
+        property: property.to_string(),
+        support: support.to_string(),
+        reason,
+    }
+}
+
+fn network_adapter_dto(a: windows_hyperv::NetworkAdapter) -> VmNetworkAdapterDto {
+    VmNetworkAdapterDto {
+        id: a.instance_id,
+        name: a.name,
+        switch_name: a.switch_name,
+        mac_address: a.mac_address,
+        dynamic_mac: a.dynamic_mac,
+        vlan_id: a.vlan_id,
+        mac_spoofing: Some(a.mac_spoofing),
+        dhcp_guard: Some(a.dhcp_guard),
+        router_guard: Some(a.router_guard),
+        port_mirroring: Some(format!("{:?}", a.port_mirroring)),
+        bandwidth: a.bandwidth.map(|b| BandwidthDto {
+            minimum_mbps: b.minimum_mbps,
+            maximum_mbps: b.maximum_mbps,
+            burst_mb: b.burst_mb,
+        }),
+    }
+}
+
+/// Switch port features of `current` with the changes in `req` applied
+fn adapter_port_settings(
+    current: &windows_hyperv::NetworkAdapter,
+    req: &UpdateVmNetworkAdapterRequest,
+) -> BackendResult<NetworkAdapterSettings> {
+    let mirroring = match &req.port_mirroring {
+        Some(mode) => port_mirroring(mode).ok_or_else(|| {
+            HvError::InvalidParameter(format!("invalid port mirroring '{}'", mode))
+        })?,
+        None => current.port_mirroring,
+    };
+    let bandwidth = match &req.bandwidth {
+        Some(b) => (*b != BandwidthDto::default()).then_some(BandwidthSettings {
+            minimum_mbps: b.minimum_mbps,
+            maximum_mbps: b.maximum_mbps,
+            burst_mb: b.burst_mb,
+        }),
+        None => current.bandwidth.clone(),
+    };
+    Ok(NetworkAdapterSettings {
+        vlan_id: req.vlan_id.or(current.vlan_id).filter(|id| *id != 0),
+        mac_spoofing: req.mac_spoofing.unwrap_or(current.mac_spoofing),
+        dhcp_guard: req.dhcp_guard.unwrap_or(current.dhcp_guard),
+        router_guard: req.router_guard.unwrap_or(current.router_guard),
+        port_mirroring: mirroring,
+        bandwidth,
+        ..NetworkAdapterSettings::default()
+    })
+}
+
+/// Fails with the fake backend's `InvalidState` message unless the VM is off
+fn require_off(vm: &windows_hyperv::VirtualMachine, action: &str) -> BackendResult<()> {
+    if vm.state() == windows_hyperv::VmState::Off {
+        return Ok(());
+    }
+    Err(HvError::InvalidState(format!(
+        "cannot {} VM '{}' while it is {:?}",
+        action,
+        vm.name(),
+        vm.state()
+    ))
+    .into())
+}
+
+/// Gen1 VMs only take adapter changes while off
+fn require_hot_plug(vm: &windows_hyperv::VirtualMachine, action: &str) -> BackendResult<()> {
+    match vm.generation() {
+        Generation::Gen1 => require_off(vm, action),
+        Generation::Gen2 => Ok(()),
+    }
+}
+
+/// Find an adapter by id, or by name when that is unambiguous
+fn find_network_adapter(
+    hyperv: &windows_hyperv::HyperV,
+    vm: &windows_hyperv::VirtualMachine,
+    adapter: &str,
+) -> BackendResult<windows_hyperv::NetworkAdapter> {
+    let mut adapters = hyperv.list_network_adapters(vm)?;
+    if let Some(index) = adapters.iter().position(|a| a.instance_id == adapter) {
+        return Ok(adapters.swap_remove(index));
+    }
+    adapters.retain(|a| a.name.eq_ignore_ascii_case(adapter));
+    match adapters.len() {
+        1 => Ok(adapters.remove(0)),
+        0 => Err(windows_hyperv::Error::NetworkAdapterNotFound {
+            vm_name: vm.name().to_string(),
+            adapter_id: adapter.to_string(),
+        }
+        .into()),
+        n => Err(HvError::InvalidParameter(format!(
+            "VM '{}' has {} network adapters named '{}'; use the adapter id",
+            vm.name(),
+            n,
+            adapter
+        ))
+        .into()),
+    }
+}
+
+fn snapshot_dto(s: &hv::Snapshot) -> SnapshotDto {
+    SnapshotDto {
+        name: s.name().to_string(),
+        id: s.id().to_string(),
+        vm_name: s.vm_name().to_string(),
+        creation_time: s.creation_time().ok(),
+        parent_name: s.parent_name().ok().flatten(),
+        parent_id: s.parent_id().ok().flatten(),
+    }
+}
+
+/// Find a checkpoint by id, or by name when that is unambiguous
+fn find_snapshot(hyperv: &HyperV, vm_name: &str, snapshot: &str) -> BackendResult<hv::Snapshot> {
+    let mut snapshots = hyperv.list_snapshots(vm_name)?;
+    if let Some(index) = snapshots.iter().position(|s| s.id() == snapshot) {
+        return Ok(snapshots.swap_remove(index));
+    }
+    snapshots.retain(|s| s.name() == snapshot);
+    match snapshots.len() {
+        1 => Ok(snapshots.remove(0)),
+        0 => Err(HvError::SnapshotNotFound(snapshot.to_string()).into()),
+        n => Err(HvError::InvalidParameter(format!(
+            "VM '{}' has {} checkpoints named '{}'; use the checkpoint id",
+            vm_name, n, snapshot
+        ))
+        .into()),
+    }
+}
+
+fn switch_dto(s: &hv::VirtualSwitch) -> SwitchDto {
+    SwitchDto {
+        name: s.name().to_string(),
+        id: s.id().to_string(),
+        switch_type: s
+            .switch_type()
+            .map(|t| format!("{:?}", t))
+            .unwrap_or_default(),
+    }
+}
+
+fn vhd_dto(vhd: &hv::Vhd, parent_path: Option<String>) -> VhdDto {
+    VhdDto {
+        path: vhd.path().to_string(),
+        format: format!("{:?}", vhd.format()),
+        vhd_type: vhd
+            .vhd_type()
+            .map(|t| format!("{:?}", t))
+            .unwrap_or_default(),
+        max_size_bytes: vhd.max_size_bytes().unwrap_or(0),
+        file_size_bytes: vhd.file_size_bytes().unwrap_or(0),
+        parent_path,
+        is_attached: vhd.is_attached().unwrap_or(false),
+    }
+}
+
+fn disk_dto(
+    controller_type: String,
+    controller_number: u32,
+    controller_location: u32,
+    path: Option<String>,
+) -> DiskDto {
+    DiskDto {
+        controller_type,
+        controller_number,
+        controller_location,
+        path,
+    }
+}
+
+fn gpu_dto(g: hv::GpuInfo) -> GpuDto {
+    GpuDto {
+        device_instance_id: g.device_instance_id,
+        name: g.name,
+        description: g.description,
+        manufacturer: g.manufacturer,
+        supports_partitioning: g.supports_partitioning,
+    }
+}
+
+fn device_dto(d: hv::AssignableDevice) -> AssignableDeviceDto {
+    AssignableDeviceDto {
+        instance_id: d.instance_id,
+        name: d.name,
+        location_path: d.location_path,
+        is_assigned: d.is_assigned,
+        assigned_vm: d.assigned_vm,
+        is_dismounted: d.is_dismounted,
+        status: d.status,
+    }
+}
+
+impl HypervBackend for NativeHyperV {
+    fn host_info(&self) -> BackendResult<HostInfoDto> {
+        let info = HyperV::new()?.host_info()?;
+        Ok(HostInfoDto {
+            computer_name: info.computer_name,
+            logical_processor_count: info.logical_processor_count,
+            memory_capacity_bytes: info.memory_capacity_bytes,
+            vm_path: info.vm_path,
+            vhd_path: info.vhd_path,
+        })
+    }
+
+    fn host_capabilities(&self) -> BackendResult<HostCapabilitiesDto> {
+        let hyperv = HyperV::new()?;
+        let mut versions: Vec<VmVersionInfo> = hyperv
+            .supported_vm_versions()?
+            .iter()
+            .filter_map(|v| VmVersionInfo::parse(&v.version))
+            .collect();
+        versions.sort_by_key(|v| (v.major, v.minor));
+
+        let connection = windows_hyperv::HyperV::connect()?.connection();
+        let mut capabilities = HostCapabilities::query(&connection)?;
+        capabilities.default_vm_version = default_vm_version(&hyperv)?;
+        let mut validator = PropertyValidator::new(&connection);
+        let properties = CAPABILITY_PROPERTIES
+            .iter()
+            .map(|(class_name, property)| {
+                let support = validator.supports_property(class_name, property);
+                property_support_dto(class_name, property, support)
+            })
+            .collect();
+        // HostCapabilities cannot detect nested virtualization; a host that
+        // can expose virtualization extensions to guests supports it
+        let nested = capabilities.nested_virtualization_supported
+            || validator
+                .supports_expose_virtualization_extensions()
+                .is_supported();
+
+        Ok(HostCapabilitiesDto {
+            supported_vm_versions: versions.iter().map(VmVersionInfo::to_string).collect(),
+            default_vm_version: capabilities
+                .recommended_vm_version()
+                .map(VmVersionInfo::to_string),
+            nested_virtualization_supported: nested,
+            dda_supported: hyperv.check_dda_support()?.is_supported,
+            gpu_p_supported: capabilities.gpu_p_supported,
+            tpm_supported: capabilities.tpm_supported,
+            live_migration_supported: capabilities.live_migration_supported,
+            max_processors_per_vm: capabilities.max_processors_per_vm,
+            max_memory_per_vm_mb: capabilities.max_memory_per_vm_mb,
+            properties,
+        })
+    }
+
+    fn list_network_adapters(&self) -> BackendResult<Vec<NetworkAdapterDto>> {
+        let adapters = HyperV::new()?.list_network_adapters()?;
+        Ok(adapters
+            .into_iter()
+            .map(|a| NetworkAdapterDto {
+                name: a.name,
+                description: a.description,
+                mac_address: a.mac_address,
+                link_speed: a.link_speed,
+            })
+            .collect())
+    }
+
+    fn list_vms(&self) -> BackendResult<Vec<VmDto>> {
+        let vms = HyperV::new()?.list_vms()?;
+        Ok(vms.into_iter().map(|mut vm| vm_dto(&mut vm)).collect())
+    }
+
+    fn get_vm(&self, name: &str) -> BackendResult<VmDto> {
+        let mut vm = HyperV::new()?.get_vm(name)?;
+        Ok(vm_dto(&mut vm))
+    }
+
+    fn create_vm(&self, req: &CreateVmRequest) -> BackendResult<VmDto> {
+        if req.version.is_some() {
+            return Err(BackendError::NotSupported(
+                "version cannot be set by the native backend".to_string(),
+            ));
+        }
+        let generation = match req.generation.unwrap_or(2) {
+            1 => VmGeneration::Gen1,
+            _ => VmGeneration::Gen2,
+        };
+        let mut vm = HyperV::new()?.create_vm(
+            &req.name,
+            req.memory_mb,
+            req.cpu_count.unwrap_or(2),
+            generation,
+            &req.vhd_path,
+            req.vhd_size_bytes,
+            req.switch_name.as_deref(),
+        )?;
+        Ok(vm_dto(&mut vm))
+    }
+
+    fn update_vm(&self, name: &str, req: &UpdateVmRequest) -> BackendResult<VmDto> {
+        let update = vm_config_update(req)?;
+        let hyperv = HyperV::new()?;
+        let mut vm = hyperv.get_vm(name)?;
+        let state = vm.state()?;
+        let config = vm.config()?;
+        let memory_mb = vm.memory_mb()?;
+        let cpu_count = vm.cpu_count()?;
+        let require_off = |action: &str| -> BackendResult<()> {
+            if state.is_off() {
+                return Ok(());
+            }
+            Err(HvError::InvalidState(format!(
+                "cannot {} VM '{}' while it is {:?}",
+                action, name, state
+            ))
+            .into())
+        };
+
+        // Same rules as the fake backend: Hyper-V only resizes a running
+        // VM's memory within what it has already promised the guest
+        if req.cpu_count.is_some_and(|c| c != cpu_count) {
+            require_off("change the processor count of")?;
+        }
+        let dynamic = update.dynamic_memory.unwrap_or(config.dynamic_memory);
+        if dynamic != config.dynamic_memory {
+            require_off(match dynamic {
+                true => "enable dynamic memory on",
+                false => "disable dynamic memory on",
+            })?;
+        }
+        if req.memory_mb.is_some_and(|mb| mb != memory_mb)
+            && (vm.generation() == Some(VmGeneration::Gen1) || dynamic)
+        {
+            require_off("change the startup memory of")?;
+        }
+        if update
+            .dynamic_memory_min_mb
+            .is_some_and(|mb| mb > config.dynamic_memory_min_mb)
+        {
+            require_off("raise the minimum memory of")?;
+        }
+        if update
+            .dynamic_memory_max_mb
+            .is_some_and(|mb| mb < config.dynamic_memory_max_mb)
+        {
+            require_off("lower the maximum memory of")?;
+        }
+        VmSettings::builder()
+            .name(name)
+            .generation(match vm.generation() {
+                Some(VmGeneration::Gen1) => Generation::Gen1,
+                _ => Generation::Gen2,
+            })
+            .memory_mb(req.memory_mb.unwrap_or(memory_mb))
+            .processor_count(req.cpu_count.unwrap_or(cpu_count))
+            .dynamic_memory(dynamic)
+            .dynamic_memory_min_mb(
+                update
+                    .dynamic_memory_min_mb
+                    .unwrap_or(config.dynamic_memory_min_mb),
+            )
+            .dynamic_memory_max_mb(
+                update
+                    .dynamic_memory_max_mb
+                    .unwrap_or(config.dynamic_memory_max_mb),
+            )
+            .memory_buffer_percentage(
+                update
+                    .memory_buffer_percentage
+                    .unwrap_or(config.memory_buffer_percentage),
+            )
+            .build()?;
+
+        let wmi = HyperVWmi::new()?;
+        if let Some(mb) = req.memory_mb {
+            wmi.set_vm_memory(name, mb)?;
+        }
+        if let Some(count) = req.cpu_count {
+            wmi.set_vm_processor(name, count)?;
+        }
+        wmi.set_vm_config(name, &update)?;
+        Ok(vm_dto(&mut hyperv.get_vm(name)?))
+    }
+
+    fn delete_vm(&self, name: &str) -> BackendResult<()> {
+        Ok(HyperV::new()?.delete_vm(name)?)
+    }
+
+    fn start_vm(&self, name: &str) -> BackendResult<()> {
+        Ok(HyperV::new()?.get_vm(name)?.start()?)
+    }
+
+    fn stop_vm(&self, name: &str) -> BackendResult<()> {
+        Ok(HyperV::new()?.get_vm(name)?.stop()?)
+    }
+
+    fn force_stop_vm(&self, name: &str) -> BackendResult<()> {
+        Ok(HyperV::new()?.get_vm(name)?.force_stop()?)
+    }
+
+    fn pause_vm(&self, name: &str) -> BackendResult<()> {
+        Ok(HyperV::new()?.get_vm(name)?.pause()?)
+    }
+
+    fn resume_vm(&self, name: &str) -> BackendResult<()> {
+        Ok(HyperV::new()?.get_vm(name)?.resume()?)
+    }
+
+    fn save_vm(&self, name: &str) -> BackendResult<()> {
+        Ok(HyperV::new()?.get_vm(name)?.save()?)
+    }
+
+    fn reset_vm(&self, name: &str) -> BackendResult<()> {
+        let mut vm = HyperV::new()?.get_vm(name)?;
+        vm.force_stop()?;
+        vm.start()?;
+        Ok(())
+    }
+
+    fn export_vm(&self, name: &str, path: &str, progress: ProgressFn<'_>) -> BackendResult<()> {
+        progress(0, "Exporting VM");
+        HyperV::new()?.export_vm(name, path)?;
+        progress(100, "Export completed");
+        Ok(())
+    }
+
+    fn upgrade_vm_version(&self, name: &str) -> BackendResult<VmDto> {
+        let hyperv = HyperV::new()?;
+        let mut vm = hyperv.get_vm(name)?;
+        let state = vm.state()?;
+        if !state.is_off() {
+            return Err(HvError::InvalidState(format!(
+                "cannot upgrade the configuration version of VM '{}' while it is {:?}",
+                name, state
+            ))
+            .into());
+        }
+        let capabilities = HostCapabilities {
+            default_vm_version: default_vm_version(&hyperv)?,
+            ..HostCapabilities::default()
+        };
+        let current = vm.version()?;
+        if let (Some(current), Some(recommended)) = (
+            current.as_deref().and_then(VmVersionInfo::parse),
+            capabilities.recommended_vm_version(),
+        ) {
+            if current.is_compatible_with(recommended) {
+                return Err(HvError::InvalidState(format!(
+                    "VM '{}' is already at configuration version {}",
+                    name, current
+                ))
+                .into());
+            }
+        }
+        hyperv.update_vm_version(name)?;
+        let mut vm = hyperv.get_vm(name)?;
+        Ok(vm_dto(&mut vm))
+    }
+
+    fn vm_processor(&self, name: &str) -> BackendResult<VmProcessorDto> {
+        let vm = HyperV::new()?.get_vm(name)?;
+        let connection = windows_hyperv::HyperV::connect()?.connection();
+        Ok(processor_dto(&ProcessorSettings::get(
+            &connection,
+            vm.id(),
+        )?))
+    }
+
+    fn set_vm_processor(
+        &self,
+        name: &str,
+        req: &SetVmProcessorRequest,
+    ) -> BackendResult<VmProcessorDto> {
+        // `ProcessorSettings::apply` writes no NUMA topology
+        if req
+            .numa_nodes
+            .as_ref()
+            .is_some_and(|nodes| !nodes.is_empty())
+        {
+            return Err(BackendError::NotSupported(
+                "numa_nodes cannot be changed by the native backend".to_string(),
+            ));
+        }
+        let settings = processor_settings(req)?;
+        let mut vm = HyperV::new()?.get_vm(name)?;
+        let state = vm.state()?;
+        let connection = windows_hyperv::HyperV::connect()?.connection();
+        let current = ProcessorSettings::get(&connection, vm.id())?;
+        let offline = settings.count != current.count
+            || settings.hw_threads_per_core != current.hw_threads_per_core
+            || settings.expose_virtualization_extensions
+                != current.expose_virtualization_extensions;
+        if offline && !state.is_off() {
+            return Err(HvError::InvalidState(format!(
+                "cannot change the processors of VM '{}' while it is {:?}",
+                name, state
+            ))
+            .into());
+        }
+        settings.apply(&connection, vm.id())?;
+        Ok(processor_dto(&ProcessorSettings::get(
+            &connection,
+            vm.id(),
+        )?))
+    }
+
+    fn vm_security(&self, name: &str) -> BackendResult<VmSecurityDto> {
+        let vm = HyperV::new()?.get_vm(name)?;
+        let connection = windows_hyperv::HyperV::connect()?.connection();
+        Ok(security_dto(&SecuritySettings::get(&connection, vm.id())?))
+    }
+
+    fn set_vm_security(
+        &self,
+        name: &str,
+        req: &SetVmSecurityRequest,
+    ) -> BackendResult<VmSecurityDto> {
+        let settings = security_settings(req)?;
+        // `SecuritySettings::apply` writes neither the key protector nor the
+        // isolation type
+        if settings.key_protector_type != KeyProtectorType::None {
+            return Err(BackendError::NotSupported(
+                "key_protector cannot be changed by the native backend".to_string(),
+            ));
+        }
+        let mut vm = HyperV::new()?.get_vm(name)?;
+        if settings.requires_gen2() && vm.generation() == Some(VmGeneration::Gen1) {
+            return Err(HvError::InvalidParameter(format!(
+                "Secure Boot and vTPM need a generation 2 VM, '{}' is generation 1",
+                name
+            ))
+            .into());
+        }
+        let state = vm.state()?;
+        let connection = windows_hyperv::HyperV::connect()?.connection();
+        let current = SecuritySettings::get(&connection, vm.id())?;
+        if settings.guest_isolation_type != current.guest_isolation_type {
+            return Err(BackendError::NotSupported(
+                "guest_isolation_type cannot be changed by the native backend".to_string(),
+            ));
+        }
+        if settings != current && !state.is_off() {
+            return Err(HvError::InvalidState(format!(
+                "cannot change the security settings of VM '{}' while it is {:?}",
+                name, state
+            ))
+            .into());
+        }
+        settings.apply(&connection, vm.id())?;
+        Ok(security_dto(&SecuritySettings::get(&connection, vm.id())?))
+    }
+
+    fn vm_disks(&self, name: &str) -> BackendResult<Vec<DiskDto>> {
+        let disks = HyperV::new()?.get_hard_disk_drives(name)?;
+        Ok(disks
+            .into_iter()
+            .map(|d| {
+                disk_dto(
+                    d.controller_type,
+                    d.controller_number,
+                    d.controller_location,
+                    d.path,
+                )
+            })
+            .collect())
+    }
+
+    fn attach_disk(&self, name: &str, vhd_path: &str) -> BackendResult<()> {
+        Ok(HyperV::new()?.add_hard_disk_drive(name, vhd_path)?)
+    }
+
+    fn detach_disk(
+        &self,
+        name: &str,
+        controller_number: u32,
+        controller_location: u32,
+    ) -> BackendResult<()> {
+        Ok(HyperV::new()?.remove_hard_disk_drive(name, controller_number, controller_location)?)
+    }
+
+    fn vm_dvd_drives(&self, name: &str) -> BackendResult<Vec<DiskDto>> {
+        let dvds = HyperV::new()?.get_dvd_drives(name)?;
+        Ok(dvds
+            .into_iter()
+            .map(|d| {
+                disk_dto(
+                    d.controller_type,
+                    d.controller_number,
+                    d.controller_location,
+                    d.path,
+                )
+            })
+            .collect())
+    }
+
+    fn mount_iso(&self, name: &str, iso_path: &str) -> BackendResult<()> {
+        Ok(HyperV::new()?.mount_iso(name, iso_path)?)
+    }
+
+    fn eject_iso(&self, name: &str) -> BackendResult<()> {
+        Ok(HyperV::new()?.eject_iso(name)?)
+    }
+
+    fn set_boot_order(&self, name: &str, devices: &[String]) -> BackendResult<()> {
+        let devices: Vec<&str> = devices.iter().map(|s| s.as_str()).collect();
+        Ok(HyperV::new()?.set_boot_order(name, &devices)?)
+    }
+
+    fn vm_network_adapters(&self, name: &str) -> BackendResult<Vec<VmNetworkAdapterDto>> {
+        let hyperv = windows_hyperv::HyperV::connect()?;
+        let vm = hyperv.get_vm(name)?;
+        let adapters = hyperv.list_network_adapters(&vm)?;
+        Ok(adapters.into_iter().map(network_adapter_dto).collect())
+    }
+
+    fn add_network_adapter(
+        &self,
+        name: &str,
+        req: &AddVmNetworkAdapterRequest,
+    ) -> BackendResult<VmNetworkAdapterDto> {
+        let mut settings = NetworkAdapterSettings::builder();
+        if let Some(adapter) = &req.name {
+            settings = settings.name(adapter);
+        }
+        settings = match &req.mac_address {
+            Some(mac) => settings.mac_address(mac),
+            None => settings.dynamic_mac(true),
+        };
+        let settings = settings.build()?;
+
+        let hyperv = windows_hyperv::HyperV::connect()?;
+        let vm = hyperv.get_vm(name)?;
+        require_hot_plug(&vm, "add a network adapter to")?;
+        let adapter = hyperv.add_network_adapter(&vm, &settings)?;
+        if let Some(switch_name) = &req.switch_name {
+            let switch = hyperv.get_switch(switch_name)?;
+            hyperv.connect_adapter_to_switch(&vm, &adapter, &switch)?;
+        }
+        let port = adapter_port_settings(
+            &adapter,
+            &UpdateVmNetworkAdapterRequest {
+                mac_address: None,
+                dynamic_mac: None,
+                vlan_id: req.vlan_id,
+                mac_spoofing: req.mac_spoofing,
+                dhcp_guard: req.dhcp_guard,
+                router_guard: req.router_guard,
+                port_mirroring: req.port_mirroring.clone(),
+                bandwidth: req.bandwidth.clone(),
+            },
+        )?;
+        hyperv.configure_adapter_port(&vm, &adapter, &port)?;
+        let adapter = find_network_adapter(&hyperv, &vm, &adapter.instance_id)?;
+        Ok(network_adapter_dto(adapter))
+    }
+
+    fn update_network_adapter(
+        &self,
+        name: &str,
+        adapter: &str,
+        req: &UpdateVmNetworkAdapterRequest,
+    ) -> BackendResult<VmNetworkAdapterDto> {
+        let hyperv = windows_hyperv::HyperV::connect()?;
+        let vm = hyperv.get_vm(name)?;
+        let adapter = find_network_adapter(&hyperv, &vm, adapter)?;
+
+        // WMI reports the MAC without separators
+        let same_mac = |mac: &str| {
+            adapter
+                .mac_address
+                .as_deref()
+                .is_some_and(|current| mac.replace([':', '-'], "").eq_ignore_ascii_case(current))
+        };
+        let mac_changes = req.dynamic_mac.is_some_and(|d| d != adapter.dynamic_mac)
+            || req.mac_address.as_deref().is_some_and(|mac| !same_mac(mac));
+        if mac_changes {
+            require_off(&vm, "change the MAC address of")?;
+            let mac = match (&req.mac_address, req.dynamic_mac) {
+                (Some(mac), _) => Some(mac.as_str()),
+                (None, Some(true)) => None,
+                // Pin the address the adapter has now
+                (None, _) => adapter.mac_address.as_deref(),
+            };
+            hyperv.set_network_adapter_mac(&adapter, mac)?;
+        }
+
+        let port_changes = req.vlan_id.is_some()
+            || req.mac_spoofing.is_some()
+            || req.dhcp_guard.is_some()
+            || req.router_guard.is_some()
+            || req.port_mirroring.is_some()
+            || req.bandwidth.is_some();
+        if port_changes {
+            let port = adapter_port_settings(&adapter, req)?;
+            hyperv.configure_adapter_port(&vm, &adapter, &port)?;
+        }
+
+        let adapter = find_network_adapter(&hyperv, &vm, &adapter.instance_id)?;
+        Ok(network_adapter_dto(adapter))
+    }
+
+    fn connect_network_adapter(
+        &self,
+        name: &str,
+        adapter: &str,
+        switch_name: Option<&str>,
+    ) -> BackendResult<VmNetworkAdapterDto> {
+        let hyperv = windows_hyperv::HyperV::connect()?;
+        let vm = hyperv.get_vm(name)?;
+        let adapter = find_network_adapter(&hyperv, &vm, adapter)?;
+        match switch_name {
+            Some(switch_name) => {
+                let switch = hyperv.get_switch(switch_name)?;
+                hyperv.connect_adapter_to_switch(&vm, &adapter, &switch)?;
+            }
+            None => hyperv.disconnect_adapter(&vm, &adapter)?,
+        }
+        let adapter = find_network_adapter(&hyperv, &vm, &adapter.instance_id)?;
+        Ok(network_adapter_dto(adapter))
+    }
+
+    fn remove_network_adapter(&self, name: &str, adapter: &str) -> BackendResult<()> {
+        let hyperv = windows_hyperv::HyperV::connect()?;
+        let vm = hyperv.get_vm(name)?;
+        let adapter = find_network_adapter(&hyperv, &vm, adapter)?;
+        require_hot_plug(&vm, "remove a network adapter from")?;
+        Ok(hyperv.remove_network_adapter(&vm, &adapter)?)
+    }
+
+    fn list_snapshots(&self, vm_name: &str) -> BackendResult<Vec<SnapshotDto>> {
+        let snapshots = HyperV::new()?.list_snapshots(vm_name)?;
+        Ok(snapshots.iter().map(snapshot_dto).collect())
+    }
+
+    fn get_snapshot(&self, vm_name: &str, snapshot: &str) -> BackendResult<SnapshotDto> {
+        let s = find_snapshot(&HyperV::new()?, vm_name, snapshot)?;
+        Ok(snapshot_dto(&s))
+    }
+
+    fn create_snapshot(
+        &self,
+        vm_name: &str,
+        req: &CreateSnapshotRequest,
+    ) -> BackendResult<SnapshotDto> {
+        let snapshot_type = match req.snapshot_type.as_deref() {
+            Some("Production") => SnapshotType::Production,
+            Some("ProductionOnly") => SnapshotType::Production,
+            _ => SnapshotType::Standard,
+        };
+        let s = HyperV::new()?.create_snapshot(vm_name, &req.name, snapshot_type)?;
+        Ok(snapshot_dto(&s))
+    }
+
+    fn apply_snapshot(
+        &self,
+        vm_name: &str,
+        snapshot: &str,
+        progress: ProgressFn<'_>,
+    ) -> BackendResult<()> {
+        progress(0, "Applying snapshot");
+        find_snapshot(&HyperV::new()?, vm_name, snapshot)?.apply()?;
+        progress(100, "Snapshot applied");
+        Ok(())
+    }
+
+    fn delete_snapshot(&self, vm_name: &str, snapshot: &str) -> BackendResult<()> {
+        Ok(find_snapshot(&HyperV::new()?, vm_name, snapshot)?.delete()?)
+    }
+
+    fn delete_snapshot_subtree(&self, vm_name: &str, snapshot: &str) -> BackendResult<()> {
+        Ok(find_snapshot(&HyperV::new()?, vm_name, snapshot)?.delete_subtree()?)
+    }
+
+    fn rename_snapshot(
+        &self,
+        vm_name: &str,
+        snapshot: &str,
+        new_name: &str,
+    ) -> BackendResult<SnapshotDto> {
+        let mut s = find_snapshot(&HyperV::new()?, vm_name, snapshot)?;
+        s.rename(new_name)?;
+        Ok(snapshot_dto(&s))
+    }
+
+    fn export_snapshot(
+        &self,
+        vm_name: &str,
+        snapshot: &str,
+        path: &str,
+        progress: ProgressFn<'_>,
+    ) -> BackendResult<()> {
+        progress(0, "Exporting snapshot");
+        find_snapshot(&HyperV::new()?, vm_name, snapshot)?.export(path)?;
+        progress(100, "Export completed");
+        Ok(())
+    }
+
+    fn current_snapshot_id(&self, vm_name: &str) -> BackendResult<Option<String>> {
+        Ok(HyperV::new()?.current_snapshot_id(vm_name)?)
+    }
+
+    fn list_switches(&self) -> BackendResult<Vec<SwitchDto>> {
+        let switches = HyperV::new()?.list_switches()?;
+        Ok(switches.iter().map(switch_dto).collect())
+    }
+
+    fn get_switch(&self, name: &str) -> BackendResult<SwitchDto> {
+        let s = HyperV::new()?.get_switch(name)?;
+        Ok(switch_dto(&s))
+    }
+
+    fn create_switch(&self, req: &CreateSwitchRequest) -> BackendResult<SwitchDto> {
+        let hv = HyperV::new()?;
+        let s = match req.switch_type.to_lowercase().as_str() {
+            "external" => {
+                let adapter = req.network_adapter.as_deref().ok_or_else(|| {
+                    hv::HvError::InvalidParameter(
+                        "network_adapter required for external switch".to_string(),
+                    )
+                })?;
+                hv.create_external_switch(
+                    &req.name,
+                    adapter,
+                    req.allow_management_os.unwrap_or(true),
+                )?
+            }
+            "internal" => hv.create_switch(&req.name, SwitchType::Internal)?,
+            "private" => hv.create_switch(&req.name, SwitchType::Private)?,
+            _ => {
+                return Err(hv::HvError::InvalidParameter("Invalid switch_type".to_string()).into())
+            }
+        };
+        Ok(switch_dto(&s))
+    }
+
+    fn delete_switch(&self, name: &str) -> BackendResult<()> {
+        Ok(HyperV::new()?.get_switch(name)?.delete()?)
+    }
+
+    fn get_vhd(&self, path: &str) -> BackendResult<VhdDto> {
+        let vhd = HyperV::new()?.get_vhd(path)?;
+        Ok(vhd_dto(&vhd, None))
+    }
+
+    fn create_vhd(
+        &self,
+        req: &CreateVhdRequest,
+        progress: ProgressFn<'_>,
+    ) -> BackendResult<VhdDto> {
+        progress(0, "Creating VHD");
+        let vhd_type = match req.vhd_type.as_deref() {
+            Some("Fixed") => VhdType::Fixed,
+            Some("Differencing") => VhdType::Differencing,
+            _ => VhdType::Dynamic,
+        };
+        let vhd =
+            HyperV::new()?.create_vhd(&req.path, req.size_bytes, vhd_type, req.block_size_bytes)?;
+        progress(100, "VHD created");
+        Ok(vhd_dto(&vhd, None))
+    }
+
+    fn resize_vhd(&self, path: &str, size_bytes: u64) -> BackendResult<()> {
+        Ok(HyperV::new()?.get_vhd(path)?.resize(size_bytes)?)
+    }
+
+    fn compact_vhd(&self, path: &str, progress: ProgressFn<'_>) -> BackendResult<()> {
+        progress(0, "Compacting VHD");
+        HyperV::new()?.get_vhd(path)?.compact()?;
+        progress(100, "VHD compacted");
+        Ok(())
+    }
+
+    fn mount_vhd(&self, path: &str) -> BackendResult<()> {
+        Ok(HyperV::new()?.get_vhd(path)?.mount(false)?)
+    }
+
+    fn dismount_vhd(&self, path: &str) -> BackendResult<()> {
+        Ok(HyperV::new()?.get_vhd(path)?.dismount()?)
+    }
+
+    fn create_differencing_vhd(&self, path: &str, parent_path: &str) -> BackendResult<VhdDto> {
+        let vhd = HyperV::new()?.create_differencing_vhd(path, parent_path)?;
+        Ok(vhd_dto(&vhd, Some(parent_path.to_string())))
+    }
+
+    fn initialize_vhd(&self, req: &InitVhdRequest) -> BackendResult<String> {
+        let partition_style = match req.partition_style.as_deref() {
+            Some("Mbr") | Some("MBR") => hv::PartitionStyle::Mbr,
+            _ => hv::PartitionStyle::Gpt,
+        };
+        let file_system = match req.file_system.as_deref() {
+            Some("ReFS") | Some("refs") => hv::FileSystem::ReFs,
+            Some("FAT32") | Some("fat32") => hv::FileSystem::Fat32,
+            Some("ExFAT") | Some("exfat") => hv::FileSystem::ExFat,
+            _ => hv::FileSystem::Ntfs,
+        };
+        Ok(HyperV::new()?.initialize_vhd(
+            &req.path,
+            partition_style,
+            file_system,
+            req.label.as_deref(),
+        )?)
+    }
+
+    fn windows_editions(&self, iso_path: &str) -> BackendResult<Vec<WindowsEditionDto>> {
+        let editions = HyperV::new()?.get_windows_editions(iso_path)?;
+        Ok(editions
+            .into_iter()
+            .map(|e| WindowsEditionDto {
+                index: e.index,
+                name: e.name,
+                description: e.description,
+                size_bytes: e.size_bytes,
+            })
+            .collect())
+    }
+
+    fn create_vhdx_from_iso(
+        &self,
+        req: &CreateVhdxFromIsoRequest,
+        progress: ProgressFn<'_>,
+    ) -> BackendResult<()> {
+        progress(0, "Applying Windows image");
+        HyperV::new()?.create_vhdx_from_iso(
+            &req.iso_path,
+            &req.vhdx_path,
+            req.size_gb,
+            req.edition_index,
+        )?;
+        progress(100, "VHDX created");
+        Ok(())
+    }
+
+    fn list_gpus(&self) -> BackendResult<Vec<GpuDto>> {
+        let gpus = HyperV::new()?.list_gpus()?;
+        Ok(gpus.into_iter().map(gpu_dto).collect())
+    }
+
+    fn list_partitionable_gpus(&self) -> BackendResult<Vec<GpuDto>> {
+        let gpus = HyperV::new()?.list_partitionable_gpus()?;
+        Ok(gpus.into_iter().map(gpu_dto).collect())
+    }
+
+    fn vm_gpu_adapters(&self, name: &str) -> BackendResult<Vec<GpuAdapterDto>> {
+        let adapters = HyperV::new()?.get_vm_gpu_adapters(name)?;
+        Ok(adapters
+            .into_iter()
+            .map(|a| GpuAdapterDto {
+                vm_name: a.vm_name,
+                instance_path: a.instance_path,
+                min_partition_vram: a.min_partition_vram.unwrap_or(0),
+                max_partition_vram: a.max_partition_vram.unwrap_or(0),
+                optimal_partition_vram: a.optimal_partition_vram.unwrap_or(0),
+            })
+            .collect())
+    }
+
+    fn add_gpu(&self, name: &str, instance_path: Option<&str>) -> BackendResult<()> {
+        Ok(HyperV::new()?.add_gpu_to_vm(name, instance_path)?)
+    }
+
+    fn remove_gpu(&self, name: &str) -> BackendResult<()> {
+        Ok(HyperV::new()?.remove_gpu_from_vm(name)?)
+    }
+
+    fn configure_gpu(&self, name: &str, low_mmio_gb: u32, high_mmio_gb: u32) -> BackendResult<()> {
+        Ok(HyperV::new()?.configure_vm_for_gpu(name, low_mmio_gb, high_mmio_gb)?)
+    }
+
+    fn dda_support(&self) -> BackendResult<DdaSupportDto> {
+        let info = HyperV::new()?.check_dda_support()?;
+        Ok(DdaSupportDto {
+            is_supported: info.is_supported,
+            is_server: info.is_server,
+            has_iommu: info.has_iommu,
+            cmdlet_available: info.cmdlet_available,
+            reason: info.reason,
+        })
+    }
+
+    fn assignable_devices(&self) -> BackendResult<Vec<AssignableDeviceDto>> {
+        let devices = HyperV::new()?.get_assignable_devices()?;
+        Ok(devices.into_iter().map(device_dto).collect())
+    }
+
+    fn device_location_path(&self, instance_id: &str) -> BackendResult<String> {
+        Ok(HyperV::new()?.get_device_location_path(instance_id)?)
+    }
+
+    fn dismount_device(&self, location_path: &str) -> BackendResult<()> {
+        Ok(HyperV::new()?.dismount_device(location_path)?)
+    }
+
+    fn mount_device(&self, location_path: &str) -> BackendResult<()> {
+        Ok(HyperV::new()?.mount_device(location_path)?)
+    }
+
+    fn vm_assigned_devices(&self, name: &str) -> BackendResult<Vec<AssignableDeviceDto>> {
+        let devices = HyperV::new()?.get_vm_assigned_devices(name)?;
+        Ok(devices.into_iter().map(device_dto).collect())
+    }
+
+    fn assign_device(&self, name: &str, location_path: &str) -> BackendResult<()> {
+        Ok(HyperV::new()?.assign_device_to_vm(name, location_path)?)
+    }
+
+    fn remove_device(&self, name: &str, location_path: &str) -> BackendResult<()> {
+        Ok(HyperV::new()?.remove_assigned_device(name, location_path)?)
+    }
+}
+
+// =============================================================================
+// Failover Cluster
+// =============================================================================
+
+/// Cluster backend using the `clus` crate
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NativeCluster;
+
+fn group_dto(g: &clus::Group) -> GroupDto {
+    let (state, owner) = g.state().ok().unwrap_or((GroupState::Unknown(0), None));
+    GroupDto {
+        name: g.name().to_string(),
+        state: format!("{:?}", state),
+        owner_node: owner,
+    }
+}
+
+fn resource_dto(r: &clus::Resource) -> ResourceDto {
+    let (state, owner) = r.state().ok().unwrap_or((ResourceState::Unknown(0), None));
+    ResourceDto {
+        name: r.name().to_string(),
+        state: format!("{:?}", state),
+        owner_node: owner,
+    }
+}
+
+fn node_dto(n: &clus::Node) -> NodeDto {
+    NodeDto {
+        name: n.name().to_string(),
+        state: format!("{:?}", n.state()),
+    }
+}
+
+impl ClusterBackend for NativeCluster {
+    fn cluster_name(&self, cluster: Option<&str>) -> BackendResult<String> {
+        Ok(Cluster::open(cluster)?.name()?)
+    }
+
+    fn list_nodes(&self, cluster: Option<&str>) -> BackendResult<Vec<NodeDto>> {
+        let nodes = Cluster::open(cluster)?.nodes()?;
+        Ok(nodes.iter().map(node_dto).collect())
+    }
+
+    fn get_node(&self, cluster: Option<&str>, name: &str) -> BackendResult<NodeDto> {
+        let node = Cluster::open(cluster)?.open_node(name)?;
+        Ok(node_dto(&node))
+    }
+
+    fn pause_node(&self, cluster: Option<&str>, name: &str) -> BackendResult<()> {
+        Ok(Cluster::open(cluster)?.open_node(name)?.pause()?)
+    }
+
+    fn resume_node(&self, cluster: Option<&str>, name: &str) -> BackendResult<()> {
+        Ok(Cluster::open(cluster)?.open_node(name)?.resume()?)
+    }
+
+    fn list_groups(&self, cluster: Option<&str>) -> BackendResult<Vec<GroupDto>> {
+        let groups = Cluster::open(cluster)?.groups()?;
+        Ok(groups.iter().map(group_dto).collect())
+    }
+
+    fn get_group(&self, cluster: Option<&str>, name: &str) -> BackendResult<GroupDto> {
+        let group = Cluster::open(cluster)?.open_group(name)?;
+        Ok(group_dto(&group))
+    }
+
+    fn group_online(&self, cluster: Option<&str>, name: &str) -> BackendResult<()> {
+        Ok(Cluster::open(cluster)?.open_group(name)?.online()?)
+    }
+
+    fn group_offline(&self, cluster: Option<&str>, name: &str) -> BackendResult<()> {
+        Ok(Cluster::open(cluster)?.open_group(name)?.offline()?)
+    }
+
+    fn move_group(
+        &self,
+        cluster: Option<&str>,
+        name: &str,
+        target_node: &str,
+        progress: ProgressFn<'_>,
+    ) -> BackendResult<()> {
+        let cluster = Cluster::open(cluster)?;
+        let group = cluster.open_group(name)?;
+        let node = cluster.open_node(target_node)?;
+        progress(0, "Moving group");
+        group.move_to(&node)?;
+        progress(100, "Group moved");
+        Ok(())
+    }
+
+    fn list_resources(&self, cluster: Option<&str>) -> BackendResult<Vec<ResourceDto>> {
+        let resources = Cluster::open(cluster)?.resources()?;
+        Ok(resources.iter().map(resource_dto).collect())
+    }
+
+    fn get_resource(&self, cluster: Option<&str>, name: &str) -> BackendResult<ResourceDto> {
+        let resource = Cluster::open(cluster)?.open_resource(name)?;
+        Ok(resource_dto(&resource))
+    }
+
+    fn resource_online(&self, cluster: Option<&str>, name: &str) -> BackendResult<()> {
+        Ok(Cluster::open(cluster)?.open_resource(name)?.online()?)
+    }
+
+    fn resource_offline(&self, cluster: Option<&str>, name: &str) -> BackendResult<()> {
+        Ok(Cluster::open(cluster)?.open_resource(name)?.offline()?)
+    }
+
+    fn list_csvs(&self, cluster: Option<&str>) -> BackendResult<Vec<CsvDto>> {
+        let resources = Cluster::open(cluster)?.resources()?;
+        Ok(resources
+            .iter()
+            .filter(|r| Csv::is_csv_resource(r).unwrap_or(false))
+            .map(|r| {
+                let dto = resource_dto(r);
+                CsvDto {
+                    name: dto.name,
+                    state: dto.state,
+                    owner_node: dto.owner_node,
+                    is_csv: true,
+                }
+            })
+            .collect())
+    }
+
+    fn is_path_on_csv(&self, path: &str) -> BackendResult<bool> {
+        Ok(Csv::is_path_on_csv(path))
+    }
+
+    fn set_csv_maintenance(
+        &self,
+        cluster: Option<&str>,
+        name: &str,
+        enable: bool,
+    ) -> BackendResult<()> {
+        let resource = Cluster::open(cluster)?.open_resource(name)?;
+        Ok(Csv::set_maintenance_mode(&resource, enable)?)
+    }
+}
@@ -0,0 +1,446 @@
+//! Backend used when the native implementation is unavailable
+//!
+//! Selected for `kind = "native"` on non-Windows hosts so that the router
+//! keeps building and every Hyper-V or cluster call reports `501`.
+
+use super::{BackendError, BackendResult, ClusterBackend, HypervBackend, ProgressFn};
+use crate::dto::*;
+
+/// Backend that rejects every operation as unsupported on this platform
+#[derive(Debug, Default, Clone, Copy)]
+pub struct UnsupportedBackend;
+
+fn hyperv_unsupported<T>() -> BackendResult<T> {
+    Err(BackendError::NotSupported(
+        "Hyper-V API only available on Windows".to_string(),
+    ))
+}
+
+fn cluster_unsupported<T>() -> BackendResult<T> {
+    Err(BackendError::NotSupported(
+        "Cluster API only available on Windows".to_string(),
+    ))
+}
+
+impl HypervBackend for UnsupportedBackend {
+    fn host_info(&self) -> BackendResult<HostInfoDto> {
+        hyperv_unsupported()
+    }
+
+    fn host_capabilities(&self) -> BackendResult<HostCapabilitiesDto> {
+        hyperv_unsupported()
+    }
+
+    fn list_network_adapters(&self) -> BackendResult<Vec<NetworkAdapterDto>> {
+        hyperv_unsupported()
+    }
+
+    fn list_vms(&self) -> BackendResult<Vec<VmDto>> {
+        hyperv_unsupported()
+    }
+
+    fn get_vm(&self, _name: &str) -> BackendResult<VmDto> {
+        hyperv_unsupported()
+    }
+
+    fn create_vm(&self, _req: &CreateVmRequest) -> BackendResult<VmDto> {
+        hyperv_unsupported()
+    }
+
+    fn update_vm(&self, _name: &str, _req: &UpdateVmRequest) -> BackendResult<VmDto> {
+        hyperv_unsupported()
+    }
+
+    fn delete_vm(&self, _name: &str) -> BackendResult<()> {
+        hyperv_unsupported()
+    }
+
+    fn start_vm(&self, _name: &str) -> BackendResult<()> {
+        hyperv_unsupported()
+    }
+
+    fn stop_vm(&self, _name: &str) -> BackendResult<()> {
+        hyperv_unsupported()
+    }
+
+    fn force_stop_vm(&self, _name: &str) -> BackendResult<()> {
+        hyperv_unsupported()
+    }
+
+    fn pause_vm(&self, _name: &str) -> BackendResult<()> {
+        hyperv_unsupported()
+    }
+
+    fn resume_vm(&self, _name: &str) -> BackendResult<()> {
+        hyperv_unsupported()
+    }
+
+    fn save_vm(&self, _name: &str) -> BackendResult<()> {
+        hyperv_unsupported()
+    }
+
+    fn reset_vm(&self, _name: &str) -> BackendResult<()> {
+        hyperv_unsupported()
+    }
+
+    fn export_vm(&self, _name: &str, _path: &str, _progress: ProgressFn<'_>) -> BackendResult<()> {
+        hyperv_unsupported()
+    }
+
+    fn upgrade_vm_version(&self, _name: &str) -> BackendResult<VmDto> {
+        hyperv_unsupported()
+    }
+
+    fn vm_processor(&self, _name: &str) -> BackendResult<VmProcessorDto> {
+        hyperv_unsupported()
+    }
+
+    fn set_vm_processor(
+        &self,
+        _name: &str,
+        _req: &SetVmProcessorRequest,
+    ) -> BackendResult<VmProcessorDto> {
+        hyperv_unsupported()
+    }
+
+    fn vm_security(&self, _name: &str) -> BackendResult<VmSecurityDto> {
+        hyperv_unsupported()
+    }
+
+    fn set_vm_security(
+        &self,
+        _name: &str,
+        _req: &SetVmSecurityRequest,
+    ) -> BackendResult<VmSecurityDto> {
+        hyperv_unsupported()
+    }
+
+    fn vm_disks(&self, _name: &str) -> BackendResult<Vec<DiskDto>> {
+        hyperv_unsupported()
+    }
+
+    fn attach_disk(&self, _name: &str, _vhd_path: &str) -> BackendResult<()> {
+        hyperv_unsupported()
+    }
+
+    fn detach_disk(
+        &self,
+        _name: &str,
+        _controller_number: u32,
+        _controller_location: u32,
+    ) -> BackendResult<()> {
+        hyperv_unsupported()
+    }
+
+    fn vm_dvd_drives(&self, _name: &str) -> BackendResult<Vec<DiskDto>> {
+        hyperv_unsupported()
+    }
+
+    fn mount_iso(&self, _name: &str, _iso_path: &str) -> BackendResult<()> {
+        hyperv_unsupported()
+    }
+
+    fn eject_iso(&self, _name: &str) -> BackendResult<()> {
+        hyperv_unsupported()
+    }
+
+    fn set_boot_order(&self, _name: &str, _devices: &[String]) -> BackendResult<()> {
+        hyperv_unsupported()
+    }
+
+    fn vm_network_adapters(&self, _name: &str) -> BackendResult<Vec<VmNetworkAdapterDto>> {
+        hyperv_unsupported()
+    }
+
+    fn add_network_adapter(
+        &self,
+        _name: &str,
+        _req: &AddVmNetworkAdapterRequest,
+    ) -> BackendResult<VmNetworkAdapterDto> {
+        hyperv_unsupported()
+    }
+
+    fn update_network_adapter(
+        &self,
+        _name: &str,
+        _adapter: &str,
+        _req: &UpdateVmNetworkAdapterRequest,
+    ) -> BackendResult<VmNetworkAdapterDto> {
+        hyperv_unsupported()
+    }
+
+    fn connect_network_adapter(
+        &self,
+        _name: &str,
+        _adapter: &str,
+        _switch_name: Option<&str>,
+    ) -> BackendResult<VmNetworkAdapterDto> {
+        hyperv_unsupported()
+    }
+
+    fn remove_network_adapter(&self, _name: &str, _adapter: &str) -> BackendResult<()> {
+        hyperv_unsupported()
+    }
+
+    fn list_snapshots(&self, _vm_name: &str) -> BackendResult<Vec<SnapshotDto>> {
+        hyperv_unsupported()
+    }
+
+    fn get_snapshot(&self, _vm_name: &str, _snapshot: &str) -> BackendResult<SnapshotDto> {
+        hyperv_unsupported()
+    }
+
+    fn create_snapshot(
+        &self,
+        _vm_name: &str,
+        _req: &CreateSnapshotRequest,
+    ) -> BackendResult<SnapshotDto> {
+        hyperv_unsupported()
+    }
+
+    fn apply_snapshot(
+        &self,
+        _vm_name: &str,
+        _snapshot: &str,
+        _progress: ProgressFn<'_>,
+    ) -> BackendResult<()> {
+        hyperv_unsupported()
+    }
+
+    fn delete_snapshot(&self, _vm_name: &str, _snapshot: &str) -> BackendResult<()> {
+        hyperv_unsupported()
+    }
+
+    fn delete_snapshot_subtree(&self, _vm_name: &str, _snapshot: &str) -> BackendResult<()> {
+        hyperv_unsupported()
+    }
+
+    fn rename_snapshot(
+        &self,
+        _vm_name: &str,
+        _snapshot: &str,
+        _new_name: &str,
+    ) -> BackendResult<SnapshotDto> {
+        hyperv_unsupported()
+    }
+
+    fn export_snapshot(
+        &self,
+        _vm_name: &str,
+        _snapshot: &str,
+        _path: &str,
+        _progress: ProgressFn<'_>,
+    ) -> BackendResult<()> {
+        hyperv_unsupported()
+    }
+
+    fn current_snapshot_id(&self, _vm_name: &str) -> BackendResult<Option<String>> {
+        hyperv_unsupported()
+    }
+
+    fn list_switches(&self) -> BackendResult<Vec<SwitchDto>> {
+        hyperv_unsupported()
+    }
+
+    fn get_switch(&self, _name: &str) -> BackendResult<SwitchDto> {
+        hyperv_unsupported()
+    }
+
+    fn create_switch(&self, _req: &CreateSwitchRequest) -> BackendResult<SwitchDto> {
+        hyperv_unsupported()
+    }
+
+    fn delete_switch(&self, _name: &str) -> BackendResult<()> {
+        hyperv_unsupported()
+    }
+
+    fn get_vhd(&self, _path: &str) -> BackendResult<VhdDto> {
+        hyperv_unsupported()
+    }
+
+    fn create_vhd(
+        &self,
+        _req: &CreateVhdRequest,
+        _progress: ProgressFn<'_>,
+    ) -> BackendResult<VhdDto> {
+        hyperv_unsupported()
+    }
+
+    fn resize_vhd(&self, _path: &str, _size_bytes: u64) -> BackendResult<()> {
+        hyperv_unsupported()
+    }
+
+    fn compact_vhd(&self, _path: &str, _progress: ProgressFn<'_>) -> BackendResult<()> {
+        hyperv_unsupported()
+    }
+
+    fn mount_vhd(&self, _path: &str) -> BackendResult<()> {
+        hyperv_unsupported()
+    }
+
+    fn dismount_vhd(&self, _path: &str) -> BackendResult<()> {
+        hyperv_unsupported()
+    }
+
+    fn create_differencing_vhd(&self, _path: &str, _parent_path: &str) -> BackendResult<VhdDto> {
+        hyperv_unsupported()
+    }
+
+    fn initialize_vhd(&self, _req: &InitVhdRequest) -> BackendResult<String> {
+        hyperv_unsupported()
+    }
+
+    fn windows_editions(&self, _iso_path: &str) -> BackendResult<Vec<WindowsEditionDto>> {
+        hyperv_unsupported()
+    }
+
+    fn create_vhdx_from_iso(
+        &self,
+        _req: &CreateVhdxFromIsoRequest,
+        _progress: ProgressFn<'_>,
+    ) -> BackendResult<()> {
+        hyperv_unsupported()
+    }
+
+    fn list_gpus(&self) -> BackendResult<Vec<GpuDto>> {
+        hyperv_unsupported()
+    }
+
+    fn list_partitionable_gpus(&self) -> BackendResult<Vec<GpuDto>> {
+        hyperv_unsupported()
+    }
+
+    fn vm_gpu_adapters(&self, _name: &str) -> BackendResult<Vec<GpuAdapterDto>> {
+        hyperv_unsupported()
+    }
+
+    fn add_gpu(&self, _name: &str, _instance_path: Option<&str>) -> BackendResult<()> {
+        hyperv_unsupported()
+    }
+
+    fn remove_gpu(&self, _name: &str) -> BackendResult<()> {
+        hyperv_unsupported()
+    }
+
+    fn configure_gpu(
+        &self,
+        _name: &str,
+        _low_mmio_gb: u32,
+        _high_mmio_gb: u32,
+    ) -> BackendResult<()> {
+        hyperv_unsupported()
+    }
+
+    fn dda_support(&self) -> BackendResult<DdaSupportDto> {
+        hyperv_unsupported()
+    }
+
+    fn assignable_devices(&self) -> BackendResult<Vec<AssignableDeviceDto>> {
+        hyperv_unsupported()
+    }
+
+    fn device_location_path(&self, _instance_id: &str) -> BackendResult<String> {
+        hyperv_unsupported()
+    }
+
+    fn dismount_device(&self, _location_path: &str) -> BackendResult<()> {
+        hyperv_unsupported()
+    }
+
+    fn mount_device(&self, _location_path: &str) -> BackendResult<()> {
+        hyperv_unsupported()
+    }
+
+    fn vm_assigned_devices(&self, _name: &str) -> BackendResult<Vec<AssignableDeviceDto>> {
+        hyperv_unsupported()
+    }
+
+    fn assign_device(&self, _name: &str, _location_path: &str) -> BackendResult<()> {
+        hyperv_unsupported()
+    }
+
+    fn remove_device(&self, _name: &str, _location_path: &str) -> BackendResult<()> {
+        hyperv_unsupported()
+    }
+}
+
+impl ClusterBackend for UnsupportedBackend {
+    fn cluster_name(&self, _cluster: Option<&str>) -> BackendResult<String> {
+        cluster_unsupported()
+    }
+
+    fn list_nodes(&self, _cluster: Option<&str>) -> BackendResult<Vec<NodeDto>> {
+        cluster_unsupported()
+    }
+
+    fn get_node(&self, _cluster: Option<&str>, _name: &str) -> BackendResult<NodeDto> {
+        cluster_unsupported()
+    }
+
+    fn pause_node(&self, _cluster: Option<&str>, _name: &str) -> BackendResult<()> {
+        cluster_unsupported()
+    }
+
+    fn resume_node(&self, _cluster: Option<&str>, _name: &str) -> BackendResult<()> {
+        cluster_unsupported()
+    }
+
+    fn list_groups(&self, _cluster: Option<&str>) -> BackendResult<Vec<GroupDto>> {
+        cluster_unsupported()
+    }
+
+    fn get_group(&self, _cluster: Option<&str>, _name: &str) -> BackendResult<GroupDto> {
+        cluster_unsupported()
+    }
+
+    fn group_online(&self, _cluster: Option<&str>, _name: &str) -> BackendResult<()> {
+        cluster_unsupported()
+    }
+
+    fn group_offline(&self, _cluster: Option<&str>, _name: &str) -> BackendResult<()> {
+        cluster_unsupported()
+    }
+
+    fn move_group(
+        &self,
+        _cluster: Option<&str>,
+        _name: &str,
+        _target_node: &str,
+        _progress: ProgressFn<'_>,
+    ) -> BackendResult<()> {
+        cluster_unsupported()
+    }
+
+    fn list_resources(&self, _cluster: Option<&str>) -> BackendResult<Vec<ResourceDto>> {
+        cluster_unsupported()
+    }
+
+    fn get_resource(&self, _cluster: Option<&str>, _name: &str) -> BackendResult<ResourceDto> {
+        cluster_unsupported()
+    }
+
+    fn resource_online(&self, _cluster: Option<&str>, _name: &str) -> BackendResult<()> {
+        cluster_unsupported()
+    }
+
+    fn resource_offline(&self, _cluster: Option<&str>, _name: &str) -> BackendResult<()> {
+        cluster_unsupported()
+    }
+
+    fn list_csvs(&self, _cluster: Option<&str>) -> BackendResult<Vec<CsvDto>> {
+        cluster_unsupported()
+    }
+
+    fn is_path_on_csv(&self, _path: &str) -> BackendResult<bool> {
+        cluster_unsupported()
+    }
+
+    fn set_csv_maintenance(
+        &self,
+        _cluster: Option<&str>,
+        _name: &str,
+        _enable: bool,
+    ) -> BackendResult<()> {
+        cluster_unsupported()
+    }
+}
@@ -0,0 +1,142 @@
+//! Bulk VM operations
+//!
+//! `POST /api/v1/hyperv/vms:batch` applies one action to many VMs as a single
+//! job. Up to `parallelism` VMs are worked on at once, each under its own VM
+//! lock, so a batch and individual calls on the same VMs still serialize.
+//! With the `stop` error policy, VMs not yet started when one fails are
+//! skipped; cancelling the job skips them too. The job's result is a
+//! [`BatchResultDto`] with one entry per VM.
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use serde_json::Value;
+use tokio::runtime::Handle;
+
+use crate::backend::HypervBackend;
+use crate::dto::{BatchAction, BatchErrorPolicy, BatchItemDto, BatchItemStatus, BatchResultDto};
+use crate::error::ApiError;
+use crate::jobs::JobContext;
+use crate::locks::{LockKey, LockManager};
+
+/// Parallelism when the request does not set one
+pub const DEFAULT_PARALLELISM: usize = 4;
+
+/// Largest accepted `parallelism`
+pub const MAX_PARALLELISM: usize = 32;
+
+/// A validated batch, ready to run as a job
+pub struct Batch {
+    pub vms: Vec<String>,
+    pub action: BatchAction,
+    pub parallelism: usize,
+    pub on_error: BatchErrorPolicy,
+}
+
+impl Batch {
+    /// Run on the blocking pool; `runtime` is used to wait for VM locks
+    pub fn run(
+        self,
+        ctx: &JobContext,
+        hyperv: &dyn HypervBackend,
+        locks: &Arc<LockManager>,
+        runtime: &Handle,
+    ) -> BatchResultDto {
+        let total = self.vms.len();
+        let next = AtomicUsize::new(0);
+        let done = AtomicUsize::new(0);
+        let failed = AtomicBool::new(false);
+        let results: Mutex<Vec<Option<BatchItemDto>>> = Mutex::new(vec![None; total]);
+
+        std::thread::scope(|scope| {
+            for _ in 0..self.parallelism.min(total) {
+                scope.spawn(|| loop {
+                    let index = next.fetch_add(1, Ordering::SeqCst);
+                    let Some(vm) = self.vms.get(index) else { break };
+                    let stopped =
+                        self.on_error == BatchErrorPolicy::Stop && failed.load(Ordering::SeqCst);
+                    let item = if stopped || ctx.is_cancelled() {
+                        outcome(vm, BatchItemStatus::Skipped, None, None)
+                    } else {
+                        match self.apply(vm, hyperv, locks, runtime) {
+                            Ok(result) => outcome(vm, BatchItemStatus::Succeeded, result, None),
+                            Err(e) => {
+                                failed.store(true, Ordering::SeqCst);
+                                outcome(vm, BatchItemStatus::Failed, None, Some(e))
+                            }
+                        }
+                    };
+                    results.lock().unwrap()[index] = Some(item);
+
+                    let finished = done.fetch_add(1, Ordering::SeqCst) + 1;
+                    ctx.report(
+                        (finished * 100 / total) as u32,
+                        &format!("{} of {} VMs done", finished, total),
+                    );
+                });
+            }
+        });
+
+        let results: Vec<BatchItemDto> = results
+            .into_inner()
+            .unwrap()
+            .into_iter()
+            .flatten()
+            .collect();
+        let count = |status| results.iter().filter(|r| r.status == status).count();
+        BatchResultDto {
+            action: self.action.as_str().to_string(),
+            succeeded: count(BatchItemStatus::Succeeded),
+            failed: count(BatchItemStatus::Failed),
+            skipped: count(BatchItemStatus::Skipped),
+            results,
+        }
+    }
+
+    /// Lock `vm` and apply the action to it
+    fn apply(
+        &self,
+        vm: &str,
+        hyperv: &dyn HypervBackend,
+        locks: &Arc<LockManager>,
+        runtime: &Handle,
+    ) -> Result<Option<Value>, ApiError> {
+        let _guard = runtime.block_on(locks.acquire([LockKey::vm(vm)]))?;
+        let no_progress = |_: u32, _: &str| {};
+        match &self.action {
+            BatchAction::Start => hyperv.start_vm(vm)?,
+            BatchAction::Stop => hyperv.stop_vm(vm)?,
+            BatchAction::ForceStop => hyperv.force_stop_vm(vm)?,
+            BatchAction::Pause => hyperv.pause_vm(vm)?,
+            BatchAction::Resume => hyperv.resume_vm(vm)?,
+            BatchAction::Save => hyperv.save_vm(vm)?,
+            BatchAction::Reset => hyperv.reset_vm(vm)?,
+            BatchAction::Snapshot(req) => {
+                let snapshot = hyperv.create_snapshot(vm, req)?;
+                return Ok(Some(
+                    serde_json::to_value(snapshot).expect("DTOs serialize to JSON"),
+                ));
+            }
+            BatchAction::ApplySnapshot(req) => {
+                hyperv.apply_snapshot(vm, &req.name, &no_progress)?
+            }
+            BatchAction::DeleteSnapshot(req) => hyperv.delete_snapshot(vm, &req.name)?,
+        }
+        Ok(None)
+    }
+}
+
+fn outcome(
+    vm: &str,
+    status: BatchItemStatus,
+    result: Option<Value>,
+    error: Option<ApiError>,
+) -> BatchItemDto {
+    BatchItemDto {
+        vm: vm.to_string(),
+        status,
+        code: error.as_ref().map(|e| e.code),
+        error: error.map(|e| e.message),
+        result,
+    }
+}
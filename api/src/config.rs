@@ -1,12 +1,27 @@
 //! Configuration module for the API server
 //!
-//! Supports loading configuration from a TOML file.
+//! [`ConfigLoader`] builds the configuration in layers, later layers winning:
+//! 1. built-in defaults
+//! 2. the TOML file (`--config`, `NODEAGENT_CONFIG`, or `config.toml`)
+//! 3. `NODEAGENT_*` environment variables, `__` separating nested keys:
+//!    `NODEAGENT_SERVER__PORT=8080` sets `[server] port`
+//! 4. command-line flags: `--port`, `--log-level`
+//!
+//! The result is checked by [`Config::validate`], which reports every problem
+//! at once instead of stopping at the first.
 
 use serde::Deserialize;
-use std::path::Path;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+
+use axum::http::{HeaderName, HeaderValue, Method};
+use toml::{Table, Value};
+
+use crate::auth::Role;
+use crate::dto::EventKind;
 
 /// Server configuration
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Default, Deserialize, Clone)]
 pub struct Config {
     /// Server settings
     #[serde(default)]
@@ -19,6 +34,58 @@ pub struct Config {
     /// Windows service settings
     #[serde(default)]
     pub service: ServiceConfig,
+
+    /// Hyper-V and cluster backend settings
+    #[serde(default)]
+    pub backend: BackendConfig,
+
+    /// Background job settings
+    #[serde(default)]
+    pub jobs: JobsConfig,
+
+    /// Authentication settings
+    #[serde(default)]
+    pub auth: AuthConfig,
+
+    /// Prometheus metrics settings
+    #[serde(default)]
+    pub metrics: MetricsConfig,
+
+    /// `/api/v1/events` settings
+    #[serde(default)]
+    pub events: EventsConfig,
+
+    /// Audit log settings
+    #[serde(default)]
+    pub audit: AuditConfig,
+
+    /// Per-resource operation locking
+    #[serde(default)]
+    pub locks: LocksConfig,
+
+    /// `Idempotency-Key` handling for POST requests
+    #[serde(default)]
+    pub idempotency: IdempotencyConfig,
+
+    /// Cross-origin resource sharing; reloaded without restart
+    #[serde(default)]
+    pub cors: CorsConfig,
+
+    /// Per-client request rate limits; reloaded without restart
+    #[serde(default)]
+    pub rate_limit: RateLimitConfig,
+
+    /// `/health/live` and `/health/ready` checks
+    #[serde(default)]
+    pub health: HealthConfig,
+
+    /// Outbound webhook notifications
+    #[serde(default)]
+    pub webhooks: WebhooksConfig,
+
+    /// Fronting the node agents of a cluster
+    #[serde(default)]
+    pub aggregator: AggregatorConfig,
 }
 
 /// Windows service configuration
@@ -41,6 +108,438 @@ pub struct ServiceConfig {
     pub install_path: String,
 }
 
+/// Backend selection
+#[derive(Debug, Default, Deserialize, Clone)]
+pub struct BackendConfig {
+    /// Backend implementation (default: native)
+    #[serde(default)]
+    pub kind: BackendKind,
+
+    /// Milliseconds long-running operations take on the fake backend
+    /// (default: 0)
+    #[serde(default)]
+    pub operation_delay_ms: u64,
+}
+
+/// Available backend implementations
+#[derive(Debug, Default, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BackendKind {
+    /// Windows Hyper-V and Failover Cluster APIs
+    #[default]
+    Native,
+    /// Stateful in-memory simulation for development and testing
+    Fake,
+}
+
+/// Background job configuration
+#[derive(Debug, Deserialize, Clone)]
+pub struct JobsConfig {
+    /// Number of finished jobs kept for `/api/v1/jobs` (default: 100)
+    #[serde(default = "default_job_history_limit")]
+    pub history_limit: usize,
+}
+
+/// Prometheus metrics configuration
+#[derive(Debug, Deserialize, Clone)]
+pub struct MetricsConfig {
+    /// Serve `GET /metrics` (default: true)
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+
+    /// Prefix prepended to every metric name (default: nodeagent)
+    #[serde(default = "default_metrics_prefix")]
+    pub prefix: String,
+
+    /// HTTP request latency histogram buckets in seconds
+    #[serde(default = "default_request_duration_buckets")]
+    pub request_duration_buckets: Vec<f64>,
+
+    /// Backend call latency histogram buckets in seconds
+    #[serde(default = "default_backend_duration_buckets")]
+    pub backend_duration_buckets: Vec<f64>,
+
+    /// Metric names, without the prefix
+    #[serde(default)]
+    pub names: MetricNames,
+}
+
+/// Metric names; counters get a `_total` suffix when exported
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct MetricNames {
+    /// Request counter (default: http_requests)
+    pub http_requests: String,
+
+    /// Request latency histogram (default: http_request_duration_seconds)
+    pub http_request_duration: String,
+
+    /// In-flight request gauge (default: http_requests_in_flight)
+    pub http_requests_in_flight: String,
+
+    /// Backend call latency histogram (default: backend_call_duration_seconds)
+    pub backend_call_duration: String,
+
+    /// Failed backend call counter (default: backend_call_errors)
+    pub backend_call_errors: String,
+}
+
+/// State change event configuration
+#[derive(Debug, Deserialize, Clone)]
+pub struct EventsConfig {
+    /// Milliseconds between inventory polls while clients are subscribed (default: 2000)
+    #[serde(default = "default_events_poll_interval")]
+    pub poll_interval_ms: u64,
+
+    /// Number of recent events kept for `Last-Event-ID` resume (default: 1000)
+    #[serde(default = "default_events_history_limit")]
+    pub history_limit: usize,
+}
+
+/// Per-resource locking of mutating operations
+#[derive(Debug, Deserialize, Clone)]
+pub struct LocksConfig {
+    /// Milliseconds a request waits for a busy VM, switch, VHD or cluster
+    /// object before answering 409; 0 answers immediately (default: 0)
+    #[serde(default)]
+    pub wait_timeout_ms: u64,
+
+    /// Mutating backend operations allowed at once, including running jobs;
+    /// 0 means unlimited (default: 16)
+    #[serde(default = "default_max_concurrent_operations")]
+    pub max_concurrent_operations: usize,
+}
+
+/// `Idempotency-Key` replay configuration
+#[derive(Debug, Deserialize, Clone)]
+pub struct IdempotencyConfig {
+    /// Honour the `Idempotency-Key` header on POST requests (default: true)
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+
+    /// Seconds a key and its response are remembered (default: 86400)
+    #[serde(default = "default_idempotency_ttl")]
+    pub ttl_secs: u64,
+
+    /// Keys remembered at once; the oldest completed key is evicted first
+    /// (default: 10000)
+    #[serde(default = "default_idempotency_max_keys")]
+    pub max_keys: usize,
+}
+
+/// Cross-origin resource sharing policy
+#[derive(Debug, Deserialize, Clone)]
+pub struct CorsConfig {
+    /// Origins allowed to call the API, e.g. "https://portal.contoso.com";
+    /// "*" allows any (default: ["*"])
+    #[serde(default = "default_any")]
+    pub allowed_origins: Vec<String>,
+
+    /// Methods allowed in cross-origin requests; "*" allows any (default: ["*"])
+    #[serde(default = "default_any")]
+    pub allowed_methods: Vec<String>,
+
+    /// Request headers allowed in cross-origin requests; "*" allows any
+    /// (default: ["*"])
+    #[serde(default = "default_any")]
+    pub allowed_headers: Vec<String>,
+
+    /// Seconds browsers may cache a preflight response; 0 omits the header
+    /// (default: 0)
+    #[serde(default)]
+    pub max_age_secs: u64,
+}
+
+/// Per-client rate limiting of `/api/v1`
+#[derive(Debug, Deserialize, Clone)]
+pub struct RateLimitConfig {
+    /// Answer `429 Too Many Requests` above the limit (default: false)
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Sustained requests per second per client (default: 50)
+    #[serde(default = "default_requests_per_second")]
+    pub requests_per_second: f64,
+
+    /// Requests a client may send at once before the sustained rate applies
+    /// (default: 100)
+    #[serde(default = "default_burst")]
+    pub burst: u32,
+}
+
+/// Health and readiness probe settings
+#[derive(Debug, Deserialize, Clone)]
+pub struct HealthConfig {
+    /// Longest a probe waits for its checks (default: 2000)
+    #[serde(default = "default_health_timeout")]
+    pub timeout_ms: u64,
+
+    /// Reuse a check's result for this long (default: 5)
+    #[serde(default = "default_health_cache_ttl")]
+    pub cache_ttl_secs: u64,
+
+    /// Fail `disk` below this much free space (default: 10 GiB)
+    #[serde(default = "default_min_free_disk")]
+    pub min_free_disk_bytes: u64,
+
+    /// Paths checked by `disk`; empty checks the host's `vm_path` and
+    /// `vhd_path` (default: empty)
+    #[serde(default)]
+    pub disk_paths: Vec<String>,
+
+    /// Fail `jobs` when this many background jobs are running (default: 32)
+    #[serde(default = "default_max_active_jobs")]
+    pub max_active_jobs: usize,
+
+    /// Checks whose failure is reported as a warning and does not make the
+    /// node unready, e.g. `["cluster"]` on a standalone host (default: empty)
+    #[serde(default)]
+    pub optional: Vec<String>,
+}
+
+/// Outbound webhook settings
+#[derive(Debug, Deserialize, Clone)]
+pub struct WebhooksConfig {
+    /// Subscriptions from the file; reloaded without restart. More can be
+    /// added through `/api/v1/webhooks` (default: empty)
+    #[serde(default)]
+    pub subscriptions: Vec<WebhookConfig>,
+
+    /// JSON file holding subscriptions added through the API, the retry
+    /// queue and dead letters; kept in memory only when unset
+    #[serde(default)]
+    pub state_path: Option<String>,
+
+    /// Attempts per delivery before it is dead-lettered (default: 8)
+    #[serde(default = "default_webhook_max_attempts")]
+    pub max_attempts: u32,
+
+    /// Wait before the first retry, doubled after each failure (default: 1000)
+    #[serde(default = "default_webhook_initial_backoff")]
+    pub initial_backoff_ms: u64,
+
+    /// Longest wait between retries (default: 300000)
+    #[serde(default = "default_webhook_max_backoff")]
+    pub max_backoff_ms: u64,
+
+    /// Per-attempt request timeout (default: 10000)
+    #[serde(default = "default_webhook_timeout")]
+    pub timeout_ms: u64,
+
+    /// Finished deliveries kept for `/api/v1/webhooks/deliveries`
+    /// (default: 1000)
+    #[serde(default = "default_webhook_history_limit")]
+    pub history_limit: usize,
+}
+
+/// One webhook subscription
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct WebhookConfig {
+    /// Unique name, used in `/api/v1/webhooks/{name}`
+    pub name: String,
+
+    /// `http` or `https` URL each event is POSTed to
+    pub url: String,
+
+    /// Key for the `X-Webhook-Signature` HMAC-SHA256
+    pub secret: String,
+
+    /// Event kinds delivered; all kinds when empty (default: empty)
+    #[serde(default)]
+    pub events: Vec<EventKind>,
+
+    /// Only events for this VM (default: all)
+    #[serde(default)]
+    pub vm: Option<String>,
+}
+
+/// Aggregator mode: one API in front of every node agent of a cluster
+#[derive(Debug, Deserialize, Clone)]
+pub struct AggregatorConfig {
+    /// Answer `/api/v1/hyperv` and `/api/v1/jobs` from the peers instead of
+    /// the local backend (default: false)
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Node agents to fan out to (default: empty)
+    #[serde(default)]
+    pub peers: Vec<PeerConfig>,
+
+    /// Also take peers from the cluster's node list (default: false)
+    #[serde(default)]
+    pub discover: bool,
+
+    /// Agent URL of a discovered node, `{node}` standing for the node name
+    /// (default: https://{node}:6001)
+    #[serde(default = "default_aggregator_node_url")]
+    pub node_url: String,
+
+    /// Seconds the discovered node list is reused (default: 60)
+    #[serde(default = "default_aggregator_discovery_interval")]
+    pub discovery_interval_secs: u64,
+
+    /// API key sent to peers; the caller's `X-API-Key` or `Authorization`
+    /// header is passed on when unset
+    #[serde(default)]
+    pub api_key: Option<String>,
+
+    /// PEM file of an additional CA trusted for peer certificates
+    #[serde(default)]
+    pub ca_path: Option<String>,
+
+    /// Per-node timeout of reads sent to every node (default: 5000)
+    #[serde(default = "default_aggregator_timeout")]
+    pub timeout_ms: u64,
+
+    /// Timeout of requests sent to a single node (default: 120000)
+    #[serde(default = "default_aggregator_forward_timeout")]
+    pub forward_timeout_ms: u64,
+}
+
+/// One statically configured node agent
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct PeerConfig {
+    /// Node name, used for `node` tags and the `node` query parameter
+    pub name: String,
+
+    /// Base URL of the agent, e.g. `https://node1:6001`
+    pub url: String,
+}
+
+/// Audit log configuration
+#[derive(Debug, Deserialize, Clone)]
+pub struct AuditConfig {
+    /// Record POST, PUT, PATCH and DELETE requests (default: true)
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+
+    /// Where records are written (default: memory)
+    #[serde(default)]
+    pub sink: AuditSink,
+
+    /// JSON-lines file for the `file` sink
+    #[serde(default = "default_audit_path")]
+    pub path: String,
+
+    /// Rotate the file once it reaches this size (default: 10 MiB)
+    #[serde(default = "default_audit_max_size")]
+    pub max_size_bytes: u64,
+
+    /// Rotate the file once it is this old (default: 24)
+    #[serde(default = "default_audit_max_age")]
+    pub max_age_hours: u64,
+
+    /// Rotated files kept besides the current one (default: 30)
+    #[serde(default = "default_audit_max_files")]
+    pub max_files: usize,
+
+    /// Records kept by the `memory` sink (default: 1000)
+    #[serde(default = "default_audit_memory_limit")]
+    pub memory_limit: usize,
+
+    /// Request body fields whose values are replaced with `***`; matched
+    /// case-insensitively as substrings of the field name
+    #[serde(default = "default_audit_redact_fields")]
+    pub redact_fields: Vec<String>,
+}
+
+/// Audit record destinations
+#[derive(Debug, Default, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditSink {
+    /// Rotating JSON-lines file; queryable
+    File,
+    /// One JSON line per record on stdout, for log collectors; not queryable
+    Stdout,
+    /// Bounded in-memory buffer; queryable, lost on restart
+    #[default]
+    Memory,
+}
+
+/// Authentication configuration
+#[derive(Debug, Default, Deserialize, Clone)]
+pub struct AuthConfig {
+    /// Require credentials on every `/api/v1` route (default: false)
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Static API keys accepted in the `X-API-Key` header
+    #[serde(default)]
+    pub api_keys: Vec<ApiKeyConfig>,
+
+    /// JWT bearer token verification
+    #[serde(default)]
+    pub jwt: Option<JwtConfig>,
+
+    /// Roles granted to mTLS client certificates by subject
+    #[serde(default)]
+    pub client_certs: Vec<ClientCertConfig>,
+}
+
+/// Role granted to a client certificate subject
+#[derive(Debug, Deserialize, Clone)]
+pub struct ClientCertConfig {
+    /// Subject distinguished name, e.g. "CN=ops-automation, O=Contoso"
+    pub subject: String,
+
+    /// Role granted to the certificate
+    pub role: Role,
+}
+
+/// A named static API key
+#[derive(Debug, Deserialize, Clone)]
+pub struct ApiKeyConfig {
+    /// Name reported as the principal for requests using this key
+    pub name: String,
+
+    /// Key value
+    pub key: String,
+
+    /// Role granted to the key
+    pub role: Role,
+}
+
+/// JWT bearer token configuration
+#[derive(Debug, Deserialize, Clone)]
+pub struct JwtConfig {
+    /// Signing algorithm (default: HS256)
+    #[serde(default)]
+    pub algorithm: JwtAlgorithm,
+
+    /// Shared secret for HS256
+    #[serde(default)]
+    pub secret: Option<String>,
+
+    /// PEM-encoded RSA public key file for RS256
+    #[serde(default)]
+    pub public_key_path: Option<String>,
+
+    /// Required `iss` claim
+    #[serde(default)]
+    pub issuer: Option<String>,
+
+    /// Required `aud` claim
+    #[serde(default)]
+    pub audience: Option<String>,
+
+    /// Claim holding the role or list of roles (default: role)
+    #[serde(default = "default_role_claim")]
+    pub role_claim: String,
+
+    /// Allowed clock skew in seconds for `exp`/`nbf` (default: 60)
+    #[serde(default = "default_jwt_leeway")]
+    pub leeway_secs: u64,
+}
+
+/// Supported JWT signing algorithms
+#[derive(Debug, Default, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[allow(clippy::upper_case_acronyms)]
+pub enum JwtAlgorithm {
+    #[default]
+    HS256,
+    RS256,
+}
+
 /// Server-specific configuration
 #[derive(Debug, Deserialize, Clone)]
 pub struct ServerConfig {
@@ -51,6 +550,56 @@ pub struct ServerConfig {
     /// Port to listen on (default: 3000)
     #[serde(default = "default_port")]
     pub port: u16,
+
+    /// HTTPS settings; plain HTTP when absent
+    #[serde(default)]
+    pub tls: Option<TlsConfig>,
+
+    /// Seconds between checks of the configuration file for changes;
+    /// 0 disables hot reload (default: 5)
+    #[serde(default = "default_config_reload_interval")]
+    pub config_reload_interval_secs: u64,
+
+    /// Seconds to wait for in-flight requests and background jobs on
+    /// shutdown (default: 30)
+    #[serde(default = "default_shutdown_timeout")]
+    pub shutdown_timeout_secs: u64,
+}
+
+/// TLS configuration
+#[derive(Debug, Deserialize, Clone)]
+pub struct TlsConfig {
+    /// PEM certificate chain
+    pub cert_path: String,
+
+    /// PEM private key (PKCS#8, PKCS#1 or SEC1)
+    pub key_path: String,
+
+    /// PEM CA bundle used to verify client certificates (enables mTLS)
+    #[serde(default)]
+    pub client_ca_path: Option<String>,
+
+    /// Reject clients without a certificate when `client_ca_path` is set (default: true)
+    #[serde(default = "default_true")]
+    pub require_client_cert: bool,
+
+    /// Minimum protocol version (default: "1.2")
+    #[serde(default)]
+    pub min_version: TlsVersion,
+
+    /// Seconds between checks for rotated certificate files (default: 60)
+    #[serde(default = "default_tls_reload_interval")]
+    pub reload_interval_secs: u64,
+}
+
+/// Supported minimum TLS protocol versions
+#[derive(Debug, Default, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum TlsVersion {
+    #[default]
+    #[serde(rename = "1.2")]
+    Tls12,
+    #[serde(rename = "1.3")]
+    Tls13,
 }
 
 /// Logging configuration
@@ -65,64 +614,364 @@ fn default_host() -> String {
     "0.0.0.0".to_string()
 }
 
-fn default_port() -> u16 {
-    6001
+fn default_port() -> u16 {
+    6001
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_tls_reload_interval() -> u64 {
+    60
+}
+
+fn default_config_reload_interval() -> u64 {
+    5
+}
+
+fn default_shutdown_timeout() -> u64 {
+    30
+}
+
+fn default_any() -> Vec<String> {
+    vec!["*".to_string()]
+}
+
+fn default_requests_per_second() -> f64 {
+    50.0
+}
+
+fn default_burst() -> u32 {
+    100
+}
+
+fn default_health_timeout() -> u64 {
+    2000
+}
+
+fn default_health_cache_ttl() -> u64 {
+    5
+}
+
+fn default_min_free_disk() -> u64 {
+    10 * 1024 * 1024 * 1024
+}
+
+fn default_max_active_jobs() -> usize {
+    32
+}
+
+fn default_webhook_max_attempts() -> u32 {
+    8
+}
+
+fn default_webhook_initial_backoff() -> u64 {
+    1000
+}
+
+fn default_webhook_max_backoff() -> u64 {
+    5 * 60 * 1000
+}
+
+fn default_webhook_timeout() -> u64 {
+    10_000
+}
+
+fn default_webhook_history_limit() -> usize {
+    1000
+}
+
+fn default_aggregator_node_url() -> String {
+    "https://{node}:6001".to_string()
+}
+
+fn default_aggregator_discovery_interval() -> u64 {
+    60
+}
+
+fn default_aggregator_timeout() -> u64 {
+    5000
+}
+
+fn default_aggregator_forward_timeout() -> u64 {
+    120_000
+}
+
+fn default_log_level() -> String {
+    "api=info,tower_http=info".to_string()
+}
+
+fn default_job_history_limit() -> usize {
+    crate::jobs::DEFAULT_HISTORY_LIMIT
+}
+
+fn default_metrics_prefix() -> String {
+    "nodeagent".to_string()
+}
+
+fn default_request_duration_buckets() -> Vec<f64> {
+    vec![
+        0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+    ]
+}
+
+fn default_backend_duration_buckets() -> Vec<f64> {
+    vec![
+        0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0, 10.0, 30.0, 60.0, 300.0,
+    ]
+}
+
+fn default_events_poll_interval() -> u64 {
+    2000
+}
+
+fn default_events_history_limit() -> usize {
+    1000
+}
+
+fn default_max_concurrent_operations() -> usize {
+    16
+}
+
+fn default_idempotency_ttl() -> u64 {
+    24 * 60 * 60
+}
+
+fn default_idempotency_max_keys() -> usize {
+    10_000
+}
+
+fn default_audit_path() -> String {
+    r"C:\ProgramData\nodeagent\audit.jsonl".to_string()
+}
+
+fn default_audit_max_size() -> u64 {
+    10 * 1024 * 1024
+}
+
+fn default_audit_max_age() -> u64 {
+    24
+}
+
+fn default_audit_max_files() -> usize {
+    30
+}
+
+fn default_audit_memory_limit() -> usize {
+    1000
+}
+
+fn default_audit_redact_fields() -> Vec<String> {
+    ["password", "secret", "token", "key", "credential"]
+        .map(String::from)
+        .to_vec()
+}
+
+fn default_role_claim() -> String {
+    "role".to_string()
+}
+
+fn default_jwt_leeway() -> u64 {
+    60
+}
+
+fn default_service_name() -> String {
+    "nodeagent".to_string()
+}
+
+fn default_display_name() -> String {
+    "Node Agent".to_string()
+}
+
+fn default_description() -> String {
+    "REST API for Windows Failover Cluster and Hyper-V management".to_string()
+}
+
+fn default_install_path() -> String {
+    r"C:\Program Files\azurestack\nodeagent".to_string()
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            host: default_host(),
+            port: default_port(),
+            tls: None,
+            config_reload_interval_secs: default_config_reload_interval(),
+            shutdown_timeout_secs: default_shutdown_timeout(),
+        }
+    }
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            level: default_log_level(),
+        }
+    }
+}
+
+impl Default for JobsConfig {
+    fn default() -> Self {
+        Self {
+            history_limit: default_job_history_limit(),
+        }
+    }
+}
+
+impl Default for LocksConfig {
+    fn default() -> Self {
+        Self {
+            wait_timeout_ms: 0,
+            max_concurrent_operations: default_max_concurrent_operations(),
+        }
+    }
+}
+
+impl Default for IdempotencyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            ttl_secs: default_idempotency_ttl(),
+            max_keys: default_idempotency_max_keys(),
+        }
+    }
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        Self {
+            allowed_origins: default_any(),
+            allowed_methods: default_any(),
+            allowed_headers: default_any(),
+            max_age_secs: 0,
+        }
+    }
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            requests_per_second: default_requests_per_second(),
+            burst: default_burst(),
+        }
+    }
 }
 
-fn default_log_level() -> String {
-    "api=info,tower_http=info".to_string()
+impl Default for HealthConfig {
+    fn default() -> Self {
+        Self {
+            timeout_ms: default_health_timeout(),
+            cache_ttl_secs: default_health_cache_ttl(),
+            min_free_disk_bytes: default_min_free_disk(),
+            disk_paths: Vec::new(),
+            max_active_jobs: default_max_active_jobs(),
+            optional: Vec::new(),
+        }
+    }
 }
 
-fn default_service_name() -> String {
-    "nodeagent".to_string()
+impl Default for WebhooksConfig {
+    fn default() -> Self {
+        Self {
+            subscriptions: Vec::new(),
+            state_path: None,
+            max_attempts: default_webhook_max_attempts(),
+            initial_backoff_ms: default_webhook_initial_backoff(),
+            max_backoff_ms: default_webhook_max_backoff(),
+            timeout_ms: default_webhook_timeout(),
+            history_limit: default_webhook_history_limit(),
+        }
+    }
 }
 
-fn default_display_name() -> String {
-    "Node Agent".to_string()
+impl Default for AggregatorConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            peers: Vec::new(),
+            discover: false,
+            node_url: default_aggregator_node_url(),
+            discovery_interval_secs: default_aggregator_discovery_interval(),
+            api_key: None,
+            ca_path: None,
+            timeout_ms: default_aggregator_timeout(),
+            forward_timeout_ms: default_aggregator_forward_timeout(),
+        }
+    }
 }
 
-fn default_description() -> String {
-    "REST API for Windows Failover Cluster and Hyper-V management".to_string()
+impl Default for AuditConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            sink: AuditSink::default(),
+            path: default_audit_path(),
+            max_size_bytes: default_audit_max_size(),
+            max_age_hours: default_audit_max_age(),
+            max_files: default_audit_max_files(),
+            memory_limit: default_audit_memory_limit(),
+            redact_fields: default_audit_redact_fields(),
+        }
+    }
 }
 
-fn default_install_path() -> String {
-    r"C:\Program Files\azurestack\nodeagent".to_string()
+impl Default for EventsConfig {
+    fn default() -> Self {
+        Self {
+            poll_interval_ms: default_events_poll_interval(),
+            history_limit: default_events_history_limit(),
+        }
+    }
 }
 
-impl Default for ServerConfig {
+impl Default for MetricsConfig {
     fn default() -> Self {
         Self {
-            host: default_host(),
-            port: default_port(),
+            enabled: true,
+            prefix: default_metrics_prefix(),
+            request_duration_buckets: default_request_duration_buckets(),
+            backend_duration_buckets: default_backend_duration_buckets(),
+            names: MetricNames::default(),
         }
     }
 }
 
-impl Default for LoggingConfig {
+impl Default for MetricNames {
     fn default() -> Self {
         Self {
-            level: default_log_level(),
+            http_requests: "http_requests".to_string(),
+            http_request_duration: "http_request_duration_seconds".to_string(),
+            http_requests_in_flight: "http_requests_in_flight".to_string(),
+            backend_call_duration: "backend_call_duration_seconds".to_string(),
+            backend_call_errors: "backend_call_errors".to_string(),
         }
     }
 }
 
-impl Default for ServiceConfig {
+impl Default for JwtConfig {
     fn default() -> Self {
         Self {
-            name: default_service_name(),
-            display_name: default_display_name(),
-            description: default_description(),
-            install_path: default_install_path(),
+            algorithm: JwtAlgorithm::default(),
+            secret: None,
+            public_key_path: None,
+            issuer: None,
+            audience: None,
+            role_claim: default_role_claim(),
+            leeway_secs: default_jwt_leeway(),
         }
     }
 }
 
-impl Default for Config {
+impl Default for ServiceConfig {
     fn default() -> Self {
         Self {
-            server: ServerConfig::default(),
-            logging: LoggingConfig::default(),
-            service: ServiceConfig::default(),
+            name: default_service_name(),
+            display_name: default_display_name(),
+            description: default_description(),
+            install_path: default_install_path(),
         }
     }
 }
@@ -150,6 +999,491 @@ impl Config {
     pub fn socket_addr(&self) -> String {
         format!("{}:{}", self.server.host, self.server.port)
     }
+
+    /// Check values that parse but cannot work, reporting all of them
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut errors = Vec::new();
+
+        if self.server.host.parse::<IpAddr>().is_err() {
+            errors.push(format!(
+                "server.host: '{}' is not an IP address",
+                self.server.host
+            ));
+        }
+        if self.server.port == 0 {
+            errors.push("server.port: must be between 1 and 65535".to_string());
+        }
+        if let Some(tls) = &self.server.tls {
+            if tls.cert_path.is_empty() {
+                errors.push("server.tls.cert_path: must not be empty".to_string());
+            }
+            if tls.key_path.is_empty() {
+                errors.push("server.tls.key_path: must not be empty".to_string());
+            }
+        }
+        if let Err(e) = tracing_subscriber::EnvFilter::try_new(&self.logging.level) {
+            errors.push(format!("logging.level: {}", e));
+        }
+
+        if self.events.poll_interval_ms == 0 {
+            errors.push("events.poll_interval_ms: must be greater than 0".to_string());
+        }
+        for (key, buckets) in [
+            (
+                "metrics.request_duration_buckets",
+                &self.metrics.request_duration_buckets,
+            ),
+            (
+                "metrics.backend_duration_buckets",
+                &self.metrics.backend_duration_buckets,
+            ),
+        ] {
+            if buckets.windows(2).any(|pair| pair[0] >= pair[1]) {
+                errors.push(format!("{}: must be in increasing order", key));
+            }
+        }
+
+        let auth = &self.auth;
+        if auth.enabled
+            && auth.api_keys.is_empty()
+            && auth.jwt.is_none()
+            && auth.client_certs.is_empty()
+        {
+            errors.push(
+                "auth: enabled but no api_keys, jwt or client_certs are configured".to_string(),
+            );
+        }
+        for (i, entry) in auth.api_keys.iter().enumerate() {
+            if entry.key.is_empty() {
+                errors.push(format!("auth.api_keys '{}': key is empty", entry.name));
+            }
+            if auth.api_keys[..i]
+                .iter()
+                .any(|other| other.name == entry.name)
+            {
+                errors.push(format!("auth.api_keys '{}': duplicate name", entry.name));
+            }
+        }
+        if let Some(jwt) = &auth.jwt {
+            match jwt.algorithm {
+                JwtAlgorithm::HS256 if jwt.secret.as_deref().unwrap_or_default().is_empty() => {
+                    errors.push("auth.jwt.secret: required for HS256".to_string())
+                }
+                JwtAlgorithm::RS256 if jwt.public_key_path.is_none() => {
+                    errors.push("auth.jwt.public_key_path: required for RS256".to_string())
+                }
+                _ => {}
+            }
+        }
+
+        if self.audit.sink == AuditSink::File && self.audit.path.is_empty() {
+            errors.push("audit.path: required for the file sink".to_string());
+        }
+        if self.idempotency.enabled && self.idempotency.max_keys == 0 {
+            errors.push("idempotency.max_keys: must be greater than 0".to_string());
+        }
+
+        errors.extend(self.cors.errors());
+        let rate = &self.rate_limit;
+        if rate.enabled {
+            if !(rate.requests_per_second.is_finite() && rate.requests_per_second > 0.0) {
+                errors.push("rate_limit.requests_per_second: must be greater than 0".to_string());
+            }
+            if rate.burst == 0 {
+                errors.push("rate_limit.burst: must be at least 1".to_string());
+            }
+        }
+
+        if self.health.timeout_ms == 0 {
+            errors.push("health.timeout_ms: must be greater than 0".to_string());
+        }
+        if self.health.max_active_jobs == 0 {
+            errors.push("health.max_active_jobs: must be greater than 0".to_string());
+        }
+        for name in &self.health.optional {
+            if !crate::health::BUILT_IN.contains(&name.as_str()) {
+                errors.push(format!(
+                    "health.optional: unknown check '{}', expected one of {}",
+                    name,
+                    crate::health::BUILT_IN.join(", ")
+                ));
+            }
+        }
+
+        errors.extend(self.webhooks.errors());
+        errors.extend(self.aggregator.errors());
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Validation(errors))
+        }
+    }
+}
+
+impl CorsConfig {
+    /// Entries that are not valid origins, methods or header names
+    pub fn errors(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        for (key, values) in [
+            ("cors.allowed_origins", &self.allowed_origins),
+            ("cors.allowed_methods", &self.allowed_methods),
+            ("cors.allowed_headers", &self.allowed_headers),
+        ] {
+            if values.len() > 1 && values.iter().any(|v| v == "*") {
+                errors.push(format!(
+                    "{}: \"*\" cannot be combined with other values",
+                    key
+                ));
+            }
+        }
+        for origin in self.allowed_origins.iter().filter(|v| *v != "*") {
+            let scheme = origin.starts_with("http://") || origin.starts_with("https://");
+            if !scheme || origin.ends_with('/') || origin.parse::<HeaderValue>().is_err() {
+                errors.push(format!(
+                    "cors.allowed_origins: '{}' is not an origin such as https://host:port",
+                    origin
+                ));
+            }
+        }
+        for method in self.allowed_methods.iter().filter(|v| *v != "*") {
+            if method.parse::<Method>().is_err() {
+                errors.push(format!(
+                    "cors.allowed_methods: '{}' is not a method",
+                    method
+                ));
+            }
+        }
+        for name in self.allowed_headers.iter().filter(|v| *v != "*") {
+            if name.parse::<HeaderName>().is_err() {
+                errors.push(format!(
+                    "cors.allowed_headers: '{}' is not a header name",
+                    name
+                ));
+            }
+        }
+        errors
+    }
+}
+
+impl WebhooksConfig {
+    /// Subscriptions and retry settings that cannot work
+    pub fn errors(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        if self.max_attempts == 0 {
+            errors.push("webhooks.max_attempts: must be at least 1".to_string());
+        }
+        if self.initial_backoff_ms == 0 {
+            errors.push("webhooks.initial_backoff_ms: must be greater than 0".to_string());
+        }
+        if self.max_backoff_ms < self.initial_backoff_ms {
+            errors.push(
+                "webhooks.max_backoff_ms: must not be less than initial_backoff_ms".to_string(),
+            );
+        }
+        if self.timeout_ms == 0 {
+            errors.push("webhooks.timeout_ms: must be greater than 0".to_string());
+        }
+        for (i, webhook) in self.subscriptions.iter().enumerate() {
+            let key = format!("webhooks.subscriptions '{}'", webhook.name);
+            if let Some(problem) = crate::webhooks::name_error(&webhook.name) {
+                errors.push(format!("{}: name {}", key, problem));
+            }
+            if let Some(problem) = crate::webhooks::url_error(&webhook.url) {
+                errors.push(format!("{}: url {}", key, problem));
+            }
+            if webhook.secret.is_empty() {
+                errors.push(format!("{}: secret is empty", key));
+            }
+            if self.subscriptions[..i]
+                .iter()
+                .any(|other| other.name == webhook.name)
+            {
+                errors.push(format!("{}: duplicate name", key));
+            }
+        }
+        errors
+    }
+}
+
+impl AggregatorConfig {
+    /// Peer lists and timeouts that cannot work
+    pub fn errors(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        if !self.enabled {
+            return errors;
+        }
+        if self.peers.is_empty() && !self.discover {
+            errors.push("aggregator.peers: required unless discover is set".to_string());
+        }
+        if self.discover {
+            if !self.node_url.contains("{node}") {
+                errors.push("aggregator.node_url: must contain {node}".to_string());
+            } else if let Some(problem) =
+                crate::webhooks::url_error(&self.node_url.replace("{node}", "node"))
+            {
+                errors.push(format!("aggregator.node_url: {}", problem));
+            }
+        }
+        if self.timeout_ms == 0 {
+            errors.push("aggregator.timeout_ms: must be greater than 0".to_string());
+        }
+        if self.forward_timeout_ms == 0 {
+            errors.push("aggregator.forward_timeout_ms: must be greater than 0".to_string());
+        }
+        if self.api_key.as_deref() == Some("") {
+            errors.push("aggregator.api_key: is empty".to_string());
+        }
+        for (i, peer) in self.peers.iter().enumerate() {
+            let key = format!("aggregator.peers '{}'", peer.name);
+            if peer.name.is_empty() || peer.name.contains(',') {
+                errors.push(format!(
+                    "{}: name must be non-empty and without commas",
+                    key
+                ));
+            }
+            if let Some(problem) = crate::webhooks::url_error(&peer.url) {
+                errors.push(format!("{}: url {}", key, problem));
+            }
+            if self.peers[..i]
+                .iter()
+                .any(|other| other.name.eq_ignore_ascii_case(&peer.name))
+            {
+                errors.push(format!("{}: duplicate name", key));
+            }
+        }
+        errors
+    }
+}
+
+// =============================================================================
+// Layered Loading
+// =============================================================================
+
+/// Prefix of environment variables that override configuration keys
+pub const ENV_PREFIX: &str = "NODEAGENT_";
+
+/// Environment variable naming the configuration file
+pub const ENV_CONFIG_PATH: &str = "NODEAGENT_CONFIG";
+
+/// Command-line flags
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct CommandLine {
+    /// `--config <path>`: configuration file, which must exist
+    pub config: Option<PathBuf>,
+    /// `--port <port>`: overrides `[server] port`
+    pub port: Option<u16>,
+    /// `--log-level <filter>`: overrides `[logging] level`
+    pub log_level: Option<String>,
+    /// `--service`: run under the Windows service control manager
+    pub service: bool,
+}
+
+impl CommandLine {
+    pub const USAGE: &'static str =
+        "Usage: api [--config <path>] [--port <port>] [--log-level <filter>] [--service]";
+
+    /// Parse flags, without the program name; values follow the flag or `=`
+    pub fn parse<I, S>(args: I) -> Result<Self, ConfigError>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let mut cli = Self::default();
+        let mut args = args.into_iter().map(Into::into);
+        while let Some(arg) = args.next() {
+            let (flag, inline) = match arg.split_once('=') {
+                Some((flag, value)) => (flag.to_string(), Some(value.to_string())),
+                None => (arg.clone(), None),
+            };
+            let mut value = || {
+                inline
+                    .clone()
+                    .or_else(|| args.next())
+                    .ok_or_else(|| ConfigError::Invalid(format!("{} requires a value", flag)))
+            };
+            match flag.as_str() {
+                "--service" => cli.service = true,
+                "--config" => cli.config = Some(PathBuf::from(value()?)),
+                "--port" => {
+                    let port = value()?;
+                    cli.port = Some(port.parse().map_err(|_| {
+                        ConfigError::Invalid(format!("--port: '{}' is not a port number", port))
+                    })?);
+                }
+                "--log-level" => cli.log_level = Some(value()?),
+                _ => return Err(ConfigError::Invalid(format!("unknown argument '{}'", arg))),
+            }
+        }
+        Ok(cli)
+    }
+}
+
+/// Builds [`Config`] from defaults, file, environment and flags
+#[derive(Debug, Clone)]
+pub struct ConfigLoader {
+    path: PathBuf,
+    /// The file was named explicitly, so it must exist
+    required: bool,
+    env: Vec<(String, String)>,
+    cli: CommandLine,
+}
+
+impl ConfigLoader {
+    /// Loader over the process environment; `default_path` is used unless
+    /// `--config` or `NODEAGENT_CONFIG` names a file
+    pub fn new(default_path: impl Into<PathBuf>, cli: CommandLine) -> Self {
+        Self::with_env(default_path, cli, std::env::vars())
+    }
+
+    /// Loader over the given environment variables
+    pub fn with_env(
+        default_path: impl Into<PathBuf>,
+        cli: CommandLine,
+        env: impl IntoIterator<Item = (String, String)>,
+    ) -> Self {
+        let mut env: Vec<_> = env
+            .into_iter()
+            .filter(|(name, _)| name.starts_with(ENV_PREFIX))
+            .collect();
+        env.sort();
+        let env_path = env
+            .iter()
+            .find(|(name, _)| name == ENV_CONFIG_PATH)
+            .map(|(_, path)| PathBuf::from(path));
+        let (path, required) = match cli.config.clone().or(env_path) {
+            Some(path) => (path, true),
+            None => (default_path.into(), false),
+        };
+        Self {
+            path,
+            required,
+            env,
+            cli,
+        }
+    }
+
+    /// Configuration file this loader reads
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Merge all layers and validate the result
+    pub fn load(&self) -> Result<Config, ConfigError> {
+        self.load_values().map(|(config, _)| config)
+    }
+
+    /// Like [`load`](Self::load), also returning the merged values that were
+    /// set explicitly by the file, environment or flags
+    pub fn load_values(&self) -> Result<(Config, Table), ConfigError> {
+        let mut values = self.read_file()?;
+        let mut errors = Vec::new();
+
+        for (name, raw) in &self.env {
+            if name == ENV_CONFIG_PATH {
+                continue;
+            }
+            let key = name[ENV_PREFIX.len()..].to_ascii_lowercase();
+            let path: Vec<&str> = key.split("__").collect();
+            if path.iter().any(|part| part.is_empty()) {
+                errors.push(format!("{}: malformed variable name", name));
+                continue;
+            }
+            if let Err(e) = set_value(&mut values, &path, env_value(raw)) {
+                errors.push(format!("{}: {}", name, e));
+            }
+        }
+        if let Some(port) = self.cli.port {
+            set_value(
+                &mut values,
+                &["server", "port"],
+                Value::Integer(port.into()),
+            )
+            .map_err(ConfigError::Invalid)?;
+        }
+        if let Some(level) = &self.cli.log_level {
+            set_value(
+                &mut values,
+                &["logging", "level"],
+                Value::String(level.clone()),
+            )
+            .map_err(ConfigError::Invalid)?;
+        }
+        if !errors.is_empty() {
+            return Err(ConfigError::Validation(errors));
+        }
+
+        let config: Config = match Value::Table(values.clone()).try_into() {
+            Ok(config) => config,
+            Err(e) => return Err(type_errors(&values, e)),
+        };
+        config.validate()?;
+        Ok((config, values))
+    }
+
+    fn read_file(&self) -> Result<Table, ConfigError> {
+        let path = &self.path;
+        if !path.exists() {
+            if self.required {
+                return Err(ConfigError::ReadError(
+                    path.display().to_string(),
+                    "file not found".to_string(),
+                ));
+            }
+            tracing::info!("Config file not found at {:?}, using defaults", path);
+            return Ok(Table::new());
+        }
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| ConfigError::ReadError(path.display().to_string(), e.to_string()))?;
+        content.parse().map_err(|e: toml::de::Error| {
+            ConfigError::ParseError(path.display().to_string(), e.to_string())
+        })
+    }
+}
+
+/// Name every section that does not deserialize, not just the first
+fn type_errors(values: &Table, err: toml::de::Error) -> ConfigError {
+    let errors: Vec<String> = values
+        .iter()
+        .filter_map(|(section, value)| {
+            let single = Table::from_iter([(section.clone(), value.clone())]);
+            Value::Table(single)
+                .try_into::<Config>()
+                .err()
+                .map(|e| format!("{}: {}", section, e.message()))
+        })
+        .collect();
+    if errors.is_empty() {
+        ConfigError::Invalid(err.message().to_string())
+    } else {
+        ConfigError::Validation(errors)
+    }
+}
+
+/// Environment values are TOML (`8080`, `true`, `["a", "b"]`); anything else
+/// is taken as a string. Quote values such as `"1.3"` to keep them strings.
+fn env_value(raw: &str) -> Value {
+    format!("value = {}", raw)
+        .parse::<Table>()
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| Value::String(raw.to_string()))
+}
+
+fn set_value(table: &mut Table, path: &[&str], value: Value) -> Result<(), String> {
+    let (last, parents) = path.split_last().expect("key path is not empty");
+    let mut current = table;
+    for (depth, part) in parents.iter().enumerate() {
+        current = match current
+            .entry(part.to_string())
+            .or_insert_with(|| Value::Table(Table::new()))
+        {
+            Value::Table(table) => table,
+            _ => return Err(format!("'{}' is not a section", path[..=depth].join("."))),
+        };
+    }
+    current.insert(last.to_string(), value);
+    Ok(())
 }
 
 /// Configuration errors
@@ -157,6 +1491,9 @@ impl Config {
 pub enum ConfigError {
     ReadError(String, String),
     ParseError(String, String),
+    Invalid(String),
+    /// Every problem found by [`Config::validate`] or in environment overrides
+    Validation(Vec<String>),
 }
 
 impl std::fmt::Display for ConfigError {
@@ -168,6 +1505,14 @@ impl std::fmt::Display for ConfigError {
             ConfigError::ParseError(path, err) => {
                 write!(f, "Failed to parse config file '{}': {}", path, err)
             }
+            ConfigError::Invalid(err) => write!(f, "Invalid configuration: {}", err),
+            ConfigError::Validation(errors) => {
+                write!(f, "Invalid configuration:")?;
+                for err in errors {
+                    write!(f, "\n  - {}", err)?;
+                }
+                Ok(())
+            }
         }
     }
 }
@@ -190,6 +1535,18 @@ mod tests {
             config.service.install_path,
             r"C:\Program Files\azurestack\nodeagent"
         );
+        assert_eq!(config.backend.kind, BackendKind::Native);
+        assert_eq!(config.jobs.history_limit, 100);
+        assert!(!config.auth.enabled);
+        assert!(config.server.tls.is_none());
+        assert!(config.metrics.enabled);
+        assert_eq!(config.metrics.prefix, "nodeagent");
+        assert_eq!(config.events.poll_interval_ms, 2000);
+        assert_eq!(config.audit.sink, AuditSink::Memory);
+        assert_eq!(config.locks.wait_timeout_ms, 0);
+        assert_eq!(config.locks.max_concurrent_operations, 16);
+        assert!(config.idempotency.enabled);
+        assert_eq!(config.idempotency.ttl_secs, 86400);
     }
 
     #[test]
@@ -233,4 +1590,307 @@ mod tests {
         assert_eq!(config.service.display_name, "My Custom API");
         assert_eq!(config.service.install_path, r"D:\Services\MyApi");
     }
+
+    #[test]
+    fn test_parse_backend_kind() {
+        let toml = r#"
+            [backend]
+            kind = "fake"
+        "#;
+        let config: Config = toml::from_str(toml).unwrap();
+        assert_eq!(config.backend.kind, BackendKind::Fake);
+
+        let toml = r#"
+            [backend]
+            kind = "simulated"
+        "#;
+        assert!(toml::from_str::<Config>(toml).is_err());
+    }
+
+    #[test]
+    fn test_parse_auth_config() {
+        let toml = r#"
+            [auth]
+            enabled = true
+
+            [[auth.api_keys]]
+            name = "ops"
+            key = "secret-key"
+            role = "operator"
+
+            [auth.jwt]
+            algorithm = "RS256"
+            public_key_path = "jwt.pem"
+            audience = "nodeagent"
+        "#;
+        let config: Config = toml::from_str(toml).unwrap();
+        assert!(config.auth.enabled);
+        assert_eq!(config.auth.api_keys[0].role, Role::Operator);
+        let jwt = config.auth.jwt.unwrap();
+        assert_eq!(jwt.algorithm, JwtAlgorithm::RS256);
+        assert_eq!(jwt.role_claim, "role");
+        assert_eq!(jwt.audience.as_deref(), Some("nodeagent"));
+
+        let toml = r#"
+            [[auth.api_keys]]
+            name = "ops"
+            key = "secret-key"
+            role = "root"
+        "#;
+        assert!(toml::from_str::<Config>(toml).is_err());
+    }
+
+    #[test]
+    fn test_parse_tls_config() {
+        let toml = r#"
+            [server.tls]
+            cert_path = "server.pem"
+            key_path = "server.key"
+            client_ca_path = "clients.pem"
+            min_version = "1.3"
+        "#;
+        let config: Config = toml::from_str(toml).unwrap();
+        let tls = config.server.tls.unwrap();
+        assert_eq!(tls.cert_path, "server.pem");
+        assert_eq!(tls.client_ca_path.as_deref(), Some("clients.pem"));
+        assert!(tls.require_client_cert);
+        assert_eq!(tls.min_version, TlsVersion::Tls13);
+        assert_eq!(tls.reload_interval_secs, 60);
+
+        let toml = r#"
+            [server.tls]
+            cert_path = "server.pem"
+            key_path = "server.key"
+            min_version = "1.0"
+        "#;
+        assert!(toml::from_str::<Config>(toml).is_err());
+    }
+
+    fn loader(file: Option<&str>, env: &[(&str, &str)], args: &[&str]) -> ConfigLoader {
+        static NEXT: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "api-config-{}-{}.toml",
+            std::process::id(),
+            NEXT.fetch_add(1, std::sync::atomic::Ordering::Relaxed)
+        ));
+        match file {
+            Some(content) => std::fs::write(&path, content).unwrap(),
+            None => {
+                let _ = std::fs::remove_file(&path);
+            }
+        }
+        ConfigLoader::with_env(
+            path,
+            CommandLine::parse(args.iter().copied()).unwrap(),
+            env.iter().map(|(k, v)| (k.to_string(), v.to_string())),
+        )
+    }
+
+    #[test]
+    fn test_layer_precedence() {
+        let file = r#"
+            [server]
+            host = "127.0.0.1"
+            port = 7000
+
+            [logging]
+            level = "info"
+        "#;
+        let env = [
+            ("NODEAGENT_SERVER__PORT", "7100"),
+            ("NODEAGENT_LOGGING__LEVEL", "api=debug"),
+            ("NODEAGENT_RATE_LIMIT__ENABLED", "true"),
+            (
+                "NODEAGENT_CORS__ALLOWED_ORIGINS",
+                r#"["https://portal.contoso.com"]"#,
+            ),
+            ("OTHER_SERVER__PORT", "1"),
+        ];
+
+        let config = loader(Some(file), &env, &[]).load().unwrap();
+        assert_eq!(config.server.host, "127.0.0.1");
+        assert_eq!(config.server.port, 7100);
+        assert_eq!(config.logging.level, "api=debug");
+        assert!(config.rate_limit.enabled);
+        assert_eq!(config.cors.allowed_origins, ["https://portal.contoso.com"]);
+
+        let config = loader(Some(file), &env, &["--port", "7200", "--log-level=warn"])
+            .load()
+            .unwrap();
+        assert_eq!(config.server.port, 7200);
+        assert_eq!(config.logging.level, "warn");
+
+        // A missing default file falls back to defaults; a named one is an error
+        let config = loader(None, &[], &[]).load().unwrap();
+        assert_eq!(config.server.port, 6001);
+        let missing = loader(
+            None,
+            &[("NODEAGENT_CONFIG", "/nonexistent/nodeagent.toml")],
+            &[],
+        );
+        assert!(matches!(missing.load(), Err(ConfigError::ReadError(..))));
+    }
+
+    #[test]
+    fn test_load_errors() {
+        let err = loader(Some("[server\nport = 1"), &[], &[])
+            .load()
+            .unwrap_err();
+        assert!(matches!(err, ConfigError::ParseError(..)));
+
+        let err = loader(
+            Some("[jobs]\nhistory_limit = -1"),
+            &[("NODEAGENT_SERVER__PORT", "eighty")],
+            &[],
+        )
+        .load()
+        .unwrap_err();
+        match err {
+            ConfigError::Validation(errors) => {
+                assert_eq!(errors.len(), 2, "{:?}", errors);
+                assert!(errors[0].starts_with("jobs: "));
+                assert!(errors[1].starts_with("server: ") && errors[1].contains("eighty"));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+
+        let err = loader(
+            None,
+            &[
+                ("NODEAGENT_SERVER__", "1"),
+                ("NODEAGENT_SERVER__PORT", "1"),
+                ("NODEAGENT_SERVER__PORT__X", "1"),
+            ],
+            &[],
+        )
+        .load()
+        .unwrap_err();
+        match err {
+            ConfigError::Validation(errors) => assert_eq!(errors.len(), 2, "{:?}", errors),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_validate_reports_every_error() {
+        let mut config = Config::default();
+        assert!(config.validate().is_ok());
+
+        config.server.host = "localhost:80".to_string();
+        config.server.port = 0;
+        config.logging.level = "api=loud".to_string();
+        config.auth.enabled = true;
+        config.cors.allowed_origins = vec!["*".to_string(), "portal".to_string()];
+        config.rate_limit = RateLimitConfig {
+            enabled: true,
+            requests_per_second: 0.0,
+            burst: 0,
+        };
+        let ConfigError::Validation(errors) = config.validate().unwrap_err() else {
+            panic!("expected validation errors");
+        };
+        let keys: Vec<&str> = errors
+            .iter()
+            .map(|e| e.split(':').next().unwrap())
+            .collect();
+        assert_eq!(
+            keys,
+            [
+                "server.host",
+                "server.port",
+                "logging.level",
+                "auth",
+                "cors.allowed_origins",
+                "cors.allowed_origins",
+                "rate_limit.requests_per_second",
+                "rate_limit.burst",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_webhooks() {
+        let config: Config = toml::from_str(
+            r#"
+            [webhooks]
+            max_attempts = 0
+
+            [[webhooks.subscriptions]]
+            name = "cmdb"
+            url = "https://cmdb.example/hooks"
+            secret = "s3cret"
+            events = ["vm_created", "group_moved"]
+
+            [[webhooks.subscriptions]]
+            name = "cmdb"
+            url = "ftp://cmdb.example"
+            secret = ""
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.webhooks.subscriptions[0].events,
+            [EventKind::VmCreated, EventKind::GroupMoved]
+        );
+        let ConfigError::Validation(errors) = config.validate().unwrap_err() else {
+            panic!("expected validation errors");
+        };
+        assert_eq!(
+            errors,
+            [
+                "webhooks.max_attempts: must be at least 1",
+                "webhooks.subscriptions 'cmdb': url must be an http or https URL",
+                "webhooks.subscriptions 'cmdb': secret is empty",
+                "webhooks.subscriptions 'cmdb': duplicate name",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_aggregator() {
+        let config: Config = toml::from_str(
+            r#"
+            [aggregator]
+            enabled = true
+            discover = true
+            node_url = "https://node:6001"
+            timeout_ms = 0
+
+            [[aggregator.peers]]
+            name = "NODE1"
+            url = "https://node1:6001"
+
+            [[aggregator.peers]]
+            name = "node1"
+            url = "node1:6001"
+            "#,
+        )
+        .unwrap();
+        let ConfigError::Validation(errors) = config.validate().unwrap_err() else {
+            panic!("expected validation errors");
+        };
+        assert_eq!(
+            errors,
+            [
+                "aggregator.node_url: must contain {node}",
+                "aggregator.timeout_ms: must be greater than 0",
+                "aggregator.peers 'node1': url must be an http or https URL",
+                "aggregator.peers 'node1': duplicate name",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_command_line() {
+        let cli =
+            CommandLine::parse(["--config", "D:\\agent.toml", "--port=8443", "--service"]).unwrap();
+        assert_eq!(cli.config, Some(PathBuf::from("D:\\agent.toml")));
+        assert_eq!(cli.port, Some(8443));
+        assert!(cli.service);
+        assert!(cli.log_level.is_none());
+
+        assert!(CommandLine::parse(["--port", "http"]).is_err());
+        assert!(CommandLine::parse(["--log-level"]).is_err());
+        assert!(CommandLine::parse(["--verbose"]).is_err());
+    }
 }
@@ -0,0 +1,107 @@
+//! Cross-origin resource sharing with a reloadable policy
+//!
+//! `tower_http`'s `CorsLayer` is fixed once the router is built, so the
+//! [`apply`] middleware wraps each request in the layer currently held by
+//! [`Cors`]. [`Cors::reconfigure`] swaps it when `[cors]` changes.
+
+use std::sync::RwLock;
+use std::time::Duration;
+
+use axum::{
+    extract::{Request, State},
+    http::{HeaderName, HeaderValue, Method},
+    middleware::Next,
+    response::Response,
+};
+use tower::{Layer, ServiceExt};
+use tower_http::cors::{AllowHeaders, AllowMethods, AllowOrigin, Any, CorsLayer};
+
+use crate::config::{ConfigError, CorsConfig};
+use crate::SharedState;
+
+/// Current CORS policy
+pub struct Cors {
+    layer: RwLock<CorsLayer>,
+}
+
+impl Cors {
+    pub fn new(config: &CorsConfig) -> Result<Self, ConfigError> {
+        Ok(Self {
+            layer: RwLock::new(build(config)?),
+        })
+    }
+
+    /// Replace the policy; the previous one stays active on error
+    pub fn reconfigure(&self, config: &CorsConfig) -> Result<(), ConfigError> {
+        let layer = build(config)?;
+        *self.layer.write().unwrap_or_else(|e| e.into_inner()) = layer;
+        Ok(())
+    }
+
+    fn layer(&self) -> CorsLayer {
+        self.layer.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+impl Default for Cors {
+    fn default() -> Self {
+        Self::new(&CorsConfig::default()).expect("default CORS policy is valid")
+    }
+}
+
+fn build(config: &CorsConfig) -> Result<CorsLayer, ConfigError> {
+    let errors = config.errors();
+    if !errors.is_empty() {
+        return Err(ConfigError::Validation(errors));
+    }
+    let any = |values: &[String]| values.iter().any(|v| v == "*");
+
+    let origins = if any(&config.allowed_origins) {
+        AllowOrigin::any()
+    } else {
+        AllowOrigin::list(
+            config
+                .allowed_origins
+                .iter()
+                .filter_map(|origin| origin.parse::<HeaderValue>().ok()),
+        )
+    };
+    let methods = if any(&config.allowed_methods) {
+        AllowMethods::any()
+    } else {
+        AllowMethods::list(
+            config
+                .allowed_methods
+                .iter()
+                .filter_map(|method| method.parse::<Method>().ok()),
+        )
+    };
+    let headers = if any(&config.allowed_headers) {
+        AllowHeaders::any()
+    } else {
+        AllowHeaders::list(
+            config
+                .allowed_headers
+                .iter()
+                .filter_map(|name| name.parse::<HeaderName>().ok()),
+        )
+    };
+
+    let mut layer = CorsLayer::new()
+        .allow_origin(origins)
+        .allow_methods(methods)
+        .allow_headers(headers)
+        .expose_headers(Any);
+    if config.max_age_secs > 0 {
+        layer = layer.max_age(Duration::from_secs(config.max_age_secs));
+    }
+    Ok(layer)
+}
+
+/// Answer preflight requests and add CORS headers with the current policy
+pub async fn apply(State(state): State<SharedState>, request: Request, next: Next) -> Response {
+    match state.cors.layer().layer(next).oneshot(request).await {
+        Ok(response) => response,
+        Err(never) => match never {},
+    }
+}
@@ -1,32 +1,37 @@
 //! Data Transfer Objects for API requests and responses
 
+use std::collections::BTreeMap;
+
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+use crate::error::ErrorCode;
+
 // =============================================================================
 // Cluster DTOs
 // =============================================================================
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct NodeDto {
     pub name: String,
     pub state: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct GroupDto {
     pub name: String,
     pub state: String,
     pub owner_node: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ResourceDto {
     pub name: String,
     pub state: String,
     pub owner_node: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct CsvDto {
     pub name: String,
     pub state: String,
@@ -34,17 +39,17 @@ pub struct CsvDto {
     pub is_csv: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct CsvPathQuery {
     pub path: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct MaintenanceModeRequest {
     pub enable: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ClusterNameQuery {
     pub name: Option<String>,
 }
@@ -53,7 +58,7 @@ pub struct ClusterNameQuery {
 // Hyper-V DTOs
 // =============================================================================
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
 pub struct VmDto {
     pub id: String,
     pub name: String,
@@ -61,9 +66,24 @@ pub struct VmDto {
     pub cpu_count: Option<u32>,
     pub memory_mb: Option<u64>,
     pub uptime_seconds: Option<u64>,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
+    pub generation: Option<u32>,
+    pub dynamic_memory: Option<bool>,
+    pub dynamic_memory_min_mb: Option<u64>,
+    pub dynamic_memory_max_mb: Option<u64>,
+    pub memory_buffer_percentage: Option<u32>,
+    /// Nothing, StartIfRunning or AlwaysStart
+    pub automatic_start_action: Option<String>,
+    pub automatic_start_delay_secs: Option<u32>,
+    /// TurnOff, Save or Shutdown
+    pub automatic_stop_action: Option<String>,
+    /// Disabled, Production, ProductionOnly or Standard
+    pub checkpoint_type: Option<String>,
+    pub notes: Option<String>,
+    /// Configuration version, e.g. "10.0"
+    pub version: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct CreateVmRequest {
     pub name: String,
     pub memory_mb: u64,
@@ -75,16 +95,112 @@ pub struct CreateVmRequest {
     pub vhd_size_bytes: u64,
     /// Optional virtual switch to connect to
     pub switch_name: Option<String>,
+    /// Configuration version; defaults to the host's default version
+    pub version: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Settings to change on an existing VM; absent fields are left as they are
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct UpdateVmRequest {
+    /// Startup memory
+    pub memory_mb: Option<u64>,
+    pub cpu_count: Option<u32>,
+    pub dynamic_memory: Option<bool>,
+    pub dynamic_memory_min_mb: Option<u64>,
+    pub dynamic_memory_max_mb: Option<u64>,
+    /// Memory Hyper-V keeps in reserve above demand, 0-100
+    pub memory_buffer_percentage: Option<u32>,
+    /// Nothing, StartIfRunning or AlwaysStart
+    pub automatic_start_action: Option<String>,
+    pub automatic_start_delay_secs: Option<u32>,
+    /// TurnOff, Save or Shutdown
+    pub automatic_stop_action: Option<String>,
+    /// Disabled, Production, ProductionOnly or Standard
+    pub checkpoint_type: Option<String>,
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct VmProcessorDto {
+    pub count: u32,
+    /// Share of each virtual processor the VM may use, 0-100
+    pub limit_percent: f64,
+    /// Share of each virtual processor held for the VM, 0-100
+    pub reservation_percent: f64,
+    /// Priority against other VMs, 1-10000
+    pub weight: u32,
+    /// SMT threads per core seen by the guest; absent follows the host
+    pub hw_threads_per_core: Option<u32>,
+    /// Nested virtualization
+    pub expose_virtualization_extensions: bool,
+    pub cpu_group_id: Option<String>,
+    /// Empty when Hyper-V lays out the NUMA nodes
+    pub numa_nodes: Vec<NumaNodeDto>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct NumaNodeDto {
+    pub id: u32,
+    pub processor_count: u32,
+    pub memory_mb: u64,
+}
+
+/// Processor settings of a VM; absent fields are reset to their defaults
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct SetVmProcessorRequest {
+    pub count: u32,
+    /// Defaults to 100
+    pub limit_percent: Option<f64>,
+    /// Defaults to 0
+    pub reservation_percent: Option<f64>,
+    /// Defaults to 100
+    pub weight: Option<u32>,
+    pub hw_threads_per_core: Option<u32>,
+    pub expose_virtualization_extensions: Option<bool>,
+    pub cpu_group_id: Option<String>,
+    /// Processor counts must add up to `count`
+    pub numa_nodes: Option<Vec<NumaNodeDto>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct VmSecurityDto {
+    pub secure_boot_enabled: bool,
+    /// MicrosoftWindows, MicrosoftUefiCa or OpenSourceShieldedVm
+    pub secure_boot_template: Option<String>,
+    pub tpm_enabled: bool,
+    /// None, Vbs, Snp or Tdx
+    pub guest_isolation_type: String,
+    pub encrypt_state_and_migration: bool,
+    pub shielding_requested: bool,
+    /// None, Local or Hgs
+    pub key_protector: String,
+}
+
+/// Security settings of a VM; absent fields are reset to their defaults
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct SetVmSecurityRequest {
+    /// Defaults to true when `secure_boot_template` is given
+    pub secure_boot_enabled: Option<bool>,
+    /// MicrosoftWindows, MicrosoftUefiCa or OpenSourceShieldedVm
+    pub secure_boot_template: Option<String>,
+    pub tpm_enabled: Option<bool>,
+    /// None, Vbs, Snp or Tdx
+    pub guest_isolation_type: Option<String>,
+    pub encrypt_state_and_migration: Option<bool>,
+    /// Requires `tpm_enabled`
+    pub shielding_requested: Option<bool>,
+    /// None, Local or Hgs
+    pub key_protector: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SwitchDto {
     pub name: String,
     pub id: String,
     pub switch_type: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct CreateSwitchRequest {
     pub name: String,
     pub switch_type: String,
@@ -92,7 +208,7 @@ pub struct CreateSwitchRequest {
     pub allow_management_os: Option<bool>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct VhdDto {
     pub path: String,
     pub format: String,
@@ -103,7 +219,7 @@ pub struct VhdDto {
     pub is_attached: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct CreateVhdRequest {
     pub path: String,
     pub size_bytes: u64,
@@ -111,24 +227,24 @@ pub struct CreateVhdRequest {
     pub block_size_bytes: Option<u32>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct VhdPathRequest {
     pub path: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ResizeVhdRequest {
     pub path: String,
     pub size_bytes: u64,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct DiffVhdRequest {
     pub path: String,
     pub parent_path: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct InitVhdRequest {
     pub path: String,
     pub partition_style: Option<String>,
@@ -136,22 +252,64 @@ pub struct InitVhdRequest {
     pub label: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SnapshotDto {
     pub name: String,
     pub id: String,
     pub vm_name: String,
     pub creation_time: Option<String>,
     pub parent_name: Option<String>,
+    /// Checkpoint names need not be unique, so the hierarchy is keyed by id
+    pub parent_id: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct CreateSnapshotRequest {
     pub name: String,
     pub snapshot_type: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Checkpoint hierarchy of a VM
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SnapshotTreeDto {
+    pub vm_name: String,
+    /// Checkpoint the running state ("Now") was taken from; "Now" sits at
+    /// the root when absent
+    pub current_snapshot: Option<String>,
+    pub current_snapshot_id: Option<String>,
+    pub roots: Vec<SnapshotNodeDto>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SnapshotNodeDto {
+    pub name: String,
+    pub id: String,
+    pub creation_time: Option<String>,
+    /// True for the parent of "Now"
+    pub is_current: bool,
+    /// Checkpoints removed along with this one by a subtree delete
+    pub descendant_count: usize,
+    pub children: Vec<SnapshotNodeDto>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct DeleteSnapshotQuery {
+    /// Also delete every child checkpoint instead of merging them into the
+    /// parent (default: false)
+    pub subtree: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RenameSnapshotRequest {
+    pub new_name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ExportSnapshotRequest {
+    pub path: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct GpuDto {
     pub device_instance_id: String,
     pub name: String,
@@ -160,7 +318,7 @@ pub struct GpuDto {
     pub supports_partitioning: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct GpuAdapterDto {
     pub vm_name: String,
     pub instance_path: Option<String>,
@@ -169,18 +327,18 @@ pub struct GpuAdapterDto {
     pub optimal_partition_vram: u64,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct AddGpuRequest {
     pub instance_path: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ConfigureGpuRequest {
     pub low_mmio_gb: u32,
     pub high_mmio_gb: u32,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct DdaSupportDto {
     pub is_supported: bool,
     pub is_server: bool,
@@ -189,7 +347,7 @@ pub struct DdaSupportDto {
     pub reason: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct AssignableDeviceDto {
     pub instance_id: String,
     pub name: String,
@@ -200,17 +358,17 @@ pub struct AssignableDeviceDto {
     pub status: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct DevicePathRequest {
     pub instance_id: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct DeviceLocationRequest {
     pub location_path: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct DiskDto {
     pub controller_type: String,
     pub controller_number: u32,
@@ -218,33 +376,97 @@ pub struct DiskDto {
     pub path: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Virtual network adapter of a VM
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct VmNetworkAdapterDto {
+    pub id: String,
+    pub name: String,
+    pub switch_name: Option<String>,
+    pub mac_address: Option<String>,
+    pub dynamic_mac: bool,
+    pub vlan_id: Option<u16>,
+    pub mac_spoofing: Option<bool>,
+    pub dhcp_guard: Option<bool>,
+    pub router_guard: Option<bool>,
+    /// None, Source or Destination
+    pub port_mirroring: Option<String>,
+    pub bandwidth: Option<BandwidthDto>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct BandwidthDto {
+    pub minimum_mbps: Option<u64>,
+    pub maximum_mbps: Option<u64>,
+    pub burst_mb: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct AddVmNetworkAdapterRequest {
+    /// Defaults to "Network Adapter"
+    pub name: Option<String>,
+    /// Switch to connect to; the adapter is left disconnected when absent
+    pub switch_name: Option<String>,
+    /// Static MAC address, XX:XX:XX:XX:XX:XX or XX-XX-XX-XX-XX-XX
+    pub mac_address: Option<String>,
+    /// Defaults to true unless `mac_address` is given
+    pub dynamic_mac: Option<bool>,
+    pub vlan_id: Option<u16>,
+    pub mac_spoofing: Option<bool>,
+    pub dhcp_guard: Option<bool>,
+    pub router_guard: Option<bool>,
+    /// None, Source or Destination
+    pub port_mirroring: Option<String>,
+    pub bandwidth: Option<BandwidthDto>,
+}
+
+/// Settings to change on a VM network adapter; absent fields are left as they are
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct UpdateVmNetworkAdapterRequest {
+    pub mac_address: Option<String>,
+    pub dynamic_mac: Option<bool>,
+    /// 0 removes the VLAN
+    pub vlan_id: Option<u16>,
+    pub mac_spoofing: Option<bool>,
+    pub dhcp_guard: Option<bool>,
+    pub router_guard: Option<bool>,
+    /// None, Source or Destination
+    pub port_mirroring: Option<String>,
+    /// Replaces all bandwidth limits; send `{}` to remove them
+    pub bandwidth: Option<BandwidthDto>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ConnectAdapterRequest {
+    pub switch_name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct AttachDiskRequest {
     pub vhd_path: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct DetachDiskRequest {
     pub controller_number: u32,
     pub controller_location: u32,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct MountIsoRequest {
     pub iso_path: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct BootOrderRequest {
     pub devices: Vec<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ExportVmRequest {
     pub path: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct HostInfoDto {
     pub computer_name: String,
     pub logical_processor_count: u32,
@@ -253,7 +475,36 @@ pub struct HostInfoDto {
     pub vhd_path: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Hyper-V features and VM configuration versions of the host
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct HostCapabilitiesDto {
+    /// VM configuration versions the host can run, oldest first
+    pub supported_vm_versions: Vec<String>,
+    /// Version new VMs get; VMs below it can be upgraded
+    pub default_vm_version: Option<String>,
+    pub nested_virtualization_supported: bool,
+    pub dda_supported: bool,
+    pub gpu_p_supported: bool,
+    pub tpm_supported: bool,
+    pub live_migration_supported: bool,
+    pub max_processors_per_vm: u32,
+    pub max_memory_per_vm_mb: u64,
+    /// Whether the host's WMI classes have the properties behind key VM
+    /// settings
+    pub properties: Vec<PropertySupportDto>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PropertySupportDto {
+    /// WMI class, e.g. `Msvm_ProcessorSettingData`
+    pub class_name: String,
+    pub property: String,
+    /// Supported, NotSupported or Unknown
+    pub support: String,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct NetworkAdapterDto {
     pub name: String,
     pub description: String,
@@ -261,7 +512,7 @@ pub struct NetworkAdapterDto {
     pub link_speed: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct WindowsEditionDto {
     pub index: u32,
     pub name: String,
@@ -269,15 +520,385 @@ pub struct WindowsEditionDto {
     pub size_bytes: u64,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct IsoPathQuery {
     pub path: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct CreateVhdxFromIsoRequest {
     pub iso_path: String,
     pub vhdx_path: String,
     pub size_gb: u64,
     pub edition_index: u32,
 }
+
+// =============================================================================
+// Job DTOs
+// =============================================================================
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct JobDto {
+    pub id: u64,
+    /// Operation name, e.g. `export_vm`
+    pub kind: String,
+    /// Object the operation acts on (VM name, VHD path, group name)
+    pub target: String,
+    /// Queued, Running, Completed, Failed or Cancelled
+    pub state: String,
+    pub percent_complete: u32,
+    pub status: Option<String>,
+    pub created_at: String,
+    pub started_at: Option<String>,
+    pub finished_at: Option<String>,
+    /// Operation output once completed (e.g. the created VHD)
+    pub result: Option<serde_json::Value>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct JobWaitQuery {
+    /// Maximum time to wait in seconds (default: 30, max: 300)
+    pub timeout_secs: Option<u64>,
+}
+
+// =============================================================================
+// Batch DTOs
+// =============================================================================
+
+/// `POST /api/v1/hyperv/vms:batch` body
+///
+/// Targets either the listed `vms` or every VM matching `selector`, which
+/// takes the same field filters as `GET /api/v1/hyperv/vms`.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct BatchVmRequest {
+    /// VM names
+    #[serde(default)]
+    pub vms: Vec<String>,
+    /// Field filters, e.g. `{"state": "Running", "name": "web-*"}`
+    pub selector: Option<BTreeMap<String, String>>,
+    #[serde(flatten)]
+    pub action: BatchAction,
+    /// VMs acted on at once (default: 4, max: 32)
+    pub parallelism: Option<usize>,
+    /// What to do after a VM fails (default: stop)
+    #[serde(default)]
+    pub on_error: BatchErrorPolicy,
+}
+
+/// Operation applied to each VM of a batch, with its `parameters`
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "action", content = "parameters", rename_all = "snake_case")]
+pub enum BatchAction {
+    Start,
+    Stop,
+    ForceStop,
+    Pause,
+    Resume,
+    Save,
+    Reset,
+    /// Create a snapshot of each VM
+    Snapshot(CreateSnapshotRequest),
+    /// Apply each VM's snapshot of this name
+    ApplySnapshot(SnapshotNameRequest),
+    /// Delete each VM's snapshot of this name
+    DeleteSnapshot(SnapshotNameRequest),
+}
+
+impl BatchAction {
+    /// Action name as sent in `action`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BatchAction::Start => "start",
+            BatchAction::Stop => "stop",
+            BatchAction::ForceStop => "force_stop",
+            BatchAction::Pause => "pause",
+            BatchAction::Resume => "resume",
+            BatchAction::Save => "save",
+            BatchAction::Reset => "reset",
+            BatchAction::Snapshot(_) => "snapshot",
+            BatchAction::ApplySnapshot(_) => "apply_snapshot",
+            BatchAction::DeleteSnapshot(_) => "delete_snapshot",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SnapshotNameRequest {
+    pub name: String,
+}
+
+/// What happens to the rest of a batch once a VM fails
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchErrorPolicy {
+    /// VMs not yet started are skipped
+    #[default]
+    Stop,
+    /// Every VM is attempted
+    Continue,
+}
+
+/// Outcome of a batch, stored as its job's `result`
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct BatchResultDto {
+    pub action: String,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub skipped: usize,
+    /// One entry per VM, in request order
+    pub results: Vec<BatchItemDto>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct BatchItemDto {
+    pub vm: String,
+    pub status: BatchItemStatus,
+    /// Error code when failed
+    pub code: Option<ErrorCode>,
+    pub error: Option<String>,
+    /// Action output, e.g. the created snapshot
+    pub result: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchItemStatus {
+    Succeeded,
+    Failed,
+    /// Not attempted after an earlier failure or cancellation
+    Skipped,
+}
+
+// =============================================================================
+// Event DTOs
+// =============================================================================
+
+/// Kind of state change reported on `/api/v1/events`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    VmCreated,
+    VmDeleted,
+    VmStateChanged,
+    SnapshotCreated,
+    SnapshotDeleted,
+    SwitchAdded,
+    SwitchRemoved,
+    NodeStateChanged,
+    GroupStateChanged,
+    GroupMoved,
+}
+
+impl EventKind {
+    /// Name used for the SSE `event:` field and the `kind` filter
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EventKind::VmCreated => "vm_created",
+            EventKind::VmDeleted => "vm_deleted",
+            EventKind::VmStateChanged => "vm_state_changed",
+            EventKind::SnapshotCreated => "snapshot_created",
+            EventKind::SnapshotDeleted => "snapshot_deleted",
+            EventKind::SwitchAdded => "switch_added",
+            EventKind::SwitchRemoved => "switch_removed",
+            EventKind::NodeStateChanged => "node_state_changed",
+            EventKind::GroupStateChanged => "group_state_changed",
+            EventKind::GroupMoved => "group_moved",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct EventDto {
+    /// Monotonic id, usable as `Last-Event-ID`
+    pub id: u64,
+    pub kind: EventKind,
+    pub timestamp: String,
+    /// Name of the VM, snapshot, switch, node or group that changed
+    pub subject: String,
+    /// VM the event relates to (VM and snapshot events)
+    pub vm_name: Option<String>,
+    /// State or owner node before the change
+    pub previous: Option<String>,
+    /// State or owner node after the change
+    pub current: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct EventQuery {
+    /// Only events for this VM
+    pub vm: Option<String>,
+    /// Comma-separated event kinds, e.g. `vm_state_changed,group_moved`
+    pub kind: Option<String>,
+    /// Replay retained events after this id (alternative to the `Last-Event-ID` header)
+    pub last_event_id: Option<u64>,
+}
+
+// =============================================================================
+// Audit DTOs
+// =============================================================================
+
+/// One mutating API call
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct AuditRecordDto {
+    pub timestamp: String,
+    /// Principal name (API key name, JWT subject or certificate subject)
+    pub caller: Option<String>,
+    pub role: Option<String>,
+    /// api_key, jwt, client_certificate or anonymous
+    pub auth_method: Option<String>,
+    pub client_addr: Option<String>,
+    pub method: String,
+    /// Matched route template, e.g. `/api/v1/hyperv/vms/{name}/stop`
+    pub route: String,
+    pub path: String,
+    pub path_params: std::collections::BTreeMap<String, String>,
+    /// VM the call acted on, if any
+    pub vm_name: Option<String>,
+    /// JSON request body with secret fields redacted
+    pub request_body: Option<serde_json::Value>,
+    pub status: u16,
+    pub duration_ms: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct AuditQuery {
+    /// Only records at or after this RFC 3339 time
+    pub since: Option<String>,
+    /// Only records before this RFC 3339 time
+    pub until: Option<String>,
+    /// Only records for this VM
+    pub vm: Option<String>,
+    /// Only records by this caller
+    pub caller: Option<String>,
+    /// Maximum number of records, newest first (default: 100, max: 1000)
+    pub limit: Option<usize>,
+}
+
+// =============================================================================
+// Health DTOs
+// =============================================================================
+
+/// Outcome of one health check, or of a whole probe (the worst check)
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthStatus {
+    Pass,
+    /// Degraded, but still serving
+    Warn,
+    /// The probe answers 503
+    Fail,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct HealthCheckDto {
+    /// Check name, e.g. `backend`
+    pub name: String,
+    pub status: HealthStatus,
+    /// How long the check took; the probe timeout if it did not finish
+    pub latency_ms: u64,
+    pub detail: String,
+    pub checked_at: String,
+    /// Result reused from an earlier probe
+    pub cached: bool,
+}
+
+/// `/health/live` and `/health/ready` body
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct HealthReportDto {
+    pub status: HealthStatus,
+    pub checks: Vec<HealthCheckDto>,
+}
+
+// =============================================================================
+// Webhook DTOs
+// =============================================================================
+
+/// Where a webhook subscription was defined
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookSource {
+    /// `[webhooks] subscriptions`; changed by editing the file
+    Config,
+    /// `POST /api/v1/webhooks`
+    Api,
+}
+
+/// Webhook subscription; the secret is never returned
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct WebhookDto {
+    pub name: String,
+    pub url: String,
+    /// Event kinds delivered; all kinds when empty
+    pub events: Vec<EventKind>,
+    /// Only events for this VM
+    pub vm: Option<String>,
+    pub source: WebhookSource,
+}
+
+/// `POST /api/v1/webhooks` body
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CreateWebhookRequest {
+    pub name: String,
+    /// `http` or `https` URL each event is POSTed to
+    pub url: String,
+    /// Key for the `X-Webhook-Signature` HMAC-SHA256
+    pub secret: String,
+    /// Event kinds to deliver; all kinds when empty
+    #[serde(default)]
+    pub events: Vec<EventKind>,
+    /// Only events for this VM
+    pub vm: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryState {
+    /// Waiting for its first attempt or a retry
+    Pending,
+    Delivered,
+    /// Out of attempts or refused by the receiver; retried only on request
+    DeadLettered,
+}
+
+/// One event sent, or to be sent, to one webhook
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct WebhookDeliveryDto {
+    /// Sent as `X-Webhook-Id`; the same on every attempt
+    pub id: u64,
+    pub webhook: String,
+    pub event: EventDto,
+    pub state: DeliveryState,
+    pub attempts: u32,
+    /// HTTP status of the last attempt, if it got a response
+    pub last_status: Option<u16>,
+    pub last_error: Option<String>,
+    pub next_attempt_at: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct WebhookDeliveryQuery {
+    /// Only deliveries to this webhook
+    pub webhook: Option<String>,
+    /// Only deliveries in this state
+    pub state: Option<DeliveryState>,
+    /// Maximum number of deliveries, newest first (default: 100, max: 1000)
+    pub limit: Option<usize>,
+}
+
+// =============================================================================
+// Aggregator DTOs
+// =============================================================================
+
+/// A node that did not contribute to an aggregated response
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct NodeErrorDto {
+    pub node: String,
+    /// HTTP status the node answered with, if it answered
+    pub status: Option<u16>,
+    pub error: String,
+}
@@ -0,0 +1,629 @@
+//! Typed API errors with stable codes and RFC 7807 problem details
+//!
+//! Every failure a handler or middleware returns is an [`ApiError`]: an HTTP
+//! status, a machine-readable [`ErrorCode`] that never changes between
+//! releases, a human-readable message and whether retrying may help.
+//!
+//! Backend errors from `hv`, `clus` and `windows_hyperv` are mapped here so a
+//! missing VM is always `404 vm_not_found` and a VM in the wrong state is
+//! always `409 invalid_state`, whichever backend produced it. Retryability
+//! follows `windows_hyperv::FailureType`; `hv` and `clus` errors are
+//! classified into a `FailureType` first.
+//!
+//! Errors are sent in the `ApiResponse` envelope by default, with `code` added
+//! next to `error`. Clients that send `Accept: application/problem+json` get
+//! an RFC 7807 document instead; the [`negotiate`] middleware does the
+//! rewrite so handlers never look at the `Accept` header.
+
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use clus::ClusError;
+use hv::HvError;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use windows_hyperv::FailureType;
+
+use crate::backend::BackendError;
+use crate::dto::NodeErrorDto;
+use crate::jobs::JobError;
+use crate::locks::LockError;
+use crate::response::ApiResponse;
+use crate::webhooks::WebhookError;
+
+/// Media type of RFC 7807 problem details
+pub const PROBLEM_JSON: &str = "application/problem+json";
+
+/// Prefix of the problem `type` URI; the error code is appended
+pub const PROBLEM_TYPE_PREFIX: &str = "urn:nodeagent:error:";
+
+// =============================================================================
+// Error Codes
+// =============================================================================
+
+/// Stable machine-readable error code
+///
+/// Codes are part of the API contract: new codes may be added, existing
+/// codes are never renamed or reused for a different condition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    /// Malformed request or query parameter
+    BadRequest,
+    /// Missing or invalid credentials
+    Unauthorized,
+    /// Caller's role is too low for the route
+    Forbidden,
+    /// Object not found, no more specific code applies
+    NotFound,
+    VmNotFound,
+    SwitchNotFound,
+    VhdNotFound,
+    SnapshotNotFound,
+    AdapterNotFound,
+    ControllerNotFound,
+    GpuNotFound,
+    DeviceNotFound,
+    NodeNotFound,
+    GroupNotFound,
+    ResourceNotFound,
+    JobNotFound,
+    WebhookNotFound,
+    DeliveryNotFound,
+    /// Request conflicts with the current state of an object
+    Conflict,
+    /// VM is in the wrong state for the operation
+    InvalidState,
+    /// Another operation on the same object is in progress
+    ResourceLocked,
+    /// Job already finished and can no longer be cancelled
+    JobFinished,
+    /// `Idempotency-Key` was already used for a different request
+    IdempotencyKeyReused,
+    /// Request with the same `Idempotency-Key` is still running
+    IdempotencyInProgress,
+    /// Request body failed validation
+    ValidationFailed,
+    /// Backend rejected a parameter
+    InvalidParameter,
+    /// Property is not supported by the host or VM configuration version
+    PropertyNotSupported,
+    /// VM configuration version is too old for the operation
+    VmVersionIncompatible,
+    PayloadTooLarge,
+    /// Operation or feature not available on this node or backend
+    NotSupported,
+    /// Backend could not be reached
+    BackendUnavailable,
+    /// Backend refused the node agent's credentials
+    BackendAuthFailed,
+    /// Node-wide cap on concurrent operations reached
+    TooManyOperations,
+    /// Caller exceeded `[rate_limit]`
+    RateLimited,
+    /// Backend operation timed out
+    Timeout,
+    /// Backend operation failed
+    BackendFailure,
+    /// Unexpected failure in the node agent
+    Internal,
+}
+
+impl ErrorCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::BadRequest => "bad_request",
+            ErrorCode::Unauthorized => "unauthorized",
+            ErrorCode::Forbidden => "forbidden",
+            ErrorCode::NotFound => "not_found",
+            ErrorCode::VmNotFound => "vm_not_found",
+            ErrorCode::SwitchNotFound => "switch_not_found",
+            ErrorCode::VhdNotFound => "vhd_not_found",
+            ErrorCode::SnapshotNotFound => "snapshot_not_found",
+            ErrorCode::AdapterNotFound => "adapter_not_found",
+            ErrorCode::ControllerNotFound => "controller_not_found",
+            ErrorCode::GpuNotFound => "gpu_not_found",
+            ErrorCode::DeviceNotFound => "device_not_found",
+            ErrorCode::NodeNotFound => "node_not_found",
+            ErrorCode::GroupNotFound => "group_not_found",
+            ErrorCode::ResourceNotFound => "resource_not_found",
+            ErrorCode::JobNotFound => "job_not_found",
+            ErrorCode::WebhookNotFound => "webhook_not_found",
+            ErrorCode::DeliveryNotFound => "delivery_not_found",
+            ErrorCode::Conflict => "conflict",
+            ErrorCode::InvalidState => "invalid_state",
+            ErrorCode::ResourceLocked => "resource_locked",
+            ErrorCode::JobFinished => "job_finished",
+            ErrorCode::IdempotencyKeyReused => "idempotency_key_reused",
+            ErrorCode::IdempotencyInProgress => "idempotency_in_progress",
+            ErrorCode::ValidationFailed => "validation_failed",
+            ErrorCode::InvalidParameter => "invalid_parameter",
+            ErrorCode::PropertyNotSupported => "property_not_supported",
+            ErrorCode::VmVersionIncompatible => "vm_version_incompatible",
+            ErrorCode::PayloadTooLarge => "payload_too_large",
+            ErrorCode::NotSupported => "not_supported",
+            ErrorCode::BackendUnavailable => "backend_unavailable",
+            ErrorCode::BackendAuthFailed => "backend_auth_failed",
+            ErrorCode::TooManyOperations => "too_many_operations",
+            ErrorCode::RateLimited => "rate_limited",
+            ErrorCode::Timeout => "timeout",
+            ErrorCode::BackendFailure => "backend_failure",
+            ErrorCode::Internal => "internal",
+        }
+    }
+
+    /// Generic code for a status, used when the caller gives none
+    pub fn for_status(status: StatusCode) -> Self {
+        match status {
+            StatusCode::BAD_REQUEST => ErrorCode::BadRequest,
+            StatusCode::UNAUTHORIZED => ErrorCode::Unauthorized,
+            StatusCode::FORBIDDEN => ErrorCode::Forbidden,
+            StatusCode::NOT_FOUND => ErrorCode::NotFound,
+            StatusCode::CONFLICT => ErrorCode::Conflict,
+            StatusCode::PAYLOAD_TOO_LARGE => ErrorCode::PayloadTooLarge,
+            StatusCode::UNPROCESSABLE_ENTITY => ErrorCode::ValidationFailed,
+            StatusCode::TOO_MANY_REQUESTS => ErrorCode::RateLimited,
+            StatusCode::NOT_IMPLEMENTED => ErrorCode::NotSupported,
+            StatusCode::BAD_GATEWAY | StatusCode::SERVICE_UNAVAILABLE => {
+                ErrorCode::BackendUnavailable
+            }
+            StatusCode::GATEWAY_TIMEOUT => ErrorCode::Timeout,
+            _ => ErrorCode::Internal,
+        }
+    }
+}
+
+impl std::fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+// =============================================================================
+// ApiError
+// =============================================================================
+
+/// Failure returned by handlers and middleware
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiError {
+    pub status: StatusCode,
+    pub code: ErrorCode,
+    pub message: String,
+    /// Whether the same request may succeed if retried later
+    pub retryable: bool,
+    /// Offending request fields of a `422 validation_failed`
+    pub errors: Vec<FieldError>,
+    /// Nodes that failed when aggregating, see [`crate::aggregator`]
+    pub node_errors: Vec<NodeErrorDto>,
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            code,
+            message: message.into(),
+            retryable: false,
+            errors: Vec::new(),
+            node_errors: Vec::new(),
+        }
+    }
+
+    /// `422 validation_failed` listing every violation
+    pub fn validation(errors: Vec<FieldError>) -> Self {
+        let message = errors
+            .iter()
+            .map(|e| format!("{}: {}", e.field, e.message))
+            .collect::<Vec<_>>()
+            .join("; ");
+        let mut err = Self::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            ErrorCode::ValidationFailed,
+            format!("Invalid request: {}", message),
+        );
+        err.errors = errors;
+        err
+    }
+
+    /// Error with the generic code for `status`; 502-504 are retryable
+    pub fn from_status(status: StatusCode, message: impl Into<String>) -> Self {
+        Self::new(status, ErrorCode::for_status(status), message).with_retryable(matches!(
+            status,
+            StatusCode::BAD_GATEWAY | StatusCode::SERVICE_UNAVAILABLE | StatusCode::GATEWAY_TIMEOUT
+        ))
+    }
+
+    pub fn with_retryable(mut self, retryable: bool) -> Self {
+        self.retryable = retryable;
+        self
+    }
+
+    /// RFC 7807 representation; `instance` is the request path
+    pub fn problem(&self, instance: Option<String>) -> ProblemDetails {
+        ProblemDetails {
+            problem_type: format!("{}{}", PROBLEM_TYPE_PREFIX, self.code),
+            title: self
+                .status
+                .canonical_reason()
+                .unwrap_or("Error")
+                .to_string(),
+            status: self.status.as_u16(),
+            detail: self.message.clone(),
+            instance,
+            code: self.code,
+            retryable: self.retryable,
+            errors: self.errors.clone(),
+            node_errors: self.node_errors.clone(),
+        }
+    }
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({}): {}", self.status, self.code, self.message)
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+/// Sends the `ApiResponse` envelope and keeps the error in the response
+/// extensions for [`negotiate`]
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let mut envelope = ApiResponse::error(&self.message);
+        envelope.code = Some(self.code);
+        envelope.errors = self.errors.clone();
+        envelope.node_errors = self.node_errors.clone();
+        let mut response = (self.status, Json(envelope)).into_response();
+        response.extensions_mut().insert(self);
+        response
+    }
+}
+
+/// RFC 7807 problem details document
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ProblemDetails {
+    /// `urn:nodeagent:error:{code}`
+    #[serde(rename = "type")]
+    pub problem_type: String,
+    /// Reason phrase of `status`
+    pub title: String,
+    pub status: u16,
+    pub detail: String,
+    /// Request path that failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instance: Option<String>,
+    pub code: ErrorCode,
+    pub retryable: bool,
+    /// Offending request fields, for `validation_failed`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
+    /// Nodes that failed, for an aggregated request
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub node_errors: Vec<NodeErrorDto>,
+}
+
+/// One offending field of a request body
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct FieldError {
+    /// Request field name, e.g. `memory_mb`
+    pub field: String,
+    pub code: FieldErrorCode,
+    pub message: String,
+}
+
+/// Why a field was rejected
+///
+/// Like [`ErrorCode`], codes are never renamed or reused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FieldErrorCode {
+    /// Empty or missing
+    Required,
+    /// Number outside the allowed range
+    OutOfRange,
+    /// Not one of the accepted values
+    InvalidValue,
+    /// Rejected by a Hyper-V settings rule, e.g. a name character or a file
+    /// extension that does not match the disk format
+    Invalid,
+}
+
+// =============================================================================
+// Backend Error Mapping
+// =============================================================================
+
+fn is_retryable(failure: FailureType) -> bool {
+    matches!(
+        failure,
+        FailureType::Transient | FailureType::ResourceBusy | FailureType::Network
+    )
+}
+
+/// Classify an `hv` error the way `windows_hyperv` classifies its own
+pub fn hv_failure_type(err: &HvError) -> FailureType {
+    match err {
+        HvError::VmNotFound(_)
+        | HvError::SwitchNotFound(_)
+        | HvError::VhdNotFound(_)
+        | HvError::SnapshotNotFound(_) => FailureType::Permanent,
+        HvError::InvalidParameter(_) => FailureType::Configuration,
+        HvError::InvalidState(_) => FailureType::ResourceBusy,
+        HvError::PermissionDenied(_) => FailureType::AuthenticationFailed,
+        HvError::HcsInitFailed(_) => FailureType::Configuration,
+        HvError::ConnectionFailed(_) => FailureType::Network,
+        HvError::Timeout(_) | HvError::IoError(_) => FailureType::Transient,
+        _ => FailureType::Unknown,
+    }
+}
+
+/// Classify a `clus` error the way `windows_hyperv` classifies its own
+pub fn clus_failure_type(err: &ClusError) -> FailureType {
+    match err {
+        ClusError::OpenClusterFailed(_) => FailureType::Network,
+        ClusError::OpenNodeFailed(_)
+        | ClusError::OpenGroupFailed(_)
+        | ClusError::OpenResourceFailed(_)
+        | ClusError::NotFound(_) => FailureType::Permanent,
+        _ => FailureType::Unknown,
+    }
+}
+
+fn hv_status(err: &HvError) -> (StatusCode, ErrorCode) {
+    match err {
+        HvError::VmNotFound(_) => (StatusCode::NOT_FOUND, ErrorCode::VmNotFound),
+        HvError::SwitchNotFound(_) => (StatusCode::NOT_FOUND, ErrorCode::SwitchNotFound),
+        HvError::VhdNotFound(_) => (StatusCode::NOT_FOUND, ErrorCode::VhdNotFound),
+        HvError::SnapshotNotFound(_) => (StatusCode::NOT_FOUND, ErrorCode::SnapshotNotFound),
+        HvError::InvalidParameter(_) => (StatusCode::BAD_REQUEST, ErrorCode::InvalidParameter),
+        HvError::InvalidState(_) => (StatusCode::CONFLICT, ErrorCode::InvalidState),
+        HvError::PermissionDenied(_) => (StatusCode::FORBIDDEN, ErrorCode::Forbidden),
+        HvError::HcsInitFailed(_) | HvError::ConnectionFailed(_) => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            ErrorCode::BackendUnavailable,
+        ),
+        HvError::Timeout(_) => (StatusCode::GATEWAY_TIMEOUT, ErrorCode::Timeout),
+        _ => (StatusCode::INTERNAL_SERVER_ERROR, ErrorCode::BackendFailure),
+    }
+}
+
+fn clus_status(err: &ClusError) -> (StatusCode, ErrorCode) {
+    match err {
+        ClusError::OpenClusterFailed(_) => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            ErrorCode::BackendUnavailable,
+        ),
+        ClusError::OpenNodeFailed(_) => (StatusCode::NOT_FOUND, ErrorCode::NodeNotFound),
+        ClusError::OpenGroupFailed(_) => (StatusCode::NOT_FOUND, ErrorCode::GroupNotFound),
+        ClusError::OpenResourceFailed(_) => (StatusCode::NOT_FOUND, ErrorCode::ResourceNotFound),
+        ClusError::NotFound(_) => (StatusCode::NOT_FOUND, ErrorCode::NotFound),
+        _ => (StatusCode::INTERNAL_SERVER_ERROR, ErrorCode::BackendFailure),
+    }
+}
+
+fn windows_hyperv_status(err: &windows_hyperv::Error) -> (StatusCode, ErrorCode) {
+    use windows_hyperv::Error as E;
+    match err {
+        E::VmNotFound(_) => (StatusCode::NOT_FOUND, ErrorCode::VmNotFound),
+        E::SwitchNotFound(_) => (StatusCode::NOT_FOUND, ErrorCode::SwitchNotFound),
+        E::VhdNotFound(_) => (StatusCode::NOT_FOUND, ErrorCode::VhdNotFound),
+        E::CheckpointNotFound { .. } => (StatusCode::NOT_FOUND, ErrorCode::SnapshotNotFound),
+        E::NetworkAdapterNotFound { .. } => (StatusCode::NOT_FOUND, ErrorCode::AdapterNotFound),
+        E::ControllerNotFound { .. } => (StatusCode::NOT_FOUND, ErrorCode::ControllerNotFound),
+        E::GpuNotFound(_) => (StatusCode::NOT_FOUND, ErrorCode::GpuNotFound),
+        E::DdaDeviceNotFound { .. } => (StatusCode::NOT_FOUND, ErrorCode::DeviceNotFound),
+        E::InvalidState { .. } => (StatusCode::CONFLICT, ErrorCode::InvalidState),
+        E::DdaDeviceAssigned { .. } | E::GpuPartitionUnavailable { .. } => {
+            (StatusCode::CONFLICT, ErrorCode::Conflict)
+        }
+        E::Validation { .. } | E::MissingRequired(_) => (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            ErrorCode::ValidationFailed,
+        ),
+        E::PropertyNotSupported { .. } => (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            ErrorCode::PropertyNotSupported,
+        ),
+        E::VmVersionIncompatible { .. } => (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            ErrorCode::VmVersionIncompatible,
+        ),
+        E::FeatureNotAvailable { .. } => (StatusCode::NOT_IMPLEMENTED, ErrorCode::NotSupported),
+        E::RemoteConnection { .. } => (StatusCode::BAD_GATEWAY, ErrorCode::BackendUnavailable),
+        E::AuthenticationFailed { .. } => (StatusCode::BAD_GATEWAY, ErrorCode::BackendAuthFailed),
+        #[cfg(windows)]
+        E::WmiConnection(_) => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            ErrorCode::BackendUnavailable,
+        ),
+        E::JobTimeout { .. } => (StatusCode::GATEWAY_TIMEOUT, ErrorCode::Timeout),
+        _ => (StatusCode::INTERNAL_SERVER_ERROR, ErrorCode::BackendFailure),
+    }
+}
+
+impl From<BackendError> for ApiError {
+    fn from(err: BackendError) -> Self {
+        let ((status, code), failure) = match &err {
+            BackendError::NotSupported(_) => (
+                (StatusCode::NOT_IMPLEMENTED, ErrorCode::NotSupported),
+                FailureType::Permanent,
+            ),
+            BackendError::HyperV(e) => (hv_status(e), hv_failure_type(e)),
+            BackendError::Cluster(e) => (clus_status(e), clus_failure_type(e)),
+            BackendError::WindowsHyperV(e) => (windows_hyperv_status(e), e.failure_type()),
+        };
+        ApiError::new(status, code, err.to_string()).with_retryable(is_retryable(failure))
+    }
+}
+
+impl From<LockError> for ApiError {
+    fn from(err: LockError) -> Self {
+        let (status, code) = match &err {
+            LockError::Conflict(_) => (StatusCode::CONFLICT, ErrorCode::ResourceLocked),
+            LockError::Busy => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                ErrorCode::TooManyOperations,
+            ),
+        };
+        ApiError::new(status, code, err.to_string()).with_retryable(true)
+    }
+}
+
+impl From<JobError> for ApiError {
+    fn from(err: JobError) -> Self {
+        let (status, code) = match &err {
+            JobError::NotFound(_) => (StatusCode::NOT_FOUND, ErrorCode::JobNotFound),
+            JobError::AlreadyFinished(_) => (StatusCode::CONFLICT, ErrorCode::JobFinished),
+        };
+        ApiError::new(status, code, err.to_string())
+    }
+}
+
+impl From<WebhookError> for ApiError {
+    fn from(err: WebhookError) -> Self {
+        let (status, code) = match &err {
+            WebhookError::NotFound(_) => (StatusCode::NOT_FOUND, ErrorCode::WebhookNotFound),
+            WebhookError::DeliveryNotFound(_) => {
+                (StatusCode::NOT_FOUND, ErrorCode::DeliveryNotFound)
+            }
+            WebhookError::Exists(_)
+            | WebhookError::Configured(_)
+            | WebhookError::NotDeadLettered(_) => (StatusCode::CONFLICT, ErrorCode::Conflict),
+            WebhookError::Io(_) => (StatusCode::INTERNAL_SERVER_ERROR, ErrorCode::Internal),
+        };
+        ApiError::new(status, code, err.to_string())
+    }
+}
+
+// =============================================================================
+// Content Negotiation
+// =============================================================================
+
+/// Whether `Accept` ranks `application/problem+json` at least as high as
+/// `application/json`
+pub fn prefers_problem(headers: &HeaderMap) -> bool {
+    let mut problem = 0.0f32;
+    let mut json = 0.0f32;
+    for accept in headers.get_all(header::ACCEPT) {
+        let Ok(accept) = accept.to_str() else {
+            continue;
+        };
+        for range in accept.split(',') {
+            let mut params = range.split(';').map(str::trim);
+            let media_type = params.next().unwrap_or_default().to_ascii_lowercase();
+            let quality = params
+                .find_map(|p| p.strip_prefix("q="))
+                .and_then(|q| q.parse().ok())
+                .unwrap_or(1.0);
+            match media_type.as_str() {
+                PROBLEM_JSON => problem = problem.max(quality),
+                "application/json" => json = json.max(quality),
+                _ => {}
+            }
+        }
+    }
+    problem > 0.0 && problem >= json
+}
+
+/// Rewrite [`ApiError`] responses as problem details when the client asks
+///
+/// Status and headers such as `WWW-Authenticate` are kept; only the body and
+/// `Content-Type` change.
+pub async fn negotiate(request: Request, next: Next) -> Response {
+    if !prefers_problem(request.headers()) {
+        return next.run(request).await;
+    }
+    let instance = request.uri().path().to_string();
+    let response = next.run(request).await;
+
+    let (mut parts, body) = response.into_parts();
+    let Some(error) = parts.extensions.remove::<ApiError>() else {
+        return Response::from_parts(parts, body);
+    };
+    let problem = match serde_json::to_vec(&error.problem(Some(instance))) {
+        Ok(problem) => problem,
+        Err(_) => return Response::from_parts(parts, body),
+    };
+    parts.headers.remove(header::CONTENT_LENGTH);
+    parts
+        .headers
+        .insert(header::CONTENT_TYPE, HeaderValue::from_static(PROBLEM_JSON));
+    parts.extensions.insert(error);
+    Response::from_parts(parts, Body::from(problem))
+}
+
+// =============================================================================
+// Tests
+// =============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn accept(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ACCEPT, HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_backend_mapping() {
+        let err = ApiError::from(BackendError::from(HvError::VmNotFound("web01".into())));
+        assert_eq!(err.status, StatusCode::NOT_FOUND);
+        assert_eq!(err.code, ErrorCode::VmNotFound);
+        assert!(!err.retryable);
+
+        let err = ApiError::from(BackendError::from(HvError::InvalidState("Running".into())));
+        assert_eq!(err.status, StatusCode::CONFLICT);
+        assert!(err.retryable);
+
+        let err = ApiError::from(BackendError::from(windows_hyperv::Error::Validation {
+            field: "memory_mb",
+            message: "must be a multiple of 2".into(),
+        }));
+        assert_eq!(err.status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(err.code, ErrorCode::ValidationFailed);
+
+        let err = ApiError::from(BackendError::from(
+            windows_hyperv::Error::RemoteConnection {
+                machine: "HV02".into(),
+                message: "RPC server unavailable".into(),
+                failure_type: FailureType::Network,
+            },
+        ));
+        assert_eq!(err.status, StatusCode::BAD_GATEWAY);
+        assert!(err.retryable);
+
+        let err = ApiError::from(BackendError::from(ClusError::OpenGroupFailed("sql".into())));
+        assert_eq!(err.code, ErrorCode::GroupNotFound);
+    }
+
+    #[test]
+    fn test_codes_serialize_as_str() {
+        for code in [
+            ErrorCode::VmNotFound,
+            ErrorCode::IdempotencyKeyReused,
+            ErrorCode::TooManyOperations,
+        ] {
+            assert_eq!(serde_json::to_value(code).unwrap(), code.as_str());
+        }
+    }
+
+    #[test]
+    fn test_prefers_problem() {
+        assert!(prefers_problem(&accept(PROBLEM_JSON)));
+        assert!(prefers_problem(&accept(
+            "application/problem+json, application/json"
+        )));
+        assert!(!prefers_problem(&accept(
+            "application/json, application/problem+json;q=0.5"
+        )));
+        assert!(!prefers_problem(&accept("*/*")));
+        assert!(!prefers_problem(&HeaderMap::new()));
+    }
+}
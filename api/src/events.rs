@@ -0,0 +1,524 @@
+//! VM and cluster state change events
+//!
+//! The backends have no change notifications, so a background watcher polls
+//! an [`Inventory`] of VMs, snapshots, switches, nodes and groups and diffs
+//! successive snapshots. Each difference becomes an [`EventDto`] published
+//! on the [`EventBus`], which `/api/v1/events` streams to clients.
+//!
+//! The watcher starts with the first subscriber; its first poll is the
+//! baseline and produces no events. Recent events are retained so clients
+//! can resume with `Last-Event-ID` after a reconnect. On shutdown the bus
+//! is closed, which ends every open stream.
+
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{SecondsFormat, Utc};
+use tokio::sync::{broadcast, watch};
+use tokio::task::JoinHandle;
+
+use crate::backend::{ClusterBackend, HypervBackend};
+use crate::config::EventsConfig;
+use crate::dto::{EventDto, EventKind};
+
+/// Capacity of the broadcast channel; slower subscribers miss events
+const CHANNEL_CAPACITY: usize = 1024;
+
+// =============================================================================
+// Filter
+// =============================================================================
+
+/// Subscriber-side event filter
+#[derive(Debug, Clone, Default)]
+pub struct EventFilter {
+    /// Only events whose `vm_name` matches
+    pub vm: Option<String>,
+    /// Only these kinds; all kinds when empty
+    pub kinds: Vec<EventKind>,
+}
+
+impl EventFilter {
+    /// Build a filter from a VM name and a comma-separated kind list
+    pub fn parse(vm: Option<String>, kinds: Option<&str>) -> Result<Self, String> {
+        let kinds = kinds
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|k| !k.is_empty())
+            .map(|k| {
+                serde_json::from_value(serde_json::Value::String(k.to_string()))
+                    .map_err(|_| format!("Unknown event kind: {}", k))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { vm, kinds })
+    }
+
+    pub fn matches(&self, event: &EventDto) -> bool {
+        let vm_matches = match &self.vm {
+            Some(vm) => event.vm_name.as_deref() == Some(vm.as_str()),
+            None => true,
+        };
+        vm_matches && (self.kinds.is_empty() || self.kinds.contains(&event.kind))
+    }
+}
+
+// =============================================================================
+// Bus
+// =============================================================================
+
+struct History {
+    events: VecDeque<EventDto>,
+    next_id: u64,
+}
+
+/// Publishes events to subscribers and retains recent events for resume
+pub struct EventBus {
+    sender: broadcast::Sender<EventDto>,
+    history: Mutex<History>,
+    history_limit: usize,
+    poll_interval: Duration,
+    watcher: Mutex<Option<JoinHandle<()>>>,
+    closed: watch::Sender<bool>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new(&EventsConfig::default())
+    }
+}
+
+impl EventBus {
+    pub fn new(config: &EventsConfig) -> Self {
+        let (sender, _) = broadcast::channel(CHANNEL_CAPACITY);
+        Self {
+            sender,
+            history: Mutex::new(History {
+                events: VecDeque::new(),
+                next_id: 1,
+            }),
+            history_limit: config.history_limit,
+            poll_interval: Duration::from_millis(config.poll_interval_ms.max(1)),
+            watcher: Mutex::new(None),
+            closed: watch::Sender::new(false),
+        }
+    }
+
+    /// Assign the next id, retain the event and send it to subscribers
+    pub fn publish(&self, change: Change) -> EventDto {
+        let mut history = self.history.lock().unwrap();
+        let event = EventDto {
+            id: history.next_id,
+            kind: change.kind,
+            timestamp: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+            subject: change.subject,
+            vm_name: change.vm_name,
+            previous: change.previous,
+            current: change.current,
+        };
+        history.next_id += 1;
+        history.events.push_back(event.clone());
+        while history.events.len() > self.history_limit {
+            history.events.pop_front();
+        }
+        // Sent under the history lock so subscribe() sees each event exactly once
+        let _ = self.sender.send(event.clone());
+        event
+    }
+
+    /// Subscribe to new events, replaying retained events after `last_event_id`
+    pub fn subscribe(
+        &self,
+        last_event_id: Option<u64>,
+    ) -> (Vec<EventDto>, broadcast::Receiver<EventDto>) {
+        let history = self.history.lock().unwrap();
+        let receiver = self.sender.subscribe();
+        let replay = match last_event_id {
+            Some(last) => history
+                .events
+                .iter()
+                .filter(|e| e.id > last)
+                .cloned()
+                .collect(),
+            None => Vec::new(),
+        };
+        (replay, receiver)
+    }
+
+    /// Start polling the backends if the watcher is not already running
+    pub fn start_watcher(
+        self: &Arc<Self>,
+        hyperv: Arc<dyn HypervBackend>,
+        cluster: Arc<dyn ClusterBackend>,
+    ) {
+        let mut watcher = self.watcher.lock().unwrap();
+        if watcher.is_some() {
+            return;
+        }
+        let bus = Arc::downgrade(self);
+        let interval = self.poll_interval;
+        *watcher = Some(tokio::spawn(async move {
+            let mut previous: Option<Inventory> = None;
+            loop {
+                let (hyperv, cluster) = (hyperv.clone(), cluster.clone());
+                let current = match tokio::task::spawn_blocking(move || {
+                    Inventory::collect(&*hyperv, &*cluster)
+                })
+                .await
+                {
+                    Ok(inventory) => inventory,
+                    Err(e) => {
+                        tracing::warn!("Event watcher poll failed: {}", e);
+                        tokio::time::sleep(interval).await;
+                        continue;
+                    }
+                };
+                let Some(bus) = bus.upgrade() else { break };
+                if let Some(previous) = &previous {
+                    for change in previous.diff(&current) {
+                        bus.publish(change);
+                    }
+                }
+                drop(bus);
+                previous = Some(current);
+                tokio::time::sleep(interval).await;
+            }
+        }));
+    }
+
+    /// Stop the watcher; subscribers stay connected but receive no new events
+    pub fn stop_watcher(&self) {
+        if let Some(handle) = self.watcher.lock().unwrap().take() {
+            handle.abort();
+        }
+    }
+
+    /// Stop the watcher and end all subscriber streams
+    pub fn close(&self) {
+        self.stop_watcher();
+        self.closed.send_replace(true);
+    }
+
+    /// Becomes `true` once the bus is closed
+    pub fn closed(&self) -> watch::Receiver<bool> {
+        self.closed.subscribe()
+    }
+}
+
+impl Drop for EventBus {
+    fn drop(&mut self) {
+        self.stop_watcher();
+    }
+}
+
+// =============================================================================
+// Inventory
+// =============================================================================
+
+/// A change detected between two inventory snapshots
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    pub kind: EventKind,
+    pub subject: String,
+    pub vm_name: Option<String>,
+    pub previous: Option<String>,
+    pub current: Option<String>,
+}
+
+impl Change {
+    fn new(kind: EventKind, subject: &str) -> Self {
+        Self {
+            kind,
+            subject: subject.to_string(),
+            vm_name: None,
+            previous: None,
+            current: None,
+        }
+    }
+
+    fn vm(mut self, vm_name: &str) -> Self {
+        self.vm_name = Some(vm_name.to_string());
+        self
+    }
+
+    fn values(mut self, previous: Option<&str>, current: Option<&str>) -> Self {
+        self.previous = previous.map(str::to_string);
+        self.current = current.map(str::to_string);
+        self
+    }
+}
+
+/// Point-in-time view of the watched objects
+///
+/// A section is `None` when its backend call failed; it is then skipped
+/// rather than reported as everything having been removed.
+#[derive(Debug, Clone, Default)]
+pub struct Inventory {
+    /// VM name -> state
+    pub vms: Option<BTreeMap<String, String>>,
+    /// VM name -> snapshot names, for VMs whose snapshots could be listed
+    pub snapshots: BTreeMap<String, BTreeSet<String>>,
+    pub switches: Option<BTreeSet<String>>,
+    /// Node name -> state
+    pub nodes: Option<BTreeMap<String, String>>,
+    /// Group name -> (state, owner node)
+    pub groups: Option<BTreeMap<String, (String, Option<String>)>>,
+}
+
+impl Inventory {
+    /// Query the backends for the current inventory
+    pub fn collect(hyperv: &dyn HypervBackend, cluster: &dyn ClusterBackend) -> Self {
+        let vms: Option<BTreeMap<_, _>> = hyperv
+            .list_vms()
+            .ok()
+            .map(|vms| vms.into_iter().map(|vm| (vm.name, vm.state)).collect());
+        let snapshots = vms
+            .iter()
+            .flat_map(|vms| vms.keys())
+            .filter_map(|vm| {
+                let snapshots = hyperv.list_snapshots(vm).ok()?;
+                Some((vm.clone(), snapshots.into_iter().map(|s| s.name).collect()))
+            })
+            .collect();
+        Self {
+            vms,
+            snapshots,
+            switches: hyperv
+                .list_switches()
+                .ok()
+                .map(|switches| switches.into_iter().map(|s| s.name).collect()),
+            nodes: cluster
+                .list_nodes(None)
+                .ok()
+                .map(|nodes| nodes.into_iter().map(|n| (n.name, n.state)).collect()),
+            groups: cluster.list_groups(None).ok().map(|groups| {
+                groups
+                    .into_iter()
+                    .map(|g| (g.name, (g.state, g.owner_node)))
+                    .collect()
+            }),
+        }
+    }
+
+    /// Changes from `self` to `current`
+    pub fn diff(&self, current: &Inventory) -> Vec<Change> {
+        let mut changes = Vec::new();
+
+        if let (Some(old), Some(new)) = (&self.vms, &current.vms) {
+            for (name, state) in new {
+                match old.get(name) {
+                    None => changes.push(
+                        Change::new(EventKind::VmCreated, name)
+                            .vm(name)
+                            .values(None, Some(state)),
+                    ),
+                    Some(previous) if previous != state => changes.push(
+                        Change::new(EventKind::VmStateChanged, name)
+                            .vm(name)
+                            .values(Some(previous), Some(state)),
+                    ),
+                    Some(_) => {}
+                }
+            }
+            for (name, state) in old {
+                if !new.contains_key(name) {
+                    changes.push(
+                        Change::new(EventKind::VmDeleted, name)
+                            .vm(name)
+                            .values(Some(state), None),
+                    );
+                }
+            }
+        }
+
+        for (vm, new) in &current.snapshots {
+            let Some(old) = self.snapshots.get(vm) else {
+                continue;
+            };
+            for name in new.difference(old) {
+                changes.push(Change::new(EventKind::SnapshotCreated, name).vm(vm));
+            }
+            for name in old.difference(new) {
+                changes.push(Change::new(EventKind::SnapshotDeleted, name).vm(vm));
+            }
+        }
+
+        if let (Some(old), Some(new)) = (&self.switches, &current.switches) {
+            for name in new.difference(old) {
+                changes.push(Change::new(EventKind::SwitchAdded, name));
+            }
+            for name in old.difference(new) {
+                changes.push(Change::new(EventKind::SwitchRemoved, name));
+            }
+        }
+
+        if let (Some(old), Some(new)) = (&self.nodes, &current.nodes) {
+            for (name, state) in new {
+                if let Some(previous) = old.get(name).filter(|p| *p != state) {
+                    changes.push(
+                        Change::new(EventKind::NodeStateChanged, name)
+                            .values(Some(previous), Some(state)),
+                    );
+                }
+            }
+        }
+
+        if let (Some(old), Some(new)) = (&self.groups, &current.groups) {
+            for (name, (state, owner)) in new {
+                let Some((previous_state, previous_owner)) = old.get(name) else {
+                    continue;
+                };
+                if previous_owner != owner {
+                    changes.push(
+                        Change::new(EventKind::GroupMoved, name)
+                            .values(previous_owner.as_deref(), owner.as_deref()),
+                    );
+                }
+                if previous_state != state {
+                    changes.push(
+                        Change::new(EventKind::GroupStateChanged, name)
+                            .values(Some(previous_state), Some(state)),
+                    );
+                }
+            }
+        }
+
+        changes
+    }
+}
+
+// =============================================================================
+// Tests
+// =============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map(entries: &[(&str, &str)]) -> BTreeMap<String, String> {
+        entries
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_diff_vms_and_snapshots() {
+        let old = Inventory {
+            vms: Some(map(&[("web", "Off"), ("db", "Running")])),
+            snapshots: [("web".to_string(), BTreeSet::from(["base".to_string()]))].into(),
+            ..Inventory::default()
+        };
+        let new = Inventory {
+            vms: Some(map(&[("web", "Running"), ("cache", "Off")])),
+            snapshots: [("web".to_string(), BTreeSet::from(["patched".to_string()]))].into(),
+            ..Inventory::default()
+        };
+
+        let kinds: Vec<_> = old
+            .diff(&new)
+            .into_iter()
+            .map(|c| (c.kind, c.subject))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (EventKind::VmCreated, "cache".to_string()),
+                (EventKind::VmStateChanged, "web".to_string()),
+                (EventKind::VmDeleted, "db".to_string()),
+                (EventKind::SnapshotCreated, "patched".to_string()),
+                (EventKind::SnapshotDeleted, "base".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diff_skips_failed_sections() {
+        let old = Inventory {
+            vms: Some(map(&[("web", "Off")])),
+            nodes: Some(map(&[("node1", "Up")])),
+            ..Inventory::default()
+        };
+        let new = Inventory {
+            vms: None,
+            nodes: Some(map(&[("node1", "Paused")])),
+            ..Inventory::default()
+        };
+
+        let changes = old.diff(&new);
+        assert_eq!(
+            changes,
+            vec![Change::new(EventKind::NodeStateChanged, "node1")
+                .values(Some("Up"), Some("Paused"))]
+        );
+    }
+
+    #[test]
+    fn test_diff_group_move() {
+        let group = |owner: &str| {
+            Some(BTreeMap::from([(
+                "sql".to_string(),
+                ("Online".to_string(), Some(owner.to_string())),
+            )]))
+        };
+        let old = Inventory {
+            groups: group("node1"),
+            ..Inventory::default()
+        };
+        let new = Inventory {
+            groups: group("node2"),
+            ..Inventory::default()
+        };
+
+        assert_eq!(
+            old.diff(&new),
+            vec![Change::new(EventKind::GroupMoved, "sql").values(Some("node1"), Some("node2"))]
+        );
+    }
+
+    #[test]
+    fn test_resume_after_last_event_id() {
+        let bus = EventBus::new(&EventsConfig {
+            history_limit: 2,
+            ..EventsConfig::default()
+        });
+        for name in ["a", "b", "c"] {
+            bus.publish(Change::new(EventKind::SwitchAdded, name));
+        }
+
+        let (replay, _) = bus.subscribe(Some(0));
+        let ids: Vec<_> = replay.iter().map(|e| e.id).collect();
+        assert_eq!(ids, vec![2, 3]);
+
+        let (replay, _) = bus.subscribe(Some(2));
+        assert_eq!(replay.len(), 1);
+        assert_eq!(replay[0].subject, "c");
+
+        let (replay, _) = bus.subscribe(None);
+        assert!(replay.is_empty());
+    }
+
+    #[test]
+    fn test_filter() {
+        let filter = EventFilter::parse(
+            Some("web".to_string()),
+            Some("vm_state_changed, snapshot_created"),
+        )
+        .unwrap();
+        let event = |kind, vm: Option<&str>| EventDto {
+            id: 1,
+            kind,
+            timestamp: String::new(),
+            subject: String::new(),
+            vm_name: vm.map(str::to_string),
+            previous: None,
+            current: None,
+        };
+        assert!(filter.matches(&event(EventKind::VmStateChanged, Some("web"))));
+        assert!(!filter.matches(&event(EventKind::VmStateChanged, Some("db"))));
+        assert!(!filter.matches(&event(EventKind::VmDeleted, Some("web"))));
+        assert!(!filter.matches(&event(EventKind::GroupMoved, None)));
+
+        assert!(EventFilter::parse(None, Some("vm_exploded")).is_err());
+    }
+}
@@ -0,0 +1,27 @@
+//! Audit log handlers
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+
+use crate::audit::AuditError;
+use crate::dto::*;
+use crate::response::{api_error, ApiResponse, ApiResult};
+use crate::SharedState;
+
+pub async fn audit_query(
+    State(state): State<SharedState>,
+    Query(query): Query<AuditQuery>,
+) -> ApiResult<Vec<AuditRecordDto>> {
+    let records = state.audit.query(&query).map_err(|e| {
+        let status = match e {
+            AuditError::NotQueryable(_) => StatusCode::NOT_IMPLEMENTED,
+            AuditError::InvalidTime(..) => StatusCode::BAD_REQUEST,
+            AuditError::Io(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        api_error(status, &e.to_string())
+    })?;
+    Ok(Json(ApiResponse::success(records)))
+}
@@ -0,0 +1,129 @@
+//! Event stream handlers
+
+use std::convert::Infallible;
+
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Query, State,
+    },
+    http::{HeaderMap, StatusCode},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        Response,
+    },
+};
+use tokio_stream::{
+    wrappers::{errors::BroadcastStreamRecvError, BroadcastStream, WatchStream},
+    Stream, StreamExt,
+};
+
+use crate::dto::*;
+use crate::error::ApiError;
+use crate::events::EventFilter;
+use crate::response::api_error;
+use crate::SharedState;
+
+type EventResult<T> = Result<T, ApiError>;
+
+/// Replayed and live events for one subscriber, filtered
+///
+/// The stream ends when the event bus is closed on shutdown.
+///
+/// The `last_event_id` query parameter takes precedence over the
+/// `Last-Event-ID` header that `EventSource` sends on reconnect.
+fn subscribe(
+    state: &SharedState,
+    headers: &HeaderMap,
+    query: EventQuery,
+) -> EventResult<impl Stream<Item = EventDto> + Send + 'static> {
+    let filter = EventFilter::parse(query.vm, query.kind.as_deref())
+        .map_err(|e| api_error(StatusCode::BAD_REQUEST, &e))?;
+    let last_event_id = match (query.last_event_id, headers.get("last-event-id")) {
+        (Some(id), _) => Some(id),
+        (None, Some(value)) => Some(
+            value
+                .to_str()
+                .ok()
+                .and_then(|v| v.trim().parse().ok())
+                .ok_or_else(|| {
+                    api_error(
+                        StatusCode::BAD_REQUEST,
+                        "Last-Event-ID must be a numeric event id",
+                    )
+                })?,
+        ),
+        (None, None) => None,
+    };
+
+    let (replay, receiver) = state.events.subscribe(last_event_id);
+    state
+        .events
+        .start_watcher(state.hyperv.clone(), state.cluster.clone());
+
+    let live = BroadcastStream::new(receiver).filter_map(|result| match result {
+        Ok(event) => Some(event),
+        Err(BroadcastStreamRecvError::Lagged(missed)) => {
+            tracing::warn!("Event subscriber lagged behind; {} events dropped", missed);
+            None
+        }
+    });
+    let closed = WatchStream::new(state.events.closed())
+        .filter(|closed| *closed)
+        .map(|_| None);
+    Ok(tokio_stream::iter(replay)
+        .chain(live)
+        .filter(move |event| filter.matches(event))
+        .map(Some)
+        .merge(closed)
+        .take_while(Option::is_some)
+        .filter_map(|event| event))
+}
+
+/// `GET /api/v1/events` as Server-Sent Events
+///
+/// Each event's SSE `id` is the event id and its `event` name the kind.
+pub async fn events_stream(
+    State(state): State<SharedState>,
+    headers: HeaderMap,
+    Query(query): Query<EventQuery>,
+) -> EventResult<Sse<impl Stream<Item = Result<Event, Infallible>>>> {
+    let events = subscribe(&state, &headers, query)?.map(|event| {
+        let data = serde_json::to_string(&event).expect("EventDto serializes to JSON");
+        Ok(Event::default()
+            .id(event.id.to_string())
+            .event(event.kind.as_str())
+            .data(data))
+    });
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+/// `GET /api/v1/events/ws`: the same events as JSON text messages
+pub async fn events_ws(
+    State(state): State<SharedState>,
+    headers: HeaderMap,
+    Query(query): Query<EventQuery>,
+    ws: WebSocketUpgrade,
+) -> EventResult<Response> {
+    let events = subscribe(&state, &headers, query)?;
+    Ok(ws.on_upgrade(move |socket| forward(socket, events)))
+}
+
+async fn forward(mut socket: WebSocket, events: impl Stream<Item = EventDto>) {
+    tokio::pin!(events);
+    loop {
+        tokio::select! {
+            event = events.next() => {
+                let Some(event) = event else { break };
+                let text = serde_json::to_string(&event).expect("EventDto serializes to JSON");
+                if socket.send(Message::Text(text.into())).await.is_err() {
+                    break;
+                }
+            }
+            message = socket.recv() => match message {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
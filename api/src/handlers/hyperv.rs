@@ -1,1175 +1,1173 @@
 //! Hyper-V API handlers
 
-use axum::{extract::Path, extract::Query, http::StatusCode, Json};
+use std::collections::HashSet;
+use std::sync::Arc;
 
-use crate::dto::*;
-use crate::response::{api_error, ApiResponse, ApiResult};
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
 
-#[cfg(windows)]
-use hv::{HyperV, SnapshotType, SwitchType, VhdType, VmGeneration};
+use crate::batch::{Batch, DEFAULT_PARALLELISM, MAX_PARALLELISM};
+use crate::dto::*;
+use crate::error::{ApiError, ErrorCode};
+use crate::listing::ListQuery;
+use crate::locks::LockKey;
+use crate::response::{
+    accepted, backend_error, lock_error, AcceptedResult, ApiResponse, ApiResult, ListResult,
+};
+use crate::validation::Valid;
+use crate::SharedState;
 
 // =============================================================================
-// Windows Implementation
+// Host
 // =============================================================================
 
-#[cfg(windows)]
-pub async fn hyperv_host_info() -> ApiResult<HostInfoDto> {
-    let hv =
-        HyperV::new().map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    let info = hv
-        .host_info()
-        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    Ok(Json(ApiResponse::success(HostInfoDto {
-        computer_name: info.computer_name,
-        logical_processor_count: info.logical_processor_count,
-        memory_capacity_bytes: info.memory_capacity_bytes,
-        vm_path: info.vm_path,
-        vhd_path: info.vhd_path,
-    })))
+pub async fn hyperv_host_info(State(state): State<SharedState>) -> ApiResult<HostInfoDto> {
+    let info = state.hyperv.host_info().map_err(backend_error)?;
+    Ok(Json(ApiResponse::success(info)))
 }
 
-#[cfg(windows)]
-pub async fn hyperv_list_adapters() -> ApiResult<Vec<NetworkAdapterDto>> {
-    let hv =
-        HyperV::new().map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    let adapters = hv
-        .list_network_adapters()
-        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    let dtos = adapters
-        .into_iter()
-        .map(|a| NetworkAdapterDto {
-            name: a.name,
-            description: a.description,
-            mac_address: a.mac_address,
-            link_speed: a.link_speed,
-        })
-        .collect();
-    Ok(Json(ApiResponse::success(dtos)))
-}
-
-#[cfg(windows)]
-pub async fn hyperv_list_vms() -> ApiResult<Vec<VmDto>> {
-    let hv =
-        HyperV::new().map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    let vms = hv
-        .list_vms()
-        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    let dtos: Vec<VmDto> = vms
-        .into_iter()
-        .map(|mut vm| VmDto {
-            id: vm.id().to_string(),
-            name: vm.name().to_string(),
-            state: vm.state().map(|s| format!("{:?}", s)).unwrap_or_default(),
-            cpu_count: vm.cpu_count().ok(),
-            memory_mb: vm.memory_mb().ok(),
-            uptime_seconds: None,
-        })
-        .collect();
-    Ok(Json(ApiResponse::success(dtos)))
-}
-
-#[cfg(windows)]
-pub async fn hyperv_get_vm(Path(name): Path<String>) -> ApiResult<VmDto> {
-    let hv =
-        HyperV::new().map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    let mut vm = hv
-        .get_vm(&name)
-        .map_err(|e| api_error(StatusCode::NOT_FOUND, &e.to_string()))?;
-    Ok(Json(ApiResponse::success(VmDto {
-        id: vm.id().to_string(),
-        name: vm.name().to_string(),
-        state: vm.state().map(|s| format!("{:?}", s)).unwrap_or_default(),
-        cpu_count: vm.cpu_count().ok(),
-        memory_mb: vm.memory_mb().ok(),
-        uptime_seconds: None,
-    })))
+/// `GET /host/capabilities`: VM configuration versions and optional
+/// features the host supports
+pub async fn hyperv_host_capabilities(
+    State(state): State<SharedState>,
+) -> ApiResult<HostCapabilitiesDto> {
+    let capabilities = state.hyperv.host_capabilities().map_err(backend_error)?;
+    Ok(Json(ApiResponse::success(capabilities)))
 }
 
-#[cfg(windows)]
-pub async fn hyperv_create_vm(Json(req): Json<CreateVmRequest>) -> ApiResult<VmDto> {
-    let hv =
-        HyperV::new().map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    let generation = match req.generation.unwrap_or(2) {
-        1 => VmGeneration::Gen1,
-        _ => VmGeneration::Gen2,
-    };
-    let mut vm = hv
-        .create_vm(
-            &req.name,
-            req.memory_mb,
-            req.cpu_count.unwrap_or(2),
-            generation,
-            &req.vhd_path,
-            req.vhd_size_bytes,
-            req.switch_name.as_deref(),
-        )
-        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    Ok(Json(ApiResponse::success(VmDto {
-        id: vm.id().to_string(),
-        name: vm.name().to_string(),
-        state: vm.state().map(|s| format!("{:?}", s)).unwrap_or_default(),
-        cpu_count: vm.cpu_count().ok(),
-        memory_mb: vm.memory_mb().ok(),
-        uptime_seconds: None,
-    })))
-}
-
-#[cfg(windows)]
-pub async fn hyperv_delete_vm(Path(name): Path<String>) -> ApiResult<&'static str> {
-    let hv =
-        HyperV::new().map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    hv.delete_vm(&name)
-        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    Ok(Json(ApiResponse::success("ok")))
-}
-
-#[cfg(windows)]
-pub async fn hyperv_start_vm(Path(name): Path<String>) -> ApiResult<&'static str> {
-    let hv =
-        HyperV::new().map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    let mut vm = hv
-        .get_vm(&name)
-        .map_err(|e| api_error(StatusCode::NOT_FOUND, &e.to_string()))?;
-    vm.start()
-        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    Ok(Json(ApiResponse::success("ok")))
-}
-
-#[cfg(windows)]
-pub async fn hyperv_stop_vm(Path(name): Path<String>) -> ApiResult<&'static str> {
-    let hv =
-        HyperV::new().map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    let mut vm = hv
-        .get_vm(&name)
-        .map_err(|e| api_error(StatusCode::NOT_FOUND, &e.to_string()))?;
-    vm.stop()
-        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    Ok(Json(ApiResponse::success("ok")))
-}
-
-#[cfg(windows)]
-pub async fn hyperv_force_stop_vm(Path(name): Path<String>) -> ApiResult<&'static str> {
-    let hv =
-        HyperV::new().map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    let mut vm = hv
-        .get_vm(&name)
-        .map_err(|e| api_error(StatusCode::NOT_FOUND, &e.to_string()))?;
-    vm.force_stop()
-        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    Ok(Json(ApiResponse::success("ok")))
-}
-
-#[cfg(windows)]
-pub async fn hyperv_pause_vm(Path(name): Path<String>) -> ApiResult<&'static str> {
-    let hv =
-        HyperV::new().map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    let mut vm = hv
-        .get_vm(&name)
-        .map_err(|e| api_error(StatusCode::NOT_FOUND, &e.to_string()))?;
-    vm.pause()
-        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    Ok(Json(ApiResponse::success("ok")))
-}
-
-#[cfg(windows)]
-pub async fn hyperv_resume_vm(Path(name): Path<String>) -> ApiResult<&'static str> {
-    let hv =
-        HyperV::new().map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    let mut vm = hv
-        .get_vm(&name)
-        .map_err(|e| api_error(StatusCode::NOT_FOUND, &e.to_string()))?;
-    vm.resume()
-        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    Ok(Json(ApiResponse::success("ok")))
+pub async fn hyperv_list_adapters(
+    State(state): State<SharedState>,
+    list: ListQuery,
+) -> ListResult<NetworkAdapterDto> {
+    let adapters = state
+        .hyperv
+        .list_network_adapters()
+        .map_err(backend_error)?;
+    list.page(adapters)
 }
 
-#[cfg(windows)]
-pub async fn hyperv_save_vm(Path(name): Path<String>) -> ApiResult<&'static str> {
-    let hv =
-        HyperV::new().map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    let mut vm = hv
-        .get_vm(&name)
-        .map_err(|e| api_error(StatusCode::NOT_FOUND, &e.to_string()))?;
-    vm.save()
-        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    Ok(Json(ApiResponse::success("ok")))
-}
+// =============================================================================
+// VMs
+// =============================================================================
 
-#[cfg(windows)]
-pub async fn hyperv_reset_vm(Path(name): Path<String>) -> ApiResult<&'static str> {
-    let hv =
-        HyperV::new().map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    let mut vm = hv
-        .get_vm(&name)
-        .map_err(|e| api_error(StatusCode::NOT_FOUND, &e.to_string()))?;
-    vm.force_stop()
-        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    vm.start()
-        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    Ok(Json(ApiResponse::success("ok")))
+pub async fn hyperv_list_vms(
+    State(state): State<SharedState>,
+    list: ListQuery,
+) -> ListResult<VmDto> {
+    let vms = state.hyperv.list_vms().map_err(backend_error)?;
+    list.page(vms)
 }
 
-#[cfg(windows)]
-pub async fn hyperv_export_vm(
+pub async fn hyperv_get_vm(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+) -> ApiResult<VmDto> {
+    let vm = state.hyperv.get_vm(&name).map_err(backend_error)?;
+    Ok(Json(ApiResponse::success(vm)))
+}
+
+pub async fn hyperv_create_vm(
+    State(state): State<SharedState>,
+    Valid(req): Valid<CreateVmRequest>,
+) -> ApiResult<VmDto> {
+    let _guard = state
+        .locks
+        .acquire([LockKey::vm(&req.name), LockKey::vhd(&req.vhd_path)])
+        .await
+        .map_err(lock_error)?;
+    let vm = state.hyperv.create_vm(&req).map_err(backend_error)?;
+    Ok(Json(ApiResponse::success(vm)))
+}
+
+pub async fn hyperv_update_vm(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+    Valid(req): Valid<UpdateVmRequest>,
+) -> ApiResult<VmDto> {
+    let _guard = state
+        .locks
+        .acquire([LockKey::vm(&name)])
+        .await
+        .map_err(lock_error)?;
+    let vm = state.hyperv.update_vm(&name, &req).map_err(backend_error)?;
+    Ok(Json(ApiResponse::success(vm)))
+}
+
+pub async fn hyperv_delete_vm(
+    State(state): State<SharedState>,
     Path(name): Path<String>,
-    Json(req): Json<ExportVmRequest>,
 ) -> ApiResult<&'static str> {
-    let hv =
-        HyperV::new().map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    hv.export_vm(&name, &req.path)
-        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
+    let _guard = state
+        .locks
+        .acquire([LockKey::vm(&name)])
+        .await
+        .map_err(lock_error)?;
+    state.hyperv.delete_vm(&name).map_err(backend_error)?;
     Ok(Json(ApiResponse::success("ok")))
 }
 
-#[cfg(windows)]
-pub async fn hyperv_vm_disks(Path(name): Path<String>) -> ApiResult<Vec<DiskDto>> {
-    let hv =
-        HyperV::new().map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    let disks = hv
-        .get_hard_disk_drives(&name)
-        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    let dtos = disks
-        .into_iter()
-        .map(|d| DiskDto {
-            controller_type: d.controller_type,
-            controller_number: d.controller_number,
-            controller_location: d.controller_location,
-            path: d.path,
-        })
-        .collect();
-    Ok(Json(ApiResponse::success(dtos)))
-}
-
-#[cfg(windows)]
-pub async fn hyperv_attach_disk(
+pub async fn hyperv_start_vm(
+    State(state): State<SharedState>,
     Path(name): Path<String>,
-    Json(req): Json<AttachDiskRequest>,
 ) -> ApiResult<&'static str> {
-    let hv =
-        HyperV::new().map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    hv.add_hard_disk_drive(&name, &req.vhd_path)
-        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
+    let _guard = state
+        .locks
+        .acquire([LockKey::vm(&name)])
+        .await
+        .map_err(lock_error)?;
+    state.hyperv.start_vm(&name).map_err(backend_error)?;
     Ok(Json(ApiResponse::success("ok")))
 }
 
-#[cfg(windows)]
-pub async fn hyperv_detach_disk(
+pub async fn hyperv_stop_vm(
+    State(state): State<SharedState>,
     Path(name): Path<String>,
-    Json(req): Json<DetachDiskRequest>,
 ) -> ApiResult<&'static str> {
-    let hv =
-        HyperV::new().map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    hv.remove_hard_disk_drive(&name, req.controller_number, req.controller_location)
-        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
+    let _guard = state
+        .locks
+        .acquire([LockKey::vm(&name)])
+        .await
+        .map_err(lock_error)?;
+    state.hyperv.stop_vm(&name).map_err(backend_error)?;
     Ok(Json(ApiResponse::success("ok")))
 }
 
-#[cfg(windows)]
-pub async fn hyperv_vm_dvd(Path(name): Path<String>) -> ApiResult<Vec<DiskDto>> {
-    let hv =
-        HyperV::new().map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    let dvds = hv
-        .get_dvd_drives(&name)
-        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    let dtos = dvds
-        .into_iter()
-        .map(|d| DiskDto {
-            controller_type: d.controller_type,
-            controller_number: d.controller_number,
-            controller_location: d.controller_location,
-            path: d.path,
-        })
-        .collect();
-    Ok(Json(ApiResponse::success(dtos)))
-}
-
-#[cfg(windows)]
-pub async fn hyperv_mount_iso(
+pub async fn hyperv_force_stop_vm(
+    State(state): State<SharedState>,
     Path(name): Path<String>,
-    Json(req): Json<MountIsoRequest>,
 ) -> ApiResult<&'static str> {
-    let hv =
-        HyperV::new().map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    hv.mount_iso(&name, &req.iso_path)
-        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
+    let _guard = state
+        .locks
+        .acquire([LockKey::vm(&name)])
+        .await
+        .map_err(lock_error)?;
+    state.hyperv.force_stop_vm(&name).map_err(backend_error)?;
     Ok(Json(ApiResponse::success("ok")))
 }
 
-#[cfg(windows)]
-pub async fn hyperv_eject_iso(Path(name): Path<String>) -> ApiResult<&'static str> {
-    let hv =
-        HyperV::new().map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    hv.eject_iso(&name)
-        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    Ok(Json(ApiResponse::success("ok")))
-}
-
-#[cfg(windows)]
-pub async fn hyperv_set_boot_order(
+pub async fn hyperv_pause_vm(
+    State(state): State<SharedState>,
     Path(name): Path<String>,
-    Json(req): Json<BootOrderRequest>,
 ) -> ApiResult<&'static str> {
-    let hv =
-        HyperV::new().map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    let devices: Vec<&str> = req.devices.iter().map(|s| s.as_str()).collect();
-    hv.set_boot_order(&name, &devices)
-        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
+    let _guard = state
+        .locks
+        .acquire([LockKey::vm(&name)])
+        .await
+        .map_err(lock_error)?;
+    state.hyperv.pause_vm(&name).map_err(backend_error)?;
     Ok(Json(ApiResponse::success("ok")))
 }
 
-#[cfg(windows)]
-pub async fn hyperv_list_snapshots(Path(name): Path<String>) -> ApiResult<Vec<SnapshotDto>> {
-    let hv =
-        HyperV::new().map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    let snapshots = hv
-        .list_snapshots(&name)
-        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    let dtos = snapshots
-        .into_iter()
-        .map(|s| SnapshotDto {
-            name: s.name().to_string(),
-            id: s.id().to_string(),
-            vm_name: s.vm_name().to_string(),
-            creation_time: s.creation_time().ok(),
-            parent_name: s.parent_name().ok().flatten(),
-        })
-        .collect();
-    Ok(Json(ApiResponse::success(dtos)))
-}
-
-#[cfg(windows)]
-pub async fn hyperv_get_snapshot(
-    Path((name, snapshot)): Path<(String, String)>,
-) -> ApiResult<SnapshotDto> {
-    let hv =
-        HyperV::new().map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    let s = hv
-        .get_snapshot(&name, &snapshot)
-        .map_err(|e| api_error(StatusCode::NOT_FOUND, &e.to_string()))?;
-    Ok(Json(ApiResponse::success(SnapshotDto {
-        name: s.name().to_string(),
-        id: s.id().to_string(),
-        vm_name: s.vm_name().to_string(),
-        creation_time: s.creation_time().ok(),
-        parent_name: s.parent_name().ok().flatten(),
-    })))
-}
-
-#[cfg(windows)]
-pub async fn hyperv_create_snapshot(
+pub async fn hyperv_resume_vm(
+    State(state): State<SharedState>,
     Path(name): Path<String>,
-    Json(req): Json<CreateSnapshotRequest>,
-) -> ApiResult<SnapshotDto> {
-    let hv =
-        HyperV::new().map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    let snapshot_type = match req.snapshot_type.as_deref() {
-        Some("Production") => SnapshotType::Production,
-        Some("ProductionOnly") => SnapshotType::Production,
-        _ => SnapshotType::Standard,
-    };
-    let s = hv
-        .create_snapshot(&name, &req.name, snapshot_type)
-        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    Ok(Json(ApiResponse::success(SnapshotDto {
-        name: s.name().to_string(),
-        id: s.id().to_string(),
-        vm_name: s.vm_name().to_string(),
-        creation_time: s.creation_time().ok(),
-        parent_name: s.parent_name().ok().flatten(),
-    })))
-}
-
-#[cfg(windows)]
-pub async fn hyperv_apply_snapshot(
-    Path((name, snapshot)): Path<(String, String)>,
 ) -> ApiResult<&'static str> {
-    let hv =
-        HyperV::new().map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    let s = hv
-        .get_snapshot(&name, &snapshot)
-        .map_err(|e| api_error(StatusCode::NOT_FOUND, &e.to_string()))?;
-    s.apply()
-        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
+    let _guard = state
+        .locks
+        .acquire([LockKey::vm(&name)])
+        .await
+        .map_err(lock_error)?;
+    state.hyperv.resume_vm(&name).map_err(backend_error)?;
     Ok(Json(ApiResponse::success("ok")))
 }
 
-#[cfg(windows)]
-pub async fn hyperv_delete_snapshot(
-    Path((name, snapshot)): Path<(String, String)>,
+pub async fn hyperv_save_vm(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
 ) -> ApiResult<&'static str> {
-    let hv =
-        HyperV::new().map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    let s = hv
-        .get_snapshot(&name, &snapshot)
-        .map_err(|e| api_error(StatusCode::NOT_FOUND, &e.to_string()))?;
-    s.delete()
-        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
+    let _guard = state
+        .locks
+        .acquire([LockKey::vm(&name)])
+        .await
+        .map_err(lock_error)?;
+    state.hyperv.save_vm(&name).map_err(backend_error)?;
     Ok(Json(ApiResponse::success("ok")))
 }
 
-#[cfg(windows)]
-pub async fn hyperv_list_switches() -> ApiResult<Vec<SwitchDto>> {
-    let hv =
-        HyperV::new().map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    let switches = hv
-        .list_switches()
-        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    let dtos = switches
-        .into_iter()
-        .map(|s| SwitchDto {
-            name: s.name().to_string(),
-            id: s.id().to_string(),
-            switch_type: format!("{:?}", s.switch_type()),
-        })
-        .collect();
-    Ok(Json(ApiResponse::success(dtos)))
-}
-
-#[cfg(windows)]
-pub async fn hyperv_get_switch(Path(name): Path<String>) -> ApiResult<SwitchDto> {
-    let hv =
-        HyperV::new().map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    let s = hv
-        .get_switch(&name)
-        .map_err(|e| api_error(StatusCode::NOT_FOUND, &e.to_string()))?;
-    Ok(Json(ApiResponse::success(SwitchDto {
-        name: s.name().to_string(),
-        id: s.id().to_string(),
-        switch_type: format!("{:?}", s.switch_type()),
-    })))
-}
-
-#[cfg(windows)]
-pub async fn hyperv_create_switch(Json(req): Json<CreateSwitchRequest>) -> ApiResult<SwitchDto> {
-    let hv =
-        HyperV::new().map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-
-    let s = match req.switch_type.to_lowercase().as_str() {
-        "external" => {
-            let adapter = req.network_adapter.ok_or_else(|| {
-                api_error(
-                    StatusCode::BAD_REQUEST,
-                    "network_adapter required for external switch",
-                )
-            })?;
-            hv.create_external_switch(&req.name, &adapter, req.allow_management_os.unwrap_or(true))
-                .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?
-        }
-        "internal" => hv
-            .create_switch(&req.name, SwitchType::Internal)
-            .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?,
-        "private" => hv
-            .create_switch(&req.name, SwitchType::Private)
-            .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?,
-        _ => return Err(api_error(StatusCode::BAD_REQUEST, "Invalid switch_type")),
-    };
-
-    Ok(Json(ApiResponse::success(SwitchDto {
-        name: s.name().to_string(),
-        id: s.id().to_string(),
-        switch_type: format!("{:?}", s.switch_type()),
-    })))
-}
-
-#[cfg(windows)]
-pub async fn hyperv_delete_switch(Path(name): Path<String>) -> ApiResult<&'static str> {
-    let hv =
-        HyperV::new().map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    let s = hv
-        .get_switch(&name)
-        .map_err(|e| api_error(StatusCode::NOT_FOUND, &e.to_string()))?;
-    s.delete()
-        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
+pub async fn hyperv_reset_vm(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+) -> ApiResult<&'static str> {
+    let _guard = state
+        .locks
+        .acquire([LockKey::vm(&name)])
+        .await
+        .map_err(lock_error)?;
+    state.hyperv.reset_vm(&name).map_err(backend_error)?;
     Ok(Json(ApiResponse::success("ok")))
 }
 
-#[cfg(windows)]
-pub async fn hyperv_get_vhd_info(Query(req): Query<VhdPathRequest>) -> ApiResult<VhdDto> {
-    let hv =
-        HyperV::new().map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    let vhd = hv
-        .get_vhd(&req.path)
-        .map_err(|e| api_error(StatusCode::NOT_FOUND, &e.to_string()))?;
-    Ok(Json(ApiResponse::success(VhdDto {
-        path: vhd.path().to_string(),
-        format: format!("{:?}", vhd.format()),
-        vhd_type: format!("{:?}", vhd.vhd_type()),
-        max_size_bytes: vhd.max_size_bytes().unwrap_or(0),
-        file_size_bytes: vhd.file_size_bytes().unwrap_or(0),
-        parent_path: None,
-        is_attached: vhd.is_attached().unwrap_or(false),
-    })))
-}
-
-#[cfg(windows)]
-pub async fn hyperv_create_vhd(Json(req): Json<CreateVhdRequest>) -> ApiResult<VhdDto> {
-    let hv =
-        HyperV::new().map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    let vhd_type = match req.vhd_type.as_deref() {
-        Some("Fixed") => VhdType::Fixed,
-        Some("Differencing") => VhdType::Differencing,
-        _ => VhdType::Dynamic,
-    };
-    let vhd = hv
-        .create_vhd(&req.path, req.size_bytes, vhd_type, req.block_size_bytes)
-        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    Ok(Json(ApiResponse::success(VhdDto {
-        path: vhd.path().to_string(),
-        format: format!("{:?}", vhd.format()),
-        vhd_type: format!("{:?}", vhd.vhd_type()),
-        max_size_bytes: vhd.max_size_bytes().unwrap_or(0),
-        file_size_bytes: vhd.file_size_bytes().unwrap_or(0),
-        parent_path: None,
-        is_attached: vhd.is_attached().unwrap_or(false),
-    })))
+pub async fn hyperv_export_vm(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+    Valid(req): Valid<ExportVmRequest>,
+) -> AcceptedResult<JobDto> {
+    let guard = state
+        .locks
+        .acquire([LockKey::vm(&name)])
+        .await
+        .map_err(lock_error)?;
+    let hyperv = Arc::clone(&state.hyperv);
+    let job = state.jobs.spawn("export_vm", name.clone(), move |ctx| {
+        let _guard = guard;
+        hyperv.export_vm(&name, &req.path, &|p, s| ctx.report(p, s))
+    });
+    Ok(accepted(job))
+}
+
+/// `POST /vms/{name}/upgrade-version`: raise the VM's configuration version
+/// to the host default; it cannot be lowered again
+pub async fn hyperv_upgrade_vm_version(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+) -> ApiResult<VmDto> {
+    let _guard = state
+        .locks
+        .acquire([LockKey::vm(&name)])
+        .await
+        .map_err(lock_error)?;
+    let vm = state
+        .hyperv
+        .upgrade_vm_version(&name)
+        .map_err(backend_error)?;
+    Ok(Json(ApiResponse::success(vm)))
 }
 
-#[cfg(windows)]
-pub async fn hyperv_resize_vhd(Json(req): Json<ResizeVhdRequest>) -> ApiResult<&'static str> {
-    let hv =
-        HyperV::new().map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    let vhd = hv
-        .get_vhd(&req.path)
-        .map_err(|e| api_error(StatusCode::NOT_FOUND, &e.to_string()))?;
-    vhd.resize(req.size_bytes)
-        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    Ok(Json(ApiResponse::success("ok")))
-}
+// =============================================================================
+// VM Processor and Security
+// =============================================================================
 
-#[cfg(windows)]
-pub async fn hyperv_compact_vhd(Json(req): Json<VhdPathRequest>) -> ApiResult<&'static str> {
-    let hv =
-        HyperV::new().map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    let vhd = hv
-        .get_vhd(&req.path)
-        .map_err(|e| api_error(StatusCode::NOT_FOUND, &e.to_string()))?;
-    vhd.compact()
-        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    Ok(Json(ApiResponse::success("ok")))
+pub async fn hyperv_vm_processor(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+) -> ApiResult<VmProcessorDto> {
+    let processor = state.hyperv.vm_processor(&name).map_err(backend_error)?;
+    Ok(Json(ApiResponse::success(processor)))
 }
 
-#[cfg(windows)]
-pub async fn hyperv_mount_vhd(Json(req): Json<VhdPathRequest>) -> ApiResult<&'static str> {
-    let hv =
-        HyperV::new().map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    let vhd = hv
-        .get_vhd(&req.path)
-        .map_err(|e| api_error(StatusCode::NOT_FOUND, &e.to_string()))?;
-    vhd.mount(false)
-        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    Ok(Json(ApiResponse::success("ok")))
+/// `PUT /vms/{name}/processor`: replace the VM's processor settings; the
+/// count, threads per core, nested virtualization and NUMA nodes need the VM
+/// off
+pub async fn hyperv_set_vm_processor(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+    Valid(req): Valid<SetVmProcessorRequest>,
+) -> ApiResult<VmProcessorDto> {
+    let _guard = state
+        .locks
+        .acquire([LockKey::vm(&name)])
+        .await
+        .map_err(lock_error)?;
+    let processor = state
+        .hyperv
+        .set_vm_processor(&name, &req)
+        .map_err(backend_error)?;
+    Ok(Json(ApiResponse::success(processor)))
+}
+
+pub async fn hyperv_vm_security(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+) -> ApiResult<VmSecurityDto> {
+    let security = state.hyperv.vm_security(&name).map_err(backend_error)?;
+    Ok(Json(ApiResponse::success(security)))
 }
 
-#[cfg(windows)]
-pub async fn hyperv_dismount_vhd(Json(req): Json<VhdPathRequest>) -> ApiResult<&'static str> {
-    let hv =
-        HyperV::new().map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    let vhd = hv
-        .get_vhd(&req.path)
-        .map_err(|e| api_error(StatusCode::NOT_FOUND, &e.to_string()))?;
-    vhd.dismount()
-        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    Ok(Json(ApiResponse::success("ok")))
+/// `PUT /vms/{name}/security`: replace the VM's Secure Boot, vTPM and
+/// isolation settings; changes need the VM off
+pub async fn hyperv_set_vm_security(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+    Valid(req): Valid<SetVmSecurityRequest>,
+) -> ApiResult<VmSecurityDto> {
+    let _guard = state
+        .locks
+        .acquire([LockKey::vm(&name)])
+        .await
+        .map_err(lock_error)?;
+    let security = state
+        .hyperv
+        .set_vm_security(&name, &req)
+        .map_err(backend_error)?;
+    Ok(Json(ApiResponse::success(security)))
 }
 
-#[cfg(windows)]
-pub async fn hyperv_create_diff_vhd(Json(req): Json<DiffVhdRequest>) -> ApiResult<VhdDto> {
-    let hv =
-        HyperV::new().map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    let vhd = hv
-        .create_differencing_vhd(&req.path, &req.parent_path)
-        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    Ok(Json(ApiResponse::success(VhdDto {
-        path: vhd.path().to_string(),
-        format: format!("{:?}", vhd.format()),
-        vhd_type: format!("{:?}", vhd.vhd_type()),
-        max_size_bytes: vhd.max_size_bytes().unwrap_or(0),
-        file_size_bytes: vhd.file_size_bytes().unwrap_or(0),
-        parent_path: Some(req.parent_path.clone()),
-        is_attached: vhd.is_attached().unwrap_or(false),
-    })))
-}
+// =============================================================================
+// VM Batches
+// =============================================================================
 
-#[cfg(windows)]
-pub async fn hyperv_initialize_vhd(Json(req): Json<InitVhdRequest>) -> ApiResult<String> {
-    let hv =
-        HyperV::new().map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    let partition_style = match req.partition_style.as_deref() {
-        Some("Mbr") | Some("MBR") => hv::PartitionStyle::Mbr,
-        _ => hv::PartitionStyle::Gpt,
+/// `POST /vms:batch`: apply one action to many VMs as a job
+///
+/// A selector is resolved when the request arrives; VMs created later are
+/// not included.
+pub async fn hyperv_batch_vms(
+    State(state): State<SharedState>,
+    Valid(req): Valid<BatchVmRequest>,
+) -> AcceptedResult<JobDto> {
+    let invalid = |message: &str| {
+        ApiError::new(
+            StatusCode::BAD_REQUEST,
+            ErrorCode::InvalidParameter,
+            message,
+        )
     };
-    let file_system = match req.file_system.as_deref() {
-        Some("ReFS") | Some("refs") => hv::FileSystem::ReFs,
-        Some("FAT32") | Some("fat32") => hv::FileSystem::Fat32,
-        Some("ExFAT") | Some("exfat") => hv::FileSystem::ExFat,
-        _ => hv::FileSystem::Ntfs,
+    let vms = match (req.vms.is_empty(), req.selector) {
+        (false, None) => {
+            let mut vms: Vec<String> = Vec::with_capacity(req.vms.len());
+            for vm in req.vms {
+                if !vms.iter().any(|v| v.eq_ignore_ascii_case(&vm)) {
+                    vms.push(vm);
+                }
+            }
+            vms
+        }
+        (true, Some(selector)) => {
+            let filter = ListQuery {
+                filters: selector,
+                ..ListQuery::default()
+            };
+            let vms = state.hyperv.list_vms().map_err(backend_error)?;
+            filter
+                .matching(vms)?
+                .into_iter()
+                .map(|vm| vm.name)
+                .collect()
+        }
+        _ => return Err(invalid("Specify either vms or selector")),
     };
-    let drive_letter = hv
-        .initialize_vhd(
-            &req.path,
-            partition_style,
-            file_system,
-            req.label.as_deref(),
-        )
-        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    Ok(Json(ApiResponse::success(drive_letter)))
+    let parallelism = req.parallelism.unwrap_or(DEFAULT_PARALLELISM);
+    if !(1..=MAX_PARALLELISM).contains(&parallelism) {
+        return Err(invalid(&format!(
+            "parallelism must be between 1 and {}",
+            MAX_PARALLELISM
+        )));
+    }
+
+    let batch = Batch {
+        vms,
+        action: req.action,
+        parallelism,
+        on_error: req.on_error,
+    };
+    let kind = format!("batch_{}", batch.action.as_str());
+    let target = batch.vms.join(", ");
+    let hyperv = Arc::clone(&state.hyperv);
+    let locks = Arc::clone(&state.locks);
+    let runtime = tokio::runtime::Handle::current();
+    let job = state.jobs.spawn(&kind, target, move |ctx| {
+        Ok(batch.run(ctx, &*hyperv, &locks, &runtime))
+    });
+    Ok(accepted(job))
 }
 
-#[cfg(windows)]
-pub async fn hyperv_iso_editions(
-    Query(req): Query<IsoPathQuery>,
-) -> ApiResult<Vec<WindowsEditionDto>> {
-    let hv =
-        HyperV::new().map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    let editions = hv
-        .get_windows_editions(&req.path)
-        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    let dtos = editions
-        .into_iter()
-        .map(|e| WindowsEditionDto {
-            index: e.index,
-            name: e.name,
-            description: e.description,
-            size_bytes: e.size_bytes,
-        })
-        .collect();
-    Ok(Json(ApiResponse::success(dtos)))
-}
-
-#[cfg(windows)]
-pub async fn hyperv_create_vhdx_from_iso(
-    Json(req): Json<CreateVhdxFromIsoRequest>,
-) -> ApiResult<&'static str> {
-    let hv =
-        HyperV::new().map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    hv.create_vhdx_from_iso(
-        &req.iso_path,
-        &req.vhdx_path,
-        req.size_gb,
-        req.edition_index,
-    )
-    .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    Ok(Json(ApiResponse::success("ok")))
-}
+// =============================================================================
+// VM Disks and DVD Drives
+// =============================================================================
 
-#[cfg(windows)]
-pub async fn hyperv_list_gpus() -> ApiResult<Vec<GpuDto>> {
-    let hv =
-        HyperV::new().map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    let gpus = hv
-        .list_gpus()
-        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    let dtos = gpus
-        .into_iter()
-        .map(|g| GpuDto {
-            device_instance_id: g.device_instance_id,
-            name: g.name,
-            description: g.description,
-            manufacturer: g.manufacturer,
-            supports_partitioning: g.supports_partitioning,
-        })
-        .collect();
-    Ok(Json(ApiResponse::success(dtos)))
-}
-
-#[cfg(windows)]
-pub async fn hyperv_list_partitionable_gpus() -> ApiResult<Vec<GpuDto>> {
-    let hv =
-        HyperV::new().map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    let gpus = hv
-        .list_partitionable_gpus()
-        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    let dtos = gpus
-        .into_iter()
-        .map(|g| GpuDto {
-            device_instance_id: g.device_instance_id,
-            name: g.name,
-            description: g.description,
-            manufacturer: g.manufacturer,
-            supports_partitioning: g.supports_partitioning,
-        })
-        .collect();
-    Ok(Json(ApiResponse::success(dtos)))
-}
-
-#[cfg(windows)]
-pub async fn hyperv_vm_gpu_adapters(Path(name): Path<String>) -> ApiResult<Vec<GpuAdapterDto>> {
-    let hv =
-        HyperV::new().map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    let adapters = hv
-        .get_vm_gpu_adapters(&name)
-        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    let dtos = adapters
-        .into_iter()
-        .map(|a| GpuAdapterDto {
-            vm_name: a.vm_name,
-            instance_path: a.instance_path,
-            min_partition_vram: a.min_partition_vram.unwrap_or(0),
-            max_partition_vram: a.max_partition_vram.unwrap_or(0),
-            optimal_partition_vram: a.optimal_partition_vram.unwrap_or(0),
-        })
-        .collect();
-    Ok(Json(ApiResponse::success(dtos)))
-}
-
-#[cfg(windows)]
-pub async fn hyperv_add_gpu(
+pub async fn hyperv_vm_disks(
+    State(state): State<SharedState>,
     Path(name): Path<String>,
-    Json(req): Json<AddGpuRequest>,
-) -> ApiResult<&'static str> {
-    let hv =
-        HyperV::new().map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    hv.add_gpu_to_vm(&name, req.instance_path.as_deref())
-        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    Ok(Json(ApiResponse::success("ok")))
+    list: ListQuery,
+) -> ListResult<DiskDto> {
+    let disks = state.hyperv.vm_disks(&name).map_err(backend_error)?;
+    list.page(disks)
 }
 
-#[cfg(windows)]
-pub async fn hyperv_remove_gpu(Path(name): Path<String>) -> ApiResult<&'static str> {
-    let hv =
-        HyperV::new().map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    hv.remove_gpu_from_vm(&name)
-        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
+pub async fn hyperv_attach_disk(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+    Valid(req): Valid<AttachDiskRequest>,
+) -> ApiResult<&'static str> {
+    let _guard = state
+        .locks
+        .acquire([LockKey::vm(&name), LockKey::vhd(&req.vhd_path)])
+        .await
+        .map_err(lock_error)?;
+    state
+        .hyperv
+        .attach_disk(&name, &req.vhd_path)
+        .map_err(backend_error)?;
     Ok(Json(ApiResponse::success("ok")))
 }
 
-#[cfg(windows)]
-pub async fn hyperv_configure_gpu(
+pub async fn hyperv_detach_disk(
+    State(state): State<SharedState>,
     Path(name): Path<String>,
-    Json(req): Json<ConfigureGpuRequest>,
+    Valid(req): Valid<DetachDiskRequest>,
 ) -> ApiResult<&'static str> {
-    let hv =
-        HyperV::new().map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    hv.configure_vm_for_gpu(&name, req.low_mmio_gb, req.high_mmio_gb)
-        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
+    let _guard = state
+        .locks
+        .acquire([LockKey::vm(&name)])
+        .await
+        .map_err(lock_error)?;
+    state
+        .hyperv
+        .detach_disk(&name, req.controller_number, req.controller_location)
+        .map_err(backend_error)?;
     Ok(Json(ApiResponse::success("ok")))
 }
 
-#[cfg(windows)]
-pub async fn hyperv_dda_support() -> ApiResult<DdaSupportDto> {
-    let hv =
-        HyperV::new().map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    let info = hv
-        .check_dda_support()
-        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    Ok(Json(ApiResponse::success(DdaSupportDto {
-        is_supported: info.is_supported,
-        is_server: info.is_server,
-        has_iommu: info.has_iommu,
-        cmdlet_available: info.cmdlet_available,
-        reason: info.reason,
-    })))
-}
-
-#[cfg(windows)]
-pub async fn hyperv_dda_devices() -> ApiResult<Vec<AssignableDeviceDto>> {
-    let hv =
-        HyperV::new().map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    let devices = hv
-        .get_assignable_devices()
-        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    let dtos = devices
-        .into_iter()
-        .map(|d| AssignableDeviceDto {
-            instance_id: d.instance_id,
-            name: d.name,
-            location_path: d.location_path,
-            is_assigned: d.is_assigned,
-            assigned_vm: d.assigned_vm,
-            is_dismounted: d.is_dismounted,
-            status: d.status,
-        })
-        .collect();
-    Ok(Json(ApiResponse::success(dtos)))
-}
-
-#[cfg(windows)]
-pub async fn hyperv_device_path(Query(req): Query<DevicePathRequest>) -> ApiResult<String> {
-    let hv =
-        HyperV::new().map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    let path = hv
-        .get_device_location_path(&req.instance_id)
-        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    Ok(Json(ApiResponse::success(path)))
-}
-
-#[cfg(windows)]
-pub async fn hyperv_dismount_device(
-    Json(req): Json<DeviceLocationRequest>,
-) -> ApiResult<&'static str> {
-    let hv =
-        HyperV::new().map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    hv.dismount_device(&req.location_path)
-        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    Ok(Json(ApiResponse::success("ok")))
+pub async fn hyperv_vm_dvd(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+    list: ListQuery,
+) -> ListResult<DiskDto> {
+    let disks = state.hyperv.vm_dvd_drives(&name).map_err(backend_error)?;
+    list.page(disks)
 }
 
-#[cfg(windows)]
-pub async fn hyperv_mount_device(
-    Json(req): Json<DeviceLocationRequest>,
+pub async fn hyperv_mount_iso(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+    Valid(req): Valid<MountIsoRequest>,
 ) -> ApiResult<&'static str> {
-    let hv =
-        HyperV::new().map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    hv.mount_device(&req.location_path)
-        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
+    let _guard = state
+        .locks
+        .acquire([LockKey::vm(&name)])
+        .await
+        .map_err(lock_error)?;
+    state
+        .hyperv
+        .mount_iso(&name, &req.iso_path)
+        .map_err(backend_error)?;
     Ok(Json(ApiResponse::success("ok")))
 }
 
-#[cfg(windows)]
-pub async fn hyperv_vm_dda_devices(
+pub async fn hyperv_eject_iso(
+    State(state): State<SharedState>,
     Path(name): Path<String>,
-) -> ApiResult<Vec<AssignableDeviceDto>> {
-    let hv =
-        HyperV::new().map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    let devices = hv
-        .get_vm_assigned_devices(&name)
-        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    let dtos = devices
-        .into_iter()
-        .map(|d| AssignableDeviceDto {
-            instance_id: d.instance_id,
-            name: d.name,
-            location_path: d.location_path,
-            is_assigned: d.is_assigned,
-            assigned_vm: d.assigned_vm,
-            is_dismounted: d.is_dismounted,
-            status: d.status,
-        })
-        .collect();
-    Ok(Json(ApiResponse::success(dtos)))
-}
-
-#[cfg(windows)]
-pub async fn hyperv_assign_device(
-    Path(name): Path<String>,
-    Json(req): Json<DeviceLocationRequest>,
 ) -> ApiResult<&'static str> {
-    let hv =
-        HyperV::new().map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    hv.assign_device_to_vm(&name, &req.location_path)
-        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
+    let _guard = state
+        .locks
+        .acquire([LockKey::vm(&name)])
+        .await
+        .map_err(lock_error)?;
+    state.hyperv.eject_iso(&name).map_err(backend_error)?;
     Ok(Json(ApiResponse::success("ok")))
 }
 
-#[cfg(windows)]
-pub async fn hyperv_remove_device(
+pub async fn hyperv_set_boot_order(
+    State(state): State<SharedState>,
     Path(name): Path<String>,
-    Json(req): Json<DeviceLocationRequest>,
+    Valid(req): Valid<BootOrderRequest>,
 ) -> ApiResult<&'static str> {
-    let hv =
-        HyperV::new().map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
-    hv.remove_assigned_device(&name, &req.location_path)
-        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
+    let _guard = state
+        .locks
+        .acquire([LockKey::vm(&name)])
+        .await
+        .map_err(lock_error)?;
+    state
+        .hyperv
+        .set_boot_order(&name, &req.devices)
+        .map_err(backend_error)?;
     Ok(Json(ApiResponse::success("ok")))
 }
 
 // =============================================================================
-// Non-Windows Stubs
+// VM Network Adapters
 // =============================================================================
 
-#[cfg(not(windows))]
-fn not_supported() -> (StatusCode, Json<ApiResponse<()>>) {
-    api_error(
-        StatusCode::NOT_IMPLEMENTED,
-        "Hyper-V API only available on Windows",
-    )
-}
-
-#[cfg(not(windows))]
-pub async fn hyperv_host_info() -> ApiResult<HostInfoDto> {
-    Err(not_supported())
-}
-
-#[cfg(not(windows))]
-pub async fn hyperv_list_adapters() -> ApiResult<Vec<NetworkAdapterDto>> {
-    Err(not_supported())
+pub async fn hyperv_vm_network_adapters(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+    list: ListQuery,
+) -> ListResult<VmNetworkAdapterDto> {
+    let adapters = state
+        .hyperv
+        .vm_network_adapters(&name)
+        .map_err(backend_error)?;
+    list.page(adapters)
 }
 
-#[cfg(not(windows))]
-pub async fn hyperv_list_vms() -> ApiResult<Vec<VmDto>> {
-    Err(not_supported())
+pub async fn hyperv_add_network_adapter(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+    Valid(req): Valid<AddVmNetworkAdapterRequest>,
+) -> ApiResult<VmNetworkAdapterDto> {
+    let switch = req.switch_name.as_deref().map(LockKey::switch);
+    let _guard = state
+        .locks
+        .acquire([LockKey::vm(&name)].into_iter().chain(switch))
+        .await
+        .map_err(lock_error)?;
+    let adapter = state
+        .hyperv
+        .add_network_adapter(&name, &req)
+        .map_err(backend_error)?;
+    Ok(Json(ApiResponse::success(adapter)))
+}
+
+pub async fn hyperv_update_network_adapter(
+    State(state): State<SharedState>,
+    Path((name, adapter)): Path<(String, String)>,
+    Valid(req): Valid<UpdateVmNetworkAdapterRequest>,
+) -> ApiResult<VmNetworkAdapterDto> {
+    let _guard = state
+        .locks
+        .acquire([LockKey::vm(&name)])
+        .await
+        .map_err(lock_error)?;
+    let adapter = state
+        .hyperv
+        .update_network_adapter(&name, &adapter, &req)
+        .map_err(backend_error)?;
+    Ok(Json(ApiResponse::success(adapter)))
+}
+
+pub async fn hyperv_connect_network_adapter(
+    State(state): State<SharedState>,
+    Path((name, adapter)): Path<(String, String)>,
+    Valid(req): Valid<ConnectAdapterRequest>,
+) -> ApiResult<VmNetworkAdapterDto> {
+    let _guard = state
+        .locks
+        .acquire([LockKey::vm(&name), LockKey::switch(&req.switch_name)])
+        .await
+        .map_err(lock_error)?;
+    let adapter = state
+        .hyperv
+        .connect_network_adapter(&name, &adapter, Some(&req.switch_name))
+        .map_err(backend_error)?;
+    Ok(Json(ApiResponse::success(adapter)))
+}
+
+pub async fn hyperv_disconnect_network_adapter(
+    State(state): State<SharedState>,
+    Path((name, adapter)): Path<(String, String)>,
+) -> ApiResult<VmNetworkAdapterDto> {
+    let _guard = state
+        .locks
+        .acquire([LockKey::vm(&name)])
+        .await
+        .map_err(lock_error)?;
+    let adapter = state
+        .hyperv
+        .connect_network_adapter(&name, &adapter, None)
+        .map_err(backend_error)?;
+    Ok(Json(ApiResponse::success(adapter)))
+}
+
+pub async fn hyperv_remove_network_adapter(
+    State(state): State<SharedState>,
+    Path((name, adapter)): Path<(String, String)>,
+) -> ApiResult<&'static str> {
+    let _guard = state
+        .locks
+        .acquire([LockKey::vm(&name)])
+        .await
+        .map_err(lock_error)?;
+    state
+        .hyperv
+        .remove_network_adapter(&name, &adapter)
+        .map_err(backend_error)?;
+    Ok(Json(ApiResponse::success("ok")))
 }
 
-#[cfg(not(windows))]
-pub async fn hyperv_get_vm(_: Path<String>) -> ApiResult<VmDto> {
-    Err(not_supported())
-}
+// =============================================================================
+// Snapshots
+// =============================================================================
 
-#[cfg(not(windows))]
-pub async fn hyperv_create_vm(_: Json<CreateVmRequest>) -> ApiResult<VmDto> {
-    Err(not_supported())
+pub async fn hyperv_list_snapshots(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+    list: ListQuery,
+) -> ListResult<SnapshotDto> {
+    let snapshots = state.hyperv.list_snapshots(&name).map_err(backend_error)?;
+    list.page(snapshots)
 }
 
-#[cfg(not(windows))]
-pub async fn hyperv_delete_vm(_: Path<String>) -> ApiResult<&'static str> {
-    Err(not_supported())
+/// `GET /vms/{name}/snapshots/tree`: checkpoints nested under their parents
+pub async fn hyperv_snapshot_tree(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+) -> ApiResult<SnapshotTreeDto> {
+    let snapshots = state.hyperv.list_snapshots(&name).map_err(backend_error)?;
+    let current_id = state
+        .hyperv
+        .current_snapshot_id(&name)
+        .map_err(backend_error)?;
+    let current_snapshot = current_id
+        .as_ref()
+        .and_then(|id| snapshots.iter().find(|s| &s.id == id))
+        .map(|s| s.name.clone());
+    let roots = snapshot_roots(&snapshots, current_id.as_deref());
+    Ok(Json(ApiResponse::success(SnapshotTreeDto {
+        vm_name: name,
+        current_snapshot,
+        current_snapshot_id: current_id,
+        roots,
+    })))
 }
 
-#[cfg(not(windows))]
-pub async fn hyperv_start_vm(_: Path<String>) -> ApiResult<&'static str> {
-    Err(not_supported())
+/// Top-level checkpoints in creation order. Links follow parent ids, since
+/// names can repeat; a checkpoint whose parent is not listed is a root, and
+/// one left unreached (a parent cycle) is listed as a root rather than lost.
+fn snapshot_roots(snapshots: &[SnapshotDto], current: Option<&str>) -> Vec<SnapshotNodeDto> {
+    let ids: HashSet<&str> = snapshots.iter().map(|s| s.id.as_str()).collect();
+    let orphans = snapshots.iter().filter(|s| {
+        !s.parent_id
+            .as_deref()
+            .is_some_and(|parent| ids.contains(parent))
+    });
+    let mut visited = HashSet::new();
+    let mut roots = Vec::new();
+    for s in orphans.chain(snapshots) {
+        if visited.insert(s.id.as_str()) {
+            roots.push(snapshot_node(snapshots, s, current, &mut visited));
+        }
+    }
+    roots
+}
+
+/// `snapshot` with every descendant not already placed in the tree
+fn snapshot_node<'a>(
+    snapshots: &'a [SnapshotDto],
+    snapshot: &'a SnapshotDto,
+    current: Option<&str>,
+    visited: &mut HashSet<&'a str>,
+) -> SnapshotNodeDto {
+    let mut children = Vec::new();
+    for child in snapshots {
+        if child.parent_id.as_deref() == Some(snapshot.id.as_str())
+            && visited.insert(child.id.as_str())
+        {
+            children.push(snapshot_node(snapshots, child, current, visited));
+        }
+    }
+    SnapshotNodeDto {
+        name: snapshot.name.clone(),
+        id: snapshot.id.clone(),
+        creation_time: snapshot.creation_time.clone(),
+        is_current: current == Some(snapshot.id.as_str()),
+        descendant_count: children.iter().map(|c| c.descendant_count + 1).sum(),
+        children,
+    }
 }
 
-#[cfg(not(windows))]
-pub async fn hyperv_stop_vm(_: Path<String>) -> ApiResult<&'static str> {
-    Err(not_supported())
+pub async fn hyperv_get_snapshot(
+    State(state): State<SharedState>,
+    Path((name, snapshot)): Path<(String, String)>,
+) -> ApiResult<SnapshotDto> {
+    let snapshot = state
+        .hyperv
+        .get_snapshot(&name, &snapshot)
+        .map_err(backend_error)?;
+    Ok(Json(ApiResponse::success(snapshot)))
 }
 
-#[cfg(not(windows))]
-pub async fn hyperv_force_stop_vm(_: Path<String>) -> ApiResult<&'static str> {
-    Err(not_supported())
+pub async fn hyperv_create_snapshot(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+    Valid(req): Valid<CreateSnapshotRequest>,
+) -> ApiResult<SnapshotDto> {
+    let _guard = state
+        .locks
+        .acquire([LockKey::vm(&name)])
+        .await
+        .map_err(lock_error)?;
+    let snapshot = state
+        .hyperv
+        .create_snapshot(&name, &req)
+        .map_err(backend_error)?;
+    Ok(Json(ApiResponse::success(snapshot)))
 }
 
-#[cfg(not(windows))]
-pub async fn hyperv_pause_vm(_: Path<String>) -> ApiResult<&'static str> {
-    Err(not_supported())
+pub async fn hyperv_apply_snapshot(
+    State(state): State<SharedState>,
+    Path((name, snapshot)): Path<(String, String)>,
+) -> AcceptedResult<JobDto> {
+    let guard = state
+        .locks
+        .acquire([LockKey::vm(&name)])
+        .await
+        .map_err(lock_error)?;
+    let hyperv = Arc::clone(&state.hyperv);
+    let target = format!("{}/{}", name, snapshot);
+    let job = state.jobs.spawn("apply_snapshot", target, move |ctx| {
+        let _guard = guard;
+        hyperv.apply_snapshot(&name, &snapshot, &|p, s| ctx.report(p, s))
+    });
+    Ok(accepted(job))
+}
+
+/// `DELETE /vms/{name}/snapshots/{snapshot}/delete`; `?subtree=true` also
+/// deletes every descendant checkpoint
+pub async fn hyperv_delete_snapshot(
+    State(state): State<SharedState>,
+    Path((name, snapshot)): Path<(String, String)>,
+    Query(params): Query<DeleteSnapshotQuery>,
+) -> ApiResult<&'static str> {
+    let _guard = state
+        .locks
+        .acquire([LockKey::vm(&name)])
+        .await
+        .map_err(lock_error)?;
+    if params.subtree.unwrap_or(false) {
+        state.hyperv.delete_snapshot_subtree(&name, &snapshot)
+    } else {
+        state.hyperv.delete_snapshot(&name, &snapshot)
+    }
+    .map_err(backend_error)?;
+    Ok(Json(ApiResponse::success("ok")))
 }
 
-#[cfg(not(windows))]
-pub async fn hyperv_resume_vm(_: Path<String>) -> ApiResult<&'static str> {
-    Err(not_supported())
+pub async fn hyperv_rename_snapshot(
+    State(state): State<SharedState>,
+    Path((name, snapshot)): Path<(String, String)>,
+    Valid(req): Valid<RenameSnapshotRequest>,
+) -> ApiResult<SnapshotDto> {
+    let _guard = state
+        .locks
+        .acquire([LockKey::vm(&name)])
+        .await
+        .map_err(lock_error)?;
+    let renamed = state
+        .hyperv
+        .rename_snapshot(&name, &snapshot, &req.new_name)
+        .map_err(backend_error)?;
+    Ok(Json(ApiResponse::success(renamed)))
+}
+
+pub async fn hyperv_export_snapshot(
+    State(state): State<SharedState>,
+    Path((name, snapshot)): Path<(String, String)>,
+    Valid(req): Valid<ExportSnapshotRequest>,
+) -> AcceptedResult<JobDto> {
+    let guard = state
+        .locks
+        .acquire([LockKey::vm(&name)])
+        .await
+        .map_err(lock_error)?;
+    let hyperv = Arc::clone(&state.hyperv);
+    let target = format!("{}/{}", name, snapshot);
+    let job = state.jobs.spawn("export_snapshot", target, move |ctx| {
+        let _guard = guard;
+        hyperv.export_snapshot(&name, &snapshot, &req.path, &|p, s| ctx.report(p, s))
+    });
+    Ok(accepted(job))
 }
 
-#[cfg(not(windows))]
-pub async fn hyperv_save_vm(_: Path<String>) -> ApiResult<&'static str> {
-    Err(not_supported())
-}
+// =============================================================================
+// Switches
+// =============================================================================
 
-#[cfg(not(windows))]
-pub async fn hyperv_reset_vm(_: Path<String>) -> ApiResult<&'static str> {
-    Err(not_supported())
+pub async fn hyperv_list_switches(
+    State(state): State<SharedState>,
+    list: ListQuery,
+) -> ListResult<SwitchDto> {
+    let switches = state.hyperv.list_switches().map_err(backend_error)?;
+    list.page(switches)
 }
 
-#[cfg(not(windows))]
-pub async fn hyperv_export_vm(
-    _: Path<String>,
-    _: Json<ExportVmRequest>,
+pub async fn hyperv_get_switch(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+) -> ApiResult<SwitchDto> {
+    let switch = state.hyperv.get_switch(&name).map_err(backend_error)?;
+    Ok(Json(ApiResponse::success(switch)))
+}
+
+pub async fn hyperv_create_switch(
+    State(state): State<SharedState>,
+    Valid(req): Valid<CreateSwitchRequest>,
+) -> ApiResult<SwitchDto> {
+    let _guard = state
+        .locks
+        .acquire([LockKey::switch(&req.name)])
+        .await
+        .map_err(lock_error)?;
+    let switch = state.hyperv.create_switch(&req).map_err(backend_error)?;
+    Ok(Json(ApiResponse::success(switch)))
+}
+
+pub async fn hyperv_delete_switch(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
 ) -> ApiResult<&'static str> {
-    Err(not_supported())
+    let _guard = state
+        .locks
+        .acquire([LockKey::switch(&name)])
+        .await
+        .map_err(lock_error)?;
+    state.hyperv.delete_switch(&name).map_err(backend_error)?;
+    Ok(Json(ApiResponse::success("ok")))
 }
 
-#[cfg(not(windows))]
-pub async fn hyperv_vm_disks(_: Path<String>) -> ApiResult<Vec<DiskDto>> {
-    Err(not_supported())
-}
+// =============================================================================
+// VHDs
+// =============================================================================
 
-#[cfg(not(windows))]
-pub async fn hyperv_attach_disk(
-    _: Path<String>,
-    _: Json<AttachDiskRequest>,
+pub async fn hyperv_get_vhd_info(
+    State(state): State<SharedState>,
+    Query(req): Query<VhdPathRequest>,
+) -> ApiResult<VhdDto> {
+    let vhd = state.hyperv.get_vhd(&req.path).map_err(backend_error)?;
+    Ok(Json(ApiResponse::success(vhd)))
+}
+
+pub async fn hyperv_create_vhd(
+    State(state): State<SharedState>,
+    Valid(req): Valid<CreateVhdRequest>,
+) -> AcceptedResult<JobDto> {
+    let guard = state
+        .locks
+        .acquire([LockKey::vhd(&req.path)])
+        .await
+        .map_err(lock_error)?;
+    let hyperv = Arc::clone(&state.hyperv);
+    let job = state
+        .jobs
+        .spawn("create_vhd", req.path.clone(), move |ctx| {
+            let _guard = guard;
+            hyperv.create_vhd(&req, &|p, s| ctx.report(p, s))
+        });
+    Ok(accepted(job))
+}
+
+pub async fn hyperv_resize_vhd(
+    State(state): State<SharedState>,
+    Valid(req): Valid<ResizeVhdRequest>,
 ) -> ApiResult<&'static str> {
-    Err(not_supported())
-}
-
-#[cfg(not(windows))]
-pub async fn hyperv_detach_disk(
-    _: Path<String>,
-    _: Json<DetachDiskRequest>,
-) -> ApiResult<&'static str> {
-    Err(not_supported())
-}
-
-#[cfg(not(windows))]
-pub async fn hyperv_vm_dvd(_: Path<String>) -> ApiResult<Vec<DiskDto>> {
-    Err(not_supported())
+    let _guard = state
+        .locks
+        .acquire([LockKey::vhd(&req.path)])
+        .await
+        .map_err(lock_error)?;
+    state
+        .hyperv
+        .resize_vhd(&req.path, req.size_bytes)
+        .map_err(backend_error)?;
+    Ok(Json(ApiResponse::success("ok")))
 }
 
-#[cfg(not(windows))]
-pub async fn hyperv_mount_iso(
-    _: Path<String>,
-    _: Json<MountIsoRequest>,
+pub async fn hyperv_compact_vhd(
+    State(state): State<SharedState>,
+    Valid(req): Valid<VhdPathRequest>,
+) -> AcceptedResult<JobDto> {
+    let guard = state
+        .locks
+        .acquire([LockKey::vhd(&req.path)])
+        .await
+        .map_err(lock_error)?;
+    let hyperv = Arc::clone(&state.hyperv);
+    let job = state
+        .jobs
+        .spawn("compact_vhd", req.path.clone(), move |ctx| {
+            let _guard = guard;
+            hyperv.compact_vhd(&req.path, &|p, s| ctx.report(p, s))
+        });
+    Ok(accepted(job))
+}
+
+pub async fn hyperv_mount_vhd(
+    State(state): State<SharedState>,
+    Valid(req): Valid<VhdPathRequest>,
 ) -> ApiResult<&'static str> {
-    Err(not_supported())
-}
-
-#[cfg(not(windows))]
-pub async fn hyperv_eject_iso(_: Path<String>) -> ApiResult<&'static str> {
-    Err(not_supported())
+    let _guard = state
+        .locks
+        .acquire([LockKey::vhd(&req.path)])
+        .await
+        .map_err(lock_error)?;
+    state.hyperv.mount_vhd(&req.path).map_err(backend_error)?;
+    Ok(Json(ApiResponse::success("ok")))
 }
 
-#[cfg(not(windows))]
-pub async fn hyperv_set_boot_order(
-    _: Path<String>,
-    _: Json<BootOrderRequest>,
+pub async fn hyperv_dismount_vhd(
+    State(state): State<SharedState>,
+    Valid(req): Valid<VhdPathRequest>,
 ) -> ApiResult<&'static str> {
-    Err(not_supported())
-}
-
-#[cfg(not(windows))]
-pub async fn hyperv_list_snapshots(_: Path<String>) -> ApiResult<Vec<SnapshotDto>> {
-    Err(not_supported())
-}
-
-#[cfg(not(windows))]
-pub async fn hyperv_get_snapshot(_: Path<(String, String)>) -> ApiResult<SnapshotDto> {
-    Err(not_supported())
-}
-
-#[cfg(not(windows))]
-pub async fn hyperv_create_snapshot(
-    _: Path<String>,
-    _: Json<CreateSnapshotRequest>,
-) -> ApiResult<SnapshotDto> {
-    Err(not_supported())
-}
-
-#[cfg(not(windows))]
-pub async fn hyperv_apply_snapshot(_: Path<(String, String)>) -> ApiResult<&'static str> {
-    Err(not_supported())
-}
-
-#[cfg(not(windows))]
-pub async fn hyperv_delete_snapshot(_: Path<(String, String)>) -> ApiResult<&'static str> {
-    Err(not_supported())
-}
-
-#[cfg(not(windows))]
-pub async fn hyperv_list_switches() -> ApiResult<Vec<SwitchDto>> {
-    Err(not_supported())
-}
-
-#[cfg(not(windows))]
-pub async fn hyperv_get_switch(_: Path<String>) -> ApiResult<SwitchDto> {
-    Err(not_supported())
-}
-
-#[cfg(not(windows))]
-pub async fn hyperv_create_switch(_: Json<CreateSwitchRequest>) -> ApiResult<SwitchDto> {
-    Err(not_supported())
-}
-
-#[cfg(not(windows))]
-pub async fn hyperv_delete_switch(_: Path<String>) -> ApiResult<&'static str> {
-    Err(not_supported())
-}
-
-#[cfg(not(windows))]
-pub async fn hyperv_get_vhd_info(_: Query<VhdPathRequest>) -> ApiResult<VhdDto> {
-    Err(not_supported())
+    let _guard = state
+        .locks
+        .acquire([LockKey::vhd(&req.path)])
+        .await
+        .map_err(lock_error)?;
+    state
+        .hyperv
+        .dismount_vhd(&req.path)
+        .map_err(backend_error)?;
+    Ok(Json(ApiResponse::success("ok")))
 }
 
-#[cfg(not(windows))]
-pub async fn hyperv_create_vhd(_: Json<CreateVhdRequest>) -> ApiResult<VhdDto> {
-    Err(not_supported())
+pub async fn hyperv_create_diff_vhd(
+    State(state): State<SharedState>,
+    Valid(req): Valid<DiffVhdRequest>,
+) -> ApiResult<VhdDto> {
+    let _guard = state
+        .locks
+        .acquire([LockKey::vhd(&req.path), LockKey::vhd(&req.parent_path)])
+        .await
+        .map_err(lock_error)?;
+    let vhd = state
+        .hyperv
+        .create_differencing_vhd(&req.path, &req.parent_path)
+        .map_err(backend_error)?;
+    Ok(Json(ApiResponse::success(vhd)))
+}
+
+pub async fn hyperv_initialize_vhd(
+    State(state): State<SharedState>,
+    Valid(req): Valid<InitVhdRequest>,
+) -> ApiResult<String> {
+    let _guard = state
+        .locks
+        .acquire([LockKey::vhd(&req.path)])
+        .await
+        .map_err(lock_error)?;
+    let drive_letter = state.hyperv.initialize_vhd(&req).map_err(backend_error)?;
+    Ok(Json(ApiResponse::success(drive_letter)))
 }
 
-#[cfg(not(windows))]
-pub async fn hyperv_resize_vhd(_: Json<ResizeVhdRequest>) -> ApiResult<&'static str> {
-    Err(not_supported())
-}
+// =============================================================================
+// Windows Images
+// =============================================================================
 
-#[cfg(not(windows))]
-pub async fn hyperv_compact_vhd(_: Json<VhdPathRequest>) -> ApiResult<&'static str> {
-    Err(not_supported())
+pub async fn hyperv_iso_editions(
+    State(state): State<SharedState>,
+    Query(params): Query<IsoPathQuery>,
+    list: ListQuery,
+) -> ListResult<WindowsEditionDto> {
+    let editions = state
+        .hyperv
+        .windows_editions(&params.path)
+        .map_err(backend_error)?;
+    list.without("path").page(editions)
 }
 
-#[cfg(not(windows))]
-pub async fn hyperv_mount_vhd(_: Json<VhdPathRequest>) -> ApiResult<&'static str> {
-    Err(not_supported())
+pub async fn hyperv_create_vhdx_from_iso(
+    State(state): State<SharedState>,
+    Valid(req): Valid<CreateVhdxFromIsoRequest>,
+) -> AcceptedResult<JobDto> {
+    let guard = state
+        .locks
+        .acquire([LockKey::vhd(&req.vhdx_path)])
+        .await
+        .map_err(lock_error)?;
+    let hyperv = Arc::clone(&state.hyperv);
+    let job = state
+        .jobs
+        .spawn("create_vhdx_from_iso", req.vhdx_path.clone(), move |ctx| {
+            let _guard = guard;
+            hyperv.create_vhdx_from_iso(&req, &|p, s| ctx.report(p, s))
+        });
+    Ok(accepted(job))
 }
 
-#[cfg(not(windows))]
-pub async fn hyperv_dismount_vhd(_: Json<VhdPathRequest>) -> ApiResult<&'static str> {
-    Err(not_supported())
-}
+// =============================================================================
+// GPUs
+// =============================================================================
 
-#[cfg(not(windows))]
-pub async fn hyperv_create_diff_vhd(_: Json<DiffVhdRequest>) -> ApiResult<VhdDto> {
-    Err(not_supported())
+pub async fn hyperv_list_gpus(
+    State(state): State<SharedState>,
+    list: ListQuery,
+) -> ListResult<GpuDto> {
+    let gpus = state.hyperv.list_gpus().map_err(backend_error)?;
+    list.page(gpus)
 }
 
-#[cfg(not(windows))]
-pub async fn hyperv_initialize_vhd(_: Json<InitVhdRequest>) -> ApiResult<String> {
-    Err(not_supported())
+pub async fn hyperv_list_partitionable_gpus(
+    State(state): State<SharedState>,
+    list: ListQuery,
+) -> ListResult<GpuDto> {
+    let gpus = state
+        .hyperv
+        .list_partitionable_gpus()
+        .map_err(backend_error)?;
+    list.page(gpus)
 }
 
-#[cfg(not(windows))]
-pub async fn hyperv_iso_editions(_: Query<IsoPathQuery>) -> ApiResult<Vec<WindowsEditionDto>> {
-    Err(not_supported())
+pub async fn hyperv_vm_gpu_adapters(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+    list: ListQuery,
+) -> ListResult<GpuAdapterDto> {
+    let adapters = state.hyperv.vm_gpu_adapters(&name).map_err(backend_error)?;
+    list.page(adapters)
 }
 
-#[cfg(not(windows))]
-pub async fn hyperv_create_vhdx_from_iso(
-    _: Json<CreateVhdxFromIsoRequest>,
+pub async fn hyperv_add_gpu(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+    Valid(req): Valid<AddGpuRequest>,
 ) -> ApiResult<&'static str> {
-    Err(not_supported())
-}
-
-#[cfg(not(windows))]
-pub async fn hyperv_list_gpus() -> ApiResult<Vec<GpuDto>> {
-    Err(not_supported())
-}
-
-#[cfg(not(windows))]
-pub async fn hyperv_list_partitionable_gpus() -> ApiResult<Vec<GpuDto>> {
-    Err(not_supported())
-}
-
-#[cfg(not(windows))]
-pub async fn hyperv_vm_gpu_adapters(_: Path<String>) -> ApiResult<Vec<GpuAdapterDto>> {
-    Err(not_supported())
-}
-
-#[cfg(not(windows))]
-pub async fn hyperv_add_gpu(_: Path<String>, _: Json<AddGpuRequest>) -> ApiResult<&'static str> {
-    Err(not_supported())
+    let _guard = state
+        .locks
+        .acquire([LockKey::vm(&name)])
+        .await
+        .map_err(lock_error)?;
+    state
+        .hyperv
+        .add_gpu(&name, req.instance_path.as_deref())
+        .map_err(backend_error)?;
+    Ok(Json(ApiResponse::success("ok")))
 }
 
-#[cfg(not(windows))]
-pub async fn hyperv_remove_gpu(_: Path<String>) -> ApiResult<&'static str> {
-    Err(not_supported())
+pub async fn hyperv_remove_gpu(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+) -> ApiResult<&'static str> {
+    let _guard = state
+        .locks
+        .acquire([LockKey::vm(&name)])
+        .await
+        .map_err(lock_error)?;
+    state.hyperv.remove_gpu(&name).map_err(backend_error)?;
+    Ok(Json(ApiResponse::success("ok")))
 }
 
-#[cfg(not(windows))]
 pub async fn hyperv_configure_gpu(
-    _: Path<String>,
-    _: Json<ConfigureGpuRequest>,
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+    Json(req): Json<ConfigureGpuRequest>,
 ) -> ApiResult<&'static str> {
-    Err(not_supported())
+    let _guard = state
+        .locks
+        .acquire([LockKey::vm(&name)])
+        .await
+        .map_err(lock_error)?;
+    state
+        .hyperv
+        .configure_gpu(&name, req.low_mmio_gb, req.high_mmio_gb)
+        .map_err(backend_error)?;
+    Ok(Json(ApiResponse::success("ok")))
 }
 
-#[cfg(not(windows))]
-pub async fn hyperv_dda_support() -> ApiResult<DdaSupportDto> {
-    Err(not_supported())
+// =============================================================================
+// DDA
+// =============================================================================
+
+pub async fn hyperv_dda_support(State(state): State<SharedState>) -> ApiResult<DdaSupportDto> {
+    let support = state.hyperv.dda_support().map_err(backend_error)?;
+    Ok(Json(ApiResponse::success(support)))
 }
 
-#[cfg(not(windows))]
-pub async fn hyperv_dda_devices() -> ApiResult<Vec<AssignableDeviceDto>> {
-    Err(not_supported())
+pub async fn hyperv_dda_devices(
+    State(state): State<SharedState>,
+    list: ListQuery,
+) -> ListResult<AssignableDeviceDto> {
+    let devices = state.hyperv.assignable_devices().map_err(backend_error)?;
+    list.page(devices)
 }
 
-#[cfg(not(windows))]
-pub async fn hyperv_device_path(_: Query<DevicePathRequest>) -> ApiResult<String> {
-    Err(not_supported())
+pub async fn hyperv_device_path(
+    State(state): State<SharedState>,
+    Query(req): Query<DevicePathRequest>,
+) -> ApiResult<String> {
+    let location_path = state
+        .hyperv
+        .device_location_path(&req.instance_id)
+        .map_err(backend_error)?;
+    Ok(Json(ApiResponse::success(location_path)))
 }
 
-#[cfg(not(windows))]
-pub async fn hyperv_dismount_device(_: Json<DeviceLocationRequest>) -> ApiResult<&'static str> {
-    Err(not_supported())
+pub async fn hyperv_dismount_device(
+    State(state): State<SharedState>,
+    Valid(req): Valid<DeviceLocationRequest>,
+) -> ApiResult<&'static str> {
+    let _guard = state
+        .locks
+        .acquire([LockKey::device(&req.location_path)])
+        .await
+        .map_err(lock_error)?;
+    state
+        .hyperv
+        .dismount_device(&req.location_path)
+        .map_err(backend_error)?;
+    Ok(Json(ApiResponse::success("ok")))
 }
 
-#[cfg(not(windows))]
-pub async fn hyperv_mount_device(_: Json<DeviceLocationRequest>) -> ApiResult<&'static str> {
-    Err(not_supported())
+pub async fn hyperv_mount_device(
+    State(state): State<SharedState>,
+    Valid(req): Valid<DeviceLocationRequest>,
+) -> ApiResult<&'static str> {
+    let _guard = state
+        .locks
+        .acquire([LockKey::device(&req.location_path)])
+        .await
+        .map_err(lock_error)?;
+    state
+        .hyperv
+        .mount_device(&req.location_path)
+        .map_err(backend_error)?;
+    Ok(Json(ApiResponse::success("ok")))
 }
 
-#[cfg(not(windows))]
-pub async fn hyperv_vm_dda_devices(_: Path<String>) -> ApiResult<Vec<AssignableDeviceDto>> {
-    Err(not_supported())
+pub async fn hyperv_vm_dda_devices(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+    list: ListQuery,
+) -> ListResult<AssignableDeviceDto> {
+    let devices = state
+        .hyperv
+        .vm_assigned_devices(&name)
+        .map_err(backend_error)?;
+    list.page(devices)
 }
 
-#[cfg(not(windows))]
 pub async fn hyperv_assign_device(
-    _: Path<String>,
-    _: Json<DeviceLocationRequest>,
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+    Valid(req): Valid<DeviceLocationRequest>,
 ) -> ApiResult<&'static str> {
-    Err(not_supported())
+    let _guard = state
+        .locks
+        .acquire([LockKey::vm(&name), LockKey::device(&req.location_path)])
+        .await
+        .map_err(lock_error)?;
+    state
+        .hyperv
+        .assign_device(&name, &req.location_path)
+        .map_err(backend_error)?;
+    Ok(Json(ApiResponse::success("ok")))
 }
 
-#[cfg(not(windows))]
 pub async fn hyperv_remove_device(
-    _: Path<String>,
-    _: Json<DeviceLocationRequest>,
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+    Valid(req): Valid<DeviceLocationRequest>,
 ) -> ApiResult<&'static str> {
-    Err(not_supported())
+    let _guard = state
+        .locks
+        .acquire([LockKey::vm(&name), LockKey::device(&req.location_path)])
+        .await
+        .map_err(lock_error)?;
+    state
+        .hyperv
+        .remove_device(&name, &req.location_path)
+        .map_err(backend_error)?;
+    Ok(Json(ApiResponse::success("ok")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(id: &str, name: &str, parent_id: Option<&str>) -> SnapshotDto {
+        SnapshotDto {
+            name: name.to_string(),
+            id: id.to_string(),
+            vm_name: "web-01".to_string(),
+            creation_time: None,
+            parent_name: None,
+            parent_id: parent_id.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_snapshot_roots_survive_parent_cycles() {
+        let snapshots = [
+            snapshot("1", "base", None),
+            snapshot("2", "loop", Some("3")),
+            snapshot("3", "loop", Some("2")),
+            snapshot("4", "self", Some("4")),
+        ];
+        let roots = snapshot_roots(&snapshots, Some("3"));
+        let ids: Vec<_> = roots.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, ["1", "2", "4"]);
+        assert_eq!(roots[1].descendant_count, 1);
+        assert!(roots[1].children[0].is_current);
+        assert!(roots[2].children.is_empty());
+    }
 }
@@ -0,0 +1,56 @@
+//! Job API handlers
+
+use std::time::Duration;
+
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+
+use crate::dto::*;
+use crate::error::ApiError;
+use crate::listing::ListQuery;
+use crate::response::{ApiResponse, ApiResult, ListResult};
+use crate::SharedState;
+
+/// Default and maximum time `/jobs/{id}/wait` blocks
+const DEFAULT_WAIT_SECS: u64 = 30;
+const MAX_WAIT_SECS: u64 = 300;
+
+pub async fn jobs_list(State(state): State<SharedState>, list: ListQuery) -> ListResult<JobDto> {
+    list.page(state.jobs.list())
+}
+
+pub async fn jobs_get(State(state): State<SharedState>, Path(id): Path<u64>) -> ApiResult<JobDto> {
+    let job = state.jobs.get(id).map_err(ApiError::from)?;
+    Ok(Json(ApiResponse::success(job)))
+}
+
+pub async fn jobs_cancel(
+    State(state): State<SharedState>,
+    Path(id): Path<u64>,
+) -> ApiResult<JobDto> {
+    let job = state.jobs.cancel(id).map_err(ApiError::from)?;
+    Ok(Json(ApiResponse::success(job)))
+}
+
+/// Block until the job finishes or the timeout elapses
+///
+/// Always answers with the job's current state; clients check `state` to
+/// see whether it finished.
+pub async fn jobs_wait(
+    State(state): State<SharedState>,
+    Path(id): Path<u64>,
+    Query(params): Query<JobWaitQuery>,
+) -> ApiResult<JobDto> {
+    let secs = params
+        .timeout_secs
+        .unwrap_or(DEFAULT_WAIT_SECS)
+        .min(MAX_WAIT_SECS);
+    let job = state
+        .jobs
+        .wait(id, Duration::from_secs(secs))
+        .await
+        .map_err(ApiError::from)?;
+    Ok(Json(ApiResponse::success(job)))
+}
@@ -1,7 +1,15 @@
 //! API request handlers
 
+pub mod audit;
 pub mod cluster;
+pub mod events;
 pub mod hyperv;
+pub mod jobs;
+pub mod webhooks;
 
+pub use audit::*;
 pub use cluster::*;
+pub use events::*;
 pub use hyperv::*;
+pub use jobs::*;
+pub use webhooks::*;
@@ -0,0 +1,76 @@
+//! Webhook subscription and delivery handlers
+
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+
+use crate::dto::*;
+use crate::error::ApiError;
+use crate::response::{ApiResponse, ApiResult};
+use crate::validation::Valid;
+use crate::SharedState;
+
+pub async fn webhooks_list(State(state): State<SharedState>) -> ApiResult<Vec<WebhookDto>> {
+    Ok(Json(ApiResponse::success(state.webhooks.list())))
+}
+
+/// Subscribe; events published from now on are delivered
+pub async fn webhooks_create(
+    State(state): State<SharedState>,
+    Valid(req): Valid<CreateWebhookRequest>,
+) -> ApiResult<WebhookDto> {
+    let webhook = state.webhooks.create(&req).map_err(ApiError::from)?;
+    state.webhooks.start(&state);
+    Ok(Json(ApiResponse::success(webhook)))
+}
+
+pub async fn webhooks_get(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+) -> ApiResult<WebhookDto> {
+    let webhook = state.webhooks.get(&name).map_err(ApiError::from)?;
+    Ok(Json(ApiResponse::success(webhook)))
+}
+
+/// Unsubscribe; webhooks from the configuration file cannot be deleted
+pub async fn webhooks_delete(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+) -> ApiResult<&'static str> {
+    state.webhooks.delete(&name).map_err(ApiError::from)?;
+    Ok(Json(ApiResponse::success("ok")))
+}
+
+/// Delivery history, newest first
+pub async fn webhooks_deliveries(
+    State(state): State<SharedState>,
+    Query(query): Query<WebhookDeliveryQuery>,
+) -> ApiResult<Vec<WebhookDeliveryDto>> {
+    Ok(Json(ApiResponse::success(
+        state.webhooks.deliveries(&query),
+    )))
+}
+
+/// Deliveries that ran out of attempts or were refused, newest first
+pub async fn webhooks_dead_letters(
+    State(state): State<SharedState>,
+    Query(query): Query<WebhookDeliveryQuery>,
+) -> ApiResult<Vec<WebhookDeliveryDto>> {
+    let query = WebhookDeliveryQuery {
+        state: Some(DeliveryState::DeadLettered),
+        ..query
+    };
+    Ok(Json(ApiResponse::success(
+        state.webhooks.deliveries(&query),
+    )))
+}
+
+/// Queue a dead-lettered delivery again
+pub async fn webhooks_retry(
+    State(state): State<SharedState>,
+    Path(id): Path<u64>,
+) -> ApiResult<WebhookDeliveryDto> {
+    let delivery = state.webhooks.retry(id).map_err(ApiError::from)?;
+    Ok(Json(ApiResponse::success(delivery)))
+}
@@ -0,0 +1,584 @@
+//! Liveness and readiness probes
+//!
+//! `/health` only shows that the process answers. `/health/live` and
+//! `/health/ready` run [`HealthCheck`]s and answer `200` when none failed,
+//! or `503` with the failing checks, so a load balancer or orchestrator can
+//! stop sending work to a node whose backend is unreachable.
+//!
+//! Built-in checks (`[health]` configures them):
+//! - `runtime` (live): the blocking pool accepts work
+//! - `backend`: the Hyper-V backend answers `host_info`
+//! - `cluster`: the cluster service answers
+//! - `disk`: free space under the host's `vm_path` and `vhd_path`
+//! - `config`: the last configuration reload succeeded
+//! - `jobs`: background jobs and operation slots are not saturated
+//!
+//! Checks run on the blocking pool, concurrently, and a probe waits at most
+//! `timeout_ms` for them; a check still running is reported as failed and
+//! its result, once it arrives, is cached like any other. Results are
+//! reused for `cache_ttl_secs`, and a check is never run twice at once, so
+//! frequent probes cannot pile up calls on a hung backend. Checks named in
+//! `optional` report failures as warnings. More checks can be added with
+//! [`Health::register`].
+
+use std::collections::HashMap;
+use std::panic::AssertUnwindSafe;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{SecondsFormat, Utc};
+use tokio::sync::watch;
+
+use crate::config::HealthConfig;
+use crate::dto::{HealthCheckDto, HealthReportDto, HealthStatus};
+use crate::response::ApiResponse;
+use crate::{AppState, SharedState};
+
+/// Names of the built-in checks
+pub const BUILT_IN: &[&str] = &["runtime", "backend", "cluster", "disk", "config", "jobs"];
+
+const GIB: f64 = (1u64 << 30) as f64;
+
+/// Probe a check belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Probe {
+    /// `/health/live`: failing means the process should be restarted
+    Live,
+    /// `/health/ready`: failing means the node should get no traffic
+    Ready,
+}
+
+/// Result of one run of a check
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Check {
+    pub status: HealthStatus,
+    pub detail: String,
+}
+
+impl Check {
+    pub fn pass(detail: impl Into<String>) -> Self {
+        Self {
+            status: HealthStatus::Pass,
+            detail: detail.into(),
+        }
+    }
+
+    pub fn warn(detail: impl Into<String>) -> Self {
+        Self {
+            status: HealthStatus::Warn,
+            detail: detail.into(),
+        }
+    }
+
+    pub fn fail(detail: impl Into<String>) -> Self {
+        Self {
+            status: HealthStatus::Fail,
+            detail: detail.into(),
+        }
+    }
+}
+
+/// One named health check
+///
+/// `check` runs on the blocking pool and may call the backends directly.
+pub trait HealthCheck: Send + Sync {
+    fn name(&self) -> &str;
+
+    fn probe(&self) -> Probe {
+        Probe::Ready
+    }
+
+    fn check(&self, state: &AppState) -> Check;
+}
+
+// =============================================================================
+// Registry
+// =============================================================================
+
+/// Registered checks and their cached results
+pub struct Health {
+    checks: RwLock<Vec<Arc<dyn HealthCheck>>>,
+    timeout: Duration,
+    cache_ttl: Duration,
+    optional: Vec<String>,
+    entries: Mutex<HashMap<String, Entry>>,
+    config_error: Mutex<Option<String>>,
+}
+
+#[derive(Default)]
+struct Entry {
+    /// Last result and when it finished
+    latest: Option<(Instant, HealthCheckDto)>,
+    /// Result of the run in progress
+    running: Option<watch::Receiver<Option<HealthCheckDto>>>,
+}
+
+enum Pending {
+    Cached(HealthCheckDto),
+    Running(watch::Receiver<Option<HealthCheckDto>>),
+}
+
+impl Health {
+    /// Registry with the built-in checks
+    pub fn new(config: &HealthConfig) -> Self {
+        let health = Self {
+            checks: RwLock::new(Vec::new()),
+            timeout: Duration::from_millis(config.timeout_ms),
+            cache_ttl: Duration::from_secs(config.cache_ttl_secs),
+            optional: config.optional.clone(),
+            entries: Mutex::new(HashMap::new()),
+            config_error: Mutex::new(None),
+        };
+        health.register(RuntimeCheck);
+        health.register(BackendCheck);
+        health.register(ClusterCheck);
+        health.register(DiskCheck {
+            min_free_bytes: config.min_free_disk_bytes,
+            paths: config.disk_paths.clone(),
+        });
+        health.register(ConfigCheck);
+        health.register(JobsCheck {
+            max_active_jobs: config.max_active_jobs,
+        });
+        health
+    }
+
+    /// Add a check, replacing any check with the same name
+    pub fn register(&self, check: impl HealthCheck + 'static) {
+        let mut checks = self.checks.write().unwrap_or_else(|e| e.into_inner());
+        checks.retain(|existing| existing.name() != check.name());
+        checks.push(Arc::new(check));
+    }
+
+    /// Record the outcome of the latest configuration reload
+    pub fn set_config_error(&self, error: Option<String>) {
+        *self.config_error.lock().unwrap_or_else(|e| e.into_inner()) = error;
+    }
+
+    pub fn config_error(&self) -> Option<String> {
+        self.config_error
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Run, or reuse, every check of `probe`
+    pub async fn report(&self, state: &SharedState, probe: Probe) -> HealthReportDto {
+        let deadline = tokio::time::Instant::now() + self.timeout;
+        let checks: Vec<Arc<dyn HealthCheck>> = self
+            .checks
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .filter(|check| check.probe() == probe)
+            .cloned()
+            .collect();
+        let pending: Vec<Pending> = checks
+            .iter()
+            .map(|check| self.start(state, check))
+            .collect();
+
+        let mut results = Vec::with_capacity(checks.len());
+        for (check, pending) in checks.iter().zip(pending) {
+            let mut result = match pending {
+                Pending::Cached(result) => result,
+                Pending::Running(mut receiver) => {
+                    match tokio::time::timeout_at(deadline, receiver.wait_for(Option::is_some))
+                        .await
+                    {
+                        Ok(Ok(result)) => result.clone().expect("waited for a result"),
+                        _ => self.unfinished(check.name()),
+                    }
+                }
+            };
+            if result.status == HealthStatus::Fail && self.optional.contains(&result.name) {
+                result.status = HealthStatus::Warn;
+            }
+            results.push(result);
+        }
+
+        HealthReportDto {
+            status: results
+                .iter()
+                .map(|result| result.status)
+                .max()
+                .unwrap_or(HealthStatus::Pass),
+            checks: results,
+        }
+    }
+
+    /// Reuse a fresh result, join a run in progress or start one
+    fn start(&self, state: &SharedState, check: &Arc<dyn HealthCheck>) -> Pending {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        let entry = entries.entry(check.name().to_string()).or_default();
+        if let Some((finished, result)) = &entry.latest {
+            if finished.elapsed() < self.cache_ttl {
+                return Pending::Cached(HealthCheckDto {
+                    cached: true,
+                    ..result.clone()
+                });
+            }
+        }
+        if let Some(running) = &entry.running {
+            return Pending::Running(running.clone());
+        }
+
+        let (sender, receiver) = watch::channel(None);
+        entry.running = Some(receiver.clone());
+        let state = state.clone();
+        let check = check.clone();
+        tokio::task::spawn_blocking(move || {
+            let started = Instant::now();
+            let outcome = std::panic::catch_unwind(AssertUnwindSafe(|| check.check(&state)))
+                .unwrap_or_else(|_| Check::fail("Check panicked"));
+            let result = HealthCheckDto {
+                name: check.name().to_string(),
+                status: outcome.status,
+                latency_ms: started.elapsed().as_millis() as u64,
+                detail: outcome.detail,
+                checked_at: now(),
+                cached: false,
+            };
+            state.health.finish(result.clone());
+            let _ = sender.send(Some(result));
+        });
+        Pending::Running(receiver)
+    }
+
+    fn finish(&self, result: HealthCheckDto) {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        let entry = entries.entry(result.name.clone()).or_default();
+        entry.running = None;
+        entry.latest = Some((Instant::now(), result));
+    }
+
+    fn unfinished(&self, name: &str) -> HealthCheckDto {
+        HealthCheckDto {
+            name: name.to_string(),
+            status: HealthStatus::Fail,
+            latency_ms: self.timeout.as_millis() as u64,
+            detail: format!("No result within {} ms", self.timeout.as_millis()),
+            checked_at: now(),
+            cached: false,
+        }
+    }
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Self::new(&HealthConfig::default())
+    }
+}
+
+fn now() -> String {
+    Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+// =============================================================================
+// Handlers
+// =============================================================================
+
+/// `GET /health/live`
+pub async fn live(State(state): State<SharedState>) -> Response {
+    respond(state.health.report(&state, Probe::Live).await)
+}
+
+/// `GET /health/ready`
+pub async fn ready(State(state): State<SharedState>) -> Response {
+    respond(state.health.report(&state, Probe::Ready).await)
+}
+
+/// `200` unless a check failed, then `503` naming the failed checks
+fn respond(report: HealthReportDto) -> Response {
+    let failed: Vec<&str> = report
+        .checks
+        .iter()
+        .filter(|check| check.status == HealthStatus::Fail)
+        .map(|check| check.name.as_str())
+        .collect();
+    let (status, error) = match failed.is_empty() {
+        true => (StatusCode::OK, None),
+        false => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Some(format!("Failed checks: {}", failed.join(", "))),
+        ),
+    };
+    let mut envelope = ApiResponse::success(report);
+    envelope.success = error.is_none();
+    envelope.error = error;
+    (status, Json(envelope)).into_response()
+}
+
+// =============================================================================
+// Built-in Checks
+// =============================================================================
+
+struct RuntimeCheck;
+
+impl HealthCheck for RuntimeCheck {
+    fn name(&self) -> &str {
+        "runtime"
+    }
+
+    fn probe(&self) -> Probe {
+        Probe::Live
+    }
+
+    fn check(&self, _: &AppState) -> Check {
+        Check::pass("Blocking pool is accepting work")
+    }
+}
+
+struct BackendCheck;
+
+impl HealthCheck for BackendCheck {
+    fn name(&self) -> &str {
+        "backend"
+    }
+
+    fn check(&self, state: &AppState) -> Check {
+        match state.hyperv.host_info() {
+            Ok(host) => Check::pass(format!("Hyper-V host {} answered", host.computer_name)),
+            Err(e) => Check::fail(e.to_string()),
+        }
+    }
+}
+
+struct ClusterCheck;
+
+impl HealthCheck for ClusterCheck {
+    fn name(&self) -> &str {
+        "cluster"
+    }
+
+    fn check(&self, state: &AppState) -> Check {
+        match state.cluster.cluster_name(None) {
+            Ok(name) => Check::pass(format!("Cluster {} answered", name)),
+            Err(e) => Check::fail(e.to_string()),
+        }
+    }
+}
+
+struct DiskCheck {
+    min_free_bytes: u64,
+    /// Configured paths; the host's VM and VHD paths when empty
+    paths: Vec<String>,
+}
+
+impl HealthCheck for DiskCheck {
+    fn name(&self) -> &str {
+        "disk"
+    }
+
+    fn check(&self, state: &AppState) -> Check {
+        let paths = match self.paths.is_empty() {
+            false => self.paths.clone(),
+            true => match state.hyperv.host_info() {
+                Ok(host) if host.vm_path == host.vhd_path => vec![host.vm_path],
+                Ok(host) => vec![host.vm_path, host.vhd_path],
+                Err(e) => return Check::warn(format!("Cannot read the host's paths: {}", e)),
+            },
+        };
+
+        let mut status = HealthStatus::Pass;
+        let mut details = Vec::with_capacity(paths.len());
+        for path in paths {
+            match free_space(&path) {
+                Ok(free) if free < self.min_free_bytes => {
+                    status = status.max(HealthStatus::Fail);
+                    details.push(format!(
+                        "{}: {:.1} GiB free, below {:.1} GiB",
+                        path,
+                        free as f64 / GIB,
+                        self.min_free_bytes as f64 / GIB
+                    ));
+                }
+                Ok(free) => details.push(format!("{}: {:.1} GiB free", path, free as f64 / GIB)),
+                Err(e) => {
+                    status = status.max(HealthStatus::Warn);
+                    details.push(format!("{}: {}", path, e));
+                }
+            }
+        }
+        Check {
+            status,
+            detail: details.join("; "),
+        }
+    }
+}
+
+struct ConfigCheck;
+
+impl HealthCheck for ConfigCheck {
+    fn name(&self) -> &str {
+        "config"
+    }
+
+    fn check(&self, state: &AppState) -> Check {
+        match state.health.config_error() {
+            None => Check::pass("Configuration is valid"),
+            Some(e) => Check::warn(format!(
+                "Last reload failed, running with the previous configuration: {}",
+                e
+            )),
+        }
+    }
+}
+
+struct JobsCheck {
+    max_active_jobs: usize,
+}
+
+impl HealthCheck for JobsCheck {
+    fn name(&self) -> &str {
+        "jobs"
+    }
+
+    fn check(&self, state: &AppState) -> Check {
+        let active = state.jobs.active_count();
+        let mut status = if active >= self.max_active_jobs {
+            HealthStatus::Fail
+        } else if active * 5 >= self.max_active_jobs * 4 {
+            HealthStatus::Warn
+        } else {
+            HealthStatus::Pass
+        };
+        let mut detail = format!(
+            "{} of {} background jobs running",
+            active, self.max_active_jobs
+        );
+        if let Some((used, capacity)) = state.locks.operations() {
+            detail.push_str(&format!(
+                "; {} of {} operation slots in use",
+                used, capacity
+            ));
+            if used >= capacity {
+                status = status.max(HealthStatus::Warn);
+            }
+        }
+        Check { status, detail }
+    }
+}
+
+/// Bytes available to this process on the volume holding `path`
+#[cfg(unix)]
+fn free_space(path: &str) -> std::io::Result<u64> {
+    let path = std::ffi::CString::new(path)?;
+    // SAFETY: `path` is NUL-terminated and `stat` is a valid out pointer
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    // Field widths differ between platforms
+    #[allow(clippy::unnecessary_cast)]
+    Ok(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+/// Bytes available to this process on the volume holding `path`
+#[cfg(windows)]
+fn free_space(path: &str) -> std::io::Result<u64> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
+
+    let path: Vec<u16> = std::ffi::OsStr::new(path)
+        .encode_wide()
+        .chain(std::iter::once(0))
+        .collect();
+    let mut available = 0u64;
+    // SAFETY: `path` is NUL-terminated; null out pointers are allowed
+    let ok = unsafe {
+        GetDiskFreeSpaceExW(
+            path.as_ptr(),
+            &mut available,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        )
+    };
+    if ok == 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(available)
+}
+
+// =============================================================================
+// Tests
+// =============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct Counting {
+        runs: Arc<AtomicUsize>,
+        delay: Duration,
+    }
+
+    impl HealthCheck for Counting {
+        fn name(&self) -> &str {
+            "counting"
+        }
+
+        fn check(&self, _: &AppState) -> Check {
+            self.runs.fetch_add(1, Ordering::SeqCst);
+            std::thread::sleep(self.delay);
+            Check::pass("done")
+        }
+    }
+
+    fn state(config: HealthConfig, delay: Duration) -> (SharedState, Arc<AtomicUsize>) {
+        let state = AppState {
+            health: Arc::new(Health::new(&config)),
+            ..AppState::default()
+        };
+        let runs = Arc::new(AtomicUsize::new(0));
+        state.health.register(Counting {
+            runs: runs.clone(),
+            delay,
+        });
+        (Arc::new(state), runs)
+    }
+
+    fn counting(report: &HealthReportDto) -> &HealthCheckDto {
+        report
+            .checks
+            .iter()
+            .find(|check| check.name == "counting")
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_results_are_cached() {
+        let (state, runs) = state(HealthConfig::default(), Duration::ZERO);
+        let first = state.health.report(&state, Probe::Ready).await;
+        let second = state.health.report(&state, Probe::Ready).await;
+        assert!(!counting(&first).cached);
+        assert!(counting(&second).cached);
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_slow_checks_time_out_and_are_not_rerun() {
+        let config = HealthConfig {
+            timeout_ms: 50,
+            cache_ttl_secs: 0,
+            ..HealthConfig::default()
+        };
+        let (state, runs) = state(config, Duration::from_millis(300));
+
+        let report = state.health.report(&state, Probe::Ready).await;
+        assert_eq!(report.status, HealthStatus::Fail);
+        assert_eq!(counting(&report).status, HealthStatus::Fail);
+        assert_eq!(counting(&report).detail, "No result within 50 ms");
+
+        // The run in progress is joined rather than started again
+        state.health.report(&state, Probe::Ready).await;
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+    }
+}
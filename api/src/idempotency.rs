@@ -0,0 +1,369 @@
+//! `Idempotency-Key` handling for POST requests
+//!
+//! Automation retries POSTs on network errors; without a key a retried
+//! `POST /api/v1/hyperv/vms` creates a second VM. The [`replay`] middleware
+//! remembers, per caller and key, a SHA-256 fingerprint of the method, path
+//! and body together with the response, for `[idempotency] ttl_secs`:
+//!
+//! - a repeat with the same fingerprint gets the stored response back, marked
+//!   with `Idempotent-Replayed: true`, without reaching the handler;
+//! - a repeat with a different fingerprint gets 422;
+//! - a repeat while the first request is still running gets 409.
+//!
+//! Only successful responses are stored. A failed request releases its key so
+//! the client can retry it once the cause is fixed.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use axum::{
+    body::{to_bytes, Body, Bytes},
+    extract::{Request, State},
+    http::{header, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use sha2::{Digest, Sha256};
+
+use crate::auth::Principal;
+use crate::config::IdempotencyConfig;
+use crate::error::{ApiError, ErrorCode};
+use crate::response::api_error;
+use crate::SharedState;
+
+/// Request header carrying the client's key
+pub const IDEMPOTENCY_KEY: &str = "idempotency-key";
+
+/// Response header set on replayed responses
+pub const IDEMPOTENT_REPLAYED: &str = "idempotent-replayed";
+
+/// Longest accepted key
+const MAX_KEY_LEN: usize = 255;
+
+/// Largest request body buffered for the fingerprint (axum's default limit)
+const BODY_LIMIT: usize = 2 * 1024 * 1024;
+
+/// A completed response kept for replay
+#[derive(Debug, Clone)]
+pub struct StoredResponse {
+    pub status: StatusCode,
+    pub content_type: Option<HeaderValue>,
+    pub body: Bytes,
+}
+
+impl IntoResponse for StoredResponse {
+    fn into_response(self) -> Response {
+        let mut response = (self.status, self.body).into_response();
+        let headers = response.headers_mut();
+        if let Some(content_type) = self.content_type {
+            headers.insert(header::CONTENT_TYPE, content_type);
+        }
+        headers.insert(IDEMPOTENT_REPLAYED, HeaderValue::from_static("true"));
+        response
+    }
+}
+
+/// What to do with a request carrying a key
+#[derive(Debug)]
+pub enum Claim {
+    /// First use of the key; run the request and [`IdempotencyStore::complete`] it
+    Started,
+    /// Same request already completed
+    Replay(StoredResponse),
+    /// Same key, different request
+    Mismatch,
+    /// Same key, original request still running
+    InProgress,
+}
+
+struct Entry {
+    fingerprint: [u8; 32],
+    created: Instant,
+    response: Option<StoredResponse>,
+}
+
+type Scope = (String, String);
+
+/// Keys and stored responses, scoped per caller
+pub struct IdempotencyStore {
+    enabled: bool,
+    ttl: Duration,
+    max_keys: usize,
+    entries: Mutex<HashMap<Scope, Entry>>,
+}
+
+impl IdempotencyStore {
+    pub fn new(config: &IdempotencyConfig) -> Self {
+        Self {
+            enabled: config.enabled,
+            ttl: Duration::from_secs(config.ttl_secs),
+            max_keys: config.max_keys,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Reserve `key` for `caller`, or report how an earlier use of it ended
+    pub fn claim(&self, caller: &str, key: &str, fingerprint: [u8; 32]) -> Claim {
+        let mut entries = self.table();
+        let now = Instant::now();
+        entries.retain(|_, entry| now.duration_since(entry.created) < self.ttl);
+
+        let scope = (caller.to_string(), key.to_string());
+        if let Some(entry) = entries.get(&scope) {
+            return if entry.fingerprint != fingerprint {
+                Claim::Mismatch
+            } else {
+                match &entry.response {
+                    Some(response) => Claim::Replay(response.clone()),
+                    None => Claim::InProgress,
+                }
+            };
+        }
+
+        if entries.len() >= self.max_keys {
+            // Evict the oldest completed key; running requests keep theirs
+            let oldest = entries
+                .iter()
+                .filter(|(_, entry)| entry.response.is_some())
+                .min_by_key(|(_, entry)| entry.created)
+                .map(|(scope, _)| scope.clone());
+            if let Some(oldest) = oldest {
+                entries.remove(&oldest);
+            }
+        }
+        entries.insert(
+            scope,
+            Entry {
+                fingerprint,
+                created: now,
+                response: None,
+            },
+        );
+        Claim::Started
+    }
+
+    /// Store the response of a started request, or release the key if it failed
+    pub fn complete(&self, caller: &str, key: &str, response: Option<StoredResponse>) {
+        let scope = (caller.to_string(), key.to_string());
+        let mut entries = self.table();
+        match response {
+            Some(response) => {
+                if let Some(entry) = entries.get_mut(&scope) {
+                    entry.response = Some(response);
+                }
+            }
+            None => {
+                entries.remove(&scope);
+            }
+        }
+    }
+
+    /// Number of keys currently remembered
+    pub fn len(&self) -> usize {
+        self.table().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn table(&self) -> std::sync::MutexGuard<'_, HashMap<Scope, Entry>> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Default for IdempotencyStore {
+    fn default() -> Self {
+        Self::new(&IdempotencyConfig::default())
+    }
+}
+
+/// Fingerprint of a request: method, path with query, and body
+pub fn fingerprint(method: &Method, path_and_query: &str, body: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(method.as_str());
+    hasher.update([0]);
+    hasher.update(path_and_query);
+    hasher.update([0]);
+    hasher.update(body);
+    hasher.finalize().into()
+}
+
+/// Releases a started key if the request never completes (panic, disconnect)
+struct Pending<'a> {
+    store: &'a IdempotencyStore,
+    caller: &'a str,
+    key: &'a str,
+    done: bool,
+}
+
+impl Drop for Pending<'_> {
+    fn drop(&mut self) {
+        if !self.done {
+            self.store.complete(self.caller, self.key, None);
+        }
+    }
+}
+
+/// Replay, reject or run POST requests that carry an `Idempotency-Key`
+pub async fn replay(State(state): State<SharedState>, request: Request, next: Next) -> Response {
+    if !state.idempotency.is_enabled() || request.method() != Method::POST {
+        return next.run(request).await;
+    }
+    let Some(key) = request.headers().get(IDEMPOTENCY_KEY) else {
+        return next.run(request).await;
+    };
+    let key = match key.to_str() {
+        Ok(key) if !key.is_empty() && key.len() <= MAX_KEY_LEN => key.to_string(),
+        _ => {
+            return api_error(
+                StatusCode::BAD_REQUEST,
+                "Idempotency-Key must be 1 to 255 visible ASCII characters",
+            )
+            .into_response()
+        }
+    };
+
+    let (parts, body) = request.into_parts();
+    let body = match to_bytes(body, BODY_LIMIT).await {
+        Ok(body) => body,
+        Err(_) => {
+            return api_error(StatusCode::PAYLOAD_TOO_LARGE, "Request body is too large")
+                .into_response()
+        }
+    };
+    let caller = parts
+        .extensions
+        .get::<Principal>()
+        .map(|p| p.name.clone())
+        .unwrap_or_default();
+    let path = parts
+        .uri
+        .path_and_query()
+        .map_or(parts.uri.path(), |p| p.as_str());
+    let fingerprint = fingerprint(&parts.method, path, &body);
+
+    let store = &state.idempotency;
+    match store.claim(&caller, &key, fingerprint) {
+        Claim::Started => {}
+        Claim::Replay(stored) => return stored.into_response(),
+        Claim::Mismatch => {
+            return ApiError::new(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                ErrorCode::IdempotencyKeyReused,
+                "Idempotency-Key was already used for a different request",
+            )
+            .into_response()
+        }
+        Claim::InProgress => {
+            return ApiError::new(
+                StatusCode::CONFLICT,
+                ErrorCode::IdempotencyInProgress,
+                "A request with this Idempotency-Key is still in progress",
+            )
+            .with_retryable(true)
+            .into_response()
+        }
+    }
+
+    let mut pending = Pending {
+        store,
+        caller: &caller,
+        key: &key,
+        done: false,
+    };
+    let response = next.run(Request::from_parts(parts, Body::from(body))).await;
+    if !response.status().is_success() {
+        return response;
+    }
+
+    let (parts, body) = response.into_parts();
+    let body = match to_bytes(body, usize::MAX).await {
+        Ok(body) => body,
+        Err(e) => {
+            tracing::warn!("Could not buffer response for idempotent replay: {}", e);
+            return api_error(StatusCode::INTERNAL_SERVER_ERROR, "Response body failed")
+                .into_response();
+        }
+    };
+    store.complete(
+        &caller,
+        &key,
+        Some(StoredResponse {
+            status: parts.status,
+            content_type: parts.headers.get(header::CONTENT_TYPE).cloned(),
+            body: body.clone(),
+        }),
+    );
+    pending.done = true;
+    Response::from_parts(parts, Body::from(body))
+}
+
+// =============================================================================
+// Tests
+// =============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store(ttl_secs: u64, max_keys: usize) -> IdempotencyStore {
+        IdempotencyStore::new(&IdempotencyConfig {
+            enabled: true,
+            ttl_secs,
+            max_keys,
+        })
+    }
+
+    fn ok() -> Option<StoredResponse> {
+        Some(StoredResponse {
+            status: StatusCode::OK,
+            content_type: None,
+            body: Bytes::from_static(b"{}"),
+        })
+    }
+
+    #[test]
+    fn test_claim_lifecycle() {
+        let store = store(60, 10);
+        let a = fingerprint(&Method::POST, "/api/v1/hyperv/vms", b"{\"name\":\"a\"}");
+        let b = fingerprint(&Method::POST, "/api/v1/hyperv/vms", b"{\"name\":\"b\"}");
+
+        assert!(matches!(store.claim("alice", "k1", a), Claim::Started));
+        assert!(matches!(store.claim("alice", "k1", a), Claim::InProgress));
+        assert!(matches!(store.claim("alice", "k1", b), Claim::Mismatch));
+        // Keys are scoped per caller
+        assert!(matches!(store.claim("bob", "k1", b), Claim::Started));
+
+        store.complete("alice", "k1", ok());
+        assert!(matches!(store.claim("alice", "k1", a), Claim::Replay(_)));
+
+        // A failed request frees its key
+        store.complete("bob", "k1", None);
+        assert!(matches!(store.claim("bob", "k1", a), Claim::Started));
+    }
+
+    #[test]
+    fn test_expiry_and_capacity() {
+        let store = store(0, 10);
+        let a = fingerprint(&Method::POST, "/x", b"");
+        store.claim("alice", "k1", a);
+        store.complete("alice", "k1", ok());
+        assert!(matches!(store.claim("alice", "k1", a), Claim::Started));
+
+        let store = self::store(60, 2);
+        for key in ["k1", "k2"] {
+            store.claim("alice", key, a);
+            store.complete("alice", key, ok());
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        store.claim("alice", "k3", a);
+        assert_eq!(store.len(), 2);
+        assert!(matches!(store.claim("alice", "k1", a), Claim::Started));
+    }
+}
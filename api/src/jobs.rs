@@ -0,0 +1,523 @@
+//! Asynchronous job manager for long-running operations
+//!
+//! Export, VHD creation and compaction, image creation, snapshot apply and
+//! cluster group moves can take minutes. Their handlers hand the work to
+//! [`JobManager::spawn`], which runs it on the blocking thread pool and
+//! returns immediately with a job id. Clients poll `/api/v1/jobs/{id}` or
+//! block on `/api/v1/jobs/{id}/wait`.
+//!
+//! Finished jobs are kept in a bounded history; the oldest finished job is
+//! evicted once the limit is reached. Queued and running jobs are never
+//! evicted.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use thiserror::Error;
+use tokio::sync::watch;
+
+use crate::backend::BackendResult;
+use crate::dto::JobDto;
+
+/// Lifecycle of a job
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobState {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl JobState {
+    /// Check if the job has reached a final state
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            JobState::Completed | JobState::Failed | JobState::Cancelled
+        )
+    }
+}
+
+/// Errors returned by job queries and cancellation
+#[derive(Error, Debug)]
+pub enum JobError {
+    #[error("Job not found: {0}")]
+    NotFound(u64),
+
+    #[error("Job {0} has already finished")]
+    AlreadyFinished(u64),
+}
+
+#[derive(Debug)]
+struct Job {
+    id: u64,
+    kind: String,
+    target: String,
+    state: JobState,
+    percent_complete: u32,
+    status: Option<String>,
+    created_at: DateTime<Utc>,
+    started_at: Option<DateTime<Utc>>,
+    finished_at: Option<DateTime<Utc>>,
+    result: Option<Value>,
+    error: Option<String>,
+}
+
+impl Job {
+    fn to_dto(&self) -> JobDto {
+        JobDto {
+            id: self.id,
+            kind: self.kind.clone(),
+            target: self.target.clone(),
+            state: format!("{:?}", self.state),
+            percent_complete: self.percent_complete,
+            status: self.status.clone(),
+            created_at: timestamp(self.created_at),
+            started_at: self.started_at.map(timestamp),
+            finished_at: self.finished_at.map(timestamp),
+            result: self.result.clone(),
+            error: self.error.clone(),
+        }
+    }
+}
+
+fn timestamp(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+struct JobEntry {
+    job: Job,
+    cancelled: Arc<AtomicBool>,
+    done: watch::Sender<bool>,
+}
+
+#[derive(Default)]
+struct JobTable {
+    jobs: BTreeMap<u64, JobEntry>,
+    finished: VecDeque<u64>,
+    next_id: u64,
+}
+
+/// Handle passed to job work for progress reporting and cancellation checks
+pub struct JobContext {
+    id: u64,
+    manager: Arc<JobManager>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl JobContext {
+    /// Id of the job being executed
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Record percent complete (clamped to 100) and a status description
+    pub fn report(&self, percent_complete: u32, status: &str) {
+        self.manager
+            .update_progress(self.id, percent_complete.min(100), status);
+    }
+
+    /// Check if the job was cancelled
+    ///
+    /// Work that runs in several steps should stop early once this is set.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
+
+/// Tracks background jobs and their history
+pub struct JobManager {
+    table: Mutex<JobTable>,
+    history_limit: usize,
+    /// Jobs whose work is executing, including cancelled jobs whose work
+    /// has not returned yet
+    executing: watch::Sender<usize>,
+}
+
+impl Default for JobManager {
+    fn default() -> Self {
+        Self::new(DEFAULT_HISTORY_LIMIT)
+    }
+}
+
+/// Number of finished jobs kept when no limit is configured
+pub const DEFAULT_HISTORY_LIMIT: usize = 100;
+
+impl JobManager {
+    /// Create a manager keeping at most `history_limit` finished jobs
+    pub fn new(history_limit: usize) -> Self {
+        Self {
+            table: Mutex::new(JobTable::default()),
+            history_limit: history_limit.max(1),
+            executing: watch::Sender::new(0),
+        }
+    }
+
+    fn table(&self) -> MutexGuard<'_, JobTable> {
+        self.table.lock().expect("job table poisoned")
+    }
+
+    /// Queue `work` on the blocking thread pool and return the new job
+    ///
+    /// `kind` names the operation (e.g. `export_vm`) and `target` the object
+    /// it acts on. The value returned by `work` is serialized as the job
+    /// result.
+    pub fn spawn<F, T>(self: &Arc<Self>, kind: &str, target: impl Into<String>, work: F) -> JobDto
+    where
+        F: FnOnce(&JobContext) -> BackendResult<T> + Send + 'static,
+        T: Serialize,
+    {
+        let target = target.into();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let (id, dto) = {
+            let mut table = self.table();
+            table.next_id += 1;
+            let id = table.next_id;
+            let job = Job {
+                id,
+                kind: kind.to_string(),
+                target: target.clone(),
+                state: JobState::Queued,
+                percent_complete: 0,
+                status: None,
+                created_at: Utc::now(),
+                started_at: None,
+                finished_at: None,
+                result: None,
+                error: None,
+            };
+            let dto = job.to_dto();
+            let (done, _) = watch::channel(false);
+            table.jobs.insert(
+                id,
+                JobEntry {
+                    job,
+                    cancelled: Arc::clone(&cancelled),
+                    done,
+                },
+            );
+            (id, dto)
+        };
+
+        tracing::info!("Job {} queued: {} {}", id, kind, target);
+
+        let ctx = JobContext {
+            id,
+            manager: Arc::clone(self),
+            cancelled,
+        };
+        tokio::task::spawn_blocking(move || {
+            if !ctx.manager.mark_running(ctx.id) {
+                return;
+            }
+            let outcome = catch_unwind(AssertUnwindSafe(|| work(&ctx)));
+            let outcome = match outcome {
+                Ok(Ok(value)) => serde_json::to_value(value).map_err(|e| e.to_string()),
+                Ok(Err(e)) => Err(e.to_string()),
+                Err(_) => Err("job panicked".to_string()),
+            };
+            ctx.manager.finish(ctx.id, outcome);
+            ctx.manager.executing.send_modify(|n| *n -= 1);
+        });
+
+        dto
+    }
+
+    /// List all known jobs, oldest first
+    pub fn list(&self) -> Vec<JobDto> {
+        self.table().jobs.values().map(|e| e.job.to_dto()).collect()
+    }
+
+    /// Get a single job
+    pub fn get(&self, id: u64) -> Result<JobDto, JobError> {
+        self.table()
+            .jobs
+            .get(&id)
+            .map(|e| e.job.to_dto())
+            .ok_or(JobError::NotFound(id))
+    }
+
+    /// Number of queued or running jobs
+    pub fn active_count(&self) -> usize {
+        self.table()
+            .jobs
+            .values()
+            .filter(|e| !e.job.state.is_finished())
+            .count()
+    }
+
+    /// Number of queued jobs plus jobs whose work is still executing
+    ///
+    /// Unlike [`active_count`](Self::active_count), this includes cancelled
+    /// jobs whose backend call has not returned yet.
+    pub fn pending_count(&self) -> usize {
+        let queued = self
+            .table()
+            .jobs
+            .values()
+            .filter(|e| e.job.state == JobState::Queued)
+            .count();
+        queued + *self.executing.borrow()
+    }
+
+    /// Cancel a queued or running job
+    ///
+    /// The job is marked cancelled immediately. Work already executing on
+    /// the backend is asked to stop via [`JobContext::is_cancelled`]; any
+    /// result it produces afterwards is discarded. Until that work returns
+    /// the job still counts towards [`pending_count`](Self::pending_count).
+    pub fn cancel(&self, id: u64) -> Result<JobDto, JobError> {
+        let mut table = self.table();
+        let entry = table.jobs.get_mut(&id).ok_or(JobError::NotFound(id))?;
+        if entry.job.state.is_finished() {
+            return Err(JobError::AlreadyFinished(id));
+        }
+        entry.cancelled.store(true, Ordering::SeqCst);
+        entry.job.state = JobState::Cancelled;
+        entry.job.finished_at = Some(Utc::now());
+        entry.done.send_replace(true);
+        let dto = entry.job.to_dto();
+        self.record_finished(&mut table, id);
+        tracing::info!("Job {} cancelled", id);
+        Ok(dto)
+    }
+
+    /// Wait until the job finishes or `timeout` elapses, then return it
+    pub async fn wait(&self, id: u64, timeout: Duration) -> Result<JobDto, JobError> {
+        let mut done = self
+            .table()
+            .jobs
+            .get(&id)
+            .map(|e| e.done.subscribe())
+            .ok_or(JobError::NotFound(id))?;
+        let _ = tokio::time::timeout(timeout, done.wait_for(|finished| *finished)).await;
+        self.get(id)
+    }
+
+    /// Wait until no job is queued or executing, or `deadline` passes
+    ///
+    /// Cancelled jobs are waited for until their work returns. Returns the
+    /// [`pending_count`](Self::pending_count) left at the deadline.
+    pub async fn wait_idle(&self, deadline: Instant) -> usize {
+        let deadline = tokio::time::Instant::from_std(deadline);
+        loop {
+            let pending: Vec<_> = self
+                .table()
+                .jobs
+                .values()
+                .filter(|e| !e.job.state.is_finished())
+                .map(|e| e.done.subscribe())
+                .collect();
+            if pending.is_empty() {
+                let mut executing = self.executing.subscribe();
+                let idle = executing.wait_for(|n| *n == 0);
+                if tokio::time::timeout_at(deadline, idle).await.is_err() {
+                    return self.pending_count();
+                }
+                return 0;
+            }
+            for mut done in pending {
+                let finished = done.wait_for(|finished| *finished);
+                if tokio::time::timeout_at(deadline, finished).await.is_err() {
+                    return self.pending_count();
+                }
+            }
+        }
+    }
+
+    /// Move a queued job to running; returns false if it was cancelled
+    fn mark_running(&self, id: u64) -> bool {
+        let mut table = self.table();
+        match table.jobs.get_mut(&id) {
+            Some(entry) if entry.job.state == JobState::Queued => {
+                entry.job.state = JobState::Running;
+                entry.job.started_at = Some(Utc::now());
+                self.executing.send_modify(|n| *n += 1);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn update_progress(&self, id: u64, percent_complete: u32, status: &str) {
+        let mut table = self.table();
+        if let Some(entry) = table.jobs.get_mut(&id) {
+            if entry.job.state == JobState::Running {
+                entry.job.percent_complete = percent_complete;
+                entry.job.status = Some(status.to_string());
+            }
+        }
+    }
+
+    fn finish(&self, id: u64, outcome: Result<Value, String>) {
+        let mut table = self.table();
+        let Some(entry) = table.jobs.get_mut(&id) else {
+            return;
+        };
+        if entry.job.state != JobState::Running {
+            // Cancelled while running; keep the cancellation
+            return;
+        }
+        match outcome {
+            Ok(value) => {
+                entry.job.state = JobState::Completed;
+                entry.job.percent_complete = 100;
+                entry.job.result = Some(value);
+                tracing::info!("Job {} completed", id);
+            }
+            Err(error) => {
+                tracing::warn!("Job {} failed: {}", id, error);
+                entry.job.state = JobState::Failed;
+                entry.job.error = Some(error);
+            }
+        }
+        entry.job.finished_at = Some(Utc::now());
+        entry.done.send_replace(true);
+        self.record_finished(&mut table, id);
+    }
+
+    fn record_finished(&self, table: &mut JobTable, id: u64) {
+        table.finished.push_back(id);
+        while table.finished.len() > self.history_limit {
+            if let Some(oldest) = table.finished.pop_front() {
+                table.jobs.remove(&oldest);
+            }
+        }
+    }
+}
+
+// =============================================================================
+// Tests
+// =============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::BackendError;
+
+    async fn wait_finished(manager: &JobManager, id: u64) -> JobDto {
+        manager.wait(id, Duration::from_secs(5)).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_job_completes_with_result() {
+        let manager = Arc::new(JobManager::default());
+        let job = manager.spawn("test", "target", |ctx| {
+            ctx.report(50, "halfway");
+            Ok(Value::from(42))
+        });
+        assert_eq!(job.state, "Queued");
+
+        let job = wait_finished(&manager, job.id).await;
+        assert_eq!(job.state, "Completed");
+        assert_eq!(job.percent_complete, 100);
+        assert_eq!(job.status.as_deref(), Some("halfway"));
+        assert_eq!(job.result, Some(Value::from(42)));
+        assert!(job.started_at.is_some());
+        assert!(job.finished_at.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_job_failure_records_error() {
+        let manager = Arc::new(JobManager::default());
+        let job = manager.spawn("test", "target", |_| -> BackendResult<()> {
+            Err(BackendError::NotSupported("nope".to_string()))
+        });
+
+        let job = wait_finished(&manager, job.id).await;
+        assert_eq!(job.state, "Failed");
+        assert_eq!(job.error.as_deref(), Some("nope"));
+        assert!(job.result.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_cancel_running_job() {
+        let manager = Arc::new(JobManager::default());
+        let job = manager.spawn("test", "target", |ctx| {
+            while !ctx.is_cancelled() {
+                std::thread::sleep(Duration::from_millis(5));
+            }
+            Ok(())
+        });
+
+        let cancelled = manager.cancel(job.id).unwrap();
+        assert_eq!(cancelled.state, "Cancelled");
+        assert!(matches!(
+            manager.cancel(job.id),
+            Err(JobError::AlreadyFinished(_))
+        ));
+
+        let job = wait_finished(&manager, job.id).await;
+        assert_eq!(job.state, "Cancelled");
+        assert!(job.result.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_history_is_bounded() {
+        let manager = Arc::new(JobManager::new(2));
+        let mut ids = Vec::new();
+        for _ in 0..3 {
+            let job = manager.spawn("test", "target", |_| Ok(()));
+            wait_finished(&manager, job.id).await;
+            ids.push(job.id);
+        }
+
+        assert!(matches!(manager.get(ids[0]), Err(JobError::NotFound(_))));
+        assert_eq!(manager.list().len(), 2);
+        assert_eq!(manager.active_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_wait_idle_stops_at_deadline() {
+        let manager = Arc::new(JobManager::default());
+        manager.spawn("quick", "target", |_| {
+            std::thread::sleep(Duration::from_millis(50));
+            Ok(())
+        });
+        let soon = Instant::now() + Duration::from_secs(5);
+        assert_eq!(manager.wait_idle(soon).await, 0);
+
+        manager.spawn("slow", "target", |_| {
+            std::thread::sleep(Duration::from_millis(500));
+            Ok(())
+        });
+        let shortly = Instant::now() + Duration::from_millis(50);
+        assert_eq!(manager.wait_idle(shortly).await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_wait_idle_waits_for_cancelled_work() {
+        let manager = Arc::new(JobManager::default());
+        let returned = Arc::new(AtomicBool::new(false));
+        let job = manager.spawn("stubborn", "target", {
+            let returned = Arc::clone(&returned);
+            move |_| {
+                // A backend call that cannot be interrupted
+                std::thread::sleep(Duration::from_millis(300));
+                returned.store(true, Ordering::SeqCst);
+                Ok(())
+            }
+        });
+        while manager.get(job.id).unwrap().state == "Queued" {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        manager.cancel(job.id).unwrap();
+        assert_eq!(manager.active_count(), 0);
+        assert_eq!(manager.pending_count(), 1);
+
+        let shortly = Instant::now() + Duration::from_millis(50);
+        assert_eq!(manager.wait_idle(shortly).await, 1);
+
+        let soon = Instant::now() + Duration::from_secs(5);
+        assert_eq!(manager.wait_idle(soon).await, 0);
+        assert!(returned.load(Ordering::SeqCst));
+        assert_eq!(manager.pending_count(), 0);
+    }
+}
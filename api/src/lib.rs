@@ -4,43 +4,205 @@
 //! - Failover Cluster: nodes, groups, resources, CSV
 //! - Hyper-V: VMs, VHDs, snapshots, switches, GPU (GPU-P and DDA)
 
+pub mod aggregator;
+pub mod audit;
+pub mod auth;
+pub mod backend;
+pub mod batch;
 pub mod config;
+pub mod cors;
 pub mod dto;
+pub mod error;
+pub mod events;
 pub mod handlers;
+pub mod health;
+pub mod idempotency;
+pub mod jobs;
+pub mod listing;
+pub mod locks;
+pub mod metrics;
+pub mod openapi;
+pub mod ratelimit;
+pub mod reload;
 pub mod response;
 pub mod routes;
 pub mod service;
+pub mod shutdown;
+pub mod tls;
+pub mod validation;
+pub mod webhooks;
 
-use std::sync::Arc;
+use std::future::IntoFuture;
+use std::sync::{Arc, OnceLock};
 
-use axum::{routing::get, Json, Router};
-use tower_http::{cors::CorsLayer, trace::TraceLayer};
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+use axum::{middleware, routing::get, Json, Router};
+use tower_http::trace::TraceLayer;
+use tracing_subscriber::{
+    layer::SubscriberExt, reload::Handle, util::SubscriberInitExt, EnvFilter, Registry,
+};
 
-pub use config::Config;
+pub use aggregator::Aggregator;
+pub use audit::AuditLog;
+pub use auth::{Authenticator, Principal, Role};
+pub use backend::{ClusterBackend, HypervBackend};
+pub use config::{CommandLine, Config, ConfigError, ConfigLoader};
+pub use cors::Cors;
 pub use dto::*;
+pub use error::{ApiError, ErrorCode, FieldError, FieldErrorCode, ProblemDetails};
+pub use events::EventBus;
+pub use health::{Health, HealthCheck};
+pub use idempotency::IdempotencyStore;
+pub use jobs::JobManager;
+pub use listing::ListQuery;
+pub use locks::LockManager;
+pub use metrics::Metrics;
+pub use ratelimit::RateLimiter;
+pub use reload::ConfigWatcher;
 pub use response::{ApiResponse, ApiResult};
+pub use shutdown::Shutdown;
+pub use tls::{ClientCertificate, ConnectionInfo};
+pub use webhooks::Webhooks;
 
 // =============================================================================
 // Tracing Initialization
 // =============================================================================
 
+static LOG_FILTER: OnceLock<Handle<EnvFilter, Registry>> = OnceLock::new();
+
 /// Initialize tracing/logging with the given filter level
+///
+/// `RUST_LOG`, when set, takes precedence until [`set_log_level`] is called.
 pub fn init_tracing(filter: &str) {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| filter.into());
+    let (filter, handle) = tracing_subscriber::reload::Layer::new(filter);
     tracing_subscriber::registry()
-        .with(
-            tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| filter.into()),
-        )
+        .with(filter)
         .with(tracing_subscriber::fmt::layer())
         .init();
+    let _ = LOG_FILTER.set(handle);
+}
+
+/// Replace the log filter installed by [`init_tracing`]
+pub fn set_log_level(filter: &str) -> Result<(), String> {
+    let filter = EnvFilter::try_new(filter).map_err(|e| e.to_string())?;
+    match LOG_FILTER.get() {
+        Some(handle) => handle.reload(filter).map_err(|e| e.to_string()),
+        None => Ok(()),
+    }
 }
 
 // =============================================================================
 // Shared State
 // =============================================================================
 
-#[derive(Default)]
-pub struct AppState;
+/// State shared by all handlers
+pub struct AppState {
+    /// Hyper-V backend used by `/api/v1/hyperv`
+    pub hyperv: Arc<dyn HypervBackend>,
+    /// Failover cluster backend used by `/api/v1/cluster`
+    pub cluster: Arc<dyn ClusterBackend>,
+    /// Background jobs for long-running operations
+    pub jobs: Arc<JobManager>,
+    /// Credential verification for `/api/v1`
+    pub auth: Arc<Authenticator>,
+    /// Prometheus metrics served at `/metrics`
+    pub metrics: Arc<Metrics>,
+    /// State change events for `/api/v1/events`
+    pub events: Arc<EventBus>,
+    /// Record of mutating calls for `/api/v1/audit`
+    pub audit: Arc<AuditLog>,
+    /// Serializes mutating operations per VM, switch, VHD and cluster object
+    pub locks: Arc<LockManager>,
+    /// Responses remembered for `Idempotency-Key` replay
+    pub idempotency: Arc<IdempotencyStore>,
+    /// CORS policy, replaced on configuration reload
+    pub cors: Arc<Cors>,
+    /// Per-client request limits, replaced on configuration reload
+    pub rate_limiter: Arc<RateLimiter>,
+    /// Checks run by `/health/live` and `/health/ready`
+    pub health: Arc<Health>,
+    /// Subscriptions and delivery queue for `/api/v1/webhooks`
+    pub webhooks: Arc<Webhooks>,
+    /// Peer node agents answering `/api/v1/hyperv` and `/api/v1/jobs` in
+    /// aggregator mode
+    pub aggregator: Arc<Aggregator>,
+}
+
+impl AppState {
+    pub fn new(hyperv: Arc<dyn HypervBackend>, cluster: Arc<dyn ClusterBackend>) -> Self {
+        Self::with_metrics(hyperv, cluster, Arc::new(Metrics::default()))
+    }
+
+    /// Wrap the backends so every call is recorded in `metrics`
+    pub fn with_metrics(
+        hyperv: Arc<dyn HypervBackend>,
+        cluster: Arc<dyn ClusterBackend>,
+        metrics: Arc<Metrics>,
+    ) -> Self {
+        Self {
+            hyperv: Arc::new(backend::InstrumentedHyperV::new(hyperv, metrics.clone())),
+            cluster: Arc::new(backend::InstrumentedCluster::new(cluster, metrics.clone())),
+            jobs: Arc::new(JobManager::default()),
+            auth: Arc::new(Authenticator::disabled()),
+            metrics,
+            events: Arc::new(EventBus::default()),
+            audit: Arc::new(AuditLog::default()),
+            locks: Arc::new(LockManager::default()),
+            idempotency: Arc::new(IdempotencyStore::default()),
+            cors: Arc::new(Cors::default()),
+            rate_limiter: Arc::new(RateLimiter::default()),
+            health: Arc::new(Health::default()),
+            webhooks: Arc::new(Webhooks::default()),
+            aggregator: Arc::new(Aggregator::default()),
+        }
+    }
+
+    /// Replace the authenticator
+    pub fn with_auth(mut self, auth: Authenticator) -> Self {
+        self.auth = Arc::new(auth);
+        self
+    }
+
+    /// Build state from the `[backend]`, `[jobs]`, `[auth]`, `[metrics]`,
+    /// `[events]`, `[audit]`, `[locks]`, `[idempotency]`, `[cors]`,
+    /// `[rate_limit]`, `[health]`, `[webhooks]` and `[aggregator]` sections
+    pub fn from_config(config: &Config) -> Result<Self, ConfigError> {
+        let (hyperv, cluster) = backend::from_config(&config.backend);
+        let metrics = Arc::new(Metrics::new(&config.metrics)?);
+        let auth = Authenticator::from_config(&config.auth)?;
+        if !auth.is_enabled() {
+            tracing::warn!("Authentication is disabled; all requests are treated as admin");
+        }
+        let audit = AuditLog::new(&config.audit)?;
+        if audit.is_enabled() && !audit.is_durable() {
+            tracing::warn!("Audit records are kept in memory only; set [audit] sink = \"file\"");
+        }
+        let webhooks = Webhooks::new(&config.webhooks)?;
+        if !config.webhooks.subscriptions.is_empty() && !webhooks.is_durable() {
+            tracing::warn!("Webhook retries are kept in memory only; set [webhooks] state_path");
+        }
+        Ok(Self {
+            jobs: Arc::new(JobManager::new(config.jobs.history_limit)),
+            auth: Arc::new(auth),
+            events: Arc::new(EventBus::new(&config.events)),
+            audit: Arc::new(audit),
+            locks: Arc::new(LockManager::new(&config.locks)),
+            idempotency: Arc::new(IdempotencyStore::new(&config.idempotency)),
+            cors: Arc::new(Cors::new(&config.cors)?),
+            rate_limiter: Arc::new(RateLimiter::new(&config.rate_limit)),
+            health: Arc::new(Health::new(&config.health)),
+            webhooks: Arc::new(webhooks),
+            aggregator: Arc::new(Aggregator::new(&config.aggregator)?),
+            ..Self::with_metrics(hyperv, cluster, metrics)
+        })
+    }
+}
+
+impl Default for AppState {
+    fn default() -> Self {
+        Self::from_config(&Config::default()).expect("default configuration is valid")
+    }
+}
 
 pub type SharedState = Arc<AppState>;
 
@@ -49,19 +211,127 @@ pub type SharedState = Arc<AppState>;
 // =============================================================================
 
 pub fn create_router(state: SharedState) -> Router {
-    Router::new()
+    let mut router = Router::new()
         .route("/", get(root))
         .route("/health", get(health))
-        .nest("/api/v1", api_routes())
+        .route("/health/live", get(health::live))
+        .route("/health/ready", get(health::ready));
+    if state.metrics.is_enabled() {
+        router = router.route("/metrics", get(metrics::metrics_handler));
+    }
+    router
+        .route("/api/v1/openapi.json", get(openapi::openapi_json))
+        .route("/api/v1/docs", get(openapi::docs))
+        .nest("/api/v1", routes::api_routes().into_router())
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            aggregator::route,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            idempotency::replay,
+        ))
+        .layer(middleware::from_fn_with_state(state.clone(), audit::record))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            ratelimit::limit,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            auth::authenticate,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            metrics::track,
+        ))
+        .layer(middleware::from_fn(error::negotiate))
         .layer(TraceLayer::new_for_http())
-        .layer(CorsLayer::permissive())
+        .layer(middleware::from_fn_with_state(state.clone(), cors::apply))
         .with_state(state)
 }
 
-fn api_routes() -> Router<SharedState> {
-    Router::new()
-        .nest("/cluster", routes::cluster_routes())
-        .nest("/hyperv", routes::hyperv_routes())
+// =============================================================================
+// Server
+// =============================================================================
+
+/// Serve `state` until `shutdown` is triggered, then drain and flush
+///
+/// Webhook deliveries start with the server. New connections are refused
+/// and event streams end as soon as shutdown starts. In-flight requests,
+/// then background jobs, get until the deadline; that includes the backend
+/// work of cancelled jobs, which cannot be interrupted once started. The
+/// audit log, metrics and logs are flushed either way.
+pub async fn run(
+    config: &Config,
+    state: SharedState,
+    shutdown: Shutdown,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    state.webhooks.start(&state);
+    let events = state.events.clone();
+    let closing = shutdown.clone();
+    tokio::spawn(async move {
+        closing.triggered().await;
+        events.close();
+    });
+
+    let served = serve(config, create_router(state.clone()), shutdown.clone()).await;
+    if let Err(e) = &served {
+        shutdown.trigger(&format!("Server error: {}", e));
+    }
+    shutdown::drain(&state, &shutdown).await;
+    served
+}
+
+/// Serve the router on the configured address, over TLS if `[server.tls]` is set
+///
+/// Handlers can extract `ConnectInfo<ConnectionInfo>` for the peer address
+/// and, with mTLS, the verified client certificate. Returns once `shutdown`
+/// is triggered and open connections have finished, or at its deadline.
+pub async fn serve(
+    config: &Config,
+    app: Router,
+    shutdown: Shutdown,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let addr: std::net::SocketAddr = config.socket_addr().parse()?;
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+
+    match &config.server.tls {
+        Some(tls_config) => {
+            let listener = tls::TlsListener::new(listener, tls_config)?;
+            tracing::info!("API server listening on https://{}", addr);
+            let server = axum::serve(
+                listener,
+                app.into_make_service_with_connect_info::<ConnectionInfo>(),
+            )
+            .with_graceful_shutdown(shutdown.wait());
+            until_deadline(server, &shutdown).await?;
+        }
+        None => {
+            tracing::warn!("TLS is not configured; serving plain HTTP");
+            tracing::info!("API server listening on http://{}", addr);
+            let server = axum::serve(
+                listener,
+                app.into_make_service_with_connect_info::<ConnectionInfo>(),
+            )
+            .with_graceful_shutdown(shutdown.wait());
+            until_deadline(server, &shutdown).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Wait for a gracefully shutting down `server`, giving up at the deadline
+async fn until_deadline<F>(server: F, shutdown: &Shutdown) -> std::io::Result<()>
+where
+    F: IntoFuture<Output = std::io::Result<()>>,
+{
+    tokio::select! {
+        result = server.into_future() => result,
+        _ = shutdown.expired() => {
+            tracing::warn!("Closing connections still open at the shutdown deadline");
+            Ok(())
+        }
+    }
 }
 
 // =============================================================================
@@ -69,7 +339,7 @@ fn api_routes() -> Router<SharedState> {
 // =============================================================================
 
 async fn root() -> &'static str {
-    "Windows Infrastructure Management API - Use /api/v1/cluster or /api/v1/hyperv"
+    "Windows Infrastructure Management API - Use /api/v1/cluster, /api/v1/hyperv, /api/v1/jobs, /api/v1/events or /api/v1/webhooks; docs at /api/v1/docs"
 }
 
 async fn health() -> Json<ApiResponse<&'static str>> {
@@ -91,7 +361,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_root_endpoint() {
-        let state = Arc::new(AppState);
+        let state = Arc::new(AppState::default());
         let app = create_router(state);
 
         let response = app
@@ -104,7 +374,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_health_endpoint() {
-        let state = Arc::new(AppState);
+        let state = Arc::new(AppState::default());
         let app = create_router(state);
 
         let response = app
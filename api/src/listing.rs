@@ -0,0 +1,490 @@
+//! Filtering, sorting, projection and pagination for list endpoints
+//!
+//! Every list route accepts the same query parameters:
+//!
+//! - `field=value` filters on a top-level field of the items. Values are
+//!   compared case-insensitively, may use `*` and `?` globs, and may list
+//!   alternatives separated by commas: `state=Running,Paused&name=web-*`.
+//! - `sort=memory_mb:desc,name` orders by one or more fields; `:asc` is the
+//!   default and missing values sort last.
+//! - `fields=name,state` returns only the listed fields of each item.
+//! - `limit=50` returns at most that many items, and the envelope's
+//!   `next_cursor` is passed back as `cursor` to get the next page.
+//!
+//! Field names are checked against the item's JSON schema so a typo is a 400
+//! rather than an empty list. The envelope's `total` counts the items that
+//! matched the filters, across all pages.
+
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::marker::PhantomData;
+
+use axum::{
+    extract::{FromRequestParts, Query},
+    http::{request::Parts, StatusCode},
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+
+use crate::error::ApiError;
+use crate::response::{api_error, ListResult, Page};
+
+/// Largest page returned; larger `limit`s are clamped
+pub const MAX_PAGE_SIZE: usize = 1000;
+
+/// Query parameters shared by all list endpoints
+///
+/// Any other parameter is a field filter; see the module documentation.
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+pub struct ListQuery {
+    /// Comma-separated sort keys, each `field` or `field:desc`
+    pub sort: Option<String>,
+    /// Comma-separated fields to return for each item
+    pub fields: Option<String>,
+    /// Maximum number of items to return (at most 1000)
+    pub limit: Option<usize>,
+    /// `next_cursor` from the previous page
+    pub cursor: Option<String>,
+    /// Field filters
+    #[serde(skip)]
+    #[schemars(skip)]
+    pub filters: BTreeMap<String, String>,
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for ListQuery {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Query(pairs) = Query::<Vec<(String, String)>>::from_request_parts(parts, state)
+            .await
+            .map_err(|e| api_error(StatusCode::BAD_REQUEST, &e.body_text()))?;
+        let mut query = ListQuery::default();
+        for (key, value) in pairs {
+            match key.as_str() {
+                "sort" => query.sort = Some(value),
+                "fields" => query.fields = Some(value),
+                "cursor" => query.cursor = Some(value),
+                "limit" => {
+                    let limit = value.parse().ok().filter(|&limit| limit > 0);
+                    query.limit = Some(limit.ok_or_else(|| {
+                        api_error(StatusCode::BAD_REQUEST, "limit must be a positive integer")
+                    })?);
+                }
+                _ => {
+                    query.filters.insert(key, value);
+                }
+            }
+        }
+        Ok(query)
+    }
+}
+
+impl ListQuery {
+    /// Drop a parameter that another extractor of the handler consumes,
+    /// e.g. the cluster `name` on `/api/v1/cluster` routes
+    pub fn without(mut self, key: &str) -> Self {
+        self.filters.remove(key);
+        self
+    }
+
+    /// Keep the `items` that match the field filters, in their original order
+    ///
+    /// Sorting, projection and paging parameters are ignored.
+    pub fn matching<T: Serialize + JsonSchema>(&self, items: Vec<T>) -> Result<Vec<T>, ApiError> {
+        let filters = self.compile_filters::<T>()?;
+        Ok(items
+            .into_iter()
+            .filter(|item| {
+                let value = serde_json::to_value(item).expect("DTOs serialize to JSON");
+                matches(&filters, &value)
+            })
+            .collect())
+    }
+
+    /// Filter, sort, paginate and project `items`
+    pub fn page<T: Serialize + JsonSchema>(&self, items: Vec<T>) -> ListResult<T> {
+        let filters = self.compile_filters::<T>()?;
+        let items = items
+            .into_iter()
+            .map(|item| serde_json::to_value(item).expect("DTOs serialize to JSON"))
+            .filter(|item| matches(&filters, item))
+            .collect();
+        self.paginate(items, Some(&known_fields::<T>()))
+    }
+
+    /// Sort, paginate and project items that other node agents already
+    /// filtered, see [`crate::aggregator`]
+    ///
+    /// Field names are checked against the keys of the items themselves, and
+    /// not at all when there are none.
+    pub fn merge(&self, items: Vec<Value>) -> ListResult<Value> {
+        let mut known: Vec<String> = items
+            .iter()
+            .filter_map(Value::as_object)
+            .flat_map(|item| item.keys().cloned())
+            .collect();
+        known.sort();
+        known.dedup();
+        let known = (!items.is_empty()).then_some(known);
+        self.paginate(items, known.as_deref())
+    }
+
+    /// Sort, paginate and project filtered `items`, checking the sort and
+    /// projection fields against `known` when given
+    fn paginate<T>(&self, mut items: Vec<Value>, known: Option<&[String]>) -> ListResult<T> {
+        let bad_request = |message: String| api_error(StatusCode::BAD_REQUEST, &message);
+        let check = |field: &str| match known {
+            Some(known) => check_field(known, field),
+            None => Ok(()),
+        };
+
+        let mut sort = Vec::new();
+        for key in split(self.sort.as_deref()) {
+            let (field, descending) = match key.split_once(':') {
+                Some((field, "asc")) => (field, false),
+                Some((field, "desc")) => (field, true),
+                Some(_) => {
+                    return Err(bad_request(format!(
+                        "Invalid sort key '{}'; use field or field:desc",
+                        key
+                    )))
+                }
+                None => (key, false),
+            };
+            check(field)?;
+            sort.push((field, descending));
+        }
+        let fields = split(self.fields.as_deref());
+        for field in &fields {
+            check(field)?;
+        }
+
+        if !sort.is_empty() {
+            items.sort_by(|a, b| {
+                sort.iter()
+                    .map(|(field, descending)| compare(&a[*field], &b[*field], *descending))
+                    .find(|ordering| ordering.is_ne())
+                    .unwrap_or(Ordering::Equal)
+            });
+        }
+
+        let total = items.len();
+        let fingerprint = self.fingerprint();
+        let offset = match &self.cursor {
+            Some(cursor) => decode_cursor(cursor, &fingerprint).ok_or_else(|| {
+                bad_request("Invalid cursor; it must come from this query's next_cursor".into())
+            })?,
+            None => 0,
+        };
+        let limit = self.limit.unwrap_or(MAX_PAGE_SIZE).min(MAX_PAGE_SIZE);
+        let end = offset.saturating_add(limit).min(total);
+        let next_cursor = (end < total).then(|| encode_cursor(end, &fingerprint));
+
+        let items = items
+            .into_iter()
+            .skip(offset)
+            .take(limit)
+            .map(|item| project(item, &fields))
+            .collect();
+        Ok(Page {
+            items,
+            total,
+            next_cursor,
+            item: PhantomData,
+        })
+    }
+
+    /// Field filters checked against `T`, as lowercased glob alternatives
+    fn compile_filters<T: JsonSchema>(&self) -> Result<Vec<Filter<'_>>, ApiError> {
+        let known = known_fields::<T>();
+        self.filters
+            .iter()
+            .map(|(field, value)| {
+                check_field(&known, field)?;
+                Ok((field.as_str(), parse_patterns(value)))
+            })
+            .collect()
+    }
+
+    /// Digest of the filters and sort a cursor is valid for
+    fn fingerprint(&self) -> String {
+        let mut hasher = Sha256::new();
+        for (field, value) in &self.filters {
+            hasher.update(field);
+            hasher.update([0]);
+            hasher.update(value);
+            hasher.update([0]);
+        }
+        hasher.update(self.sort.as_deref().unwrap_or_default());
+        let digest = hasher.finalize();
+        format!(
+            "{:08x}",
+            u32::from_be_bytes([digest[0], digest[1], digest[2], digest[3]])
+        )
+    }
+}
+
+type Filter<'a> = (&'a str, Vec<Vec<char>>);
+
+fn check_field(known: &[String], field: &str) -> Result<(), ApiError> {
+    if known.iter().any(|k| k == field) {
+        Ok(())
+    } else {
+        Err(api_error(
+            StatusCode::BAD_REQUEST,
+            &format!(
+                "Unknown field '{}'; expected one of: {}",
+                field,
+                known.join(", ")
+            ),
+        ))
+    }
+}
+
+fn matches(filters: &[Filter<'_>], item: &Value) -> bool {
+    filters.iter().all(|(field, patterns)| {
+        let value = text(&item[*field]);
+        patterns.iter().any(|pattern| glob_match(pattern, &value))
+    })
+}
+
+/// Top-level property names of `T`'s JSON schema
+fn known_fields<T: JsonSchema>() -> Vec<String> {
+    let schema = schemars::schema_for!(T);
+    schema
+        .get("properties")
+        .and_then(Value::as_object)
+        .map(|properties| properties.keys().cloned().collect())
+        .unwrap_or_default()
+}
+
+fn split(list: Option<&str>) -> Vec<&str> {
+    list.map(|list| {
+        list.split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .collect()
+    })
+    .unwrap_or_default()
+}
+
+fn parse_patterns(value: &str) -> Vec<Vec<char>> {
+    value
+        .split(',')
+        .map(|pattern| pattern.trim().to_lowercase().chars().collect())
+        .collect()
+}
+
+/// A field value as filters see it; `null` and missing fields are empty
+fn text(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.to_lowercase(),
+        other => other.to_string().to_lowercase(),
+    }
+}
+
+/// Match a lowercased glob where `*` is any run of characters and `?` one character
+fn glob_match(pattern: &[char], value: &str) -> bool {
+    let value: Vec<char> = value.chars().collect();
+    let (mut p, mut v) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    while v < value.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, v));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == value[v] => {
+                p += 1;
+                v += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    v = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Order two field values; missing values sort last in either direction
+fn compare(a: &Value, b: &Value, descending: bool) -> Ordering {
+    let ordering = match (a, b) {
+        (Value::Null, Value::Null) => return Ordering::Equal,
+        (Value::Null, _) => return Ordering::Greater,
+        (_, Value::Null) => return Ordering::Less,
+        (Value::Number(a), Value::Number(b)) => a
+            .as_f64()
+            .partial_cmp(&b.as_f64())
+            .unwrap_or(Ordering::Equal),
+        (Value::String(a), Value::String(b)) => a.to_lowercase().cmp(&b.to_lowercase()),
+        (Value::Bool(a), Value::Bool(b)) => a.cmp(b),
+        (a, b) => a.to_string().cmp(&b.to_string()),
+    };
+    if descending {
+        ordering.reverse()
+    } else {
+        ordering
+    }
+}
+
+fn project(item: Value, fields: &[&str]) -> Value {
+    if fields.is_empty() {
+        return item;
+    }
+    let Value::Object(mut object) = item else {
+        return item;
+    };
+    let projected: Map<String, Value> = fields
+        .iter()
+        .filter_map(|field| object.remove_entry(*field))
+        .collect();
+    Value::Object(projected)
+}
+
+fn encode_cursor(offset: usize, fingerprint: &str) -> String {
+    format!("{:x}.{}", offset, fingerprint)
+}
+
+fn decode_cursor(cursor: &str, fingerprint: &str) -> Option<usize> {
+    let (offset, digest) = cursor.split_once('.')?;
+    if digest != fingerprint {
+        return None;
+    }
+    usize::from_str_radix(offset, 16).ok()
+}
+
+// =============================================================================
+// Tests
+// =============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dto::VmDto;
+
+    fn vm(name: &str, state: &str, memory_mb: Option<u64>) -> VmDto {
+        VmDto {
+            id: name.to_string(),
+            name: name.to_string(),
+            state: state.to_string(),
+            cpu_count: Some(2),
+            memory_mb,
+            ..VmDto::default()
+        }
+    }
+
+    fn vms() -> Vec<VmDto> {
+        vec![
+            vm("web-01", "Running", Some(4096)),
+            vm("web-02", "Off", Some(2048)),
+            vm("sql-01", "Running", Some(16384)),
+            vm("build", "Running", None),
+        ]
+    }
+
+    fn query(filters: &[(&str, &str)]) -> ListQuery {
+        ListQuery {
+            filters: filters
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            ..ListQuery::default()
+        }
+    }
+
+    fn names(page: &Page<VmDto>) -> Vec<&str> {
+        page.items
+            .iter()
+            .map(|item| item["name"].as_str().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_glob_match() {
+        let glob = |p: &str, v: &str| glob_match(&p.chars().collect::<Vec<_>>(), v);
+        assert!(glob("web-*", "web-01"));
+        assert!(glob("*-0?", "sql-01"));
+        assert!(glob("*", ""));
+        assert!(glob("a*b*c", "axxbyyc"));
+        assert!(!glob("web-*", "sql-01"));
+        assert!(!glob("web-0?", "web-010"));
+    }
+
+    #[test]
+    fn test_filters() {
+        let page = query(&[("state", "running"), ("name", "*-01")])
+            .page(vms())
+            .unwrap();
+        assert_eq!(names(&page), ["web-01", "sql-01"]);
+        assert_eq!(page.total, 2);
+
+        let page = query(&[("state", "Off,Paused")]).page(vms()).unwrap();
+        assert_eq!(names(&page), ["web-02"]);
+
+        let page = query(&[("memory_mb", "4096")]).page(vms()).unwrap();
+        assert_eq!(names(&page), ["web-01"]);
+
+        assert!(query(&[("colour", "red")]).page(vms()).is_err());
+    }
+
+    #[test]
+    fn test_sort_and_projection() {
+        let list = ListQuery {
+            sort: Some("memory_mb:desc".to_string()),
+            fields: Some("name,memory_mb".to_string()),
+            ..ListQuery::default()
+        };
+        let page = list.page(vms()).unwrap();
+        assert_eq!(names(&page), ["sql-01", "web-01", "web-02", "build"]);
+        assert_eq!(
+            page.items[0],
+            serde_json::json!({ "name": "sql-01", "memory_mb": 16384 })
+        );
+
+        let list = ListQuery {
+            sort: Some("state,name:desc".to_string()),
+            ..ListQuery::default()
+        };
+        let page = list.page(vms()).unwrap();
+        assert_eq!(names(&page), ["web-02", "web-01", "sql-01", "build"]);
+
+        for sort in ["memory_mb:down", "size"] {
+            let list = ListQuery {
+                sort: Some(sort.to_string()),
+                ..ListQuery::default()
+            };
+            assert!(list.page(vms()).is_err());
+        }
+    }
+
+    #[test]
+    fn test_cursor_pagination() {
+        let mut list = ListQuery {
+            sort: Some("name".to_string()),
+            limit: Some(3),
+            ..ListQuery::default()
+        };
+        let first = list.page(vms()).unwrap();
+        assert_eq!(names(&first), ["build", "sql-01", "web-01"]);
+        assert_eq!(first.total, 4);
+
+        list.cursor = first.next_cursor.clone();
+        let second = list.page(vms()).unwrap();
+        assert_eq!(names(&second), ["web-02"]);
+        assert!(second.next_cursor.is_none());
+
+        // A cursor is only valid for the query that produced it
+        list.sort = Some("name:desc".to_string());
+        assert!(list.page(vms()).is_err());
+        list.cursor = Some("garbage".to_string());
+        assert!(list.page(vms()).is_err());
+    }
+}
@@ -0,0 +1,293 @@
+//! Per-resource locking of mutating operations
+//!
+//! Hyper-V and the cluster service do not serialize concurrent changes to the
+//! same object: a snapshot apply racing a VM delete interleaves WMI and
+//! PowerShell calls unpredictably. Mutating handlers therefore take an
+//! [`OperationGuard`] from the [`LockManager`] for every object they touch
+//! before calling the backend; reads never lock.
+//!
+//! A request that finds an object busy waits up to `[locks] wait_timeout_ms`
+//! and is then refused with 409 Conflict. Independently of the per-object
+//! locks, a global semaphore caps how many mutating backend operations run at
+//! once; a request that cannot get a slot within the same wait gets 503.
+//! Handlers that start a job move the guard into the job, so the object stays
+//! locked until the job finishes.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use thiserror::Error;
+use tokio::sync::{Mutex as AsyncMutex, OwnedMutexGuard, OwnedSemaphorePermit, Semaphore};
+
+use crate::config::LocksConfig;
+
+/// An object that mutating operations are serialized on
+///
+/// Names are compared case-insensitively, as Hyper-V and the cluster service
+/// do; VHD paths additionally treat `/` and `\` alike.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LockKey {
+    Vm(String),
+    Switch(String),
+    Vhd(String),
+    Device(String),
+    Node(String),
+    Group(String),
+    Resource(String),
+    Csv(String),
+}
+
+impl LockKey {
+    pub fn vm(name: &str) -> Self {
+        LockKey::Vm(name.to_lowercase())
+    }
+
+    pub fn switch(name: &str) -> Self {
+        LockKey::Switch(name.to_lowercase())
+    }
+
+    pub fn vhd(path: &str) -> Self {
+        LockKey::Vhd(path.replace('/', "\\").to_lowercase())
+    }
+
+    pub fn device(location_path: &str) -> Self {
+        LockKey::Device(location_path.to_lowercase())
+    }
+
+    pub fn node(name: &str) -> Self {
+        LockKey::Node(name.to_lowercase())
+    }
+
+    pub fn group(name: &str) -> Self {
+        LockKey::Group(name.to_lowercase())
+    }
+
+    pub fn resource(name: &str) -> Self {
+        LockKey::Resource(name.to_lowercase())
+    }
+
+    pub fn csv(name: &str) -> Self {
+        LockKey::Csv(name.to_lowercase())
+    }
+}
+
+impl fmt::Display for LockKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (kind, name) = match self {
+            LockKey::Vm(name) => ("VM", name),
+            LockKey::Switch(name) => ("switch", name),
+            LockKey::Vhd(path) => ("VHD", path),
+            LockKey::Device(path) => ("device", path),
+            LockKey::Node(name) => ("node", name),
+            LockKey::Group(name) => ("group", name),
+            LockKey::Resource(name) => ("resource", name),
+            LockKey::Csv(name) => ("CSV", name),
+        };
+        write!(f, "{} '{}'", kind, name)
+    }
+}
+
+/// Reasons an operation could not start
+#[derive(Error, Debug)]
+pub enum LockError {
+    #[error("Another operation on {0} is in progress")]
+    Conflict(LockKey),
+
+    #[error("Too many operations in progress; retry later")]
+    Busy,
+}
+
+/// Locks held by one mutating operation; released on drop
+pub struct OperationGuard {
+    manager: Arc<LockManager>,
+    held: Vec<(LockKey, OwnedMutexGuard<()>)>,
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
+impl Drop for OperationGuard {
+    fn drop(&mut self) {
+        let keys: Vec<LockKey> = self.held.drain(..).map(|(key, _)| key).collect();
+        self.manager.release(&keys);
+    }
+}
+
+/// Keyed locks plus a global cap on concurrent operations
+pub struct LockManager {
+    locks: Mutex<HashMap<LockKey, Arc<AsyncMutex<()>>>>,
+    wait: Duration,
+    operations: Option<Arc<Semaphore>>,
+    capacity: usize,
+}
+
+impl LockManager {
+    pub fn new(config: &LocksConfig) -> Self {
+        Self {
+            locks: Mutex::new(HashMap::new()),
+            wait: Duration::from_millis(config.wait_timeout_ms),
+            operations: (config.max_concurrent_operations > 0)
+                .then(|| Arc::new(Semaphore::new(config.max_concurrent_operations))),
+            capacity: config.max_concurrent_operations,
+        }
+    }
+
+    /// Lock every key and take an operation slot
+    ///
+    /// Keys are locked in sorted order so operations touching several objects
+    /// (a VM and its VHD, say) cannot deadlock each other.
+    pub async fn acquire(
+        self: &Arc<Self>,
+        keys: impl IntoIterator<Item = LockKey>,
+    ) -> Result<OperationGuard, LockError> {
+        let mut keys: Vec<LockKey> = keys.into_iter().collect();
+        keys.sort();
+        keys.dedup();
+
+        let mut guard = OperationGuard {
+            manager: Arc::clone(self),
+            held: Vec::with_capacity(keys.len()),
+            _permit: None,
+        };
+        for key in keys {
+            let lock = self.entry(&key);
+            let locked = match lock.clone().try_lock_owned() {
+                Ok(locked) => Some(locked),
+                Err(_) if self.wait.is_zero() => None,
+                Err(_) => tokio::time::timeout(self.wait, lock.clone().lock_owned())
+                    .await
+                    .ok(),
+            };
+            match locked {
+                Some(locked) => guard.held.push((key, locked)),
+                None => {
+                    // Forget the entry if this attempt created it; dropping
+                    // `guard` releases the keys locked so far
+                    drop(lock);
+                    self.release(std::slice::from_ref(&key));
+                    return Err(LockError::Conflict(key));
+                }
+            }
+        }
+
+        if let Some(operations) = &self.operations {
+            let permit = match operations.clone().try_acquire_owned() {
+                Ok(permit) => Some(permit),
+                Err(_) if self.wait.is_zero() => None,
+                Err(_) => tokio::time::timeout(self.wait, operations.clone().acquire_owned())
+                    .await
+                    .ok()
+                    .and_then(Result::ok),
+            };
+            guard._permit = Some(permit.ok_or(LockError::Busy)?);
+        }
+        Ok(guard)
+    }
+
+    /// Number of objects currently locked or waited on
+    pub fn held(&self) -> usize {
+        self.table().len()
+    }
+
+    /// Operation slots in use and the cap, when `max_concurrent_operations`
+    /// is set
+    pub fn operations(&self) -> Option<(usize, usize)> {
+        self.operations.as_ref().map(|operations| {
+            let capacity = self.capacity;
+            (capacity - operations.available_permits(), capacity)
+        })
+    }
+
+    fn entry(&self, key: &LockKey) -> Arc<AsyncMutex<()>> {
+        self.table().entry(key.clone()).or_default().clone()
+    }
+
+    /// Forget locks nobody holds or waits on, keeping the table small
+    fn release(&self, keys: &[LockKey]) {
+        let mut table = self.table();
+        for key in keys {
+            if table
+                .get(key)
+                .is_some_and(|lock| Arc::strong_count(lock) == 1)
+            {
+                table.remove(key);
+            }
+        }
+    }
+
+    fn table(&self) -> std::sync::MutexGuard<'_, HashMap<LockKey, Arc<AsyncMutex<()>>>> {
+        self.locks.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Default for LockManager {
+    fn default() -> Self {
+        Self::new(&LocksConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manager(wait_timeout_ms: u64, max_concurrent_operations: usize) -> Arc<LockManager> {
+        Arc::new(LockManager::new(&LocksConfig {
+            wait_timeout_ms,
+            max_concurrent_operations,
+        }))
+    }
+
+    #[tokio::test]
+    async fn test_same_key_conflicts() {
+        let locks = manager(0, 0);
+        let guard = locks.acquire([LockKey::vm("web01")]).await.unwrap();
+
+        let err = locks.acquire([LockKey::vm("WEB01")]).await.err().unwrap();
+        assert!(matches!(err, LockError::Conflict(LockKey::Vm(_))));
+        assert!(locks.acquire([LockKey::vm("web02")]).await.is_ok());
+
+        drop(guard);
+        assert_eq!(locks.held(), 0);
+        assert!(locks.acquire([LockKey::vm("web01")]).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_failed_acquire_releases_earlier_keys() {
+        let locks = manager(0, 0);
+        let _disk = locks
+            .acquire([LockKey::vhd("C:/VMs/a.vhdx")])
+            .await
+            .unwrap();
+
+        let err = locks
+            .acquire([LockKey::vm("web01"), LockKey::vhd(r"c:\vms\A.vhdx")])
+            .await
+            .err()
+            .unwrap();
+        assert!(matches!(err, LockError::Conflict(LockKey::Vhd(_))));
+        assert!(locks.acquire([LockKey::vm("web01")]).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_bounded_wait() {
+        let locks = manager(500, 0);
+        let guard = locks.acquire([LockKey::group("sql")]).await.unwrap();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            drop(guard);
+        });
+        assert!(locks.acquire([LockKey::group("sql")]).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_operation_cap() {
+        let locks = manager(0, 1);
+        let first = locks.acquire([LockKey::vm("web01")]).await.unwrap();
+        let err = locks.acquire([LockKey::vm("web02")]).await.err().unwrap();
+        assert!(matches!(err, LockError::Busy));
+        assert_eq!(locks.held(), 1, "refused operations hold no locks");
+
+        drop(first);
+        assert!(locks.acquire([LockKey::vm("web02")]).await.is_ok());
+    }
+}
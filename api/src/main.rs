@@ -4,48 +4,62 @@
 //! - Console application (default)
 //! - Windows Service (with --service flag)
 //!
-//! Configuration is loaded from config.toml in the executable directory.
+//! Configuration is layered: defaults, then config.toml (or `--config`), then
+//! `NODEAGENT_*` environment variables, then `--port` and `--log-level`.
+//!
+//! Ctrl-C, SIGTERM and a service Stop shut the server down gracefully.
 
 use std::sync::Arc;
+use std::time::Duration;
 
-use api::{create_router, init_tracing, service::windows_service, AppState, Config};
+use api::{
+    init_tracing, run, service::windows_service, AppState, CommandLine, ConfigLoader,
+    ConfigWatcher, Shutdown,
+};
 
 fn main() {
-    let args: Vec<String> = std::env::args().collect();
+    let cli = CommandLine::parse(std::env::args().skip(1)).unwrap_or_else(|e| {
+        eprintln!("{}\n{}", e, CommandLine::USAGE);
+        std::process::exit(2);
+    });
 
     // Check if running as Windows service
-    if args.iter().any(|arg| arg == "--service") {
-        run_as_service();
+    if cli.service {
+        run_as_service(cli);
     } else {
-        run_console();
+        run_console(cli);
     }
 }
 
-fn run_as_service() {
+fn run_as_service(cli: CommandLine) {
     // Load config to get the service name
-    let config_path = windows_service::get_config_path();
-    let config = Config::load(&config_path).unwrap_or_default();
+    let loader = ConfigLoader::new(windows_service::get_config_path(), cli);
+    let config = loader.load().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
 
     #[cfg(windows)]
     {
-        if let Err(e) = windows_service::run_as_service(&config.service.name) {
+        if let Err(e) = windows_service::run_as_service(&config.service.name, loader) {
             eprintln!("Service error: {}", e);
             std::process::exit(1);
         }
     }
     #[cfg(not(windows))]
     {
-        let _ = config; // silence unused warning
+        let _ = (config, loader); // silence unused warning
         eprintln!("Windows service mode is only available on Windows");
         std::process::exit(1);
     }
 }
 
-fn run_console() {
+fn run_console(cli: CommandLine) {
     // Load configuration
-    let config = Config::load("config.toml").unwrap_or_else(|e| {
-        eprintln!("Warning: {}", e);
-        Config::default()
+    let loader = ConfigLoader::new("config.toml", cli);
+    let config = loader.load().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
     });
 
     // Initialize tracing
@@ -60,21 +74,31 @@ fn run_console() {
     // Create tokio runtime and run
     let runtime = tokio::runtime::Runtime::new().expect("Failed to create Tokio runtime");
 
-    runtime.block_on(async {
-        let state = Arc::new(AppState);
-        let app = create_router(state);
-
-        let addr: std::net::SocketAddr = config
-            .socket_addr()
-            .parse()
-            .expect("Invalid socket address");
-
-        tracing::info!("API server listening on {}", addr);
+    let state = AppState::from_config(&config).unwrap_or_else(|e| {
+        tracing::error!("{}", e);
+        std::process::exit(1);
+    });
 
-        let listener = tokio::net::TcpListener::bind(addr)
-            .await
-            .expect("Failed to bind to address");
+    runtime.block_on(async {
+        let state = Arc::new(state);
+        if config.server.config_reload_interval_secs > 0 {
+            match ConfigWatcher::new(loader, state.clone()) {
+                Ok(watcher) => {
+                    watcher.spawn(Duration::from_secs(
+                        config.server.config_reload_interval_secs,
+                    ));
+                }
+                Err(e) => tracing::warn!("Configuration hot reload is disabled: {}", e),
+            }
+        }
 
-        axum::serve(listener, app).await.expect("Server error");
+        let shutdown = Shutdown::new(Duration::from_secs(config.server.shutdown_timeout_secs));
+        shutdown.listen_for_signals();
+        if let Err(e) = run(&config, state, shutdown).await {
+            tracing::error!("Server error: {}", e);
+            std::process::exit(1);
+        }
     });
+    // Jobs abandoned at the deadline must not hold up the exit
+    runtime.shutdown_background();
 }
@@ -0,0 +1,298 @@
+//! Prometheus metrics
+//!
+//! HTTP metrics are recorded by the [`track`] middleware and labelled with
+//! the matched route template (`/api/v1/hyperv/vms/{name}`), not the raw
+//! path, to keep cardinality bounded. Backend metrics are recorded by
+//! [`InstrumentedHyperV`](crate::backend::InstrumentedHyperV) and
+//! [`InstrumentedCluster`](crate::backend::InstrumentedCluster) for every
+//! `hv`/`clus` operation.
+
+use std::time::{Duration, Instant};
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::header,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use prometheus_client::{
+    encoding::{text::encode, EncodeLabelSet},
+    metrics::{
+        counter::Counter,
+        family::{Family, MetricConstructor},
+        gauge::Gauge,
+        histogram::Histogram,
+    },
+    registry::Registry,
+};
+
+use crate::config::{ConfigError, MetricsConfig};
+use crate::SharedState;
+
+/// Route label for requests that matched no route
+const UNMATCHED_ROUTE: &str = "unmatched";
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct RequestLabels {
+    method: String,
+    route: String,
+    status_class: String,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct RouteLabels {
+    method: String,
+    route: String,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct BackendLabels {
+    /// `hyperv` or `cluster`
+    backend: String,
+    operation: String,
+}
+
+#[derive(Clone)]
+struct Buckets(Vec<f64>);
+
+impl MetricConstructor<Histogram> for Buckets {
+    fn new_metric(&self) -> Histogram {
+        Histogram::new(self.0.iter().copied())
+    }
+}
+
+/// Metric registry and families for the API
+pub struct Metrics {
+    enabled: bool,
+    registry: Registry,
+    requests: Family<RequestLabels, Counter>,
+    request_duration: Family<RouteLabels, Histogram, Buckets>,
+    in_flight: Family<RouteLabels, Gauge>,
+    backend_duration: Family<BackendLabels, Histogram, Buckets>,
+    backend_errors: Family<BackendLabels, Counter>,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new(&MetricsConfig::default()).expect("default metrics configuration is valid")
+    }
+}
+
+impl Metrics {
+    /// Register metrics using the names and buckets from `[metrics]`
+    pub fn new(config: &MetricsConfig) -> Result<Self, ConfigError> {
+        let request_buckets = validate_buckets(
+            "metrics.request_duration_buckets",
+            &config.request_duration_buckets,
+        )?;
+        let backend_buckets = validate_buckets(
+            "metrics.backend_duration_buckets",
+            &config.backend_duration_buckets,
+        )?;
+
+        let mut registry = if config.prefix.is_empty() {
+            Registry::default()
+        } else {
+            Registry::with_prefix(config.prefix.clone())
+        };
+
+        let requests = Family::<RequestLabels, Counter>::default();
+        let request_duration = Family::new_with_constructor(request_buckets);
+        let in_flight = Family::<RouteLabels, Gauge>::default();
+        let backend_duration = Family::new_with_constructor(backend_buckets);
+        let backend_errors = Family::<BackendLabels, Counter>::default();
+
+        let names = &config.names;
+        registry.register(
+            names.http_requests.clone(),
+            "HTTP requests by method, route template and status class",
+            requests.clone(),
+        );
+        registry.register(
+            names.http_request_duration.clone(),
+            "HTTP request latency in seconds by method and route template",
+            request_duration.clone(),
+        );
+        registry.register(
+            names.http_requests_in_flight.clone(),
+            "HTTP requests currently being served by method and route template",
+            in_flight.clone(),
+        );
+        registry.register(
+            names.backend_call_duration.clone(),
+            "Hyper-V and cluster backend call latency in seconds by operation",
+            backend_duration.clone(),
+        );
+        registry.register(
+            names.backend_call_errors.clone(),
+            "Failed Hyper-V and cluster backend calls by operation",
+            backend_errors.clone(),
+        );
+
+        Ok(Self {
+            enabled: config.enabled,
+            registry,
+            requests,
+            request_duration,
+            in_flight,
+            backend_duration,
+            backend_errors,
+        })
+    }
+
+    /// Whether `GET /metrics` is served
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Record one backend call
+    pub fn observe_backend(
+        &self,
+        backend: &str,
+        operation: &str,
+        elapsed: Duration,
+        succeeded: bool,
+    ) {
+        let labels = BackendLabels {
+            backend: backend.to_string(),
+            operation: operation.to_string(),
+        };
+        self.backend_duration
+            .get_or_create(&labels)
+            .observe(elapsed.as_secs_f64());
+        if !succeeded {
+            self.backend_errors.get_or_create(&labels).inc();
+        }
+    }
+
+    /// Render all metrics in the OpenMetrics text format
+    pub fn encode(&self) -> String {
+        let mut buffer = String::new();
+        encode(&mut buffer, &self.registry).expect("writing to a String cannot fail");
+        buffer
+    }
+}
+
+fn validate_buckets(name: &str, buckets: &[f64]) -> Result<Buckets, ConfigError> {
+    if buckets.is_empty() {
+        return Err(ConfigError::Invalid(format!("{} must not be empty", name)));
+    }
+    if buckets.windows(2).any(|w| w[0] >= w[1]) {
+        return Err(ConfigError::Invalid(format!(
+            "{} must be strictly increasing",
+            name
+        )));
+    }
+    Ok(Buckets(buckets.to_vec()))
+}
+
+/// Decrements the in-flight gauge even if the request future is dropped
+struct InFlight(Gauge);
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.0.dec();
+    }
+}
+
+// =============================================================================
+// Middleware and Handler
+// =============================================================================
+
+/// Record request count, latency and in-flight requests
+pub async fn track(State(state): State<SharedState>, request: Request, next: Next) -> Response {
+    let metrics = &state.metrics;
+    let labels = RouteLabels {
+        method: request.method().to_string(),
+        route: request
+            .extensions()
+            .get::<MatchedPath>()
+            .map(|path| path.as_str().to_string())
+            .unwrap_or_else(|| UNMATCHED_ROUTE.to_string()),
+    };
+
+    let gauge = metrics.in_flight.get_or_create(&labels).clone();
+    gauge.inc();
+    let _in_flight = InFlight(gauge);
+    let start = Instant::now();
+
+    let response = next.run(request).await;
+
+    metrics
+        .request_duration
+        .get_or_create(&labels)
+        .observe(start.elapsed().as_secs_f64());
+    metrics
+        .requests
+        .get_or_create(&RequestLabels {
+            method: labels.method,
+            route: labels.route,
+            status_class: format!("{}xx", response.status().as_u16() / 100),
+        })
+        .inc();
+    response
+}
+
+/// `GET /metrics`
+pub async fn metrics_handler(State(state): State<SharedState>) -> impl IntoResponse {
+    (
+        [(
+            header::CONTENT_TYPE,
+            "application/openmetrics-text; version=1.0.0; charset=utf-8",
+        )],
+        state.metrics.encode(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backend_metrics() {
+        let metrics = Metrics::default();
+        metrics.observe_backend("hyperv", "start_vm", Duration::from_millis(20), true);
+        metrics.observe_backend("hyperv", "start_vm", Duration::from_millis(30), false);
+
+        let text = metrics.encode();
+        assert!(text.contains(
+            r#"nodeagent_backend_call_duration_seconds_count{backend="hyperv",operation="start_vm"} 2"#
+        ));
+        assert!(text.contains(
+            r#"nodeagent_backend_call_errors_total{backend="hyperv",operation="start_vm"} 1"#
+        ));
+    }
+
+    #[test]
+    fn test_custom_names_and_buckets() {
+        let mut config = MetricsConfig {
+            prefix: "agent".to_string(),
+            backend_duration_buckets: vec![0.5, 1.0],
+            ..MetricsConfig::default()
+        };
+        config.names.backend_call_duration = "hv_latency_seconds".to_string();
+        let metrics = Metrics::new(&config).unwrap();
+        metrics.observe_backend("cluster", "move_group", Duration::from_millis(700), true);
+
+        let text = metrics.encode();
+        assert!(text.contains(
+            r#"agent_hv_latency_seconds_bucket{le="1.0",backend="cluster",operation="move_group"} 1"#
+        ));
+        assert!(!text.contains("le=\"0.005\""));
+    }
+
+    #[test]
+    fn test_invalid_buckets() {
+        let config = MetricsConfig {
+            request_duration_buckets: vec![1.0, 0.5],
+            ..MetricsConfig::default()
+        };
+        assert!(Metrics::new(&config).is_err());
+
+        let config = MetricsConfig {
+            backend_duration_buckets: Vec::new(),
+            ..MetricsConfig::default()
+        };
+        assert!(Metrics::new(&config).is_err());
+    }
+}
@@ -0,0 +1,419 @@
+//! OpenAPI 3.1 document and docs UI
+//!
+//! The document is generated from the route table rather than maintained by
+//! hand: paths, methods and required roles come from route registration,
+//! parameters, request bodies and responses from each handler's extractor
+//! and return types, and schemas from the DTOs' `JsonSchema` derives.
+
+use std::future::Future;
+use std::sync::OnceLock;
+
+use axum::{
+    extract::{ws::WebSocketUpgrade, Path, Query, State},
+    http::{header, HeaderMap, Method, StatusCode},
+    response::{sse::Sse, Html, IntoResponse, Response},
+    Json,
+};
+use schemars::{generate::SchemaSettings, JsonSchema, SchemaGenerator};
+use serde_json::{json, Map, Value};
+
+use crate::dto::EventDto;
+use crate::error::{ErrorCode, FieldError, ProblemDetails, PROBLEM_JSON};
+use crate::listing::ListQuery;
+use crate::response::{ApiResponse, Page};
+use crate::routes::{self, RoutePolicy, RouteTable};
+use crate::validation::Valid;
+
+/// Prefix under which the route table is mounted
+pub const API_PREFIX: &str = "/api/v1";
+
+const DOCS_HTML: &str = include_str!("docs.html");
+
+/// Operation details gathered from a handler's signature
+#[derive(Debug, Default, Clone)]
+pub struct Operation {
+    pub operation_id: String,
+    path: Option<Value>,
+    query: Vec<Value>,
+    request_body: Option<Value>,
+    response: Option<(StatusCode, Value)>,
+    /// Media type of a success response sent without the JSON envelope
+    media_type: Option<&'static str>,
+    /// List response with `total` and `next_cursor` in the envelope
+    paginated: bool,
+    /// Body checked by [`Valid`], which may answer `422`
+    validated: bool,
+}
+
+/// Builds the [`Operation`] for one registered handler
+pub type DescribeFn = fn(&mut SchemaGenerator) -> Operation;
+
+/// Extractors that contribute parameters or a request body
+pub trait OperationInput {
+    fn describe(_gen: &mut SchemaGenerator, _op: &mut Operation) {}
+}
+
+/// Handler return types that describe the success response
+pub trait OperationOutput {
+    fn describe(gen: &mut SchemaGenerator, op: &mut Operation);
+}
+
+/// Implemented for every handler function whose arguments are
+/// [`OperationInput`]s and whose return type is an [`OperationOutput`]
+pub trait HandlerDoc<T> {
+    fn describe(gen: &mut SchemaGenerator, op: &mut Operation);
+}
+
+impl<S> OperationInput for State<S> {}
+
+impl OperationInput for HeaderMap {}
+
+/// WebSocket routes answer `101` and then send [`EventDto`] messages
+impl OperationInput for WebSocketUpgrade {
+    fn describe(gen: &mut SchemaGenerator, op: &mut Operation) {
+        op.response = Some((
+            StatusCode::SWITCHING_PROTOCOLS,
+            gen.subschema_for::<EventDto>().to_value(),
+        ));
+        op.media_type = Some("application/json");
+    }
+}
+
+impl<T: JsonSchema> OperationInput for Path<T> {
+    fn describe(gen: &mut SchemaGenerator, op: &mut Operation) {
+        op.path = Some(gen.subschema_for::<T>().to_value());
+    }
+}
+
+impl<T: JsonSchema> OperationInput for Query<T> {
+    fn describe(gen: &mut SchemaGenerator, op: &mut Operation) {
+        op.query.push(gen.subschema_for::<T>().to_value());
+    }
+}
+
+impl<T: JsonSchema> OperationInput for Json<T> {
+    fn describe(gen: &mut SchemaGenerator, op: &mut Operation) {
+        op.request_body = Some(gen.subschema_for::<T>().to_value());
+    }
+}
+
+impl<T: JsonSchema> OperationInput for Valid<T> {
+    fn describe(gen: &mut SchemaGenerator, op: &mut Operation) {
+        op.request_body = Some(gen.subschema_for::<T>().to_value());
+        op.validated = true;
+    }
+}
+
+impl<T: JsonSchema, E> OperationOutput for Result<Json<ApiResponse<T>>, E> {
+    fn describe(gen: &mut SchemaGenerator, op: &mut Operation) {
+        op.response = Some((StatusCode::OK, gen.subschema_for::<T>().to_value()));
+    }
+}
+
+/// Job-starting handlers answer `202 Accepted`
+impl<T: JsonSchema, E> OperationOutput for Result<(StatusCode, Json<ApiResponse<T>>), E> {
+    fn describe(gen: &mut SchemaGenerator, op: &mut Operation) {
+        op.response = Some((StatusCode::ACCEPTED, gen.subschema_for::<T>().to_value()));
+    }
+}
+
+/// List routes take the shared [`ListQuery`] parameters
+impl OperationInput for ListQuery {
+    fn describe(gen: &mut SchemaGenerator, op: &mut Operation) {
+        op.query.push(gen.subschema_for::<ListQuery>().to_value());
+    }
+}
+
+/// List routes answer an array of `T` with pagination metadata
+impl<T: JsonSchema, E> OperationOutput for Result<Page<T>, E> {
+    fn describe(gen: &mut SchemaGenerator, op: &mut Operation) {
+        op.response = Some((StatusCode::OK, gen.subschema_for::<Vec<T>>().to_value()));
+        op.paginated = true;
+    }
+}
+
+/// Event streams carry one [`EventDto`] per SSE `data:` field
+impl<S, E> OperationOutput for Result<Sse<S>, E> {
+    fn describe(gen: &mut SchemaGenerator, op: &mut Operation) {
+        op.response = Some((StatusCode::OK, gen.subschema_for::<EventDto>().to_value()));
+        op.media_type = Some("text/event-stream");
+    }
+}
+
+/// Untyped responses are described by their extractors, e.g. [`WebSocketUpgrade`]
+impl<E> OperationOutput for Result<Response, E> {
+    fn describe(_gen: &mut SchemaGenerator, _op: &mut Operation) {}
+}
+
+macro_rules! impl_handler_doc {
+    ($($ty:ident),*) => {
+        impl<F, Fut, R, $($ty,)*> HandlerDoc<($($ty,)*)> for F
+        where
+            F: FnOnce($($ty,)*) -> Fut,
+            Fut: Future<Output = R>,
+            R: OperationOutput,
+            $($ty: OperationInput,)*
+        {
+            fn describe(gen: &mut SchemaGenerator, op: &mut Operation) {
+                $($ty::describe(gen, op);)*
+                R::describe(gen, op);
+            }
+        }
+    };
+}
+
+impl_handler_doc!();
+impl_handler_doc!(T1);
+impl_handler_doc!(T1, T2);
+impl_handler_doc!(T1, T2, T3);
+impl_handler_doc!(T1, T2, T3, T4);
+impl_handler_doc!(T1, T2, T3, T4, T5);
+impl_handler_doc!(T1, T2, T3, T4, T5, T6);
+
+/// [`DescribeFn`] for handler `H`, named after the handler function
+pub fn describe<H: HandlerDoc<T>, T>(gen: &mut SchemaGenerator) -> Operation {
+    let name = std::any::type_name::<H>();
+    let mut op = Operation {
+        operation_id: name.rsplit("::").next().unwrap_or(name).to_string(),
+        ..Operation::default()
+    };
+    H::describe(gen, &mut op);
+    op
+}
+
+// =============================================================================
+// Document
+// =============================================================================
+
+/// The OpenAPI document for all `/api/v1` routes
+pub fn spec() -> &'static Value {
+    static SPEC: OnceLock<Value> = OnceLock::new();
+    SPEC.get_or_init(|| build(&routes::api_routes()))
+}
+
+/// Build the OpenAPI document for a route table mounted at [`API_PREFIX`]
+pub fn build(table: &RouteTable) -> Value {
+    let mut settings = SchemaSettings::draft2020_12();
+    settings.definitions_path = "/components/schemas".into();
+    settings.meta_schema = None;
+    let mut gen = settings.into_generator();
+
+    let mut paths = Map::new();
+    for policy in table.policies() {
+        let op = (policy.describe)(&mut gen);
+        let operation = operation(policy, &op, &gen);
+        let path = format!("{}{}", API_PREFIX, policy.path);
+        paths
+            .entry(path)
+            .or_insert_with(|| json!({}))
+            .as_object_mut()
+            .unwrap()
+            .insert(policy.method.as_str().to_ascii_lowercase(), operation);
+    }
+
+    let code = gen.subschema_for::<ErrorCode>().to_value();
+    let problem = gen.subschema_for::<ProblemDetails>().to_value();
+    let errors = gen.subschema_for::<Vec<FieldError>>().to_value();
+    let mut schemas = gen.take_definitions(true);
+    schemas.insert(
+        "ErrorResponse".to_string(),
+        json!({
+            "type": "object",
+            "required": ["success", "error", "code"],
+            "properties": {
+                "success": { "const": false },
+                "data": { "type": "null" },
+                "error": { "type": "string" },
+                "code": code,
+                "errors": errors
+            }
+        }),
+    );
+
+    json!({
+        "openapi": "3.1.0",
+        "info": {
+            "title": "Node Agent API",
+            "description": "REST API for Windows Failover Cluster and Hyper-V management",
+            "version": env!("CARGO_PKG_VERSION")
+        },
+        "servers": [{ "url": "/" }],
+        "paths": paths,
+        "components": {
+            "schemas": schemas,
+            "responses": {
+                "Error": {
+                    "description": "Error envelope, or RFC 7807 problem details when requested with `Accept: application/problem+json`",
+                    "content": {
+                        "application/json": {
+                            "schema": { "$ref": "#/components/schemas/ErrorResponse" }
+                        },
+                        PROBLEM_JSON: { "schema": problem }
+                    }
+                }
+            },
+            "securitySchemes": {
+                "apiKey": { "type": "apiKey", "in": "header", "name": "X-API-Key" },
+                "bearer": { "type": "http", "scheme": "bearer", "bearerFormat": "JWT" },
+                "mutualTLS": { "type": "mutualTLS" }
+            }
+        },
+        "security": [{ "apiKey": [] }, { "bearer": [] }, { "mutualTLS": [] }]
+    })
+}
+
+fn operation(policy: &RoutePolicy, op: &Operation, gen: &SchemaGenerator) -> Value {
+    let mut parameters = path_parameters(&policy.path, op.path.as_ref());
+    for query in &op.query {
+        parameters.extend(query_parameters(resolve(query, gen)));
+    }
+
+    if policy.method == Method::POST {
+        parameters.push(json!({
+            "name": "Idempotency-Key",
+            "in": "header",
+            "required": false,
+            "description": "Replay the stored response when this POST is retried",
+            "schema": { "type": "string", "maxLength": 255 }
+        }));
+    }
+
+    let (status, data) = op.response.clone().unwrap_or((StatusCode::OK, json!({})));
+    let content = match op.media_type {
+        Some(media_type) => json!({ media_type: { "schema": data } }),
+        None => {
+            let mut envelope = json!({
+                "type": "object",
+                "required": ["success", "data"],
+                "properties": {
+                    "success": { "const": true },
+                    "data": data,
+                    "error": { "type": "null" }
+                }
+            });
+            if op.paginated {
+                envelope["required"] = json!(["success", "data", "total"]);
+                envelope["properties"]["total"] = json!({ "type": "integer", "minimum": 0 });
+                envelope["properties"]["next_cursor"] = json!({ "type": "string" });
+            }
+            json!({ "application/json": { "schema": envelope } })
+        }
+    };
+    let mut responses = Map::new();
+    responses.insert(
+        status.as_u16().to_string(),
+        json!({
+            "description": status.canonical_reason().unwrap_or("Success"),
+            "content": content,
+        }),
+    );
+    let mut codes = vec!["401", "403", "default"];
+    if op.validated {
+        codes.insert(2, "422");
+    }
+    for code in codes {
+        responses.insert(
+            code.to_string(),
+            json!({ "$ref": "#/components/responses/Error" }),
+        );
+    }
+
+    let tag = policy
+        .path
+        .trim_start_matches('/')
+        .split('/')
+        .next()
+        .unwrap_or_default();
+    let mut operation = json!({
+        "operationId": op.operation_id,
+        "tags": [tag],
+        "x-required-role": policy.role.as_str(),
+        "responses": responses,
+    });
+    if !parameters.is_empty() {
+        operation["parameters"] = Value::Array(parameters);
+    }
+    if let Some(body) = &op.request_body {
+        operation["requestBody"] = json!({
+            "required": true,
+            "content": { "application/json": { "schema": body } }
+        });
+    }
+    operation
+}
+
+/// Path parameters in template order, typed from the `Path<T>` extractor
+fn path_parameters(path: &str, schema: Option<&Value>) -> Vec<Value> {
+    let names: Vec<&str> = path
+        .split('/')
+        .filter_map(|s| s.strip_prefix('{').and_then(|s| s.strip_suffix('}')))
+        .collect();
+    let schemas: Vec<Value> = match schema {
+        Some(Value::Object(s)) if s.contains_key("prefixItems") => {
+            s["prefixItems"].as_array().cloned().unwrap_or_default()
+        }
+        Some(s) if names.len() == 1 => vec![s.clone()],
+        _ => Vec::new(),
+    };
+    names
+        .iter()
+        .enumerate()
+        .map(|(i, name)| {
+            json!({
+                "name": name,
+                "in": "path",
+                "required": true,
+                "schema": schemas.get(i).cloned().unwrap_or_else(|| json!({ "type": "string" }))
+            })
+        })
+        .collect()
+}
+
+/// One query parameter per property of the `Query<T>` struct
+fn query_parameters(schema: &Value) -> Vec<Value> {
+    let required: Vec<&str> = schema["required"]
+        .as_array()
+        .map(|r| r.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+    schema["properties"]
+        .as_object()
+        .map(|properties| {
+            properties
+                .iter()
+                .map(|(name, property)| {
+                    let mut parameter = json!({
+                        "name": name,
+                        "in": "query",
+                        "required": required.contains(&name.as_str()),
+                        "schema": property,
+                    });
+                    if let Some(description) = property.get("description") {
+                        parameter["description"] = description.clone();
+                    }
+                    parameter
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Follow a `$ref` into the generator's definitions
+fn resolve<'a>(schema: &'a Value, gen: &'a SchemaGenerator) -> &'a Value {
+    schema["$ref"]
+        .as_str()
+        .and_then(|r| r.strip_prefix("#/components/schemas/"))
+        .and_then(|name| gen.definitions().get(name))
+        .unwrap_or(schema)
+}
+
+// =============================================================================
+// Handlers
+// =============================================================================
+
+pub async fn openapi_json() -> Json<&'static Value> {
+    Json(spec())
+}
+
+pub async fn docs() -> impl IntoResponse {
+    ([(header::CACHE_CONTROL, "no-cache")], Html(DOCS_HTML))
+}
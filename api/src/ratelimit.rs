@@ -0,0 +1,175 @@
+//! Per-client rate limiting of `/api/v1`
+//!
+//! Each client gets a token bucket holding up to `[rate_limit] burst` tokens
+//! and refilled at `requests_per_second`. A request takes one token; without
+//! one it gets `429 Too Many Requests` with `Retry-After`. Clients are told
+//! apart by authenticated principal, or by peer address when authentication
+//! is disabled. `/health` and `/metrics` are never limited.
+
+use std::collections::HashMap;
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+use crate::auth::{AuthMethod, Principal};
+use crate::config::RateLimitConfig;
+use crate::error::{ApiError, ErrorCode};
+use crate::tls::ConnectionInfo;
+use crate::SharedState;
+
+/// Buckets kept before full ones are pruned
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Token buckets per client under the current limits
+pub struct RateLimiter {
+    config: RwLock<RateLimitConfig>,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(config: &RateLimitConfig) -> Self {
+        Self {
+            config: RwLock::new(config.clone()),
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Apply new limits; clients start again with a full bucket
+    pub fn reconfigure(&self, config: &RateLimitConfig) {
+        *self.config.write().unwrap_or_else(|e| e.into_inner()) = config.clone();
+        self.table().clear();
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.config
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .enabled
+    }
+
+    /// Take a token for `client`, or return how long until one is available
+    pub fn check(&self, client: &str) -> Result<(), Duration> {
+        let config = self
+            .config
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+        if !config.enabled {
+            return Ok(());
+        }
+        let burst = f64::from(config.burst);
+        let rate = config.requests_per_second;
+        let now = Instant::now();
+
+        let mut buckets = self.table();
+        if buckets.len() >= MAX_TRACKED_CLIENTS {
+            buckets.retain(|_, b| {
+                b.tokens + now.duration_since(b.updated).as_secs_f64() * rate < burst
+            });
+        }
+        let bucket = buckets.entry(client.to_string()).or_insert(Bucket {
+            tokens: burst,
+            updated: now,
+        });
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * rate).min(burst);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / rate))
+        }
+    }
+
+    fn table(&self) -> std::sync::MutexGuard<'_, HashMap<String, Bucket>> {
+        self.buckets.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new(&RateLimitConfig::default())
+    }
+}
+
+fn client_key(request: &Request) -> String {
+    match request.extensions().get::<Principal>() {
+        Some(principal) if principal.method != AuthMethod::Anonymous => {
+            format!("principal:{}", principal.name)
+        }
+        _ => request
+            .extensions()
+            .get::<ConnectInfo<ConnectionInfo>>()
+            .map(|info| format!("addr:{}", info.0.remote_addr.ip()))
+            .unwrap_or_else(|| "anonymous".to_string()),
+    }
+}
+
+/// Reject requests over the caller's limit with 429
+pub async fn limit(State(state): State<SharedState>, request: Request, next: Next) -> Response {
+    let limiter = &state.rate_limiter;
+    if !limiter.is_enabled() || !request.uri().path().starts_with("/api/v1/") {
+        return next.run(request).await;
+    }
+    match limiter.check(&client_key(&request)) {
+        Ok(()) => next.run(request).await,
+        Err(wait) => {
+            let secs = wait.as_secs_f64().ceil().max(1.0) as u64;
+            let mut response = ApiError::new(
+                StatusCode::TOO_MANY_REQUESTS,
+                ErrorCode::RateLimited,
+                format!("Rate limit exceeded; retry in {} s", secs),
+            )
+            .with_retryable(true)
+            .into_response();
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from(secs));
+            response
+        }
+    }
+}
+
+// =============================================================================
+// Tests
+// =============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_refills() {
+        let limiter = RateLimiter::new(&RateLimitConfig {
+            enabled: true,
+            requests_per_second: 20.0,
+            burst: 2,
+        });
+        assert!(limiter.check("alice").is_ok());
+        assert!(limiter.check("alice").is_ok());
+        let wait = limiter.check("alice").unwrap_err();
+        assert!(wait <= Duration::from_millis(50));
+        // Clients have separate buckets
+        assert!(limiter.check("bob").is_ok());
+
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(limiter.check("alice").is_ok());
+
+        limiter.reconfigure(&RateLimitConfig::default());
+        assert!(!limiter.is_enabled());
+        assert!(limiter.check("alice").is_ok());
+    }
+}
@@ -0,0 +1,219 @@
+//! Hot reload of the configuration file
+//!
+//! [`ConfigWatcher`] re-reads the configuration file every
+//! `[server] config_reload_interval_secs`. When its contents change, the
+//! layered configuration is rebuilt and validated (environment variables and
+//! flags still win over the file), the changed keys are logged, and the
+//! settings that are safe to change on a running server are applied:
+//! - `logging.level`
+//! - `[cors]`
+//! - `[rate_limit]`
+//! - `webhooks.subscriptions`
+//!
+//! Other changed keys are logged as needing a restart. An invalid file is
+//! logged and the running configuration is kept.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use tokio::task::JoinHandle;
+use toml::{Table, Value};
+
+use crate::config::{Config, ConfigError, ConfigLoader};
+use crate::SharedState;
+
+/// Keys, or sections, applied without a restart
+pub const RELOADABLE: &[&str] = &[
+    "logging.level",
+    "cors",
+    "rate_limit",
+    "webhooks.subscriptions",
+];
+
+/// Outcome of a reload that found changes
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Reload {
+    /// Keys now in effect
+    pub applied: Vec<String>,
+    /// Keys that changed in the file but need a restart
+    pub restart_required: Vec<String>,
+}
+
+/// Watches the configuration file and applies reloadable changes
+pub struct ConfigWatcher {
+    loader: ConfigLoader,
+    state: SharedState,
+    contents: Mutex<Option<String>>,
+    values: Mutex<Table>,
+}
+
+impl ConfigWatcher {
+    /// Watch `loader`'s file, starting from its current contents
+    pub fn new(loader: ConfigLoader, state: SharedState) -> Result<Self, ConfigError> {
+        let contents = std::fs::read_to_string(loader.path()).ok();
+        let (_, values) = loader.load_values()?;
+        Ok(Self {
+            loader,
+            state,
+            contents: Mutex::new(contents),
+            values: Mutex::new(values),
+        })
+    }
+
+    /// Reload if the file changed; `None` when there was nothing to do
+    pub fn reload_if_changed(&self) -> Result<Option<Reload>, ConfigError> {
+        let contents = std::fs::read_to_string(self.loader.path()).ok();
+        let mut last = self.contents.lock().unwrap_or_else(|e| e.into_inner());
+        if *last == contents {
+            return Ok(None);
+        }
+        // Remember the contents even if invalid, so the error is logged once
+        *last = contents;
+
+        let loaded = self.loader.load_values();
+        self.state
+            .health
+            .set_config_error(loaded.as_ref().err().map(ToString::to_string));
+        let (config, values) = loaded?;
+        let mut current = self.values.lock().unwrap_or_else(|e| e.into_inner());
+        let changed = changed_keys(&current, &values);
+        if changed.is_empty() {
+            return Ok(None);
+        }
+
+        let (applied, restart_required): (Vec<_>, Vec<_>) =
+            changed.into_iter().partition(|key| is_reloadable(key));
+        if !applied.is_empty() {
+            self.apply(&config)?;
+        }
+        *current = values;
+        Ok(Some(Reload {
+            applied,
+            restart_required,
+        }))
+    }
+
+    fn apply(&self, config: &Config) -> Result<(), ConfigError> {
+        crate::set_log_level(&config.logging.level).map_err(ConfigError::Invalid)?;
+        self.state.cors.reconfigure(&config.cors)?;
+        self.state.rate_limiter.reconfigure(&config.rate_limit);
+        self.state.webhooks.reconfigure(&config.webhooks);
+        Ok(())
+    }
+
+    /// Check for changes every `interval` until the task is aborted
+    pub fn spawn(self, interval: Duration) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                match self.reload_if_changed() {
+                    Ok(Some(reload)) => {
+                        if !reload.applied.is_empty() {
+                            tracing::info!(
+                                "Reloaded configuration from {:?}; applied {}",
+                                self.loader.path(),
+                                reload.applied.join(", ")
+                            );
+                        }
+                        if !reload.restart_required.is_empty() {
+                            tracing::warn!(
+                                "Configuration changes need a restart to take effect: {}",
+                                reload.restart_required.join(", ")
+                            );
+                        }
+                    }
+                    Ok(None) => {}
+                    Err(e) => tracing::warn!("Keeping previous configuration: {}", e),
+                }
+            }
+        })
+    }
+}
+
+fn is_reloadable(key: &str) -> bool {
+    RELOADABLE.iter().any(|prefix| {
+        key == *prefix
+            || key
+                .strip_prefix(prefix)
+                .is_some_and(|rest| rest.starts_with('.'))
+    })
+}
+
+/// Dotted keys whose value differs between `old` and `new`
+pub fn changed_keys(old: &Table, new: &Table) -> Vec<String> {
+    let (mut old_keys, mut new_keys) = (BTreeMap::new(), BTreeMap::new());
+    flatten("", old, &mut old_keys);
+    flatten("", new, &mut new_keys);
+    let mut changed: Vec<String> = old_keys
+        .keys()
+        .chain(new_keys.keys())
+        .filter(|key| old_keys.get(*key) != new_keys.get(*key))
+        .cloned()
+        .collect();
+    changed.sort();
+    changed.dedup();
+    changed
+}
+
+fn flatten<'a>(prefix: &str, table: &'a Table, out: &mut BTreeMap<String, &'a Value>) {
+    for (key, value) in table {
+        let key = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{}.{}", prefix, key)
+        };
+        match value {
+            Value::Table(table) => flatten(&key, table, out),
+            _ => {
+                out.insert(key, value);
+            }
+        }
+    }
+}
+
+// =============================================================================
+// Tests
+// =============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changed_keys() {
+        let old: Table = r#"
+            [server]
+            port = 6001
+            [logging]
+            level = "info"
+            [cors]
+            allowed_origins = ["https://a.example"]
+        "#
+        .parse()
+        .unwrap();
+        let new: Table = r#"
+            [server]
+            port = 6001
+            host = "127.0.0.1"
+            [logging]
+            level = "debug"
+            [cors]
+            allowed_origins = ["https://b.example"]
+        "#
+        .parse()
+        .unwrap();
+        assert_eq!(
+            changed_keys(&old, &new),
+            ["cors.allowed_origins", "logging.level", "server.host"]
+        );
+        assert!(changed_keys(&old, &old).is_empty());
+
+        assert!(is_reloadable("logging.level"));
+        assert!(is_reloadable("rate_limit.burst"));
+        assert!(!is_reloadable("rate_limits"));
+        assert!(!is_reloadable("server.host"));
+    }
+}
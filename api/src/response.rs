@@ -1,13 +1,40 @@
 //! API response types and utilities
 
-use axum::{http::StatusCode, Json};
+use std::marker::PhantomData;
+
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::backend::BackendError;
+use crate::dto::NodeErrorDto;
+use crate::error::{ApiError, ErrorCode, FieldError};
+use crate::locks::LockError;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ApiResponse<T> {
     pub success: bool,
     pub data: Option<T>,
     pub error: Option<String>,
+    /// Stable error code, see [`ErrorCode`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<ErrorCode>,
+    /// Cursor for the next page of a list; absent on the last page
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+    /// Items matching a list's filters, across all pages
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total: Option<usize>,
+    /// Offending request fields, see [`FieldError`]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
+    /// Nodes missing from an aggregated response, see [`crate::aggregator`]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub node_errors: Vec<NodeErrorDto>,
 }
 
 impl<T: Serialize> ApiResponse<T> {
@@ -16,6 +43,11 @@ impl<T: Serialize> ApiResponse<T> {
             success: true,
             data: Some(data),
             error: None,
+            code: None,
+            next_cursor: None,
+            total: None,
+            errors: Vec::new(),
+            node_errors: Vec::new(),
         }
     }
 }
@@ -26,12 +58,65 @@ impl ApiResponse<()> {
             success: false,
             data: None,
             error: Some(message.to_string()),
+            code: None,
+            next_cursor: None,
+            total: None,
+            errors: Vec::new(),
+            node_errors: Vec::new(),
         }
     }
 }
 
-pub type ApiResult<T> = Result<Json<ApiResponse<T>>, (StatusCode, Json<ApiResponse<()>>)>;
+pub type ApiResult<T> = Result<Json<ApiResponse<T>>, ApiError>;
+
+/// Result of handlers that start a background job and answer `202 Accepted`
+pub type AcceptedResult<T> = Result<(StatusCode, Json<ApiResponse<T>>), ApiError>;
+
+/// One page of a list endpoint, see [`crate::listing`]
+///
+/// Items are JSON values because `fields` may project them; `T` is the item
+/// type the OpenAPI document describes.
+pub struct Page<T> {
+    pub items: Vec<Value>,
+    pub total: usize,
+    pub next_cursor: Option<String>,
+    pub item: PhantomData<T>,
+}
+
+impl<T> IntoResponse for Page<T> {
+    fn into_response(self) -> Response {
+        Json(ApiResponse {
+            success: true,
+            data: Some(self.items),
+            error: None,
+            code: None,
+            next_cursor: self.next_cursor,
+            total: Some(self.total),
+            errors: Vec::new(),
+            node_errors: Vec::new(),
+        })
+        .into_response()
+    }
+}
+
+/// Result of list handlers
+pub type ListResult<T> = Result<Page<T>, ApiError>;
+
+pub fn accepted<T: Serialize>(data: T) -> (StatusCode, Json<ApiResponse<T>>) {
+    (StatusCode::ACCEPTED, Json(ApiResponse::success(data)))
+}
+
+/// Error with the generic [`ErrorCode`] for `status`
+pub fn api_error(status: StatusCode, message: &str) -> ApiError {
+    ApiError::from_status(status, message)
+}
+
+/// Map a backend failure to an HTTP status and error code, see [`crate::error`]
+pub fn backend_error(err: BackendError) -> ApiError {
+    ApiError::from(err)
+}
 
-pub fn api_error(status: StatusCode, message: &str) -> (StatusCode, Json<ApiResponse<()>>) {
-    (status, Json(ApiResponse::error(message)))
+/// Map a refused lock to 409 (object busy) or 503 (operation cap reached)
+pub fn lock_error(err: LockError) -> ApiError {
+    ApiError::from(err)
 }
@@ -1,176 +1,422 @@
 //! Route definitions
+//!
+//! Every route is registered with the minimum [`Role`] it requires. The
+//! resulting [`RoutePolicy`] list documents the authorization surface and
+//! is the source of the OpenAPI document.
 
-use axum::{routing::get, Router};
+use axum::{handler::Handler, http::Method, middleware, routing::MethodRouter, Router};
 
+use crate::auth::{require_role, Role};
 use crate::handlers::*;
+use crate::openapi::{describe, DescribeFn, HandlerDoc};
 use crate::SharedState;
 
-pub fn cluster_routes() -> Router<SharedState> {
-    Router::new()
-        // Cluster info
-        .route("/", get(cluster_info))
-        .route("/connect/{name}", get(cluster_connect))
-        // Nodes
-        .route("/nodes", get(cluster_list_nodes))
-        .route("/nodes/{name}", get(cluster_get_node))
-        .route(
-            "/nodes/{name}/pause",
-            axum::routing::post(cluster_pause_node),
+use Role::{Admin, Operator, Reader};
+
+/// Minimum role required for one method on one path
+#[derive(Debug, Clone)]
+pub struct RoutePolicy {
+    pub method: Method,
+    /// Path relative to `/api/v1`, in axum `{param}` syntax
+    pub path: String,
+    pub role: Role,
+    /// Describes the handler for the OpenAPI document
+    pub describe: DescribeFn,
+}
+
+/// Router builder that records the role of every registered route
+pub struct RouteTable {
+    router: Router<SharedState>,
+    policies: Vec<RoutePolicy>,
+}
+
+impl Default for RouteTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RouteTable {
+    pub fn new() -> Self {
+        Self {
+            router: Router::new(),
+            policies: Vec::new(),
+        }
+    }
+
+    pub fn get<H, T, D>(self, path: &str, role: Role, handler: H) -> Self
+    where
+        H: Handler<T, SharedState> + HandlerDoc<D>,
+        T: 'static,
+    {
+        let describe = describe::<H, D>;
+        self.add(
+            Method::GET,
+            path,
+            role,
+            describe,
+            axum::routing::get(handler),
         )
-        .route(
-            "/nodes/{name}/resume",
-            axum::routing::post(cluster_resume_node),
+    }
+
+    pub fn post<H, T, D>(self, path: &str, role: Role, handler: H) -> Self
+    where
+        H: Handler<T, SharedState> + HandlerDoc<D>,
+        T: 'static,
+    {
+        let describe = describe::<H, D>;
+        self.add(
+            Method::POST,
+            path,
+            role,
+            describe,
+            axum::routing::post(handler),
         )
-        // Groups
-        .route("/groups", get(cluster_list_groups))
-        .route("/groups/{name}", get(cluster_get_group))
-        .route(
-            "/groups/{name}/online",
-            axum::routing::post(cluster_group_online),
+    }
+
+    pub fn put<H, T, D>(self, path: &str, role: Role, handler: H) -> Self
+    where
+        H: Handler<T, SharedState> + HandlerDoc<D>,
+        T: 'static,
+    {
+        let describe = describe::<H, D>;
+        self.add(
+            Method::PUT,
+            path,
+            role,
+            describe,
+            axum::routing::put(handler),
         )
-        .route(
-            "/groups/{name}/offline",
-            axum::routing::post(cluster_group_offline),
+    }
+
+    pub fn patch<H, T, D>(self, path: &str, role: Role, handler: H) -> Self
+    where
+        H: Handler<T, SharedState> + HandlerDoc<D>,
+        T: 'static,
+    {
+        let describe = describe::<H, D>;
+        self.add(
+            Method::PATCH,
+            path,
+            role,
+            describe,
+            axum::routing::patch(handler),
+        )
+    }
+
+    pub fn delete<H, T, D>(self, path: &str, role: Role, handler: H) -> Self
+    where
+        H: Handler<T, SharedState> + HandlerDoc<D>,
+        T: 'static,
+    {
+        let describe = describe::<H, D>;
+        self.add(
+            Method::DELETE,
+            path,
+            role,
+            describe,
+            axum::routing::delete(handler),
         )
-        .route(
+    }
+
+    fn add(
+        mut self,
+        method: Method,
+        path: &str,
+        role: Role,
+        describe: DescribeFn,
+        route: MethodRouter<SharedState>,
+    ) -> Self {
+        let route = route.route_layer(middleware::from_fn_with_state(role, require_role));
+        self.router = self.router.route(path, route);
+        self.policies.push(RoutePolicy {
+            method,
+            path: path.to_string(),
+            role,
+            describe,
+        });
+        self
+    }
+
+    /// Mount another table under `prefix`
+    pub fn nest(mut self, prefix: &str, table: RouteTable) -> Self {
+        self.router = self.router.nest(prefix, table.router);
+        self.policies
+            .extend(table.policies.into_iter().map(|policy| RoutePolicy {
+                path: match policy.path.as_str() {
+                    "/" => prefix.to_string(),
+                    path => format!("{}{}", prefix, path),
+                },
+                ..policy
+            }));
+        self
+    }
+
+    pub fn policies(&self) -> &[RoutePolicy] {
+        &self.policies
+    }
+
+    pub fn into_router(self) -> Router<SharedState> {
+        self.router
+    }
+}
+
+/// All `/api/v1` routes
+pub fn api_routes() -> RouteTable {
+    RouteTable::new()
+        .nest("/cluster", cluster_routes())
+        .nest("/hyperv", hyperv_routes())
+        .nest("/jobs", job_routes())
+        .nest("/events", event_routes())
+        .nest("/webhooks", webhook_routes())
+        .get("/audit", Admin, audit_query)
+}
+
+pub fn cluster_routes() -> RouteTable {
+    RouteTable::new()
+        // Cluster info
+        .get("/", Reader, cluster_info)
+        .get("/connect/{name}", Reader, cluster_connect)
+        // Nodes
+        .get("/nodes", Reader, cluster_list_nodes)
+        .get("/nodes/{name}", Reader, cluster_get_node)
+        .post("/nodes/{name}/pause", Admin, cluster_pause_node)
+        .post("/nodes/{name}/resume", Admin, cluster_resume_node)
+        // Groups
+        .get("/groups", Reader, cluster_list_groups)
+        .get("/groups/{name}", Reader, cluster_get_group)
+        .post("/groups/{name}/online", Operator, cluster_group_online)
+        .post("/groups/{name}/offline", Operator, cluster_group_offline)
+        .post(
             "/groups/{name}/move/{target_node}",
-            axum::routing::post(cluster_move_group),
+            Operator,
+            cluster_move_group,
         )
         // Resources
-        .route("/resources", get(cluster_list_resources))
-        .route("/resources/{name}", get(cluster_get_resource))
-        .route(
+        .get("/resources", Reader, cluster_list_resources)
+        .get("/resources/{name}", Reader, cluster_get_resource)
+        .post(
             "/resources/{name}/online",
-            axum::routing::post(cluster_resource_online),
+            Operator,
+            cluster_resource_online,
         )
-        .route(
+        .post(
             "/resources/{name}/offline",
-            axum::routing::post(cluster_resource_offline),
+            Operator,
+            cluster_resource_offline,
         )
         // CSV
-        .route("/csv", get(cluster_list_csv))
-        .route("/csv/check-path", get(cluster_csv_check_path))
-        .route(
-            "/csv/{name}/maintenance",
-            axum::routing::post(cluster_csv_maintenance),
-        )
+        .get("/csv", Reader, cluster_list_csv)
+        .get("/csv/check-path", Reader, cluster_csv_check_path)
+        .post("/csv/{name}/maintenance", Admin, cluster_csv_maintenance)
 }
 
-pub fn hyperv_routes() -> Router<SharedState> {
-    Router::new()
+pub fn hyperv_routes() -> RouteTable {
+    RouteTable::new()
         // Host info
-        .route("/host", get(hyperv_host_info))
-        .route("/adapters", get(hyperv_list_adapters))
+        .get("/host", Reader, hyperv_host_info)
+        .get("/host/capabilities", Reader, hyperv_host_capabilities)
+        .get("/adapters", Reader, hyperv_list_adapters)
         // VMs
-        .route("/vms", get(hyperv_list_vms).post(hyperv_create_vm))
-        .route("/vms/{name}", get(hyperv_get_vm).delete(hyperv_delete_vm))
-        .route("/vms/{name}/start", axum::routing::post(hyperv_start_vm))
-        .route("/vms/{name}/stop", axum::routing::post(hyperv_stop_vm))
-        .route(
-            "/vms/{name}/force-stop",
-            axum::routing::post(hyperv_force_stop_vm),
-        )
-        .route("/vms/{name}/pause", axum::routing::post(hyperv_pause_vm))
-        .route("/vms/{name}/resume", axum::routing::post(hyperv_resume_vm))
-        .route("/vms/{name}/save", axum::routing::post(hyperv_save_vm))
-        .route("/vms/{name}/reset", axum::routing::post(hyperv_reset_vm))
-        .route("/vms/{name}/export", axum::routing::post(hyperv_export_vm))
-        // VM Disks
-        .route("/vms/{name}/disks", get(hyperv_vm_disks))
-        .route(
-            "/vms/{name}/disks/attach",
-            axum::routing::post(hyperv_attach_disk),
-        )
-        .route(
-            "/vms/{name}/disks/detach",
-            axum::routing::post(hyperv_detach_disk),
+        .get("/vms", Reader, hyperv_list_vms)
+        .post("/vms", Operator, hyperv_create_vm)
+        .post("/vms:batch", Operator, hyperv_batch_vms)
+        .get("/vms/{name}", Reader, hyperv_get_vm)
+        .patch("/vms/{name}", Operator, hyperv_update_vm)
+        .delete("/vms/{name}", Admin, hyperv_delete_vm)
+        .post("/vms/{name}/start", Operator, hyperv_start_vm)
+        .post("/vms/{name}/stop", Operator, hyperv_stop_vm)
+        .post("/vms/{name}/force-stop", Operator, hyperv_force_stop_vm)
+        .post("/vms/{name}/pause", Operator, hyperv_pause_vm)
+        .post("/vms/{name}/resume", Operator, hyperv_resume_vm)
+        .post("/vms/{name}/save", Operator, hyperv_save_vm)
+        .post("/vms/{name}/reset", Operator, hyperv_reset_vm)
+        .post("/vms/{name}/export", Operator, hyperv_export_vm)
+        .post(
+            "/vms/{name}/upgrade-version",
+            Operator,
+            hyperv_upgrade_vm_version,
         )
+        // VM Processor and Security
+        .get("/vms/{name}/processor", Reader, hyperv_vm_processor)
+        .put("/vms/{name}/processor", Operator, hyperv_set_vm_processor)
+        .get("/vms/{name}/security", Reader, hyperv_vm_security)
+        .put("/vms/{name}/security", Operator, hyperv_set_vm_security)
+        // VM Disks
+        .get("/vms/{name}/disks", Reader, hyperv_vm_disks)
+        .post("/vms/{name}/disks/attach", Operator, hyperv_attach_disk)
+        .post("/vms/{name}/disks/detach", Operator, hyperv_detach_disk)
         // VM DVD/ISO
-        .route("/vms/{name}/dvd", get(hyperv_vm_dvd))
-        .route(
-            "/vms/{name}/dvd/mount",
-            axum::routing::post(hyperv_mount_iso),
+        .get("/vms/{name}/dvd", Reader, hyperv_vm_dvd)
+        .post("/vms/{name}/dvd/mount", Operator, hyperv_mount_iso)
+        .post("/vms/{name}/dvd/eject", Operator, hyperv_eject_iso)
+        .post("/vms/{name}/boot-order", Operator, hyperv_set_boot_order)
+        // VM Network Adapters
+        .get("/vms/{name}/adapters", Reader, hyperv_vm_network_adapters)
+        .post("/vms/{name}/adapters", Operator, hyperv_add_network_adapter)
+        .patch(
+            "/vms/{name}/adapters/{adapter}",
+            Operator,
+            hyperv_update_network_adapter,
+        )
+        .delete(
+            "/vms/{name}/adapters/{adapter}",
+            Operator,
+            hyperv_remove_network_adapter,
         )
-        .route(
-            "/vms/{name}/dvd/eject",
-            axum::routing::post(hyperv_eject_iso),
+        .post(
+            "/vms/{name}/adapters/{adapter}/connect",
+            Operator,
+            hyperv_connect_network_adapter,
         )
-        .route(
-            "/vms/{name}/boot-order",
-            axum::routing::post(hyperv_set_boot_order),
+        .post(
+            "/vms/{name}/adapters/{adapter}/disconnect",
+            Operator,
+            hyperv_disconnect_network_adapter,
         )
         // VM Snapshots
-        .route(
-            "/vms/{name}/snapshots",
-            get(hyperv_list_snapshots).post(hyperv_create_snapshot),
+        .get("/vms/{name}/snapshots", Reader, hyperv_list_snapshots)
+        .post("/vms/{name}/snapshots", Operator, hyperv_create_snapshot)
+        .get("/vms/{name}/snapshots/tree", Reader, hyperv_snapshot_tree)
+        .get(
+            "/vms/{name}/snapshots/{snapshot}",
+            Reader,
+            hyperv_get_snapshot,
         )
-        .route("/vms/{name}/snapshots/{snapshot}", get(hyperv_get_snapshot))
-        .route(
+        .post(
             "/vms/{name}/snapshots/{snapshot}/apply",
-            axum::routing::post(hyperv_apply_snapshot),
+            Operator,
+            hyperv_apply_snapshot,
         )
-        .route(
+        .delete(
             "/vms/{name}/snapshots/{snapshot}/delete",
-            axum::routing::delete(hyperv_delete_snapshot),
+            Operator,
+            hyperv_delete_snapshot,
         )
-        // VM GPU
-        .route("/vms/{name}/gpu", get(hyperv_vm_gpu_adapters))
-        .route("/vms/{name}/gpu/add", axum::routing::post(hyperv_add_gpu))
-        .route(
-            "/vms/{name}/gpu/remove",
-            axum::routing::post(hyperv_remove_gpu),
+        .post(
+            "/vms/{name}/snapshots/{snapshot}/rename",
+            Operator,
+            hyperv_rename_snapshot,
         )
-        .route(
-            "/vms/{name}/gpu/configure",
-            axum::routing::post(hyperv_configure_gpu),
+        .post(
+            "/vms/{name}/snapshots/{snapshot}/export",
+            Operator,
+            hyperv_export_snapshot,
         )
+        // VM GPU
+        .get("/vms/{name}/gpu", Reader, hyperv_vm_gpu_adapters)
+        .post("/vms/{name}/gpu/add", Admin, hyperv_add_gpu)
+        .post("/vms/{name}/gpu/remove", Admin, hyperv_remove_gpu)
+        .post("/vms/{name}/gpu/configure", Admin, hyperv_configure_gpu)
         // VM DDA
-        .route("/vms/{name}/dda", get(hyperv_vm_dda_devices))
-        .route(
-            "/vms/{name}/dda/assign",
-            axum::routing::post(hyperv_assign_device),
-        )
-        .route(
-            "/vms/{name}/dda/remove",
-            axum::routing::post(hyperv_remove_device),
-        )
+        .get("/vms/{name}/dda", Reader, hyperv_vm_dda_devices)
+        .post("/vms/{name}/dda/assign", Admin, hyperv_assign_device)
+        .post("/vms/{name}/dda/remove", Admin, hyperv_remove_device)
         // Switches
-        .route(
-            "/switches",
-            get(hyperv_list_switches).post(hyperv_create_switch),
-        )
-        .route(
-            "/switches/{name}",
-            get(hyperv_get_switch).delete(hyperv_delete_switch),
-        )
+        .get("/switches", Reader, hyperv_list_switches)
+        .post("/switches", Admin, hyperv_create_switch)
+        .get("/switches/{name}", Reader, hyperv_get_switch)
+        .delete("/switches/{name}", Admin, hyperv_delete_switch)
         // VHDs
-        .route("/vhds", axum::routing::post(hyperv_create_vhd))
-        .route("/vhds/info", get(hyperv_get_vhd_info))
-        .route("/vhds/resize", axum::routing::post(hyperv_resize_vhd))
-        .route("/vhds/compact", axum::routing::post(hyperv_compact_vhd))
-        .route("/vhds/mount", axum::routing::post(hyperv_mount_vhd))
-        .route("/vhds/dismount", axum::routing::post(hyperv_dismount_vhd))
-        .route(
-            "/vhds/differencing",
-            axum::routing::post(hyperv_create_diff_vhd),
-        )
-        .route(
-            "/vhds/initialize",
-            axum::routing::post(hyperv_initialize_vhd),
-        )
+        .post("/vhds", Operator, hyperv_create_vhd)
+        .get("/vhds/info", Reader, hyperv_get_vhd_info)
+        .post("/vhds/resize", Operator, hyperv_resize_vhd)
+        .post("/vhds/compact", Operator, hyperv_compact_vhd)
+        .post("/vhds/mount", Operator, hyperv_mount_vhd)
+        .post("/vhds/dismount", Operator, hyperv_dismount_vhd)
+        .post("/vhds/differencing", Operator, hyperv_create_diff_vhd)
+        .post("/vhds/initialize", Operator, hyperv_initialize_vhd)
         // Windows Image
-        .route("/iso/editions", get(hyperv_iso_editions))
-        .route(
-            "/iso/create-vhdx",
-            axum::routing::post(hyperv_create_vhdx_from_iso),
-        )
+        .get("/iso/editions", Reader, hyperv_iso_editions)
+        .post("/iso/create-vhdx", Operator, hyperv_create_vhdx_from_iso)
         // GPUs
-        .route("/gpus", get(hyperv_list_gpus))
-        .route("/gpus/partitionable", get(hyperv_list_partitionable_gpus))
+        .get("/gpus", Reader, hyperv_list_gpus)
+        .get(
+            "/gpus/partitionable",
+            Reader,
+            hyperv_list_partitionable_gpus,
+        )
         // DDA
-        .route("/dda/support", get(hyperv_dda_support))
-        .route("/dda/devices", get(hyperv_dda_devices))
-        .route("/dda/device-path", get(hyperv_device_path))
-        .route("/dda/dismount", axum::routing::post(hyperv_dismount_device))
-        .route("/dda/mount", axum::routing::post(hyperv_mount_device))
+        .get("/dda/support", Reader, hyperv_dda_support)
+        .get("/dda/devices", Reader, hyperv_dda_devices)
+        .get("/dda/device-path", Reader, hyperv_device_path)
+        .post("/dda/dismount", Admin, hyperv_dismount_device)
+        .post("/dda/mount", Admin, hyperv_mount_device)
+}
+
+pub fn job_routes() -> RouteTable {
+    RouteTable::new()
+        .get("/", Reader, jobs_list)
+        .get("/{id}", Reader, jobs_get)
+        .post("/{id}/cancel", Operator, jobs_cancel)
+        .get("/{id}/wait", Reader, jobs_wait)
+}
+
+/// Webhook URLs may embed credentials, so even reads are admin-only
+pub fn webhook_routes() -> RouteTable {
+    RouteTable::new()
+        .get("/", Admin, webhooks_list)
+        .post("/", Admin, webhooks_create)
+        .get("/deliveries", Admin, webhooks_deliveries)
+        .get("/dead-letters", Admin, webhooks_dead_letters)
+        .post("/dead-letters/{id}/retry", Admin, webhooks_retry)
+        .get("/{name}", Admin, webhooks_get)
+        .delete("/{name}", Admin, webhooks_delete)
+}
+
+pub fn event_routes() -> RouteTable {
+    RouteTable::new()
+        .get("/", Reader, events_stream)
+        .get("/ws", Reader, events_ws)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policies_are_unique() {
+        let table = api_routes();
+        let policies = table.policies();
+        for (i, a) in policies.iter().enumerate() {
+            assert!(
+                !policies[i + 1..]
+                    .iter()
+                    .any(|b| a.method == b.method && a.path == b.path),
+                "duplicate route {} {}",
+                a.method,
+                a.path
+            );
+        }
+    }
+
+    #[test]
+    fn test_destructive_routes_require_admin() {
+        let table = api_routes();
+        let role = |method: Method, path: &str| {
+            table
+                .policies()
+                .iter()
+                .find(|p| p.method == method && p.path == path)
+                .map(|p| p.role)
+        };
+
+        assert_eq!(role(Method::DELETE, "/hyperv/vms/{name}"), Some(Admin));
+        assert_eq!(role(Method::POST, "/hyperv/dda/dismount"), Some(Admin));
+        assert_eq!(
+            role(Method::POST, "/cluster/csv/{name}/maintenance"),
+            Some(Admin)
+        );
+        assert_eq!(role(Method::GET, "/hyperv/vms"), Some(Reader));
+        assert_eq!(role(Method::GET, "/jobs"), Some(Reader));
+        assert_eq!(role(Method::GET, "/audit"), Some(Admin));
+        assert_eq!(role(Method::GET, "/webhooks"), Some(Admin));
+        assert!(table
+            .policies()
+            .iter()
+            .filter(|p| p.method == Method::GET
+                && p.path != "/audit"
+                && !p.path.starts_with("/webhooks"))
+            .all(|p| p.role == Reader));
+    }
 }
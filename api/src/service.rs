@@ -8,7 +8,6 @@
 #[cfg(windows)]
 pub mod windows_service {
     use std::ffi::OsString;
-    use std::sync::mpsc;
     use std::time::Duration;
 
     use windows_service::{
@@ -21,18 +20,24 @@ pub mod windows_service {
         service_dispatcher,
     };
 
-    use crate::config::Config;
+    use crate::config::ConfigLoader;
+    use crate::shutdown::Shutdown;
 
     const SERVICE_TYPE: ServiceType = ServiceType::OWN_PROCESS;
 
     /// Run the application as a Windows service
     ///
     /// The service_name parameter must match the name used when the service was installed.
-    pub fn run_as_service(service_name: &str) -> Result<(), windows_service::Error> {
+    /// `loader` is re-run once the service starts and then watched for changes.
+    pub fn run_as_service(
+        service_name: &str,
+        loader: ConfigLoader,
+    ) -> Result<(), windows_service::Error> {
         // Store the service name in a thread-local for the service main function
         SERVICE_NAME.with(|name| {
             *name.borrow_mut() = service_name.to_string();
         });
+        let _ = LOADER.set(loader);
         service_dispatcher::start(service_name, ffi_service_main)
     }
 
@@ -41,6 +46,9 @@ pub mod windows_service {
         static SERVICE_NAME: std::cell::RefCell<String> = std::cell::RefCell::new(String::new());
     }
 
+    // The dispatcher calls service_main on its own thread
+    static LOADER: std::sync::OnceLock<ConfigLoader> = std::sync::OnceLock::new();
+
     define_windows_service!(ffi_service_main, service_main);
 
     fn service_main(_arguments: Vec<OsString>) {
@@ -53,14 +61,26 @@ pub mod windows_service {
         // Get the service name from thread-local storage
         let service_name = SERVICE_NAME.with(|name| name.borrow().clone());
 
-        // Create a channel to receive stop events
-        let (shutdown_tx, shutdown_rx) = mpsc::channel();
-
-        // Define the service control handler
+        // Load configuration
+        let loader = LOADER
+            .get()
+            .cloned()
+            .unwrap_or_else(|| ConfigLoader::new(get_config_path(), Default::default()));
+        let config_path = loader.path().to_path_buf();
+        let config = loader.load()?;
+
+        // Stop and system shutdown drain the server like Ctrl-C does
+        let timeout = Duration::from_secs(config.server.shutdown_timeout_secs);
+        let shutdown = Shutdown::new(timeout);
+        let stop = shutdown.clone();
         let event_handler = move |control_event| -> ServiceControlHandlerResult {
             match control_event {
                 ServiceControl::Stop => {
-                    let _ = shutdown_tx.send(());
+                    stop.trigger("Received service stop");
+                    ServiceControlHandlerResult::NoError
+                }
+                ServiceControl::Shutdown => {
+                    stop.trigger("Received system shutdown");
                     ServiceControlHandlerResult::NoError
                 }
                 ServiceControl::Interrogate => ServiceControlHandlerResult::NoError,
@@ -75,17 +95,13 @@ pub mod windows_service {
         status_handle.set_service_status(ServiceStatus {
             service_type: SERVICE_TYPE,
             current_state: ServiceState::Running,
-            controls_accepted: ServiceControlAccept::STOP,
+            controls_accepted: ServiceControlAccept::STOP | ServiceControlAccept::SHUTDOWN,
             exit_code: ServiceExitCode::Win32(0),
             checkpoint: 0,
             wait_hint: Duration::default(),
             process_id: None,
         })?;
 
-        // Load configuration
-        let config_path = get_config_path();
-        let config = Config::load(&config_path).unwrap_or_default();
-
         // Initialize logging
         crate::init_tracing(&config.logging.level);
 
@@ -104,33 +120,35 @@ pub mod windows_service {
         let runtime = tokio::runtime::Runtime::new()?;
 
         runtime.block_on(async {
-            let state = std::sync::Arc::new(crate::AppState);
-            let app = crate::create_router(state);
-
-            let addr: std::net::SocketAddr = config.socket_addr().parse()?;
-            let listener = tokio::net::TcpListener::bind(addr).await?;
-
-            tracing::info!("API server listening on {}", addr);
-
-            // Spawn a task to handle shutdown
-            let server = axum::serve(listener, app);
+            let state = std::sync::Arc::new(crate::AppState::from_config(&config)?);
+            if config.server.config_reload_interval_secs > 0 {
+                let interval = Duration::from_secs(config.server.config_reload_interval_secs);
+                crate::ConfigWatcher::new(loader, state.clone())?.spawn(interval);
+            }
 
-            tokio::select! {
-                result = server => {
-                    if let Err(e) = result {
-                        tracing::error!("Server error: {}", e);
-                    }
-                }
-                _ = tokio::task::spawn_blocking(move || {
-                    // Wait for stop signal
-                    let _ = shutdown_rx.recv();
-                }) => {
-                    tracing::info!("Received stop signal");
-                }
+            // Tell the SCM how long draining may take
+            let pending = shutdown.clone();
+            tokio::spawn(async move {
+                pending.triggered().await;
+                let _ = status_handle.set_service_status(ServiceStatus {
+                    service_type: SERVICE_TYPE,
+                    current_state: ServiceState::StopPending,
+                    controls_accepted: ServiceControlAccept::empty(),
+                    exit_code: ServiceExitCode::Win32(0),
+                    checkpoint: 1,
+                    wait_hint: timeout + Duration::from_secs(5),
+                    process_id: None,
+                });
+            });
+
+            if let Err(e) = crate::run(&config, state, shutdown).await {
+                tracing::error!("Server error: {}", e);
             }
 
             Ok::<(), Box<dyn std::error::Error>>(())
         })?;
+        // Jobs abandoned at the deadline must not hold up the stop
+        runtime.shutdown_background();
 
         // Report stopped status
         status_handle.set_service_status(ServiceStatus {
@@ -167,7 +185,10 @@ pub mod windows_service {
 #[cfg(not(windows))]
 pub mod windows_service {
     /// Placeholder for non-Windows platforms
-    pub fn run_as_service(_service_name: &str) -> Result<(), std::io::Error> {
+    pub fn run_as_service(
+        _service_name: &str,
+        _loader: crate::config::ConfigLoader,
+    ) -> Result<(), std::io::Error> {
         Err(std::io::Error::new(
             std::io::ErrorKind::Unsupported,
             "Windows service is only supported on Windows",
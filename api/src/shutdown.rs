@@ -0,0 +1,193 @@
+//! Graceful shutdown
+//!
+//! Ctrl-C, SIGTERM and a Windows service Stop all trigger the same
+//! [`Shutdown`]. Once triggered, [`run`](crate::run) stops accepting
+//! connections and closes event streams, then gives in-flight requests and
+//! background jobs until the deadline (`[server] shutdown_timeout_secs`) to
+//! finish. The audit log, metrics and logs are flushed before it returns,
+//! whether or not everything finished in time.
+
+use std::future::Future;
+use std::io::Write;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+use crate::AppState;
+
+/// Shared shutdown trigger with a drain deadline
+#[derive(Clone)]
+pub struct Shutdown {
+    timeout: Duration,
+    deadline: Arc<watch::Sender<Option<Instant>>>,
+}
+
+impl Shutdown {
+    /// Allow `timeout` for draining once triggered
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            deadline: Arc::new(watch::Sender::new(None)),
+        }
+    }
+
+    /// Start shutting down; later calls keep the first deadline
+    pub fn trigger(&self, reason: &str) {
+        let timeout = self.timeout;
+        let triggered = self.deadline.send_if_modified(|deadline| {
+            if deadline.is_some() {
+                return false;
+            }
+            *deadline = Some(Instant::now() + timeout);
+            true
+        });
+        if triggered {
+            tracing::info!(
+                "{}; shutting down (waiting up to {} s for requests and jobs)",
+                reason,
+                timeout.as_secs()
+            );
+        }
+    }
+
+    pub fn is_triggered(&self) -> bool {
+        self.deadline.borrow().is_some()
+    }
+
+    /// Wait for the trigger and return the drain deadline
+    pub async fn triggered(&self) -> Instant {
+        let mut receiver = self.deadline.subscribe();
+        let deadline = *receiver
+            .wait_for(Option::is_some)
+            .await
+            .expect("sender is held by self");
+        deadline.expect("deadline is set once triggered")
+    }
+
+    /// Resolve once triggered, for `with_graceful_shutdown`
+    pub fn wait(&self) -> impl Future<Output = ()> + Send + 'static {
+        let shutdown = self.clone();
+        async move {
+            shutdown.triggered().await;
+        }
+    }
+
+    /// Wait until the deadline once triggered
+    pub async fn expired(&self) {
+        let deadline = self.triggered().await;
+        tokio::time::sleep_until(tokio::time::Instant::from_std(deadline)).await;
+    }
+
+    /// Trigger on Ctrl-C or SIGTERM (Ctrl-Close on Windows)
+    pub fn listen_for_signals(&self) -> JoinHandle<()> {
+        let shutdown = self.clone();
+        tokio::spawn(async move {
+            let reason = signal().await;
+            shutdown.trigger(reason);
+        })
+    }
+}
+
+#[cfg(unix)]
+async fn signal() -> &'static str {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut terminate = match signal(SignalKind::terminate()) {
+        Ok(terminate) => terminate,
+        Err(e) => {
+            tracing::warn!("Cannot listen for SIGTERM: {}", e);
+            let _ = tokio::signal::ctrl_c().await;
+            return "Received Ctrl-C";
+        }
+    };
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => "Received Ctrl-C",
+        _ = terminate.recv() => "Received SIGTERM",
+    }
+}
+
+#[cfg(windows)]
+async fn signal() -> &'static str {
+    let mut close = match tokio::signal::windows::ctrl_close() {
+        Ok(close) => close,
+        Err(e) => {
+            tracing::warn!("Cannot listen for Ctrl-Close: {}", e);
+            let _ = tokio::signal::ctrl_c().await;
+            return "Received Ctrl-C";
+        }
+    };
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => "Received Ctrl-C",
+        _ = close.recv() => "Received Ctrl-Close",
+    }
+}
+
+/// Wait for background jobs until the deadline, then flush
+pub async fn drain(state: &AppState, shutdown: &Shutdown) {
+    let deadline = shutdown.triggered().await;
+    let active = state.jobs.pending_count();
+    if active > 0 {
+        tracing::info!("Waiting for {} background job(s)", active);
+        let remaining = state.jobs.wait_idle(deadline).await;
+        if remaining > 0 {
+            tracing::warn!(
+                "Abandoning {} background job(s) still running at the shutdown deadline",
+                remaining
+            );
+        }
+    }
+    flush(state);
+}
+
+/// Flush the audit log, metrics and logs
+pub fn flush(state: &AppState) {
+    state.audit.flush();
+    if state.metrics.is_enabled() {
+        tracing::debug!("Final metrics:\n{}", state.metrics.encode());
+    }
+    tracing::info!("Shutdown complete");
+    let _ = std::io::stdout().flush();
+    let _ = std::io::stderr().flush();
+}
+
+// =============================================================================
+// Tests
+// =============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_first_trigger_sets_deadline() {
+        let shutdown = Shutdown::new(Duration::from_secs(30));
+        assert!(!shutdown.is_triggered());
+
+        let waiter = tokio::spawn({
+            let shutdown = shutdown.clone();
+            async move { shutdown.triggered().await }
+        });
+        shutdown.trigger("test");
+        let deadline = waiter.await.unwrap();
+        assert!(shutdown.is_triggered());
+        assert!(deadline > Instant::now() + Duration::from_secs(29));
+
+        shutdown.trigger("again");
+        assert_eq!(shutdown.triggered().await, deadline);
+    }
+
+    #[tokio::test]
+    async fn test_drain_waits_for_jobs() {
+        let state = AppState::default();
+        state.jobs.spawn("test", "target", |_| {
+            std::thread::sleep(Duration::from_millis(100));
+            Ok(())
+        });
+        let shutdown = Shutdown::new(Duration::from_secs(5));
+        shutdown.trigger("test");
+        drain(&state, &shutdown).await;
+        assert_eq!(state.jobs.active_count(), 0);
+    }
+}
@@ -0,0 +1,300 @@
+//! TLS and mutual TLS termination
+//!
+//! [`TlsListener`] wraps a `TcpListener` and performs the rustls handshake
+//! off the accept loop. Certificate, key and client CA files are polled
+//! and swapped in for new connections when they change, so rotation does
+//! not need a restart. Client certificate details are exposed to handlers
+//! through [`ConnectionInfo`].
+
+use std::io::BufReader;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime};
+
+use axum::extract::connect_info::Connected;
+use axum::serve::{IncomingStream, Listener};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio_rustls::rustls::{
+    self,
+    crypto::ring,
+    pki_types::{CertificateDer, PrivateKeyDer},
+    server::WebPkiClientVerifier,
+    RootCertStore, ServerConfig,
+};
+use tokio_rustls::{server::TlsStream, TlsAcceptor};
+
+use crate::config::{ConfigError, TlsConfig, TlsVersion};
+
+/// Time allowed for a client to complete the TLS handshake
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Handshakes waiting to be picked up by the server
+const ACCEPT_BACKLOG: usize = 128;
+
+/// Client certificate presented during the TLS handshake
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientCertificate {
+    /// Subject distinguished name, e.g. `CN=ops-automation, O=Contoso`
+    pub subject: String,
+    /// Issuer distinguished name
+    pub issuer: String,
+    /// Serial number as colon-separated hex
+    pub serial: String,
+}
+
+impl ClientCertificate {
+    /// Parse the leaf certificate of a verified chain
+    pub fn from_der(der: &[u8]) -> Option<Self> {
+        let (_, cert) = x509_parser::parse_x509_certificate(der).ok()?;
+        Some(Self {
+            subject: cert.subject().to_string(),
+            issuer: cert.issuer().to_string(),
+            serial: cert.raw_serial_as_string(),
+        })
+    }
+}
+
+/// Per-connection details, available as `ConnectInfo<ConnectionInfo>`
+#[derive(Debug, Clone)]
+pub struct ConnectionInfo {
+    pub remote_addr: SocketAddr,
+    /// Present when the client authenticated with a certificate (mTLS)
+    pub client_certificate: Option<ClientCertificate>,
+}
+
+impl Connected<IncomingStream<'_, TcpListener>> for ConnectionInfo {
+    fn connect_info(stream: IncomingStream<'_, TcpListener>) -> Self {
+        Self {
+            remote_addr: *stream.remote_addr(),
+            client_certificate: None,
+        }
+    }
+}
+
+impl Connected<IncomingStream<'_, TlsListener>> for ConnectionInfo {
+    fn connect_info(stream: IncomingStream<'_, TlsListener>) -> Self {
+        let (_, session) = stream.io().get_ref();
+        Self {
+            remote_addr: *stream.remote_addr(),
+            client_certificate: session
+                .peer_certificates()
+                .and_then(|chain| chain.first())
+                .and_then(|leaf| ClientCertificate::from_der(leaf)),
+        }
+    }
+}
+
+// =============================================================================
+// Server Configuration
+// =============================================================================
+
+/// Build a rustls server configuration from the `[server.tls]` section
+pub fn load_server_config(tls: &TlsConfig) -> Result<ServerConfig, ConfigError> {
+    let provider = Arc::new(ring::default_provider());
+    let versions: &[&'static rustls::SupportedProtocolVersion] = match tls.min_version {
+        TlsVersion::Tls12 => &[&rustls::version::TLS13, &rustls::version::TLS12],
+        TlsVersion::Tls13 => &[&rustls::version::TLS13],
+    };
+    let builder = ServerConfig::builder_with_provider(provider.clone())
+        .with_protocol_versions(versions)
+        .map_err(|e| ConfigError::Invalid(format!("server.tls: {}", e)))?;
+
+    let builder = match &tls.client_ca_path {
+        Some(path) => {
+            let mut roots = RootCertStore::empty();
+            for cert in read_certs(path)? {
+                roots
+                    .add(cert)
+                    .map_err(|e| ConfigError::ParseError(path.clone(), e.to_string()))?;
+            }
+            let verifier = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider);
+            let verifier = if tls.require_client_cert {
+                verifier
+            } else {
+                verifier.allow_unauthenticated()
+            };
+            let verifier = verifier
+                .build()
+                .map_err(|e| ConfigError::ParseError(path.clone(), e.to_string()))?;
+            builder.with_client_cert_verifier(verifier)
+        }
+        None => builder.with_no_client_auth(),
+    };
+
+    let certs = read_certs(&tls.cert_path)?;
+    let key = read_key(&tls.key_path)?;
+    let mut config = builder
+        .with_single_cert(certs, key)
+        .map_err(|e| ConfigError::ParseError(tls.cert_path.clone(), e.to_string()))?;
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    Ok(config)
+}
+
+fn open(path: &str) -> Result<BufReader<std::fs::File>, ConfigError> {
+    std::fs::File::open(path)
+        .map(BufReader::new)
+        .map_err(|e| ConfigError::ReadError(path.to_string(), e.to_string()))
+}
+
+fn read_certs(path: &str) -> Result<Vec<CertificateDer<'static>>, ConfigError> {
+    let certs = rustls_pemfile::certs(&mut open(path)?)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| ConfigError::ParseError(path.to_string(), e.to_string()))?;
+    if certs.is_empty() {
+        return Err(ConfigError::ParseError(
+            path.to_string(),
+            "no certificates found".to_string(),
+        ));
+    }
+    Ok(certs)
+}
+
+fn read_key(path: &str) -> Result<PrivateKeyDer<'static>, ConfigError> {
+    rustls_pemfile::private_key(&mut open(path)?)
+        .map_err(|e| ConfigError::ParseError(path.to_string(), e.to_string()))?
+        .ok_or_else(|| ConfigError::ParseError(path.to_string(), "no private key found".into()))
+}
+
+/// Current server configuration, rebuilt when any source file changes
+struct Reloader {
+    tls: TlsConfig,
+    current: RwLock<Arc<ServerConfig>>,
+    modified: Mutex<Vec<Option<SystemTime>>>,
+}
+
+impl Reloader {
+    fn new(tls: &TlsConfig) -> Result<Self, ConfigError> {
+        let config = load_server_config(tls)?;
+        Ok(Self {
+            modified: Mutex::new(Self::modified_times(tls)),
+            tls: tls.clone(),
+            current: RwLock::new(Arc::new(config)),
+        })
+    }
+
+    fn modified_times(tls: &TlsConfig) -> Vec<Option<SystemTime>> {
+        [
+            Some(&tls.cert_path),
+            Some(&tls.key_path),
+            tls.client_ca_path.as_ref(),
+        ]
+        .into_iter()
+        .flatten()
+        .map(|path| std::fs::metadata(path).and_then(|m| m.modified()).ok())
+        .collect()
+    }
+
+    fn acceptor(&self) -> TlsAcceptor {
+        TlsAcceptor::from(self.current.read().unwrap().clone())
+    }
+
+    /// Reload if a file changed; the previous configuration stays active on error
+    fn reload_if_changed(&self) -> Result<bool, ConfigError> {
+        let modified = Self::modified_times(&self.tls);
+        let mut last = self.modified.lock().unwrap();
+        if *last == modified {
+            return Ok(false);
+        }
+        let config = load_server_config(&self.tls)?;
+        *self.current.write().unwrap() = Arc::new(config);
+        *last = modified;
+        Ok(true)
+    }
+}
+
+// =============================================================================
+// Listener
+// =============================================================================
+
+/// TLS listener for `axum::serve`
+pub struct TlsListener {
+    incoming: mpsc::Receiver<(TlsStream<TcpStream>, SocketAddr)>,
+    local_addr: SocketAddr,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl TlsListener {
+    /// Terminate TLS on an already bound listener
+    pub fn new(listener: TcpListener, tls: &TlsConfig) -> Result<Self, ConfigError> {
+        let reloader = Arc::new(Reloader::new(tls)?);
+        let local_addr = listener
+            .local_addr()
+            .map_err(|e| ConfigError::Invalid(e.to_string()))?;
+        let (tx, incoming) = mpsc::channel(ACCEPT_BACKLOG);
+
+        let accept = tokio::spawn(accept_loop(listener, reloader.clone(), tx));
+        let interval = Duration::from_secs(tls.reload_interval_secs.max(1));
+        let reload = tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                match reloader.reload_if_changed() {
+                    Ok(true) => tracing::info!("Reloaded TLS certificates"),
+                    Ok(false) => {}
+                    Err(e) => tracing::warn!("Keeping previous TLS certificates: {}", e),
+                }
+            }
+        });
+
+        Ok(Self {
+            incoming,
+            local_addr,
+            tasks: vec![accept, reload],
+        })
+    }
+}
+
+async fn accept_loop(
+    listener: TcpListener,
+    reloader: Arc<Reloader>,
+    tx: mpsc::Sender<(TlsStream<TcpStream>, SocketAddr)>,
+) {
+    loop {
+        let (stream, addr) = match listener.accept().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                tracing::warn!("Failed to accept connection: {}", e);
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                continue;
+            }
+        };
+        let acceptor = reloader.acceptor();
+        let tx = tx.clone();
+        tokio::spawn(async move {
+            match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                Ok(Ok(stream)) => {
+                    let _ = tx.send((stream, addr)).await;
+                }
+                Ok(Err(e)) => tracing::debug!("TLS handshake with {} failed: {}", addr, e),
+                Err(_) => tracing::debug!("TLS handshake with {} timed out", addr),
+            }
+        });
+    }
+}
+
+impl Listener for TlsListener {
+    type Io = TlsStream<TcpStream>;
+    type Addr = SocketAddr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        match self.incoming.recv().await {
+            Some(conn) => conn,
+            None => std::future::pending().await,
+        }
+    }
+
+    fn local_addr(&self) -> std::io::Result<Self::Addr> {
+        Ok(self.local_addr)
+    }
+}
+
+impl Drop for TlsListener {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
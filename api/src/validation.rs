@@ -0,0 +1,1009 @@
+//! Pre-flight validation of request bodies
+//!
+//! Handlers take their body as [`Valid<T>`] instead of `Json<T>`, so a bad
+//! memory size or VHD path is refused with `422 validation_failed` before any
+//! lock is taken or the backend is called, instead of failing deep inside WMI.
+//! The response lists every offending field:
+//!
+//! ```json
+//! {"success":false,"data":null,"error":"Invalid request: memory_mb: ...",
+//!  "code":"validation_failed",
+//!  "errors":[{"field":"memory_mb","code":"out_of_range","message":"..."}]}
+//! ```
+//!
+//! The rules are `windows_hyperv`'s own: each request is converted to the
+//! matching settings type (`VmSettings`, `VhdSettings`,
+//! `VirtualSwitchSettings`, `DiskAttachment`, ...) and its `validate` or
+//! builder run,
+//! with the settings' field names mapped back to the request's. Those rules
+//! stop at the first problem, so fields are also checked one by one where
+//! the strong types (`MemoryMB`, `ProcessorCount`) allow it; a field is
+//! reported once.
+
+use axum::{
+    extract::{FromRequest, Request},
+    Json,
+};
+use serde::de::DeserializeOwned;
+use windows_hyperv::{
+    AutomaticStartAction, AutomaticStopAction, CheckpointSettings, CheckpointType,
+    ConsistencyLevel, CpuWeight, DiskAttachment, Generation, GuestIsolationType, HwThreadsPerCore,
+    IsoAttachment, KeyProtectorType, MemoryBufferPercent, MemoryMB, NetworkAdapterSettings,
+    NumaNode, NumaTopology, PortMirroringMode, ProcessorCount, ProcessorSettings,
+    SecureBootTemplate, SecuritySettings, StartupDelay, SwitchType, VhdFormat, VhdSettings,
+    VhdType, VirtualSwitchSettings, VmSettings, VmVersionInfo,
+};
+
+use crate::dto::*;
+use crate::error::{ApiError, FieldError, FieldErrorCode};
+use crate::webhooks::{name_error, url_error};
+
+const GIB: u64 = 1 << 30;
+
+/// Request body rules
+pub trait Validate {
+    fn validate(&self, violations: &mut Violations);
+}
+
+/// Violations found so far, at most one per field
+#[derive(Debug, Default)]
+pub struct Violations {
+    errors: Vec<FieldError>,
+}
+
+impl Violations {
+    /// Record a violation unless `field` already has one
+    pub fn add(&mut self, field: &str, code: FieldErrorCode, message: impl Into<String>) {
+        if self.errors.iter().all(|e| e.field != field) {
+            self.errors.push(FieldError {
+                field: field.to_string(),
+                code,
+                message: message.into(),
+            });
+        }
+    }
+
+    /// Require a non-blank value
+    pub fn required(&mut self, field: &str, value: &str) {
+        if value.trim().is_empty() {
+            self.add(field, FieldErrorCode::Required, "must not be empty");
+        }
+    }
+
+    /// Record the outcome of a `windows_hyperv` `validate`, renaming the
+    /// settings field to the request field through `fields`
+    pub fn rule(&mut self, result: windows_hyperv::Result<()>, fields: &[(&str, &str)]) {
+        let (field, code, message) = match result {
+            Ok(()) => return,
+            Err(windows_hyperv::Error::Validation { field, message }) => {
+                (field, FieldErrorCode::Invalid, message)
+            }
+            Err(windows_hyperv::Error::MissingRequired(field)) => (
+                field,
+                FieldErrorCode::Required,
+                "must not be empty".to_string(),
+            ),
+            Err(other) => ("", FieldErrorCode::Invalid, other.to_string()),
+        };
+        let field = fields
+            .iter()
+            .find(|(from, _)| *from == field)
+            .map_or(field, |(_, to)| *to);
+        self.add(field, code, message);
+    }
+
+    pub fn into_result(self) -> Result<(), ApiError> {
+        match self.errors.is_empty() {
+            true => Ok(()),
+            false => Err(ApiError::validation(self.errors)),
+        }
+    }
+}
+
+/// Check a request against its rules
+pub fn validate<T: Validate>(request: &T) -> Result<(), ApiError> {
+    let mut violations = Violations::default();
+    request.validate(&mut violations);
+    violations.into_result()
+}
+
+// =============================================================================
+// Extractor
+// =============================================================================
+
+/// JSON body that passed [`Validate`]
+///
+/// Bodies that do not parse are refused like `Json<T>` refuses them (`400`,
+/// `415` or `422`), but as an [`ApiError`].
+#[derive(Debug, Clone)]
+pub struct Valid<T>(pub T);
+
+impl<T, S> FromRequest<S> for Valid<T>
+where
+    T: DeserializeOwned + Validate,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let Json(body) = Json::<T>::from_request(request, state)
+            .await
+            .map_err(|e| ApiError::from_status(e.status(), e.body_text()))?;
+        validate(&body)?;
+        Ok(Valid(body))
+    }
+}
+
+// =============================================================================
+// VMs
+// =============================================================================
+
+impl Validate for CreateVmRequest {
+    fn validate(&self, v: &mut Violations) {
+        let memory = MemoryMB::new(self.memory_mb);
+        if memory.is_none() {
+            v.add(
+                "memory_mb",
+                FieldErrorCode::OutOfRange,
+                format!("must be between {} and {}", MemoryMB::MIN, MemoryMB::MAX),
+            );
+        }
+        let processors = match self.cpu_count {
+            None => Some(ProcessorCount::one()),
+            Some(count) => ProcessorCount::new(count),
+        };
+        if processors.is_none() {
+            v.add(
+                "cpu_count",
+                FieldErrorCode::OutOfRange,
+                format!(
+                    "must be between {} and {}",
+                    ProcessorCount::MIN,
+                    ProcessorCount::MAX
+                ),
+            );
+        }
+        let generation = match self.generation {
+            None | Some(2) => Generation::Gen2,
+            Some(1) => Generation::Gen1,
+            Some(_) => {
+                v.add("generation", FieldErrorCode::InvalidValue, "must be 1 or 2");
+                Generation::Gen2
+            }
+        };
+
+        // Fields already refused are replaced so the name rules still run
+        let settings = VmSettings::builder()
+            .name(&self.name)
+            .generation(generation)
+            .memory(memory.unwrap_or(MemoryMB::gb_1()))
+            .processors(processors.unwrap_or(ProcessorCount::one()))
+            .build();
+        v.rule(settings.map(drop), &[]);
+
+        v.rule(
+            vhd_settings(&self.vhd_path, VhdType::Dynamic, self.vhd_size_bytes).validate(),
+            &[("path", "vhd_path"), ("size_bytes", "vhd_size_bytes")],
+        );
+        if let Some(switch) = &self.switch_name {
+            v.required("switch_name", switch);
+        }
+        if let Some(version) = &self.version {
+            if VmVersionInfo::parse(version).is_none() {
+                v.add(
+                    "version",
+                    FieldErrorCode::InvalidValue,
+                    "must be a configuration version such as 10.0",
+                );
+            }
+        }
+    }
+}
+
+impl Validate for UpdateVmRequest {
+    fn validate(&self, v: &mut Violations) {
+        let memory = |v: &mut Violations, field: &str, value: Option<u64>| match value {
+            Some(mb) if MemoryMB::new(mb).is_none() => {
+                v.add(
+                    field,
+                    FieldErrorCode::OutOfRange,
+                    format!("must be between {} and {}", MemoryMB::MIN, MemoryMB::MAX),
+                );
+                None
+            }
+            _ => value,
+        };
+        let startup = memory(v, "memory_mb", self.memory_mb);
+        let min = memory(v, "dynamic_memory_min_mb", self.dynamic_memory_min_mb);
+        let max = memory(v, "dynamic_memory_max_mb", self.dynamic_memory_max_mb);
+        if self
+            .cpu_count
+            .is_some_and(|count| ProcessorCount::new(count).is_none())
+        {
+            v.add(
+                "cpu_count",
+                FieldErrorCode::OutOfRange,
+                format!(
+                    "must be between {} and {}",
+                    ProcessorCount::MIN,
+                    ProcessorCount::MAX
+                ),
+            );
+        }
+        if self
+            .memory_buffer_percentage
+            .is_some_and(|percent| MemoryBufferPercent::new(percent).is_none())
+        {
+            v.add(
+                "memory_buffer_percentage",
+                FieldErrorCode::OutOfRange,
+                "must be between 0 and 100",
+            );
+        }
+        if self
+            .automatic_start_delay_secs
+            .is_some_and(|secs| StartupDelay::from_secs(secs).is_none())
+        {
+            v.add(
+                "automatic_start_delay_secs",
+                FieldErrorCode::OutOfRange,
+                format!("must be at most {}", StartupDelay::MAX_SECONDS),
+            );
+        }
+        if let Some(action) = &self.automatic_start_action {
+            if start_action(action).is_none() {
+                v.add(
+                    "automatic_start_action",
+                    FieldErrorCode::InvalidValue,
+                    "must be Nothing, StartIfRunning or AlwaysStart",
+                );
+            }
+        }
+        if let Some(action) = &self.automatic_stop_action {
+            if stop_action(action).is_none() {
+                v.add(
+                    "automatic_stop_action",
+                    FieldErrorCode::InvalidValue,
+                    "must be TurnOff, Save or Shutdown",
+                );
+            }
+        }
+        if let Some(value) = &self.checkpoint_type {
+            if checkpoint_type(value).is_none() {
+                v.add(
+                    "checkpoint_type",
+                    FieldErrorCode::InvalidValue,
+                    "must be Disabled, Production, ProductionOnly or Standard",
+                );
+            }
+        }
+
+        // Only the sizes in the request can be compared here; the backend
+        // checks them again against the VM's current ones
+        if self.dynamic_memory == Some(false) {
+            return;
+        }
+        if let Some(startup) = startup.or(min).or(max).and_then(MemoryMB::new) {
+            let mut settings = VmSettings::builder()
+                .name("update")
+                .generation(Generation::Gen2)
+                .memory(startup)
+                .processors(ProcessorCount::one())
+                .dynamic_memory(true);
+            if let Some(min) = min {
+                settings = settings.dynamic_memory_min_mb(min);
+            }
+            if let Some(max) = max {
+                settings = settings.dynamic_memory_max_mb(max);
+            }
+            v.rule(
+                settings.build().map(drop),
+                &[
+                    ("dynamic_memory_min", "dynamic_memory_min_mb"),
+                    ("dynamic_memory_max", "dynamic_memory_max_mb"),
+                    ("dynamic_memory", "dynamic_memory_min_mb"),
+                ],
+            );
+        }
+    }
+}
+
+/// Automatic start action named as in [`UpdateVmRequest`], case-insensitively
+pub fn start_action(value: &str) -> Option<AutomaticStartAction> {
+    match value.to_ascii_lowercase().as_str() {
+        "nothing" => Some(AutomaticStartAction::Nothing),
+        "startifrunning" => Some(AutomaticStartAction::StartIfRunning),
+        "alwaysstart" => Some(AutomaticStartAction::AlwaysStart),
+        _ => None,
+    }
+}
+
+/// Automatic stop action named as in [`UpdateVmRequest`], case-insensitively
+pub fn stop_action(value: &str) -> Option<AutomaticStopAction> {
+    match value.to_ascii_lowercase().as_str() {
+        "turnoff" => Some(AutomaticStopAction::TurnOff),
+        "save" => Some(AutomaticStopAction::Save),
+        "shutdown" => Some(AutomaticStopAction::Shutdown),
+        _ => None,
+    }
+}
+
+/// Checkpoint type named as in [`UpdateVmRequest`], case-insensitively
+pub fn checkpoint_type(value: &str) -> Option<CheckpointType> {
+    match value.to_ascii_lowercase().as_str() {
+        "disabled" => Some(CheckpointType::Disabled),
+        "production" => Some(CheckpointType::Production),
+        "productiononly" => Some(CheckpointType::ProductionOnly),
+        "standard" => Some(CheckpointType::Standard),
+        _ => None,
+    }
+}
+
+impl Validate for ExportVmRequest {
+    fn validate(&self, v: &mut Violations) {
+        v.required("path", &self.path);
+    }
+}
+
+impl Validate for BootOrderRequest {
+    fn validate(&self, v: &mut Violations) {
+        if self.devices.iter().all(|device| device.trim().is_empty()) {
+            v.add(
+                "devices",
+                FieldErrorCode::Required,
+                "must list at least one device",
+            );
+        }
+    }
+}
+
+impl Validate for BatchVmRequest {
+    fn validate(&self, v: &mut Violations) {
+        match &self.action {
+            BatchAction::Snapshot(request) => {
+                checkpoint_rules(v, &request.name, "parameters.name");
+                snapshot_type(
+                    v,
+                    request.snapshot_type.as_deref(),
+                    "parameters.snapshot_type",
+                );
+            }
+            BatchAction::ApplySnapshot(request) | BatchAction::DeleteSnapshot(request) => {
+                checkpoint_rules(v, &request.name, "parameters.name")
+            }
+            _ => {}
+        }
+    }
+}
+
+// =============================================================================
+// VM Processor and Security
+// =============================================================================
+
+const PROCESSOR_FIELDS: &[(&str, &str)] = &[
+    ("limit", "limit_percent"),
+    ("reservation", "reservation_percent"),
+    ("numa_topology", "numa_nodes"),
+];
+
+impl Validate for SetVmProcessorRequest {
+    fn validate(&self, v: &mut Violations) {
+        // The builder stops at the first problem, so the fields it checks on
+        // their own are tried first and left out of the full build
+        let mut checked = self.clone();
+        if let Some(percent) = self.limit_percent {
+            let result = ProcessorSettings::builder().limit_percent(percent);
+            if result.is_err() {
+                checked.limit_percent = None;
+            }
+            v.rule(result.map(drop), PROCESSOR_FIELDS);
+        }
+        if let Some(percent) = self.reservation_percent {
+            let result = ProcessorSettings::builder().reservation_percent(percent);
+            if result.is_err() {
+                checked.reservation_percent = None;
+            }
+            v.rule(result.map(drop), PROCESSOR_FIELDS);
+        }
+        if self.weight.is_some_and(|w| CpuWeight::new(w).is_none()) {
+            v.add(
+                "weight",
+                FieldErrorCode::OutOfRange,
+                format!("must be between {} and {}", CpuWeight::LOW, CpuWeight::MAX),
+            );
+            checked.weight = None;
+        }
+        if let Some(threads) = self.hw_threads_per_core {
+            let result = HwThreadsPerCore::new(threads);
+            if result.is_err() {
+                checked.hw_threads_per_core = None;
+            }
+            v.rule(result.map(drop), &[]);
+        }
+        if let Some(group) = &self.cpu_group_id {
+            v.required("cpu_group_id", group);
+        }
+        v.rule(processor_settings(&checked).map(drop), PROCESSOR_FIELDS);
+    }
+}
+
+/// `windows_hyperv` processor settings for a [`SetVmProcessorRequest`]
+///
+/// Fails with the builder's own error so [`Violations::rule`] can name the
+/// field.
+pub fn processor_settings(
+    request: &SetVmProcessorRequest,
+) -> windows_hyperv::Result<ProcessorSettings> {
+    let mut builder = ProcessorSettings::builder()
+        .count(request.count)
+        .limit_percent(request.limit_percent.unwrap_or(100.0))?
+        .reservation_percent(request.reservation_percent.unwrap_or(0.0))?
+        .expose_virtualization_extensions(
+            request.expose_virtualization_extensions.unwrap_or(false),
+        );
+    if let Some(weight) = request.weight {
+        let weight = CpuWeight::new(weight).ok_or_else(|| windows_hyperv::Error::Validation {
+            field: "weight",
+            message: format!("Invalid CPU weight: {}", weight),
+        })?;
+        builder = builder.weight(weight);
+    }
+    if let Some(threads) = request.hw_threads_per_core {
+        builder = builder.hw_threads_per_core_value(threads)?;
+    }
+    if let Some(group) = &request.cpu_group_id {
+        builder = builder.cpu_group(group);
+    }
+    if let Some(nodes) = request.numa_nodes.as_deref().filter(|n| !n.is_empty()) {
+        let mut topology = NumaTopology::new();
+        for node in nodes {
+            topology.add_node(NumaNode::new(node.id, node.processor_count, node.memory_mb));
+        }
+        builder = builder.numa_topology(topology);
+    }
+    builder.build()
+}
+
+impl Validate for SetVmSecurityRequest {
+    fn validate(&self, v: &mut Violations) {
+        let mut checked = self.clone();
+        if let Some(template) = &self.secure_boot_template {
+            if secure_boot_template(template).is_none() {
+                v.add(
+                    "secure_boot_template",
+                    FieldErrorCode::InvalidValue,
+                    "must be MicrosoftWindows, MicrosoftUefiCa or OpenSourceShieldedVm",
+                );
+                checked.secure_boot_template = None;
+            }
+        }
+        if let Some(isolation) = &self.guest_isolation_type {
+            if guest_isolation(isolation).is_none() {
+                v.add(
+                    "guest_isolation_type",
+                    FieldErrorCode::InvalidValue,
+                    "must be None, Vbs, Snp or Tdx",
+                );
+                checked.guest_isolation_type = None;
+            }
+        }
+        if let Some(protector) = &self.key_protector {
+            if key_protector(protector).is_none() {
+                v.add(
+                    "key_protector",
+                    FieldErrorCode::InvalidValue,
+                    "must be None, Local or Hgs",
+                );
+                checked.key_protector = None;
+            }
+        }
+        v.rule(security_settings(&checked).map(drop), &[]);
+    }
+}
+
+/// `windows_hyperv` security settings for a [`SetVmSecurityRequest`]
+pub fn security_settings(
+    request: &SetVmSecurityRequest,
+) -> windows_hyperv::Result<SecuritySettings> {
+    let unknown = |field: &'static str, value: &str| windows_hyperv::Error::Validation {
+        field,
+        message: format!("Unknown value: {}", value),
+    };
+    let mut builder = SecuritySettings::builder();
+    if let Some(value) = &request.secure_boot_template {
+        let template =
+            secure_boot_template(value).ok_or_else(|| unknown("secure_boot_template", value))?;
+        builder = builder.secure_boot_template(template);
+    }
+    if let Some(enabled) = request.secure_boot_enabled {
+        builder = builder.secure_boot(enabled);
+    }
+    if let Some(value) = &request.guest_isolation_type {
+        let isolation =
+            guest_isolation(value).ok_or_else(|| unknown("guest_isolation_type", value))?;
+        builder = builder.guest_isolation(isolation);
+    }
+    if let Some(value) = &request.key_protector {
+        let protector = key_protector(value).ok_or_else(|| unknown("key_protector", value))?;
+        builder = builder.key_protector(protector);
+    }
+    builder
+        .tpm(request.tpm_enabled.unwrap_or(false))
+        .encrypt_state_and_migration(request.encrypt_state_and_migration.unwrap_or(false))
+        .shielding(request.shielding_requested.unwrap_or(false))
+        .build()
+}
+
+/// Secure Boot template named as in [`VmSecurityDto`], case-insensitively
+pub fn secure_boot_template(value: &str) -> Option<SecureBootTemplate> {
+    match value.to_ascii_lowercase().as_str() {
+        "microsoftwindows" => Some(SecureBootTemplate::MicrosoftWindows),
+        "microsoftuefica" => Some(SecureBootTemplate::MicrosoftUefiCa),
+        "opensourceshieldedvm" => Some(SecureBootTemplate::OpenSourceShieldedVm),
+        _ => None,
+    }
+}
+
+/// Guest isolation type named as in [`VmSecurityDto`], case-insensitively
+pub fn guest_isolation(value: &str) -> Option<GuestIsolationType> {
+    match value.to_ascii_lowercase().as_str() {
+        "none" => Some(GuestIsolationType::None),
+        "vbs" => Some(GuestIsolationType::Vbs),
+        "snp" => Some(GuestIsolationType::Snp),
+        "tdx" => Some(GuestIsolationType::Tdx),
+        _ => None,
+    }
+}
+
+/// Key protector named as in [`VmSecurityDto`], case-insensitively
+pub fn key_protector(value: &str) -> Option<KeyProtectorType> {
+    match value.to_ascii_lowercase().as_str() {
+        "none" => Some(KeyProtectorType::None),
+        "local" => Some(KeyProtectorType::Local),
+        "hgs" => Some(KeyProtectorType::Hgs),
+        _ => None,
+    }
+}
+
+// =============================================================================
+// Disks and Snapshots
+// =============================================================================
+
+impl Validate for AttachDiskRequest {
+    fn validate(&self, v: &mut Violations) {
+        v.required("vhd_path", &self.vhd_path);
+        v.rule(DiskAttachment::new(&self.vhd_path).validate(), &[]);
+    }
+}
+
+impl Validate for DetachDiskRequest {
+    fn validate(&self, v: &mut Violations) {
+        // The controller type is only known once the disk is found; the SCSI
+        // limits are the wider ones
+        let attachment = DiskAttachment::new("detach")
+            .controller_number(self.controller_number)
+            .controller_location(self.controller_location);
+        v.rule(attachment.validate(), &[]);
+    }
+}
+
+impl Validate for MountIsoRequest {
+    fn validate(&self, v: &mut Violations) {
+        v.rule(IsoAttachment::new(&self.iso_path).validate(), &[]);
+    }
+}
+
+impl Validate for CreateSnapshotRequest {
+    fn validate(&self, v: &mut Violations) {
+        checkpoint_rules(v, &self.name, "name");
+        snapshot_type(v, self.snapshot_type.as_deref(), "snapshot_type");
+    }
+}
+
+impl Validate for SnapshotNameRequest {
+    fn validate(&self, v: &mut Violations) {
+        checkpoint_rules(v, &self.name, "name");
+    }
+}
+
+impl Validate for RenameSnapshotRequest {
+    fn validate(&self, v: &mut Violations) {
+        checkpoint_rules(v, &self.new_name, "new_name");
+    }
+}
+
+impl Validate for ExportSnapshotRequest {
+    fn validate(&self, v: &mut Violations) {
+        v.required("path", &self.path);
+    }
+}
+
+fn checkpoint_rules(v: &mut Violations, name: &str, field: &str) {
+    let settings = CheckpointSettings {
+        name: name.to_string(),
+        notes: None,
+        checkpoint_type: CheckpointType::default(),
+        consistency_level: ConsistencyLevel::default(),
+    };
+    v.rule(settings.validate(), &[("name", field)]);
+}
+
+fn snapshot_type(v: &mut Violations, value: Option<&str>, field: &str) {
+    if !matches!(
+        value,
+        None | Some("Standard" | "Production" | "ProductionOnly")
+    ) {
+        v.add(
+            field,
+            FieldErrorCode::InvalidValue,
+            "must be Standard, Production or ProductionOnly",
+        );
+    }
+}
+
+// =============================================================================
+// Network Adapters
+// =============================================================================
+
+impl Validate for AddVmNetworkAdapterRequest {
+    fn validate(&self, v: &mut Violations) {
+        if let Some(name) = &self.name {
+            v.required("name", name);
+        }
+        if let Some(switch) = &self.switch_name {
+            v.required("switch_name", switch);
+        }
+        if self.dynamic_mac == Some(true) && self.mac_address.is_some() {
+            v.add(
+                "dynamic_mac",
+                FieldErrorCode::Invalid,
+                "must not be true when mac_address is given",
+            );
+        }
+        adapter_rules(
+            v,
+            &UpdateVmNetworkAdapterRequest {
+                mac_address: self.mac_address.clone(),
+                dynamic_mac: self.dynamic_mac,
+                vlan_id: self.vlan_id,
+                mac_spoofing: self.mac_spoofing,
+                dhcp_guard: self.dhcp_guard,
+                router_guard: self.router_guard,
+                port_mirroring: self.port_mirroring.clone(),
+                bandwidth: self.bandwidth.clone(),
+            },
+        );
+    }
+}
+
+impl Validate for UpdateVmNetworkAdapterRequest {
+    fn validate(&self, v: &mut Violations) {
+        if self.dynamic_mac == Some(true) && self.mac_address.is_some() {
+            v.add(
+                "dynamic_mac",
+                FieldErrorCode::Invalid,
+                "must not be true when mac_address is given",
+            );
+        }
+        adapter_rules(v, self);
+    }
+}
+
+impl Validate for ConnectAdapterRequest {
+    fn validate(&self, v: &mut Violations) {
+        v.required("switch_name", &self.switch_name);
+    }
+}
+
+fn adapter_rules(v: &mut Violations, request: &UpdateVmNetworkAdapterRequest) {
+    let mode = match request.port_mirroring.as_deref().map(port_mirroring) {
+        None => PortMirroringMode::None,
+        Some(Some(mode)) => mode,
+        Some(None) => {
+            v.add(
+                "port_mirroring",
+                FieldErrorCode::InvalidValue,
+                "must be None, Source or Destination",
+            );
+            PortMirroringMode::None
+        }
+    };
+    if let Some(bandwidth) = &request.bandwidth {
+        if let (Some(min), Some(max)) = (bandwidth.minimum_mbps, bandwidth.maximum_mbps) {
+            if max > 0 && min > max {
+                v.add(
+                    "bandwidth.minimum_mbps",
+                    FieldErrorCode::Invalid,
+                    "must not exceed bandwidth.maximum_mbps",
+                );
+            }
+        }
+    }
+    let settings = NetworkAdapterSettings {
+        mac_address: request.mac_address.clone(),
+        dynamic_mac: request.mac_address.is_none(),
+        vlan_id: request.vlan_id,
+        port_mirroring: mode,
+        ..NetworkAdapterSettings::default()
+    };
+    v.rule(settings.validate(), &[]);
+}
+
+/// Port mirroring mode named as in [`VmNetworkAdapterDto`], case-insensitively
+pub fn port_mirroring(value: &str) -> Option<PortMirroringMode> {
+    match value.to_ascii_lowercase().as_str() {
+        "none" => Some(PortMirroringMode::None),
+        "source" => Some(PortMirroringMode::Source),
+        "destination" => Some(PortMirroringMode::Destination),
+        _ => None,
+    }
+}
+
+// =============================================================================
+// Switches
+// =============================================================================
+
+impl Validate for CreateSwitchRequest {
+    fn validate(&self, v: &mut Violations) {
+        let switch_type = match self.switch_type.to_ascii_lowercase().as_str() {
+            "external" => SwitchType::External,
+            "internal" => SwitchType::Internal,
+            "private" => SwitchType::Private,
+            _ => {
+                v.add(
+                    "switch_type",
+                    FieldErrorCode::InvalidValue,
+                    "must be External, Internal or Private",
+                );
+                SwitchType::Private
+            }
+        };
+        let settings = VirtualSwitchSettings {
+            name: self.name.clone(),
+            switch_type,
+            notes: None,
+            allow_management_os: self.allow_management_os.unwrap_or(false),
+            external_adapter: self.network_adapter.clone(),
+        };
+        v.rule(
+            settings.validate(),
+            &[("external_adapter", "network_adapter")],
+        );
+    }
+}
+
+// =============================================================================
+// VHDs
+// =============================================================================
+
+/// Settings for a VHD whose format follows the path's extension
+fn vhd_settings(path: &str, disk_type: VhdType, size_bytes: u64) -> VhdSettings {
+    VhdSettings {
+        path: path.to_string(),
+        format: VhdFormat::from_path(path),
+        disk_type,
+        size_bytes,
+        block_size_bytes: None,
+        logical_sector_size: None,
+        physical_sector_size: None,
+        parent_path: None,
+    }
+}
+
+impl Validate for CreateVhdRequest {
+    fn validate(&self, v: &mut Violations) {
+        let disk_type = match self.vhd_type.as_deref() {
+            None | Some("Dynamic") => VhdType::Dynamic,
+            Some("Fixed") => VhdType::Fixed,
+            Some(_) => {
+                v.add(
+                    "vhd_type",
+                    FieldErrorCode::InvalidValue,
+                    "must be Fixed or Dynamic; create differencing disks with POST /api/v1/hyperv/vhds/differencing",
+                );
+                VhdType::Dynamic
+            }
+        };
+        let settings = VhdSettings {
+            block_size_bytes: self.block_size_bytes,
+            ..vhd_settings(&self.path, disk_type, self.size_bytes)
+        };
+        v.rule(settings.validate(), &[]);
+    }
+}
+
+impl Validate for ResizeVhdRequest {
+    fn validate(&self, v: &mut Violations) {
+        v.rule(
+            vhd_settings(&self.path, VhdType::Dynamic, self.size_bytes).validate(),
+            &[],
+        );
+    }
+}
+
+impl Validate for DiffVhdRequest {
+    fn validate(&self, v: &mut Violations) {
+        v.required("parent_path", &self.parent_path);
+        let settings = VhdSettings {
+            parent_path: Some(self.parent_path.clone()),
+            ..vhd_settings(&self.path, VhdType::Differencing, 0)
+        };
+        v.rule(settings.validate(), &[]);
+    }
+}
+
+impl Validate for VhdPathRequest {
+    fn validate(&self, v: &mut Violations) {
+        v.required("path", &self.path);
+    }
+}
+
+impl Validate for InitVhdRequest {
+    fn validate(&self, v: &mut Violations) {
+        v.required("path", &self.path);
+        let lower = |value: &Option<String>| value.as_deref().map(str::to_ascii_lowercase);
+        if !matches!(
+            lower(&self.partition_style).as_deref(),
+            None | Some("gpt" | "mbr")
+        ) {
+            v.add(
+                "partition_style",
+                FieldErrorCode::InvalidValue,
+                "must be Gpt or Mbr",
+            );
+        }
+        if !matches!(
+            lower(&self.file_system).as_deref(),
+            None | Some("ntfs" | "refs" | "fat32" | "exfat")
+        ) {
+            v.add(
+                "file_system",
+                FieldErrorCode::InvalidValue,
+                "must be NTFS, ReFS, FAT32 or ExFAT",
+            );
+        }
+    }
+}
+
+impl Validate for CreateVhdxFromIsoRequest {
+    fn validate(&self, v: &mut Violations) {
+        v.rule(IsoAttachment::new(&self.iso_path).validate(), &[]);
+        let settings = VhdSettings {
+            format: VhdFormat::Vhdx,
+            ..vhd_settings(
+                &self.vhdx_path,
+                VhdType::Dynamic,
+                self.size_gb.saturating_mul(GIB),
+            )
+        };
+        v.rule(
+            settings.validate(),
+            &[("path", "vhdx_path"), ("size_bytes", "size_gb")],
+        );
+    }
+}
+
+// =============================================================================
+// GPUs and Devices
+// =============================================================================
+
+impl Validate for AddGpuRequest {
+    fn validate(&self, v: &mut Violations) {
+        if let Some(path) = &self.instance_path {
+            v.required("instance_path", path);
+        }
+    }
+}
+
+impl Validate for DeviceLocationRequest {
+    fn validate(&self, v: &mut Violations) {
+        v.required("location_path", &self.location_path);
+    }
+}
+
+// =============================================================================
+// Webhooks
+// =============================================================================
+
+impl Validate for CreateWebhookRequest {
+    fn validate(&self, v: &mut Violations) {
+        v.required("name", &self.name);
+        if let Some(problem) = name_error(&self.name) {
+            v.add("name", FieldErrorCode::Invalid, problem);
+        }
+        if let Some(problem) = url_error(&self.url) {
+            v.add("url", FieldErrorCode::Invalid, problem);
+        }
+        v.required("secret", &self.secret);
+        if let Some(vm) = &self.vm {
+            v.required("vm", vm);
+        }
+    }
+}
+
+// =============================================================================
+// Tests
+// =============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn errors<T: Validate>(request: &T) -> Vec<(String, FieldErrorCode)> {
+        let mut v = Violations::default();
+        request.validate(&mut v);
+        v.errors.into_iter().map(|e| (e.field, e.code)).collect()
+    }
+
+    fn create_vm() -> CreateVmRequest {
+        CreateVmRequest {
+            name: "web-01".to_string(),
+            memory_mb: 2048,
+            cpu_count: Some(2),
+            generation: Some(2),
+            vhd_path: r"C:\VMs\web-01.vhdx".to_string(),
+            vhd_size_bytes: 40 * GIB,
+            switch_name: None,
+            version: None,
+        }
+    }
+
+    #[test]
+    fn test_valid_vm_passes() {
+        assert!(errors(&create_vm()).is_empty());
+    }
+
+    #[test]
+    fn test_every_bad_vm_field_is_reported() {
+        let request = CreateVmRequest {
+            name: "web/01".to_string(),
+            memory_mb: 16,
+            cpu_count: Some(0),
+            generation: Some(3),
+            vhd_path: r"C:\VMs\web-01.img".to_string(),
+            vhd_size_bytes: 65 * 1024 * 1024 * GIB,
+            switch_name: Some(String::new()),
+            version: Some("ten".to_string()),
+        };
+        assert_eq!(
+            errors(&request),
+            [
+                ("memory_mb".to_string(), FieldErrorCode::OutOfRange),
+                ("cpu_count".to_string(), FieldErrorCode::OutOfRange),
+                ("generation".to_string(), FieldErrorCode::InvalidValue),
+                ("name".to_string(), FieldErrorCode::Invalid),
+                ("vhd_path".to_string(), FieldErrorCode::Invalid),
+                ("switch_name".to_string(), FieldErrorCode::Required),
+                ("version".to_string(), FieldErrorCode::InvalidValue),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_settings_fields_are_renamed() {
+        let request = CreateSwitchRequest {
+            name: "ext".to_string(),
+            switch_type: "External".to_string(),
+            network_adapter: None,
+            allow_management_os: None,
+        };
+        assert_eq!(
+            errors(&request),
+            [("network_adapter".to_string(), FieldErrorCode::Invalid)]
+        );
+
+        let request = CreateVhdxFromIsoRequest {
+            iso_path: r"D:\iso\server.img".to_string(),
+            vhdx_path: r"C:\VMs\os.vhdx".to_string(),
+            size_gb: 0,
+            edition_index: 1,
+        };
+        assert_eq!(
+            errors(&request),
+            [
+                ("iso_path".to_string(), FieldErrorCode::Invalid),
+                ("size_gb".to_string(), FieldErrorCode::Invalid),
+            ]
+        );
+    }
+}
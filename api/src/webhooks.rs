@@ -0,0 +1,711 @@
+//! Outbound webhook notifications
+//!
+//! Every [`EventDto`] published on the [`EventBus`] is matched against the
+//! webhook subscriptions, from `[webhooks] subscriptions` and from
+//! `POST /api/v1/webhooks`, and queued as one delivery per match. The event
+//! is POSTed as JSON with these headers:
+//! - `X-Webhook-Id`: the delivery id, the same on every attempt
+//! - `X-Webhook-Event`: the event kind, e.g. `vm_created`
+//! - `X-Webhook-Timestamp`: Unix seconds of the attempt
+//! - `X-Webhook-Signature`: `sha256=` and the hex HMAC-SHA256 of
+//!   `<timestamp>.<body>` keyed with the subscription's secret, see [`sign`]
+//!
+//! A 2xx answer delivers the event. Connection failures, timeouts, 408, 429
+//! and 5xx are retried after `initial_backoff_ms`, doubling up to
+//! `max_backoff_ms`. After `max_attempts`, or on any other status, the
+//! delivery is dead-lettered until retried through the API.
+//!
+//! With `state_path` set, API-managed subscriptions and the deliveries
+//! (pending, dead-lettered and recent history) are rewritten to that file
+//! after every change, so queued retries survive a restart.
+
+use std::collections::{HashSet, VecDeque};
+use std::fmt::Write;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use axum::http::{header, StatusCode};
+use chrono::{DateTime, SecondsFormat, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use tokio::sync::{broadcast::error::RecvError, Notify};
+use tokio::task::JoinHandle;
+
+use crate::config::{ConfigError, WebhookConfig, WebhooksConfig};
+use crate::dto::*;
+use crate::events::EventFilter;
+use crate::AppState;
+
+/// Names taken by other routes under `/api/v1/webhooks`
+const RESERVED_NAMES: &[&str] = &["deliveries", "dead-letters"];
+
+const DEFAULT_QUERY_LIMIT: usize = 100;
+const MAX_QUERY_LIMIT: usize = 1000;
+
+/// How long the dispatcher sleeps when nothing is scheduled
+const IDLE_WAIT: Duration = Duration::from_secs(60);
+
+/// Errors returned by [`Webhooks`] operations
+#[derive(Debug, thiserror::Error)]
+pub enum WebhookError {
+    #[error("Webhook '{0}' not found")]
+    NotFound(String),
+
+    #[error("Webhook '{0}' already exists")]
+    Exists(String),
+
+    #[error("Webhook '{0}' is defined in the configuration file")]
+    Configured(String),
+
+    #[error("Delivery {0} not found")]
+    DeliveryNotFound(u64),
+
+    #[error("Delivery {0} is not dead-lettered")]
+    NotDeadLettered(u64),
+
+    #[error("Failed to save webhook state: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// Why `name` cannot name a webhook, if it cannot
+pub(crate) fn name_error(name: &str) -> Option<&'static str> {
+    if name.is_empty() {
+        Some("must not be empty")
+    } else if !name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    {
+        Some("may only contain letters, digits, '-', '_' and '.'")
+    } else if RESERVED_NAMES.contains(&name) {
+        Some("is reserved")
+    } else {
+        None
+    }
+}
+
+/// Why `url` cannot receive webhooks, if it cannot
+pub(crate) fn url_error(url: &str) -> Option<&'static str> {
+    match reqwest::Url::parse(url) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") && url.has_host() => None,
+        Ok(_) => Some("must be an http or https URL"),
+        Err(_) => Some("is not a URL"),
+    }
+}
+
+/// `X-Webhook-Signature` value for a body sent at `timestamp`
+///
+/// Receivers recompute it over the raw body and the `X-Webhook-Timestamp`
+/// header, compare in constant time, and reject old timestamps.
+pub fn sign(secret: &[u8], timestamp: i64, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC accepts any key length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    let mut signature = String::from("sha256=");
+    for byte in mac.finalize().into_bytes() {
+        let _ = write!(signature, "{:02x}", byte);
+    }
+    signature
+}
+
+fn timestamp(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+// =============================================================================
+// Subscriptions
+// =============================================================================
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Subscription {
+    name: String,
+    url: String,
+    secret: String,
+    #[serde(default)]
+    events: Vec<EventKind>,
+    vm: Option<String>,
+}
+
+impl Subscription {
+    fn matches(&self, event: &EventDto) -> bool {
+        EventFilter {
+            vm: self.vm.clone(),
+            kinds: self.events.clone(),
+        }
+        .matches(event)
+    }
+
+    fn dto(&self, source: WebhookSource) -> WebhookDto {
+        WebhookDto {
+            name: self.name.clone(),
+            url: self.url.clone(),
+            events: self.events.clone(),
+            vm: self.vm.clone(),
+            source,
+        }
+    }
+}
+
+impl From<&WebhookConfig> for Subscription {
+    fn from(config: &WebhookConfig) -> Self {
+        Self {
+            name: config.name.clone(),
+            url: config.url.clone(),
+            secret: config.secret.clone(),
+            events: config.events.clone(),
+            vm: config.vm.clone(),
+        }
+    }
+}
+
+// =============================================================================
+// Queue
+// =============================================================================
+
+/// Contents of `state_path`
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Saved {
+    next_id: u64,
+    subscriptions: Vec<Subscription>,
+    deliveries: VecDeque<WebhookDeliveryDto>,
+}
+
+struct Queue {
+    saved: Saved,
+    /// `[webhooks] subscriptions`; never saved
+    configured: Vec<Subscription>,
+    /// Deliveries with an attempt in progress
+    in_flight: HashSet<u64>,
+}
+
+impl Queue {
+    fn subscription(&self, name: &str) -> Option<&Subscription> {
+        self.configured
+            .iter()
+            .chain(&self.saved.subscriptions)
+            .find(|s| s.name == name)
+    }
+
+    /// Subscriptions created through the API, minus those the configuration file shadows
+    fn api_subscriptions(&self) -> impl Iterator<Item = &Subscription> {
+        self.saved
+            .subscriptions
+            .iter()
+            .filter(|s| self.configured.iter().all(|c| c.name != s.name))
+    }
+
+    fn delivery(&mut self, id: u64) -> Option<&mut WebhookDeliveryDto> {
+        self.saved.deliveries.iter_mut().find(|d| d.id == id)
+    }
+
+    /// Dead-letter pending deliveries whose webhook was removed from the
+    /// configuration file; returns how many
+    fn dead_letter_orphans(&mut self) -> usize {
+        let orphans: Vec<u64> = self
+            .saved
+            .deliveries
+            .iter()
+            .filter(|d| d.state == DeliveryState::Pending && !self.in_flight.contains(&d.id))
+            .filter(|d| self.subscription(&d.webhook).is_none())
+            .map(|d| d.id)
+            .collect();
+        let now = timestamp(Utc::now());
+        for id in &orphans {
+            let delivery = self.delivery(*id).expect("orphan was just found");
+            delivery.state = DeliveryState::DeadLettered;
+            delivery.next_attempt_at = None;
+            delivery.last_error = Some("Webhook removed from the configuration".to_string());
+            delivery.updated_at = now.clone();
+            tracing::warn!(
+                "Webhook '{}' delivery {} dead-lettered: webhook removed from the configuration",
+                delivery.webhook,
+                delivery.id
+            );
+        }
+        orphans.len()
+    }
+}
+
+/// Result of one delivery attempt
+enum Outcome {
+    Delivered(u16),
+    Failed {
+        status: Option<u16>,
+        error: String,
+        retryable: bool,
+    },
+}
+
+/// Webhook subscriptions and their delivery queue
+pub struct Webhooks {
+    queue: Mutex<Queue>,
+    client: reqwest::Client,
+    path: Option<PathBuf>,
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    history_limit: usize,
+    wake: Notify,
+    dispatcher: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl Default for Webhooks {
+    fn default() -> Self {
+        Self::new(&WebhooksConfig::default()).expect("default webhook configuration is valid")
+    }
+}
+
+impl Webhooks {
+    /// Load `state_path`, if set and present, and build the HTTP client
+    pub fn new(config: &WebhooksConfig) -> Result<Self, ConfigError> {
+        let path = config.state_path.as_ref().map(PathBuf::from);
+        let saved = match &path {
+            Some(path) if path.exists() => std::fs::read_to_string(path)
+                .map_err(|e| e.to_string())
+                .and_then(|text| serde_json::from_str(&text).map_err(|e| e.to_string()))
+                .map_err(|e| {
+                    ConfigError::Invalid(format!(
+                        "cannot read webhook state '{}': {}",
+                        path.display(),
+                        e
+                    ))
+                })?,
+            _ => Saved::default(),
+        };
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_millis(config.timeout_ms))
+            .build()
+            .map_err(|e| ConfigError::Invalid(format!("cannot create webhook client: {}", e)))?;
+        Ok(Self {
+            queue: Mutex::new(Queue {
+                saved,
+                configured: config
+                    .subscriptions
+                    .iter()
+                    .map(Subscription::from)
+                    .collect(),
+                in_flight: HashSet::new(),
+            }),
+            client,
+            path,
+            max_attempts: config.max_attempts.max(1),
+            initial_backoff: Duration::from_millis(config.initial_backoff_ms),
+            max_backoff: Duration::from_millis(config.max_backoff_ms),
+            history_limit: config.history_limit,
+            wake: Notify::new(),
+            dispatcher: Mutex::new(None),
+        })
+    }
+
+    /// Whether queued deliveries survive a restart
+    pub fn is_durable(&self) -> bool {
+        self.path.is_some()
+    }
+
+    /// Replace the subscriptions from the configuration file
+    pub fn reconfigure(&self, config: &WebhooksConfig) {
+        let mut queue = self.queue.lock().unwrap();
+        queue.configured = config
+            .subscriptions
+            .iter()
+            .map(Subscription::from)
+            .collect();
+        for subscription in &queue.saved.subscriptions {
+            if queue.configured.iter().any(|c| c.name == subscription.name) {
+                tracing::warn!(
+                    "Webhook '{}' is now in the configuration file, which takes precedence",
+                    subscription.name
+                );
+            }
+        }
+        if queue.dead_letter_orphans() > 0 {
+            if let Err(e) = self.save(&queue) {
+                tracing::error!("Failed to save webhook queue: {}", e);
+            }
+        }
+        self.wake.notify_one();
+    }
+
+    pub fn list(&self) -> Vec<WebhookDto> {
+        let queue = self.queue.lock().unwrap();
+        let configured = queue
+            .configured
+            .iter()
+            .map(|s| s.dto(WebhookSource::Config));
+        let api = queue.api_subscriptions().map(|s| s.dto(WebhookSource::Api));
+        configured.chain(api).collect()
+    }
+
+    pub fn get(&self, name: &str) -> Result<WebhookDto, WebhookError> {
+        self.list()
+            .into_iter()
+            .find(|w| w.name == name)
+            .ok_or_else(|| WebhookError::NotFound(name.to_string()))
+    }
+
+    /// Add a subscription; it receives events published from now on
+    pub fn create(&self, request: &CreateWebhookRequest) -> Result<WebhookDto, WebhookError> {
+        let mut queue = self.queue.lock().unwrap();
+        if queue.subscription(&request.name).is_some() {
+            return Err(WebhookError::Exists(request.name.clone()));
+        }
+        let subscription = Subscription {
+            name: request.name.clone(),
+            url: request.url.clone(),
+            secret: request.secret.clone(),
+            events: request.events.clone(),
+            vm: request.vm.clone(),
+        };
+        queue.saved.subscriptions.push(subscription.clone());
+        if let Err(e) = self.save(&queue) {
+            queue.saved.subscriptions.pop();
+            return Err(e.into());
+        }
+        self.wake.notify_one();
+        Ok(subscription.dto(WebhookSource::Api))
+    }
+
+    /// Remove a subscription added through the API, with its pending and
+    /// dead-lettered deliveries
+    pub fn delete(&self, name: &str) -> Result<(), WebhookError> {
+        let mut queue = self.queue.lock().unwrap();
+        if queue.configured.iter().any(|s| s.name == name) {
+            return Err(WebhookError::Configured(name.to_string()));
+        }
+        let Some(index) = queue
+            .saved
+            .subscriptions
+            .iter()
+            .position(|s| s.name == name)
+        else {
+            return Err(WebhookError::NotFound(name.to_string()));
+        };
+        queue.saved.subscriptions.remove(index);
+        queue
+            .saved
+            .deliveries
+            .retain(|d| d.webhook != name || d.state == DeliveryState::Delivered);
+        self.save(&queue)?;
+        Ok(())
+    }
+
+    /// Matching deliveries, newest first
+    pub fn deliveries(&self, query: &WebhookDeliveryQuery) -> Vec<WebhookDeliveryDto> {
+        let limit = query
+            .limit
+            .unwrap_or(DEFAULT_QUERY_LIMIT)
+            .min(MAX_QUERY_LIMIT);
+        let queue = self.queue.lock().unwrap();
+        queue
+            .saved
+            .deliveries
+            .iter()
+            .rev()
+            .filter(|d| query.webhook.as_ref().is_none_or(|w| d.webhook == *w))
+            .filter(|d| query.state.is_none_or(|s| d.state == s))
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    /// Queue a dead-lettered delivery again, with a fresh set of attempts
+    pub fn retry(&self, id: u64) -> Result<WebhookDeliveryDto, WebhookError> {
+        let mut queue = self.queue.lock().unwrap();
+        let delivery = queue
+            .delivery(id)
+            .ok_or(WebhookError::DeliveryNotFound(id))?;
+        if delivery.state != DeliveryState::DeadLettered {
+            return Err(WebhookError::NotDeadLettered(id));
+        }
+        let now = timestamp(Utc::now());
+        delivery.state = DeliveryState::Pending;
+        delivery.attempts = 0;
+        delivery.next_attempt_at = Some(now.clone());
+        delivery.updated_at = now;
+        let delivery = delivery.clone();
+        self.save(&queue)?;
+        self.wake.notify_one();
+        Ok(delivery)
+    }
+
+    /// Queue one delivery per subscription matching `event`
+    pub fn enqueue(&self, event: &EventDto) -> usize {
+        let mut queue = self.queue.lock().unwrap();
+        let names: Vec<String> = queue
+            .configured
+            .iter()
+            .chain(queue.api_subscriptions())
+            .filter(|s| s.matches(event))
+            .map(|s| s.name.clone())
+            .collect();
+        if names.is_empty() {
+            return 0;
+        }
+        let now = timestamp(Utc::now());
+        for webhook in &names {
+            queue.saved.next_id += 1;
+            let id = queue.saved.next_id;
+            queue.saved.deliveries.push_back(WebhookDeliveryDto {
+                id,
+                webhook: webhook.clone(),
+                event: event.clone(),
+                state: DeliveryState::Pending,
+                attempts: 0,
+                last_status: None,
+                last_error: None,
+                next_attempt_at: Some(now.clone()),
+                created_at: now.clone(),
+                updated_at: now.clone(),
+            });
+        }
+        if let Err(e) = self.save(&queue) {
+            tracing::error!("Failed to save webhook queue: {}", e);
+        }
+        self.wake.notify_one();
+        names.len()
+    }
+
+    /// Write the API-managed subscriptions and deliveries to `state_path`
+    fn save(&self, queue: &Queue) -> std::io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_vec(&queue.saved).expect("webhook state serializes");
+        let temp = path.with_extension("tmp");
+        std::fs::write(&temp, json)?;
+        std::fs::rename(&temp, path)
+    }
+
+    // =========================================================================
+    // Dispatcher
+    // =========================================================================
+
+    /// Start delivering; does nothing if already started
+    ///
+    /// Also starts the event watcher once there is a subscription. The
+    /// dispatcher stops when the event bus is closed.
+    pub fn start(self: &Arc<Self>, state: &AppState) {
+        let mut dispatcher = self.dispatcher.lock().unwrap();
+        if dispatcher.is_some() {
+            return;
+        }
+        let (_, mut events) = state.events.subscribe(None);
+        let mut closed = state.events.closed();
+        let bus = Arc::downgrade(&state.events);
+        let (hyperv, cluster) = (state.hyperv.clone(), state.cluster.clone());
+        let webhooks = self.clone();
+        *dispatcher = Some(tokio::spawn(async move {
+            loop {
+                if webhooks.has_subscriptions() {
+                    if let Some(bus) = bus.upgrade() {
+                        bus.start_watcher(hyperv.clone(), cluster.clone());
+                    }
+                }
+                let wait = webhooks.dispatch_due().map_or(IDLE_WAIT, |due| {
+                    (due - Utc::now()).to_std().unwrap_or_default()
+                });
+                tokio::select! {
+                    received = events.recv() => match received {
+                        Ok(event) => {
+                            webhooks.enqueue(&event);
+                        }
+                        Err(RecvError::Lagged(missed)) => {
+                            tracing::warn!("Webhook dispatcher lagged behind; {} events dropped", missed);
+                        }
+                        Err(RecvError::Closed) => break,
+                    },
+                    _ = webhooks.wake.notified() => {}
+                    _ = tokio::time::sleep(wait) => {}
+                    _ = closed.wait_for(|closed| *closed) => break,
+                }
+            }
+        }));
+    }
+
+    fn has_subscriptions(&self) -> bool {
+        let queue = self.queue.lock().unwrap();
+        !queue.configured.is_empty() || !queue.saved.subscriptions.is_empty()
+    }
+
+    /// Start an attempt for every due delivery; returns when the next one is due
+    fn dispatch_due(self: &Arc<Self>) -> Option<DateTime<Utc>> {
+        let now = Utc::now();
+        let mut queue = self.queue.lock().unwrap();
+        // Left over from a previous configuration or a retry
+        if queue.dead_letter_orphans() > 0 {
+            if let Err(e) = self.save(&queue) {
+                tracing::error!("Failed to save webhook queue: {}", e);
+            }
+        }
+        let mut next: Option<DateTime<Utc>> = None;
+        let mut due = Vec::new();
+        for delivery in &queue.saved.deliveries {
+            if delivery.state != DeliveryState::Pending || queue.in_flight.contains(&delivery.id) {
+                continue;
+            }
+            let at = delivery
+                .next_attempt_at
+                .as_deref()
+                .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+                .map(|t| t.with_timezone(&Utc));
+            match at {
+                Some(at) if at > now => next = Some(next.map_or(at, |next| next.min(at))),
+                _ => due.push(delivery.clone()),
+            }
+        }
+        for delivery in due {
+            let Some(subscription) = queue.subscription(&delivery.webhook).cloned() else {
+                continue;
+            };
+            queue.in_flight.insert(delivery.id);
+            tokio::spawn(self.clone().attempt(delivery, subscription));
+        }
+        next
+    }
+
+    async fn attempt(self: Arc<Self>, delivery: WebhookDeliveryDto, subscription: Subscription) {
+        let body = serde_json::to_vec(&delivery.event).expect("EventDto serializes to JSON");
+        let sent_at = Utc::now().timestamp();
+        let response = self
+            .client
+            .post(&subscription.url)
+            .header(header::CONTENT_TYPE, "application/json")
+            .header("x-webhook-id", delivery.id.to_string())
+            .header("x-webhook-event", delivery.event.kind.as_str())
+            .header("x-webhook-timestamp", sent_at.to_string())
+            .header(
+                "x-webhook-signature",
+                sign(subscription.secret.as_bytes(), sent_at, &body),
+            )
+            .body(body)
+            .send()
+            .await;
+        let outcome = match response {
+            Ok(response) if response.status().is_success() => {
+                Outcome::Delivered(response.status().as_u16())
+            }
+            Ok(response) => {
+                let status = response.status();
+                Outcome::Failed {
+                    status: Some(status.as_u16()),
+                    error: format!("Receiver answered {}", status),
+                    retryable: status.is_server_error()
+                        || matches!(
+                            status,
+                            StatusCode::REQUEST_TIMEOUT | StatusCode::TOO_MANY_REQUESTS
+                        ),
+                }
+            }
+            Err(e) => Outcome::Failed {
+                status: None,
+                error: e.to_string(),
+                retryable: true,
+            },
+        };
+        self.finish(delivery.id, outcome);
+    }
+
+    /// Record an attempt and schedule the retry or dead-letter the delivery
+    fn finish(&self, id: u64, outcome: Outcome) {
+        let mut queue = self.queue.lock().unwrap();
+        queue.in_flight.remove(&id);
+        let now = Utc::now();
+        let max_attempts = self.max_attempts;
+        let backoff = |attempts: u32| {
+            self.initial_backoff
+                .saturating_mul(1 << (attempts - 1).min(20))
+                .min(self.max_backoff)
+        };
+        // Gone if its webhook was deleted meanwhile
+        let Some(delivery) = queue.delivery(id) else {
+            return;
+        };
+        delivery.attempts += 1;
+        delivery.updated_at = timestamp(now);
+        delivery.next_attempt_at = None;
+        match outcome {
+            Outcome::Delivered(status) => {
+                delivery.state = DeliveryState::Delivered;
+                delivery.last_status = Some(status);
+                delivery.last_error = None;
+            }
+            Outcome::Failed {
+                status,
+                error,
+                retryable,
+            } => {
+                delivery.last_status = status;
+                if retryable && delivery.attempts < max_attempts {
+                    let at = now + backoff(delivery.attempts);
+                    delivery.next_attempt_at = Some(timestamp(at));
+                } else {
+                    delivery.state = DeliveryState::DeadLettered;
+                    tracing::warn!(
+                        "Webhook '{}' delivery {} dead-lettered after {} attempt(s): {}",
+                        delivery.webhook,
+                        delivery.id,
+                        delivery.attempts,
+                        error
+                    );
+                }
+                delivery.last_error = Some(error);
+            }
+        }
+
+        // Keep pending deliveries and the newest finished ones
+        let finished = queue
+            .saved
+            .deliveries
+            .iter()
+            .filter(|d| d.state != DeliveryState::Pending)
+            .count();
+        let mut excess = finished.saturating_sub(self.history_limit);
+        queue.saved.deliveries.retain(|d| {
+            let drop = excess > 0 && d.state != DeliveryState::Pending;
+            excess -= usize::from(drop);
+            !drop
+        });
+        if let Err(e) = self.save(&queue) {
+            tracing::error!("Failed to save webhook queue: {}", e);
+        }
+        self.wake.notify_one();
+    }
+}
+
+// =============================================================================
+// Tests
+// =============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign() {
+        let signature = sign(b"key", 1_700_000_000, b"{}");
+        assert_eq!(
+            signature,
+            "sha256=9d713ed406bb7076d4123f0dc2c39d2df5c654ed4b0cd56b52c8b4c940bd63ae"
+        );
+        assert_ne!(signature, sign(b"key", 1_700_000_001, b"{}"));
+        assert_ne!(signature, sign(b"other", 1_700_000_000, b"{}"));
+    }
+
+    #[test]
+    fn test_names_and_urls() {
+        assert_eq!(name_error("cmdb"), None);
+        assert!(name_error("").is_some());
+        assert!(name_error("a/b").is_some());
+        assert!(name_error("deliveries").is_some());
+
+        assert_eq!(url_error("https://cmdb.example/hooks"), None);
+        assert_eq!(url_error("http://127.0.0.1:9000/"), None);
+        assert!(url_error("ftp://cmdb.example").is_some());
+        assert!(url_error("cmdb.example").is_some());
+    }
+}
@@ -0,0 +1,362 @@
+//! Integration tests for aggregator mode, against node agents served on
+//! local ports
+
+use std::future::IntoFuture;
+use std::sync::Arc;
+
+use axum::{http::StatusCode, Router};
+use serde_json::{json, Value};
+
+use api::config::{AggregatorConfig, ApiKeyConfig, AuthConfig, PeerConfig};
+use api::{create_router, AppState, Config, CreateVmRequest, Role};
+
+mod common;
+use common::{fake_config, send, send_with};
+
+/// A node agent with the fake backend, serving on a local port
+struct Node {
+    name: &'static str,
+    url: String,
+    state: Arc<AppState>,
+}
+
+impl Node {
+    async fn start(name: &'static str, vms: &[&str]) -> Self {
+        Self::start_with(name, vms, fake_config()).await
+    }
+
+    async fn start_with(name: &'static str, vms: &[&str], config: Config) -> Self {
+        let state = Arc::new(AppState::from_config(&config).unwrap());
+        for vm in vms {
+            create_vm(&state, vm);
+        }
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(axum::serve(listener, create_router(state.clone())).into_future());
+        Node { name, url, state }
+    }
+
+    fn peer(&self) -> PeerConfig {
+        PeerConfig {
+            name: self.name.to_string(),
+            url: self.url.clone(),
+        }
+    }
+}
+
+fn create_vm(state: &AppState, name: &str) {
+    state
+        .hyperv
+        .create_vm(&CreateVmRequest {
+            name: name.to_string(),
+            memory_mb: 2048,
+            cpu_count: Some(2),
+            generation: Some(2),
+            vhd_path: format!(r"C:\VMs\{}.vhdx", name),
+            vhd_size_bytes: 1 << 30,
+            switch_name: None,
+            version: None,
+        })
+        .unwrap();
+}
+
+fn aggregator(peers: Vec<PeerConfig>) -> Router {
+    let mut config = fake_config();
+    config.aggregator = AggregatorConfig {
+        enabled: true,
+        peers,
+        timeout_ms: 500,
+        ..AggregatorConfig::default()
+    };
+    create_router(Arc::new(AppState::from_config(&config).unwrap()))
+}
+
+fn names(body: &Value) -> Vec<(String, String)> {
+    body["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|vm| {
+            (
+                vm["name"].as_str().unwrap().to_string(),
+                vm["node"].as_str().unwrap().to_string(),
+            )
+        })
+        .collect()
+}
+
+fn pairs(expected: &[(&str, &str)]) -> Vec<(String, String)> {
+    expected
+        .iter()
+        .map(|(vm, node)| (vm.to_string(), node.to_string()))
+        .collect()
+}
+
+#[tokio::test]
+async fn test_lists_are_merged_and_tagged_with_node() {
+    let node1 = Node::start("NODE1", &["web-01", "web-02"]).await;
+    let node2 = Node::start("NODE2", &["sql-01"]).await;
+    let app = aggregator(vec![node1.peer(), node2.peer()]);
+
+    let (status, body) = send(&app, "GET", "/api/v1/hyperv/vms?sort=name:desc", None).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(
+        names(&body),
+        pairs(&[
+            ("web-02", "NODE1"),
+            ("web-01", "NODE1"),
+            ("sql-01", "NODE2")
+        ])
+    );
+    assert_eq!(body["total"], 3);
+    assert!(body.get("node_errors").is_none());
+
+    // Paging runs over the merged list
+    let (_, first) = send(&app, "GET", "/api/v1/hyperv/vms?sort=name&limit=2", None).await;
+    assert_eq!(
+        names(&first),
+        pairs(&[("sql-01", "NODE2"), ("web-01", "NODE1")])
+    );
+    let cursor = first["next_cursor"].as_str().unwrap();
+    let uri = format!("/api/v1/hyperv/vms?sort=name&limit=2&cursor={}", cursor);
+    let (_, second) = send(&app, "GET", &uri, None).await;
+    assert_eq!(names(&second), pairs(&[("web-02", "NODE1")]));
+    assert!(second.get("next_cursor").is_none());
+
+    // Filters are applied by the nodes, `node` narrows the fan-out
+    let (_, body) = send(&app, "GET", "/api/v1/hyperv/vms?name=*-01", None).await;
+    assert_eq!(
+        names(&body),
+        pairs(&[("web-01", "NODE1"), ("sql-01", "NODE2")])
+    );
+    let (_, body) = send(&app, "GET", "/api/v1/hyperv/vms?node=node2", None).await;
+    assert_eq!(names(&body), pairs(&[("sql-01", "NODE2")]));
+    let (status, body) = send(&app, "GET", "/api/v1/hyperv/vms?node=NODE9", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["code"], "node_not_found");
+
+    // A filter every node rejects is the nodes' answer
+    let (status, body) = send(&app, "GET", "/api/v1/hyperv/vms?colour=red", None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{}", body);
+
+    // Reads that are not lists return one entry per node
+    let (status, body) = send(&app, "GET", "/api/v1/hyperv/host", None).await;
+    assert_eq!(status, StatusCode::OK);
+    let hosts = body["data"].as_array().unwrap();
+    assert_eq!(hosts.len(), 2);
+    assert_eq!(hosts[0]["node"], "NODE1");
+    assert_eq!(hosts[1]["node"], "NODE2");
+}
+
+#[tokio::test]
+async fn test_vm_requests_go_to_the_owning_node() {
+    let node1 = Node::start("NODE1", &["web-01"]).await;
+    let node2 = Node::start("NODE2", &["sql-01"]).await;
+    let app = aggregator(vec![node1.peer(), node2.peer()]);
+
+    let (status, body) = send(&app, "GET", "/api/v1/hyperv/vms/sql-01", None).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["data"]["name"], "sql-01");
+    assert_eq!(body["data"]["node"], "NODE2");
+
+    let (status, body) = send(&app, "POST", "/api/v1/hyperv/vms/sql-01/start", None).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(
+        node2.state.hyperv.get_vm("sql-01").unwrap().state,
+        "Running"
+    );
+    assert_eq!(node1.state.hyperv.get_vm("web-01").unwrap().state, "Off");
+
+    // Jobs live on the node that runs them
+    let uri = "/api/v1/hyperv/vms/sql-01/export";
+    let (status, body) = send(&app, "POST", uri, Some(json!({"path": r"D:\Exports"}))).await;
+    assert_eq!(status, StatusCode::ACCEPTED, "{}", body);
+    assert_eq!(body["data"]["node"], "NODE2");
+    let uri = format!("/api/v1/jobs/{}/wait?node=NODE2", body["data"]["id"]);
+    let (status, body) = send(&app, "GET", &uri, None).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["data"]["node"], "NODE2");
+    let (_, body) = send(&app, "GET", "/api/v1/jobs", None).await;
+    assert_eq!(body["data"].as_array().unwrap().len(), 1);
+    assert_eq!(body["data"][0]["node"], "NODE2");
+
+    let (status, body) = send(&app, "GET", "/api/v1/hyperv/vms/db-09", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["code"], "vm_not_found");
+
+    // A VM that moved is looked up again
+    node2.state.hyperv.force_stop_vm("sql-01").unwrap();
+    node2.state.hyperv.delete_vm("sql-01").unwrap();
+    create_vm(&node1.state, "sql-01");
+    let (status, body) = send(&app, "GET", "/api/v1/hyperv/vms/sql-01", None).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["data"]["node"], "NODE1");
+
+    // The same name on two nodes needs `node`
+    create_vm(&node2.state, "web-01");
+    let (status, body) = send(&app, "POST", "/api/v1/hyperv/vms/web-01/stop", None).await;
+    assert_eq!(status, StatusCode::CONFLICT, "{}", body);
+    let uri = "/api/v1/hyperv/vms/web-01?node=NODE2";
+    let (status, body) = send(&app, "GET", uri, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["data"]["node"], "NODE2");
+}
+
+#[tokio::test]
+async fn test_other_requests_name_their_node() {
+    let node1 = Node::start("NODE1", &[]).await;
+    let node2 = Node::start("NODE2", &[]).await;
+    let app = aggregator(vec![node1.peer(), node2.peer()]);
+    let switch = json!({"name": "lab", "switch_type": "Internal"});
+
+    let (status, body) = send(
+        &app,
+        "POST",
+        "/api/v1/hyperv/switches",
+        Some(switch.clone()),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{}", body);
+
+    let uri = "/api/v1/hyperv/switches?node=NODE2";
+    let (status, body) = send(&app, "POST", uri, Some(switch)).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["data"]["node"], "NODE2");
+    assert_eq!(node2.state.hyperv.list_switches().unwrap().len(), 1);
+    assert!(node1.state.hyperv.list_switches().unwrap().is_empty());
+
+    // Nodes without the object are left out
+    let (status, body) = send(&app, "GET", "/api/v1/hyperv/switches/lab", None).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["data"].as_array().unwrap().len(), 1);
+    assert_eq!(body["data"][0]["node"], "NODE2");
+    let (status, _) = send(&app, "GET", "/api/v1/hyperv/switches/wan", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, _) = send(&app, "GET", "/api/v1/jobs/1", None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_failed_nodes_are_reported_not_fatal() {
+    let node1 = Node::start("NODE1", &["web-01"]).await;
+    let closed = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let closed_url = format!("http://{}", closed.local_addr().unwrap());
+    drop(closed);
+    // Accepts connections and never answers
+    let silent = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let silent_url = format!("http://{}", silent.local_addr().unwrap());
+    tokio::spawn(async move {
+        let mut open = Vec::new();
+        while let Ok((socket, _)) = silent.accept().await {
+            open.push(socket);
+        }
+    });
+    let down = |name: &str, url: &str| PeerConfig {
+        name: name.to_string(),
+        url: url.to_string(),
+    };
+    let app = aggregator(vec![
+        node1.peer(),
+        down("NODE2", &closed_url),
+        down("NODE3", &silent_url),
+    ]);
+
+    let (status, body) = send(&app, "GET", "/api/v1/hyperv/vms", None).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(names(&body), pairs(&[("web-01", "NODE1")]));
+    let errors = body["node_errors"].as_array().unwrap();
+    assert_eq!(errors.len(), 2);
+    assert_eq!(errors[0]["node"], "NODE2");
+    assert!(errors[0]["status"].is_null());
+    assert_eq!(errors[1]["node"], "NODE3");
+    assert_eq!(errors[1]["error"], "Timed out after 500 ms");
+
+    // The owner answers even while other nodes are down
+    let (status, body) = send(&app, "POST", "/api/v1/hyperv/vms/web-01/start", None).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    let (status, body) = send(&app, "GET", "/api/v1/hyperv/vms/db-09", None).await;
+    assert_eq!(status, StatusCode::BAD_GATEWAY, "{}", body);
+    assert_eq!(body["node_errors"].as_array().unwrap().len(), 2);
+
+    let app = aggregator(vec![down("NODE2", &closed_url)]);
+    let (status, body) = send(&app, "GET", "/api/v1/hyperv/vms", None).await;
+    assert_eq!(status, StatusCode::BAD_GATEWAY);
+    assert_eq!(body["code"], "backend_unavailable");
+    assert_eq!(body["node_errors"][0]["node"], "NODE2");
+}
+
+#[tokio::test]
+async fn test_roles_are_checked_before_forwarding() {
+    let key = |name: &str, role: Role| ApiKeyConfig {
+        name: name.to_string(),
+        key: format!("{}-key", name),
+        role,
+    };
+    let mut node_config = fake_config();
+    node_config.auth = AuthConfig {
+        enabled: true,
+        api_keys: vec![key("aggregator", Role::Admin)],
+        ..AuthConfig::default()
+    };
+    let node = Node::start_with("NODE1", &["web-01"], node_config).await;
+
+    let mut config = fake_config();
+    config.auth = AuthConfig {
+        enabled: true,
+        api_keys: vec![key("reader", Role::Reader)],
+        ..AuthConfig::default()
+    };
+    config.aggregator = AggregatorConfig {
+        enabled: true,
+        peers: vec![node.peer()],
+        api_key: Some("aggregator-key".to_string()),
+        ..AggregatorConfig::default()
+    };
+    let app = create_router(Arc::new(AppState::from_config(&config).unwrap()));
+
+    let uri = "/api/v1/hyperv/vms";
+    let (status, _) = send(&app, "GET", uri, None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, body) = send_with(&app, "GET", uri, &[("x-api-key", "reader-key")], None).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(names(&body), pairs(&[("web-01", "NODE1")]));
+
+    let uri = "/api/v1/hyperv/vms/web-01/start";
+    let (status, _) = send_with(&app, "POST", uri, &[("x-api-key", "reader-key")], None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(node.state.hyperv.get_vm("web-01").unwrap().state, "Off");
+}
+
+#[tokio::test]
+async fn test_peers_are_discovered_from_cluster_nodes() {
+    // One server standing in for both agents of the fake two-node cluster
+    let node1 = Arc::new(AppState::from_config(&fake_config()).unwrap());
+    let node2 = Arc::new(AppState::from_config(&fake_config()).unwrap());
+    create_vm(&node1, "web-01");
+    create_vm(&node2, "sql-01");
+    let agents = Router::new()
+        .nest("/NODE1", create_router(node1))
+        .nest("/NODE2", create_router(node2));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let node_url = format!("http://{}/{{node}}", listener.local_addr().unwrap());
+    tokio::spawn(axum::serve(listener, agents).into_future());
+
+    let mut config = fake_config();
+    config.aggregator = AggregatorConfig {
+        enabled: true,
+        discover: true,
+        node_url,
+        ..AggregatorConfig::default()
+    };
+    config.validate().unwrap();
+    let app = create_router(Arc::new(AppState::from_config(&config).unwrap()));
+
+    let (status, body) = send(&app, "GET", "/api/v1/hyperv/vms?sort=name", None).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(
+        names(&body),
+        pairs(&[("sql-01", "NODE2"), ("web-01", "NODE1")])
+    );
+}
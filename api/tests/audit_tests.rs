@@ -0,0 +1,167 @@
+//! Integration tests for the audit log of mutating calls
+
+use axum::{http::StatusCode, Router};
+use serde_json::{json, Value};
+
+use api::config::{ApiKeyConfig, AuditSink, AuthConfig};
+use api::{ApiResponse, AuditRecordDto, Config, Role};
+
+mod common;
+use common::{create_app, fake_config, send_with, vm_request};
+
+fn auth_config() -> Config {
+    let mut config = fake_config();
+    config.auth = AuthConfig {
+        enabled: true,
+        api_keys: [
+            ("reader", Role::Reader),
+            ("alice", Role::Operator),
+            ("bob", Role::Operator),
+            ("admin", Role::Admin),
+        ]
+        .into_iter()
+        .map(|(name, role)| ApiKeyConfig {
+            name: name.to_string(),
+            key: format!("{}-key", name),
+            role,
+        })
+        .collect(),
+        ..AuthConfig::default()
+    };
+    config
+}
+
+async fn send(app: &Router, key: &str, method: &str, uri: &str, body: Option<Value>) -> StatusCode {
+    let key = format!("{}-key", key);
+    send_with(app, method, uri, &[("x-api-key", &key)], body)
+        .await
+        .0
+}
+
+async fn audit(app: &Router, query: &str) -> Vec<AuditRecordDto> {
+    let uri = format!("/api/v1/audit{}", query);
+    let (status, body) = send_with(app, "GET", &uri, &[("x-api-key", "admin-key")], None).await;
+    assert_eq!(status, StatusCode::OK);
+    let response: ApiResponse<Vec<AuditRecordDto>> = serde_json::from_value(body).unwrap();
+    response.data.unwrap()
+}
+
+/// [`vm_request`] with a secret the audit log must redact
+fn create_vm(name: &str) -> Value {
+    let mut request = vm_request(name);
+    request["admin_password"] = json!("hunter2");
+    request
+}
+
+#[tokio::test]
+async fn test_mutating_calls_are_recorded() {
+    let app = create_app(&auth_config());
+
+    let status = send(
+        &app,
+        "alice",
+        "POST",
+        "/api/v1/hyperv/vms",
+        Some(create_vm("web01")),
+    )
+    .await;
+    assert!(status.is_success());
+    send(
+        &app,
+        "alice",
+        "POST",
+        "/api/v1/hyperv/vms/web01/start",
+        None,
+    )
+    .await;
+    send(
+        &app,
+        "bob",
+        "POST",
+        "/api/v1/hyperv/vms/web01/force-stop",
+        None,
+    )
+    .await;
+    send(&app, "alice", "GET", "/api/v1/hyperv/vms/web01", None).await;
+    let status = send(&app, "bob", "DELETE", "/api/v1/hyperv/vms/web01", None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let records = audit(&app, "").await;
+    assert_eq!(records.len(), 4, "GET requests are not audited");
+
+    let delete = &records[0];
+    assert_eq!(delete.method, "DELETE");
+    assert_eq!(delete.status, 403);
+    assert_eq!(delete.caller.as_deref(), Some("bob"));
+
+    let force_stop = &records[1];
+    assert_eq!(force_stop.route, "/api/v1/hyperv/vms/{name}/force-stop");
+    assert_eq!(force_stop.path, "/api/v1/hyperv/vms/web01/force-stop");
+    assert_eq!(force_stop.path_params["name"], "web01");
+    assert_eq!(force_stop.vm_name.as_deref(), Some("web01"));
+    assert_eq!(force_stop.role.as_deref(), Some("operator"));
+    assert_eq!(force_stop.auth_method.as_deref(), Some("api_key"));
+    assert_eq!(force_stop.status, 200);
+
+    let create = &records[3];
+    assert_eq!(create.vm_name.as_deref(), Some("web01"));
+    let body = create.request_body.as_ref().unwrap();
+    assert_eq!(body["memory_mb"], 1024);
+    assert_eq!(body["admin_password"], "***");
+}
+
+#[tokio::test]
+async fn test_audit_query_filters() {
+    let app = create_app(&auth_config());
+    for (caller, vm) in [("alice", "web01"), ("bob", "web02"), ("bob", "web01")] {
+        send(
+            &app,
+            caller,
+            "POST",
+            "/api/v1/hyperv/vms",
+            Some(create_vm(vm)),
+        )
+        .await;
+    }
+
+    assert_eq!(audit(&app, "?vm=web01").await.len(), 2);
+    assert_eq!(audit(&app, "?caller=bob").await.len(), 2);
+    assert_eq!(audit(&app, "?caller=bob&vm=web02").await.len(), 1);
+    assert_eq!(audit(&app, "?limit=1").await.len(), 1);
+    assert!(audit(&app, "?since=2999-01-01T00:00:00Z").await.is_empty());
+    assert_eq!(audit(&app, "?until=2999-01-01T00:00:00Z").await.len(), 3);
+
+    let status = send(&app, "admin", "GET", "/api/v1/audit?since=yesterday", None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let status = send(&app, "alice", "GET", "/api/v1/audit", None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_file_sink() {
+    let dir = std::env::temp_dir().join(format!("api-audit-it-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let mut config = auth_config();
+    config.audit.sink = AuditSink::File;
+    config.audit.path = dir.join("audit.jsonl").display().to_string();
+
+    let app = create_app(&config);
+    send(
+        &app,
+        "alice",
+        "POST",
+        "/api/v1/hyperv/vms",
+        Some(create_vm("web01")),
+    )
+    .await;
+
+    let content = std::fs::read_to_string(dir.join("audit.jsonl")).unwrap();
+    let record: AuditRecordDto = serde_json::from_str(content.lines().next().unwrap()).unwrap();
+    assert_eq!(record.caller.as_deref(), Some("alice"));
+
+    // Records survive a restart
+    let app = create_app(&config);
+    assert_eq!(audit(&app, "?caller=alice").await.len(), 1);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
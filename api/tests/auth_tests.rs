@@ -0,0 +1,211 @@
+//! Integration tests for authentication and role-based authorization
+
+use axum::{
+    body::Body,
+    http::{header, Request, StatusCode},
+    Router,
+};
+use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+use serde_json::{json, Value};
+use tower::ServiceExt;
+
+use api::config::{ApiKeyConfig, AuthConfig, BackendKind, JwtAlgorithm, JwtConfig};
+use api::{create_router, ApiResponse, AppState, Config, Role};
+use std::sync::Arc;
+
+const SECRET: &str = "integration-secret";
+
+fn fixture(name: &str) -> String {
+    format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name)
+}
+
+fn api_key(name: &str, role: Role) -> ApiKeyConfig {
+    ApiKeyConfig {
+        name: name.to_string(),
+        key: format!("{}-key", name),
+        role,
+    }
+}
+
+fn create_app(jwt: JwtConfig) -> Router {
+    let mut config = Config::default();
+    config.backend.kind = BackendKind::Fake;
+    config.auth = AuthConfig {
+        enabled: true,
+        api_keys: vec![
+            api_key("reader", Role::Reader),
+            api_key("operator", Role::Operator),
+            api_key("admin", Role::Admin),
+        ],
+        jwt: Some(jwt),
+        client_certs: Vec::new(),
+    };
+    create_router(Arc::new(AppState::from_config(&config).unwrap()))
+}
+
+fn create_hs256_app() -> Router {
+    create_app(JwtConfig {
+        algorithm: JwtAlgorithm::HS256,
+        secret: Some(SECRET.to_string()),
+        audience: Some("nodeagent".to_string()),
+        ..JwtConfig::default()
+    })
+}
+
+fn claims(role: &str) -> Value {
+    json!({
+        "sub": "jwt-user",
+        "role": role,
+        "aud": "nodeagent",
+        "exp": chrono::Utc::now().timestamp() + 300,
+    })
+}
+
+async fn send(
+    app: &Router,
+    method: &str,
+    uri: &str,
+    auth: Option<(&str, String)>,
+) -> (StatusCode, Option<String>, ApiResponse<Value>) {
+    let mut request = Request::builder().method(method).uri(uri);
+    if let Some((name, value)) = auth {
+        request = request.header(name, value);
+    }
+    let request = request
+        .header("content-type", "application/json")
+        .body(Body::from(
+            json!({
+                "name": "vm01",
+                "memory_mb": 1024,
+                "vhd_path": r"C:\VMs\vm01.vhdx",
+                "vhd_size_bytes": 1024,
+            })
+            .to_string(),
+        ))
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let challenge = response
+        .headers()
+        .get(header::WWW_AUTHENTICATE)
+        .map(|v| v.to_str().unwrap().to_string());
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, challenge, serde_json::from_slice(&body).unwrap())
+}
+
+fn key(name: &str) -> Option<(&'static str, String)> {
+    Some(("x-api-key", format!("{}-key", name)))
+}
+
+fn bearer(token: String) -> Option<(&'static str, String)> {
+    Some(("authorization", format!("Bearer {}", token)))
+}
+
+#[tokio::test]
+async fn test_missing_credentials_returns_401() {
+    let app = create_hs256_app();
+
+    let (status, challenge, body) = send(&app, "GET", "/api/v1/hyperv/vms", None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert!(challenge.unwrap().starts_with("Bearer"));
+    assert!(!body.success);
+    assert!(body.error.is_some());
+
+    // Public endpoints stay reachable
+    let (status, _, _) = send(&app, "GET", "/health", None).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_invalid_api_key_returns_401() {
+    let app = create_hs256_app();
+
+    let (status, _, body) = send(&app, "GET", "/api/v1/hyperv/vms", key("unknown")).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(body.error.as_deref(), Some("Invalid API key"));
+}
+
+#[tokio::test]
+async fn test_roles_are_enforced() {
+    let app = create_hs256_app();
+
+    let (status, _, _) = send(&app, "GET", "/api/v1/hyperv/vms", key("reader")).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _, body) = send(&app, "POST", "/api/v1/hyperv/vms", key("reader")).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert!(body.error.unwrap().contains("operator"));
+
+    let (status, _, _) = send(&app, "POST", "/api/v1/hyperv/vms", key("operator")).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _, _) = send(&app, "DELETE", "/api/v1/hyperv/vms/vm01", key("operator")).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, _, _) = send(&app, "DELETE", "/api/v1/hyperv/vms/vm01", key("admin")).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _, _) = send(
+        &app,
+        "POST",
+        "/api/v1/cluster/csv/Cluster%20Disk%201/maintenance",
+        key("operator"),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_hs256_bearer_token() {
+    let app = create_hs256_app();
+    let encoding = EncodingKey::from_secret(SECRET.as_bytes());
+
+    let token = encode(&Header::default(), &claims("operator"), &encoding).unwrap();
+    let (status, _, _) = send(&app, "POST", "/api/v1/hyperv/vms", bearer(token)).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let token = encode(&Header::default(), &claims("reader"), &encoding).unwrap();
+    let (status, _, _) = send(&app, "POST", "/api/v1/hyperv/vms", bearer(token)).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let mut wrong_audience = claims("admin");
+    wrong_audience["aud"] = json!("someone-else");
+    let token = encode(&Header::default(), &wrong_audience, &encoding).unwrap();
+    let (status, _, _) = send(&app, "GET", "/api/v1/hyperv/vms", bearer(token)).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let forged = EncodingKey::from_secret(b"not-the-secret");
+    let token = encode(&Header::default(), &claims("admin"), &forged).unwrap();
+    let (status, _, _) = send(&app, "GET", "/api/v1/hyperv/vms", bearer(token)).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_rs256_bearer_token() {
+    let app = create_app(JwtConfig {
+        algorithm: JwtAlgorithm::RS256,
+        public_key_path: Some(fixture("jwt_rsa_public.pem")),
+        audience: Some("nodeagent".to_string()),
+        ..JwtConfig::default()
+    });
+    let pem = std::fs::read(fixture("jwt_rsa_private.pem")).unwrap();
+    let encoding = EncodingKey::from_rsa_pem(&pem).unwrap();
+
+    let token = encode(&Header::new(Algorithm::RS256), &claims("admin"), &encoding).unwrap();
+    let (status, _, _) = send(
+        &app,
+        "DELETE",
+        "/api/v1/hyperv/switches/none",
+        bearer(token),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // HS256 tokens are not accepted when RS256 is configured
+    let hs = EncodingKey::from_secret(SECRET.as_bytes());
+    let token = encode(&Header::default(), &claims("admin"), &hs).unwrap();
+    let (status, _, _) = send(&app, "GET", "/api/v1/hyperv/vms", bearer(token)).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
@@ -0,0 +1,143 @@
+//! Integration tests for `POST /api/v1/hyperv/vms:batch` against the fake backend
+
+use axum::{http::StatusCode, Router};
+use serde_json::{json, Value};
+
+use api::{BatchItemStatus, BatchResultDto, SnapshotDto, VmDto};
+
+mod common;
+use common::{create_fake_app, create_vm, send};
+
+async fn create_vms(app: &Router, names: &[&str]) {
+    for name in names {
+        create_vm(app, name).await;
+    }
+}
+
+/// Submit a batch and wait for its job
+async fn run_batch(app: &Router, request: Value) -> BatchResultDto {
+    let (status, body) = send(app, "POST", "/api/v1/hyperv/vms:batch", Some(request)).await;
+    assert_eq!(status, StatusCode::ACCEPTED, "{}", body);
+    let id = body["data"]["id"].as_u64().unwrap();
+    let (_, body) = send(
+        app,
+        "GET",
+        &format!("/api/v1/jobs/{}/wait?timeout_secs=5", id),
+        None,
+    )
+    .await;
+    assert_eq!(body["data"]["state"], "Completed", "{}", body);
+    serde_json::from_value(body["data"]["result"].clone()).unwrap()
+}
+
+async fn vm_state(app: &Router, name: &str) -> String {
+    let (_, body) = send(app, "GET", &format!("/api/v1/hyperv/vms/{}", name), None).await;
+    let vm: VmDto = serde_json::from_value(body["data"].clone()).unwrap();
+    vm.state
+}
+
+#[tokio::test]
+async fn test_batch_by_name_and_selector() {
+    let app = create_fake_app();
+    create_vms(&app, &["web-01", "web-02", "web-03", "db-01"]).await;
+
+    let result = run_batch(
+        &app,
+        json!({ "vms": ["web-01", "web-02", "db-01"], "action": "start", "parallelism": 2 }),
+    )
+    .await;
+    assert_eq!(result.action, "start");
+    assert_eq!((result.succeeded, result.failed, result.skipped), (3, 0, 0));
+    let names: Vec<&str> = result.results.iter().map(|r| r.vm.as_str()).collect();
+    assert_eq!(names, ["web-01", "web-02", "db-01"]);
+    assert_eq!(vm_state(&app, "web-02").await, "Running");
+
+    // Running web VMs only
+    let result = run_batch(
+        &app,
+        json!({
+            "selector": { "state": "Running", "name": "web-*" },
+            "action": "snapshot",
+            "parameters": { "name": "before-patch" }
+        }),
+    )
+    .await;
+    assert_eq!(result.succeeded, 2);
+    let snapshot: SnapshotDto =
+        serde_json::from_value(result.results[0].result.clone().unwrap()).unwrap();
+    assert_eq!(snapshot.name, "before-patch");
+    assert_eq!(snapshot.vm_name, "web-01");
+
+    // A selector matching nothing is an empty batch
+    let result = run_batch(
+        &app,
+        json!({ "selector": { "name": "none-*" }, "action": "stop" }),
+    )
+    .await;
+    assert!(result.results.is_empty());
+}
+
+#[tokio::test]
+async fn test_error_policies() {
+    let app = create_fake_app();
+    create_vms(&app, &["app-01", "app-02"]).await;
+
+    let result = run_batch(
+        &app,
+        json!({ "vms": ["missing", "app-01", "app-02"], "action": "start", "parallelism": 1 }),
+    )
+    .await;
+    assert_eq!((result.succeeded, result.failed, result.skipped), (0, 1, 2));
+    let failed = &result.results[0];
+    assert_eq!(failed.status, BatchItemStatus::Failed);
+    assert_eq!(json!(failed.code), json!("vm_not_found"));
+    assert!(failed.error.as_deref().unwrap().contains("missing"));
+    assert_eq!(vm_state(&app, "app-01").await, "Off");
+
+    let result = run_batch(
+        &app,
+        json!({
+            "vms": ["missing", "app-01", "app-02"],
+            "action": "start",
+            "parallelism": 1,
+            "on_error": "continue"
+        }),
+    )
+    .await;
+    assert_eq!((result.succeeded, result.failed, result.skipped), (2, 1, 0));
+    assert_eq!(vm_state(&app, "app-02").await, "Running");
+}
+
+#[tokio::test]
+async fn test_invalid_batches() {
+    let app = create_fake_app();
+    for request in [
+        json!({ "action": "start" }),
+        json!({ "vms": ["a"], "selector": { "name": "a" }, "action": "start" }),
+        json!({ "vms": ["a"], "action": "start", "parallelism": 0 }),
+    ] {
+        let (status, body) = send(&app, "POST", "/api/v1/hyperv/vms:batch", Some(request)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["code"], "invalid_parameter");
+    }
+
+    let (status, body) = send(
+        &app,
+        "POST",
+        "/api/v1/hyperv/vms:batch",
+        Some(json!({ "selector": { "colour": "blue" }, "action": "start" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body["error"].as_str().unwrap().contains("colour"));
+
+    // Snapshot actions need their parameters
+    let (status, _) = send(
+        &app,
+        "POST",
+        "/api/v1/hyperv/vms:batch",
+        Some(json!({ "vms": ["a"], "action": "snapshot" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+}
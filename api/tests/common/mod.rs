@@ -0,0 +1,91 @@
+//! Helpers shared by the integration tests
+//!
+//! Each test binary compiles its own copy and uses only some of them.
+#![allow(dead_code)]
+
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+    Router,
+};
+use serde_json::{json, Value};
+use tower::ServiceExt;
+
+use api::config::BackendKind;
+use api::{create_router, AppState, Config, VmDto};
+
+/// Default configuration with the in-memory fake backend
+pub fn fake_config() -> Config {
+    let mut config = Config::default();
+    config.backend.kind = BackendKind::Fake;
+    config
+}
+
+pub fn create_app(config: &Config) -> Router {
+    create_router(Arc::new(AppState::from_config(config).unwrap()))
+}
+
+pub fn create_fake_app() -> Router {
+    create_app(&fake_config())
+}
+
+/// Send a request, with `body` as JSON; the response body is `Null` when
+/// it is not JSON
+pub async fn send(
+    app: &Router,
+    method: &str,
+    uri: &str,
+    body: Option<Value>,
+) -> (StatusCode, Value) {
+    send_with(app, method, uri, &[], body).await
+}
+
+/// [`send`] with extra request headers
+pub async fn send_with(
+    app: &Router,
+    method: &str,
+    uri: &str,
+    headers: &[(&str, &str)],
+    body: Option<Value>,
+) -> (StatusCode, Value) {
+    let mut request = Request::builder().method(method).uri(uri);
+    for (name, value) in headers {
+        request = request.header(*name, *value);
+    }
+    let request = match body {
+        Some(body) => request
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string())),
+        None => request.body(Body::empty()),
+    };
+    call(app, request.unwrap()).await
+}
+
+/// Send a prepared request
+pub async fn call(app: &Router, request: Request<Body>) -> (StatusCode, Value) {
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+/// Body of `POST /api/v1/hyperv/vms` for a 1 GiB VM with a 10 GiB disk
+pub fn vm_request(name: &str) -> Value {
+    json!({
+        "name": name,
+        "memory_mb": 1024,
+        "vhd_path": format!(r"C:\VMs\{}.vhdx", name),
+        "vhd_size_bytes": 10737418240u64
+    })
+}
+
+/// Create a VM from [`vm_request`]
+pub async fn create_vm(app: &Router, name: &str) -> VmDto {
+    let (status, body) = send(app, "POST", "/api/v1/hyperv/vms", Some(vm_request(name))).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    serde_json::from_value(body["data"].clone()).unwrap()
+}
@@ -0,0 +1,177 @@
+//! Integration tests for layered configuration and hot reload
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+    response::Response,
+    Router,
+};
+use tower::ServiceExt;
+
+use api::{create_router, AppState, CommandLine, ConfigError, ConfigLoader, ConfigWatcher};
+
+const INITIAL: &str = r#"
+[server]
+port = 6001
+
+[backend]
+kind = "fake"
+"#;
+
+const UPDATED: &str = r#"
+[server]
+port = 6002
+
+[backend]
+kind = "fake"
+
+[cors]
+allowed_origins = ["https://ops.example"]
+
+[rate_limit]
+enabled = true
+requests_per_second = 0.1
+burst = 2
+"#;
+
+fn config_file(name: &str, contents: &str) -> PathBuf {
+    let path =
+        std::env::temp_dir().join(format!("api-config-{}-{}.toml", name, std::process::id()));
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+fn loader(path: &PathBuf, cli: CommandLine, env: &[(&str, &str)]) -> ConfigLoader {
+    let env = env.iter().map(|(k, v)| (k.to_string(), v.to_string()));
+    ConfigLoader::with_env(path, cli, env)
+}
+
+async fn send(app: &Router, method: &str, uri: &str, origin: Option<&str>) -> Response {
+    let mut request = Request::builder().method(method).uri(uri);
+    if let Some(origin) = origin {
+        request = request
+            .header("origin", origin)
+            .header("access-control-request-method", "GET");
+    }
+    app.clone()
+        .oneshot(request.body(Body::empty()).unwrap())
+        .await
+        .unwrap()
+}
+
+#[test]
+fn test_environment_and_flags_override_file() {
+    let path = config_file("layers", INITIAL);
+    let cli = CommandLine::parse(["--log-level", "warn"].map(String::from)).unwrap();
+    let config = loader(
+        &path,
+        cli,
+        &[
+            ("NODEAGENT_SERVER__PORT", "7001"),
+            ("NODEAGENT_LOGGING__LEVEL", "trace"),
+        ],
+    )
+    .load()
+    .unwrap();
+    assert_eq!(config.server.port, 7001);
+    assert_eq!(config.logging.level, "warn");
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn test_hot_reload_applies_cors_and_rate_limits() {
+    let path = config_file("reload", INITIAL);
+    let loader = loader(&path, CommandLine::default(), &[]);
+    let state = Arc::new(AppState::from_config(&loader.load().unwrap()).unwrap());
+    let watcher = ConfigWatcher::new(loader, state.clone()).unwrap();
+    let app = create_router(state);
+
+    assert!(watcher.reload_if_changed().unwrap().is_none());
+    for _ in 0..3 {
+        let response = send(&app, "GET", "/api/v1/hyperv/vms", None).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    std::fs::write(&path, UPDATED).unwrap();
+    let reload = watcher.reload_if_changed().unwrap().unwrap();
+    assert_eq!(
+        reload.applied,
+        [
+            "cors.allowed_origins",
+            "rate_limit.burst",
+            "rate_limit.enabled",
+            "rate_limit.requests_per_second"
+        ]
+    );
+    assert_eq!(reload.restart_required, ["server.port"]);
+
+    // The new CORS policy answers preflights for the allowed origin only
+    let response = send(
+        &app,
+        "OPTIONS",
+        "/api/v1/hyperv/vms",
+        Some("https://ops.example"),
+    )
+    .await;
+    assert_eq!(
+        response.headers()["access-control-allow-origin"],
+        "https://ops.example"
+    );
+    let response = send(
+        &app,
+        "OPTIONS",
+        "/api/v1/hyperv/vms",
+        Some("https://other.example"),
+    )
+    .await;
+    assert!(!response
+        .headers()
+        .contains_key("access-control-allow-origin"));
+
+    // Rate limits apply to the API but not to health checks
+    for _ in 0..2 {
+        let response = send(&app, "GET", "/api/v1/hyperv/vms", None).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+    let response = send(&app, "GET", "/api/v1/hyperv/vms", None).await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert!(response.headers().contains_key("retry-after"));
+    let response = send(&app, "GET", "/health", None).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn test_invalid_reload_keeps_previous_configuration() {
+    let path = config_file("invalid", UPDATED);
+    let loader = loader(&path, CommandLine::default(), &[]);
+    let state = Arc::new(AppState::from_config(&loader.load().unwrap()).unwrap());
+    let watcher = ConfigWatcher::new(loader, state.clone()).unwrap();
+    let app = create_router(state);
+
+    std::fs::write(
+        &path,
+        UPDATED.replace("burst = 2", "burst = 0") + "\n[logging]\nlevel = \"[\"\n",
+    )
+    .unwrap();
+    match watcher.reload_if_changed() {
+        Err(ConfigError::Validation(errors)) => assert_eq!(errors.len(), 2, "{:?}", errors),
+        other => panic!("unexpected {:?}", other),
+    }
+    // An unchanged invalid file is reported once
+    assert!(watcher.reload_if_changed().unwrap().is_none());
+
+    for _ in 0..2 {
+        let response = send(&app, "GET", "/api/v1/hyperv/vms", None).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+    let response = send(&app, "GET", "/api/v1/hyperv/vms", None).await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    std::fs::remove_file(&path).unwrap();
+}
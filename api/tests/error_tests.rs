@@ -0,0 +1,169 @@
+//! Integration tests for error codes and `application/problem+json` negotiation
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+    response::Response,
+    Router,
+};
+use serde_json::{json, Value};
+use tower::ServiceExt;
+
+use api::backend::{FakeCluster, FakeHyperV};
+use api::config::{ApiKeyConfig, AuthConfig};
+use api::{create_router, AppState, ErrorCode, ProblemDetails, Role};
+
+mod common;
+use common::{create_app, create_fake_app, fake_config};
+
+async fn send(
+    app: &Router,
+    method: &str,
+    uri: &str,
+    accept: Option<&str>,
+    body: Option<Value>,
+) -> Response {
+    let mut request = Request::builder().method(method).uri(uri);
+    if let Some(accept) = accept {
+        request = request.header("accept", accept);
+    }
+    let request = match body {
+        Some(body) => request
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string())),
+        None => request.body(Body::empty()),
+    };
+    app.clone().oneshot(request.unwrap()).await.unwrap()
+}
+
+async fn body(response: Response) -> Value {
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    serde_json::from_slice(&body).unwrap()
+}
+
+#[tokio::test]
+async fn test_envelope_carries_code_by_default() {
+    let app = create_fake_app();
+    let response = send(&app, "GET", "/api/v1/hyperv/vms/missing", None, None).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_eq!(response.headers()["content-type"], "application/json");
+
+    let body = body(response).await;
+    assert_eq!(body["success"], false);
+    assert_eq!(body["code"], "vm_not_found");
+    assert_eq!(body["error"], "VM not found: missing");
+}
+
+#[tokio::test]
+async fn test_problem_json_on_request() {
+    let app = create_fake_app();
+    let response = send(
+        &app,
+        "GET",
+        "/api/v1/hyperv/vms/missing",
+        Some("application/problem+json"),
+        None,
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_eq!(
+        response.headers()["content-type"],
+        "application/problem+json"
+    );
+
+    let problem: ProblemDetails = serde_json::from_value(body(response).await).unwrap();
+    assert_eq!(problem.problem_type, "urn:nodeagent:error:vm_not_found");
+    assert_eq!(problem.title, "Not Found");
+    assert_eq!(problem.status, 404);
+    assert_eq!(problem.detail, "VM not found: missing");
+    assert_eq!(
+        problem.instance.as_deref(),
+        Some("/api/v1/hyperv/vms/missing")
+    );
+    assert_eq!(problem.code, ErrorCode::VmNotFound);
+    assert!(!problem.retryable);
+
+    // Successful responses keep the envelope
+    let response = send(
+        &app,
+        "GET",
+        "/api/v1/hyperv/vms",
+        Some("application/problem+json, application/json"),
+        None,
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(body(response).await["success"], true);
+}
+
+#[tokio::test]
+async fn test_lock_conflict_is_retryable() {
+    let hyperv = FakeHyperV::new().with_operation_delay(Duration::from_millis(500));
+    let app = create_router(Arc::new(AppState::new(
+        Arc::new(hyperv),
+        Arc::new(FakeCluster::new()),
+    )));
+    let create = json!({
+        "name": "web01",
+        "memory_mb": 1024,
+        "vhd_path": "C:\\VMs\\web01.vhdx",
+        "vhd_size_bytes": 10737418240u64
+    });
+    send(&app, "POST", "/api/v1/hyperv/vms", None, Some(create)).await;
+    let export = json!({ "path": r"D:\Exports" });
+    let response = send(
+        &app,
+        "POST",
+        "/api/v1/hyperv/vms/web01/export",
+        None,
+        Some(export),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+
+    let response = send(
+        &app,
+        "POST",
+        "/api/v1/hyperv/vms/web01/start",
+        Some("application/problem+json"),
+        None,
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+    let problem = body(response).await;
+    assert_eq!(problem["code"], "resource_locked");
+    assert_eq!(problem["retryable"], true);
+}
+
+#[tokio::test]
+async fn test_auth_errors_keep_challenge() {
+    let mut config = fake_config();
+    config.auth = AuthConfig {
+        enabled: true,
+        api_keys: vec![ApiKeyConfig {
+            name: "admin".to_string(),
+            key: "admin-key".to_string(),
+            role: Role::Admin,
+        }],
+        jwt: None,
+        client_certs: Vec::new(),
+    };
+    let app = create_app(&config);
+
+    let response = send(
+        &app,
+        "GET",
+        "/api/v1/hyperv/vms",
+        Some("application/problem+json"),
+        None,
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    assert!(response.headers().contains_key("www-authenticate"));
+    assert_eq!(body(response).await["code"], "unauthorized");
+}
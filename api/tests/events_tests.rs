@@ -0,0 +1,171 @@
+//! Integration tests for the `/api/v1/events` stream against the fake backend
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{
+    body::{Body, BodyDataStream},
+    http::{header, Request, StatusCode},
+    Router,
+};
+use serde_json::{json, Value};
+use tokio_stream::StreamExt;
+use tower::ServiceExt;
+
+use api::{create_router, AppState, EventDto, EventKind};
+
+mod common;
+use common::{create_vm, fake_config};
+
+fn create_app() -> Router {
+    let mut config = fake_config();
+    config.events.poll_interval_ms = 20;
+    common::create_app(&config)
+}
+
+/// Send a request that must succeed
+async fn send(app: &Router, method: &str, uri: &str, body: Option<Value>) {
+    let (status, body) = common::send(app, method, uri, body).await;
+    assert!(
+        status.is_success(),
+        "{} {} returned {}: {}",
+        method,
+        uri,
+        status,
+        body
+    );
+}
+
+/// Reads SSE frames from a streaming response body
+struct EventReader {
+    body: BodyDataStream,
+    buffer: String,
+}
+
+impl EventReader {
+    async fn open(app: &Router, uri: &str, last_event_id: Option<u64>) -> Self {
+        let mut request = Request::builder().uri(uri);
+        if let Some(id) = last_event_id {
+            request = request.header("last-event-id", id.to_string());
+        }
+        let response = app
+            .clone()
+            .oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "text/event-stream"
+        );
+        // Let the watcher take its baseline snapshot
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        Self {
+            body: response.into_body().into_data_stream(),
+            buffer: String::new(),
+        }
+    }
+
+    async fn next(&mut self) -> EventDto {
+        loop {
+            if let Some(end) = self.buffer.find("\n\n") {
+                let frame: String = self.buffer.drain(..end + 2).collect();
+                if let Some(data) = frame.lines().find_map(|l| l.strip_prefix("data: ")) {
+                    let event: EventDto = serde_json::from_str(data).unwrap();
+                    assert!(frame.contains(&format!("id: {}\n", event.id)));
+                    assert!(frame.contains(&format!("event: {}\n", event.kind.as_str())));
+                    return event;
+                }
+                continue;
+            }
+            let chunk = tokio::time::timeout(Duration::from_secs(5), self.body.next())
+                .await
+                .expect("timed out waiting for an event")
+                .unwrap()
+                .unwrap();
+            self.buffer.push_str(std::str::from_utf8(&chunk).unwrap());
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_vm_lifecycle_events() {
+    let app = create_app();
+    let mut events = EventReader::open(&app, "/api/v1/events", None).await;
+
+    create_vm(&app, "evt01").await;
+    let created = events.next().await;
+    assert_eq!(created.kind, EventKind::VmCreated);
+    assert_eq!(created.vm_name.as_deref(), Some("evt01"));
+
+    send(&app, "POST", "/api/v1/hyperv/vms/evt01/start", None).await;
+    let started = events.next().await;
+    assert_eq!(started.kind, EventKind::VmStateChanged);
+    assert_eq!(started.previous.as_deref(), Some("Off"));
+    assert_eq!(started.current.as_deref(), Some("Running"));
+    assert!(started.id > created.id);
+
+    send(
+        &app,
+        "POST",
+        "/api/v1/hyperv/vms/evt01/snapshots",
+        Some(json!({ "name": "before-patch" })),
+    )
+    .await;
+    let snapshot = events.next().await;
+    assert_eq!(snapshot.kind, EventKind::SnapshotCreated);
+    assert_eq!(snapshot.subject, "before-patch");
+
+    send(&app, "POST", "/api/v1/cluster/nodes/NODE1/pause", None).await;
+    let paused = events.next().await;
+    assert_eq!(paused.kind, EventKind::NodeStateChanged);
+    assert_eq!(paused.subject, "NODE1");
+    assert_eq!(paused.current.as_deref(), Some("Paused"));
+}
+
+#[tokio::test]
+async fn test_filters_and_resume() {
+    let app = create_app();
+    let mut filtered =
+        EventReader::open(&app, "/api/v1/events?vm=evt02&kind=vm_state_changed", None).await;
+
+    for name in ["evt01", "evt02"] {
+        create_vm(&app, name).await;
+    }
+    // Changes within one poll interval coalesce; let the creations be observed
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    send(&app, "POST", "/api/v1/hyperv/vms/evt01/start", None).await;
+    send(&app, "POST", "/api/v1/hyperv/vms/evt02/start", None).await;
+
+    let event = filtered.next().await;
+    assert_eq!(event.kind, EventKind::VmStateChanged);
+    assert_eq!(event.vm_name.as_deref(), Some("evt02"));
+
+    // A reconnecting client replays everything after its last seen id
+    let mut resumed = EventReader::open(&app, "/api/v1/events", Some(event.id - 1)).await;
+    let replayed = resumed.next().await;
+    assert_eq!(replayed.id, event.id);
+}
+
+#[tokio::test]
+async fn test_invalid_filter() {
+    let app = create_app();
+    let (status, _) = common::send(&app, "GET", "/api/v1/events?kind=vm_exploded", None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_streams_end_when_bus_closes() {
+    let state = Arc::new(AppState::from_config(&fake_config()).unwrap());
+    let app = create_router(state.clone());
+    let mut events = EventReader::open(&app, "/api/v1/events", None).await;
+
+    state.events.close();
+    let end = tokio::time::timeout(Duration::from_secs(5), async {
+        while let Some(chunk) = events.body.next().await {
+            chunk.unwrap();
+        }
+    })
+    .await;
+    assert!(end.is_ok(), "stream still open after close");
+}
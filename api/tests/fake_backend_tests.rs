@@ -0,0 +1,902 @@
+//! Integration tests against the in-memory fake backend
+//!
+//! These tests drive full create/start/snapshot/cluster flows through the
+//! router on any platform.
+
+use axum::{http::StatusCode, Router};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+
+use api::backend::{FakeCluster, FakeHyperV};
+use api::{
+    create_router, ApiResponse, AppState, GroupDto, HostCapabilitiesDto, JobDto, SnapshotDto,
+    SnapshotTreeDto, VhdDto, VmDto, VmNetworkAdapterDto, VmProcessorDto, VmSecurityDto,
+};
+use std::sync::Arc;
+use std::time::Duration;
+
+mod common;
+use common::{create_fake_app, create_vm, send};
+
+async fn send_ok<T: DeserializeOwned>(
+    app: &Router,
+    method: &str,
+    uri: &str,
+    body: Option<Value>,
+) -> T {
+    let (status, body) = send(app, method, uri, body).await;
+    assert_eq!(
+        status,
+        StatusCode::OK,
+        "{} {} failed: {}",
+        method,
+        uri,
+        body
+    );
+    let response: ApiResponse<T> = serde_json::from_value(body).unwrap();
+    assert!(response.success);
+    response.data.unwrap()
+}
+
+/// Start a job and wait for it to finish
+async fn run_job(app: &Router, method: &str, uri: &str, body: Option<Value>) -> JobDto {
+    let (status, body) = send(app, method, uri, body).await;
+    assert_eq!(
+        status,
+        StatusCode::ACCEPTED,
+        "{} {} failed: {}",
+        method,
+        uri,
+        body
+    );
+    let response: ApiResponse<JobDto> = serde_json::from_value(body).unwrap();
+    let job = response.data.unwrap();
+    send_ok(
+        app,
+        "GET",
+        &format!("/api/v1/jobs/{}/wait?timeout_secs=5", job.id),
+        None,
+    )
+    .await
+}
+
+#[tokio::test]
+async fn test_vm_lifecycle() {
+    let app = create_fake_app();
+
+    let vm = create_vm(&app, "web01").await;
+    assert_eq!(vm.state, "Off");
+    assert_eq!(vm.memory_mb, Some(1024));
+
+    let _: String = send_ok(&app, "POST", "/api/v1/hyperv/vms/web01/start", None).await;
+    let vm: VmDto = send_ok(&app, "GET", "/api/v1/hyperv/vms/web01", None).await;
+    assert_eq!(vm.state, "Running");
+    assert!(vm.uptime_seconds.is_some());
+
+    let _: String = send_ok(&app, "POST", "/api/v1/hyperv/vms/web01/pause", None).await;
+    let vm: VmDto = send_ok(&app, "GET", "/api/v1/hyperv/vms/web01", None).await;
+    assert_eq!(vm.state, "Paused");
+
+    // Running VMs cannot be deleted
+    let (status, _) = send(&app, "DELETE", "/api/v1/hyperv/vms/web01", None).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let _: String = send_ok(&app, "POST", "/api/v1/hyperv/vms/web01/stop", None).await;
+    let _: String = send_ok(&app, "DELETE", "/api/v1/hyperv/vms/web01", None).await;
+
+    let vms: Vec<VmDto> = send_ok(&app, "GET", "/api/v1/hyperv/vms", None).await;
+    assert!(vms.is_empty());
+}
+
+#[tokio::test]
+async fn test_invalid_state_transition_returns_conflict() {
+    let app = create_fake_app();
+    create_vm(&app, "db01").await;
+
+    let (status, body) = send(&app, "POST", "/api/v1/hyperv/vms/db01/stop", None).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let response: ApiResponse<()> = serde_json::from_value(body).unwrap();
+    assert!(!response.success);
+    assert!(response.error.unwrap().contains("db01"));
+}
+
+#[tokio::test]
+async fn test_unknown_vm_returns_not_found() {
+    let app = create_fake_app();
+
+    let (status, _) = send(&app, "GET", "/api/v1/hyperv/vms/missing", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_create_vm_rejects_duplicates_and_unknown_switch() {
+    let app = create_fake_app();
+    create_vm(&app, "app01").await;
+
+    let body = json!({
+        "name": "app01",
+        "memory_mb": 1024,
+        "vhd_path": r"C:\VMs\other.vhdx",
+        "vhd_size_bytes": 1024,
+    });
+    let (status, _) = send(&app, "POST", "/api/v1/hyperv/vms", Some(body)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let body = json!({
+        "name": "app02",
+        "memory_mb": 1024,
+        "vhd_path": r"C:\VMs\app02.vhdx",
+        "vhd_size_bytes": 1024,
+        "switch_name": "missing",
+    });
+    let (status, _) = send(&app, "POST", "/api/v1/hyperv/vms", Some(body)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_host_capabilities_and_version_upgrade() {
+    let app = create_fake_app();
+
+    let capabilities: HostCapabilitiesDto =
+        send_ok(&app, "GET", "/api/v1/hyperv/host/capabilities", None).await;
+    assert_eq!(capabilities.default_vm_version.as_deref(), Some("10.0"));
+    assert!(capabilities
+        .supported_vm_versions
+        .iter()
+        .any(|v| v == "9.0"));
+    assert!(capabilities.gpu_p_supported);
+    assert!(capabilities
+        .properties
+        .iter()
+        .any(|p| p.property == "HwThreadsPerCore" && p.support == "Supported"));
+
+    assert_eq!(
+        create_vm(&app, "web01").await.version.as_deref(),
+        Some("10.0")
+    );
+    let vm: VmDto = send_ok(
+        &app,
+        "POST",
+        "/api/v1/hyperv/vms",
+        Some(json!({
+            "name": "legacy",
+            "memory_mb": 2048,
+            "vhd_path": r"C:\VMs\legacy.vhdx",
+            "vhd_size_bytes": 1u64 << 30,
+            "version": "9.0",
+        })),
+    )
+    .await;
+    assert_eq!(vm.version.as_deref(), Some("9.0"));
+
+    let _: String = send_ok(&app, "POST", "/api/v1/hyperv/vms/legacy/start", None).await;
+    let (status, _) = send(
+        &app,
+        "POST",
+        "/api/v1/hyperv/vms/legacy/upgrade-version",
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let _: String = send_ok(&app, "POST", "/api/v1/hyperv/vms/legacy/stop", None).await;
+    let vm: VmDto = send_ok(
+        &app,
+        "POST",
+        "/api/v1/hyperv/vms/legacy/upgrade-version",
+        None,
+    )
+    .await;
+    assert_eq!(vm.version.as_deref(), Some("10.0"));
+
+    // Already at the host default
+    let (status, _) = send(
+        &app,
+        "POST",
+        "/api/v1/hyperv/vms/web01/upgrade-version",
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn test_update_vm() {
+    let app = create_fake_app();
+    let vm = create_vm(&app, "web01").await;
+    assert_eq!(vm.generation, Some(2));
+    assert_eq!(vm.dynamic_memory, Some(false));
+    assert_eq!(vm.checkpoint_type.as_deref(), Some("Production"));
+
+    let vm: VmDto = send_ok(
+        &app,
+        "PATCH",
+        "/api/v1/hyperv/vms/web01",
+        Some(json!({
+            "cpu_count": 4,
+            "dynamic_memory": true,
+            "dynamic_memory_min_mb": 1024,
+            "dynamic_memory_max_mb": 8192,
+            "automatic_start_action": "AlwaysStart",
+            "automatic_stop_action": "shutdown",
+            "checkpoint_type": "Standard",
+            "notes": "web tier",
+        })),
+    )
+    .await;
+    assert_eq!(vm.cpu_count, Some(4));
+    assert_eq!(vm.memory_mb, Some(1024));
+    assert_eq!(vm.dynamic_memory, Some(true));
+    assert_eq!(vm.dynamic_memory_min_mb, Some(1024));
+    assert_eq!(vm.dynamic_memory_max_mb, Some(8192));
+    assert_eq!(vm.automatic_start_action.as_deref(), Some("AlwaysStart"));
+    assert_eq!(vm.automatic_stop_action.as_deref(), Some("Shutdown"));
+    assert_eq!(vm.checkpoint_type.as_deref(), Some("Standard"));
+    assert_eq!(vm.notes.as_deref(), Some("web tier"));
+
+    // Minimum above the VM's current startup memory
+    let (status, body) = send(
+        &app,
+        "PATCH",
+        "/api/v1/hyperv/vms/web01",
+        Some(json!({ "dynamic_memory_min_mb": 4096 })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(body["code"], "validation_failed");
+
+    // Running: processor count and dynamic memory need the VM off, the
+    // maximum may still grow
+    let _: String = send_ok(&app, "POST", "/api/v1/hyperv/vms/web01/start", None).await;
+    for change in [
+        json!({ "cpu_count": 2 }),
+        json!({ "dynamic_memory": false }),
+        json!({ "memory_mb": 4096 }),
+        json!({ "dynamic_memory_max_mb": 4096 }),
+    ] {
+        let (status, body) = send(&app, "PATCH", "/api/v1/hyperv/vms/web01", Some(change)).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(body["code"], "invalid_state");
+    }
+    let vm: VmDto = send_ok(
+        &app,
+        "PATCH",
+        "/api/v1/hyperv/vms/web01",
+        Some(json!({ "dynamic_memory_max_mb": 16384, "notes": "" })),
+    )
+    .await;
+    assert_eq!(vm.cpu_count, Some(4));
+    assert_eq!(vm.dynamic_memory_max_mb, Some(16384));
+    assert_eq!(vm.notes.as_deref(), Some(""));
+
+    let (status, _) = send(
+        &app,
+        "PATCH",
+        "/api/v1/hyperv/vms/missing",
+        Some(json!({ "notes": "x" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_update_running_gen1_vm_memory_conflicts() {
+    let app = create_fake_app();
+    let body = json!({
+        "name": "legacy",
+        "memory_mb": 2048,
+        "generation": 1,
+        "vhd_path": r"C:\VMs\legacy.vhdx",
+        "vhd_size_bytes": 64u64 * 1024 * 1024 * 1024,
+    });
+    let _: VmDto = send_ok(&app, "POST", "/api/v1/hyperv/vms", Some(body)).await;
+    let _: String = send_ok(&app, "POST", "/api/v1/hyperv/vms/legacy/start", None).await;
+
+    let change = json!({ "memory_mb": 4096 });
+    let (status, _) = send(&app, "PATCH", "/api/v1/hyperv/vms/legacy", Some(change)).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let _: String = send_ok(&app, "POST", "/api/v1/hyperv/vms/legacy/force-stop", None).await;
+    let vm: VmDto = send_ok(
+        &app,
+        "PATCH",
+        "/api/v1/hyperv/vms/legacy",
+        Some(json!({ "memory_mb": 4096, "cpu_count": 2 })),
+    )
+    .await;
+    assert_eq!(vm.memory_mb, Some(4096));
+    assert_eq!(vm.cpu_count, Some(2));
+}
+
+#[tokio::test]
+async fn test_vm_processor_settings() {
+    let app = create_fake_app();
+    create_vm(&app, "web01").await;
+
+    let processor: VmProcessorDto =
+        send_ok(&app, "GET", "/api/v1/hyperv/vms/web01/processor", None).await;
+    assert_eq!(processor.count, 2);
+    assert_eq!(processor.limit_percent, 100.0);
+    assert_eq!(processor.weight, 100);
+    assert!(!processor.expose_virtualization_extensions);
+
+    let processor: VmProcessorDto = send_ok(
+        &app,
+        "PUT",
+        "/api/v1/hyperv/vms/web01/processor",
+        Some(json!({
+            "count": 4,
+            "limit_percent": 50.0,
+            "reservation_percent": 10.0,
+            "weight": 200,
+            "hw_threads_per_core": 2,
+            "expose_virtualization_extensions": true,
+            "numa_nodes": [
+                { "id": 0, "processor_count": 2, "memory_mb": 1024 },
+                { "id": 1, "processor_count": 2, "memory_mb": 1024 },
+            ],
+        })),
+    )
+    .await;
+    assert_eq!(processor.count, 4);
+    assert_eq!(processor.reservation_percent, 10.0);
+    assert_eq!(processor.hw_threads_per_core, Some(2));
+    assert_eq!(processor.numa_nodes.len(), 2);
+    let vm: VmDto = send_ok(&app, "GET", "/api/v1/hyperv/vms/web01", None).await;
+    assert_eq!(vm.cpu_count, Some(4));
+
+    // Running: resource controls may change, the processor layout may not
+    let _: String = send_ok(&app, "POST", "/api/v1/hyperv/vms/web01/start", None).await;
+    let (status, body) = send(
+        &app,
+        "PUT",
+        "/api/v1/hyperv/vms/web01/processor",
+        Some(json!({ "count": 4, "limit_percent": 75.0 })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body["code"], "invalid_state");
+
+    let processor: VmProcessorDto = send_ok(
+        &app,
+        "PUT",
+        "/api/v1/hyperv/vms/web01/processor",
+        Some(json!({
+            "count": 4,
+            "limit_percent": 75.0,
+            "hw_threads_per_core": 2,
+            "expose_virtualization_extensions": true,
+            "numa_nodes": [
+                { "id": 0, "processor_count": 2, "memory_mb": 1024 },
+                { "id": 1, "processor_count": 2, "memory_mb": 1024 },
+            ],
+        })),
+    )
+    .await;
+    assert_eq!(processor.limit_percent, 75.0);
+    assert_eq!(processor.reservation_percent, 0.0);
+    assert_eq!(processor.weight, 100);
+
+    let (status, _) = send(&app, "GET", "/api/v1/hyperv/vms/missing/processor", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_vm_security_settings() {
+    let app = create_fake_app();
+    create_vm(&app, "web01").await;
+
+    let security: VmSecurityDto =
+        send_ok(&app, "GET", "/api/v1/hyperv/vms/web01/security", None).await;
+    assert!(security.secure_boot_enabled);
+    assert_eq!(
+        security.secure_boot_template.as_deref(),
+        Some("MicrosoftWindows")
+    );
+    assert!(!security.tpm_enabled);
+
+    let change = json!({
+        "secure_boot_template": "microsoftuefica",
+        "tpm_enabled": true,
+        "guest_isolation_type": "Vbs",
+        "key_protector": "Local",
+    });
+    let _: String = send_ok(&app, "POST", "/api/v1/hyperv/vms/web01/start", None).await;
+    let (status, _) = send(
+        &app,
+        "PUT",
+        "/api/v1/hyperv/vms/web01/security",
+        Some(change.clone()),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let _: String = send_ok(&app, "POST", "/api/v1/hyperv/vms/web01/stop", None).await;
+    let security: VmSecurityDto = send_ok(
+        &app,
+        "PUT",
+        "/api/v1/hyperv/vms/web01/security",
+        Some(change),
+    )
+    .await;
+    assert_eq!(
+        security.secure_boot_template.as_deref(),
+        Some("MicrosoftUefiCa")
+    );
+    assert!(security.tpm_enabled);
+    assert_eq!(security.guest_isolation_type, "Vbs");
+    assert_eq!(security.key_protector, "Local");
+
+    // Secure Boot and vTPM need generation 2
+    let body = json!({
+        "name": "legacy",
+        "memory_mb": 2048,
+        "generation": 1,
+        "vhd_path": r"C:\VMs\legacy.vhdx",
+        "vhd_size_bytes": 1u64 << 30,
+    });
+    let _: VmDto = send_ok(&app, "POST", "/api/v1/hyperv/vms", Some(body)).await;
+    let security: VmSecurityDto =
+        send_ok(&app, "GET", "/api/v1/hyperv/vms/legacy/security", None).await;
+    assert!(!security.secure_boot_enabled);
+    let (status, _) = send(
+        &app,
+        "PUT",
+        "/api/v1/hyperv/vms/legacy/security",
+        Some(json!({ "tpm_enabled": true })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_vm_network_adapters() {
+    let app = create_fake_app();
+    let _: Value = send_ok(
+        &app,
+        "POST",
+        "/api/v1/hyperv/switches",
+        Some(json!({ "name": "lan", "switch_type": "Internal" })),
+    )
+    .await;
+    create_vm(&app, "web01").await;
+    let adapters: Vec<VmNetworkAdapterDto> =
+        send_ok(&app, "GET", "/api/v1/hyperv/vms/web01/adapters", None).await;
+    assert_eq!(adapters.len(), 1);
+    assert_eq!(adapters[0].name, "Network Adapter");
+    assert!(adapters[0].dynamic_mac);
+    assert_eq!(adapters[0].switch_name, None);
+
+    let added: VmNetworkAdapterDto = send_ok(
+        &app,
+        "POST",
+        "/api/v1/hyperv/vms/web01/adapters",
+        Some(json!({
+            "name": "storage",
+            "switch_name": "lan",
+            "mac_address": "00-15-5D-01-02-03",
+            "vlan_id": 20,
+            "dhcp_guard": true,
+            "port_mirroring": "source",
+            "bandwidth": { "maximum_mbps": 1000 },
+        })),
+    )
+    .await;
+    assert_eq!(added.switch_name.as_deref(), Some("lan"));
+    assert_eq!(added.mac_address.as_deref(), Some("00-15-5D-01-02-03"));
+    assert!(!added.dynamic_mac);
+    assert_eq!(added.vlan_id, Some(20));
+    assert_eq!(added.dhcp_guard, Some(true));
+    assert_eq!(added.router_guard, Some(false));
+    assert_eq!(added.port_mirroring.as_deref(), Some("Source"));
+    assert_eq!(added.bandwidth.unwrap().maximum_mbps, Some(1000));
+
+    // By name, and by id
+    let updated: VmNetworkAdapterDto = send_ok(
+        &app,
+        "PATCH",
+        "/api/v1/hyperv/vms/web01/adapters/storage",
+        Some(json!({ "vlan_id": 0, "router_guard": true, "bandwidth": {} })),
+    )
+    .await;
+    assert_eq!(updated.vlan_id, None);
+    assert_eq!(updated.router_guard, Some(true));
+    assert!(updated.bandwidth.is_none());
+    let connected: VmNetworkAdapterDto = send_ok(
+        &app,
+        "POST",
+        &format!(
+            "/api/v1/hyperv/vms/web01/adapters/{}/connect",
+            adapters[0].id
+        ),
+        Some(json!({ "switch_name": "lan" })),
+    )
+    .await;
+    assert_eq!(connected.switch_name.as_deref(), Some("lan"));
+
+    // The MAC only changes while the VM is off; VLANs and disconnects do not care
+    let _: String = send_ok(&app, "POST", "/api/v1/hyperv/vms/web01/start", None).await;
+    let (status, _) = send(
+        &app,
+        "PATCH",
+        "/api/v1/hyperv/vms/web01/adapters/storage",
+        Some(json!({ "dynamic_mac": true })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let disconnected: VmNetworkAdapterDto = send_ok(
+        &app,
+        "POST",
+        "/api/v1/hyperv/vms/web01/adapters/storage/disconnect",
+        None,
+    )
+    .await;
+    assert_eq!(disconnected.switch_name, None);
+
+    // Removing the switch disconnects the rest
+    let _: String = send_ok(&app, "DELETE", "/api/v1/hyperv/switches/lan", None).await;
+    let adapters: Vec<VmNetworkAdapterDto> =
+        send_ok(&app, "GET", "/api/v1/hyperv/vms/web01/adapters", None).await;
+    assert!(adapters.iter().all(|a| a.switch_name.is_none()));
+
+    let _: String = send_ok(
+        &app,
+        "DELETE",
+        "/api/v1/hyperv/vms/web01/adapters/storage",
+        None,
+    )
+    .await;
+    let (status, body) = send(
+        &app,
+        "DELETE",
+        "/api/v1/hyperv/vms/web01/adapters/storage",
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["code"], "adapter_not_found");
+
+    let (status, _) = send(
+        &app,
+        "POST",
+        "/api/v1/hyperv/vms/web01/adapters",
+        Some(json!({ "switch_name": "missing" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_snapshot_flow() {
+    let app = create_fake_app();
+    create_vm(&app, "dev01").await;
+    let _: String = send_ok(&app, "POST", "/api/v1/hyperv/vms/dev01/start", None).await;
+
+    let base: SnapshotDto = send_ok(
+        &app,
+        "POST",
+        "/api/v1/hyperv/vms/dev01/snapshots",
+        Some(json!({ "name": "base" })),
+    )
+    .await;
+    assert_eq!(base.vm_name, "dev01");
+    assert!(base.parent_name.is_none());
+    assert!(base.creation_time.is_some());
+
+    let patched: SnapshotDto = send_ok(
+        &app,
+        "POST",
+        "/api/v1/hyperv/vms/dev01/snapshots",
+        Some(json!({ "name": "patched" })),
+    )
+    .await;
+    assert_eq!(patched.parent_name.as_deref(), Some("base"));
+
+    let job = run_job(
+        &app,
+        "POST",
+        "/api/v1/hyperv/vms/dev01/snapshots/base/apply",
+        None,
+    )
+    .await;
+    assert_eq!(job.state, "Completed");
+    assert_eq!(job.kind, "apply_snapshot");
+    let vm: VmDto = send_ok(&app, "GET", "/api/v1/hyperv/vms/dev01", None).await;
+    assert_eq!(vm.state, "Off");
+
+    let _: String = send_ok(
+        &app,
+        "DELETE",
+        "/api/v1/hyperv/vms/dev01/snapshots/base/delete",
+        None,
+    )
+    .await;
+    let snapshots: Vec<SnapshotDto> =
+        send_ok(&app, "GET", "/api/v1/hyperv/vms/dev01/snapshots", None).await;
+    assert_eq!(snapshots.len(), 1);
+    assert_eq!(snapshots[0].name, "patched");
+    assert!(snapshots[0].parent_name.is_none());
+
+    let (status, _) = send(&app, "GET", "/api/v1/hyperv/vms/dev01/snapshots/base", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_snapshot_tree() {
+    let app = create_fake_app();
+    create_vm(&app, "dev01").await;
+    for (name, apply) in [
+        ("base", None),
+        ("a", None),
+        ("a1", None),
+        ("b", Some("base")),
+    ] {
+        if let Some(snapshot) = apply {
+            let uri = format!("/api/v1/hyperv/vms/dev01/snapshots/{}/apply", snapshot);
+            run_job(&app, "POST", &uri, None).await;
+        }
+        let _: SnapshotDto = send_ok(
+            &app,
+            "POST",
+            "/api/v1/hyperv/vms/dev01/snapshots",
+            Some(json!({ "name": name })),
+        )
+        .await;
+    }
+
+    // base -> (a -> a1, b), with "Now" under b
+    let tree: SnapshotTreeDto =
+        send_ok(&app, "GET", "/api/v1/hyperv/vms/dev01/snapshots/tree", None).await;
+    assert_eq!(tree.current_snapshot.as_deref(), Some("b"));
+    assert_eq!(tree.roots.len(), 1);
+    assert_eq!(
+        tree.current_snapshot_id.as_deref(),
+        Some(tree.roots[0].children[1].id.as_str())
+    );
+    let base = &tree.roots[0];
+    assert_eq!(base.name, "base");
+    assert_eq!(base.descendant_count, 3);
+    assert!(!base.is_current);
+    let children: Vec<_> = base.children.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(children, ["a", "b"]);
+    assert_eq!(base.children[0].descendant_count, 1);
+    assert_eq!(base.children[0].children[0].name, "a1");
+    assert!(base.children[1].is_current);
+
+    let renamed: SnapshotDto = send_ok(
+        &app,
+        "POST",
+        "/api/v1/hyperv/vms/dev01/snapshots/a/rename",
+        Some(json!({ "new_name": "patched" })),
+    )
+    .await;
+    assert_eq!(renamed.name, "patched");
+    assert_eq!(renamed.id, base.children[0].id);
+    let a1: SnapshotDto = send_ok(&app, "GET", "/api/v1/hyperv/vms/dev01/snapshots/a1", None).await;
+    assert_eq!(a1.parent_name.as_deref(), Some("patched"));
+    assert_eq!(a1.parent_id.as_deref(), Some(renamed.id.as_str()));
+
+    let job = run_job(
+        &app,
+        "POST",
+        "/api/v1/hyperv/vms/dev01/snapshots/patched/export",
+        Some(json!({ "path": "D:\\Exports" })),
+    )
+    .await;
+    assert_eq!(job.state, "Completed");
+    assert_eq!(job.kind, "export_snapshot");
+    assert_eq!(job.target, "dev01/patched");
+
+    let _: String = send_ok(
+        &app,
+        "DELETE",
+        "/api/v1/hyperv/vms/dev01/snapshots/patched/delete?subtree=true",
+        None,
+    )
+    .await;
+    let snapshots: Vec<SnapshotDto> =
+        send_ok(&app, "GET", "/api/v1/hyperv/vms/dev01/snapshots", None).await;
+    let names: Vec<_> = snapshots.iter().map(|s| s.name.as_str()).collect();
+    assert_eq!(names, ["base", "b"]);
+
+    // Deleting the subtree holding "Now" moves it to the deleted root's parent
+    let _: String = send_ok(
+        &app,
+        "DELETE",
+        "/api/v1/hyperv/vms/dev01/snapshots/base/delete?subtree=true",
+        None,
+    )
+    .await;
+    let tree: SnapshotTreeDto =
+        send_ok(&app, "GET", "/api/v1/hyperv/vms/dev01/snapshots/tree", None).await;
+    assert!(tree.roots.is_empty());
+    assert!(tree.current_snapshot.is_none());
+}
+
+#[tokio::test]
+async fn test_snapshot_tree_with_duplicate_names() {
+    let app = create_fake_app();
+    create_vm(&app, "dev01").await;
+    let mut ids = Vec::new();
+    for _ in 0..3 {
+        let snapshot: SnapshotDto = send_ok(
+            &app,
+            "POST",
+            "/api/v1/hyperv/vms/dev01/snapshots",
+            Some(json!({ "name": "nightly" })),
+        )
+        .await;
+        ids.push(snapshot.id);
+    }
+
+    // Each "nightly" hangs off the previous one, not off itself
+    let tree: SnapshotTreeDto =
+        send_ok(&app, "GET", "/api/v1/hyperv/vms/dev01/snapshots/tree", None).await;
+    assert_eq!(tree.roots.len(), 1);
+    let mut node = &tree.roots[0];
+    for (depth, id) in ids.iter().enumerate() {
+        assert_eq!(&node.id, id);
+        assert_eq!(node.descendant_count, 2 - depth);
+        assert_eq!(node.is_current, depth == 2);
+        if let Some(child) = node.children.first() {
+            assert_eq!(node.children.len(), 1);
+            node = child;
+        }
+    }
+    assert_eq!(tree.current_snapshot.as_deref(), Some("nightly"));
+    assert_eq!(tree.current_snapshot_id.as_ref(), Some(&ids[2]));
+
+    // The name is ambiguous, so checkpoints are addressed by id
+    let (status, body) = send(
+        &app,
+        "GET",
+        "/api/v1/hyperv/vms/dev01/snapshots/nightly",
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body["error"].as_str().unwrap().contains("3 checkpoints"));
+    let uri = format!("/api/v1/hyperv/vms/dev01/snapshots/{}/apply", ids[0]);
+    run_job(&app, "POST", &uri, None).await;
+    let sibling: SnapshotDto = send_ok(
+        &app,
+        "POST",
+        "/api/v1/hyperv/vms/dev01/snapshots",
+        Some(json!({ "name": "nightly" })),
+    )
+    .await;
+    assert_eq!(sibling.parent_id.as_ref(), Some(&ids[0]));
+
+    let tree: SnapshotTreeDto =
+        send_ok(&app, "GET", "/api/v1/hyperv/vms/dev01/snapshots/tree", None).await;
+    let children: Vec<_> = tree.roots[0].children.iter().map(|c| &c.id).collect();
+    assert_eq!(children, [&ids[1], &sibling.id]);
+    assert!(tree.roots[0].children[1].is_current);
+
+    let uri = format!(
+        "/api/v1/hyperv/vms/dev01/snapshots/{}/delete?subtree=true",
+        ids[1]
+    );
+    let _: String = send_ok(&app, "DELETE", &uri, None).await;
+    let snapshots: Vec<SnapshotDto> =
+        send_ok(&app, "GET", "/api/v1/hyperv/vms/dev01/snapshots", None).await;
+    let remaining: Vec<_> = snapshots.iter().map(|s| &s.id).collect();
+    assert_eq!(remaining, [&ids[0], &sibling.id]);
+}
+
+#[tokio::test]
+async fn test_cluster_group_move() {
+    let app = create_fake_app();
+
+    let groups: Vec<GroupDto> = send_ok(&app, "GET", "/api/v1/cluster/groups", None).await;
+    assert!(groups.iter().any(|g| g.name == "Cluster Group"));
+
+    let job = run_job(
+        &app,
+        "POST",
+        "/api/v1/cluster/groups/Cluster%20Group/move/NODE2",
+        None,
+    )
+    .await;
+    assert_eq!(job.state, "Completed");
+    let group: GroupDto =
+        send_ok(&app, "GET", "/api/v1/cluster/groups/Cluster%20Group", None).await;
+    assert_eq!(group.owner_node.as_deref(), Some("NODE2"));
+
+    // Paused nodes cannot take ownership
+    let _: String = send_ok(&app, "POST", "/api/v1/cluster/nodes/NODE1/pause", None).await;
+    let job = run_job(
+        &app,
+        "POST",
+        "/api/v1/cluster/groups/Cluster%20Group/move/NODE1",
+        None,
+    )
+    .await;
+    assert_eq!(job.state, "Failed");
+    assert!(job.error.unwrap().contains("NODE1"));
+
+    let (status, _) = send(&app, "GET", "/api/v1/cluster/nodes/NODE9", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_create_vhd_job_returns_result() {
+    let app = create_fake_app();
+
+    let job = run_job(
+        &app,
+        "POST",
+        "/api/v1/hyperv/vhds",
+        Some(json!({ "path": r"C:\VHDs\data.vhdx", "size_bytes": 1073741824u64 })),
+    )
+    .await;
+    assert_eq!(job.state, "Completed");
+    assert_eq!(job.percent_complete, 100);
+    let vhd: VhdDto = serde_json::from_value(job.result.unwrap()).unwrap();
+    assert_eq!(vhd.format, "Vhdx");
+    assert_eq!(vhd.max_size_bytes, 1073741824);
+
+    let jobs: Vec<JobDto> = send_ok(&app, "GET", "/api/v1/jobs", None).await;
+    assert_eq!(jobs.len(), 1);
+    let fetched: JobDto = send_ok(&app, "GET", &format!("/api/v1/jobs/{}", job.id), None).await;
+    assert_eq!(fetched.state, "Completed");
+}
+
+#[tokio::test]
+async fn test_cancel_running_export() {
+    let hyperv = FakeHyperV::new().with_operation_delay(Duration::from_millis(500));
+    let app = create_router(Arc::new(AppState::new(
+        Arc::new(hyperv),
+        Arc::new(FakeCluster::new()),
+    )));
+    create_vm(&app, "big01").await;
+
+    let (status, body) = send(
+        &app,
+        "POST",
+        "/api/v1/hyperv/vms/big01/export",
+        Some(json!({ "path": r"D:\Exports" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    let job = serde_json::from_value::<ApiResponse<JobDto>>(body)
+        .unwrap()
+        .data
+        .unwrap();
+
+    // Still running after a short wait
+    let waited: JobDto = send_ok(
+        &app,
+        "GET",
+        &format!("/api/v1/jobs/{}/wait?timeout_secs=0", job.id),
+        None,
+    )
+    .await;
+    assert!(waited.state == "Queued" || waited.state == "Running");
+
+    let cancelled: JobDto = send_ok(
+        &app,
+        "POST",
+        &format!("/api/v1/jobs/{}/cancel", job.id),
+        None,
+    )
+    .await;
+    assert_eq!(cancelled.state, "Cancelled");
+
+    let (status, _) = send(
+        &app,
+        "POST",
+        &format!("/api/v1/jobs/{}/cancel", job.id),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn test_unknown_job_returns_not_found() {
+    let app = create_fake_app();
+
+    let (status, _) = send(&app, "GET", "/api/v1/jobs/999", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
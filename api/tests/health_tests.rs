@@ -0,0 +1,195 @@
+//! Integration tests for the liveness and readiness probes
+
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Duration;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+    Router,
+};
+use tower::ServiceExt;
+
+use api::config::{BackendKind, HealthConfig};
+use api::health::Check;
+use api::{
+    create_router, ApiResponse, AppState, CommandLine, Config, ConfigLoader, ConfigWatcher,
+    HealthCheck, HealthReportDto, HealthStatus,
+};
+
+fn config(health: HealthConfig) -> Config {
+    let mut config = Config::default();
+    config.backend.kind = BackendKind::Fake;
+    config.health = health;
+    config
+}
+
+/// Checks against the temp directory, with no minimum free space
+fn health_config() -> HealthConfig {
+    HealthConfig {
+        min_free_disk_bytes: 0,
+        disk_paths: vec![std::env::temp_dir().display().to_string()],
+        cache_ttl_secs: 0,
+        ..HealthConfig::default()
+    }
+}
+
+async fn probe(app: &Router, uri: &str) -> (StatusCode, ApiResponse<HealthReportDto>) {
+    let response = app
+        .clone()
+        .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+fn status_of(report: &HealthReportDto, name: &str) -> HealthStatus {
+    report
+        .checks
+        .iter()
+        .find(|check| check.name == name)
+        .unwrap_or_else(|| panic!("no {} check", name))
+        .status
+}
+
+#[tokio::test]
+async fn test_ready_and_live_pass_with_fake_backend() {
+    let state = Arc::new(AppState::from_config(&config(health_config())).unwrap());
+    let app = create_router(state);
+
+    let (status, body) = probe(&app, "/health/ready").await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.success);
+    let report = body.data.unwrap();
+    assert_eq!(report.status, HealthStatus::Pass);
+    let names: Vec<_> = report.checks.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(names, ["backend", "cluster", "disk", "config", "jobs"]);
+    assert!(report.checks[1].detail.contains("FAKE-CLUSTER"));
+    assert!(report
+        .checks
+        .iter()
+        .all(|check| !check.checked_at.is_empty()));
+
+    let (status, body) = probe(&app, "/health/live").await;
+    assert_eq!(status, StatusCode::OK);
+    let report = body.data.unwrap();
+    assert_eq!(report.checks.len(), 1);
+    assert_eq!(report.checks[0].name, "runtime");
+}
+
+#[tokio::test]
+async fn test_low_disk_fails_unless_optional() {
+    let low_disk = HealthConfig {
+        min_free_disk_bytes: u64::MAX,
+        ..health_config()
+    };
+    let app = create_router(Arc::new(
+        AppState::from_config(&config(low_disk.clone())).unwrap(),
+    ));
+    let (status, body) = probe(&app, "/health/ready").await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert!(!body.success);
+    assert_eq!(body.error.as_deref(), Some("Failed checks: disk"));
+    let report = body.data.unwrap();
+    assert_eq!(report.status, HealthStatus::Fail);
+    assert_eq!(status_of(&report, "backend"), HealthStatus::Pass);
+
+    // Optional checks only warn
+    let optional = HealthConfig {
+        optional: vec!["disk".to_string()],
+        ..low_disk
+    };
+    let app = create_router(Arc::new(AppState::from_config(&config(optional)).unwrap()));
+    let (status, body) = probe(&app, "/health/ready").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body.data.unwrap().status, HealthStatus::Warn);
+}
+
+#[tokio::test]
+async fn test_saturated_job_queue_fails_readiness() {
+    let saturated = HealthConfig {
+        max_active_jobs: 1,
+        ..health_config()
+    };
+    let state = Arc::new(AppState::from_config(&config(saturated)).unwrap());
+    let app = create_router(state.clone());
+
+    let (release, wait) = mpsc::channel::<()>();
+    let wait = Mutex::new(wait);
+    let job = state.jobs.spawn("export_vm", "web-01", move |_| {
+        let _ = wait.lock().unwrap().recv();
+        Ok(())
+    });
+
+    let (status, body) = probe(&app, "/health/ready").await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    let report = body.data.unwrap();
+    assert_eq!(status_of(&report, "jobs"), HealthStatus::Fail);
+
+    release.send(()).unwrap();
+    state
+        .jobs
+        .wait(job.id, Duration::from_secs(5))
+        .await
+        .unwrap();
+    let (status, _) = probe(&app, "/health/ready").await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_failed_reload_warns_in_config_check() {
+    let path = std::env::temp_dir().join(format!("api-health-{}.toml", std::process::id()));
+    std::fs::write(&path, "[backend]\nkind = \"fake\"\n").unwrap();
+    let loader = ConfigLoader::with_env(&path, CommandLine::default(), std::iter::empty());
+    let mut config = loader.load().unwrap();
+    config.health = health_config();
+    let state = Arc::new(AppState::from_config(&config).unwrap());
+    let watcher = ConfigWatcher::new(loader, state.clone()).unwrap();
+    let app = create_router(state);
+
+    std::fs::write(&path, "[backend]\nkind = \"hyper-v-please\"\n").unwrap();
+    assert!(watcher.reload_if_changed().is_err());
+    let (status, body) = probe(&app, "/health/ready").await;
+    assert_eq!(status, StatusCode::OK);
+    let report = body.data.unwrap();
+    assert_eq!(report.status, HealthStatus::Warn);
+    assert_eq!(status_of(&report, "config"), HealthStatus::Warn);
+
+    std::fs::write(&path, "[backend]\nkind = \"fake\"\n").unwrap();
+    watcher.reload_if_changed().unwrap();
+    let (_, body) = probe(&app, "/health/ready").await;
+    assert_eq!(status_of(&body.data.unwrap(), "config"), HealthStatus::Pass);
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+struct Flaky;
+
+impl HealthCheck for Flaky {
+    fn name(&self) -> &str {
+        "flaky"
+    }
+
+    fn check(&self, _: &AppState) -> Check {
+        panic!("probe bug")
+    }
+}
+
+#[tokio::test]
+async fn test_registered_checks_run_and_panics_fail() {
+    let state = Arc::new(AppState::from_config(&config(health_config())).unwrap());
+    state.health.register(Flaky);
+    let app = create_router(state);
+
+    let (status, body) = probe(&app, "/health/ready").await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body.error.as_deref(), Some("Failed checks: flaky"));
+    let report = body.data.unwrap();
+    let flaky = report.checks.last().unwrap();
+    assert_eq!(flaky.status, HealthStatus::Fail);
+    assert_eq!(flaky.detail, "Check panicked");
+}
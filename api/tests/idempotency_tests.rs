@@ -0,0 +1,127 @@
+//! Integration tests for `Idempotency-Key` replay of POST requests
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+    response::Response,
+    Router,
+};
+use serde_json::{json, Value};
+use tower::ServiceExt;
+
+use api::{ApiResponse, SnapshotDto, VmDto};
+
+mod common;
+use common::{create_fake_app, send, vm_request};
+
+async fn post(app: &Router, uri: &str, key: Option<&str>, body: Value) -> Response {
+    let mut request = Request::builder()
+        .method("POST")
+        .uri(uri)
+        .header("content-type", "application/json");
+    if let Some(key) = key {
+        request = request.header("idempotency-key", key);
+    }
+    app.clone()
+        .oneshot(request.body(Body::from(body.to_string())).unwrap())
+        .await
+        .unwrap()
+}
+
+async fn body(response: Response) -> Value {
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    serde_json::from_slice(&body).unwrap()
+}
+
+async fn list<T: serde::de::DeserializeOwned>(app: &Router, uri: &str) -> Vec<T> {
+    let (_, body) = send(app, "GET", uri, None).await;
+    serde_json::from_value::<ApiResponse<Vec<T>>>(body)
+        .unwrap()
+        .data
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_retried_create_is_replayed() {
+    let app = create_fake_app();
+
+    let first = post(
+        &app,
+        "/api/v1/hyperv/vms",
+        Some("create-web01"),
+        vm_request("web01"),
+    )
+    .await;
+    assert_eq!(first.status(), StatusCode::OK);
+    assert!(first.headers().get("idempotent-replayed").is_none());
+    let first = body(first).await;
+
+    let retry = post(
+        &app,
+        "/api/v1/hyperv/vms",
+        Some("create-web01"),
+        vm_request("web01"),
+    )
+    .await;
+    assert_eq!(retry.status(), StatusCode::OK);
+    assert_eq!(retry.headers()["idempotent-replayed"], "true");
+    assert_eq!(retry.headers()["content-type"], "application/json");
+    assert_eq!(body(retry).await, first);
+
+    let vms: Vec<VmDto> = list(&app, "/api/v1/hyperv/vms").await;
+    assert_eq!(vms.len(), 1);
+
+    // Without a key the duplicate reaches the backend
+    let duplicate = post(&app, "/api/v1/hyperv/vms", None, vm_request("web01")).await;
+    assert!(!duplicate.status().is_success());
+}
+
+#[tokio::test]
+async fn test_key_reused_for_different_request() {
+    let app = create_fake_app();
+    post(&app, "/api/v1/hyperv/vms", Some("k1"), vm_request("web01")).await;
+
+    let response = post(&app, "/api/v1/hyperv/vms", Some("k1"), vm_request("web02")).await;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let response = post(
+        &app,
+        "/api/v1/hyperv/vms/web01/snapshots",
+        Some("k1"),
+        vm_request("web01"),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[tokio::test]
+async fn test_failed_request_releases_key() {
+    let app = create_fake_app();
+    let snapshot = json!({ "name": "before-patch" });
+
+    // VM does not exist yet; the failure is not stored
+    let response = post(
+        &app,
+        "/api/v1/hyperv/vms/web01/snapshots",
+        Some("snap-1"),
+        snapshot.clone(),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    post(&app, "/api/v1/hyperv/vms", None, vm_request("web01")).await;
+    for _ in 0..2 {
+        let response = post(
+            &app,
+            "/api/v1/hyperv/vms/web01/snapshots",
+            Some("snap-1"),
+            snapshot.clone(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+    let snapshots: Vec<SnapshotDto> = list(&app, "/api/v1/hyperv/vms/web01/snapshots").await;
+    assert_eq!(snapshots.len(), 1);
+}
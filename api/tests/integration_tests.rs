@@ -12,7 +12,7 @@ use api::{create_router, ApiResponse, AppState};
 use std::sync::Arc;
 
 fn create_test_app() -> axum::Router {
-    let state = Arc::new(AppState);
+    let state = Arc::new(AppState::default());
     create_router(state)
 }
 
@@ -0,0 +1,106 @@
+//! Integration tests for filtering, sorting and pagination of list routes
+
+use axum::{http::StatusCode, Router};
+use serde_json::{json, Value};
+
+use api::ApiResponse;
+
+mod common;
+use common::{create_fake_app, send, vm_request};
+
+async fn create_app() -> Router {
+    let app = create_fake_app();
+    for (name, memory_mb) in [("web-01", 2048), ("web-02", 4096), ("sql-01", 16384)] {
+        let mut request = vm_request(name);
+        request["memory_mb"] = json!(memory_mb);
+        send(&app, "POST", "/api/v1/hyperv/vms", Some(request)).await;
+    }
+    send(&app, "POST", "/api/v1/hyperv/vms/web-02/start", None).await;
+    app
+}
+
+async fn list(app: &Router, uri: &str) -> ApiResponse<Vec<Value>> {
+    let (status, body) = send(app, "GET", uri, None).await;
+    assert_eq!(status, StatusCode::OK, "{}: {}", uri, body);
+    serde_json::from_value(body).unwrap()
+}
+
+fn names(response: &ApiResponse<Vec<Value>>) -> Vec<&str> {
+    response
+        .data
+        .as_ref()
+        .unwrap()
+        .iter()
+        .map(|vm| vm["name"].as_str().unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn test_filter_and_sort_vms() {
+    let app = create_app().await;
+
+    let response = list(&app, "/api/v1/hyperv/vms?name=web-*&sort=memory_mb:desc").await;
+    assert_eq!(names(&response), ["web-02", "web-01"]);
+    assert_eq!(response.total, Some(2));
+    assert!(response.next_cursor.is_none());
+
+    let response = list(&app, "/api/v1/hyperv/vms?state=Running").await;
+    assert_eq!(names(&response), ["web-02"]);
+
+    let response = list(&app, "/api/v1/hyperv/vms?sort=name&fields=name,state").await;
+    assert_eq!(
+        response.data.unwrap(),
+        [
+            json!({ "name": "sql-01", "state": "Off" }),
+            json!({ "name": "web-01", "state": "Off" }),
+            json!({ "name": "web-02", "state": "Running" }),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_cursor_pagination() {
+    let app = create_app().await;
+
+    let mut uri = "/api/v1/hyperv/vms?sort=name&limit=2".to_string();
+    let mut seen = Vec::new();
+    loop {
+        let response = list(&app, &uri).await;
+        assert_eq!(response.total, Some(3));
+        seen.extend(names(&response).into_iter().map(str::to_string));
+        match response.next_cursor {
+            Some(cursor) => uri = format!("/api/v1/hyperv/vms?sort=name&limit=2&cursor={}", cursor),
+            None => break,
+        }
+    }
+    assert_eq!(seen, ["sql-01", "web-01", "web-02"]);
+}
+
+#[tokio::test]
+async fn test_invalid_list_queries() {
+    let app = create_app().await;
+    for query in [
+        "colour=red",
+        "sort=size",
+        "sort=name:sideways",
+        "fields=name,secret",
+        "limit=0",
+        "limit=lots",
+        "cursor=bogus",
+    ] {
+        let (status, _) = send(&app, "GET", &format!("/api/v1/hyperv/vms?{}", query), None).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{}", query);
+    }
+}
+
+#[tokio::test]
+async fn test_cluster_name_is_not_a_filter() {
+    let app = create_app().await;
+    let all = list(&app, "/api/v1/cluster/nodes").await;
+    let named = list(&app, "/api/v1/cluster/nodes?name=FAKE-CLUSTER").await;
+    assert_eq!(all.total, named.total);
+    assert!(all.total.unwrap() > 0);
+
+    let filtered = list(&app, "/api/v1/cluster/nodes?state=Down").await;
+    assert_eq!(filtered.total, Some(0));
+}
@@ -0,0 +1,123 @@
+//! Integration tests for per-resource locking of mutating operations
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{http::StatusCode, Router};
+use serde_json::{json, Value};
+
+use api::backend::{FakeCluster, FakeHyperV};
+use api::config::LocksConfig;
+use api::{create_router, ApiResponse, AppState, JobDto, LockManager};
+
+mod common;
+use common::{create_vm, send};
+
+/// Router over a fake backend whose jobs take `delay`
+fn create_app(delay: Duration, locks: LocksConfig) -> Router {
+    let hyperv = FakeHyperV::new().with_operation_delay(delay);
+    let mut state = AppState::new(Arc::new(hyperv), Arc::new(FakeCluster::new()));
+    state.locks = Arc::new(LockManager::new(&locks));
+    create_router(Arc::new(state))
+}
+
+async fn start_job(app: &Router, uri: &str, body: Option<Value>) -> JobDto {
+    let (status, body) = send(app, "POST", uri, body).await;
+    assert_eq!(status, StatusCode::ACCEPTED, "{}", body);
+    serde_json::from_value::<ApiResponse<JobDto>>(body)
+        .unwrap()
+        .data
+        .unwrap()
+}
+
+async fn wait_job(app: &Router, id: u64) -> JobDto {
+    let (_, body) = send(
+        app,
+        "GET",
+        &format!("/api/v1/jobs/{}/wait?timeout_secs=10", id),
+        None,
+    )
+    .await;
+    serde_json::from_value::<ApiResponse<JobDto>>(body)
+        .unwrap()
+        .data
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_delete_conflicts_with_running_apply() {
+    let app = create_app(Duration::from_millis(500), LocksConfig::default());
+    create_vm(&app, "web01").await;
+    let (status, _) = send(
+        &app,
+        "POST",
+        "/api/v1/hyperv/vms/web01/snapshots",
+        Some(json!({ "name": "base" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let job = start_job(&app, "/api/v1/hyperv/vms/web01/snapshots/base/apply", None).await;
+
+    // Reads are never blocked; other VMs are unaffected
+    let (status, _) = send(&app, "GET", "/api/v1/hyperv/vms/web01", None).await;
+    assert_eq!(status, StatusCode::OK);
+    create_vm(&app, "web02").await;
+
+    let (status, body) = send(&app, "DELETE", "/api/v1/hyperv/vms/WEB01", None).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(
+        body["error"],
+        "Another operation on VM 'web01' is in progress"
+    );
+
+    assert_eq!(wait_job(&app, job.id).await.state, "Completed");
+    let (status, _) = send(&app, "DELETE", "/api/v1/hyperv/vms/web01", None).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_bounded_wait() {
+    let locks = LocksConfig {
+        wait_timeout_ms: 5000,
+        ..LocksConfig::default()
+    };
+    let app = create_app(Duration::from_millis(200), locks);
+    create_vm(&app, "web01").await;
+
+    let job = start_job(
+        &app,
+        "/api/v1/hyperv/vms/web01/export",
+        Some(json!({ "path": r"D:\Exports" })),
+    )
+    .await;
+
+    // Waits for the export to finish instead of failing
+    let (status, _) = send(&app, "POST", "/api/v1/hyperv/vms/web01/start", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(wait_job(&app, job.id).await.state, "Completed");
+}
+
+#[tokio::test]
+async fn test_concurrent_operation_cap() {
+    let locks = LocksConfig {
+        max_concurrent_operations: 1,
+        ..LocksConfig::default()
+    };
+    let app = create_app(Duration::from_millis(500), locks);
+    create_vm(&app, "web01").await;
+    create_vm(&app, "web02").await;
+
+    let job = start_job(
+        &app,
+        "/api/v1/hyperv/vms/web01/export",
+        Some(json!({ "path": r"D:\Exports" })),
+    )
+    .await;
+    let (status, _) = send(&app, "POST", "/api/v1/hyperv/vms/web02/start", None).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+
+    wait_job(&app, job.id).await;
+    let (status, _) = send(&app, "POST", "/api/v1/hyperv/vms/web02/start", None).await;
+    assert_eq!(status, StatusCode::OK);
+}
@@ -0,0 +1,116 @@
+//! Integration tests for the Prometheus `/metrics` endpoint
+
+use axum::{
+    body::Body,
+    http::{header, Request, StatusCode},
+    Router,
+};
+use tower::ServiceExt;
+
+use api::AppState;
+
+mod common;
+use common::{create_app, fake_config};
+
+async fn get(app: &Router, uri: &str) -> (StatusCode, String) {
+    let response = app
+        .clone()
+        .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, String::from_utf8(body.to_vec()).unwrap())
+}
+
+#[tokio::test]
+async fn test_request_metrics_use_route_templates() {
+    let app = create_app(&fake_config());
+
+    get(&app, "/api/v1/hyperv/vms").await;
+    let (status, _) = get(&app, "/api/v1/hyperv/vms/does-not-exist").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    get(&app, "/no/such/route").await;
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/metrics")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers()[header::CONTENT_TYPE]
+        .to_str()
+        .unwrap()
+        .starts_with("application/openmetrics-text"));
+
+    let (_, text) = get(&app, "/metrics").await;
+    assert!(text.contains(
+        r#"nodeagent_http_requests_total{method="GET",route="/api/v1/hyperv/vms",status_class="2xx"} 1"#
+    ));
+    assert!(text.contains(
+        r#"nodeagent_http_requests_total{method="GET",route="/api/v1/hyperv/vms/{name}",status_class="4xx"} 1"#
+    ));
+    assert!(text.contains(
+        r#"nodeagent_http_requests_total{method="GET",route="unmatched",status_class="4xx"} 1"#
+    ));
+    assert!(text.contains(
+        r#"nodeagent_http_request_duration_seconds_count{method="GET",route="/api/v1/hyperv/vms"} 1"#
+    ));
+    assert!(!text.contains("does-not-exist"));
+
+    // The scrape in progress is itself in flight; finished requests are not
+    assert!(text.contains(r#"nodeagent_http_requests_in_flight{method="GET",route="/metrics"} 1"#));
+    assert!(text.contains(
+        r#"nodeagent_http_requests_in_flight{method="GET",route="/api/v1/hyperv/vms"} 0"#
+    ));
+}
+
+#[tokio::test]
+async fn test_backend_call_metrics() {
+    let app = create_app(&fake_config());
+
+    get(&app, "/api/v1/hyperv/vms").await;
+    get(&app, "/api/v1/hyperv/vms/does-not-exist").await;
+    get(&app, "/api/v1/cluster/nodes").await;
+
+    let (_, text) = get(&app, "/metrics").await;
+    assert!(text.contains(
+        r#"nodeagent_backend_call_duration_seconds_count{backend="hyperv",operation="list_vms"} 1"#
+    ));
+    assert!(text
+        .contains(r#"nodeagent_backend_call_errors_total{backend="hyperv",operation="get_vm"} 1"#));
+    assert!(text.contains(
+        r#"nodeagent_backend_call_duration_seconds_count{backend="cluster",operation="list_nodes"} 1"#
+    ));
+    assert!(!text.contains(r#"backend_call_errors_total{backend="hyperv",operation="list_vms"}"#));
+}
+
+#[tokio::test]
+async fn test_metrics_configuration() {
+    let mut config = fake_config();
+    config.metrics.prefix = "agent".to_string();
+    config.metrics.names.http_requests = "requests".to_string();
+    let app = create_app(&config);
+
+    get(&app, "/health").await;
+    let (_, text) = get(&app, "/metrics").await;
+    assert!(
+        text.contains(r#"agent_requests_total{method="GET",route="/health",status_class="2xx"} 1"#)
+    );
+
+    let mut config = fake_config();
+    config.metrics.enabled = false;
+    let (status, _) = get(&create_app(&config), "/metrics").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let mut config = fake_config();
+    config.metrics.request_duration_buckets = vec![1.0, 1.0];
+    assert!(AppState::from_config(&config).is_err());
+}
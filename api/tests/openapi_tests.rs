@@ -0,0 +1,127 @@
+//! Tests that the OpenAPI document covers the route table and matches the
+//! checked-in contract in `openapi.json`
+//!
+//! After an intentional API change, regenerate the contract with
+//! `UPDATE_OPENAPI=1 cargo test -p api --test openapi_tests`.
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use serde_json::Value;
+use tower::ServiceExt;
+
+use api::{create_router, openapi, routes, AppState};
+use std::sync::Arc;
+
+const CONTRACT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/openapi.json");
+
+fn operation<'a>(spec: &'a Value, method: &str, path: &str) -> Option<&'a Value> {
+    spec["paths"]
+        .get(format!("{}{}", openapi::API_PREFIX, path))?
+        .get(method.to_ascii_lowercase())
+}
+
+#[test]
+fn test_every_route_is_documented() {
+    let spec = openapi::spec();
+    let table = routes::api_routes();
+
+    for policy in table.policies() {
+        let op = operation(spec, policy.method.as_str(), &policy.path)
+            .unwrap_or_else(|| panic!("{} {} missing from OpenAPI", policy.method, policy.path));
+        assert_eq!(op["x-required-role"], policy.role.as_str());
+    }
+
+    let documented: usize = spec["paths"]
+        .as_object()
+        .unwrap()
+        .values()
+        .map(|methods| methods.as_object().unwrap().len())
+        .sum();
+    assert_eq!(documented, table.policies().len());
+}
+
+#[test]
+fn test_operations_are_typed_from_handlers() {
+    let spec = openapi::spec();
+
+    let create = operation(spec, "POST", "/hyperv/vms").unwrap();
+    assert_eq!(create["operationId"], "hyperv_create_vm");
+    assert_eq!(
+        create["requestBody"]["content"]["application/json"]["schema"]["$ref"],
+        "#/components/schemas/CreateVmRequest"
+    );
+
+    let export = operation(spec, "POST", "/hyperv/vms/{name}/export").unwrap();
+    assert_eq!(
+        export["responses"]["202"]["content"]["application/json"]["schema"]["properties"]["data"]
+            ["$ref"],
+        "#/components/schemas/JobDto"
+    );
+
+    let job = operation(spec, "GET", "/jobs/{id}").unwrap();
+    assert_eq!(job["parameters"][0]["name"], "id");
+    assert_eq!(job["parameters"][0]["schema"]["type"], "integer");
+
+    let nodes = operation(spec, "GET", "/cluster/nodes").unwrap();
+    assert_eq!(nodes["parameters"][0]["name"], "name");
+    assert_eq!(nodes["parameters"][0]["in"], "query");
+
+    let events = operation(spec, "GET", "/events").unwrap();
+    assert_eq!(
+        events["responses"]["200"]["content"]["text/event-stream"]["schema"]["$ref"],
+        "#/components/schemas/EventDto"
+    );
+
+    let vm = &spec["components"]["schemas"]["VmDto"];
+    assert!(vm["properties"]["memory_mb"].is_object());
+}
+
+#[test]
+fn test_spec_matches_contract() {
+    let generated = serde_json::to_string_pretty(openapi::spec()).unwrap() + "\n";
+    if std::env::var_os("UPDATE_OPENAPI").is_some() {
+        std::fs::write(CONTRACT, &generated).unwrap();
+        return;
+    }
+    let contract = std::fs::read_to_string(CONTRACT).unwrap_or_default();
+    assert!(
+        contract == generated,
+        "openapi.json is out of date with the routes or DTOs; review the change and run \
+         `UPDATE_OPENAPI=1 cargo test -p api --test openapi_tests`"
+    );
+}
+
+#[tokio::test]
+async fn test_openapi_and_docs_are_served() {
+    let app = create_router(Arc::new(AppState::default()));
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/v1/openapi.json")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let spec: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(spec["openapi"], "3.1.0");
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/v1/docs")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
@@ -0,0 +1,188 @@
+//! Graceful shutdown of the `api` binary on SIGTERM
+#![cfg(unix)]
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+struct Server {
+    child: Child,
+    port: u16,
+    dir: PathBuf,
+    lines: mpsc::Receiver<String>,
+}
+
+impl Server {
+    /// Start the binary on a free port with the fake backend
+    fn start(name: &str, operation_delay_ms: u64, shutdown_timeout_secs: u64) -> Self {
+        let dir =
+            std::env::temp_dir().join(format!("api-shutdown-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+
+        let mut child = Command::new(env!("CARGO_BIN_EXE_api"))
+            .current_dir(&dir)
+            .env_remove("RUST_LOG")
+            .env("NODEAGENT_SERVER__HOST", "127.0.0.1")
+            .env("NODEAGENT_SERVER__PORT", port.to_string())
+            .env(
+                "NODEAGENT_SERVER__SHUTDOWN_TIMEOUT_SECS",
+                shutdown_timeout_secs.to_string(),
+            )
+            .env("NODEAGENT_SERVER__CONFIG_RELOAD_INTERVAL_SECS", "0")
+            .env("NODEAGENT_LOGGING__LEVEL", "info")
+            .env("NODEAGENT_BACKEND__KIND", "fake")
+            .env(
+                "NODEAGENT_BACKEND__OPERATION_DELAY_MS",
+                operation_delay_ms.to_string(),
+            )
+            .env("NODEAGENT_AUDIT__SINK", "file")
+            .env("NODEAGENT_AUDIT__PATH", dir.join("audit.jsonl"))
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .unwrap();
+
+        let (sender, lines) = mpsc::channel();
+        let stdout = child.stdout.take().unwrap();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                let _ = sender.send(line);
+            }
+        });
+
+        let server = Self {
+            child,
+            port,
+            dir,
+            lines,
+        };
+        let started = Instant::now();
+        while server.request("GET", "/health", None).is_none() {
+            assert!(
+                started.elapsed() < Duration::from_secs(10),
+                "server did not start"
+            );
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        server
+    }
+
+    /// Send one request and return the status code, or `None` if refused
+    fn request(&self, method: &str, path: &str, body: Option<&str>) -> Option<u16> {
+        let mut stream = TcpStream::connect(("127.0.0.1", self.port)).ok()?;
+        let body = body.unwrap_or("");
+        write!(
+            stream,
+            "{} {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\
+             Content-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            method,
+            path,
+            body.len(),
+            body
+        )
+        .ok()?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response).ok()?;
+        response.split(' ').nth(1)?.parse().ok()
+    }
+
+    fn terminate(&self) {
+        let status = Command::new("kill")
+            .args(["-TERM", &self.child.id().to_string()])
+            .status()
+            .unwrap();
+        assert!(status.success());
+    }
+
+    fn wait(&mut self, timeout: Duration) -> ExitStatus {
+        let started = Instant::now();
+        loop {
+            if let Some(status) = self.child.try_wait().unwrap() {
+                return status;
+            }
+            if started.elapsed() > timeout {
+                self.child.kill().unwrap();
+                panic!("server did not exit within {:?}", timeout);
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        }
+    }
+
+    /// Everything logged, once the process has exited
+    fn output(&self) -> String {
+        self.lines.iter().collect::<Vec<_>>().join("\n")
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+const CREATE_VM: &str = r#"{"name":"web01","memory_mb":1024,"vhd_path":"C:\\VMs\\web01.vhdx","vhd_size_bytes":10737418240}"#;
+const EXPORT_VM: &str = r#"{"path":"D:\\Exports"}"#;
+
+#[test]
+fn test_sigterm_waits_for_jobs_and_flushes_audit() {
+    let mut server = Server::start("drain", 1500, 30);
+    assert_eq!(
+        server.request("POST", "/api/v1/hyperv/vms", Some(CREATE_VM)),
+        Some(200)
+    );
+    assert_eq!(
+        server.request("POST", "/api/v1/hyperv/vms/web01/export", Some(EXPORT_VM)),
+        Some(202)
+    );
+
+    let signalled = Instant::now();
+    server.terminate();
+    let status = server.wait(Duration::from_secs(20));
+    assert!(status.success(), "exit status {:?}", status);
+    // The export job was allowed to finish
+    assert!(signalled.elapsed() >= Duration::from_millis(1000));
+
+    let output = server.output();
+    assert!(output.contains("Received SIGTERM"), "{}", output);
+    assert!(output.contains("Job 1 completed"), "{}", output);
+    assert!(output.contains("Shutdown complete"), "{}", output);
+    assert!(server.request("GET", "/health", None).is_none());
+
+    let audit = std::fs::read_to_string(server.dir.join("audit.jsonl")).unwrap();
+    assert_eq!(audit.lines().count(), 2);
+}
+
+#[test]
+fn test_sigterm_gives_up_at_deadline() {
+    let mut server = Server::start("deadline", 60_000, 1);
+    assert_eq!(
+        server.request("POST", "/api/v1/hyperv/vms", Some(CREATE_VM)),
+        Some(200)
+    );
+    assert_eq!(
+        server.request("POST", "/api/v1/hyperv/vms/web01/export", Some(EXPORT_VM)),
+        Some(202)
+    );
+
+    server.terminate();
+    let status = server.wait(Duration::from_secs(10));
+    assert!(status.success(), "exit status {:?}", status);
+
+    let output = server.output();
+    assert!(
+        output.contains("Abandoning 1 background job(s)"),
+        "{}",
+        output
+    );
+    assert!(output.contains("Shutdown complete"), "{}", output);
+}
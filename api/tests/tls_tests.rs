@@ -0,0 +1,257 @@
+//! Integration tests for TLS termination, mTLS and certificate rotation
+//!
+//! Certificates are generated per test with a throwaway CA.
+
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use rcgen::{
+    BasicConstraints, Certificate, CertificateParams, DnType, ExtendedKeyUsagePurpose, IsCa,
+    KeyPair,
+};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_rustls::rustls::{
+    self,
+    crypto::ring,
+    pki_types::{CertificateDer, PrivateKeyDer, ServerName},
+    RootCertStore,
+};
+use tokio_rustls::TlsConnector;
+
+use api::config::{AuthConfig, BackendKind, ClientCertConfig, TlsConfig, TlsVersion};
+use api::tls::TlsListener;
+use api::{create_router, AppState, Config, ConnectionInfo, Role};
+
+struct Ca {
+    cert: Certificate,
+    key: KeyPair,
+}
+
+impl Ca {
+    fn new() -> Self {
+        let key = KeyPair::generate().unwrap();
+        let mut params = CertificateParams::new(Vec::<String>::new()).unwrap();
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        params
+            .distinguished_name
+            .push(DnType::CommonName, "Test CA");
+        let cert = params.self_signed(&key).unwrap();
+        Self { cert, key }
+    }
+
+    fn issue(&self, common_name: &str, purpose: ExtendedKeyUsagePurpose) -> (String, String) {
+        let key = KeyPair::generate().unwrap();
+        let mut params = CertificateParams::new(vec!["localhost".to_string()]).unwrap();
+        params
+            .distinguished_name
+            .push(DnType::CommonName, common_name);
+        params
+            .distinguished_name
+            .push(DnType::OrganizationName, "Contoso");
+        params.extended_key_usages = vec![purpose];
+        let cert = params.signed_by(&key, &self.cert, &self.key).unwrap();
+        (cert.pem(), key.serialize_pem())
+    }
+}
+
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("api-tls-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+
+    fn write(&self, name: &str, content: &str) -> String {
+        let path = self.0.join(name);
+        std::fs::write(&path, content).unwrap();
+        path.display().to_string()
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+fn tls_config(dir: &TempDir, ca: &Ca, server_cn: &str) -> TlsConfig {
+    let (cert, key) = ca.issue(server_cn, ExtendedKeyUsagePurpose::ServerAuth);
+    TlsConfig {
+        cert_path: dir.write("server.pem", &cert),
+        key_path: dir.write("server.key", &key),
+        client_ca_path: Some(dir.write("ca.pem", &ca.cert.pem())),
+        require_client_cert: false,
+        min_version: TlsVersion::Tls12,
+        reload_interval_secs: 1,
+    }
+}
+
+async fn start_server(tls: &TlsConfig) -> SocketAddr {
+    let mut config = Config::default();
+    config.backend.kind = BackendKind::Fake;
+    config.auth = AuthConfig {
+        enabled: true,
+        client_certs: vec![ClientCertConfig {
+            subject: "CN=ops-automation, O=Contoso".to_string(),
+            role: Role::Operator,
+        }],
+        ..AuthConfig::default()
+    };
+    let app = create_router(Arc::new(AppState::from_config(&config).unwrap()));
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let listener = TlsListener::new(listener, tls).unwrap();
+    let addr = axum::serve::Listener::local_addr(&listener).unwrap();
+    tokio::spawn(async move {
+        axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<ConnectionInfo>(),
+        )
+        .await
+        .unwrap();
+    });
+    addr
+}
+
+fn client(ca: &Ca, identity: Option<(String, String)>) -> TlsConnector {
+    let mut roots = RootCertStore::empty();
+    roots.add(ca.cert.der().clone()).unwrap();
+    let builder = rustls::ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_root_certificates(roots);
+    let config = match identity {
+        Some((cert, key)) => {
+            let chain: Vec<CertificateDer<'static>> = rustls_pemfile::certs(&mut cert.as_bytes())
+                .collect::<Result<_, _>>()
+                .unwrap();
+            let key: PrivateKeyDer<'static> = rustls_pemfile::private_key(&mut key.as_bytes())
+                .unwrap()
+                .unwrap();
+            builder.with_client_auth_cert(chain, key).unwrap()
+        }
+        None => builder.with_no_client_auth(),
+    };
+    TlsConnector::from(Arc::new(config))
+}
+
+/// Send a GET over TLS and return the status code and server certificate CN
+async fn get(connector: &TlsConnector, addr: SocketAddr, path: &str) -> (u16, String) {
+    let tcp = TcpStream::connect(addr).await.unwrap();
+    let mut stream = connector
+        .connect(ServerName::try_from("localhost").unwrap(), tcp)
+        .await
+        .unwrap();
+    let server_cert = stream.get_ref().1.peer_certificates().unwrap()[0].clone();
+    let server_subject = api::ClientCertificate::from_der(&server_cert)
+        .unwrap()
+        .subject;
+
+    let request = format!(
+        "GET {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+        path
+    );
+    stream.write_all(request.as_bytes()).await.unwrap();
+    let mut response = String::new();
+    let _ = stream.read_to_string(&mut response).await;
+    let status = response
+        .split_whitespace()
+        .nth(1)
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(0);
+    (status, server_subject)
+}
+
+#[tokio::test]
+async fn test_serves_https() {
+    let dir = TempDir::new("https");
+    let ca = Ca::new();
+    let tls = tls_config(&dir, &ca, "node01");
+    let addr = start_server(&tls).await;
+
+    let (status, server) = get(&client(&ca, None), addr, "/health").await;
+    assert_eq!(status, 200);
+    assert!(server.contains("CN=node01"));
+}
+
+#[tokio::test]
+async fn test_client_certificate_maps_to_role() {
+    let dir = TempDir::new("mtls");
+    let ca = Ca::new();
+    let tls = tls_config(&dir, &ca, "node01");
+    let addr = start_server(&tls).await;
+
+    let identity = ca.issue("ops-automation", ExtendedKeyUsagePurpose::ClientAuth);
+    let (status, _) = get(&client(&ca, Some(identity)), addr, "/api/v1/hyperv/vms").await;
+    assert_eq!(status, 200);
+
+    let (status, _) = get(&client(&ca, None), addr, "/api/v1/hyperv/vms").await;
+    assert_eq!(status, 401);
+
+    let identity = ca.issue("intruder", ExtendedKeyUsagePurpose::ClientAuth);
+    let (status, _) = get(&client(&ca, Some(identity)), addr, "/api/v1/hyperv/vms").await;
+    assert_eq!(status, 401);
+}
+
+#[tokio::test]
+async fn test_required_client_certificate() {
+    let dir = TempDir::new("required");
+    let ca = Ca::new();
+    let tls = TlsConfig {
+        require_client_cert: true,
+        ..tls_config(&dir, &ca, "node01")
+    };
+    let addr = start_server(&tls).await;
+
+    // The server aborts the handshake; the client sees the failure on first use
+    let tcp = TcpStream::connect(addr).await.unwrap();
+    let result = client(&ca, None)
+        .connect(ServerName::try_from("localhost").unwrap(), tcp)
+        .await;
+    let failed = match result {
+        Err(_) => true,
+        Ok(mut stream) => {
+            let _ = stream
+                .write_all(b"GET /health HTTP/1.1\r\nHost: localhost\r\n\r\n")
+                .await;
+            let mut buf = Vec::new();
+            stream.read_to_end(&mut buf).await.is_err() || buf.is_empty()
+        }
+    };
+    assert!(failed);
+}
+
+#[tokio::test]
+async fn test_certificate_rotation() {
+    let dir = TempDir::new("rotation");
+    let ca = Ca::new();
+    let tls = tls_config(&dir, &ca, "node01");
+    let addr = start_server(&tls).await;
+
+    let connector = client(&ca, None);
+    let (_, server) = get(&connector, addr, "/health").await;
+    assert!(server.contains("CN=node01"));
+
+    // Modification times can have coarse resolution
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    let (cert, key) = ca.issue("node01-renewed", ExtendedKeyUsagePurpose::ServerAuth);
+    dir.write("server.key", &key);
+    dir.write("server.pem", &cert);
+
+    let mut rotated = false;
+    for _ in 0..30 {
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let (status, server) = get(&connector, addr, "/health").await;
+        assert_eq!(status, 200);
+        if server.contains("CN=node01-renewed") {
+            rotated = true;
+            break;
+        }
+    }
+    assert!(rotated, "server certificate was not reloaded");
+}
@@ -0,0 +1,296 @@
+//! Integration tests for pre-flight request validation
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+    Router,
+};
+use serde_json::{json, Value};
+
+use api::error::PROBLEM_JSON;
+
+mod common;
+use common::{call, create_fake_app};
+
+/// Send `body` as is, so malformed JSON reaches the extractor
+async fn send(
+    app: &Router,
+    method: &str,
+    uri: &str,
+    body: &str,
+    accept: Option<&str>,
+) -> (StatusCode, Value) {
+    let mut request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header("content-type", "application/json");
+    if let Some(accept) = accept {
+        request = request.header("accept", accept);
+    }
+    call(app, request.body(Body::from(body.to_string())).unwrap()).await
+}
+
+fn fields(body: &Value) -> Vec<(&str, &str)> {
+    body["errors"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| (e["field"].as_str().unwrap(), e["code"].as_str().unwrap()))
+        .collect()
+}
+
+#[tokio::test]
+async fn test_create_vm_reports_every_bad_field() {
+    let app = create_fake_app();
+    let request = json!({
+        "name": "web:01",
+        "memory_mb": 8,
+        "cpu_count": 500,
+        "generation": 3,
+        "vhd_path": r"C:\VMs\web-01.vhdx",
+        "vhd_size_bytes": 0
+    });
+    let (status, body) = send(
+        &app,
+        "POST",
+        "/api/v1/hyperv/vms",
+        &request.to_string(),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(body["code"], "validation_failed");
+    assert_eq!(
+        fields(&body),
+        [
+            ("memory_mb", "out_of_range"),
+            ("cpu_count", "out_of_range"),
+            ("generation", "invalid_value"),
+            ("name", "invalid"),
+            ("vhd_size_bytes", "invalid"),
+        ]
+    );
+    assert_eq!(
+        body["errors"][3]["message"],
+        "VM name contains invalid characters"
+    );
+
+    // Nothing reached the backend
+    let (status, body) = send(&app, "GET", "/api/v1/hyperv/vms", "", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["total"], 0);
+}
+
+#[tokio::test]
+async fn test_update_vm_reports_every_bad_field() {
+    let app = create_fake_app();
+    let request = json!({
+        "memory_mb": 4096,
+        "cpu_count": 0,
+        "dynamic_memory_max_mb": 2048,
+        "memory_buffer_percentage": 150,
+        "automatic_start_delay_secs": 100000,
+        "automatic_start_action": "Sometimes",
+        "checkpoint_type": "Snapshot"
+    });
+    let (status, body) = send(
+        &app,
+        "PATCH",
+        "/api/v1/hyperv/vms/web-01",
+        &request.to_string(),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(
+        fields(&body),
+        [
+            ("cpu_count", "out_of_range"),
+            ("memory_buffer_percentage", "out_of_range"),
+            ("automatic_start_delay_secs", "out_of_range"),
+            ("automatic_start_action", "invalid_value"),
+            ("checkpoint_type", "invalid_value"),
+            ("dynamic_memory_max_mb", "invalid"),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_network_adapter_rules() {
+    let app = create_fake_app();
+    let request = json!({
+        "name": " ",
+        "mac_address": "00-15-5D-01-02",
+        "dynamic_mac": true,
+        "vlan_id": 5000,
+        "port_mirroring": "both",
+        "bandwidth": { "minimum_mbps": 500, "maximum_mbps": 100 }
+    });
+    let (status, body) = send(
+        &app,
+        "POST",
+        "/api/v1/hyperv/vms/web-01/adapters",
+        &request.to_string(),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(
+        fields(&body),
+        [
+            ("name", "required"),
+            ("dynamic_mac", "invalid"),
+            ("port_mirroring", "invalid_value"),
+            ("bandwidth.minimum_mbps", "invalid"),
+            ("mac_address", "invalid"),
+        ]
+    );
+
+    let (status, body) = send(
+        &app,
+        "PATCH",
+        "/api/v1/hyperv/vms/web-01/adapters/lan",
+        r#"{"vlan_id": 4095}"#,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(fields(&body), [("vlan_id", "invalid")]);
+}
+
+#[tokio::test]
+async fn test_processor_and_security_rules() {
+    let app = create_fake_app();
+    for (uri, request, expected) in [
+        (
+            "/api/v1/hyperv/vms/web-01/processor",
+            json!({
+                "count": 0,
+                "limit_percent": 150.0,
+                "weight": 0,
+                "hw_threads_per_core": 9,
+                "cpu_group_id": " "
+            }),
+            vec![
+                ("limit_percent", "invalid"),
+                ("weight", "out_of_range"),
+                ("hw_threads_per_core", "invalid"),
+                ("cpu_group_id", "required"),
+                ("count", "invalid"),
+            ],
+        ),
+        (
+            "/api/v1/hyperv/vms/web-01/processor",
+            json!({ "count": 4, "limit_percent": 20.0, "reservation_percent": 50.0 }),
+            vec![("reservation_percent", "invalid")],
+        ),
+        (
+            "/api/v1/hyperv/vms/web-01/processor",
+            json!({
+                "count": 4,
+                "numa_nodes": [{ "id": 0, "processor_count": 2, "memory_mb": 2048 }]
+            }),
+            vec![("numa_nodes", "invalid")],
+        ),
+        (
+            "/api/v1/hyperv/vms/web-01/security",
+            json!({
+                "secure_boot_template": "Ubuntu",
+                "guest_isolation_type": "vbs",
+                "key_protector": "Cloud"
+            }),
+            vec![
+                ("secure_boot_template", "invalid_value"),
+                ("key_protector", "invalid_value"),
+                ("tpm_enabled", "invalid"),
+            ],
+        ),
+        (
+            "/api/v1/hyperv/vms/web-01/security",
+            json!({ "secure_boot_enabled": true }),
+            vec![("secure_boot_template", "invalid")],
+        ),
+        (
+            "/api/v1/hyperv/vms/web-01/security",
+            json!({ "secure_boot_template": "MicrosoftWindows", "shielding_requested": true }),
+            vec![("tpm_enabled", "invalid")],
+        ),
+    ] {
+        let (status, body) = send(&app, "PUT", uri, &request.to_string(), None).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{}", request);
+        assert_eq!(fields(&body), expected, "{}", request);
+    }
+}
+
+#[tokio::test]
+async fn test_problem_details_carry_errors() {
+    let app = create_fake_app();
+    let request = json!({ "name": "ext", "switch_type": "External" });
+    let (status, body) = send(
+        &app,
+        "POST",
+        "/api/v1/hyperv/switches",
+        &request.to_string(),
+        Some(PROBLEM_JSON),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(body["type"], "urn:nodeagent:error:validation_failed");
+    assert_eq!(fields(&body), [("network_adapter", "invalid")]);
+}
+
+#[tokio::test]
+async fn test_vhd_and_disk_rules() {
+    let app = create_fake_app();
+    for (uri, request, expected) in [
+        (
+            "/api/v1/hyperv/vhds",
+            json!({ "path": r"C:\VMs\data.vhd", "size_bytes": 3u64 << 40 }),
+            ("size_bytes", "invalid"),
+        ),
+        (
+            "/api/v1/hyperv/vhds",
+            json!({ "path": r"C:\VMs\data.vhdx", "size_bytes": 1024, "vhd_type": "Sparse" }),
+            ("vhd_type", "invalid_value"),
+        ),
+        (
+            "/api/v1/hyperv/vms/web-01/disks/attach",
+            json!({ "vhd_path": "" }),
+            ("vhd_path", "required"),
+        ),
+        (
+            "/api/v1/hyperv/vms/web-01/snapshots",
+            json!({ "name": "x".repeat(101) }),
+            ("name", "invalid"),
+        ),
+        (
+            "/api/v1/hyperv/vms/web-01/snapshots/base/rename",
+            json!({ "new_name": "" }),
+            ("new_name", "invalid"),
+        ),
+    ] {
+        let (status, body) = send(&app, "POST", uri, &request.to_string(), None).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{}", uri);
+        assert_eq!(fields(&body), [expected], "{}", uri);
+    }
+}
+
+#[tokio::test]
+async fn test_unparseable_bodies_use_the_error_envelope() {
+    let app = create_fake_app();
+    let (status, body) = send(
+        &app,
+        "POST",
+        "/api/v1/hyperv/vms",
+        r#"{"name": "web-01"}"#,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(body["code"], "validation_failed");
+    assert!(body["error"].as_str().unwrap().contains("memory_mb"));
+
+    let (status, body) = send(&app, "POST", "/api/v1/hyperv/vms", "{", None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], "bad_request");
+}
@@ -0,0 +1,408 @@
+//! Integration tests for webhook subscriptions and deliveries, against a
+//! local HTTP receiver
+
+use std::future::IntoFuture;
+use std::sync::atomic::{AtomicU16, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    routing::post,
+    Router,
+};
+use serde_json::json;
+
+use api::config::{WebhookConfig, WebhooksConfig};
+use api::events::Change;
+use api::{
+    create_router, AppState, Config, CreateWebhookRequest, DeliveryState, EventDto, EventKind,
+    WebhookDeliveryDto, WebhookDeliveryQuery,
+};
+
+mod common;
+use common::{create_vm, fake_config, send};
+
+/// Stand-in webhook receiver that records requests and answers `status`
+#[derive(Clone)]
+struct Receiver {
+    url: String,
+    requests: Arc<Mutex<Vec<(HeaderMap, Bytes)>>>,
+    status: Arc<AtomicU16>,
+}
+
+impl Receiver {
+    async fn start(status: u16) -> Self {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let receiver = Receiver {
+            url: format!("http://{}/hook", listener.local_addr().unwrap()),
+            requests: Arc::default(),
+            status: Arc::new(AtomicU16::new(status)),
+        };
+        let app = Router::new()
+            .route(
+                "/hook",
+                post(
+                    |State(receiver): State<Receiver>, headers: HeaderMap, body: Bytes| async move {
+                        receiver.requests.lock().unwrap().push((headers, body));
+                        StatusCode::from_u16(receiver.status.load(Ordering::SeqCst)).unwrap()
+                    },
+                ),
+            )
+            .with_state(receiver.clone());
+        tokio::spawn(axum::serve(listener, app).into_future());
+        receiver
+    }
+
+    fn count(&self) -> usize {
+        self.requests.lock().unwrap().len()
+    }
+}
+
+fn config(webhooks: WebhooksConfig) -> Config {
+    let mut config = fake_config();
+    config.events.poll_interval_ms = 20;
+    config.webhooks = WebhooksConfig {
+        initial_backoff_ms: 10,
+        max_backoff_ms: 40,
+        timeout_ms: 2000,
+        ..webhooks
+    };
+    config
+}
+
+fn change(kind: EventKind, vm: &str) -> Change {
+    Change {
+        kind,
+        subject: vm.to_string(),
+        vm_name: Some(vm.to_string()),
+        previous: None,
+        current: None,
+    }
+}
+
+/// Wait up to 5 s for `done`
+async fn eventually(mut done: impl FnMut() -> bool) {
+    for _ in 0..500 {
+        if done() {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("condition not met within 5 s");
+}
+
+fn deliveries(state: &AppState, delivery_state: DeliveryState) -> Vec<WebhookDeliveryDto> {
+    state.webhooks.deliveries(&WebhookDeliveryQuery {
+        state: Some(delivery_state),
+        ..WebhookDeliveryQuery::default()
+    })
+}
+
+#[tokio::test]
+async fn test_created_vm_is_delivered_signed() {
+    let receiver = Receiver::start(200).await;
+    let state = Arc::new(AppState::from_config(&config(WebhooksConfig::default())).unwrap());
+    let app = create_router(state.clone());
+
+    let (status, body) = send(
+        &app,
+        "POST",
+        "/api/v1/webhooks",
+        Some(json!({
+            "name": "cmdb",
+            "url": receiver.url,
+            "secret": "s3cret",
+            "events": ["vm_created"]
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["data"]["source"], "api");
+    assert!(body["data"].get("secret").is_none());
+
+    // Let the event watcher take its baseline
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    create_vm(&app, "web-01").await;
+    eventually(|| receiver.count() == 1).await;
+
+    let (headers, body) = receiver.requests.lock().unwrap()[0].clone();
+    let event: EventDto = serde_json::from_slice(&body).unwrap();
+    assert_eq!(event.kind, EventKind::VmCreated);
+    assert_eq!(event.subject, "web-01");
+    assert_eq!(headers["x-webhook-event"], "vm_created");
+    let timestamp: i64 = headers["x-webhook-timestamp"]
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert_eq!(
+        headers["x-webhook-signature"],
+        api::webhooks::sign(b"s3cret", timestamp, &body).as_str()
+    );
+
+    eventually(|| deliveries(&state, DeliveryState::Delivered).len() == 1).await;
+    let (_, body) = send(
+        &app,
+        "GET",
+        "/api/v1/webhooks/deliveries?webhook=cmdb",
+        None,
+    )
+    .await;
+    let history: Vec<WebhookDeliveryDto> = serde_json::from_value(body["data"].clone()).unwrap();
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].attempts, 1);
+    assert_eq!(history[0].last_status, Some(200));
+    assert_eq!(headers["x-webhook-id"], history[0].id.to_string().as_str());
+}
+
+#[tokio::test]
+async fn test_failures_retry_then_dead_letter() {
+    let receiver = Receiver::start(503).await;
+    let state = Arc::new(
+        AppState::from_config(&config(WebhooksConfig {
+            max_attempts: 3,
+            subscriptions: vec![WebhookConfig {
+                name: "ticketing".to_string(),
+                url: receiver.url.clone(),
+                secret: "s3cret".to_string(),
+                events: Vec::new(),
+                vm: Some("db-01".to_string()),
+            }],
+            ..WebhooksConfig::default()
+        }))
+        .unwrap(),
+    );
+    let app = create_router(state.clone());
+    state.webhooks.start(&state);
+
+    state.events.publish(change(EventKind::VmDeleted, "web-01"));
+    state.events.publish(change(EventKind::VmDeleted, "db-01"));
+    eventually(|| deliveries(&state, DeliveryState::DeadLettered).len() == 1).await;
+    assert_eq!(receiver.count(), 3);
+
+    let (status, body) = send(&app, "GET", "/api/v1/webhooks/dead-letters", None).await;
+    assert_eq!(status, StatusCode::OK);
+    let dead: Vec<WebhookDeliveryDto> = serde_json::from_value(body["data"].clone()).unwrap();
+    assert_eq!(dead.len(), 1);
+    assert_eq!(dead[0].event.subject, "db-01");
+    assert_eq!(dead[0].attempts, 3);
+    assert_eq!(dead[0].last_status, Some(503));
+
+    // Same id on every attempt
+    let ids: Vec<_> = receiver
+        .requests
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(headers, _)| headers["x-webhook-id"].clone())
+        .collect();
+    assert!(ids.iter().all(|id| *id == ids[0]));
+
+    receiver.status.store(204, Ordering::SeqCst);
+    let uri = format!("/api/v1/webhooks/dead-letters/{}/retry", dead[0].id);
+    let (status, _) = send(&app, "POST", &uri, None).await;
+    assert_eq!(status, StatusCode::OK);
+    eventually(|| deliveries(&state, DeliveryState::Delivered).len() == 1).await;
+    let (status, body) = send(&app, "POST", &uri, None).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body["code"], "conflict");
+}
+
+#[tokio::test]
+async fn test_client_errors_are_not_retried() {
+    let receiver = Receiver::start(400).await;
+    let state = Arc::new(AppState::from_config(&config(WebhooksConfig::default())).unwrap());
+    let app = create_router(state.clone());
+    let (status, _) = send(
+        &app,
+        "POST",
+        "/api/v1/webhooks",
+        Some(json!({"name": "cmdb", "url": receiver.url, "secret": "s3cret"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    state
+        .events
+        .publish(change(EventKind::SnapshotCreated, "web-01"));
+    eventually(|| deliveries(&state, DeliveryState::DeadLettered).len() == 1).await;
+    assert_eq!(receiver.count(), 1);
+}
+
+#[tokio::test]
+async fn test_queue_survives_restart() {
+    let receiver = Receiver::start(200).await;
+    let path = std::env::temp_dir().join(format!("api-webhooks-{}.json", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let config = config(WebhooksConfig {
+        state_path: Some(path.display().to_string()),
+        ..WebhooksConfig::default()
+    });
+
+    // Queued, but never dispatched
+    let state = AppState::from_config(&config).unwrap();
+    state
+        .webhooks
+        .create(&CreateWebhookRequest {
+            name: "cmdb".to_string(),
+            url: receiver.url.clone(),
+            secret: "s3cret".to_string(),
+            events: Vec::new(),
+            vm: None,
+        })
+        .unwrap();
+    let event = state
+        .events
+        .publish(change(EventKind::GroupMoved, "web-01"));
+    assert_eq!(state.webhooks.enqueue(&event), 1);
+    drop(state);
+
+    let state = Arc::new(AppState::from_config(&config).unwrap());
+    assert_eq!(state.webhooks.list()[0].name, "cmdb");
+    assert_eq!(deliveries(&state, DeliveryState::Pending).len(), 1);
+    state.webhooks.start(&state);
+    eventually(|| receiver.count() == 1).await;
+    eventually(|| deliveries(&state, DeliveryState::Delivered).len() == 1).await;
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn test_configured_webhook_shadows_api_webhook() {
+    let receiver = Receiver::start(200).await;
+    let state = Arc::new(AppState::from_config(&config(WebhooksConfig::default())).unwrap());
+    state
+        .webhooks
+        .create(&CreateWebhookRequest {
+            name: "cmdb".to_string(),
+            url: receiver.url.clone(),
+            secret: "s3cret".to_string(),
+            events: Vec::new(),
+            vm: None,
+        })
+        .unwrap();
+    state.webhooks.reconfigure(&WebhooksConfig {
+        subscriptions: vec![WebhookConfig {
+            name: "cmdb".to_string(),
+            url: receiver.url.clone(),
+            secret: "s3cret".to_string(),
+            events: Vec::new(),
+            vm: None,
+        }],
+        ..WebhooksConfig::default()
+    });
+    state.webhooks.start(&state);
+
+    state.events.publish(change(EventKind::VmDeleted, "web-01"));
+    state.events.publish(change(EventKind::VmDeleted, "web-02"));
+    eventually(|| deliveries(&state, DeliveryState::Delivered).len() == 2).await;
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(receiver.count(), 2);
+    assert_eq!(deliveries(&state, DeliveryState::Pending).len(), 0);
+    assert_eq!(state.webhooks.list().len(), 1);
+}
+
+#[tokio::test]
+async fn test_removed_webhook_dead_letters_pending_deliveries() {
+    let receiver = Receiver::start(200).await;
+    let state = Arc::new(
+        AppState::from_config(&config(WebhooksConfig {
+            subscriptions: vec![WebhookConfig {
+                name: "ticketing".to_string(),
+                url: receiver.url.clone(),
+                secret: "s3cret".to_string(),
+                events: Vec::new(),
+                vm: None,
+            }],
+            ..WebhooksConfig::default()
+        }))
+        .unwrap(),
+    );
+
+    // Queued, but never dispatched
+    let event = state.events.publish(change(EventKind::VmDeleted, "web-01"));
+    assert_eq!(state.webhooks.enqueue(&event), 1);
+    state.webhooks.reconfigure(&WebhooksConfig::default());
+
+    let dead = deliveries(&state, DeliveryState::DeadLettered);
+    assert_eq!(dead.len(), 1);
+    assert_eq!(dead[0].webhook, "ticketing");
+    assert_eq!(dead[0].attempts, 0);
+    assert!(dead[0].next_attempt_at.is_none());
+    assert!(dead[0].last_error.as_deref().unwrap().contains("removed"));
+    assert!(deliveries(&state, DeliveryState::Pending).is_empty());
+
+    // A retry has nowhere to go either
+    state.webhooks.retry(dead[0].id).unwrap();
+    state.webhooks.start(&state);
+    eventually(|| deliveries(&state, DeliveryState::DeadLettered).len() == 1).await;
+    assert_eq!(receiver.count(), 0);
+}
+
+#[tokio::test]
+async fn test_manage_subscriptions() {
+    let state = Arc::new(
+        AppState::from_config(&config(WebhooksConfig {
+            subscriptions: vec![WebhookConfig {
+                name: "ticketing".to_string(),
+                url: "https://tickets.example/hooks".to_string(),
+                secret: "s3cret".to_string(),
+                events: vec![EventKind::GroupMoved],
+                vm: None,
+            }],
+            ..WebhooksConfig::default()
+        }))
+        .unwrap(),
+    );
+    let app = create_router(state);
+
+    let (status, body) = send(
+        &app,
+        "POST",
+        "/api/v1/webhooks",
+        Some(json!({"name": "deliveries", "url": "cmdb.example", "secret": ""})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    let fields: Vec<_> = body["errors"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| e["field"].as_str().unwrap())
+        .collect();
+    assert_eq!(fields, ["name", "url", "secret"]);
+
+    let (status, _) = send(
+        &app,
+        "POST",
+        "/api/v1/webhooks",
+        Some(json!({"name": "ticketing", "url": "https://cmdb.example", "secret": "x"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let (status, _) = send(
+        &app,
+        "POST",
+        "/api/v1/webhooks",
+        Some(json!({"name": "cmdb", "url": "https://cmdb.example", "secret": "x"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (_, body) = send(&app, "GET", "/api/v1/webhooks", None).await;
+    assert_eq!(body["data"][0]["name"], "ticketing");
+    assert_eq!(body["data"][0]["source"], "config");
+    assert_eq!(body["data"][0]["events"], json!(["group_moved"]));
+    assert_eq!(body["data"][1]["name"], "cmdb");
+
+    let (status, _) = send(&app, "DELETE", "/api/v1/webhooks/ticketing", None).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let (status, _) = send(&app, "DELETE", "/api/v1/webhooks/cmdb", None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, body) = send(&app, "GET", "/api/v1/webhooks/cmdb", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["code"], "webhook_not_found");
+}
@@ -5,6 +5,7 @@
 use crate::disk::{self, DvdDrive, FileSystem, HardDiskDrive, PartitionStyle, WindowsEdition};
 use crate::error::{HvError, Result};
 use crate::gpu::{self, AssignableDevice, DdaSupportInfo, GpuInfo, GpuPartitionAdapter};
+use crate::powershell;
 use crate::snapshot::{self, Snapshot, SnapshotType};
 use crate::switch::{self, SwitchType, VirtualSwitch};
 use crate::vhd::{self, Vhd, VhdType};
@@ -197,6 +198,25 @@ impl HyperV {
         Ok(())
     }
 
+    /// Upgrades a VM's configuration version to the host's default
+    pub fn update_vm_version(&self, name: &str) -> Result<()> {
+        let output = Command::new("powershell")
+            .args([
+                "-NoProfile",
+                "-Command",
+                &powershell::update_vm_version(name),
+            ])
+            .output()
+            .map_err(|e| HvError::OperationFailed(e.to_string()))?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(HvError::OperationFailed(stderr.to_string()));
+        }
+
+        Ok(())
+    }
+
     // =========================================================================
     // Virtual Switch Operations
     // =========================================================================
@@ -269,6 +289,11 @@ impl HyperV {
         snapshot::get_snapshot(vm_name, snapshot_name)
     }
 
+    /// Gets the ID of the snapshot the VM's current state is based on
+    pub fn current_snapshot_id(&self, vm_name: &str) -> Result<Option<String>> {
+        snapshot::current_snapshot_id(vm_name)
+    }
+
     /// Creates a new snapshot for a VM
     pub fn create_snapshot(
         &self,
@@ -655,6 +680,52 @@ impl HyperV {
         })
     }
 
+    /// Lists the VM configuration versions the host supports
+    pub fn supported_vm_versions(&self) -> Result<Vec<SupportedVmVersion>> {
+        let output = Command::new("powershell")
+            .args([
+                "-NoProfile",
+                "-Command",
+                "Get-VMHostSupportedVersion | \
+                 Select-Object @{n='Version';e={$_.Version.ToString()}}, IsDefault | \
+                 ConvertTo-Json -Compress",
+            ])
+            .output()
+            .map_err(|e| HvError::OperationFailed(e.to_string()))?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(HvError::OperationFailed(stderr.to_string()));
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let trimmed = stdout.trim();
+
+        if trimmed.is_empty() || trimmed == "null" {
+            return Ok(Vec::new());
+        }
+
+        // Handle both single object and array
+        let versions: Vec<SupportedVersionJson> = if trimmed.starts_with('[') {
+            serde_json::from_str(trimmed)
+                .map_err(|e| HvError::JsonError(format!("Failed to parse versions: {}", e)))?
+        } else {
+            let single: SupportedVersionJson = serde_json::from_str(trimmed)
+                .map_err(|e| HvError::JsonError(format!("Failed to parse version: {}", e)))?;
+            vec![single]
+        };
+
+        Ok(versions
+            .into_iter()
+            .filter_map(|v| {
+                Some(SupportedVmVersion {
+                    version: v.version?,
+                    is_default: v.is_default.unwrap_or(false),
+                })
+            })
+            .collect())
+    }
+
     /// Lists available physical network adapters
     pub fn list_network_adapters(&self) -> Result<Vec<NetworkAdapterInfo>> {
         let output = Command::new("powershell")
@@ -721,6 +792,20 @@ struct HostInfoJson {
     virtual_hard_disk_path: Option<String>,
 }
 
+/// VM configuration version supported by the host
+#[derive(Debug, Clone)]
+pub struct SupportedVmVersion {
+    pub version: String,
+    pub is_default: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct SupportedVersionJson {
+    version: Option<String>,
+    is_default: Option<bool>,
+}
+
 /// Network adapter information
 #[derive(Debug, Clone)]
 pub struct NetworkAdapterInfo {
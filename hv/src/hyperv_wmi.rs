@@ -4,6 +4,7 @@
 //! using WMI (Msvm_* classes) instead of HCS or PowerShell.
 
 use crate::error::Result;
+use crate::vm_config::{VmConfig, VmConfigUpdate};
 use crate::wmi::msvm::*;
 use crate::wmi::operations::{self, VhdFormat, VhdType};
 use crate::wmi::WmiConnection;
@@ -199,6 +200,18 @@ impl HyperVWmi {
         operations::configure_vm_processor(&self.conn, &vm.id, cpu_count)
     }
 
+    /// Get VM settings beyond memory size and processor count
+    pub fn vm_config(&self, vm_name: &str) -> Result<VmConfig> {
+        let vm = self.get_vm(vm_name)?;
+        operations::get_vm_config(&self.conn, &vm.id)
+    }
+
+    /// Change dynamic memory, automatic actions, checkpoint type or notes
+    pub fn set_vm_config(&self, vm_name: &str, update: &VmConfigUpdate) -> Result<()> {
+        let vm = self.get_vm(vm_name)?;
+        operations::configure_vm_config(&self.conn, &vm.id, update)
+    }
+
     /// Add a VHD to a VM
     pub fn add_vhd(&self, vm_name: &str, vhd_path: &str) -> Result<()> {
         let vm = self.get_vm(vm_name)?;
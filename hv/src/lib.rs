@@ -38,6 +38,7 @@ mod hcs;
 mod hyperv;
 #[cfg(windows)]
 mod hyperv_wmi;
+mod powershell;
 #[cfg(windows)]
 mod snapshot;
 #[cfg(windows)]
@@ -46,6 +47,7 @@ mod switch;
 mod vhd;
 #[cfg(windows)]
 mod vm;
+mod vm_config;
 #[cfg(windows)]
 pub mod wmi;
 
@@ -66,3 +68,4 @@ pub use switch::{SwitchType, VirtualSwitch};
 pub use vhd::{Vhd, VhdFormat, VhdType};
 #[cfg(windows)]
 pub use vm::{Vm, VmGeneration, VmState};
+pub use vm_config::{CheckpointType, StartAction, StopAction, VmConfig, VmConfigUpdate};
@@ -0,0 +1,90 @@
+//! PowerShell command construction
+//!
+//! Values that reach a `-Command` string (VM names, checkpoint names)
+//! come straight from API callers, so they are always emitted as
+//! single-quoted literals with embedded quotes doubled.
+
+// The callers are Windows-only; the builders are kept portable for testing.
+#![cfg_attr(not(windows), allow(dead_code))]
+
+/// Characters PowerShell treats as a single quote inside a verbatim string
+const SINGLE_QUOTES: [char; 5] = ['\'', '\u{2018}', '\u{2019}', '\u{201A}', '\u{201B}'];
+
+/// Quotes a value as a single-quoted PowerShell string literal
+pub(crate) fn quote(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('\'');
+    for c in value.chars() {
+        if SINGLE_QUOTES.contains(&c) {
+            quoted.push(c);
+        }
+        quoted.push(c);
+    }
+    quoted.push('\'');
+    quoted
+}
+
+/// Returns true when stderr reports that `Get-VM` (or a `-VMName` cmdlet)
+/// could not find the VM, as opposed to any other failure
+pub(crate) fn is_vm_not_found(stderr: &str) -> bool {
+    stderr.contains("unable to find a virtual machine")
+}
+
+/// Command that upgrades a VM's configuration version
+pub(crate) fn update_vm_version(vm_name: &str) -> String {
+    format!(
+        "Update-VMVersion -Name {} -Force -Confirm:$false",
+        quote(vm_name)
+    )
+}
+
+/// Command that prints the ID of the checkpoint a VM is running from
+pub(crate) fn parent_snapshot_id(vm_name: &str) -> String {
+    format!(
+        "(Get-VM -Name {} -ErrorAction Stop).ParentSnapshotId",
+        quote(vm_name)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quote_doubles_single_quotes() {
+        assert_eq!(quote("web-01"), "'web-01'");
+        assert_eq!(quote("it's"), "'it''s'");
+        assert_eq!(quote("a\u{2019}b"), "'a\u{2019}\u{2019}b'");
+        assert_eq!(quote(""), "''");
+    }
+
+    #[test]
+    fn test_update_vm_version_escapes_name() {
+        assert_eq!(
+            update_vm_version("web-01"),
+            "Update-VMVersion -Name 'web-01' -Force -Confirm:$false"
+        );
+        assert_eq!(
+            update_vm_version("x'; Remove-VM -Name * -Force; '"),
+            "Update-VMVersion -Name 'x''; Remove-VM -Name * -Force; ''' -Force -Confirm:$false"
+        );
+    }
+
+    #[test]
+    fn test_parent_snapshot_id_escapes_name() {
+        assert_eq!(
+            parent_snapshot_id("x'; Stop-Computer; '"),
+            "(Get-VM -Name 'x''; Stop-Computer; ''' -ErrorAction Stop).ParentSnapshotId"
+        );
+    }
+
+    #[test]
+    fn test_is_vm_not_found() {
+        assert!(is_vm_not_found(
+            "Get-VM : Hyper-V was unable to find a virtual machine with name \"web-01\"."
+        ));
+        assert!(!is_vm_not_found(
+            "Get-VM : You do not have the required permission to complete this task."
+        ));
+    }
+}
@@ -3,6 +3,7 @@
 //! Provides snapshot creation, restoration, and management for Hyper-V VMs.
 
 use crate::error::{HvError, Result};
+use crate::powershell;
 use serde::{Deserialize, Serialize};
 use std::process::Command;
 
@@ -60,16 +61,21 @@ impl Snapshot {
         &self.vm_name
     }
 
+    /// Selects this snapshot by ID; names need not be unique within a VM
+    fn select(&self) -> String {
+        format!(
+            "Get-VMSnapshot -Id {}",
+            powershell::quote(&self.snapshot_id)
+        )
+    }
+
     /// Gets the creation time of the snapshot
     pub fn creation_time(&self) -> Result<String> {
         let output = Command::new("powershell")
             .args([
                 "-NoProfile",
                 "-Command",
-                &format!(
-                    "(Get-VMSnapshot -VMName '{}' -Name '{}').CreationTime.ToString('o')",
-                    self.vm_name, self.name
-                ),
+                &format!("({}).CreationTime.ToString('o')", self.select()),
             ])
             .output()
             .map_err(|e| HvError::OperationFailed(e.to_string()))?;
@@ -88,10 +94,33 @@ impl Snapshot {
             .args([
                 "-NoProfile",
                 "-Command",
-                &format!(
-                    "(Get-VMSnapshot -VMName '{}' -Name '{}').ParentSnapshotName",
-                    self.vm_name, self.name
-                ),
+                &format!("({}).ParentSnapshotName", self.select()),
+            ])
+            .output()
+            .map_err(|e| HvError::OperationFailed(e.to_string()))?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(HvError::OperationFailed(stderr.to_string()));
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let parent = stdout.trim();
+
+        if parent.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(parent.to_string()))
+        }
+    }
+
+    /// Gets the parent snapshot ID (if any)
+    pub fn parent_id(&self) -> Result<Option<String>> {
+        let output = Command::new("powershell")
+            .args([
+                "-NoProfile",
+                "-Command",
+                &format!("({}).ParentSnapshotId", self.select()),
             ])
             .output()
             .map_err(|e| HvError::OperationFailed(e.to_string()))?;
@@ -117,10 +146,7 @@ impl Snapshot {
             .args([
                 "-NoProfile",
                 "-Command",
-                &format!(
-                    "Restore-VMSnapshot -VMName '{}' -Name '{}' -Confirm:$false",
-                    self.vm_name, self.name
-                ),
+                &format!("{} | Restore-VMSnapshot -Confirm:$false", self.select()),
             ])
             .output()
             .map_err(|e| HvError::OperationFailed(e.to_string()))?;
@@ -139,10 +165,7 @@ impl Snapshot {
             .args([
                 "-NoProfile",
                 "-Command",
-                &format!(
-                    "Remove-VMSnapshot -VMName '{}' -Name '{}' -Confirm:$false",
-                    self.vm_name, self.name
-                ),
+                &format!("{} | Remove-VMSnapshot -Confirm:$false", self.select()),
             ])
             .output()
             .map_err(|e| HvError::OperationFailed(e.to_string()))?;
@@ -162,8 +185,8 @@ impl Snapshot {
                 "-NoProfile",
                 "-Command",
                 &format!(
-                    "Remove-VMSnapshot -VMName '{}' -Name '{}' -IncludeAllChildSnapshots -Confirm:$false",
-                    self.vm_name, self.name
+                    "{} | Remove-VMSnapshot -IncludeAllChildSnapshots -Confirm:$false",
+                    self.select()
                 ),
             ])
             .output()
@@ -184,8 +207,9 @@ impl Snapshot {
                 "-NoProfile",
                 "-Command",
                 &format!(
-                    "Rename-VMSnapshot -VMName '{}' -Name '{}' -NewName '{}'",
-                    self.vm_name, self.name, new_name
+                    "{} | Rename-VMSnapshot -NewName {}",
+                    self.select(),
+                    powershell::quote(new_name)
                 ),
             ])
             .output()
@@ -207,8 +231,9 @@ impl Snapshot {
                 "-NoProfile",
                 "-Command",
                 &format!(
-                    "Export-VMSnapshot -VMName '{}' -Name '{}' -Path '{}'",
-                    self.vm_name, self.name, destination_path
+                    "{} | Export-VMSnapshot -Path {}",
+                    self.select(),
+                    powershell::quote(destination_path)
                 ),
             ])
             .output()
@@ -250,8 +275,8 @@ pub fn list_snapshots(vm_name: &str) -> Result<Vec<Snapshot>> {
             "-NoProfile",
             "-Command",
             &format!(
-                "Get-VMSnapshot -VMName '{}' | Select-Object Name, Id | ConvertTo-Json -Compress",
-                vm_name
+                "Get-VMSnapshot -VMName {} | Select-Object Name, Id | ConvertTo-Json -Compress",
+                powershell::quote(vm_name)
             ),
         ])
         .output()
@@ -333,6 +358,35 @@ pub fn get_snapshot(vm_name: &str, snapshot_name: &str) -> Result<Snapshot> {
     ))
 }
 
+/// Gets the ID of the snapshot the VM's current state is based on (if any)
+pub fn current_snapshot_id(vm_name: &str) -> Result<Option<String>> {
+    let output = Command::new("powershell")
+        .args([
+            "-NoProfile",
+            "-Command",
+            &powershell::parent_snapshot_id(vm_name),
+        ])
+        .output()
+        .map_err(|e| HvError::OperationFailed(e.to_string()))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if powershell::is_vm_not_found(&stderr) {
+            return Err(HvError::VmNotFound(vm_name.to_string()));
+        }
+        return Err(HvError::OperationFailed(stderr.to_string()));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let parent = stdout.trim();
+
+    if parent.is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(parent.to_string()))
+    }
+}
+
 /// Creates a new snapshot for a VM
 pub fn create_snapshot(
     vm_name: &str,
@@ -348,17 +402,14 @@ pub fn create_snapshot(
         SnapshotType::ProductionFallback => "-CheckpointType ProductionOnly",
     };
 
-    let cmd = if type_arg.is_empty() {
-        format!(
-            "Checkpoint-VM -Name '{}' -SnapshotName '{}'",
-            vm_name, snapshot_name
-        )
-    } else {
-        format!(
-            "Checkpoint-VM -Name '{}' -SnapshotName '{}' {}",
-            vm_name, snapshot_name, type_arg
-        )
-    };
+    // Names need not be unique, so the new checkpoint is identified from the
+    // cmdlet's output rather than looked up by name afterwards
+    let cmd = format!(
+        "Checkpoint-VM -Name {} -SnapshotName {} {} -Passthru | Select-Object Id | ConvertTo-Json -Compress",
+        powershell::quote(vm_name),
+        powershell::quote(snapshot_name),
+        type_arg
+    );
 
     let output = Command::new("powershell")
         .args(["-NoProfile", "-Command", &cmd])
@@ -370,5 +421,16 @@ pub fn create_snapshot(
         return Err(HvError::OperationFailed(stderr.to_string()));
     }
 
-    get_snapshot(vm_name, snapshot_name)
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let info: SnapshotInfo = serde_json::from_str(stdout.trim())
+        .map_err(|e| HvError::JsonError(format!("Failed to parse snapshot: {}", e)))?;
+    let id = info
+        .id
+        .ok_or_else(|| HvError::OperationFailed("Checkpoint-VM returned no id".to_string()))?;
+
+    Ok(Snapshot::new(
+        snapshot_name.to_string(),
+        id,
+        vm_name.to_string(),
+    ))
 }
@@ -3,6 +3,7 @@
 //! Uses WMI Msvm_* classes for VM operations.
 
 use crate::error::{HvError, Result};
+use crate::vm_config::VmConfig;
 use crate::wmi::msvm::MsvmVm;
 use crate::wmi::{hyperv::EnabledState, operations as wmi_ops, WmiConnection};
 use serde::{Deserialize, Serialize};
@@ -291,6 +292,19 @@ impl Vm {
         Ok(mem.virtual_quantity_mb)
     }
 
+    /// Gets the VM configuration version (e.g. "10.0")
+    pub fn version(&self) -> Result<Option<String>> {
+        let conn = WmiConnection::connect_hyperv()?;
+        let settings = wmi_ops::get_vm_settings(&conn, &self.id)?;
+        Ok(settings.version)
+    }
+
+    /// Gets dynamic memory, automatic actions, checkpoint type and notes
+    pub fn config(&self) -> Result<VmConfig> {
+        let conn = WmiConnection::connect_hyperv()?;
+        wmi_ops::get_vm_config(&conn, &self.id)
+    }
+
     /// Gets the VM generation
     pub fn generation(&self) -> Option<VmGeneration> {
         self.generation.map(|g| {
@@ -0,0 +1,246 @@
+//! VM-wide settings beyond memory size and processor count
+//!
+//! Read from and written to `Msvm_VirtualSystemSettingData` (start/stop
+//! actions, checkpoint type, notes) and `Msvm_MemorySettingData` (dynamic
+//! memory).
+
+// The WMI operations using these are Windows-only
+#![cfg_attr(not(windows), allow(dead_code))]
+
+/// What a VM does when the host starts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StartAction {
+    /// Stay off
+    Nothing,
+    /// Start if the VM was running when the host stopped
+    StartIfRunning,
+    /// Always start
+    AlwaysStart,
+}
+
+impl StartAction {
+    /// Parses an `AutomaticStartupAction` value
+    pub(crate) fn from_wmi(value: u32) -> Option<Self> {
+        match value {
+            2 => Some(StartAction::Nothing),
+            3 => Some(StartAction::StartIfRunning),
+            4 => Some(StartAction::AlwaysStart),
+            _ => None,
+        }
+    }
+
+    /// Returns the `AutomaticStartupAction` value
+    pub(crate) fn to_wmi(self) -> u16 {
+        match self {
+            StartAction::Nothing => 2,
+            StartAction::StartIfRunning => 3,
+            StartAction::AlwaysStart => 4,
+        }
+    }
+}
+
+/// What a VM does when the host shuts down
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopAction {
+    /// Power off
+    TurnOff,
+    /// Save the VM's state
+    Save,
+    /// Shut the guest down
+    Shutdown,
+}
+
+impl StopAction {
+    /// Parses an `AutomaticShutdownAction` value
+    pub(crate) fn from_wmi(value: u32) -> Option<Self> {
+        match value {
+            2 => Some(StopAction::TurnOff),
+            3 => Some(StopAction::Save),
+            4 => Some(StopAction::Shutdown),
+            _ => None,
+        }
+    }
+
+    /// Returns the `AutomaticShutdownAction` value
+    pub(crate) fn to_wmi(self) -> u16 {
+        match self {
+            StopAction::TurnOff => 2,
+            StopAction::Save => 3,
+            StopAction::Shutdown => 4,
+        }
+    }
+}
+
+/// Kind of checkpoint taken for the VM
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckpointType {
+    /// Checkpoints are not allowed
+    Disabled,
+    /// Application-consistent, falling back to standard
+    Production,
+    /// Application-consistent only
+    ProductionOnly,
+    /// Includes memory state
+    Standard,
+}
+
+impl CheckpointType {
+    /// Parses a `UserSnapshotType` value
+    pub(crate) fn from_wmi(value: u32) -> Option<Self> {
+        match value {
+            2 => Some(CheckpointType::Disabled),
+            3 => Some(CheckpointType::Production),
+            4 => Some(CheckpointType::ProductionOnly),
+            5 => Some(CheckpointType::Standard),
+            _ => None,
+        }
+    }
+
+    /// Returns the `UserSnapshotType` value
+    pub(crate) fn to_wmi(self) -> u16 {
+        match self {
+            CheckpointType::Disabled => 2,
+            CheckpointType::Production => 3,
+            CheckpointType::ProductionOnly => 4,
+            CheckpointType::Standard => 5,
+        }
+    }
+}
+
+/// VM settings beyond memory size and processor count
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VmConfig {
+    /// Dynamic memory enabled
+    pub dynamic_memory: bool,
+    /// Minimum memory when dynamic
+    pub dynamic_memory_min_mb: u64,
+    /// Maximum memory when dynamic
+    pub dynamic_memory_max_mb: u64,
+    /// Memory kept in reserve above demand, as a percentage
+    pub memory_buffer_percentage: u32,
+    /// Action on host start
+    pub automatic_start_action: StartAction,
+    /// Delay before the automatic start
+    pub automatic_start_delay_secs: u32,
+    /// Action on host shutdown
+    pub automatic_stop_action: StopAction,
+    /// Checkpoint type
+    pub checkpoint_type: CheckpointType,
+    /// Notes
+    pub notes: String,
+}
+
+/// Changes to a [`VmConfig`]; `None` leaves a setting as it is
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VmConfigUpdate {
+    /// Enable or disable dynamic memory
+    pub dynamic_memory: Option<bool>,
+    /// Minimum memory when dynamic
+    pub dynamic_memory_min_mb: Option<u64>,
+    /// Maximum memory when dynamic
+    pub dynamic_memory_max_mb: Option<u64>,
+    /// Memory kept in reserve above demand, as a percentage
+    pub memory_buffer_percentage: Option<u32>,
+    /// Action on host start
+    pub automatic_start_action: Option<StartAction>,
+    /// Delay before the automatic start
+    pub automatic_start_delay_secs: Option<u32>,
+    /// Action on host shutdown
+    pub automatic_stop_action: Option<StopAction>,
+    /// Checkpoint type
+    pub checkpoint_type: Option<CheckpointType>,
+    /// Notes
+    pub notes: Option<String>,
+}
+
+impl VmConfigUpdate {
+    /// Returns true if any `Msvm_MemorySettingData` property changes
+    pub(crate) fn changes_memory(&self) -> bool {
+        self.dynamic_memory.is_some()
+            || self.dynamic_memory_min_mb.is_some()
+            || self.dynamic_memory_max_mb.is_some()
+            || self.memory_buffer_percentage.is_some()
+    }
+
+    /// Returns true if any `Msvm_VirtualSystemSettingData` property changes
+    pub(crate) fn changes_system(&self) -> bool {
+        self.automatic_start_action.is_some()
+            || self.automatic_start_delay_secs.is_some()
+            || self.automatic_stop_action.is_some()
+            || self.checkpoint_type.is_some()
+            || self.notes.is_some()
+    }
+}
+
+/// Formats seconds as a CIM interval (`ddddddddhhmmss.mmmmmm:000`)
+pub(crate) fn to_cim_interval(secs: u32) -> String {
+    format!(
+        "{:08}{:02}{:02}{:02}.000000:000",
+        secs / 86400,
+        secs / 3600 % 24,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+/// Parses a CIM interval into whole seconds
+pub(crate) fn from_cim_interval(interval: &str) -> Option<u32> {
+    let field = |range: std::ops::Range<usize>| interval.get(range)?.parse::<u32>().ok();
+    let days = field(0..8)?;
+    let hours = field(8..10)?;
+    let minutes = field(10..12)?;
+    let seconds = field(12..14)?;
+    days.checked_mul(86400)?
+        .checked_add(hours * 3600 + minutes * 60 + seconds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wmi_values_roundtrip() {
+        for action in [
+            StartAction::Nothing,
+            StartAction::StartIfRunning,
+            StartAction::AlwaysStart,
+        ] {
+            assert_eq!(StartAction::from_wmi(action.to_wmi() as u32), Some(action));
+        }
+        for action in [StopAction::TurnOff, StopAction::Save, StopAction::Shutdown] {
+            assert_eq!(StopAction::from_wmi(action.to_wmi() as u32), Some(action));
+        }
+        for kind in [
+            CheckpointType::Disabled,
+            CheckpointType::Production,
+            CheckpointType::ProductionOnly,
+            CheckpointType::Standard,
+        ] {
+            assert_eq!(CheckpointType::from_wmi(kind.to_wmi() as u32), Some(kind));
+        }
+        assert_eq!(StartAction::from_wmi(0), None);
+    }
+
+    #[test]
+    fn test_cim_interval() {
+        assert_eq!(to_cim_interval(0), "00000000000000.000000:000");
+        assert_eq!(to_cim_interval(90061), "00000001010101.000000:000");
+        assert_eq!(from_cim_interval("00000001010101.000000:000"), Some(90061));
+        assert_eq!(from_cim_interval("00000000000130.000000:000"), Some(90));
+        assert_eq!(from_cim_interval("garbage"), None);
+    }
+
+    #[test]
+    fn test_update_groups() {
+        let notes = VmConfigUpdate {
+            notes: Some("web tier".to_string()),
+            ..Default::default()
+        };
+        assert!(notes.changes_system() && !notes.changes_memory());
+        let memory = VmConfigUpdate {
+            memory_buffer_percentage: Some(20),
+            ..Default::default()
+        };
+        assert!(memory.changes_memory() && !memory.changes_system());
+    }
+}
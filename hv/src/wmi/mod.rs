@@ -495,6 +495,11 @@ impl WmiObject {
 
     /// Get an array of strings
     pub fn get_string_array(&self, property: &str) -> Result<Vec<String>> {
+        use windows::Win32::System::Ole::{
+            SafeArrayGetElement, SafeArrayGetLBound, SafeArrayGetUBound,
+        };
+        use windows::Win32::System::Variant::{VT_ARRAY, VT_BSTR};
+
         unsafe {
             let prop_wide = to_wide(property);
             let mut value = VARIANT::default();
@@ -510,13 +515,29 @@ impl WmiObject {
                     HvError::WmiError(format!("Failed to get property {}: {:?}", property, e))
                 })?;
 
-            if value.is_empty() {
+            if value.is_empty() || value.vt() != (VT_ARRAY | VT_BSTR) {
                 return Ok(Vec::new());
             }
 
-            // For now, return empty - array handling is complex
-            // TODO: Implement proper SAFEARRAY parsing
-            Ok(Vec::new())
+            let sa = (*value.Anonymous.Anonymous).Anonymous.parray;
+            if sa.is_null() {
+                return Ok(Vec::new());
+            }
+            let read_error = |e: windows::core::Error| {
+                HvError::WmiError(format!("Failed to read property {}: {:?}", property, e))
+            };
+            let lower = SafeArrayGetLBound(sa, 1).map_err(read_error)?;
+            let upper = SafeArrayGetUBound(sa, 1).map_err(read_error)?;
+
+            let mut values = Vec::new();
+            for index in lower..=upper {
+                // SafeArrayGetElement copies the BSTR; dropping it frees the copy
+                let mut element = BSTR::new();
+                SafeArrayGetElement(sa, &index, &mut element as *mut BSTR as *mut _)
+                    .map_err(read_error)?;
+                values.push(element.to_string());
+            }
+            Ok(values)
         }
     }
 
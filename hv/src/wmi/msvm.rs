@@ -151,6 +151,8 @@ pub struct MsvmVmSettings {
     pub bios_guid: Option<String>,
     /// Configuration path
     pub config_path: Option<String>,
+    /// Configuration version (e.g. "10.0")
+    pub version: Option<String>,
     /// WMI path
     pub path: String,
 }
@@ -172,6 +174,7 @@ impl MsvmVmSettings {
             notes: obj.get_string("Notes")?,
             bios_guid: obj.get_string("BIOSGUID")?,
             config_path: obj.get_string("ConfigurationDataRoot")?,
+            version: obj.get_string("Version")?,
             path: obj.path()?,
         })
     }
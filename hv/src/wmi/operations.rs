@@ -6,6 +6,10 @@
 use super::msvm::*;
 use super::{hyperv, WmiConnection, WmiObject};
 use crate::error::{HvError, Result};
+use crate::vm_config::{
+    from_cim_interval, to_cim_interval, CheckpointType, StartAction, StopAction, VmConfig,
+    VmConfigUpdate,
+};
 
 // ============================================================================
 // VM Operations
@@ -84,6 +88,11 @@ pub fn get_vm_by_id(conn: &WmiConnection, id: &str) -> Result<MsvmVm> {
 
 /// Get VM settings (Msvm_VirtualSystemSettingData)
 pub fn get_vm_settings(conn: &WmiConnection, vm_id: &str) -> Result<MsvmVmSettings> {
+    MsvmVmSettings::from_wmi(&vm_settings_object(conn, vm_id)?)
+}
+
+/// Get the active Msvm_VirtualSystemSettingData instance of a VM
+fn vm_settings_object(conn: &WmiConnection, vm_id: &str) -> Result<WmiObject> {
     // Get the active settings (SettingType = 3 is Current)
     let query = format!(
         r#"SELECT * FROM Msvm_VirtualSystemSettingData WHERE VirtualSystemIdentifier = '{}' AND VirtualSystemType = 'Microsoft:Hyper-V:System:Realized'"#,
@@ -91,30 +100,133 @@ pub fn get_vm_settings(conn: &WmiConnection, vm_id: &str) -> Result<MsvmVmSettin
     );
     let mut results = conn.query(&query)?;
 
-    let obj = results
+    results
         .next()
-        .ok_or_else(|| HvError::WmiError("VM settings not found".to_string()))??;
-
-    MsvmVmSettings::from_wmi(&obj)
+        .ok_or_else(|| HvError::WmiError("VM settings not found".to_string()))?
 }
 
-/// Get VM memory settings
-pub fn get_vm_memory_settings(conn: &WmiConnection, vm_id: &str) -> Result<MsvmMemorySettings> {
-    // First get the VM settings path
+/// Get the Msvm_MemorySettingData instance of a VM
+fn memory_settings_object(conn: &WmiConnection, vm_id: &str) -> Result<WmiObject> {
     let settings = get_vm_settings(conn, vm_id)?;
-
-    // Query memory settings associated with these VM settings
     let query = format!(
         r#"ASSOCIATORS OF {{{}}} WHERE AssocClass = Msvm_VirtualSystemSettingDataComponent ResultClass = Msvm_MemorySettingData"#,
         settings.path
     );
     let mut results = conn.query(&query)?;
 
-    let obj = results
+    results
         .next()
-        .ok_or_else(|| HvError::WmiError("Memory settings not found".to_string()))??;
+        .ok_or_else(|| HvError::WmiError("Memory settings not found".to_string()))?
+}
+
+/// Get VM settings beyond memory size and processor count
+pub fn get_vm_config(conn: &WmiConnection, vm_id: &str) -> Result<VmConfig> {
+    let settings = vm_settings_object(conn, vm_id)?;
+    let memory = get_vm_memory_settings(conn, vm_id)?;
+
+    Ok(VmConfig {
+        dynamic_memory: memory.dynamic_memory_enabled,
+        dynamic_memory_min_mb: memory.minimum_mb.unwrap_or(memory.virtual_quantity_mb),
+        dynamic_memory_max_mb: memory.maximum_mb.unwrap_or(memory.virtual_quantity_mb),
+        memory_buffer_percentage: memory.target_memory_buffer.unwrap_or(0),
+        automatic_start_action: settings
+            .get_u32("AutomaticStartupAction")?
+            .and_then(StartAction::from_wmi)
+            .unwrap_or(StartAction::StartIfRunning),
+        automatic_start_delay_secs: settings
+            .get_string("AutomaticStartupActionDelay")?
+            .as_deref()
+            .and_then(from_cim_interval)
+            .unwrap_or(0),
+        automatic_stop_action: settings
+            .get_u32("AutomaticShutdownAction")?
+            .and_then(StopAction::from_wmi)
+            .unwrap_or(StopAction::Save),
+        checkpoint_type: settings
+            .get_u32("UserSnapshotType")?
+            .and_then(CheckpointType::from_wmi)
+            .unwrap_or(CheckpointType::Production),
+        notes: settings.get_string_array("Notes")?.join("\n"),
+    })
+}
+
+/// Change VM settings beyond memory size and processor count
+///
+/// Dynamic memory goes through `ModifyResourceSettings` on the memory
+/// settings; the rest through `ModifySystemSettings`.
+pub fn configure_vm_config(
+    conn: &WmiConnection,
+    vm_id: &str,
+    update: &VmConfigUpdate,
+) -> Result<()> {
+    let vsms = hyperv::get_vsms(conn)?;
+    let vsms_path = vsms.path()?;
+
+    if update.changes_memory() {
+        let mem_obj = memory_settings_object(conn, vm_id)?;
+        if let Some(enabled) = update.dynamic_memory {
+            mem_obj.put_bool("DynamicMemoryEnabled", enabled)?;
+        }
+        if let Some(min) = update.dynamic_memory_min_mb {
+            mem_obj.put_u64("Reservation", min)?;
+        }
+        if let Some(max) = update.dynamic_memory_max_mb {
+            mem_obj.put_u64("Limit", max)?;
+        }
+        if let Some(buffer) = update.memory_buffer_percentage {
+            mem_obj.put_u32("TargetMemoryBuffer", buffer)?;
+        }
 
-    MsvmMemorySettings::from_wmi(&obj)
+        let mem_text = get_instance_text(conn, &mem_obj)?;
+        let params = conn.get_method_params(
+            "Msvm_VirtualSystemManagementService",
+            "ModifyResourceSettings",
+        )?;
+        params.put_string_array("ResourceSettings", &[&mem_text])?;
+
+        let result = conn.exec_method(&vsms_path, "ModifyResourceSettings", Some(&params))?;
+        if let Some(result_obj) = result {
+            hyperv::check_job_result(conn, &result_obj)?;
+        }
+    }
+
+    if update.changes_system() {
+        let settings = vm_settings_object(conn, vm_id)?;
+        if let Some(action) = update.automatic_start_action {
+            settings.put_u16("AutomaticStartupAction", action.to_wmi())?;
+        }
+        if let Some(secs) = update.automatic_start_delay_secs {
+            settings.put_string("AutomaticStartupActionDelay", &to_cim_interval(secs))?;
+        }
+        if let Some(action) = update.automatic_stop_action {
+            settings.put_u16("AutomaticShutdownAction", action.to_wmi())?;
+        }
+        if let Some(kind) = update.checkpoint_type {
+            settings.put_u16("UserSnapshotType", kind.to_wmi())?;
+        }
+        if let Some(notes) = &update.notes {
+            settings.put_string_array("Notes", &[notes])?;
+        }
+
+        let settings_text = get_instance_text(conn, &settings)?;
+        let params = conn.get_method_params(
+            "Msvm_VirtualSystemManagementService",
+            "ModifySystemSettings",
+        )?;
+        params.put_string("SystemSettings", &settings_text)?;
+
+        let result = conn.exec_method(&vsms_path, "ModifySystemSettings", Some(&params))?;
+        if let Some(result_obj) = result {
+            hyperv::check_job_result(conn, &result_obj)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Get VM memory settings
+pub fn get_vm_memory_settings(conn: &WmiConnection, vm_id: &str) -> Result<MsvmMemorySettings> {
+    MsvmMemorySettings::from_wmi(&memory_settings_object(conn, vm_id)?)
 }
 
 /// Get VM processor settings
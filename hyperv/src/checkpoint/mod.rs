@@ -1,9 +1,12 @@
 use crate::error::{Error, Result};
 use crate::vm::CheckpointType;
+#[cfg(windows)]
 use crate::wmi::WbemClassObjectExt;
+#[cfg(windows)]
 use windows::Win32::System::Wmi::IWbemClassObject;
 
 /// Represents a VM checkpoint (snapshot).
+#[cfg(windows)]
 #[derive(Debug)]
 pub struct Checkpoint {
     /// Checkpoint display name.
@@ -22,6 +25,7 @@ pub struct Checkpoint {
     path: String,
 }
 
+#[cfg(windows)]
 impl Checkpoint {
     /// Create from WMI object (Msvm_VirtualSystemSettingData with VirtualSystemType = snapshot).
     pub(crate) fn from_wmi(obj: &IWbemClassObject) -> Result<Self> {
@@ -326,7 +326,9 @@ pub enum Error {
     },
 
     /// Migration operation failed.
-    Migration(MigrationError),
+    ///
+    /// Boxed so the details do not inflate every `Result` in the crate.
+    Migration(Box<MigrationError>),
 
     /// Security operation failed (TPM, SecureBoot, etc.).
     Security(SecurityError),
@@ -566,7 +568,7 @@ impl From<std::io::Error> for Error {
 
 impl From<MigrationError> for Error {
     fn from(e: MigrationError) -> Self {
-        Error::Migration(e)
+        Error::Migration(Box::new(e))
     }
 }
 
@@ -1,6 +1,8 @@
 use crate::checkpoint::{Checkpoint, CheckpointSettings};
 use crate::error::{Error, Result};
-use crate::network::{NetworkAdapter, NetworkAdapterSettings, VirtualSwitch};
+use crate::network::{
+    BandwidthSettings, NetworkAdapter, NetworkAdapterSettings, PortMirroringMode, VirtualSwitch,
+};
 use crate::storage::{ControllerType, DiskAttachment, IsoAttachment, VhdManager};
 use crate::vm::{Generation, ImportSettings, VirtualMachine, VmSettings, VmState};
 use crate::wmi::{WbemClassObjectExt, WmiConnection};
@@ -8,6 +10,13 @@ use std::path::Path;
 use std::sync::Arc;
 use windows::Win32::System::Wmi::IWbemClassObject;
 
+/// `EnabledState` of a switch port that is connected.
+const PORT_ENABLED: u16 = 2;
+/// `EnabledState` of a switch port that is disconnected.
+const PORT_DISABLED: u16 = 3;
+/// `OperationMode` of an access-mode VLAN.
+const VLAN_ACCESS_MODE: u32 = 1;
+
 /// Main entry point for Hyper-V management operations.
 pub struct HyperV {
     connection: Arc<WmiConnection>,
@@ -608,6 +617,9 @@ impl HyperV {
     }
 
     /// List network adapters attached to a VM.
+    ///
+    /// Each adapter reports its switch connection and the VLAN, security
+    /// and bandwidth features of its switch port.
     pub fn list_network_adapters(&self, vm: &VirtualMachine) -> Result<Vec<NetworkAdapter>> {
         // Get VM settings first
         let vm_settings = self.get_vm_settings(vm)?;
@@ -620,10 +632,20 @@ impl HyperV {
         );
         let objects = self.connection.query(&query)?;
 
-        objects.iter().map(NetworkAdapter::from_wmi).collect()
+        objects
+            .iter()
+            .map(|obj| {
+                let mut adapter = NetworkAdapter::from_wmi(obj)?;
+                self.read_port_settings(vm, &mut adapter)?;
+                Ok(adapter)
+            })
+            .collect()
     }
 
     /// Connect a network adapter to a switch.
+    ///
+    /// An adapter that already has a switch port is moved to the new switch,
+    /// keeping its port features.
     pub fn connect_adapter_to_switch(
         &self,
         vm: &VirtualMachine,
@@ -647,6 +669,13 @@ impl HyperV {
             .ok_or_else(|| Error::SwitchNotFound(switch.name().to_string()))?;
         let switch_path = switch_settings.get_path()?;
 
+        // Reconnect an existing port
+        if let Some(port) = self.find_port_allocation(vm, adapter)? {
+            port.put_string_array("HostResource", &[&switch_path])?;
+            port.put_u16("EnabledState", PORT_ENABLED)?;
+            return self.modify_resource(&port);
+        }
+
         // Create connection
         let port = self
             .connection
@@ -668,6 +697,143 @@ impl HyperV {
         self.handle_job_result(&out_params, "AddResourceSettings")
     }
 
+    /// Disconnect a network adapter from its switch.
+    ///
+    /// The switch port is disabled rather than removed, so its features
+    /// survive a later reconnect. Disconnecting an unconnected adapter is a
+    /// no-op.
+    pub fn disconnect_adapter(&self, vm: &VirtualMachine, adapter: &NetworkAdapter) -> Result<()> {
+        match self.find_port_allocation(vm, adapter)? {
+            Some(port) => {
+                port.put_u16("EnabledState", PORT_DISABLED)?;
+                self.modify_resource(&port)
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Remove a network adapter from a VM, along with its switch port.
+    pub fn remove_network_adapter(
+        &self,
+        vm: &VirtualMachine,
+        adapter: &NetworkAdapter,
+    ) -> Result<()> {
+        let mut paths = Vec::new();
+        if let Some(port) = self.find_port_allocation(vm, adapter)? {
+            paths.push(port.get_path()?);
+        }
+        paths.push(adapter.path().to_string());
+        let paths: Vec<&str> = paths.iter().map(String::as_str).collect();
+
+        let mgmt_service = self.get_management_service()?;
+        let mgmt_path = mgmt_service.get_path()?;
+
+        let in_params = self.connection.get_method_params(
+            "Msvm_VirtualSystemManagementService",
+            "RemoveResourceSettings",
+        )?;
+        in_params.put_string_array("ResourceSettings", &paths)?;
+
+        let out_params =
+            self.connection
+                .exec_method(&mgmt_path, "RemoveResourceSettings", Some(&in_params))?;
+        self.handle_job_result(&out_params, "RemoveResourceSettings")
+    }
+
+    /// Set a network adapter's MAC address; `None` switches to a dynamic MAC.
+    ///
+    /// Hyper-V only accepts this while the VM is off.
+    pub fn set_network_adapter_mac(
+        &self,
+        adapter: &NetworkAdapter,
+        mac_address: Option<&str>,
+    ) -> Result<()> {
+        let settings = NetworkAdapterSettings {
+            mac_address: mac_address.map(str::to_string),
+            dynamic_mac: mac_address.is_none(),
+            ..NetworkAdapterSettings::default()
+        };
+        settings.validate()?;
+
+        let obj = self.connection.get_object(adapter.path())?;
+        match mac_address {
+            Some(mac) => {
+                obj.put_bool("StaticMacAddress", true)?;
+                obj.put_string("Address", &mac.replace([':', '-'], ""))?;
+            }
+            None => obj.put_bool("StaticMacAddress", false)?,
+        }
+        self.modify_resource(&obj)
+    }
+
+    /// Apply the VLAN, security, port mirroring and bandwidth fields of
+    /// `settings` to a network adapter's switch port.
+    ///
+    /// The fields replace the port's current features: `vlan_id: None` and
+    /// `bandwidth: None` remove them. Name, MAC and switch are ignored. An
+    /// unconnected adapter gets a disabled port to carry any features.
+    pub fn configure_adapter_port(
+        &self,
+        vm: &VirtualMachine,
+        adapter: &NetworkAdapter,
+        settings: &NetworkAdapterSettings,
+    ) -> Result<()> {
+        settings.validate()?;
+
+        let vlan = settings.vlan_id.filter(|id| *id != 0);
+        let secured = settings.mac_spoofing
+            || settings.dhcp_guard
+            || settings.router_guard
+            || settings.port_mirroring != PortMirroringMode::None;
+        let bandwidth = settings
+            .bandwidth
+            .as_ref()
+            .map(BandwidthSettings::to_wmi)
+            .filter(|values| *values != (0, 0, 0));
+
+        let port = match self.find_port_allocation(vm, adapter)? {
+            Some(port) => port,
+            None if vlan.is_none() && !secured && bandwidth.is_none() => return Ok(()),
+            None => self.add_disabled_port(vm, adapter)?,
+        };
+        let port_path = port.get_path()?;
+
+        self.set_port_feature(
+            &port_path,
+            "Msvm_EthernetSwitchPortVlanSettingData",
+            vlan.map(|id| {
+                move |feature: &IWbemClassObject| {
+                    feature.put_u32("OperationMode", VLAN_ACCESS_MODE)?;
+                    feature.put_u16("AccessVlanId", id)
+                }
+            }),
+        )?;
+        self.set_port_feature(
+            &port_path,
+            "Msvm_EthernetSwitchPortSecuritySettingData",
+            secured.then_some(|feature: &IWbemClassObject| {
+                feature.put_bool("AllowMacSpoofing", settings.mac_spoofing)?;
+                feature.put_bool("EnableDhcpGuard", settings.dhcp_guard)?;
+                feature.put_bool("EnableRouterGuard", settings.router_guard)?;
+                feature.put_u16(
+                    "MonitorMode",
+                    u16::from(settings.port_mirroring.to_monitor_mode()),
+                )
+            }),
+        )?;
+        self.set_port_feature(
+            &port_path,
+            "Msvm_EthernetSwitchPortBandwidthSettingData",
+            bandwidth.map(|(reservation, limit, burst_size)| {
+                move |feature: &IWbemClassObject| {
+                    feature.put_u64("Reservation", reservation)?;
+                    feature.put_u64("Limit", limit)?;
+                    feature.put_u64("BurstSize", burst_size)
+                }
+            }),
+        )
+    }
+
     // ========== Checkpoint Operations ==========
 
     /// List checkpoints for a VM.
@@ -876,6 +1042,187 @@ impl HyperV {
 
     // ========== Helper Methods ==========
 
+    /// Find the switch port (`Msvm_EthernetPortAllocationSettingData`) of an adapter.
+    fn find_port_allocation(
+        &self,
+        vm: &VirtualMachine,
+        adapter: &NetworkAdapter,
+    ) -> Result<Option<IWbemClassObject>> {
+        let vm_settings = self.get_vm_settings(vm)?;
+        let query = format!(
+            "ASSOCIATORS OF {{{}}} WHERE ResultClass=Msvm_EthernetPortAllocationSettingData",
+            vm_settings.get_path()?
+        );
+        // `Parent` may name the adapter with or without the server prefix, so
+        // compare instance IDs rather than paths.
+        for port in self.connection.query(&query)? {
+            let Some(parent) = port.get_string_prop("Parent")? else {
+                continue;
+            };
+            let parent = self.connection.get_object(&parent)?;
+            if parent.get_string_prop("InstanceID")?.as_deref()
+                == Some(adapter.instance_id.as_str())
+            {
+                return Ok(Some(port));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Add a switch port with no switch, to carry port features.
+    fn add_disabled_port(
+        &self,
+        vm: &VirtualMachine,
+        adapter: &NetworkAdapter,
+    ) -> Result<IWbemClassObject> {
+        let vm_settings = self.get_vm_settings(vm)?;
+        let settings_path = vm_settings.get_path()?;
+
+        let mgmt_service = self.get_management_service()?;
+        let mgmt_path = mgmt_service.get_path()?;
+
+        let port = self
+            .connection
+            .spawn_instance("Msvm_EthernetPortAllocationSettingData")?;
+        port.put_string("Parent", adapter.path())?;
+        port.put_u16("EnabledState", PORT_DISABLED)?;
+        let port_text = port.get_text()?;
+
+        let in_params = self
+            .connection
+            .get_method_params("Msvm_VirtualSystemManagementService", "AddResourceSettings")?;
+        in_params.put_string("AffectedConfiguration", &settings_path)?;
+        in_params.put_string_array("ResourceSettings", &[&port_text])?;
+
+        let out_params =
+            self.connection
+                .exec_method(&mgmt_path, "AddResourceSettings", Some(&in_params))?;
+        self.handle_job_result(&out_params, "AddResourceSettings")?;
+
+        self.find_port_allocation(vm, adapter)?
+            .ok_or_else(|| Error::OperationFailed {
+                failure_type: crate::error::FailureType::Unknown,
+                operation: "AddResourceSettings",
+                return_value: 0,
+                message: "Failed to find created switch port".to_string(),
+            })
+    }
+
+    /// Fill an adapter's switch and port features from its switch port.
+    fn read_port_settings(&self, vm: &VirtualMachine, adapter: &mut NetworkAdapter) -> Result<()> {
+        let Some(port) = self.find_port_allocation(vm, adapter)? else {
+            return Ok(());
+        };
+        let port_path = port.get_path()?;
+
+        if port.get_u16("EnabledState")? != Some(PORT_DISABLED) {
+            let host = port.get_string_array("HostResource")?.unwrap_or_default();
+            if let Some(switch_path) = host.first() {
+                let switch = self.connection.get_object(switch_path)?;
+                adapter.switch_name = switch.get_string_prop("ElementName")?;
+            }
+        }
+
+        if let Some(vlan) =
+            self.find_port_feature(&port_path, "Msvm_EthernetSwitchPortVlanSettingData")?
+        {
+            adapter.vlan_id = vlan.get_u16("AccessVlanId")?.filter(|id| *id != 0);
+        }
+        if let Some(security) =
+            self.find_port_feature(&port_path, "Msvm_EthernetSwitchPortSecuritySettingData")?
+        {
+            adapter.mac_spoofing = security.get_bool("AllowMacSpoofing")?.unwrap_or(false);
+            adapter.dhcp_guard = security.get_bool("EnableDhcpGuard")?.unwrap_or(false);
+            adapter.router_guard = security.get_bool("EnableRouterGuard")?.unwrap_or(false);
+            let mode = security.get_u16("MonitorMode")?.unwrap_or(0);
+            adapter.port_mirroring = PortMirroringMode::from_monitor_mode(mode as u8);
+        }
+        if let Some(bandwidth) =
+            self.find_port_feature(&port_path, "Msvm_EthernetSwitchPortBandwidthSettingData")?
+        {
+            adapter.bandwidth = Some(BandwidthSettings::from_wmi(
+                bandwidth.get_u64("Reservation")?.unwrap_or(0),
+                bandwidth.get_u64("Limit")?.unwrap_or(0),
+                bandwidth.get_u64("BurstSize")?.unwrap_or(0),
+            ));
+        }
+        Ok(())
+    }
+
+    fn find_port_feature(&self, port_path: &str, class: &str) -> Result<Option<IWbemClassObject>> {
+        let query = format!(
+            "ASSOCIATORS OF {{{}}} WHERE ResultClass={}",
+            port_path, class
+        );
+        self.connection.query_first(&query)
+    }
+
+    /// Add, modify or remove one feature of a switch port.
+    ///
+    /// `configure` sets the feature's properties; `None` removes the feature.
+    fn set_port_feature<F>(&self, port_path: &str, class: &str, configure: Option<F>) -> Result<()>
+    where
+        F: Fn(&IWbemClassObject) -> Result<()>,
+    {
+        let existing = self.find_port_feature(port_path, class)?;
+        let mgmt_service = self.get_management_service()?;
+        let mgmt_path = mgmt_service.get_path()?;
+
+        let (method, in_params) = match (existing, configure) {
+            (None, None) => return Ok(()),
+            (Some(feature), None) => {
+                let in_params = self.connection.get_method_params(
+                    "Msvm_VirtualSystemManagementService",
+                    "RemoveFeatureSettings",
+                )?;
+                in_params.put_string_array("FeatureSettings", &[&feature.get_path()?])?;
+                ("RemoveFeatureSettings", in_params)
+            }
+            (Some(feature), Some(configure)) => {
+                configure(&feature)?;
+                let in_params = self.connection.get_method_params(
+                    "Msvm_VirtualSystemManagementService",
+                    "ModifyFeatureSettings",
+                )?;
+                in_params.put_string_array("FeatureSettings", &[&feature.get_text()?])?;
+                ("ModifyFeatureSettings", in_params)
+            }
+            (None, Some(configure)) => {
+                let feature = self.connection.spawn_instance(class)?;
+                configure(&feature)?;
+                let in_params = self.connection.get_method_params(
+                    "Msvm_VirtualSystemManagementService",
+                    "AddFeatureSettings",
+                )?;
+                in_params.put_string("AffectedConfiguration", port_path)?;
+                in_params.put_string_array("FeatureSettings", &[&feature.get_text()?])?;
+                ("AddFeatureSettings", in_params)
+            }
+        };
+
+        let out_params = self
+            .connection
+            .exec_method(&mgmt_path, method, Some(&in_params))?;
+        self.handle_job_result(&out_params, method)
+    }
+
+    /// Apply a modified resource setting with ModifyResourceSettings.
+    fn modify_resource(&self, resource: &IWbemClassObject) -> Result<()> {
+        let mgmt_service = self.get_management_service()?;
+        let mgmt_path = mgmt_service.get_path()?;
+
+        let in_params = self.connection.get_method_params(
+            "Msvm_VirtualSystemManagementService",
+            "ModifyResourceSettings",
+        )?;
+        in_params.put_string_array("ResourceSettings", &[&resource.get_text()?])?;
+
+        let out_params =
+            self.connection
+                .exec_method(&mgmt_path, "ModifyResourceSettings", Some(&in_params))?;
+        self.handle_job_result(&out_params, "ModifyResourceSettings")
+    }
+
     fn get_management_service(&self) -> Result<IWbemClassObject> {
         self.connection
             .query_first("SELECT * FROM Msvm_VirtualSystemManagementService")?
@@ -46,62 +46,56 @@
 //! - Hyper-V feature enabled
 //! - Administrator privileges
 
-#[cfg(windows)]
 pub mod checkpoint;
-#[cfg(windows)]
 pub mod error;
 #[cfg(windows)]
 pub mod gpu;
 #[cfg(windows)]
 mod hyperv;
-#[cfg(windows)]
 pub mod network;
-#[cfg(windows)]
 pub mod processor;
-#[cfg(windows)]
 pub mod security;
-#[cfg(windows)]
 pub mod storage;
-#[cfg(windows)]
 pub mod validation;
-#[cfg(windows)]
 pub mod vm;
 #[cfg(windows)]
 pub mod wmi;
 
 // Re-export main types at crate root
-#[cfg(windows)]
 pub use error::{Error, FailureType, JobState, MigrationError, Result, SecurityError};
 #[cfg(windows)]
 pub use hyperv::HyperV;
 
 // VM types
 #[cfg(windows)]
+pub use vm::VirtualMachine;
 pub use vm::{
     AutomaticStartAction, AutomaticStopAction, BlockSize, CaptureLiveState, CheckpointType,
     DiskLocation, DiskSize, ExportSettings, Generation, ImportSettings, MemoryBufferPercent,
     MemoryMB, OperationalStatus, OperationalStatusSecondary, ProcessorCount, RequestedState,
-    SectorSize, ShutdownType, SnapshotExportMode, StartupDelay, VirtualMachine, VmSettings,
-    VmSettingsBuilder, VmState,
+    SectorSize, ShutdownType, SnapshotExportMode, StartupDelay, VmSettings, VmSettingsBuilder,
+    VmState,
 };
 
 // Checkpoint types
 #[cfg(windows)]
-pub use checkpoint::{Checkpoint, CheckpointSettings, CheckpointSettingsBuilder, ConsistencyLevel};
+pub use checkpoint::Checkpoint;
+pub use checkpoint::{CheckpointSettings, CheckpointSettingsBuilder, ConsistencyLevel};
 
 // Storage types
 #[cfg(windows)]
+pub use storage::VhdManager;
 pub use storage::{
-    ControllerType, DiskAttachment, IsoAttachment, StorageController, Vhd, VhdFormat, VhdManager,
-    VhdSettings, VhdSettingsBuilder, VhdType,
+    ControllerType, DiskAttachment, IsoAttachment, StorageController, Vhd, VhdFormat, VhdSettings,
+    VhdSettingsBuilder, VhdType,
 };
 
 // Network types
 #[cfg(windows)]
+pub use network::{NetworkAdapter, VirtualSwitch};
 pub use network::{
-    BandwidthSettings, NetworkAdapter, NetworkAdapterSettings, NetworkAdapterSettingsBuilder,
-    PortMirroringMode, SwitchType, VirtualSwitch, VirtualSwitchSettings,
-    VirtualSwitchSettingsBuilder,
+    BandwidthSettings, NetworkAdapterSettings, NetworkAdapterSettingsBuilder, PortMirroringMode,
+    SwitchType, VirtualSwitchSettings, VirtualSwitchSettingsBuilder,
 };
 
 // WMI types for advanced usage
@@ -111,18 +105,17 @@ pub use wmi::{
 };
 
 // Validation types
+pub use validation::{HostCapabilities, VmVersionInfo};
 #[cfg(windows)]
-pub use validation::{HostCapabilities, PropertySupport, PropertyValidator, VmVersionInfo};
+pub use validation::{PropertySupport, PropertyValidator};
 
 // Security types
-#[cfg(windows)]
 pub use security::{
     FirmwareType, GuestIsolationType, KeyProtectorType, SecureBootTemplate, SecuritySettings,
     SecuritySettingsBuilder, TpmState,
 };
 
 // Processor types
-#[cfg(windows)]
 pub use processor::{
     CpuGroupId, CpuLimit, CpuReservation, CpuWeight, HwThreadsPerCore, L3DistributionPolicy,
     NumaNode, NumaTopology, ProcessorSettings, ProcessorSettingsBuilder,
@@ -1,8 +1,11 @@
 use crate::error::{Error, Result};
+#[cfg(windows)]
 use crate::wmi::WbemClassObjectExt;
+#[cfg(windows)]
 use windows::Win32::System::Wmi::IWbemClassObject;
 
 /// Represents a virtual network adapter attached to a VM.
+#[cfg(windows)]
 #[derive(Debug)]
 pub struct NetworkAdapter {
     /// Adapter instance ID.
@@ -17,10 +20,21 @@ pub struct NetworkAdapter {
     pub switch_name: Option<String>,
     /// VLAN ID (if configured).
     pub vlan_id: Option<u16>,
+    /// Whether MAC address spoofing is allowed.
+    pub mac_spoofing: bool,
+    /// Whether DHCP guard is enabled.
+    pub dhcp_guard: bool,
+    /// Whether router guard is enabled.
+    pub router_guard: bool,
+    /// Port mirroring mode.
+    pub port_mirroring: PortMirroringMode,
+    /// Bandwidth limits (if configured).
+    pub bandwidth: Option<BandwidthSettings>,
     /// WMI path.
     path: String,
 }
 
+#[cfg(windows)]
 impl NetworkAdapter {
     /// Create from WMI object.
     pub(crate) fn from_wmi(obj: &IWbemClassObject) -> Result<Self> {
@@ -40,6 +54,11 @@ impl NetworkAdapter {
             dynamic_mac,
             switch_name: None,
             vlan_id: None,
+            mac_spoofing: false,
+            dhcp_guard: false,
+            router_guard: false,
+            port_mirroring: PortMirroringMode::None,
+            bandwidth: None,
             path,
         })
     }
@@ -248,10 +267,31 @@ impl PortMirroringMode {
             PortMirroringMode::Destination => 2,
         }
     }
+
+    /// Value of `Msvm_EthernetSwitchPortSecuritySettingData.MonitorMode`,
+    /// which orders Destination before Source.
+    #[cfg_attr(not(windows), allow(dead_code))]
+    pub(crate) fn to_monitor_mode(self) -> u8 {
+        match self {
+            PortMirroringMode::None => 0,
+            PortMirroringMode::Destination => 1,
+            PortMirroringMode::Source => 2,
+        }
+    }
+
+    /// Parse a `MonitorMode` value.
+    #[cfg_attr(not(windows), allow(dead_code))]
+    pub(crate) fn from_monitor_mode(value: u8) -> Self {
+        match value {
+            1 => PortMirroringMode::Destination,
+            2 => PortMirroringMode::Source,
+            _ => PortMirroringMode::None,
+        }
+    }
 }
 
 /// Bandwidth management settings.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct BandwidthSettings {
     /// Minimum bandwidth in Mbps.
     pub minimum_mbps: Option<u64>,
@@ -261,16 +301,6 @@ pub struct BandwidthSettings {
     pub burst_mb: Option<u64>,
 }
 
-impl Default for BandwidthSettings {
-    fn default() -> Self {
-        Self {
-            minimum_mbps: None,
-            maximum_mbps: None,
-            burst_mb: None,
-        }
-    }
-}
-
 impl BandwidthSettings {
     pub fn new() -> Self {
         Self::default()
@@ -290,8 +320,41 @@ impl BandwidthSettings {
         self.burst_mb = Some(mb);
         self
     }
+
+    /// Values for `Msvm_EthernetSwitchPortBandwidthSettingData`:
+    /// `Reservation` and `Limit` in bits per second, `BurstSize` in bytes.
+    /// Unset limits are 0.
+    #[cfg_attr(not(windows), allow(dead_code))]
+    pub(crate) fn to_wmi(&self) -> (u64, u64, u64) {
+        (
+            self.minimum_mbps
+                .unwrap_or(0)
+                .saturating_mul(BITS_PER_MEGABIT),
+            self.maximum_mbps
+                .unwrap_or(0)
+                .saturating_mul(BITS_PER_MEGABIT),
+            self.burst_mb
+                .unwrap_or(0)
+                .saturating_mul(BYTES_PER_MEGABYTE),
+        )
+    }
+
+    /// Parse `Reservation`, `Limit` and `BurstSize`; 0 means unset.
+    #[cfg_attr(not(windows), allow(dead_code))]
+    pub(crate) fn from_wmi(reservation: u64, limit: u64, burst_size: u64) -> Self {
+        Self {
+            minimum_mbps: Some(reservation / BITS_PER_MEGABIT).filter(|v| *v > 0),
+            maximum_mbps: Some(limit / BITS_PER_MEGABIT).filter(|v| *v > 0),
+            burst_mb: Some(burst_size / BYTES_PER_MEGABYTE).filter(|v| *v > 0),
+        }
+    }
 }
 
+#[cfg_attr(not(windows), allow(dead_code))]
+const BITS_PER_MEGABIT: u64 = 1_000_000;
+#[cfg_attr(not(windows), allow(dead_code))]
+const BYTES_PER_MEGABYTE: u64 = 1024 * 1024;
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -410,6 +473,31 @@ mod tests {
         assert_eq!(PortMirroringMode::Destination.to_value(), 2);
     }
 
+    #[test]
+    fn test_port_mirroring_monitor_mode() {
+        for mode in [
+            PortMirroringMode::None,
+            PortMirroringMode::Source,
+            PortMirroringMode::Destination,
+        ] {
+            assert_eq!(
+                PortMirroringMode::from_monitor_mode(mode.to_monitor_mode()),
+                mode
+            );
+        }
+        assert_eq!(PortMirroringMode::Destination.to_monitor_mode(), 1);
+    }
+
+    #[test]
+    fn test_bandwidth_settings_wmi_values() {
+        let bw = BandwidthSettings::new().maximum_mbps(100).burst_mb(2);
+        assert_eq!(bw.to_wmi(), (0, 100_000_000, 2 * 1024 * 1024));
+        let parsed = BandwidthSettings::from_wmi(0, 100_000_000, 2 * 1024 * 1024);
+        assert_eq!(parsed.minimum_mbps, None);
+        assert_eq!(parsed.maximum_mbps, Some(100));
+        assert_eq!(parsed.burst_mb, Some(2));
+    }
+
     #[test]
     fn test_port_mirroring_default() {
         assert_eq!(PortMirroringMode::default(), PortMirroringMode::None);
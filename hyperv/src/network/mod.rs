@@ -1,8 +1,11 @@
 mod adapter;
 mod switch;
 
+#[cfg(windows)]
+pub use adapter::NetworkAdapter;
 pub use adapter::{
-    BandwidthSettings, NetworkAdapter, NetworkAdapterSettings, NetworkAdapterSettingsBuilder,
-    PortMirroringMode,
+    BandwidthSettings, NetworkAdapterSettings, NetworkAdapterSettingsBuilder, PortMirroringMode,
 };
-pub use switch::{SwitchType, VirtualSwitch, VirtualSwitchSettings, VirtualSwitchSettingsBuilder};
+#[cfg(windows)]
+pub use switch::VirtualSwitch;
+pub use switch::{SwitchType, VirtualSwitchSettings, VirtualSwitchSettingsBuilder};
@@ -1,15 +1,18 @@
 use crate::error::{Error, Result};
+#[cfg(windows)]
 use crate::wmi::WbemClassObjectExt;
+#[cfg(windows)]
 use windows::Win32::System::Wmi::IWbemClassObject;
 
 /// Virtual switch type.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 pub enum SwitchType {
     /// External - connected to physical network adapter.
     External,
     /// Internal - accessible from host and VMs.
     Internal,
     /// Private - only accessible between VMs.
+    #[default]
     Private,
 }
 
@@ -33,6 +36,7 @@ impl SwitchType {
 }
 
 /// Represents a Hyper-V virtual switch.
+#[cfg(windows)]
 #[derive(Debug)]
 pub struct VirtualSwitch {
     /// Switch display name.
@@ -50,6 +54,7 @@ pub struct VirtualSwitch {
     path: String,
 }
 
+#[cfg(windows)]
 impl VirtualSwitch {
     /// Create from WMI object.
     pub(crate) fn from_wmi(obj: &IWbemClassObject) -> Result<Self> {
@@ -146,12 +151,6 @@ pub struct VirtualSwitchSettingsBuilder {
     external_adapter: Option<String>,
 }
 
-impl Default for SwitchType {
-    fn default() -> Self {
-        SwitchType::Private
-    }
-}
-
 impl VirtualSwitchSettingsBuilder {
     /// Set switch name (required).
     pub fn name(mut self, name: impl Into<String>) -> Self {
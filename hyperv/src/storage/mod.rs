@@ -2,4 +2,6 @@ mod controller;
 mod vhd;
 
 pub use controller::{ControllerType, DiskAttachment, IsoAttachment, StorageController};
-pub use vhd::{Vhd, VhdFormat, VhdManager, VhdSettings, VhdSettingsBuilder, VhdType};
+#[cfg(windows)]
+pub use vhd::VhdManager;
+pub use vhd::{Vhd, VhdFormat, VhdSettings, VhdSettingsBuilder, VhdType};
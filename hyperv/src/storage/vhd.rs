@@ -1,4 +1,5 @@
 use crate::error::{Error, Result};
+#[cfg(windows)]
 use crate::wmi::{WbemClassObjectExt, WmiConnection};
 
 /// Virtual hard disk format.
@@ -282,10 +283,12 @@ impl VhdSettingsBuilder {
 }
 
 /// VHD management operations.
+#[cfg(windows)]
 pub struct VhdManager {
     connection: std::sync::Arc<WmiConnection>,
 }
 
+#[cfg(windows)]
 impl VhdManager {
     pub(crate) fn new(connection: std::sync::Arc<WmiConnection>) -> Self {
         Self { connection }
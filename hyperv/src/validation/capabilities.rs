@@ -3,7 +3,9 @@
 //! This module provides utilities for querying host-level capabilities
 //! and VM version compatibility.
 
+#[cfg(windows)]
 use crate::error::Result;
+#[cfg(windows)]
 use crate::wmi::{WbemClassObjectExt, WmiConnection};
 
 /// VM configuration version information.
@@ -122,6 +124,7 @@ impl Default for HostCapabilities {
 
 impl HostCapabilities {
     /// Query host capabilities from WMI.
+    #[cfg(windows)]
     pub fn query(connection: &WmiConnection) -> Result<Self> {
         let mut caps = Self::default();
 
@@ -187,6 +190,7 @@ impl HostCapabilities {
 }
 
 /// Query the default VM configuration version for the host.
+#[cfg(windows)]
 pub fn get_default_vm_version(connection: &WmiConnection) -> Result<Option<VmVersionInfo>> {
     // Query Msvm_VirtualSystemManagementCapabilities for supported versions
     let query = "SELECT * FROM Msvm_VirtualSystemManagementCapabilities";
@@ -216,6 +220,7 @@ pub fn get_default_vm_version(connection: &WmiConnection) -> Result<Option<VmVer
 }
 
 /// Check if a specific VM version is supported on the host.
+#[cfg(windows)]
 pub fn is_vm_version_supported(connection: &WmiConnection, version: &str) -> Result<bool> {
     let query = format!(
         "SELECT * FROM Msvm_VirtualSystemManagementCapabilities WHERE SupportedVirtualSystemTypes LIKE '%{}%'",
@@ -350,8 +355,10 @@ mod tests {
 
     #[test]
     fn test_host_capabilities_vm_version_check() {
-        let mut caps = HostCapabilities::default();
-        caps.default_vm_version = Some(VmVersionInfo::windows_server_2022());
+        let caps = HostCapabilities {
+            default_vm_version: Some(VmVersionInfo::windows_server_2022()),
+            ..Default::default()
+        };
 
         let v9 = VmVersionInfo::parse("9.0").unwrap();
         let v10 = VmVersionInfo::parse("10.0").unwrap();
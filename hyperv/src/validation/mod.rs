@@ -8,7 +8,9 @@
 //! This mirrors the C++ wmiv2 `Supports*Property` family of functions.
 
 mod capabilities;
+#[cfg(windows)]
 mod property;
 
 pub use capabilities::{HostCapabilities, VmVersionInfo};
+#[cfg(windows)]
 pub use property::{PropertySupport, PropertyValidator};
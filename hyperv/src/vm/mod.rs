@@ -1,8 +1,10 @@
+#[cfg(windows)]
 mod computer_system;
 mod settings;
 mod state;
 mod types;
 
+#[cfg(windows)]
 pub use computer_system::VirtualMachine;
 pub use settings::{VmSettings, VmSettingsBuilder};
 pub use state::{
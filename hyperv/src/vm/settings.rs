@@ -130,13 +130,11 @@ impl VmSettings {
         }
 
         // Gen1-specific validations
-        if self.generation == Generation::Gen1 {
-            if self.secure_boot {
-                return Err(Error::Validation {
-                    field: "secure_boot",
-                    message: "Secure Boot is only available for Generation 2 VMs".to_string(),
-                });
-            }
+        if self.generation == Generation::Gen1 && self.secure_boot {
+            return Err(Error::Validation {
+                field: "secure_boot",
+                message: "Secure Boot is only available for Generation 2 VMs".to_string(),
+            });
         }
 
         Ok(())
@@ -23,7 +23,7 @@ impl MemoryMB {
     ///
     /// Returns `None` if outside valid range (32 MB - 12 TB).
     pub fn new(mb: u64) -> Option<Self> {
-        if mb >= Self::MIN && mb <= Self::MAX {
+        if (Self::MIN..=Self::MAX).contains(&mb) {
             Some(Self(mb))
         } else {
             None
@@ -108,7 +108,7 @@ impl ProcessorCount {
     ///
     /// Returns `None` if outside valid range (1-240).
     pub fn new(count: u32) -> Option<Self> {
-        if count >= Self::MIN && count <= Self::MAX {
+        if (Self::MIN..=Self::MAX).contains(&count) {
             Some(Self(count))
         } else {
             None
@@ -181,7 +181,7 @@ impl fmt::Display for MemoryBufferPercent {
 }
 
 /// SCSI controller location (0-63) or IDE location (0-1).
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
 pub struct DiskLocation(u32);
 
 impl DiskLocation {
@@ -214,12 +214,6 @@ impl DiskLocation {
     }
 }
 
-impl Default for DiskLocation {
-    fn default() -> Self {
-        Self(0)
-    }
-}
-
 impl fmt::Display for DiskLocation {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "Location {}", self.0)
@@ -227,9 +221,10 @@ impl fmt::Display for DiskLocation {
 }
 
 /// Sector size for VHD/VHDX (512 or 4096 bytes).
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
 pub enum SectorSize {
     /// 512 bytes (legacy).
+    #[default]
     Bytes512,
     /// 4096 bytes (4K native).
     Bytes4K,
@@ -254,12 +249,6 @@ impl SectorSize {
     }
 }
 
-impl Default for SectorSize {
-    fn default() -> Self {
-        SectorSize::Bytes512
-    }
-}
-
 impl fmt::Display for SectorSize {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -48,6 +48,7 @@ impl FromVariant for u16 {
                 return Ok(None);
             }
             match vt {
+                VT_UI1 => Ok(Some(u16::from(v.Anonymous.Anonymous.Anonymous.bVal))),
                 VT_UI2 => Ok(Some(v.Anonymous.Anonymous.Anonymous.uiVal)),
                 VT_I2 => Ok(Some(v.Anonymous.Anonymous.Anonymous.iVal as u16)),
                 VT_UI4 => Ok(Some(v.Anonymous.Anonymous.Anonymous.ulVal as u16)),